@@ -1,13 +1,26 @@
-use als_compression::{AlsCompressor, AlsError, AlsParser, CompressorConfig};
+use als_compression::{Aggregate, AllocationCounts, AlsCompressor, AlsContainerReader, AlsContainerWriter, AlsError, AlsGenerator, AlsParser, AlsQuery, AlsSerializer, AlsSignature, ColumnProfile, ColumnStrategy, Comparison, CompressorConfig, FileMetadata, ForeignKey, JsonFormat, ParserConfig, Predicate, PseudonymKey, Quantization, Redaction, SchemaValidator, SharedDictionary, StoreFrame, StoredFormat, StreamingCompressor, TabularData, TimeGranularity, TimeTruncation};
+use als_compression::als::{parse_signing_key, parse_verifying_key, sign_document, split_signature, verify_document};
+use als_compression::convert::csv::{parse_csv, to_csv};
+use als_compression::convert::{Column, Value};
+use als_compression::convert::json::{parse_json_with_config, to_json, to_json_columnar_with_format, to_json_with_format};
+use als_compression::convert::otlp::{is_otlp_json, parse_otlp_json};
+use als_compression::daemon::Daemon;
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::{debug, error, info, warn};
 use std::fs;
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::Instant;
 
+/// Counts allocations for `explain --timing`, at the cost of two atomic
+/// additions per allocation, so only when built with `alloc-metrics`.
+#[cfg(feature = "alloc-metrics")]
+#[global_allocator]
+static ALLOCATOR: als_compression::CountingAllocator = als_compression::CountingAllocator;
+
 /// ALS (Adaptive Logic Stream) compression tool for structured data
 #[derive(Parser)]
 #[command(name = "als")]
@@ -26,10 +39,41 @@ struct Cli {
     #[arg(short, long, global = true, value_name = "FILE")]
     config: Option<PathBuf>,
 
+    /// How to report a failure on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+
+    /// Write output files in place rather than under a temporary name
+    /// renamed over them once complete
+    #[arg(long, global = true)]
+    no_atomic: bool,
+
+    /// Flush output files to disk before finishing
+    #[arg(long, global = true)]
+    fsync: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Formats for reporting a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    /// Human-readable message
+    Text,
+    /// One JSON object with the error class, exit code and message
+    Json,
+}
+
+/// Formats of the schema command
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SchemaFormat {
+    /// JSON Schema for the rows as JSON decompression writes them
+    Jsonschema,
+    /// SQL CREATE TABLE statement
+    Ddl,
+}
+
 /// Supported input/output formats
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum Format {
@@ -54,21 +98,122 @@ impl Format {
     }
 }
 
+/// Layout of JSON output
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum JsonLayout {
+    /// Array of row objects: [{"id":1,"name":"a"}, ...]
+    Rows,
+    /// Object of column arrays: {"id":[1,...],"name":["a",...]}
+    Columns,
+}
+
+/// Formatting of JSON output
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum JsonStyle {
+    /// Single-line JSON
+    Compact,
+    /// Indented JSON (see --indent)
+    Pretty,
+    /// Newline-delimited JSON, one object per line
+    Ndjson,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Compress CSV or JSON data to ALS format
     Compress {
-        /// Input file (use '-' for stdin)
-        #[arg(short, long, value_name = "FILE", default_value = "-")]
-        input: String,
+        /// Input file or http(s) URL (use '-' for stdin); several inputs are
+        /// compressed in parallel, each to its own .als file
+        #[arg(short, long, value_name = "FILE", default_value = "-", num_args = 1..)]
+        input: Vec<String>,
 
         /// Output file (use '-' for stdout)
         #[arg(short, long, value_name = "FILE", default_value = "-")]
         output: String,
 
+        /// Directory for the outputs of several inputs (default: next to
+        /// each input)
+        #[arg(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+
+        /// Number of inputs to compress at once (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<usize>,
+
+        /// Write a JSON summary of a run over several inputs to FILE
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+
         /// Input format: csv, json, or auto-detect
         #[arg(short, long, value_enum, default_value = "auto")]
         format: Format,
+
+        /// JSON Pointer to the record array in JSON input (e.g. /data/items)
+        #[arg(long, value_name = "POINTER")]
+        records_path: Option<String>,
+
+        /// Reject JSON records whose keys differ instead of merging them
+        #[arg(long)]
+        strict_schema: bool,
+
+        /// Emit the input unchanged when compression would make it bigger
+        #[arg(long)]
+        allow_store: bool,
+
+        /// Pin the encoding of a column: auto, raw, dict or range (repeatable)
+        #[arg(long = "column", value_name = "NAME=STRATEGY", value_parser = parse_column_strategy)]
+        columns: Vec<(String, ColumnStrategy)>,
+
+        /// Append value indexes so 'query' can skip expanding indexed columns
+        #[arg(long)]
+        build_indexes: bool,
+
+        /// Encode rows in independent blocks of N rows; with --build-indexes,
+        /// each block gets Bloom filters that let 'query' skip it
+        #[arg(long, value_name = "N")]
+        block_size: Option<usize>,
+
+        /// Record min/max statistics per column (and per block) so range
+        /// queries can skip blocks
+        #[arg(long)]
+        zone_maps: bool,
+
+        /// Normalize column names and text values to Unicode NFC, so values
+        /// that differ only in how accents are encoded compress together
+        #[arg(long)]
+        normalize_unicode: bool,
+
+        #[command(flatten)]
+        preparation: Box<PrepareArgs>,
+
+        /// Split rows by the time in COLUMN, writing one file per period
+        /// under --output-dir
+        #[arg(long, value_name = "COLUMN")]
+        partition_by: Option<String>,
+
+        /// Length of a period with --partition-by: year, month, day or hour
+        #[arg(long, value_name = "UNIT", default_value = "day", value_parser = parse_granularity)]
+        granularity: TimeGranularity,
+
+        /// Path of each partition, using %Y, %m, %d and %H for its start
+        /// and {stem} for the input name (default: Hive-style, such as
+        /// year=%Y/month=%m/day=%d/{stem}.als)
+        #[arg(long, value_name = "TEMPLATE")]
+        partition_template: Option<String>,
+
+        /// Stream the input, compressing N rows at a time into a container
+        /// of independent documents
+        #[arg(long, value_name = "N")]
+        chunk_rows: Option<usize>,
+
+        /// Record the input's name, modification time, permissions and size
+        /// so 'decompress --preserve' can restore them
+        #[arg(long)]
+        preserve: bool,
+
+        /// Compression level, from 1 (fastest) to 9 (smallest output)
+        #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(u8).range(1..=9))]
+        level: Option<u8>,
     },
 
     /// Decompress ALS data to CSV or JSON format
@@ -84,6 +229,28 @@ enum Commands {
         /// Output format: csv or json
         #[arg(short, long, value_enum, default_value = "csv")]
         format: Format,
+
+        /// Layout of JSON output
+        #[arg(long, value_enum, default_value = "rows")]
+        json_layout: JsonLayout,
+
+        /// Formatting of JSON output
+        #[arg(long, value_enum, default_value = "compact")]
+        json_style: JsonStyle,
+
+        /// Spaces per indentation level for --json-style pretty
+        #[arg(long, value_name = "N", default_value_t = 2)]
+        indent: usize,
+
+        /// Restore the real values of pseudonymized columns from the key
+        /// written by 'compress --keyfile'
+        #[arg(long, value_name = "FILE")]
+        keyfile: Option<PathBuf>,
+
+        /// Restore the modification time and permissions recorded by
+        /// 'compress --preserve' on the output file
+        #[arg(long)]
+        preserve: bool,
     },
 
     /// Display information about ALS compressed data
@@ -92,382 +259,3289 @@ enum Commands {
         #[arg(short, long, value_name = "FILE", default_value = "-")]
         input: String,
     },
-}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+    /// Show the encodings considered for each column of CSV or JSON data
+    Explain {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
 
-    // Set up logging based on verbosity flags
-    setup_logging(cli.verbose, cli.quiet);
+        /// Input format: csv, json, or auto-detect
+        #[arg(short, long, value_enum, default_value = "auto")]
+        format: Format,
 
-    // Load configuration if specified
-    let config = if let Some(config_path) = &cli.config {
-        load_config(config_path)?
-    } else {
-        CompressorConfig::default()
-    };
+        /// Number of candidates to list per column, winner included
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 3)]
+        top: usize,
 
-    // Execute the appropriate command
-    match cli.command {
-        Commands::Compress {
-            input,
-            output,
-            format,
-        } => {
-            compress_command(&input, &output, format, config, cli.verbose, cli.quiet)?;
-        }
-        Commands::Decompress {
-            input,
-            output,
-            format,
-        } => {
-            decompress_command(&input, &output, format, cli.verbose, cli.quiet)?;
-        }
-        Commands::Info { input } => {
-            info_command(&input, cli.verbose, cli.quiet)?;
-        }
-    }
+        /// Also compress the input and report the time each detector took per column
+        #[arg(long)]
+        timing: bool,
+    },
 
-    Ok(())
-}
+    /// Suggest changes to CSV or JSON data that would compress it better
+    Advise {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
 
-/// Set up logging based on verbosity flags
-fn setup_logging(verbose: bool, quiet: bool) {
-    let log_level = if quiet {
-        "error"
-    } else if verbose {
-        "debug"
-    } else {
-        "info"
-    };
+        /// Input format: csv, json, or auto-detect
+        #[arg(short, long, value_enum, default_value = "auto")]
+        format: Format,
+    },
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
-        .format_timestamp(None)
-        .format_module_path(false)
-        .format_target(false)
-        .init();
+    /// Print the rows of ALS data matching a predicate on a column
+    Query {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
 
-    debug!("Logging initialized at {} level", log_level);
-}
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
 
-/// Load configuration from a file
-fn load_config(_path: &PathBuf) -> Result<CompressorConfig> {
-    // For now, return default config
-    // TODO: Implement actual config file loading in task 35.6
-    Ok(CompressorConfig::default())
-}
+        /// Predicate to match: COLUMN=VALUE, a comparison using !=, <, <=, > or >=, or COLUMN~REGEX
+        #[arg(short = 'w', long = "where", value_name = "COLUMN=VALUE")]
+        predicate: Predicate,
 
-/// Read input from file or stdin
-fn read_input(input: &str) -> Result<String> {
-    if input == "-" {
-        // Read from stdin
-        let mut buffer = String::new();
-        io::stdin()
-            .read_to_string(&mut buffer)
-            .context("Failed to read from stdin")?;
-        Ok(buffer)
-    } else {
-        // Read from file
-        fs::read_to_string(input)
-            .with_context(|| format!("Failed to read input file: {}", input))
-    }
-}
+        /// Output format: csv or json
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: Format,
 
-/// Write output to file or stdout
-fn write_output(output: &str, content: &str) -> Result<()> {
-    if output == "-" {
-        // Write to stdout
-        io::stdout()
-            .write_all(content.as_bytes())
-            .context("Failed to write to stdout")?;
-        io::stdout().flush().context("Failed to flush stdout")?;
-    } else {
-        // Write to file
-        fs::write(output, content)
-            .with_context(|| format!("Failed to write output file: {}", output))?;
-    }
-    Ok(())
-}
+        /// Print the number of matching rows instead of the rows
+        #[arg(long)]
+        count: bool,
+    },
 
-/// Detect input format from content or file extension
-fn detect_format(input: &str, content: &str) -> Format {
-    // First try to detect from file extension
-    if input != "-" {
-        if input.ends_with(".csv") {
-            return Format::Csv;
-        } else if input.ends_with(".json") {
-            return Format::Json;
-        } else if input.ends_with(".als") {
-            return Format::Als;
-        }
-    }
+    /// Print the rows of ALS data with a value containing a pattern
+    Grep {
+        /// Text to look for in any column
+        #[arg(value_name = "PATTERN", required_unless_present = "regexp", conflicts_with = "regexp")]
+        pattern: Option<String>,
 
-    // Try to detect from content
-    let trimmed = content.trim_start();
-    
-    // JSON typically starts with [ or {
-    if trimmed.starts_with('[') || trimmed.starts_with('{') {
-        return Format::Json;
-    }
-    
-    // ALS format starts with version (!v) or schema (#)
-    if trimmed.starts_with("!v") || trimmed.starts_with('#') || trimmed.starts_with('$') {
-        return Format::Als;
-    }
-    
-    // Default to CSV
-    Format::Csv
-}
+        /// Regular expression to look for in any column instead of plain text
+        #[arg(short = 'e', long, value_name = "REGEX")]
+        regexp: Option<String>,
 
-/// Execute the compress command
-fn compress_command(
-    input: &str,
-    output: &str,
-    format: Format,
-    config: CompressorConfig,
-    _verbose: bool,
-    quiet: bool,
-) -> Result<()> {
-    let start_time = Instant::now();
-    
-    info!("Starting compression: {} -> {}", input, output);
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
 
-    // Read input with progress bar for large files
-    let progress = create_progress_bar(quiet, "Reading input");
-    let input_data = read_input(input)?;
-    progress.finish_and_clear();
-    
-    if input_data.is_empty() {
-        warn!("Input is empty");
-        write_output(output, "")?;
-        return Ok(());
-    }
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
 
-    let input_size = input_data.len();
-    debug!("Read {} bytes from input", input_size);
+        /// Output format: csv or json
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: Format,
 
-    // Detect format if auto
-    let detected_format = match format {
-        Format::Auto => {
-            let detected = detect_format(input, &input_data);
-            info!("Auto-detected format: {}", detected.as_str());
-            detected
-        }
-        _ => format,
-    };
+        /// Print the number of matching rows instead of the rows
+        #[arg(long, conflicts_with = "cells")]
+        count: bool,
 
-    debug!("Input format: {}", detected_format.as_str());
+        /// Print the row number and column of each matching value instead of the rows
+        #[arg(long)]
+        cells: bool,
+    },
 
-    // Create compressor
-    let compressor = AlsCompressor::with_config(config);
+    /// Count, sum or bound a column of ALS data, optionally per value of another column
+    Agg {
+        /// Column to aggregate
+        #[arg(value_name = "COLUMN")]
+        column: String,
 
-    // Compress based on format with progress indication
-    let progress = create_progress_bar(quiet, "Compressing");
-    let compress_start = Instant::now();
-    
-    let compressed = match detected_format {
-        Format::Csv => {
-            debug!("Compressing CSV data");
-            compressor
-                .compress_csv(&input_data)
-                .map_err(|e| map_als_error(e, "CSV compression"))?
-        }
-        Format::Json => {
-            debug!("Compressing JSON data");
-            compressor
-                .compress_json(&input_data)
-                .map_err(|e| map_als_error(e, "JSON compression"))?
-        }
-        Format::Als => {
-            error!("Input is already in ALS format");
-            anyhow::bail!("Input is already in ALS format. Use 'decompress' command instead.");
-        }
-        Format::Auto => {
-            error!("Failed to detect input format");
-            anyhow::bail!("Failed to detect input format");
-        }
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Aggregates to compute, separated by commas: count, sum, min or max
+        #[arg(short, long = "agg", value_name = "AGGREGATE", value_delimiter = ',', default_value = "count", value_parser = parse_aggregate)]
+        aggregates: Vec<Aggregate>,
+
+        /// Column to group rows by, aggregating once per value
+        #[arg(short, long, value_name = "COLUMN")]
+        by: Option<String>,
+
+        /// Output format: csv or json
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: Format,
+    },
+
+    /// Join the rows of two ALS files with equal values in a key column
+    Join {
+        /// Left ALS file, whose row order the result keeps
+        #[arg(value_name = "LEFT")]
+        left: String,
+
+        /// Right ALS file, typically a dimension table
+        #[arg(value_name = "RIGHT")]
+        right: String,
+
+        /// Key column both files have
+        #[arg(long, value_name = "COLUMN")]
+        on: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Output format: als, csv or json
+        #[arg(short, long, value_enum, default_value = "als")]
+        format: Format,
+    },
+
+    /// Unpivot CSV or JSON data from wide to long and compress it
+    Melt {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Input format: csv, json, or auto-detect
+        #[arg(short, long, value_enum, default_value = "auto")]
+        format: Format,
+
+        /// Columns kept as they are, separated by commas; all others are unpivoted
+        #[arg(long = "id", value_name = "COLUMN", value_delimiter = ',')]
+        ids: Vec<String>,
+
+        /// Name of the column holding the names of the unpivoted columns
+        #[arg(long, value_name = "NAME", default_value = "variable")]
+        variable: String,
+
+        /// Name of the column holding their values
+        #[arg(long, value_name = "NAME", default_value = "value")]
+        value: String,
+    },
+
+    /// Pivot CSV or JSON data from long to wide and compress it
+    Pivot {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Input format: csv, json, or auto-detect
+        #[arg(short, long, value_enum, default_value = "auto")]
+        format: Format,
+
+        /// Column whose values name the new columns
+        #[arg(long, value_name = "COLUMN")]
+        columns: String,
+
+        /// Column whose values fill the new columns
+        #[arg(long, value_name = "COLUMN")]
+        values: String,
+    },
+
+    /// Compress only the rows of CSV or JSON data that differ from a base
+    Delta {
+        /// ALS file the delta is made against
+        #[arg(short, long, value_name = "FILE")]
+        base: String,
+
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Input format: csv, json, or auto-detect
+        #[arg(short, long, value_enum, default_value = "auto")]
+        format: Format,
+    },
+
+    /// Rebuild CSV or JSON data from a base and a delta
+    Patch {
+        /// ALS file the delta was made against
+        #[arg(short, long, value_name = "FILE")]
+        base: String,
+
+        /// Delta file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Output format: csv or json
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: Format,
+    },
+
+    /// Compress several CSV, JSON or ALS files into one container
+    Pack {
+        /// Input files, stored under the names given here
+        #[arg(required = true, value_name = "FILE")]
+        inputs: Vec<String>,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Input format: csv, json, als, or auto-detect per file
+        #[arg(short, long, value_enum, default_value = "auto")]
+        format: Format,
+
+        /// Store segments repeated across files only once
+        #[arg(long)]
+        dedup: bool,
+
+        /// Store values that columns of several files have in common, such
+        /// as a user_id in every table, in dictionaries the files share
+        #[arg(long)]
+        shared_dictionaries: bool,
+
+        /// Store a column as row numbers into the keys of a column of
+        /// another file, such as orders.csv:user_id=users.csv:id
+        #[arg(long = "foreign-key", value_name = "FILE:COLUMN=FILE:COLUMN", value_parser = parse_foreign_key)]
+        foreign_keys: Vec<(String, String, ForeignKey)>,
+    },
+
+    /// Extract the documents of a container
+    Unpack {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Directory to extract into
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        output: PathBuf,
+
+        /// Output format: csv, json or als
+        #[arg(short, long, value_enum, default_value = "als")]
+        format: Format,
+
+        /// List the table of contents instead of extracting
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Sign an ALS file or container with an Ed25519 key
+    Sign {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// File holding the secret signing key as 64 hex digits
+        #[arg(short, long, value_name = "FILE")]
+        key: PathBuf,
+
+        /// Write only the signature line instead of the signed file
+        #[arg(long)]
+        detached: bool,
+    },
+
+    /// Check the Ed25519 signature of an ALS file or container
+    VerifySignature {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Detached signature file, instead of the signature embedded in the input
+        #[arg(short, long, value_name = "FILE")]
+        signature: Option<String>,
+
+        /// Trusted public key, as 64 hex digits or a file holding them
+        #[arg(short = 'k', long, value_name = "KEY")]
+        public_key: String,
+    },
+
+    /// Rewrite an ALS file into its canonical form, so equal data compares equal
+    Canonicalize {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+    },
+
+    /// Recover the rows that can be read from a truncated or damaged ALS file
+    Repair {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+    },
+
+    /// Compress an ALS file again, with other settings or a newer release
+    Recompress {
+        /// ALS file to rewrite (use '-' for stdin)
+        #[arg(value_name = "FILE")]
+        input: String,
+
+        /// Write to FILE instead of rewriting the input (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Compression level, from 1 (fastest) to 9 (smallest output)
+        #[arg(long, value_name = "LEVEL", default_value_t = 6, value_parser = clap::value_parser!(u8).range(1..=9))]
+        level: u8,
+
+        /// Rewrite the input even if the result is no smaller
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Derive a JSON Schema or SQL table definition from ALS data
+    Schema {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Schema format
+        #[arg(short, long, value_enum, default_value = "jsonschema")]
+        format: SchemaFormat,
+
+        /// Table name for DDL (default: the input file name)
+        #[arg(long, value_name = "NAME")]
+        table: Option<String>,
+    },
+
+    /// Grow ALS data by a whole factor, continuing the patterns of each column
+    Scale {
+        /// Input file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Number of times as many rows to write
+        #[arg(short = 'x', long, value_name = "N")]
+        factor: usize,
+    },
+
+    /// Expand a hand-written ALS spec into rows of CSV or JSON test data
+    Generate {
+        /// ALS spec file (use '-' for stdin)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        input: String,
+
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Output format: csv or json
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: Format,
+
+        /// Number of rows to generate (default: the longest column of the spec)
+        #[arg(short = 'n', long, value_name = "N")]
+        rows: Option<usize>,
+
+        /// Draw columns of dictionary references at random from their values
+        #[arg(long)]
+        sample: bool,
+
+        /// Seed for --sample
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        seed: u64,
+    },
+
+    /// Write a self-contained JavaScript decoder for ALS version 1 text
+    JsDecoder {
+        /// Output file (use '-' for stdout)
+        #[arg(short, long, value_name = "FILE", default_value = "-")]
+        output: String,
+
+        /// Write the TypeScript declarations instead of the decoder
+        #[arg(long)]
+        types: bool,
+    },
+
+    /// Answer compress and decompress requests on a Unix socket or over
+    /// HTTP until stopped
+    Serve {
+        /// Path of the Unix socket to listen on
+        #[arg(long, value_name = "PATH", required_unless_present = "http", conflicts_with = "http")]
+        socket: Option<PathBuf>,
+
+        /// Address to answer HTTP requests on, such as 0.0.0.0:8080
+        /// (POST /compress, /decompress and /info)
+        #[arg(long, value_name = "ADDR")]
+        http: Option<String>,
+    },
+}
+
+/// Options of the compress command that change the input before it is
+/// compressed
+#[derive(clap::Args, Debug)]
+struct PrepareArgs {
+    /// Keep only the rows matching a predicate such as
+    /// 'status != "debug"', written as for 'query --where'
+    #[arg(long = "where", value_name = "PREDICATE")]
+    row_filter: Option<Predicate>,
+
+    /// Check the input against a JSON Schema contract (such as 'als schema'
+    /// writes) and fail on any column that breaks it
+    #[arg(long, value_name = "FILE")]
+    schema: Option<PathBuf>,
+
+    /// Redact a column before compressing: drop, hash, mask or
+    /// pseudonymize (repeatable)
+    #[arg(long = "redact", value_name = "NAME=POLICY", value_parser = parse_redaction)]
+    redactions: Vec<(String, Redaction)>,
+
+    /// Round the floats of a column before compressing, to sig:N
+    /// significant digits or dp:N decimal places (repeatable; lossy)
+    #[arg(long = "quantize", value_name = "NAME=SPEC", value_parser = parse_quantization)]
+    quantizations: Vec<(String, Quantization)>,
+
+    /// Truncate the timestamps of a column before compressing, to the
+    /// second or minute (repeatable; lossy)
+    #[arg(long = "truncate", value_name = "NAME=UNIT", value_parser = parse_truncation)]
+    truncations: Vec<(String, TimeTruncation)>,
+
+    /// Salt for hashed and pseudonymized columns (default: the
+    /// ALS_REDACTION_SALT environment variable)
+    #[arg(long, value_name = "SALT")]
+    redaction_salt: Option<String>,
+
+    /// Write the key that reverses pseudonymized columns to FILE, adding
+    /// to the keys already in it
+    #[arg(long, value_name = "FILE")]
+    keyfile: Option<PathBuf>,
+}
+
+fn main() -> std::process::ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            // The flag itself may not have parsed, so look for it by hand
+            let args: Vec<String> = std::env::args().collect();
+            let json = args.iter().any(|arg| arg == "--error-format=json")
+                || args.windows(2).any(|pair| pair[0] == "--error-format" && pair[1] == "json");
+            if !json {
+                e.exit();
+            }
+            let text = e.to_string();
+            let first = text.lines().next().unwrap_or_default();
+            let message = first.strip_prefix("error: ").unwrap_or(first);
+            report_error(ErrorClass::Usage, message, &[]);
+            return ErrorClass::Usage.exit_code();
+        }
+    };
+
+    let error_format = cli.error_format;
+    match run(cli) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            let class = ErrorClass::of(&e);
+            match error_format {
+                ErrorFormat::Text => eprintln!("Error: {:?}", e),
+                ErrorFormat::Json => {
+                    let causes: Vec<String> = e.chain().skip(1).map(|cause| cause.to_string()).collect();
+                    report_error(class, &e.to_string(), &causes);
+                }
+            }
+            class.exit_code()
+        }
+    }
+}
+
+/// Run the command line
+fn run(cli: Cli) -> Result<()> {
+    // Set up logging based on verbosity flags
+    setup_logging(cli.verbose, cli.quiet);
+    setup_output(!cli.no_atomic, cli.fsync);
+
+    // Load configuration if specified
+    let config = if let Some(config_path) = &cli.config {
+        load_config(config_path)?
+    } else {
+        CompressorConfig::default()
+    };
+
+    // Execute the appropriate command
+    match cli.command {
+        Commands::Compress {
+            input,
+            output,
+            output_dir,
+            jobs,
+            report,
+            format,
+            records_path,
+            strict_schema,
+            allow_store,
+            columns,
+            build_indexes,
+            block_size,
+            zone_maps,
+            normalize_unicode,
+            preparation,
+            partition_by,
+            granularity,
+            partition_template,
+            chunk_rows,
+            preserve,
+            level,
+        } => {
+            let PrepareArgs {
+                row_filter,
+                schema,
+                redactions,
+                quantizations,
+                truncations,
+                redaction_salt,
+                keyfile,
+            } = *preparation;
+            let mut config = config;
+            if let Some(level) = level {
+                config = config.with_level(level);
+            }
+            config.allow_store_mode |= allow_store;
+            config.build_indexes |= build_indexes;
+            config.build_zone_maps |= zone_maps;
+            config.normalize_unicode |= normalize_unicode;
+            if let Some(predicate) = row_filter {
+                config = config.with_row_filter(predicate);
+            }
+            if let Some(path) = schema {
+                config = config.with_schema_contract(load_schema_contract(&path)?);
+            }
+            let hashes = redactions
+                .iter()
+                .any(|(_, redaction)| matches!(redaction, Redaction::Hash | Redaction::Pseudonymize));
+            let pseudonyms = redactions.iter().any(|(_, redaction)| *redaction == Redaction::Pseudonymize);
+            for (column, redaction) in redactions {
+                config = config.with_column_redaction(column, redaction);
+            }
+            for (column, quantization) in quantizations {
+                config = config.with_column_quantization(column, quantization);
+            }
+            for (column, truncation) in truncations {
+                config = config.with_column_truncation(column, truncation);
+            }
+            if let Some(salt) = redaction_salt.or_else(|| std::env::var("ALS_REDACTION_SALT").ok()) {
+                config = config.with_redaction_salt(salt);
+            }
+            if hashes && config.redaction_salt.is_empty() {
+                anyhow::bail!("Hashed and pseudonymized columns need a salt: pass --redaction-salt or set ALS_REDACTION_SALT");
+            }
+            if pseudonyms && keyfile.is_none() {
+                anyhow::bail!("Pseudonymized columns need --keyfile to write their key to");
+            }
+            if keyfile.is_some() && chunk_rows.is_some() {
+                anyhow::bail!("--keyfile cannot be combined with --chunk-rows");
+            }
+            // Without pseudonymized columns there is no key to write
+            let keyfile = keyfile.filter(|_| pseudonyms);
+            if let Some(block_size) = block_size {
+                config = config.with_block_size(block_size);
+            }
+            for (column, strategy) in columns {
+                config = config.with_column_strategy(column, strategy);
+            }
+            if records_path.is_some() {
+                config.json.records_path = records_path;
+            }
+            config.json.strict_schema |= strict_schema;
+            if preserve && (partition_by.is_some() || chunk_rows.is_some()) {
+                anyhow::bail!("--preserve cannot be combined with --partition-by or --chunk-rows");
+            }
+            if let Some(column) = partition_by {
+                let [input] = input.as_slice() else {
+                    anyhow::bail!("--partition-by takes a single input");
+                };
+                if output != "-" || chunk_rows.is_some() {
+                    anyhow::bail!("--partition-by writes to --output-dir and cannot be combined with --output or --chunk-rows");
+                }
+                let template = partition_template
+                    .unwrap_or_else(|| format!("{}/{{stem}}.als", granularity.default_template()));
+                let partitioning = PartitionOptions {
+                    column,
+                    granularity,
+                    template,
+                    output_dir: output_dir.unwrap_or_else(|| PathBuf::from(".")),
+                };
+                compress_partitioned_command(input, format, &partitioning, config, keyfile.as_deref(), cli.quiet)?;
+            } else if let [input] = input.as_slice() {
+                if preserve {
+                    match source_metadata(input)? {
+                        Some(metadata) => config = config.with_file_metadata(metadata),
+                        None => warn!("--preserve has no file to describe when reading {}", input),
+                    }
+                }
+                match chunk_rows {
+                    Some(rows) => compress_chunked_command(input, &output, format, rows, config, cli.quiet)?,
+                    None => compress_command(input, &output, format, config, keyfile.as_deref(), cli.quiet)?,
+                }
+            } else {
+                if output != "-" {
+                    anyhow::bail!("Use --output-dir rather than --output with several inputs");
+                }
+                if chunk_rows.is_some() {
+                    anyhow::bail!("--chunk-rows takes a single input");
+                }
+                let batch = BatchOptions {
+                    output_dir,
+                    jobs,
+                    report,
+                    keyfile,
+                    preserve,
+                };
+                compress_many_command(&input, format, &batch, config, cli.quiet)?;
+            }
+        }
+        Commands::Decompress {
+            input,
+            output,
+            format,
+            json_layout,
+            json_style,
+            indent,
+            keyfile,
+            preserve,
+        } => {
+            let json_format = match json_style {
+                JsonStyle::Compact => JsonFormat::Compact,
+                JsonStyle::Pretty => JsonFormat::Pretty { indent },
+                JsonStyle::Ndjson => JsonFormat::Ndjson,
+            };
+            let json = JsonOutput {
+                layout: json_layout,
+                format: json_format,
+            };
+            let key = keyfile.as_deref().map(load_pseudonym_key).transpose()?;
+            decompress_command(&input, &output, format, json, key.as_ref(), preserve, cli.quiet)?;
+        }
+        Commands::Info { input } => {
+            info_command(&input, cli.verbose, cli.quiet)?;
+        }
+        Commands::Explain { input, format, top, timing } => {
+            explain_command(&input, format, top, timing, config, cli.quiet)?;
+        }
+        Commands::Advise { input, format } => {
+            advise_command(&input, format, config, cli.quiet)?;
+        }
+        Commands::Query {
+            input,
+            output,
+            predicate,
+            format,
+            count,
+        } => {
+            query_command(&input, &output, &predicate, format, count, cli.quiet)?;
+        }
+        Commands::Grep {
+            pattern,
+            regexp,
+            input,
+            output,
+            format,
+            count,
+            cells,
+        } => {
+            let pattern = match &regexp {
+                Some(regex) => GrepPattern::Regex(regex),
+                None => GrepPattern::Text(pattern.as_deref().unwrap_or_default()),
+            };
+            grep_command(pattern, &input, &output, format, count, cells, cli.quiet)?;
+        }
+        Commands::Agg {
+            column,
+            input,
+            output,
+            aggregates,
+            by,
+            format,
+        } => {
+            agg_command(&column, &input, &output, &aggregates, by.as_deref(), format, cli.quiet)?;
+        }
+        Commands::Join {
+            left,
+            right,
+            on,
+            output,
+            format,
+        } => {
+            join_command(&left, &right, &on, &output, format, config, cli.quiet)?;
+        }
+        Commands::Melt {
+            input,
+            output,
+            format,
+            ids,
+            variable,
+            value,
+        } => {
+            let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+            let reshape = Reshape::Melt {
+                ids: &ids,
+                variable: &variable,
+                value: &value,
+            };
+            reshape_command(&input, &output, format, reshape, config, cli.quiet)?;
+        }
+        Commands::Pivot {
+            input,
+            output,
+            format,
+            columns,
+            values,
+        } => {
+            let reshape = Reshape::Pivot {
+                columns: &columns,
+                values: &values,
+            };
+            reshape_command(&input, &output, format, reshape, config, cli.quiet)?;
+        }
+        Commands::Delta {
+            base,
+            input,
+            output,
+            format,
+        } => {
+            delta_command(&base, &input, &output, format, config, cli.quiet)?;
+        }
+        Commands::Patch {
+            base,
+            input,
+            output,
+            format,
+        } => {
+            patch_command(&base, &input, &output, format, cli.quiet)?;
+        }
+        Commands::Pack {
+            inputs,
+            output,
+            format,
+            dedup,
+            shared_dictionaries,
+            foreign_keys,
+        } => {
+            let pack = PackOptions {
+                dedup,
+                shared_dictionaries,
+                foreign_keys,
+            };
+            pack_command(&inputs, &output, format, &pack, config, cli.quiet)?;
+        }
+        Commands::Unpack {
+            input,
+            output,
+            format,
+            list,
+        } => {
+            unpack_command(&input, &output, format, list, cli.quiet)?;
+        }
+        Commands::Sign {
+            input,
+            output,
+            key,
+            detached,
+        } => {
+            sign_command(&input, &output, &key, detached, cli.quiet)?;
+        }
+        Commands::VerifySignature {
+            input,
+            signature,
+            public_key,
+        } => {
+            verify_signature_command(&input, signature.as_deref(), &public_key, cli.quiet)?;
+        }
+        Commands::Canonicalize { input, output } => {
+            canonicalize_command(&input, &output, cli.quiet)?;
+        }
+        Commands::Repair { input, output } => {
+            repair_command(&input, &output, cli.quiet)?;
+        }
+        Commands::Recompress {
+            input,
+            output,
+            level,
+            force,
+        } => {
+            let output = output.unwrap_or_else(|| input.clone());
+            recompress_command(&input, &output, config.with_level(level), force, cli.quiet)?;
+        }
+        Commands::Schema {
+            input,
+            output,
+            format,
+            table,
+        } => {
+            schema_command(&input, &output, format, table.as_deref(), cli.quiet)?;
+        }
+        Commands::Scale { input, output, factor } => {
+            scale_command(&input, &output, factor, cli.quiet)?;
+        }
+        Commands::Generate {
+            input,
+            output,
+            format,
+            rows,
+            sample,
+            seed,
+        } => {
+            generate_command(&input, &output, format, rows, sample, seed, cli.quiet)?;
+        }
+        Commands::JsDecoder { output, types } => {
+            js_decoder_command(&output, types, cli.quiet)?;
+        }
+        Commands::Serve { socket, http } => match (socket, http) {
+            (Some(socket), _) => serve_command(&socket, config, cli.quiet)?,
+            (None, Some(address)) => serve_http_command(&address, config, cli.quiet)?,
+            (None, None) => unreachable!("clap requires --socket or --http"),
+        },
+    }
+
+    Ok(())
+}
+
+/// Parse a `NAME=STRATEGY` column strategy argument
+fn parse_column_strategy(arg: &str) -> std::result::Result<(String, ColumnStrategy), String> {
+    let (name, strategy) = arg
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected NAME=STRATEGY, got '{arg}'"))?;
+    let strategy = ColumnStrategy::from_name(strategy).ok_or_else(|| {
+        let names: Vec<&str> = ColumnStrategy::ALL.iter().map(|s| s.name()).collect();
+        format!("unknown strategy '{strategy}', expected one of: {}", names.join(", "))
+    })?;
+    Ok((name.to_string(), strategy))
+}
+
+/// Parse a `NAME=POLICY` column redaction argument
+fn parse_redaction(arg: &str) -> std::result::Result<(String, Redaction), String> {
+    let (name, policy) = arg
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected NAME=POLICY, got '{arg}'"))?;
+    let redaction = Redaction::from_name(policy).ok_or_else(|| {
+        let names: Vec<&str> = Redaction::ALL.iter().map(|r| r.name()).collect();
+        format!("unknown redaction '{policy}', expected one of: {}", names.join(", "))
+    })?;
+    Ok((name.to_string(), redaction))
+}
+
+/// Parse a `NAME=SPEC` column quantization argument
+fn parse_quantization(arg: &str) -> std::result::Result<(String, Quantization), String> {
+    let (name, spec) = arg
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected NAME=SPEC, got '{arg}'"))?;
+    let quantization = Quantization::parse(spec).ok_or_else(|| {
+        format!("unknown quantization '{spec}', expected sig:N (1 to 17 significant digits) or dp:N (0 to 17 decimal places)")
+    })?;
+    Ok((name.to_string(), quantization))
+}
+
+/// Parse a `NAME=UNIT` column truncation argument
+fn parse_truncation(arg: &str) -> std::result::Result<(String, TimeTruncation), String> {
+    let (name, unit) = arg
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected NAME=UNIT, got '{arg}'"))?;
+    let truncation = TimeTruncation::from_name(unit).ok_or_else(|| {
+        let names: Vec<&str> = TimeTruncation::ALL.iter().map(|t| t.name()).collect();
+        format!("unknown truncation '{unit}', expected one of: {}", names.join(", "))
+    })?;
+    Ok((name.to_string(), truncation))
+}
+
+/// Parse a `FILE:COLUMN=FILE:COLUMN` foreign key argument into the child
+/// file, its column and the parent it refers to
+fn parse_foreign_key(arg: &str) -> std::result::Result<(String, String, ForeignKey), String> {
+    let split = |side: &str| {
+        side.rsplit_once(':')
+            .filter(|(file, column)| !file.is_empty() && !column.is_empty())
+            .map(|(file, column)| (file.to_string(), column.to_string()))
+            .ok_or_else(|| format!("expected FILE:COLUMN=FILE:COLUMN, got '{arg}'"))
+    };
+    let (child, parent) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected FILE:COLUMN=FILE:COLUMN, got '{arg}'"))?;
+    let (file, column) = split(child)?;
+    let (parent, parent_column) = split(parent)?;
+    Ok((file, column, ForeignKey::new(parent, parent_column)))
+}
+
+/// Parse an --agg argument
+fn parse_aggregate(arg: &str) -> std::result::Result<Aggregate, String> {
+    Aggregate::from_name(arg).ok_or_else(|| {
+        let names: Vec<&str> = Aggregate::ALL.iter().map(|a| a.name()).collect();
+        format!("unknown aggregate '{arg}', expected one of: {}", names.join(", "))
+    })
+}
+
+fn parse_granularity(arg: &str) -> std::result::Result<TimeGranularity, String> {
+    TimeGranularity::from_name(arg).ok_or_else(|| {
+        let names: Vec<&str> = TimeGranularity::ALL.iter().map(|g| g.name()).collect();
+        format!("unknown granularity '{arg}', expected one of: {}", names.join(", "))
+    })
+}
+
+/// Set up logging based on verbosity flags
+fn setup_logging(verbose: bool, quiet: bool) {
+    let log_level = if quiet {
+        "error"
+    } else if verbose {
+        "debug"
+    } else {
+        "info"
+    };
+
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level))
+        .format_timestamp(None)
+        .format_module_path(false)
+        .format_target(false)
+        .init();
+
+    debug!("Logging initialized at {} level", log_level);
+}
+
+/// How output files are written, set from the command line
+#[derive(Debug, Clone, Copy)]
+struct OutputMode {
+    /// Write under a temporary name and rename over the output once complete
+    atomic: bool,
+    /// Flush files to disk before they count as written
+    fsync: bool,
+}
+
+static OUTPUT_MODE: OnceLock<OutputMode> = OnceLock::new();
+
+/// Set how output files are written
+fn setup_output(atomic: bool, fsync: bool) {
+    let _ = OUTPUT_MODE.set(OutputMode { atomic, fsync });
+    debug!("Writing output files {}", if atomic { "atomically" } else { "in place" });
+}
+
+/// Get how output files are written
+fn output_mode() -> OutputMode {
+    OUTPUT_MODE.get().copied().unwrap_or(OutputMode { atomic: true, fsync: false })
+}
+
+/// An output file being written.
+///
+/// The file is written under a temporary name next to the output and only
+/// renamed over it by `commit`, so a crash partway through can't leave a
+/// half-written file that downstream jobs mistake for a complete one.
+/// Dropping it uncommitted removes the temporary file. With `--no-atomic`
+/// the output is written in place.
+struct OutputFile {
+    writer: Option<io::BufWriter<fs::File>>,
+    path: PathBuf,
+    /// Name the file is written under until committed, when atomic
+    temp: Option<PathBuf>,
+}
+
+impl OutputFile {
+    /// Start writing the file at `path`
+    fn create(path: &Path) -> io::Result<Self> {
+        Self::open(path, false)
+    }
+
+    /// Start writing the file at `path`, readable and writable by its
+    /// owner only, as for secrets such as a pseudonym key
+    fn create_private(path: &Path) -> io::Result<Self> {
+        Self::open(path, true)
+    }
+
+    fn open(path: &Path, private: bool) -> io::Result<Self> {
+        // A symlink is written through to its target rather than replaced
+        let existing = fs::symlink_metadata(path).ok();
+        let path = match &existing {
+            Some(metadata) if metadata.file_type().is_symlink() => {
+                fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+            }
+            _ => path.to_path_buf(),
+        };
+        let existing = fs::symlink_metadata(&path).ok();
+        // Renaming over a device or pipe, such as /dev/null, would replace
+        // it, and over a dangling symlink would replace the link
+        let regular = existing.as_ref().is_none_or(|metadata| metadata.is_file());
+        let temp = (output_mode().atomic && regular).then(|| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+        });
+        let file = match &temp {
+            Some(temp) => {
+                let file = fs::OpenOptions::new().write(true).create_new(true).open(temp)?;
+                // The replacement keeps the permissions of the file it
+                // replaces, such as a private key file's
+                if let Some(metadata) = existing.as_ref().filter(|_| !private) {
+                    file.set_permissions(metadata.permissions())?;
+                }
+                file
+            }
+            None => fs::File::create(&path)?,
+        };
+        // Restricted before anything is written, whatever the old mode was
+        if private {
+            restrict_to_owner(&file)?;
+        }
+        Ok(Self {
+            writer: Some(io::BufWriter::new(file)),
+            path,
+            temp,
+        })
+    }
+
+    /// Finish the file, moving it into place
+    fn commit(mut self) -> io::Result<()> {
+        let file = self.writer.take().expect("output file is open").into_inner()?;
+        if output_mode().fsync {
+            file.sync_all()?;
+        }
+        // Closed first, as Windows can't rename an open file
+        drop(file);
+
+        if let Some(temp) = &self.temp {
+            fs::rename(temp, &self.path)?;
+            self.temp = None;
+            // The rename itself is only durable once the directory is
+            #[cfg(unix)]
+            if output_mode().fsync {
+                let dir = self.path.parent().filter(|dir| !dir.as_os_str().is_empty());
+                fs::File::open(dir.unwrap_or(Path::new(".")))?.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.as_mut().expect("output file is open").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().expect("output file is open").flush()
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if let Some(temp) = &self.temp {
+            // Close it before removing it, for Windows
+            self.writer = None;
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+/// Make a file readable and writable by its owner only
+#[cfg(unix)]
+fn restrict_to_owner(file: &fs::File) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+}
+
+/// Make a file readable and writable by its owner only
+#[cfg(not(unix))]
+fn restrict_to_owner(_file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+/// Write a whole file as `fs::write` does, but through an [`OutputFile`]
+fn write_file(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = OutputFile::create(path.as_ref())?;
+    file.write_all(content.as_ref())?;
+    file.commit()
+}
+
+/// Write a whole file as [`write_file`] does, readable by its owner only
+fn write_private_file(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut file = OutputFile::create_private(path.as_ref())?;
+    file.write_all(content.as_ref())?;
+    file.commit()
+}
+
+/// Read a schema contract from a JSON Schema file
+fn load_schema_contract(path: &Path) -> Result<SchemaValidator> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read schema contract: {}", path.display()))?;
+    SchemaValidator::from_json_schema(&text).map_err(|e| map_als_error(e, &format!("Schema contract {}", path.display())))
+}
+
+/// Read the key written by 'compress --keyfile'
+fn load_pseudonym_key(path: &Path) -> Result<PseudonymKey> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read key file: {}", path.display()))?;
+    PseudonymKey::from_json(&text).with_context(|| format!("Invalid key file: {}", path.display()))
+}
+
+/// Add `key` to the keys already in the key file at `path`, creating it if
+/// needed
+fn save_pseudonym_key(path: &Path, key: PseudonymKey) -> Result<()> {
+    let mut merged = if path.exists() {
+        load_pseudonym_key(path)?
+    } else {
+        PseudonymKey::new()
+    };
+    merged.merge(key);
+    // The key reverses the pseudonyms, so only its owner may read it
+    write_private_file(path, merged.to_json()).with_context(|| format!("Failed to write key file: {}", path.display()))?;
+    info!("Wrote {} pseudonyms to {}", merged.len(), path.display());
+    Ok(())
+}
+
+/// Load configuration from a file
+fn load_config(_path: &PathBuf) -> Result<CompressorConfig> {
+    // For now, return default config
+    // TODO: Implement actual config file loading in task 35.6
+    Ok(CompressorConfig::default())
+}
+
+/// Check if an input names an http(s) URL rather than a file
+fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Describe the file an input names, or `None` for stdin and URLs
+fn source_metadata(input: &str) -> Result<Option<FileMetadata>> {
+    if input == "-" || is_url(input) {
+        return Ok(None);
+    }
+    let metadata = fs::metadata(input).with_context(|| format!("Failed to read metadata of {}", input))?;
+    let name = Path::new(input).file_name().map(|name| name.to_string_lossy().into_owned());
+    Ok(Some(FileMetadata::from_fs(name, &metadata)))
+}
+
+/// Set the modification time and permissions recorded in `metadata` on a
+/// written output file
+///
+/// Only the read, write and execute bits are restored: archives may come
+/// from anyone, and must not make their output setuid, setgid or sticky.
+fn restore_file_metadata(path: &str, metadata: &FileMetadata) -> Result<()> {
+    if let Some(modified) = metadata.modified_time() {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified))
+            .with_context(|| format!("Failed to set the modification time of {}", path))?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = metadata.mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+            .with_context(|| format!("Failed to set the permissions of {}", path))?;
+    }
+    Ok(())
+}
+
+/// Start downloading a URL, returning its body as it arrives
+#[cfg(feature = "http")]
+fn open_url(url: &str) -> Result<Box<dyn Read>> {
+    // No overall timeout: large downloads may take a while
+    let client = reqwest::blocking::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .timeout(None)
+        .build()
+        .context("Failed to create HTTP client")?;
+    let response = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to download input: {}", url))?;
+    Ok(Box::new(response))
+}
+
+#[cfg(not(feature = "http"))]
+fn open_url(url: &str) -> Result<Box<dyn Read>> {
+    anyhow::bail!("Cannot read {}: this build of als has no HTTP support (enable the 'http' feature)", url)
+}
+
+/// Open input from a file, an http(s) URL or stdin for reading as a stream
+fn open_input(input: &str) -> Result<Box<dyn Read>> {
+    if input == "-" {
+        Ok(Box::new(io::stdin()))
+    } else if is_url(input) {
+        open_url(input)
+    } else {
+        let file = fs::File::open(input).with_context(|| format!("Failed to read input file: {}", input))?;
+        Ok(Box::new(file))
+    }
+}
+
+/// Read input from file, http(s) URL or stdin
+fn read_input(input: &str) -> Result<String> {
+    if is_url(input) {
+        let mut buffer = String::new();
+        open_url(input)?
+            .read_to_string(&mut buffer)
+            .with_context(|| format!("Failed to download input: {}", input))?;
+        Ok(buffer)
+    } else if input == "-" {
+        // Read from stdin
+        let mut buffer = String::new();
+        io::stdin()
+            .read_to_string(&mut buffer)
+            .context("Failed to read from stdin")?;
+        Ok(buffer)
+    } else {
+        // Read from file
+        fs::read_to_string(input)
+            .with_context(|| format!("Failed to read input file: {}", input))
+    }
+}
+
+/// Read input from a file, an http(s) URL or stdin as bytes, which need
+/// not be UTF-8
+fn read_input_bytes(input: &str) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    if input == "-" || is_url(input) {
+        open_input(input)?
+            .read_to_end(&mut buffer)
+            .with_context(|| format!("Failed to read input: {}", input))?;
+        Ok(buffer)
+    } else {
+        fs::read(input).with_context(|| format!("Failed to read input file: {}", input))
+    }
+}
+
+/// Write output to file or stdout
+fn write_output(output: &str, content: &str) -> Result<()> {
+    if output == "-" {
+        // Write to stdout
+        io::stdout()
+            .write_all(content.as_bytes())
+            .context("Failed to write to stdout")?;
+        io::stdout().flush().context("Failed to flush stdout")?;
+    } else {
+        // Write to file
+        write_file(output, content)
+            .with_context(|| format!("Failed to write output file: {}", output))?;
+    }
+    Ok(())
+}
+
+/// Detect input format from content or file extension
+fn detect_format(input: &str, content: &str) -> Format {
+    // First try to detect from file extension, ignoring any URL query
+    if input != "-" {
+        let input = if is_url(input) {
+            input.split(['?', '#']).next().unwrap_or(input)
+        } else {
+            input
+        };
+        if input.ends_with(".csv") {
+            return Format::Csv;
+        } else if input.ends_with(".json") {
+            return Format::Json;
+        } else if input.ends_with(".als") {
+            return Format::Als;
+        }
+    }
+
+    // Try to detect from content
+    let trimmed = content.trim_start();
+    
+    // JSON typically starts with [ or {
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        return Format::Json;
+    }
+    
+    // ALS format starts with version (!v) or schema (#)
+    if trimmed.starts_with("!v") || trimmed.starts_with('#') || trimmed.starts_with('$') {
+        return Format::Als;
+    }
+    
+    // Default to CSV
+    Format::Csv
+}
+
+/// Compress JSON input, flattening OpenTelemetry OTLP exports into rows
+fn compress_json_input(compressor: &AlsCompressor, input: &str) -> Result<String> {
+    if is_otlp_json(input) {
+        debug!("Compressing OTLP export");
+        compressor
+            .compress_otlp(input)
+            .map_err(|e| map_als_error(e, "OTLP compression"))
+    } else {
+        compressor
+            .compress_json(input)
+            .map_err(|e| map_als_error(e, "JSON compression"))
+    }
+}
+
+/// Compress CSV or JSON text in the given format
+fn compress_text(compressor: &AlsCompressor, input_data: &str, format: Format) -> Result<String> {
+    match format {
+        Format::Csv => {
+            debug!("Compressing CSV data");
+            compressor
+                .compress_csv(input_data)
+                .map_err(|e| map_als_error(e, "CSV compression"))
+        }
+        Format::Json => {
+            debug!("Compressing JSON data");
+            compress_json_input(compressor, input_data)
+        }
+        Format::Als => {
+            error!("Input is already in ALS format");
+            anyhow::bail!("Input is already in ALS format. Use 'decompress' command instead.");
+        }
+        Format::Auto => {
+            error!("Failed to detect input format");
+            anyhow::bail!("Failed to detect input format");
+        }
+    }
+}
+
+/// Parse CSV or JSON text as the compressor would before compressing it
+fn parse_text(compressor: &AlsCompressor, input_data: &str, format: Format) -> Result<TabularData<'static>> {
+    match format {
+        Format::Csv => parse_csv(input_data).map_err(|e| map_als_error(e, "CSV parsing")),
+        Format::Json if is_otlp_json(input_data) => {
+            parse_otlp_json(input_data).map_err(|e| map_als_error(e, "OTLP parsing"))
+        }
+        Format::Json => parse_json_with_config(input_data, &compressor.config().json)
+            .map_err(|e| map_als_error(e, "JSON parsing")),
+        Format::Als => anyhow::bail!("Input is already in ALS format. Use 'decompress' command instead."),
+        Format::Auto => anyhow::bail!("Failed to detect input format"),
+    }
+}
+
+/// Build the key reversing the pseudonymized columns of CSV or JSON text
+fn pseudonym_key(compressor: &AlsCompressor, input_data: &str, format: Format) -> Result<PseudonymKey> {
+    let data = parse_text(compressor, input_data, format)?;
+    compressor
+        .pseudonym_key(&data)
+        .map_err(|e| map_als_error(e, "Pseudonymization"))
+}
+
+/// Get the name of an input file without its directory and extensions, or
+/// `data` for stdin and names that leave nothing
+fn input_stem(input: &str) -> &str {
+    Path::new(input)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('.').next())
+        .filter(|name| !name.is_empty() && *name != "-")
+        .unwrap_or("data")
+}
+
+/// JSON output options for the decompress command
+#[derive(Debug, Clone, Copy)]
+struct JsonOutput {
+    layout: JsonLayout,
+    format: JsonFormat,
+}
+
+/// Execute the compress command
+fn compress_command(
+    input: &str,
+    output: &str,
+    format: Format,
+    config: CompressorConfig,
+    keyfile: Option<&Path>,
+    quiet: bool,
+) -> Result<()> {
+    let start_time = Instant::now();
+    
+    info!("Starting compression: {} -> {}", input, output);
+
+    // Read input with progress bar for large files
+    let progress = create_progress_bar(quiet, "Reading input");
+    let input_data = read_input(input)?;
+    progress.finish_and_clear();
+    
+    if input_data.is_empty() {
+        warn!("Input is empty");
+        write_output(output, "")?;
+        return Ok(());
+    }
+
+    let input_size = input_data.len();
+    debug!("Read {} bytes from input", input_size);
+
+    // Detect format if auto
+    let detected_format = match format {
+        Format::Auto => {
+            let detected = detect_format(input, &input_data);
+            info!("Auto-detected format: {}", detected.as_str());
+            detected
+        }
+        _ => format,
+    };
+
+    debug!("Input format: {}", detected_format.as_str());
+
+    // Create compressor
+    let compressor = AlsCompressor::with_config(config);
+
+    // Compress based on format with progress indication
+    let progress = create_progress_bar(quiet, "Compressing");
+    let compress_start = Instant::now();
+    
+    let compressed = compress_text(&compressor, &input_data, detected_format)?;
+    
+    let compress_duration = compress_start.elapsed();
+    progress.finish_and_clear();
+
+    let output_size = compressed.len();
+    let ratio = input_size as f64 / output_size as f64;
+    let throughput = (input_size as f64 / 1_048_576.0) / compress_duration.as_secs_f64();
+    
+    debug!("Compressed {} bytes to {} bytes", input_size, output_size);
+    debug!("Compression ratio: {:.2}x", ratio);
+    debug!("Compression time: {:.3}s", compress_duration.as_secs_f64());
+    debug!("Throughput: {:.2} MB/s", throughput);
+
+    // Write output
+    let progress = create_progress_bar(quiet, "Writing output");
+    write_output(output, &compressed)?;
+    if let Some(path) = keyfile {
+        save_pseudonym_key(path, pseudonym_key(&compressor, &input_data, detected_format)?)?;
+    }
+    progress.finish_and_clear();
+
+    let total_duration = start_time.elapsed();
+
+    // Display summary
+    if !quiet {
+        let savings = ((1.0 - (output_size as f64 / input_size as f64)) * 100.0).max(0.0);
+        eprintln!("✓ Compression complete");
+        eprintln!("  Input:       {}", format_bytes(input_size));
+        eprintln!("  Output:      {}", format_bytes(output_size));
+        eprintln!("  Ratio:       {:.2}x", ratio);
+        eprintln!("  Savings:     {:.1}%", savings);
+        eprintln!("  Time:        {:.3}s", total_duration.as_secs_f64());
+        eprintln!("  Throughput:  {:.2} MB/s", throughput);
+    }
+
+    info!("Compression completed in {:.3}s", total_duration.as_secs_f64());
+
+    Ok(())
+}
+
+/// Options of the compress command for several inputs
+#[derive(Debug)]
+struct BatchOptions {
+    output_dir: Option<PathBuf>,
+    jobs: Option<usize>,
+    report: Option<PathBuf>,
+    keyfile: Option<PathBuf>,
+    preserve: bool,
+}
+
+/// Options of the pack command
+#[derive(Debug)]
+struct PackOptions {
+    dedup: bool,
+    shared_dictionaries: bool,
+    foreign_keys: Vec<(String, String, ForeignKey)>,
+}
+
+/// Outcome of compressing one of several inputs
+#[derive(Debug)]
+struct FileOutcome {
+    input: String,
+    output: PathBuf,
+    input_size: usize,
+    output_size: usize,
+    duration: std::time::Duration,
+    error: Option<String>,
+}
+
+impl FileOutcome {
+    fn ratio(&self) -> f64 {
+        if self.output_size == 0 {
+            0.0
+        } else {
+            self.input_size as f64 / self.output_size as f64
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "input": self.input,
+            "output": self.output.display().to_string(),
+            "input_bytes": self.input_size,
+            "output_bytes": self.output_size,
+            "ratio": self.ratio(),
+            "seconds": self.duration.as_secs_f64(),
+            "error": self.error,
+        })
+    }
+}
+
+/// Get the file an input is compressed to: `<name>.als` in the output
+/// directory, or next to the input
+fn batch_output_path(input: &str, output_dir: Option<&Path>) -> Result<PathBuf> {
+    if input == "-" {
+        anyhow::bail!("Cannot read stdin ('-') along with other inputs");
+    }
+    match output_dir {
+        Some(dir) => {
+            let name = input
+                .split(['?', '#'])
+                .next()
+                .and_then(|path| path.rsplit(['/', '\\']).find(|segment| !segment.is_empty()))
+                .with_context(|| format!("Cannot name the output of {}", input))?;
+            Ok(dir.join(format!("{}.als", name)))
+        }
+        None if is_url(input) => anyhow::bail!("Use --output-dir to compress URLs along with other inputs"),
+        None => Ok(PathBuf::from(format!("{}.als", input))),
+    }
+}
+
+/// Compress one of several inputs to its output file, adding its
+/// pseudonyms to `key` if given and recording its file metadata if
+/// `preserve` is set
+fn compress_file(
+    compressor: &AlsCompressor,
+    input: &str,
+    output: &Path,
+    format: Format,
+    key: Option<&std::sync::Mutex<PseudonymKey>>,
+    preserve: bool,
+) -> Result<(usize, usize)> {
+    let input_data = read_input(input)?;
+    let format = match format {
+        Format::Auto => detect_format(input, &input_data),
+        _ => format,
+    };
+    let compressed = match source_metadata(input)?.filter(|_| preserve) {
+        Some(metadata) => {
+            let compressor = AlsCompressor::with_config(compressor.config().clone().with_file_metadata(metadata));
+            compress_text(&compressor, &input_data, format)?
+        }
+        None => compress_text(compressor, &input_data, format)?,
+    };
+    write_file(output, &compressed).with_context(|| format!("Failed to write output file: {}", output.display()))?;
+    if let Some(key) = key {
+        let file_key = pseudonym_key(compressor, &input_data, format)?;
+        key.lock().expect("key lock poisoned").merge(file_key);
+    }
+    Ok((input_data.len(), compressed.len()))
+}
+
+/// Execute the compress command for several inputs
+///
+/// Inputs are shared out among worker threads, each compressing one file at
+/// a time. A failed input does not stop the others; the command fails once
+/// all are done.
+fn compress_many_command(
+    inputs: &[String],
+    format: Format,
+    batch: &BatchOptions,
+    config: CompressorConfig,
+    quiet: bool,
+) -> Result<()> {
+    use indicatif::MultiProgress;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let start_time = Instant::now();
+    let jobs = batch
+        .jobs
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, inputs.len());
+    info!("Compressing {} files with {} jobs", inputs.len(), jobs);
+
+    // Check every output path before compressing anything
+    let outputs = inputs
+        .iter()
+        .map(|input| batch_output_path(input, batch.output_dir.as_deref()))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(dir) = &batch.output_dir {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    }
+
+    let multi = if quiet {
+        MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
+    let overall = multi.add(ProgressBar::new(inputs.len() as u64));
+    overall.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:30.green} {pos}/{len} files  {elapsed}")
+            .unwrap(),
+    );
+
+    let compressor = AlsCompressor::with_config(config);
+    let key = batch.keyfile.as_ref().map(|_| std::sync::Mutex::new(PseudonymKey::new()));
+    let next = AtomicUsize::new(0);
+    let mut outcomes: Vec<(usize, FileOutcome)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                let spinner = multi.insert_before(&overall, create_progress_bar(quiet, ""));
+                let (compressor, next, outputs, overall, key) = (&compressor, &next, &outputs, &overall, key.as_ref());
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(input) = inputs.get(index) else { break };
+                        spinner.set_message(input.clone());
+                        let file_start = Instant::now();
+                        let result = compress_file(compressor, input, &outputs[index], format, key, batch.preserve);
+                        let (input_size, output_size, error) = match result {
+                            Ok((input_size, output_size)) => (input_size, output_size, None),
+                            Err(e) => (0, 0, Some(format!("{:#}", e))),
+                        };
+                        done.push((
+                            index,
+                            FileOutcome {
+                                input: input.clone(),
+                                output: outputs[index].clone(),
+                                input_size,
+                                output_size,
+                                duration: file_start.elapsed(),
+                                error,
+                            },
+                        ));
+                        overall.inc(1);
+                    }
+                    spinner.finish_and_clear();
+                    done
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("compression worker panicked"))
+            .collect()
+    });
+    overall.finish_and_clear();
+    outcomes.sort_by_key(|(index, _)| *index);
+    let outcomes: Vec<FileOutcome> = outcomes.into_iter().map(|(_, outcome)| outcome).collect();
+    let elapsed = start_time.elapsed();
+
+    let failed = outcomes.iter().filter(|outcome| outcome.error.is_some()).count();
+    let total_in: usize = outcomes.iter().map(|outcome| outcome.input_size).sum();
+    let total_out: usize = outcomes.iter().map(|outcome| outcome.output_size).sum();
+    let savings = if total_in == 0 {
+        0.0
+    } else {
+        (1.0 - total_out as f64 / total_in as f64) * 100.0
+    };
+
+    if !quiet {
+        eprintln!("{:<40} {:>12} {:>12} {:>8} {:>10}", "File", "Input", "Output", "Ratio", "Time");
+        for outcome in &outcomes {
+            match &outcome.error {
+                None => eprintln!(
+                    "{:<40} {:>12} {:>12} {:>7.2}x {:>10.2?}",
+                    outcome.input,
+                    format_bytes(outcome.input_size),
+                    format_bytes(outcome.output_size),
+                    outcome.ratio(),
+                    outcome.duration,
+                ),
+                Some(error) => eprintln!("{:<40} failed: {}", outcome.input, error),
+            }
+        }
+        eprintln!(
+            "{} of {} files compressed, {} -> {} ({:.1}% saved) in {:.2?}",
+            outcomes.len() - failed,
+            outcomes.len(),
+            format_bytes(total_in),
+            format_bytes(total_out),
+            savings,
+            elapsed,
+        );
+    }
+
+    if let Some(path) = &batch.report {
+        let report = serde_json::json!({
+            "files": outcomes.iter().map(FileOutcome::to_json).collect::<Vec<_>>(),
+            "succeeded": outcomes.len() - failed,
+            "failed": failed,
+            "input_bytes": total_in,
+            "output_bytes": total_out,
+            "savings_percent": savings,
+            "seconds": elapsed.as_secs_f64(),
+        });
+        let text = serde_json::to_string_pretty(&report).expect("report is valid JSON");
+        write_file(path, text).with_context(|| format!("Failed to write report: {}", path.display()))?;
+        info!("Wrote report to {}", path.display());
+    }
+
+    // The key covers the inputs that were compressed, failed or not
+    if let (Some(path), Some(key)) = (&batch.keyfile, key) {
+        save_pseudonym_key(path, key.into_inner().expect("key lock poisoned"))?;
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} files failed to compress", failed, outcomes.len());
+    }
+    Ok(())
+}
+
+/// Options of the compress command with --partition-by
+#[derive(Debug)]
+struct PartitionOptions {
+    column: String,
+    granularity: TimeGranularity,
+    template: String,
+    output_dir: PathBuf,
+}
+
+/// Execute the compress command with --partition-by
+///
+/// Rows are grouped by the period their time falls in, and each group is
+/// compressed to its own file, named by formatting the start of the period
+/// with the template.
+fn compress_partitioned_command(
+    input: &str,
+    format: Format,
+    partitioning: &PartitionOptions,
+    config: CompressorConfig,
+    keyfile: Option<&Path>,
+    quiet: bool,
+) -> Result<()> {
+    let start_time = Instant::now();
+    info!(
+        "Partitioning {} by {} of {}",
+        input,
+        partitioning.granularity.name(),
+        partitioning.column
+    );
+
+    // The input name is substituted first, so a % in it stays literal
+    let template = partitioning
+        .template
+        .replace("{stem}", &input_stem(input).replace('%', "%%"));
+
+    let input_data = read_input(input)?;
+    let format = match format {
+        Format::Auto => detect_format(input, &input_data),
+        _ => format,
+    };
+    let compressor = AlsCompressor::with_config(config);
+    let mut data = parse_text(&compressor, &input_data, format)?;
+    // Filter first so periods whose rows are all dropped get no file
+    if let Some(predicate) = &compressor.config().row_filter {
+        data = data.filter(predicate).map_err(|e| map_als_error(e, "Row filter"))?;
+    }
+    let partitions = data
+        .partition_by_time(&partitioning.column, partitioning.granularity)
+        .map_err(|e| map_als_error(e, "Partitioning"))?;
+
+    // Name every partition before writing any, so a template too coarse for
+    // the granularity fails without leaving files behind
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(partitions.len());
+    for partition in &partitions {
+        let name = partition
+            .path(&template)
+            .with_context(|| format!("Unsupported % specifier in --partition-template: {}", partitioning.template))?;
+        let path = partitioning.output_dir.join(name);
+        if paths.contains(&path) {
+            anyhow::bail!(
+                "Several {} partitions map to {}; add finer specifiers to --partition-template",
+                partitioning.granularity.name(),
+                path.display()
+            );
+        }
+        paths.push(path);
+    }
+
+    let progress = create_progress_bar(quiet, "Compressing partitions");
+    let mut output_size = 0;
+    for (partition, path) in partitions.iter().zip(&paths) {
+        progress.set_message(path.display().to_string());
+        let doc = compressor
+            .compress(&partition.data)
+            .map_err(|e| map_als_error(e, "Compression"))?;
+        let compressed = AlsSerializer::new().serialize(&doc);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        write_file(path, &compressed).with_context(|| format!("Failed to write output file: {}", path.display()))?;
+        debug!("Wrote {} rows to {}", partition.data.row_count, path.display());
+        output_size += compressed.len();
+    }
+    if let Some(path) = keyfile {
+        let key = compressor
+            .pseudonym_key(&data)
+            .map_err(|e| map_als_error(e, "Pseudonymization"))?;
+        save_pseudonym_key(path, key)?;
+    }
+    progress.finish_and_clear();
+
+    if !quiet {
+        eprintln!("✓ Compression complete");
+        eprintln!("  Partitions:  {} by {}", partitions.len(), partitioning.granularity.name());
+        eprintln!("  Rows:        {}", data.row_count);
+        eprintln!("  Input:       {}", format_bytes(input_data.len()));
+        eprintln!("  Output:      {}", format_bytes(output_size));
+        eprintln!("  Time:        {:.3}s", start_time.elapsed().as_secs_f64());
+    }
+
+    Ok(())
+}
+
+/// Execute the compress command with --chunk-rows
+///
+/// The input is read as a stream and never held in memory as a whole; each
+/// chunk of rows becomes an independent document of the output container.
+fn compress_chunked_command(
+    input: &str,
+    output: &str,
+    format: Format,
+    rows: usize,
+    config: CompressorConfig,
+    quiet: bool,
+) -> Result<()> {
+    let start_time = Instant::now();
+    info!("Starting chunked compression: {} -> {}", input, output);
+    if rows == 0 {
+        anyhow::bail!("--chunk-rows must be at least 1");
+    }
+
+    let mut reader = io::BufReader::new(open_input(input)?);
+    let detected_format = match format {
+        Format::Auto => {
+            let start = reader.fill_buf().context("Failed to read input")?;
+            let detected = detect_format(input, &String::from_utf8_lossy(start));
+            info!("Auto-detected format: {}", detected.as_str());
+            detected
+        }
+        _ => format,
+    };
+
+    let mut file = if output == "-" {
+        None
+    } else {
+        let file = OutputFile::create(Path::new(output)).with_context(|| format!("Failed to write output file: {}", output))?;
+        Some(file)
+    };
+    let output_writer: Box<dyn Write + '_> = match &mut file {
+        Some(file) => Box::new(file),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = AlsContainerWriter::new(output_writer).map_err(|e| map_als_error(e, "Container"))?;
+
+    let mut compressor = StreamingCompressor::with_config(reader, config)
+        .with_csv_chunk_size(rows)
+        .with_json_chunk_size(rows);
+    let chunks: Box<dyn Iterator<Item = als_compression::Result<String>>> = match detected_format {
+        Format::Csv => Box::new(compressor.compress_csv_chunks()),
+        Format::Json => Box::new(compressor.compress_json_chunks()),
+        Format::Als => anyhow::bail!("Input is already in ALS format. Use 'decompress' command instead."),
+        Format::Auto => anyhow::bail!("Failed to detect input format"),
+    };
+
+    let progress = create_progress_bar(quiet, "Compressing");
+    let mut count = 0;
+    for chunk in chunks {
+        let chunk = chunk.map_err(|e| map_als_error(e, "Chunked compression"))?;
+        writer
+            .add(&format!("part-{:05}", count), &chunk)
+            .map_err(|e| map_als_error(e, "Container"))?;
+        count += 1;
+        progress.set_message(format!("Compressed {} chunks", count));
+    }
+    writer.finish().map_err(|e| map_als_error(e, "Container"))?;
+    if let Some(file) = file {
+        file.commit().with_context(|| format!("Failed to write output file: {}", output))?;
+    }
+    progress.finish_and_clear();
+
+    if !quiet {
+        eprintln!("✓ Compression complete");
+        eprintln!("  Chunks:      {} of up to {} rows", count, rows);
+        eprintln!("  Time:        {:.3}s", start_time.elapsed().as_secs_f64());
+    }
+
+    Ok(())
+}
+
+/// Execute the decompress command
+fn decompress_command(
+    input: &str,
+    output: &str,
+    format: Format,
+    json: JsonOutput,
+    key: Option<&PseudonymKey>,
+    preserve: bool,
+    quiet: bool,
+) -> Result<()> {
+    let start_time = Instant::now();
+    
+    info!("Starting decompression: {} -> {}", input, output);
+    debug!("Output format: {}", format.as_str());
+
+    // Read ALS input with progress bar
+    let progress = create_progress_bar(quiet, "Reading input");
+    let als_data = read_input(input)?;
+    progress.finish_and_clear();
+    
+    if als_data.is_empty() {
+        warn!("Input is empty");
+        write_output(output, "")?;
+        return Ok(());
+    }
+
+    let input_size = als_data.len();
+    debug!("Read {} bytes from input", input_size);
+
+    // Validate that format is CSV or JSON (not ALS or Auto)
+    let output_format = match format {
+        Format::Csv => Format::Csv,
+        Format::Json => Format::Json,
+        Format::Als => {
+            error!("Cannot decompress to ALS format");
+            anyhow::bail!("Cannot decompress to ALS format. Use 'csv' or 'json' as output format.");
+        }
+        Format::Auto => {
+            // Default to CSV for auto-detection
+            info!("Auto-detecting output format: defaulting to CSV");
+            Format::Csv
+        }
+    };
+
+    // Create parser
+    let parser = AlsParser::with_config(ParserConfig::new().with_json_format(json.format));
+
+    // Decompress based on output format with progress indication
+    let progress = create_progress_bar(quiet, "Decompressing");
+    let decompress_start = Instant::now();
+    
+    let decompressed = if let Some(key) = key {
+        debug!("Revealing pseudonymized columns");
+        let data = parser
+            .parse(&als_data)
+            .and_then(|doc| parser.reveal(&doc, key))
+            .map_err(|e| map_als_error_in(e, "ALS decompression", &als_data))?;
+        let result = match (output_format, json.layout) {
+            (Format::Json, JsonLayout::Rows) => to_json_with_format(&data, json.format),
+            (Format::Json, JsonLayout::Columns) => to_json_columnar_with_format(&data, json.format),
+            _ => to_csv(&data),
+        };
+        result.map_err(|e| map_als_error(e, "Output conversion"))?
+    } else {
+        match output_format {
+            Format::Csv => match StoreFrame::parse(&als_data) {
+                // Stored CSV is returned exactly as it was compressed
+                Ok(Some(frame)) if frame.format == StoredFormat::Csv => {
+                    debug!("Passing through stored CSV input");
+                    frame.payload.to_string()
+                }
+                _ => {
+                    debug!("Decompressing to CSV");
+                    parser
+                        .to_csv(&als_data)
+                        .map_err(|e| map_als_error_in(e, "ALS decompression to CSV", &als_data))?
+                }
+            },
+            Format::Json => {
+                debug!("Decompressing to JSON ({:?} layout, {:?})", json.layout, json.format);
+                let result = match json.layout {
+                    JsonLayout::Rows => parser.to_json(&als_data),
+                    JsonLayout::Columns => parser.to_json_columnar(&als_data),
+                };
+                result.map_err(|e| map_als_error_in(e, "ALS decompression to JSON", &als_data))?
+            }
+            _ => unreachable!("Output format should be CSV or JSON at this point"),
+        }
+    };
+    
+    let decompress_duration = decompress_start.elapsed();
+    progress.finish_and_clear();
+
+    let output_size = decompressed.len();
+    let expansion_ratio = output_size as f64 / input_size as f64;
+    let throughput = (output_size as f64 / 1_048_576.0) / decompress_duration.as_secs_f64();
+    
+    debug!("Decompressed {} bytes to {} bytes", input_size, output_size);
+    debug!("Expansion ratio: {:.2}x", expansion_ratio);
+    debug!("Decompression time: {:.3}s", decompress_duration.as_secs_f64());
+    debug!("Throughput: {:.2} MB/s", throughput);
+
+    // Write output
+    let progress = create_progress_bar(quiet, "Writing output");
+    write_output(output, &decompressed)?;
+    progress.finish_and_clear();
+
+    if preserve {
+        let metadata = FileMetadata::find(&als_data).map_err(|e| map_als_error_in(e, "Reading file metadata", &als_data))?;
+        match metadata {
+            Some(_) if output == "-" => warn!("--preserve has no file to restore metadata on when writing to stdout"),
+            Some(metadata) => restore_file_metadata(output, &metadata)?,
+            None => warn!("{} records no file metadata to restore", input),
+        }
+    }
+
+    let total_duration = start_time.elapsed();
+
+    // Display summary
+    if !quiet {
+        eprintln!("✓ Decompression complete");
+        eprintln!("  Input:       {}", format_bytes(input_size));
+        eprintln!("  Output:      {}", format_bytes(output_size));
+        eprintln!("  Expansion:   {:.2}x", expansion_ratio);
+        eprintln!("  Time:        {:.3}s", total_duration.as_secs_f64());
+        eprintln!("  Throughput:  {:.2} MB/s", throughput);
+    }
+
+    info!("Decompression completed in {:.3}s", total_duration.as_secs_f64());
+
+    Ok(())
+}
+
+/// Execute the info command
+fn info_command(input: &str, verbose: bool, quiet: bool) -> Result<()> {
+    let start_time = Instant::now();
+    
+    info!("Reading ALS document info from {}", input);
+
+    // Read ALS input with progress bar
+    let progress = create_progress_bar(quiet, "Reading input");
+    let als_data = read_input(input)?;
+    progress.finish_and_clear();
+    
+    if als_data.is_empty() {
+        warn!("Input is empty");
+        return Ok(());
+    }
+
+    debug!("Read {} bytes from input", als_data.len());
+
+    // Parse the ALS document
+    let progress = create_progress_bar(quiet, "Parsing ALS");
+    let parser = AlsParser::new();
+    let parse_start = Instant::now();
+    
+    let doc = parser
+        .parse(&als_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &als_data))?;
+    
+    let parse_duration = parse_start.elapsed();
+    progress.finish_and_clear();
+    
+    debug!("Parsed ALS document in {:.3}s", parse_duration.as_secs_f64());
+
+    let uncompressed = doc
+        .uncompressed_size(StoredFormat::Csv)
+        .map_err(|e| map_als_error_in(e, "ALS expansion", &als_data))?;
+
+    // Column profiles need the values, so only expand for verbose output
+    let profiles = if verbose {
+        parser
+            .profile(&doc)
+            .map_err(|e| map_als_error_in(e, "ALS expansion", &als_data))?
+    } else {
+        Vec::new()
+    };
+
+    // Display document information
+    if !quiet {
+        display_document_info(&doc, &als_data, uncompressed, &profiles, verbose);
+    }
+
+    let total_duration = start_time.elapsed();
+    debug!("Info command completed in {:.3}s", total_duration.as_secs_f64());
+
+    Ok(())
+}
+
+/// Execute the explain command
+fn explain_command(input: &str, format: Format, top: usize, timing: bool, config: CompressorConfig, quiet: bool) -> Result<()> {
+    info!("Explaining column encodings for {}", input);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let input_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    if input_data.is_empty() {
+        warn!("Input is empty");
+        return Ok(());
+    }
+
+    let detected_format = match format {
+        Format::Auto => detect_format(input, &input_data),
+        _ => format,
+    };
+
+    let data = match detected_format {
+        Format::Csv => parse_csv(&input_data).map_err(|e| map_als_error(e, "CSV parsing"))?,
+        Format::Json => parse_json_with_config(&input_data, &config.json).map_err(|e| map_als_error(e, "JSON parsing"))?,
+        Format::Als => {
+            anyhow::bail!("Input is already in ALS format. Use 'info' command instead.");
+        }
+        Format::Auto => {
+            anyhow::bail!("Failed to detect input format");
+        }
+    };
+
+    let compressor = AlsCompressor::with_config(config.clone().with_metrics(timing));
+    let progress = create_progress_bar(quiet, "Detecting patterns");
+    let report = compressor.explain(&data, top);
+    let metrics = if timing {
+        let (_, stats) = compressor
+            .compress_with_stats(&data)
+            .map_err(|e| map_als_error(e, "Compression"))?;
+        stats.metrics
+    } else {
+        None
+    };
+    progress.finish_and_clear();
+
+    let serializer = AlsSerializer::new();
+    for column in &report {
+        println!("{}:", column.name);
+        for (rank, result) in column.ranked().enumerate() {
+            let mut operator = String::new();
+            serializer.serialize_operator(&mut operator, &result.operator);
+            println!(
+                "  {}. {:<16} {:>8.2}x  {}",
+                rank + 1,
+                format!("{:?}", result.pattern_type),
+                result.compression_ratio,
+                truncate_for_display(&operator, 60)
+            );
+        }
+        let column_metrics = metrics.as_ref().and_then(|metrics| metrics.columns.iter().find(|c| c.name == column.name));
+        if let Some(column_metrics) = column_metrics {
+            println!("  time {}{}", format_duration(column_metrics.elapsed), format_allocations(column_metrics.allocations));
+            for timing in &column_metrics.detectors {
+                println!("    {:<22} {:>10}  x{}", timing.detector, format_duration(timing.elapsed), timing.calls);
+            }
+        }
+    }
+
+    if let Some(metrics) = metrics {
+        println!();
+        println!("Timing:");
+        println!("  Total:       {}", format_duration(metrics.elapsed));
+        println!(
+            "  Dictionary:  {}{}",
+            format_duration(metrics.dictionary_time),
+            format_allocations(metrics.dictionary_allocations)
+        );
+        for timing in metrics.detector_totals() {
+            println!("  {:<24} {:>10}  x{}", timing.detector, format_duration(timing.elapsed), timing.calls);
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute the advise command
+fn advise_command(input: &str, format: Format, config: CompressorConfig, quiet: bool) -> Result<()> {
+    info!("Looking for changes that compress {} better", input);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let input_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    let format = match format {
+        Format::Auto => detect_format(input, &input_data),
+        _ => format,
+    };
+    let compressor = AlsCompressor::with_config(config);
+    let data = parse_text(&compressor, &input_data, format)?;
+
+    let progress = create_progress_bar(quiet, "Trying changes");
+    let advice = compressor.advise(&data).map_err(|e| map_als_error(e, "Advice"))?;
+    progress.finish_and_clear();
+
+    println!("Current ratio: {:.2}x over {} rows", advice.ratio, advice.sample_rows);
+    if advice.suggestions.is_empty() {
+        println!("No change is predicted to improve the ratio");
+        return Ok(());
+    }
+    println!("Suggestions:");
+    for (rank, suggestion) in advice.suggestions.iter().enumerate() {
+        println!(
+            "  {}. {:<48} {:>8.2}x  {:+.0}%",
+            rank + 1,
+            suggestion.change.to_string(),
+            suggestion.ratio,
+            (suggestion.ratio / advice.ratio - 1.0) * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Format a duration in milliseconds with microsecond precision.
+fn format_duration(duration: std::time::Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Format allocation counts as a suffix, or nothing if they were not counted.
+fn format_allocations(allocations: Option<AllocationCounts>) -> String {
+    allocations.map_or_else(String::new, |counts| {
+        format!(" ({} allocations, {})", counts.allocations, format_bytes(counts.bytes as usize))
+    })
+}
+
+/// Execute the query command
+fn query_command(
+    input: &str,
+    output: &str,
+    predicate: &Predicate,
+    format: Format,
+    count: bool,
+    quiet: bool,
+) -> Result<()> {
+    let (column, value) = (&predicate.column, &predicate.value);
+    info!(
+        "Querying {} for {} {} {}",
+        input,
+        column,
+        predicate.comparison.as_str(),
+        value
+    );
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let als_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    let query = AlsQuery::parse(&als_data).map_err(|e| map_als_error_in(e, "ALS parsing", &als_data))?;
+    if predicate.comparison != Comparison::Eq {
+        if let Some(range) = predicate.range() {
+            if let Ok(blocks) = query.candidate_range_blocks(column, &range) {
+                debug!(
+                    "Scanning {} of {} blocks of column '{}'",
+                    blocks.len(),
+                    query.document().block_count(),
+                    column
+                );
+            }
+        }
+        let rows = query
+            .find(predicate)
+            .map_err(|e| map_als_error(e, "ALS query"))?;
+        if count {
+            return write_output(output, &format!("{}\n", rows.len()));
+        }
+        let selected = query
+            .select_rows(&rows)
+            .map_err(|e| map_als_error(e, "ALS query"))?;
+        return write_query_result(output, &selected, format);
+    }
+
+    if query.is_indexed(column) {
+        debug!("Answering from the index of column '{}'", column);
+    } else if let Ok(blocks) = query.candidate_blocks(column, value) {
+        debug!(
+            "Scanning {} of {} blocks of column '{}'",
+            blocks.len(),
+            query.document().block_count(),
+            column
+        );
+    }
+
+    if count {
+        let matches = query
+            .count_eq(column, value)
+            .map_err(|e| map_als_error(e, "ALS query"))?;
+        return write_output(output, &format!("{}\n", matches));
+    }
+
+    let selected = query
+        .select_eq(column, value)
+        .map_err(|e| map_als_error(e, "ALS query"))?;
+    write_query_result(output, &selected, format)
+}
+
+/// Pattern the grep command looks for
+enum GrepPattern<'a> {
+    /// Plain text
+    Text(&'a str),
+    /// A regular expression
+    Regex(&'a str),
+}
+
+/// Execute the grep command
+fn grep_command(
+    pattern: GrepPattern,
+    input: &str,
+    output: &str,
+    format: Format,
+    count: bool,
+    cells: bool,
+    quiet: bool,
+) -> Result<()> {
+    let (GrepPattern::Text(text) | GrepPattern::Regex(text)) = pattern;
+    info!("Searching {} for '{}'", input, text);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let als_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    let parser = AlsParser::new();
+    let doc = parser
+        .parse(&als_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &als_data))?;
+    let matches = match pattern {
+        GrepPattern::Text(text) => parser.search(&doc, text),
+        GrepPattern::Regex(regex) => parser.search_regex(&doc, regex),
+    }
+    .map_err(|e| map_als_error(e, "ALS search"))?;
+    debug!("Found {} matching values", matches.len());
+
+    if cells {
+        let (rows, columns): (Vec<Value>, Vec<Value>) = matches
+            .iter()
+            .map(|&(row, column)| (Value::Integer(row as i64), Value::String(doc.schema[column].clone().into())))
+            .unzip();
+        let mut listing = TabularData::with_capacity(2);
+        listing.add_column(Column::new("row", rows));
+        listing.add_column(Column::new("column", columns));
+        let listing = match format {
+            Format::Json => to_json(&listing),
+            _ => to_csv(&listing),
+        };
+        return write_output(output, &listing.map_err(|e| map_als_error(e, "ALS search"))?);
+    }
+
+    let mut rows: Vec<usize> = matches.into_iter().map(|(row, _)| row).collect();
+    rows.dedup();
+    if count {
+        return write_output(output, &format!("{}\n", rows.len()));
+    }
+    let selected = AlsQuery::new(doc)
+        .select_rows(&rows)
+        .map_err(|e| map_als_error(e, "ALS search"))?;
+    write_query_result(output, &selected, format)
+}
+
+/// Execute the agg command
+fn agg_command(
+    column: &str,
+    input: &str,
+    output: &str,
+    aggregates: &[Aggregate],
+    by: Option<&str>,
+    format: Format,
+    quiet: bool,
+) -> Result<()> {
+    info!("Aggregating column '{}' of {}", column, input);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let als_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    let query = AlsQuery::parse(&als_data).map_err(|e| map_als_error_in(e, "ALS parsing", &als_data))?;
+    let mut table = TabularData::with_capacity(aggregates.len() + 1);
+    for (i, &aggregate) in aggregates.iter().enumerate() {
+        let name = format!("{}({})", aggregate.name(), column);
+        let values = match by {
+            Some(group) => {
+                let (keys, values): (Vec<Value>, Vec<Value>) = query
+                    .aggregate_by(group, column, aggregate)
+                    .map_err(|e| map_als_error(e, "ALS aggregation"))?
+                    .into_iter()
+                    .unzip();
+                if i == 0 {
+                    table.add_column(Column::new(group.to_string(), keys));
+                }
+                values
+            }
+            None => vec![query
+                .aggregate(column, aggregate)
+                .map_err(|e| map_als_error(e, "ALS aggregation"))?],
+        };
+        table.add_column(Column::new(name, values));
+    }
+
+    let result = match format {
+        Format::Json => to_json(&table),
+        _ => to_csv(&table),
+    };
+    write_output(output, &result.map_err(|e| map_als_error(e, "ALS aggregation"))?)
+}
+
+/// Execute the join command
+fn join_command(
+    left: &str,
+    right: &str,
+    on: &str,
+    output: &str,
+    format: Format,
+    config: CompressorConfig,
+    quiet: bool,
+) -> Result<()> {
+    info!("Joining {} and {} on '{}'", left, right, on);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let parser = AlsParser::new();
+    let left_data = read_input(left)?;
+    let left_doc = parser
+        .parse(&left_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &left_data))?;
+    let right_data = read_input(right)?;
+    let right_doc = parser
+        .parse(&right_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &right_data))?;
+    progress.finish_and_clear();
+
+    let progress = create_progress_bar(quiet, "Joining");
+    let joined = als_compression::join(&left_doc, &right_doc, on, &AlsCompressor::with_config(config))
+        .map_err(|e| map_als_error(e, "ALS join"))?;
+    progress.finish_and_clear();
+
+    write_query_result(output, &joined, format)?;
+
+    if !quiet {
+        eprintln!("✓ Joined {} and {} on '{}'", left, right, on);
+        eprintln!("  Left rows:   {}", left_doc.row_count());
+        eprintln!("  Right rows:  {}", right_doc.row_count());
+        eprintln!("  Joined rows: {}", joined.row_count());
+    }
+
+    Ok(())
+}
+
+/// Layout change the melt and pivot commands make
+enum Reshape<'a> {
+    /// Unpivot all columns but `ids`
+    Melt {
+        ids: &'a [&'a str],
+        variable: &'a str,
+        value: &'a str,
+    },
+    /// Spread `values` over one column per value of `columns`
+    Pivot { columns: &'a str, values: &'a str },
+}
+
+/// Execute the melt or pivot command
+fn reshape_command(
+    input: &str,
+    output: &str,
+    format: Format,
+    reshape: Reshape,
+    config: CompressorConfig,
+    quiet: bool,
+) -> Result<()> {
+    let start_time = Instant::now();
+    let verb = match reshape {
+        Reshape::Melt { .. } => "Melted",
+        Reshape::Pivot { .. } => "Pivoted",
+    };
+    info!("Reshaping {}", input);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let input_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    let format = match format {
+        Format::Auto => detect_format(input, &input_data),
+        _ => format,
+    };
+    let compressor = AlsCompressor::with_config(config);
+    let data = parse_text(&compressor, &input_data, format)?;
+    let input_rows = data.row_count;
+    let data = match reshape {
+        Reshape::Melt { ids, variable, value } => data.melt(ids, variable, value),
+        Reshape::Pivot { columns, values } => data.pivot(columns, values),
+    }
+    .map_err(|e| map_als_error(e, "Reshaping"))?;
+
+    let progress = create_progress_bar(quiet, "Compressing");
+    let doc = compressor.compress(&data).map_err(|e| map_als_error(e, "Compression"))?;
+    let compressed = AlsSerializer::new().serialize(&doc);
+    progress.finish_and_clear();
+    write_output(output, &compressed)?;
+
+    if !quiet {
+        eprintln!("✓ {} {}", verb, input);
+        eprintln!("  Rows:        {} → {}", input_rows, data.row_count);
+        eprintln!("  Columns:     {}", data.column_count());
+        eprintln!("  Input:       {}", format_bytes(input_data.len()));
+        eprintln!("  Output:      {}", format_bytes(compressed.len()));
+        eprintln!("  Ratio:       {:.2}x", input_data.len() as f64 / compressed.len().max(1) as f64);
+        eprintln!("  Time:        {:.3}s", start_time.elapsed().as_secs_f64());
+    }
+
+    Ok(())
+}
+
+/// Write the rows selected by a query in the requested format
+fn write_query_result(output: &str, selected: &als_compression::AlsDocument, format: Format) -> Result<()> {
+    let selected = AlsSerializer::new().serialize(selected);
+    let parser = AlsParser::new();
+    let result = match format {
+        Format::Csv | Format::Auto => parser.to_csv(&selected),
+        Format::Json => parser.to_json(&selected),
+        Format::Als => Ok(selected),
+    };
+    let result = result.map_err(|e| map_als_error(e, "ALS query"))?;
+    write_output(output, &result)
+}
+
+/// Execute the delta command
+fn delta_command(
+    base: &str,
+    input: &str,
+    output: &str,
+    format: Format,
+    config: CompressorConfig,
+    quiet: bool,
+) -> Result<()> {
+    info!("Compressing {} as a delta against {}", input, base);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let base_data = read_input(base)?;
+    let base_doc = AlsParser::new()
+        .parse(&base_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &base_data))?;
+    let input_data = read_input(input)?;
+    progress.finish_and_clear();
+
+    let data = match format {
+        Format::Auto => detect_format(input, &input_data),
+        _ => format,
+    };
+    let data = match data {
+        Format::Csv => parse_csv(&input_data).map_err(|e| map_als_error(e, "CSV parsing"))?,
+        Format::Json => parse_json_with_config(&input_data, &config.json).map_err(|e| map_als_error(e, "JSON parsing"))?,
+        Format::Als | Format::Auto => anyhow::bail!("Delta input must be CSV or JSON"),
+    };
+
+    let progress = create_progress_bar(quiet, "Compressing");
+    let delta = AlsCompressor::with_config(config)
+        .compress_delta(&base_doc, &data)
+        .map_err(|e| map_als_error(e, "Delta compression"))?;
+    progress.finish_and_clear();
+
+    let script = delta.delta.as_ref().expect("compress_delta sets the edit script");
+    let compressed = AlsSerializer::new().serialize(&delta);
+    write_output(output, &compressed)?;
+
+    if !quiet {
+        eprintln!("✓ Delta complete");
+        eprintln!("  Copied:      {} rows", script.copied_rows());
+        eprintln!("  Inserted:    {} rows", script.inserted_rows());
+        eprintln!("  Deleted:     {} rows", script.deleted_rows());
+        eprintln!("  Output:      {}", format_bytes(compressed.len()));
+    }
+
+    Ok(())
+}
+
+/// Execute the patch command
+fn patch_command(base: &str, input: &str, output: &str, format: Format, quiet: bool) -> Result<()> {
+    info!("Applying delta {} to {}", input, base);
+
+    let progress = create_progress_bar(quiet, "Reading input");
+    let parser = AlsParser::new();
+    let base_data = read_input(base)?;
+    let base_doc = parser
+        .parse(&base_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &base_data))?;
+    let delta_data = read_input(input)?;
+    let delta = parser
+        .parse(&delta_data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &delta_data))?;
+    progress.finish_and_clear();
+
+    let data = parser
+        .apply_delta(&base_doc, &delta)
+        .map_err(|e| map_als_error(e, "Delta"))?;
+    let result = match format {
+        Format::Json => to_json(&data),
+        _ => to_csv(&data),
+    };
+    let result = result.map_err(|e| map_als_error(e, "Delta"))?;
+    write_output(output, &result)
+}
+
+/// Execute the sign command
+fn sign_command(input: &str, output: &str, key: &Path, detached: bool, quiet: bool) -> Result<()> {
+    info!("Signing {}", input);
+
+    let secret = fs::read_to_string(key)
+        .with_context(|| format!("Failed to read signing key: {}", key.display()))?;
+    let key = parse_signing_key(&secret).map_err(|e| map_als_error(e, "Signing key"))?;
+    let data = read_input(input)?;
+
+    let signed = if detached {
+        AlsSignature::sign(data.as_bytes(), &key).to_line() + "\n"
+    } else {
+        sign_document(&data, &key)
+    };
+    write_output(output, &signed)?;
+
+    if !quiet {
+        let public_key: String = key.verifying_key().to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        eprintln!("✓ Signed {}", input);
+        eprintln!("  Public key:  {}", public_key);
+    }
+
+    Ok(())
+}
+
+/// Execute the verify-signature command
+fn verify_signature_command(input: &str, signature: Option<&str>, public_key: &str, quiet: bool) -> Result<()> {
+    info!("Verifying signature of {}", input);
+
+    let public_key = if Path::new(public_key).is_file() {
+        fs::read_to_string(public_key).with_context(|| format!("Failed to read public key: {}", public_key))?
+    } else {
+        public_key.to_string()
+    };
+    let key = parse_verifying_key(&public_key).map_err(|e| map_als_error(e, "Public key"))?;
+    let data = read_input(input)?;
+
+    let verified = match signature {
+        Some(signature) => AlsSignature::parse_line(&read_input(signature)?)
+            .and_then(|signature| signature.verify(data.as_bytes(), &key)),
+        None => verify_document(&data, &key).map(|_| ()),
+    };
+    verified.map_err(|e| map_als_error(e, "Signature"))?;
+
+    if !quiet {
+        eprintln!("✓ Signature of {} is valid", input);
+    }
+
+    Ok(())
+}
+
+/// Execute the canonicalize command
+fn canonicalize_command(input: &str, output: &str, quiet: bool) -> Result<()> {
+    info!("Canonicalizing {}", input);
+
+    let data = read_input(input)?;
+    let mut doc = AlsParser::new()
+        .parse(&data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &data))?;
+    doc.canonicalize();
+    let canonical = AlsSerializer::new().serialize(&doc);
+    write_output(output, &canonical)?;
+
+    if !quiet {
+        eprintln!("✓ Canonicalized {}", input);
+        eprintln!("  Input:       {}", format_bytes(data.len()));
+        eprintln!("  Output:      {}", format_bytes(canonical.len()));
+    }
+
+    Ok(())
+}
+
+/// Execute the repair command
+fn repair_command(input: &str, output: &str, quiet: bool) -> Result<()> {
+    info!("Repairing {}", input);
+
+    // A file cut short may end partway through a UTF-8 sequence, which
+    // falls in the last operator and is dropped with it
+    let data = read_input_bytes(input)?;
+    let data = String::from_utf8_lossy(&data);
+    let partial = AlsParser::new()
+        .parse_partial(&data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &data))?;
+    let repaired = AlsSerializer::new().serialize(&partial.document);
+    write_output(output, &repaired)?;
+
+    if !quiet {
+        match (&partial.error, partial.expected_rows) {
+            (None, _) => eprintln!("✓ {} is intact", input),
+            (Some(_), Some(expected)) => eprintln!("✓ Recovered {} of {} rows from {}", partial.rows(), expected, input),
+            (Some(_), None) => eprintln!("✓ Recovered {} rows from {}", partial.rows(), input),
+        }
+        eprintln!("  Columns:     {}", partial.document.column_count());
+        if !partial.lost_columns.is_empty() {
+            eprintln!("  Lost:        {}", partial.lost_columns.join(", "));
+        }
+        if let Some(error) = &partial.error {
+            eprintln!("  Damage:      {}", error);
+        }
+        eprintln!("  Output:      {}", format_bytes(repaired.len()));
+    }
+
+    Ok(())
+}
+
+/// Execute the recompress command
+///
+/// Rewriting the input in place is skipped when the result would be no
+/// smaller, unless `force` is set. Writes go through a temporary file, so
+/// a failure leaves the input as it was.
+fn recompress_command(input: &str, output: &str, config: CompressorConfig, force: bool, quiet: bool) -> Result<()> {
+    let start_time = Instant::now();
+    info!("Recompressing {} -> {}", input, output);
+
+    let data = read_input(input)?;
+    if split_signature(&data).1.is_some() {
+        warn!("The signature of {} no longer matches once recompressed and is dropped; sign the output again", input);
+    }
+    let doc = AlsParser::new()
+        .parse(&data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &data))?;
+    let recompressed = AlsCompressor::with_config(config)
+        .recompress(&doc)
+        .map_err(|e| map_als_error(e, "Recompression"))?;
+    let text = AlsSerializer::new().serialize(&recompressed);
+
+    let change = (text.len() as f64 / data.len().max(1) as f64 - 1.0) * 100.0;
+    let in_place = output == input && input != "-";
+    if in_place && text.len() >= data.len() && !force {
+        if !quiet {
+            eprintln!("✓ {} is no smaller recompressed; left as it is (use --force to rewrite it)", input);
+            eprintln!("  Size:        {} ({} recompressed)", format_bytes(data.len()), format_bytes(text.len()));
+        }
+        return Ok(());
+    }
+    write_output(output, &text)?;
+
+    if !quiet {
+        eprintln!("✓ Recompressed {}", input);
+        eprintln!("  Before:      {}", format_bytes(data.len()));
+        eprintln!("  After:       {}", format_bytes(text.len()));
+        eprintln!("  Change:      {:+.1}%", change);
+        eprintln!("  Time:        {:.3}s", start_time.elapsed().as_secs_f64());
+    }
+
+    Ok(())
+}
+
+/// Execute the schema command
+fn schema_command(input: &str, output: &str, format: SchemaFormat, table: Option<&str>, quiet: bool) -> Result<()> {
+    info!("Deriving schema of {}", input);
+
+    let data = read_input(input)?;
+    let doc = AlsParser::new()
+        .parse(&data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &data))?;
+    let schema = doc
+        .schema_descriptor()
+        .map_err(|e| map_als_error(e, "Schema inference"))?;
+
+    let text = match format {
+        SchemaFormat::Jsonschema => schema.to_json_schema() + "\n",
+        SchemaFormat::Ddl => {
+            // Name the table after the input file, without extensions
+            schema.to_sql_ddl(table.unwrap_or_else(|| input_stem(input)))
+        }
+    };
+    write_output(output, &text)?;
+
+    if !quiet {
+        eprintln!("✓ Derived schema of {}", input);
+        eprintln!("  Columns:     {}", schema.columns.len());
+        eprintln!("  Rows:        {}", schema.row_count);
+        for column in &schema.columns {
+            debug!(
+                "{}: {:?}, {} nulls, {} distinct values",
+                column.name, column.column_type, column.null_count, column.cardinality
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute the scale command
+fn scale_command(input: &str, output: &str, factor: usize, quiet: bool) -> Result<()> {
+    info!("Scaling {} by {}", input, factor);
+
+    let data = read_input(input)?;
+    let doc = AlsParser::new()
+        .parse(&data)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &data))?;
+    let scaled = doc.scale(factor).map_err(|e| map_als_error(e, "Scaling"))?;
+    let text = AlsSerializer::new().serialize(&scaled);
+    write_output(output, &text)?;
+
+    if !quiet {
+        eprintln!("✓ Scaled {} by {}", input, factor);
+        eprintln!("  Rows:        {} -> {}", doc.row_count(), scaled.row_count());
+        eprintln!("  Input:       {}", format_bytes(data.len()));
+        eprintln!("  Output:      {}", format_bytes(text.len()));
+    }
+
+    Ok(())
+}
+
+/// Execute the generate command
+fn generate_command(
+    input: &str,
+    output: &str,
+    format: Format,
+    rows: Option<usize>,
+    sample: bool,
+    seed: u64,
+    quiet: bool,
+) -> Result<()> {
+    info!("Generating data from {}", input);
+
+    let spec = read_input(input)?;
+    let mut generator = AlsGenerator::parse(&spec)
+        .map_err(|e| map_als_error_in(e, "ALS parsing", &spec))?
+        .with_random_sampling(sample)
+        .with_seed(seed);
+    if let Some(rows) = rows {
+        generator = generator.with_rows(rows);
+    }
+    let data = generator.generate().map_err(|e| map_als_error(e, "Generation"))?;
+
+    let text = match format {
+        Format::Json => to_json(&data),
+        Format::Csv | Format::Auto => to_csv(&data),
+        Format::Als => anyhow::bail!("Cannot generate ALS data. Use 'csv' or 'json' as output format."),
+    };
+    let text = text.map_err(|e| map_als_error(e, "Output conversion"))?;
+    write_output(output, &text)?;
+
+    if !quiet {
+        eprintln!("✓ Generated data from {}", input);
+        eprintln!("  Columns:     {}", data.column_count());
+        eprintln!("  Rows:        {}", data.row_count);
+        eprintln!("  Output:      {}", format_bytes(text.len()));
+    }
+
+    Ok(())
+}
+
+/// Execute the js-decoder command
+fn js_decoder_command(output: &str, types: bool, quiet: bool) -> Result<()> {
+    let source = if types { als_compression::js::DECODER_TYPES } else { als_compression::js::DECODER };
+    write_output(output, source)?;
+
+    if !quiet {
+        let what = if types { "TypeScript declarations" } else { "JavaScript decoder" };
+        eprintln!("✓ Wrote the {} to {}", what, output);
+        eprintln!("  Output:      {}", format_bytes(source.len()));
+    }
+
+    Ok(())
+}
+
+/// Execute the serve command
+#[cfg(unix)]
+fn serve_command(socket: &Path, config: CompressorConfig, quiet: bool) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a daemon that did not shut down cleanly
+    // would make binding fail
+    if fs::symlink_metadata(socket).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(socket).with_context(|| format!("Failed to remove stale socket: {}", socket.display()))?;
+    }
+    let listener =
+        UnixListener::bind(socket).with_context(|| format!("Failed to listen on socket: {}", socket.display()))?;
+    let daemon = Daemon::new().with_compressor(AlsCompressor::with_config(config));
+
+    if !quiet {
+        eprintln!("✓ Listening on {}", socket.display());
+    }
+    info!("Serving requests on {}", socket.display());
+    daemon.serve(&listener).context("Failed to accept a connection")?;
+
+    Ok(())
+}
+
+/// Execute the serve command
+#[cfg(not(unix))]
+fn serve_command(_socket: &Path, _config: CompressorConfig, _quiet: bool) -> Result<()> {
+    anyhow::bail!("Unix sockets are not supported on this platform")
+}
+
+/// Largest request body the HTTP server reads
+#[cfg(feature = "server")]
+const HTTP_MAX_BODY_LEN: usize = 16 << 20;
+
+/// Execute the serve command with --http
+#[cfg(feature = "server")]
+fn serve_http_command(address: &str, config: CompressorConfig, quiet: bool) -> Result<()> {
+    use als_compression::daemon::DEFAULT_MAX_CONNECTIONS;
+    use axum::extract::DefaultBodyLimit;
+    use axum::routing::post;
+
+    let server = HttpServer {
+        daemon: std::sync::Arc::new(Daemon::new().with_compressor(AlsCompressor::with_config(config))),
+        permits: std::sync::Arc::new(tokio::sync::Semaphore::new(DEFAULT_MAX_CONNECTIONS)),
+    };
+    // Every request body is held in memory while it waits for a permit,
+    // so bodies are capped well below the socket protocol's frame limit
+    let app = axum::Router::new()
+        .route("/compress", post(http_compress))
+        .route("/decompress", post(http_decompress))
+        .route("/info", post(http_info))
+        .layer(DefaultBodyLimit::max(HTTP_MAX_BODY_LEN))
+        .with_state(server);
+
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start the async runtime")?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to listen on address: {}", address))?;
+        let local = listener.local_addr().context("Failed to get the listening address")?;
+
+        if !quiet {
+            eprintln!("✓ Listening on http://{}", local);
+        }
+        info!("Serving HTTP requests on {}", local);
+        axum::serve(listener, app).await.context("HTTP server failed")
+    })
+}
+
+#[cfg(not(feature = "server"))]
+fn serve_http_command(_address: &str, _config: CompressorConfig, _quiet: bool) -> Result<()> {
+    anyhow::bail!("this build of als has no HTTP server (enable the 'server' feature)")
+}
+
+/// State shared by the HTTP handlers: the daemon doing the work and the
+/// permits bounding how many requests it works on at once, as the socket
+/// daemon bounds its connections
+#[cfg(feature = "server")]
+#[derive(Clone)]
+struct HttpServer {
+    daemon: std::sync::Arc<Daemon>,
+    permits: std::sync::Arc<tokio::sync::Semaphore>,
+}
+
+/// State shared by the HTTP handlers
+#[cfg(feature = "server")]
+type HttpState = axum::extract::State<HttpServer>;
+
+/// Answer an HTTP request with `body` of the given content type
+#[cfg(feature = "server")]
+fn http_reply(status: axum::http::StatusCode, content_type: &'static str, body: String) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    (status, [(axum::http::header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+/// Answer an HTTP request with an error message
+#[cfg(feature = "server")]
+fn http_error(status: axum::http::StatusCode, message: impl std::fmt::Display) -> axum::response::Response {
+    http_reply(status, "text/plain; charset=utf-8", format!("{}\n", message))
+}
+
+/// Get a header of an HTTP request, if present and readable
+#[cfg(feature = "server")]
+fn http_header(headers: &axum::http::HeaderMap, name: axum::http::header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Compress or decompress off the async threads once a permit is free,
+/// answering with the output as `media_type` or with the error as a bad
+/// request
+#[cfg(feature = "server")]
+async fn http_run<F>(
+    server: HttpServer,
+    media_type: als_compression::http::MediaType,
+    work: F,
+) -> axum::response::Response
+where
+    F: FnOnce(&Daemon) -> als_compression::Result<String> + Send + 'static,
+{
+    use axum::http::StatusCode;
+
+    // Acquiring only fails once the semaphore is closed, which it never is
+    let Ok(_permit) = server.permits.acquire_owned().await else {
+        return http_error(StatusCode::SERVICE_UNAVAILABLE, "Server is not accepting requests");
+    };
+    let daemon = server.daemon;
+    match tokio::task::spawn_blocking(move || work(&daemon)).await {
+        Ok(Ok(output)) => http_reply(StatusCode::OK, media_type.mime_type(), output),
+        Ok(Err(e)) => http_error(StatusCode::BAD_REQUEST, e),
+        Err(e) => http_error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Handle POST /compress: CSV or JSON, by Content-Type, answered with ALS
+#[cfg(feature = "server")]
+async fn http_compress(
+    axum::extract::State(server): HttpState,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    use als_compression::http::MediaType;
+    use axum::http::{header, StatusCode};
+
+    let format = match http_header(&headers, header::CONTENT_TYPE).and_then(MediaType::from_content_type) {
+        Some(MediaType::Csv) => StoredFormat::Csv,
+        Some(MediaType::Json) => StoredFormat::Json,
+        _ => return http_error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Content-Type must be text/csv or application/json"),
+    };
+    let Ok(input) = String::from_utf8(body.into()) else {
+        return http_error(StatusCode::BAD_REQUEST, "Body is not UTF-8");
     };
-    
-    let compress_duration = compress_start.elapsed();
-    progress.finish_and_clear();
 
-    let output_size = compressed.len();
-    let ratio = input_size as f64 / output_size as f64;
-    let throughput = (input_size as f64 / 1_048_576.0) / compress_duration.as_secs_f64();
-    
-    debug!("Compressed {} bytes to {} bytes", input_size, output_size);
-    debug!("Compression ratio: {:.2}x", ratio);
-    debug!("Compression time: {:.3}s", compress_duration.as_secs_f64());
-    debug!("Throughput: {:.2} MB/s", throughput);
+    http_run(server, MediaType::Als, move |daemon| daemon.compress(format, &input)).await
+}
 
-    // Write output
-    let progress = create_progress_bar(quiet, "Writing output");
-    write_output(output, &compressed)?;
-    progress.finish_and_clear();
+/// Handle POST /decompress: ALS answered with CSV or JSON, by Accept
+#[cfg(feature = "server")]
+async fn http_decompress(
+    axum::extract::State(server): HttpState,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    use als_compression::http::{negotiate, MediaType};
+    use axum::http::{header, StatusCode};
 
-    let total_duration = start_time.elapsed();
+    let accept = http_header(&headers, header::ACCEPT).unwrap_or_default();
+    let (media_type, format) = match negotiate(accept, &[MediaType::Csv, MediaType::Json]) {
+        Some(MediaType::Json) => (MediaType::Json, StoredFormat::Json),
+        Some(_) => (MediaType::Csv, StoredFormat::Csv),
+        None => return http_error(StatusCode::NOT_ACCEPTABLE, "Accept must allow text/csv or application/json"),
+    };
+    let Ok(input) = String::from_utf8(body.into()) else {
+        return http_error(StatusCode::BAD_REQUEST, "Body is not UTF-8");
+    };
 
-    // Display summary
-    if !quiet {
-        let savings = ((1.0 - (output_size as f64 / input_size as f64)) * 100.0).max(0.0);
-        eprintln!("✓ Compression complete");
-        eprintln!("  Input:       {}", format_bytes(input_size));
-        eprintln!("  Output:      {}", format_bytes(output_size));
-        eprintln!("  Ratio:       {:.2}x", ratio);
-        eprintln!("  Savings:     {:.1}%", savings);
-        eprintln!("  Time:        {:.3}s", total_duration.as_secs_f64());
-        eprintln!("  Throughput:  {:.2} MB/s", throughput);
-    }
+    http_run(server, media_type, move |daemon| daemon.decompress(format, &input)).await
+}
 
-    info!("Compression completed in {:.3}s", total_duration.as_secs_f64());
+/// Handle POST /info: ALS answered with a JSON summary of the document
+#[cfg(feature = "server")]
+async fn http_info(axum::extract::State(server): HttpState, body: axum::body::Bytes) -> axum::response::Response {
+    use als_compression::http::MediaType;
+    use als_compression::FormatIndicator;
+    use axum::http::StatusCode;
 
-    Ok(())
+    let Ok(input) = String::from_utf8(body.into()) else {
+        return http_error(StatusCode::BAD_REQUEST, "Body is not UTF-8");
+    };
+
+    http_run(server, MediaType::Json, move |daemon| {
+        let doc = daemon.parser().parse(&input)?;
+        let info = serde_json::json!({
+            "format": match doc.format_indicator {
+                FormatIndicator::Als => "als",
+                FormatIndicator::Ctx => "ctx",
+            },
+            "version": doc.version,
+            "features": doc.features().iter().map(|feature| feature.name()).collect::<Vec<_>>(),
+            "columns": doc.schema,
+            "rows": doc.row_count(),
+            "compressed_bytes": input.len(),
+            "uncompressed_bytes": doc.uncompressed_size(StoredFormat::Csv)?,
+        });
+        Ok(info.to_string())
+    })
+    .await
 }
 
-/// Execute the decompress command
-fn decompress_command(
-    input: &str,
+/// Execute the pack command
+fn pack_command(
+    inputs: &[String],
     output: &str,
     format: Format,
-    _verbose: bool,
+    pack: &PackOptions,
+    config: CompressorConfig,
     quiet: bool,
 ) -> Result<()> {
     let start_time = Instant::now();
-    
-    info!("Starting decompression: {} -> {}", input, output);
-    debug!("Output format: {}", format.as_str());
+    info!("Packing {} files into {}", inputs.len(), output);
 
-    // Read ALS input with progress bar
-    let progress = create_progress_bar(quiet, "Reading input");
-    let als_data = read_input(input)?;
-    progress.finish_and_clear();
-    
-    if als_data.is_empty() {
-        warn!("Input is empty");
-        write_output(output, "")?;
-        return Ok(());
+    for (file, _, key) in &pack.foreign_keys {
+        if let Some(name) = [file, &key.parent].into_iter().find(|name| !inputs.contains(name)) {
+            anyhow::bail!("Foreign key file '{}' is not one of the files being packed", name);
+        }
     }
 
-    let input_size = als_data.len();
-    debug!("Read {} bytes from input", input_size);
+    let compressor = AlsCompressor::with_config(config.clone());
+    let mut writer = AlsContainerWriter::new(Vec::new())
+        .map_err(|e| map_als_error(e, "Container"))?
+        .with_dedup(pack.dedup);
+    let mut input_size = 0;
+    let mut sources = Vec::with_capacity(inputs.len());
+    let mut documents = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let progress = create_progress_bar(quiet, &format!("Compressing {}", input));
+        let input_data = read_input(input)?;
+        input_size += input_data.len();
 
-    // Validate that format is CSV or JSON (not ALS or Auto)
-    let output_format = match format {
-        Format::Csv => Format::Csv,
-        Format::Json => Format::Json,
-        Format::Als => {
-            error!("Cannot decompress to ALS format");
-            anyhow::bail!("Cannot decompress to ALS format. Use 'csv' or 'json' as output format.");
-        }
-        Format::Auto => {
-            // Default to CSV for auto-detection
-            info!("Auto-detecting output format: defaulting to CSV");
-            Format::Csv
-        }
-    };
+        let detected_format = match format {
+            Format::Auto => detect_format(input, &input_data),
+            _ => format,
+        };
+        debug!("Packing {} as {}", input, detected_format.as_str());
+        let compressed = match detected_format {
+            Format::Csv => compressor
+                .compress_csv(&input_data)
+                .map_err(|e| map_als_error(e, "CSV compression"))?,
+            Format::Json => compress_json_input(&compressor, &input_data)?,
+            // Already compressed, stored as is
+            Format::Als | Format::Auto => input_data.clone(),
+        };
+        documents.push(compressed);
+        sources.push((input_data, detected_format));
+        progress.finish_and_clear();
+    }
 
-    // Create parser
+    // Children are compressed again against the keys of their parents as
+    // first compressed, so parents may be children too, or their own parent
     let parser = AlsParser::new();
-
-    // Decompress based on output format with progress indication
-    let progress = create_progress_bar(quiet, "Decompressing");
-    let decompress_start = Instant::now();
-    
-    let decompressed = match output_format {
-        Format::Csv => {
-            debug!("Decompressing to CSV");
-            parser
-                .to_csv(&als_data)
-                .map_err(|e| map_als_error(e, "ALS decompression to CSV"))?
+    let mut keyed = Vec::new();
+    for (index, input) in inputs.iter().enumerate() {
+        let keys: Vec<_> = pack.foreign_keys.iter().filter(|(file, _, _)| file == input).collect();
+        if keys.is_empty() {
+            continue;
         }
-        Format::Json => {
-            debug!("Decompressing to JSON");
-            parser
-                .to_json(&als_data)
-                .map_err(|e| map_als_error(e, "ALS decompression to JSON"))?
+        let (input_data, detected_format) = &sources[index];
+        if !matches!(detected_format, Format::Csv | Format::Json) {
+            anyhow::bail!("Foreign keys of '{}' need CSV or JSON input", input);
         }
-        _ => unreachable!("Output format should be CSV or JSON at this point"),
-    };
-    
-    let decompress_duration = decompress_start.elapsed();
-    progress.finish_and_clear();
-
-    let output_size = decompressed.len();
-    let expansion_ratio = output_size as f64 / input_size as f64;
-    let throughput = (output_size as f64 / 1_048_576.0) / decompress_duration.as_secs_f64();
-    
-    debug!("Decompressed {} bytes to {} bytes", input_size, output_size);
-    debug!("Expansion ratio: {:.2}x", expansion_ratio);
-    debug!("Decompression time: {:.3}s", decompress_duration.as_secs_f64());
-    debug!("Throughput: {:.2} MB/s", throughput);
+        let mut child_config = config.clone();
+        for (_, column, key) in keys {
+            let parent = inputs.iter().position(|name| *name == key.parent).unwrap_or(index);
+            let parent = parser.parse(&documents[parent]).map_err(|e| map_als_error(e, &key.parent))?;
+            let position = parent
+                .schema
+                .iter()
+                .position(|name| *name == key.parent_column)
+                .with_context(|| format!("'{}' has no column '{}'", key.parent, key.parent_column))?;
+            if parent.foreign_keys.contains_key(&key.parent_column) {
+                anyhow::bail!("Column '{}' of '{}' is itself a foreign key", key.parent_column, key.parent);
+            }
+            let rows = parser.expand(&parent).map_err(|e| map_als_error(e, &key.parent))?;
+            let parent_keys = rows.into_iter().map(|mut row| row.swap_remove(position)).collect();
+            child_config = child_config.with_foreign_key(column.as_str(), key.clone(), parent_keys);
+        }
+        let child_compressor = AlsCompressor::with_config(child_config);
+        keyed.push((index, compress_text(&child_compressor, input_data, *detected_format)?));
+    }
+    for (index, compressed) in keyed {
+        documents[index] = compressed;
+    }
 
-    // Write output
-    let progress = create_progress_bar(quiet, "Writing output");
-    write_output(output, &decompressed)?;
-    progress.finish_and_clear();
+    // Shared dictionaries must be written before the documents using them
+    let mut shared = Vec::new();
+    if pack.shared_dictionaries {
+        let parsed: Vec<_> = documents.iter().filter_map(|als| parser.parse(als).ok()).collect();
+        shared = SharedDictionary::find(&parsed).map_err(|e| map_als_error(e, "Shared dictionaries"))?;
+        for dictionary in &shared {
+            debug!("Sharing {} values as {}", dictionary.entries.len(), dictionary.name);
+            writer
+                .add_shared_dictionary(dictionary.clone())
+                .map_err(|e| map_als_error(e, "Container"))?;
+        }
+    }
+    for (input, compressed) in inputs.iter().zip(&documents) {
+        writer
+            .add(input, compressed)
+            .map_err(|e| map_als_error(e, "Container"))?;
+    }
 
-    let total_duration = start_time.elapsed();
+    let deduplicated = writer.deduplicated_bytes();
+    let container = writer.finish().map_err(|e| map_als_error(e, "Container"))?;
+    let container = String::from_utf8(container).context("Container is not valid UTF-8")?;
+    write_output(output, &container)?;
 
-    // Display summary
     if !quiet {
-        eprintln!("✓ Decompression complete");
+        eprintln!("✓ Packed {} files", inputs.len());
         eprintln!("  Input:       {}", format_bytes(input_size));
-        eprintln!("  Output:      {}", format_bytes(output_size));
-        eprintln!("  Expansion:   {:.2}x", expansion_ratio);
-        eprintln!("  Time:        {:.3}s", total_duration.as_secs_f64());
-        eprintln!("  Throughput:  {:.2} MB/s", throughput);
+        eprintln!("  Output:      {}", format_bytes(container.len()));
+        if pack.dedup {
+            eprintln!("  Deduped:     {}", format_bytes(deduplicated));
+        }
+        if pack.shared_dictionaries {
+            let names: Vec<&str> = shared.iter().map(|dictionary| dictionary.name.as_str()).collect();
+            eprintln!("  Shared:      {}", if names.is_empty() { "none".to_string() } else { names.join(", ") });
+        }
+        eprintln!("  Time:        {:.3}s", start_time.elapsed().as_secs_f64());
     }
 
-    info!("Decompression completed in {:.3}s", total_duration.as_secs_f64());
-
     Ok(())
 }
 
-/// Execute the info command
-fn info_command(input: &str, verbose: bool, quiet: bool) -> Result<()> {
-    let start_time = Instant::now();
-    
-    info!("Reading ALS document info from {}", input);
+/// Execute the unpack command
+fn unpack_command(input: &str, output: &Path, format: Format, list: bool, quiet: bool) -> Result<()> {
+    info!("Unpacking {} into {}", input, output.display());
 
-    // Read ALS input with progress bar
     let progress = create_progress_bar(quiet, "Reading input");
-    let als_data = read_input(input)?;
+    let data = read_input(input)?;
     progress.finish_and_clear();
-    
-    if als_data.is_empty() {
-        warn!("Input is empty");
+
+    let reader = AlsContainerReader::new(&data).map_err(|e| map_als_error(e, "Container"))?;
+    if list {
+        for entry in reader.entries() {
+            println!("{}\t{}\t{:08x}", entry.name, format_bytes(entry.length), entry.checksum);
+        }
         return Ok(());
     }
 
-    debug!("Read {} bytes from input", als_data.len());
+    let extension = match format {
+        Format::Csv => "csv",
+        Format::Json => "json",
+        Format::Als | Format::Auto => "als",
+    };
+    for entry in reader.entries() {
+        let target = entry_path(output, &entry.name, extension)?;
+        // Foreign keys are resolved when documents are expanded, so ALS
+        // output keeps their row numbers
+        let content = match format {
+            Format::Csv => reader.to_csv(&entry.name),
+            Format::Json => reader.to_json(&entry.name),
+            Format::Als | Format::Auto => reader.get(&entry.name).map(|als| als.map(|als| als.into_owned())),
+        };
+        let content = content
+            .map_err(|e| map_als_error(e, "ALS decompression"))?
+            .expect("entry is in the container");
 
-    // Parse the ALS document
-    let progress = create_progress_bar(quiet, "Parsing ALS");
-    let parser = AlsParser::new();
-    let parse_start = Instant::now();
-    
-    let doc = parser
-        .parse(&als_data)
-        .map_err(|e| map_als_error(e, "ALS parsing"))?;
-    
-    let parse_duration = parse_start.elapsed();
-    progress.finish_and_clear();
-    
-    debug!("Parsed ALS document in {:.3}s", parse_duration.as_secs_f64());
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        debug!("Extracting {} to {}", entry.name, target.display());
+        write_file(&target, content)
+            .with_context(|| format!("Failed to write output file: {}", target.display()))?;
+    }
 
-    // Display document information
     if !quiet {
-        display_document_info(&doc, &als_data, verbose);
+        eprintln!("✓ Unpacked {} documents into {}", reader.len(), output.display());
     }
 
-    let total_duration = start_time.elapsed();
-    debug!("Info command completed in {:.3}s", total_duration.as_secs_f64());
-
     Ok(())
 }
 
+/// Get the path to extract a container entry to, refusing names that would
+/// escape the output directory
+fn entry_path(output: &Path, name: &str, extension: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    let safe = relative
+        .components()
+        .all(|component| matches!(component, std::path::Component::Normal(_)));
+    if !safe || name.is_empty() {
+        anyhow::bail!("Refusing to extract entry with unsafe name: '{}'", name);
+    }
+
+    let mut target = output.join(relative);
+    // `users.csv` unpacks to `users.csv` as CSV and to `users.csv.als` as ALS
+    if target.extension().and_then(|e| e.to_str()) != Some(extension) {
+        let mut file_name = target.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".");
+        file_name.push(extension);
+        target.set_file_name(file_name);
+    }
+    Ok(target)
+}
+
+/// Shorten text to at most `max_chars` characters, marking the cut with `...`
+fn truncate_for_display(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{kept}...")
+}
+
 /// Display information about an ALS document
-fn display_document_info(doc: &als_compression::AlsDocument, als_data: &str, verbose: bool) {
+fn display_document_info(
+    doc: &als_compression::AlsDocument,
+    als_data: &str,
+    uncompressed: usize,
+    profiles: &[ColumnProfile],
+    verbose: bool,
+) {
     use als_compression::FormatIndicator;
 
     println!("=== ALS Document Information ===\n");
@@ -478,17 +3552,43 @@ fn display_document_info(doc: &als_compression::AlsDocument, als_data: &str, ver
         FormatIndicator::Ctx => "CTX (Columnar Text - Fallback)",
     });
     println!("Version: {}", doc.version);
+    let features: Vec<&str> = doc.features().iter().map(|feature| feature.name()).collect();
+    if !features.is_empty() {
+        println!("Features: {}", features.join(", "));
+    }
     println!("Columns: {}", doc.column_count());
     println!("Rows: {}", doc.row_count());
+    if doc.nfc_normalized {
+        println!("Text: normalized to Unicode NFC");
+    }
+    for (column, redaction) in &doc.redactions {
+        println!("Redacted: {} ({})", column, redaction.name());
+    }
+    for (column, quantization) in &doc.quantizations {
+        println!("Quantized: {} ({})", column, quantization);
+    }
+    for (column, truncation) in &doc.truncations {
+        println!("Truncated: {} ({})", column, truncation.name());
+    }
+    for (column, key) in &doc.foreign_keys {
+        println!("Foreign key: {} -> {}:{}", column, key.parent, key.parent_column);
+    }
+    if let Some(metadata) = &doc.file_metadata {
+        let mut source = Vec::new();
+        source.extend(metadata.name.clone());
+        source.extend(metadata.size.map(|size| format!("{} bytes", size)));
+        source.extend(metadata.modified.map(|seconds| format!("modified {}", seconds)));
+        source.extend(metadata.mode.map(|mode| format!("mode {:o}", mode)));
+        println!("Source: {}", source.join(", "));
+    }
     println!("Compressed size: {} bytes", als_data.len());
 
-    // Calculate estimated uncompressed size
-    let estimated_uncompressed = estimate_uncompressed_size(doc);
-    if estimated_uncompressed > 0 {
-        let ratio = estimated_uncompressed as f64 / als_data.len() as f64;
-        println!("Estimated uncompressed size: {} bytes", estimated_uncompressed);
+    // Size of the document as CSV
+    if uncompressed > 0 {
+        let ratio = uncompressed as f64 / als_data.len() as f64;
+        println!("Uncompressed size: {} bytes", uncompressed);
         println!("Compression ratio: {:.2}x", ratio);
-        let savings = ((1.0 - (als_data.len() as f64 / estimated_uncompressed as f64)) * 100.0).max(0.0);
+        let savings = ((1.0 - (als_data.len() as f64 / uncompressed as f64)) * 100.0).max(0.0);
         println!("Space savings: {:.1}%", savings);
     }
 
@@ -507,17 +3607,54 @@ fn display_document_info(doc: &als_compression::AlsDocument, als_data: &str, ver
             println!("  {}: {} entries", dict_name, entries.len());
             if verbose {
                 for (i, entry) in entries.iter().enumerate() {
-                    let display_entry = if entry.len() > 50 {
-                        format!("{}...", &entry[..47])
-                    } else {
-                        entry.clone()
-                    };
-                    println!("    [{}]: {}", i, display_entry);
+                    println!("    [{}]: {}", i, truncate_for_display(entry, 50));
                 }
             }
         }
     }
 
+    // Block layout
+    if let Some(block_size) = doc.block_size {
+        println!("\n--- Blocks ---");
+        println!("  {} blocks of up to {} rows", doc.block_count(), block_size);
+        for (column, filters) in &doc.blooms {
+            let name = doc.schema.get(*column).map(String::as_str).unwrap_or("?");
+            let bytes: usize = filters.iter().map(|f| f.byte_len()).sum();
+            println!("  {}: Bloom filters, {}", name, format_bytes(bytes));
+        }
+    }
+
+    // Column statistics
+    if !doc.zone_maps.is_empty() {
+        println!("\n--- Column Statistics ---");
+        for column in doc.zone_maps.keys() {
+            let name = doc.schema.get(*column).map(String::as_str).unwrap_or("?");
+            if let Some(zone) = doc.column_zone(*column) {
+                let order = match zone.order {
+                    als_compression::als::ZoneOrder::Numeric => "numeric",
+                    als_compression::als::ZoneOrder::Text => "text",
+                };
+                println!(
+                    "  {}: min {}, max {}, {} nulls ({})",
+                    name,
+                    zone.min.as_deref().unwrap_or("-"),
+                    zone.max.as_deref().unwrap_or("-"),
+                    zone.null_count,
+                    order
+                );
+            }
+        }
+    }
+
+    // Value indexes
+    if !doc.indexes.is_empty() {
+        println!("\n--- Indexes ---");
+        for (column, index) in &doc.indexes {
+            let name = doc.schema.get(*column).map(String::as_str).unwrap_or("?");
+            println!("  {}: {} distinct values", name, index.len());
+        }
+    }
+
     // Pattern statistics
     println!("\n--- Compression Patterns ---");
     let pattern_stats = analyze_patterns(doc);
@@ -534,16 +3671,20 @@ fn display_document_info(doc: &als_compression::AlsDocument, als_data: &str, ver
     if pattern_stats.dict_refs > 0 {
         println!("  Dictionary references: {}", pattern_stats.dict_refs);
     }
+    if pattern_stats.column_refs > 0 {
+        println!("  Column references: {} (derived from other columns)", pattern_stats.column_refs);
+    }
     if pattern_stats.raw_values > 0 {
         println!("  Raw values: {} (no compression)", pattern_stats.raw_values);
     }
     
     let total_operators = pattern_stats.ranges + pattern_stats.multipliers + 
                          pattern_stats.toggles + pattern_stats.dict_refs + 
-                         pattern_stats.raw_values;
+                         pattern_stats.column_refs + pattern_stats.raw_values;
     if total_operators > 0 {
         let compressed_ops = pattern_stats.ranges + pattern_stats.multipliers + 
-                            pattern_stats.toggles + pattern_stats.dict_refs;
+                            pattern_stats.toggles + pattern_stats.dict_refs +
+                            pattern_stats.column_refs;
         let compression_effectiveness = (compressed_ops as f64 / total_operators as f64) * 100.0;
         println!("  Compression effectiveness: {:.1}% of operators use compression", compression_effectiveness);
     }
@@ -553,9 +3694,27 @@ fn display_document_info(doc: &als_compression::AlsDocument, als_data: &str, ver
         println!("\n--- Per-Column Details ---");
         for (i, (col_name, stream)) in doc.schema.iter().zip(doc.streams.iter()).enumerate() {
             let col_stats = analyze_column_stream(stream);
-            println!("  Column {}: {}", i + 1, col_name);
+            if stream.is_ctx() {
+                println!("  Column {}: {} (CTX, stored verbatim)", i + 1, col_name);
+            } else {
+                println!("  Column {}: {}", i + 1, col_name);
+            }
             println!("    Operators: {}", stream.operator_count());
             println!("    Expanded values: {}", stream.expanded_count());
+            if let Some(profile) = profiles.get(i) {
+                println!(
+                    "    Distinct values: {} ({:.1}%)",
+                    profile.distinct_count,
+                    profile.distinct_ratio() * 100.0
+                );
+                println!("    Entropy: {:.2} bits/value", profile.entropy);
+                println!(
+                    "    Size: {} expanded, {} encoded ({:.2} bytes/row)",
+                    format_bytes(profile.expanded_bytes),
+                    format_bytes(profile.encoded_bytes),
+                    profile.bytes_per_row()
+                );
+            }
             if col_stats.ranges > 0 {
                 println!("    - Ranges: {}", col_stats.ranges);
             }
@@ -568,6 +3727,9 @@ fn display_document_info(doc: &als_compression::AlsDocument, als_data: &str, ver
             if col_stats.dict_refs > 0 {
                 println!("    - Dictionary refs: {}", col_stats.dict_refs);
             }
+            if col_stats.column_refs > 0 {
+                println!("    - Column refs: {}", col_stats.column_refs);
+            }
             if col_stats.raw_values > 0 {
                 println!("    - Raw values: {}", col_stats.raw_values);
             }
@@ -584,6 +3746,7 @@ struct PatternStats {
     multipliers: usize,
     toggles: usize,
     dict_refs: usize,
+    column_refs: usize,
     raw_values: usize,
 }
 
@@ -616,36 +3779,27 @@ fn count_operator_patterns(op: &als_compression::AlsOperator, stats: &mut Patter
     use als_compression::AlsOperator;
     
     match op {
-        AlsOperator::Range { .. } => stats.ranges += 1,
+        AlsOperator::Range { .. } | AlsOperator::DateRange { .. } | AlsOperator::FloatDelta { .. } => stats.ranges += 1,
         AlsOperator::Multiply { value, .. } => {
             stats.multipliers += 1;
             // Count nested operator
             count_operator_patterns(value, stats);
         }
-        AlsOperator::Toggle { .. } => stats.toggles += 1,
+        AlsOperator::Toggle { .. } | AlsOperator::Cycle { .. } | AlsOperator::BoolRuns { .. } => {
+            stats.toggles += 1
+        }
         AlsOperator::DictRef(_) => stats.dict_refs += 1,
+        AlsOperator::ColumnRef { .. } => stats.column_refs += 1,
+        AlsOperator::Transform { value, .. } => count_operator_patterns(value, stats),
+        AlsOperator::Split { parts, .. } => {
+            for op in parts.iter().flatten() {
+                count_operator_patterns(op, stats);
+            }
+        }
         AlsOperator::Raw(_) => stats.raw_values += 1,
     }
 }
 
-/// Estimate the uncompressed size of the document
-fn estimate_uncompressed_size(doc: &als_compression::AlsDocument) -> usize {
-    let row_count = doc.row_count();
-    if row_count == 0 {
-        return 0;
-    }
-    
-    // Estimate based on expanded values
-    // Assume average value length of 10 characters + 1 for delimiter
-    let estimated_value_size = 11;
-    let total_values = row_count * doc.column_count();
-    
-    // Add schema overhead (column names + delimiters)
-    let schema_size: usize = doc.schema.iter().map(|s| s.len() + 1).sum();
-    
-    schema_size + (total_values * estimated_value_size)
-}
-
 /// Create a progress bar (spinner) for operations
 fn create_progress_bar(quiet: bool, message: &str) -> ProgressBar {
     if quiet {
@@ -688,32 +3842,301 @@ fn format_bytes(bytes: usize) -> String {
     }
 }
 
+/// Map an error from reading the ALS text `input`, reporting syntax errors
+/// by line and column rather than byte offset
+fn map_als_error_in(error: AlsError, context: &str, input: &str) -> anyhow::Error {
+    match (error.location(input), &error) {
+        (Some(location), AlsError::AlsSyntaxError { message, .. }) => anyhow::Error::new(ClassifiedError {
+            class: ErrorClass::of_als(&error),
+            message: format!("{}: ALS syntax error at {}: {}", context, location, message),
+        }),
+        _ => map_als_error(error, context),
+    }
+}
+
+/// Class of a failure, each with its own stable exit code
+///
+/// Scripts and orchestration systems can branch on the exit code, or on the
+/// `class` field with `--error-format json`, instead of matching messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorClass {
+    /// Anything not covered below (exit code 1)
+    Other,
+    /// Invalid command line (exit code 2, as clap uses)
+    Usage,
+    /// Malformed CSV, JSON, log or ALS input (exit code 3)
+    Parse,
+    /// Failure reading or writing files, streams or URLs (exit code 4)
+    Io,
+    /// A security limit such as the range expansion limit was hit (exit code 5)
+    LimitExceeded,
+    /// The input needs a newer ALS version or feature than this build supports (exit code 6)
+    VersionMismatch,
+    /// A checksum or signature did not verify (exit code 7)
+    Integrity,
+    /// The input broke the schema contract given with --schema (exit code 8)
+    SchemaViolation,
+}
+
+impl ErrorClass {
+    /// Classify an error by the first cause in its chain that has a class.
+    fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(classified) = cause.downcast_ref::<ClassifiedError>() {
+                    Some(classified.class)
+                } else if let Some(error) = cause.downcast_ref::<AlsError>() {
+                    Some(Self::of_als(error))
+                } else if cause.is::<io::Error>() {
+                    Some(ErrorClass::Io)
+                } else if cause.is::<serde_json::Error>() {
+                    Some(ErrorClass::Parse)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorClass::Other)
+    }
+
+    fn of_als(error: &AlsError) -> Self {
+        match error {
+            AlsError::CsvParseError { .. }
+            | AlsError::LogParseError { .. }
+            | AlsError::JsonParseError(_)
+            | AlsError::AlsSyntaxError { .. }
+            | AlsError::InvalidDictRef { .. }
+            | AlsError::InvalidColumnRef { .. }
+            | AlsError::ColumnMismatch { .. }
+            | AlsError::UnknownColumn { .. }
+            | AlsError::InvalidDelta { .. }
+            | AlsError::DuplicateEntry { .. }
+            | AlsError::InvalidSchema { .. }
+            | AlsError::InvalidTimestamp { .. }
+            | AlsError::InvalidForeignKey { .. } => ErrorClass::Parse,
+            AlsError::InvalidPredicate { .. } | AlsError::InvalidPattern { .. } | AlsError::InvalidRow { .. }
+            | AlsError::InvalidBlock { .. } | AlsError::InvalidReshape { .. } => ErrorClass::Usage,
+            AlsError::RangeOverflow { .. } => ErrorClass::LimitExceeded,
+            AlsError::VersionMismatch { .. } | AlsError::UnsupportedFeature { .. } => ErrorClass::VersionMismatch,
+            AlsError::ChecksumMismatch { .. } | AlsError::InvalidSignature { .. } => ErrorClass::Integrity,
+            AlsError::SchemaViolation { .. } => ErrorClass::SchemaViolation,
+            AlsError::IoError(_) => ErrorClass::Io,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ErrorClass::Other => "other",
+            ErrorClass::Usage => "usage",
+            ErrorClass::Parse => "parse",
+            ErrorClass::Io => "io",
+            ErrorClass::LimitExceeded => "limit_exceeded",
+            ErrorClass::VersionMismatch => "version_mismatch",
+            ErrorClass::Integrity => "integrity",
+            ErrorClass::SchemaViolation => "schema_violation",
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            ErrorClass::Other => 1,
+            ErrorClass::Usage => 2,
+            ErrorClass::Parse => 3,
+            ErrorClass::Io => 4,
+            ErrorClass::LimitExceeded => 5,
+            ErrorClass::VersionMismatch => 6,
+            ErrorClass::Integrity => 7,
+            ErrorClass::SchemaViolation => 8,
+        }
+    }
+
+    fn exit_code(self) -> std::process::ExitCode {
+        std::process::ExitCode::from(self.code())
+    }
+}
+
+/// Error message that keeps the class of the `AlsError` it was built from
+#[derive(Debug)]
+struct ClassifiedError {
+    class: ErrorClass,
+    message: String,
+}
+
+impl std::fmt::Display for ClassifiedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ClassifiedError {}
+
+/// Print a failure to stderr as a single line of JSON
+fn report_error(class: ErrorClass, message: &str, causes: &[String]) {
+    let report = serde_json::json!({
+        "error": {
+            "class": class.name(),
+            "exit_code": class.code(),
+            "message": message,
+            "causes": causes,
+        }
+    });
+    eprintln!("{}", report);
+}
+
 /// Map AlsError to anyhow::Error with context
 fn map_als_error(error: AlsError, context: &str) -> anyhow::Error {
-    match error {
+    let class = ErrorClass::of_als(&error);
+    let message = match error {
         AlsError::CsvParseError { line, column, message } => {
-            anyhow::anyhow!("{}: CSV parse error at line {}, column {}: {}", context, line, column, message)
+            format!("{}: CSV parse error at line {}, column {}: {}", context, line, column, message)
+        }
+        AlsError::LogParseError { line, message } => {
+            format!("{}: Log parse error at line {}: {}", context, line, message)
         }
         AlsError::JsonParseError(e) => {
-            anyhow::anyhow!("{}: JSON parse error: {}", context, e)
+            format!("{}: JSON parse error: {}", context, e)
         }
         AlsError::AlsSyntaxError { position, message } => {
-            anyhow::anyhow!("{}: ALS syntax error at position {}: {}", context, position, message)
+            format!("{}: ALS syntax error at position {}: {}", context, position, message)
         }
         AlsError::InvalidDictRef { index, size } => {
-            anyhow::anyhow!("{}: Invalid dictionary reference _{} (dictionary has {} entries)", context, index, size)
+            format!("{}: Invalid dictionary reference _{} (dictionary has {} entries)", context, index, size)
+        }
+        AlsError::InvalidColumnRef { index, columns } => {
+            format!("{}: Invalid column reference @{} (document has {} preceding columns)", context, index, columns)
         }
         AlsError::RangeOverflow { start, end, step } => {
-            anyhow::anyhow!("{}: Range overflow: {} to {} with step {} would produce too many values", context, start, end, step)
+            format!("{}: Range overflow: {} to {} with step {} would produce too many values", context, start, end, step)
         }
         AlsError::VersionMismatch { expected, found } => {
-            anyhow::anyhow!("{}: Version mismatch: expected <= {}, found {}", context, expected, found)
+            format!("{}: Version mismatch: expected <= {}, found {}", context, expected, found)
+        }
+        AlsError::UnsupportedFeature { feature } => {
+            format!("{}: Unsupported feature: {}", context, feature)
         }
         AlsError::ColumnMismatch { schema, data } => {
-            anyhow::anyhow!("{}: Column count mismatch: schema has {} columns, data has {} columns", context, schema, data)
+            format!("{}: Column count mismatch: schema has {} columns, data has {} columns", context, schema, data)
+        }
+        AlsError::UnknownColumn { name } => {
+            format!("{}: Unknown column: {}", context, name)
+        }
+        AlsError::ChecksumMismatch { name, expected, found } => {
+            format!("{}: Checksum mismatch in '{}': expected {:08x}, found {:08x}", context, name, expected, found)
+        }
+        AlsError::InvalidDelta { message } => {
+            format!("{}: Invalid delta: {}", context, message)
+        }
+        AlsError::InvalidSignature { message } => {
+            format!("{}: Invalid signature: {}", context, message)
+        }
+        AlsError::DuplicateEntry { name } => {
+            format!("{}: Duplicate container entry: {}", context, name)
+        }
+        AlsError::SchemaViolation { violations } => {
+            let lines: Vec<String> = violations.iter().map(|v| format!("\n  - {}", v)).collect();
+            format!("{}: Input violates the schema contract:{}", context, lines.concat())
+        }
+        AlsError::InvalidSchema { message } => {
+            format!("{}: Invalid schema contract: {}", context, message)
+        }
+        AlsError::InvalidPredicate { message } => {
+            format!("{}: Invalid predicate: {}", context, message)
+        }
+        AlsError::InvalidPattern { pattern, message } => {
+            format!("{}: Invalid regular expression '{}': {}", context, pattern, message)
+        }
+        AlsError::InvalidTimestamp { column, row, value } => {
+            format!("{}: Unreadable time in column '{}' at row {}: {}", context, column, row, value)
+        }
+        AlsError::InvalidRow { row, rows } => {
+            format!("{}: Row {} is out of range (document has {} rows)", context, row, rows)
+        }
+        AlsError::InvalidBlock { block, blocks } => {
+            format!("{}: Block {} is out of range (document has {} blocks)", context, block, blocks)
+        }
+        AlsError::InvalidReshape { message } => {
+            format!("{}: Invalid reshape: {}", context, message)
+        }
+        AlsError::InvalidForeignKey { column, message } => {
+            format!("{}: Invalid foreign key '{}': {}", context, column, message)
         }
         AlsError::IoError(e) => {
-            anyhow::anyhow!("{}: IO error: {}", context, e)
+            format!("{}: IO error: {}", context, e)
         }
+    };
+    anyhow::Error::new(ClassifiedError { class, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_file_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        write_file(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pseudonym_key_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        save_pseudonym_key(&path, PseudonymKey::new()).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        // Merging into a key readable by others restricts it too
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        save_pseudonym_key(&path, PseudonymKey::new()).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_restore_file_metadata_drops_special_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.csv");
+        fs::write(&path, "id\n1\n").unwrap();
+        let metadata = FileMetadata {
+            mode: Some(0o4755),
+            ..FileMetadata::default()
+        };
+
+        restore_file_metadata(path.to_str().unwrap(), &metadata).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o7777, 0o755);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_file_writes_through_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.als");
+        let link = dir.path().join("link.als");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_file(&link, "new").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        // A dangling link is written through too, creating its target
+        fs::remove_file(&target).unwrap();
+        write_file(&link, "created").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "created");
     }
 }
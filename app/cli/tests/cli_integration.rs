@@ -0,0 +1,161 @@
+//! End-to-end tests of the `als` command line.
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use tempfile::TempDir;
+
+/// Parse the JSON error report `als --error-format json` prints on stderr.
+fn error_report(stderr: &[u8]) -> serde_json::Value {
+    let line = String::from_utf8_lossy(stderr);
+    let line = line.lines().last().expect("an error report");
+    serde_json::from_str(line).expect("the error report is JSON")
+}
+
+#[test]
+fn test_syntax_errors_exit_with_parse_class() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("truncated.als");
+    fs::write(&input, "#id\n1>").unwrap();
+
+    for args in [&["decompress"][..], &["info"], &["grep", "1"]] {
+        let output = cargo_bin_cmd!("als")
+            .args(["--error-format", "json"])
+            .args(args)
+            .arg("-i")
+            .arg(&input)
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(3), "als {:?}", args);
+        let report = error_report(&output.stderr);
+        assert_eq!(report["error"]["class"], "parse", "als {:?}", args);
+        assert_eq!(report["error"]["exit_code"], 3, "als {:?}", args);
+        assert!(report["error"]["message"].as_str().unwrap().contains("line 2"), "{}", report);
+    }
+}
+
+/// Write a CSV of `rows` rows whose values fit no pattern, so each is
+/// stored as it is.
+fn write_csv(path: &Path, rows: usize) -> String {
+    let mut csv = String::from("id,host\n");
+    for i in 0..rows {
+        csv.push_str(&format!("{},host-{}\n", i * 7919 % 1000, i * 104_729 % 997));
+    }
+    fs::write(path, &csv).unwrap();
+    csv
+}
+
+#[test]
+fn test_batch_report() {
+    let dir = TempDir::new().unwrap();
+    write_csv(&dir.path().join("a.csv"), 50);
+    write_csv(&dir.path().join("b.csv"), 80);
+    let out = dir.path().join("out");
+    let report = dir.path().join("report.json");
+
+    let output = cargo_bin_cmd!("als")
+        .current_dir(dir.path())
+        .args(["-q", "compress", "-i", "a.csv", "b.csv", "missing.csv", "--output-dir"])
+        .arg(&out)
+        .arg("--report")
+        .arg(&report)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(file_names(&out), ["a.csv.als", "b.csv.als"]);
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!((report["succeeded"].as_u64(), report["failed"].as_u64()), (Some(2), Some(1)));
+
+    let files = report["files"].as_array().unwrap();
+    let inputs: Vec<&str> = files.iter().map(|file| file["input"].as_str().unwrap()).collect();
+    assert_eq!(inputs, ["a.csv", "b.csv", "missing.csv"]);
+    for file in &files[..2] {
+        assert!(file["error"].is_null(), "{}", file);
+        assert!(file["output"].as_str().unwrap().ends_with(".csv.als"));
+        assert!(file["output_bytes"].as_u64().unwrap() > 0);
+        assert!(file["ratio"].as_f64().unwrap() > 0.0);
+        assert!(file["seconds"].is_number());
+    }
+    assert!(files[2]["error"].is_string());
+
+    let input_bytes: u64 = files.iter().map(|file| file["input_bytes"].as_u64().unwrap()).sum();
+    assert_eq!(report["input_bytes"].as_u64(), Some(input_bytes));
+    assert!(report["output_bytes"].is_u64() && report["savings_percent"].is_number() && report["seconds"].is_number());
+}
+
+/// List the names of the files in `dir`.
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_recompress_in_place() {
+    let dir = TempDir::new().unwrap();
+    let csv = write_csv(&dir.path().join("data.csv"), 100);
+    let archive = dir.path().join("data.als");
+    cargo_bin_cmd!("als")
+        .args(["-q", "compress", "-i"])
+        .arg(dir.path().join("data.csv"))
+        .arg("-o")
+        .arg(&archive)
+        .assert()
+        .success();
+    fs::remove_file(dir.path().join("data.csv")).unwrap();
+
+    cargo_bin_cmd!("als")
+        .args(["-q", "recompress", "--force", "--level", "9"])
+        .arg(&archive)
+        .assert()
+        .success();
+    assert_eq!(file_names(dir.path()), ["data.als"]);
+    let output = cargo_bin_cmd!("als").args(["decompress", "-i"]).arg(&archive).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), csv);
+
+    // A failure leaves the input untouched, with no temporary file behind
+    let broken = dir.path().join("broken.als");
+    fs::write(&broken, "#id\n1>").unwrap();
+    let output = cargo_bin_cmd!("als").args(["-q", "recompress", "--force"]).arg(&broken).output().unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(fs::read_to_string(&broken).unwrap(), "#id\n1>");
+    assert_eq!(file_names(dir.path()), ["broken.als", "data.als"]);
+}
+
+#[test]
+fn test_repair_truncated_archive() {
+    let dir = TempDir::new().unwrap();
+    let csv = write_csv(&dir.path().join("data.csv"), 200);
+    let archive = dir.path().join("data.als");
+    cargo_bin_cmd!("als")
+        .args(["-q", "compress", "-i"])
+        .arg(dir.path().join("data.csv"))
+        .arg("-o")
+        .arg(&archive)
+        .assert()
+        .success();
+    let bytes = fs::read(&archive).unwrap();
+    fs::write(&archive, &bytes[..bytes.len() * 3 / 4]).unwrap();
+
+    let repaired = dir.path().join("repaired.als");
+    cargo_bin_cmd!("als")
+        .args(["-q", "repair", "-i"])
+        .arg(&archive)
+        .arg("-o")
+        .arg(&repaired)
+        .assert()
+        .success();
+
+    let output = cargo_bin_cmd!("als").args(["decompress", "-i"]).arg(&repaired).output().unwrap();
+    assert!(output.status.success());
+    let recovered = String::from_utf8(output.stdout).unwrap();
+    let rows = recovered.lines().count() - 1;
+    assert!(rows > 0 && rows < 200, "recovered {} rows", rows);
+    assert!(csv.starts_with(&recovered), "{}", recovered);
+}
@@ -0,0 +1,136 @@
+//! Benchmarks over the synthetic datasets.
+//!
+//! Run with: cargo bench --features datasets
+//!
+//! Filter by group, e.g. `cargo bench --features datasets -- detectors/`.
+
+use std::hint::black_box;
+
+use als_compression::convert::csv::parse_csv;
+use als_compression::datasets::Dataset;
+use als_compression::{
+    AlsCompressor, AlsParser, AlsSerializer, BooleanDetector, CalendarCycleDetector, CombinedDetector, DateRangeDetector,
+    MotifDetector, PatternDetector, RangeDetector, RepeatDetector, SegmentDetector, TabularData, ToggleDetector,
+    Tokenizer, TransformDetector,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const ROWS: usize = 10_000;
+const MIN_PATTERN_LENGTH: usize = 3;
+
+/// CSV text of every dataset.
+fn csv_inputs() -> Vec<(Dataset, String)> {
+    Dataset::ALL.iter().map(|&dataset| (dataset, dataset.csv(ROWS))).collect()
+}
+
+/// String values of every column, as the compressor sees them.
+fn column_values(data: &TabularData) -> Vec<Vec<String>> {
+    data.columns
+        .iter()
+        .map(|column| column.values.iter().map(|value| value.to_string_repr().into_owned()).collect())
+        .collect()
+}
+
+fn bench_tokenizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenizer");
+    for (dataset, csv) in csv_inputs() {
+        let als = AlsCompressor::new().compress_csv(&csv).unwrap();
+        group.throughput(Throughput::Bytes(als.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(dataset.name()), &als, |b, als| {
+            b.iter(|| Tokenizer::new(black_box(als)).tokenize_all().unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_detectors(c: &mut Criterion) {
+    let detectors: [&dyn PatternDetector; 7] = [
+        &RangeDetector::new(MIN_PATTERN_LENGTH),
+        &RepeatDetector::new(MIN_PATTERN_LENGTH),
+        &ToggleDetector::new(MIN_PATTERN_LENGTH),
+        &CombinedDetector::new(MIN_PATTERN_LENGTH),
+        &BooleanDetector::new(MIN_PATTERN_LENGTH),
+        &CalendarCycleDetector::new(MIN_PATTERN_LENGTH),
+        &DateRangeDetector::new(MIN_PATTERN_LENGTH),
+    ];
+    let transform = TransformDetector::new(MIN_PATTERN_LENGTH);
+    let motif = MotifDetector::new(MIN_PATTERN_LENGTH);
+    let segment = SegmentDetector::new(MIN_PATTERN_LENGTH);
+
+    let mut group = c.benchmark_group("detectors");
+    for &dataset in &Dataset::ALL {
+        let columns = column_values(&dataset.generate(ROWS));
+        let columns: Vec<Vec<&str>> = columns.iter().map(|values| values.iter().map(String::as_str).collect()).collect();
+        let bytes: usize = columns.iter().flatten().map(|value| value.len()).sum();
+        group.throughput(Throughput::Bytes(bytes as u64));
+
+        // Every detector runs over every column of the dataset
+        for detector in detectors {
+            group.bench_with_input(BenchmarkId::new(detector.name(), dataset.name()), &columns, |b, columns| {
+                b.iter(|| {
+                    columns.iter().for_each(|values| {
+                        black_box(detector.detect(black_box(values)));
+                    })
+                })
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("TransformDetector", dataset.name()), &columns, |b, columns| {
+            b.iter(|| {
+                columns.iter().for_each(|values| {
+                    black_box(transform.candidates(black_box(values)));
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("MotifDetector", dataset.name()), &columns, |b, columns| {
+            b.iter(|| {
+                columns.iter().for_each(|values| {
+                    black_box(motif.find_motifs(black_box(values)));
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("SegmentDetector", dataset.name()), &columns, |b, columns| {
+            b.iter(|| {
+                columns.iter().for_each(|values| {
+                    black_box(segment.split(black_box(values)));
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_serializer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serializer");
+    for (dataset, csv) in csv_inputs() {
+        let doc = AlsCompressor::new().compress(&parse_csv(&csv).unwrap()).unwrap();
+        let als = AlsSerializer::new().serialize(&doc);
+        group.throughput(Throughput::Bytes(als.len() as u64));
+        group.bench_with_input(BenchmarkId::new("serialize", dataset.name()), &doc, |b, doc| {
+            b.iter(|| AlsSerializer::new().serialize(black_box(doc)))
+        });
+        group.bench_with_input(BenchmarkId::new("parse", dataset.name()), &als, |b, als| {
+            b.iter(|| AlsParser::new().parse(black_box(als)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_end_to_end(c: &mut Criterion) {
+    let mut group = c.benchmark_group("end_to_end");
+    group.sample_size(20);
+    for (dataset, csv) in csv_inputs() {
+        let compressor = AlsCompressor::new();
+        let als = compressor.compress_csv(&csv).unwrap();
+        group.throughput(Throughput::Bytes(csv.len() as u64));
+        group.bench_with_input(BenchmarkId::new("compress", dataset.name()), &csv, |b, csv| {
+            b.iter(|| compressor.compress_csv(black_box(csv)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("decompress", dataset.name()), &als, |b, als| {
+            b.iter(|| AlsParser::new().to_csv(black_box(als)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tokenizer, bench_detectors, bench_serializer, bench_end_to_end);
+criterion_main!(benches);
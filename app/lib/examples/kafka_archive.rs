@@ -0,0 +1,66 @@
+//! Example archiving the JSON messages of a Kafka topic as ALS frames.
+//!
+//! Messages are batched into frames of up to 10,000 messages, cutting a
+//! frame at least once a minute, and each frame is written to its own
+//! `.als` file. With a fifth argument, frames are produced to that topic
+//! instead. Stop with Ctrl-D (end of stdin); the last partial frame is
+//! written before exiting.
+//!
+//! Run with: cargo run --example kafka_archive --features kafka -- \
+//!     <brokers> <group> <topic> <dir> [output-topic]
+
+#[cfg(feature = "kafka")]
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use als_compression::kafka::{consumer, DirectorySink, FrameSink, KafkaArchiver, TopicSink};
+    use als_compression::FrameBatcher;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 5 {
+        eprintln!("Usage: {} <brokers> <group> <topic> <dir> [output-topic]", args[0]);
+        std::process::exit(2);
+    }
+    let (brokers, group, topic, dir) = (&args[1], &args[2], &args[3], &args[4]);
+
+    let sink: Box<dyn FrameSink> = match args.get(5) {
+        Some(output_topic) => {
+            let producer = rdkafka::ClientConfig::new()
+                .set("bootstrap.servers", brokers.as_str())
+                .create()?;
+            Box::new(TopicSink::new(producer, output_topic))
+        }
+        None => Box::new(DirectorySink::new(dir, topic)?),
+    };
+    let batcher = FrameBatcher::new()
+        .with_max_records(10_000)
+        .with_max_age(Duration::from_secs(60));
+    let mut archiver = KafkaArchiver::new(consumer(brokers, group, &[topic.as_str()])?, sink).with_batcher(batcher);
+
+    // Stop when stdin is closed
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopper = Arc::clone(&stop);
+    std::thread::spawn(move || {
+        let _ = std::io::stdin().read_to_end(&mut Vec::new());
+        stopper.store(true, Ordering::Relaxed);
+    });
+
+    println!("Archiving {} from {} (Ctrl-D to stop)", topic, brokers);
+    archiver.run(&stop)?;
+    println!(
+        "Wrote {} frames, skipped {} messages that were not JSON objects",
+        archiver.frames_written(),
+        archiver.skipped_messages()
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "kafka"))]
+fn main() {
+    eprintln!("This example requires the 'kafka' feature to be enabled.");
+    eprintln!("Run with: cargo run --example kafka_archive --features kafka -- <brokers> <group> <topic> <dir>");
+    std::process::exit(1);
+}
@@ -0,0 +1,23 @@
+//! Compress arbitrary CSV and check the rows survive decompression.
+
+#![no_main]
+
+use als_compression::convert::csv::parse_csv;
+use als_compression::{AlsCompressor, AlsParser, AlsSerializer};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let Ok(data) = parse_csv(input) else {
+        return;
+    };
+    let Ok(doc) = AlsCompressor::new().compress(&data) else {
+        return;
+    };
+
+    let expected: Vec<Vec<String>> = (0..data.row_count)
+        .map(|row| data.columns.iter().map(|column| column.values[row].to_string_repr().into_owned()).collect())
+        .collect();
+    let text = AlsSerializer::new().serialize(&doc);
+    let (_, rows) = AlsParser::new().parse_and_expand(&text).expect("compressed document expands");
+    assert_eq!(rows, expected);
+});
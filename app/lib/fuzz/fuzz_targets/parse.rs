@@ -0,0 +1,25 @@
+//! Parse arbitrary text as an ALS document.
+//!
+//! Parsing must fail with an error rather than panic, and whatever parses
+//! must serialize to text that parses again.
+
+#![no_main]
+
+use als_compression::{AlsParser, AlsSerializer};
+use libfuzzer_sys::fuzz_target;
+
+/// Largest document expanded, in values per column.
+const MAX_EXPANDED: usize = 100_000;
+
+fuzz_target!(|input: &str| {
+    let parser = AlsParser::new();
+    let Ok(doc) = parser.parse(input) else {
+        return;
+    };
+    if doc.streams.iter().all(|stream| stream.expanded_count() <= MAX_EXPANDED) {
+        let _ = parser.expand(&doc);
+    }
+
+    let text = AlsSerializer::new().serialize(&doc);
+    parser.parse(&text).expect("serialized document parses");
+});
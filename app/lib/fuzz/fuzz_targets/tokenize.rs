@@ -0,0 +1,10 @@
+//! Tokenize arbitrary text.
+
+#![no_main]
+
+use als_compression::als::Tokenizer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = Tokenizer::new(input).tokenize_all();
+});
@@ -0,0 +1,26 @@
+//! Serialize arbitrary values in every text-carrying operator and check
+//! they parse back unchanged.
+
+#![no_main]
+
+use als_compression::{AlsDocument, AlsOperator, AlsParser, AlsSerializer, ColumnStream, ValueTransform};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|value: &str| {
+    if value.is_empty() {
+        return;
+    }
+    let mut doc = AlsDocument::with_schema(vec!["a"]);
+    doc.add_stream(ColumnStream::from_operators(vec![
+        AlsOperator::multiply(AlsOperator::raw(value), 2),
+        AlsOperator::toggle(value, "z", 3),
+        AlsOperator::bool_runs("z", value, vec![1, 1]),
+        AlsOperator::transform(AlsOperator::raw("v"), ValueTransform::new().with_prefix(value).with_suffix(value)),
+        AlsOperator::split('/', vec![vec![AlsOperator::raw(value)], vec![AlsOperator::raw("q")]]),
+    ]));
+
+    let parser = AlsParser::new();
+    let text = AlsSerializer::new().serialize(&doc);
+    let parsed = parser.parse(&text).expect("serialized values parse");
+    assert_eq!(parser.expand(&parsed).unwrap(), parser.expand(&doc).unwrap());
+});
@@ -0,0 +1,58 @@
+// Type declarations for the ALS decoder written by `als js-decoder --types`.
+
+export as namespace AlsDecoder;
+
+/** Limits guarding against hostile documents. */
+export interface DecodeOptions {
+  /** Most values a single range may expand to (default 10,000,000) */
+  maxRangeExpansion?: number;
+  /** Deepest nesting of groups (default 64) */
+  maxNestingDepth?: number;
+}
+
+/** A value as written, or `null` for a null. */
+export type AlsValue = string | null;
+
+/** A document expanded into rows. */
+export interface DecodedRows {
+  /** Column names, in order */
+  columns: string[];
+  /** Rows, each with a value per column */
+  rows: AlsValue[][];
+}
+
+/** A document expanded into columns. */
+export interface DecodedColumns {
+  /** Column names, in order */
+  columns: string[];
+  /** Values of each column, in column order */
+  values: AlsValue[][];
+}
+
+/** Kind of a decoding error, as named by the crate's conformance vectors. */
+export type AlsErrorKind =
+  | "syntax"
+  | "invalid_dict_ref"
+  | "range_overflow"
+  | "version_mismatch"
+  | "unsupported_feature"
+  | "column_mismatch";
+
+/** Error thrown for text that is not a valid ALS version 1 document. */
+export class AlsDecodeError extends Error {
+  readonly kind: AlsErrorKind;
+  /** Offset into the text, for syntax errors */
+  readonly position?: number;
+}
+
+/** Media type of ALS documents, for `Accept` headers. */
+export const MEDIA_TYPE: "application/vnd.als";
+
+/** Feature flags the decoder accepts, all of which leave the values unchanged. */
+export const SUPPORTED_FEATURES: readonly string[];
+
+/** Expand an ALS document into rows. */
+export function decode(text: string, options?: DecodeOptions): DecodedRows;
+
+/** Expand an ALS document into its columns. */
+export function decodeColumns(text: string, options?: DecodeOptions): DecodedColumns;
@@ -0,0 +1,753 @@
+/*
+ * ALS decoder for JavaScript.
+ *
+ * Expands ALS version 1 text, as written by the als-compression crate, into
+ * arrays of rows or columns, so web pages can read ALS responses without a
+ * WebAssembly build. Operators added after version 1 (date ranges, column
+ * references and the other feature flags) are rejected with an
+ * `unsupported_feature` error: servers answering such clients should write
+ * their documents with `AlsSerializer::serialize_as_version(&doc, 1)`.
+ *
+ * This file is self-contained and works as a CommonJS module or, loaded
+ * with a <script> tag, as the global `AlsDecoder`. It is written by
+ * `als js-decoder` and checked against the crate's conformance vectors.
+ */
+(function (root, factory) {
+  if (typeof module === "object" && module.exports) {
+    module.exports = factory();
+  } else {
+    root.AlsDecoder = factory();
+  }
+})(typeof globalThis !== "undefined" ? globalThis : this, function () {
+  "use strict";
+
+  /** Media type of ALS documents, for `Accept` headers. */
+  const MEDIA_TYPE = "application/vnd.als";
+
+  const MAX_SUPPORTED_VERSION = 2;
+  const NULL_TOKEN = "\\0";
+  const EMPTY_TOKEN = "\\e";
+
+  // Characters ending a raw value, and those a backslash makes literal
+  const VALUE_DELIMITERS = " \t\n\r|>*~:()";
+  const ESCAPED = ">*~|_#$@%&^?<!:(){}\\";
+  const ESCAPE_CODES = { n: "\n", t: "\t", r: "\r", " ": " " };
+
+  const BASE62 = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+  const CYCLES = ["dow", "dayname", "mon", "monthname", "qtr"];
+  const I64_MIN = -(2n ** 63n);
+  const I64_MAX = 2n ** 63n - 1n;
+
+  // Features that only speed up queries or record how values were
+  // prepared, so the values read the same without them
+  const SUPPORTED_FEATURES = [
+    "blocks", "bloom", "zones", "index", "nfc", "redact", "quantize", "truncate", "meta", "ref62", "relative-refs",
+    "front-coding",
+  ];
+  const IGNORED_TRAILERS = ["!blocks ", "!bloom ", "!zone ", "!idx ", "!redact ", "!quantize ", "!truncate ", "!meta "];
+  const DELTA_PREFIX = "!delta ";
+  const NFC_LINE = "!nfc";
+  const SIGNATURE_PREFIX = "!sig ";
+
+  const DEFAULT_OPTIONS = { maxRangeExpansion: 10000000, maxNestingDepth: 64 };
+
+  /** Error thrown for text that is not a valid ALS version 1 document. */
+  class AlsDecodeError extends Error {
+    constructor(kind, message, position) {
+      super(position === undefined ? message : message + " at position " + position);
+      this.name = "AlsDecodeError";
+      this.kind = kind;
+      this.position = position;
+    }
+  }
+
+  function syntaxError(message, position) {
+    return new AlsDecodeError("syntax", message, position);
+  }
+
+  function unsupported(feature) {
+    return new AlsDecodeError("unsupported_feature", "Unsupported feature: " + feature);
+  }
+
+  function isDigit(c) {
+    return typeof c === "string" && c.length === 1 && c >= "0" && c <= "9";
+  }
+
+  function isAlphanumeric(c) {
+    return c !== null && /^[\p{Alphabetic}\p{N}]$/u.test(c);
+  }
+
+  function describe(token) {
+    return token.type === "value" ? "value '" + token.value + "'" : token.type;
+  }
+
+  // Decode the body of a dictionary reference: decimal, or base-62
+  // numbered on from where the shorter references end
+  function decodeDictRef(body) {
+    if (isDigit(body[0])) {
+      return /^[0-9]+$/.test(body) ? safeInteger(body) : null;
+    }
+    let value = BASE62.indexOf(body[0]) - 10;
+    if (value < 0) {
+      return null;
+    }
+    let offset = 10;
+    let block = 52;
+    for (const c of body.slice(1)) {
+      const digit = BASE62.indexOf(c);
+      if (digit < 0) {
+        return null;
+      }
+      offset += block;
+      block *= 62;
+      value = value * 62 + digit;
+    }
+    return Number.isSafeInteger(offset + value) ? offset + value : null;
+  }
+
+  function safeInteger(digits) {
+    const value = Number(digits);
+    return Number.isSafeInteger(value) ? value : null;
+  }
+
+  class Tokenizer {
+    constructor(input) {
+      this.input = input;
+      this.pos = 0;
+      this.lastDictRef = null;
+    }
+
+    peekChar() {
+      return this.pos < this.input.length ? String.fromCodePoint(this.input.codePointAt(this.pos)) : null;
+    }
+
+    nextChar() {
+      const c = this.peekChar();
+      if (c !== null) {
+        this.pos += c.length;
+      }
+      return c;
+    }
+
+    skipWhitespace() {
+      for (let c = this.peekChar(); c === " " || c === "\t" || c === "\r"; c = this.peekChar()) {
+        this.nextChar();
+      }
+    }
+
+    readEscaped(delimiters) {
+      const start = this.pos;
+      let result = "";
+      for (let c = this.peekChar(); c !== null && !delimiters.includes(c); c = this.peekChar()) {
+        this.nextChar();
+        if (c !== "\\") {
+          result += c;
+          continue;
+        }
+        const escaped = this.nextChar();
+        if (escaped === null) {
+          throw syntaxError("Incomplete escape sequence at end of input", start);
+        } else if (ESCAPED.includes(escaped)) {
+          result += escaped;
+        } else if (Object.prototype.hasOwnProperty.call(ESCAPE_CODES, escaped)) {
+          result += ESCAPE_CODES[escaped];
+        } else if (escaped === "0") {
+          return "\0";
+        } else if (escaped === "e") {
+          return "";
+        } else {
+          throw syntaxError("Unknown escape sequence: \\" + escaped, this.pos);
+        }
+      }
+      return result;
+    }
+
+    readIdentifier() {
+      let result = "";
+      for (let c = this.peekChar(); isAlphanumeric(c) || c === "_" || c === "."; c = this.peekChar()) {
+        result += this.nextChar();
+      }
+      return result;
+    }
+
+    next() {
+      // Values reading as empty are skipped along with the character after them
+      for (;;) {
+        const token = this.read();
+        if (token !== null) {
+          return token;
+        }
+        this.nextChar();
+      }
+    }
+
+    peek() {
+      const pos = this.pos;
+      const lastDictRef = this.lastDictRef;
+      try {
+        return this.next();
+      } finally {
+        this.pos = pos;
+        this.lastDictRef = lastDictRef;
+      }
+    }
+
+    read() {
+      this.skipWhitespace();
+      const start = this.pos;
+      const c = this.nextChar();
+      switch (c) {
+        case null:
+          return { type: "eof" };
+        case "!":
+          return this.readVersion(start);
+        case "$":
+          return this.readDictionary();
+        case "#":
+          return { type: "schema", value: this.readIdentifier() + this.readEscaped(" \t\n\r|") };
+        case "_":
+          return this.readDictRef(start);
+        case "@":
+          if (isDigit(this.peekChar())) {
+            throw unsupported("column-ref");
+          }
+          return { type: "value", value: "@" };
+        case "%":
+          if (this.peekChar() === ".") {
+            throw unsupported("float-delta");
+          } else if (this.peekChar() === "{") {
+            throw unsupported("date-range");
+          }
+          return { type: "value", value: "%" + this.readEscaped(VALUE_DELIMITERS) };
+        case "&": {
+          const name = this.readIdentifier();
+          if (CYCLES.includes(name)) {
+            throw unsupported("cycle");
+          }
+          return { type: "value", value: "&" + name + this.readEscaped(VALUE_DELIMITERS) };
+        }
+        case "^":
+          throw unsupported("transform");
+        case "?":
+          throw unsupported("bool-runs");
+        case "<":
+          throw unsupported("split");
+        case ">":
+          return { type: "range" };
+        case "*":
+          return { type: "multiply" };
+        case "~":
+          return { type: "toggle" };
+        case "|":
+          this.lastDictRef = null;
+          return { type: "separator" };
+        case ":":
+          return { type: "step" };
+        case "(":
+          return { type: "open" };
+        case ")":
+          return { type: "close" };
+        case "\n":
+          return { type: "newline" };
+        default: {
+          if (c === "-" || isDigit(c)) {
+            return this.readNumber(c);
+          }
+          this.pos = start;
+          const value = this.readEscaped(VALUE_DELIMITERS);
+          return value === "" ? null : { type: "value", value };
+        }
+      }
+    }
+
+    readVersion(start) {
+      let name = "";
+      while (isAlphanumeric(this.peekChar())) {
+        name += this.nextChar();
+      }
+      if (name === "ctx") {
+        return { type: "version", ctx: true, version: null, flags: [] };
+      } else if (!name.startsWith("v")) {
+        throw syntaxError("Invalid version prefix: !" + name, start);
+      }
+      const version = /^[0-9]+$/.test(name.slice(1)) ? Number(name.slice(1)) : NaN;
+      if (!(version <= 255)) {
+        throw syntaxError("Invalid version number: " + name, start);
+      }
+
+      const flags = [];
+      for (;;) {
+        this.skipWhitespace();
+        if (this.peekChar() !== "+") {
+          break;
+        }
+        this.nextChar();
+        const flagStart = this.pos;
+        let flag = "";
+        while (/^[A-Za-z0-9-]$/.test(this.peekChar() || "")) {
+          flag += this.nextChar();
+        }
+        if (flag === "") {
+          throw syntaxError("Expected a feature name after '+'", flagStart);
+        }
+        flags.push(flag);
+      }
+      return { type: "version", ctx: false, version, flags };
+    }
+
+    readDictionary() {
+      const name = this.readIdentifier();
+      const frontCoded = this.peekChar() === "~";
+      if (frontCoded) {
+        this.nextChar();
+      }
+      if (this.peekChar() !== ":") {
+        throw syntaxError("Expected ':' after dictionary name", this.pos);
+      }
+      this.nextChar();
+
+      const values = [this.readEscaped("|\n\r")];
+      while (this.peekChar() === "|") {
+        this.nextChar();
+        values.push(this.readEscaped("|\n\r"));
+      }
+      return { type: "dictionary", name, values, frontCoded, pos: this.pos };
+    }
+
+    readDictRef(start) {
+      let delta = null;
+      if (this.peekChar() === "=") {
+        this.nextChar();
+        delta = 0;
+      } else if (this.peekChar() === "+") {
+        this.nextChar();
+        delta = 1;
+        if (isDigit(this.peekChar())) {
+          let digits = "";
+          while (isDigit(this.peekChar())) {
+            digits += this.nextChar();
+          }
+          delta = safeInteger(digits);
+          if (delta === null) {
+            throw syntaxError("Invalid unsigned number: " + digits, start);
+          }
+        }
+      }
+      if (delta !== null) {
+        if (this.lastDictRef === null) {
+          throw syntaxError("Relative dictionary reference without a preceding reference", start);
+        }
+        this.lastDictRef += delta;
+        return { type: "dictref", index: this.lastDictRef };
+      }
+
+      let body = "";
+      while (/^[A-Za-z0-9]$/.test(this.peekChar() || "")) {
+        body += this.nextChar();
+      }
+      if (body === "") {
+        return { type: "value", value: "_" };
+      }
+      const index = decodeDictRef(body);
+      if (index === null) {
+        throw syntaxError("Invalid dictionary reference index: " + body, start);
+      }
+      this.lastDictRef = index;
+      return { type: "dictref", index };
+    }
+
+    readNumber(first) {
+      let text = first;
+      let hasDot = false;
+      let hasExp = false;
+      for (;;) {
+        const c = this.peekChar();
+        if (isDigit(c)) {
+          text += this.nextChar();
+        } else if (c === "." && !hasDot && !hasExp) {
+          hasDot = true;
+          text += this.nextChar();
+        } else if ((c === "e" || c === "E") && !hasExp && /[0-9]/.test(text)) {
+          // An exponent needs digits, with an optional sign before them
+          const ahead = this.input.slice(this.pos + 1, this.pos + 3);
+          const signed = ahead[0] === "+" || ahead[0] === "-";
+          if (!isDigit(signed ? ahead[1] : ahead[0])) {
+            break;
+          }
+          hasExp = true;
+          text += this.nextChar();
+          if (signed) {
+            text += this.nextChar();
+          }
+        } else {
+          break;
+        }
+      }
+
+      // Digits followed by anything but a delimiter start a raw value, such as a date
+      const c = this.peekChar();
+      if (c !== null && !VALUE_DELIMITERS.includes(c)) {
+        return { type: "value", value: text + this.readEscaped(VALUE_DELIMITERS) };
+      }
+      // Only integers that print back the same can start a range or count
+      if (!hasDot && !hasExp && /^-?(0|[1-9][0-9]*)$/.test(text) && text !== "-0") {
+        const value = BigInt(text);
+        if (value >= I64_MIN && value <= I64_MAX) {
+          return { type: "int", value, text };
+        }
+      }
+      return { type: "value", value: text };
+    }
+  }
+
+  class Parser {
+    constructor(tokenizer, options) {
+      this.tokenizer = tokenizer;
+      this.options = options;
+    }
+
+    skipNewlines() {
+      while (this.tokenizer.peek().type === "newline") {
+        this.tokenizer.next();
+      }
+    }
+
+    document() {
+      const doc = { dictionaries: new Map(), schema: [], streams: [] };
+      this.skipNewlines();
+      const version = this.tokenizer.peek();
+      if (version.type === "version") {
+        this.tokenizer.next();
+        if (!version.ctx) {
+          if (version.version > MAX_SUPPORTED_VERSION) {
+            throw new AlsDecodeError(
+              "version_mismatch",
+              "Version mismatch: expected <= " + MAX_SUPPORTED_VERSION + ", found " + version.version
+            );
+          }
+          if (version.version < 2 && version.flags.length > 0) {
+            throw syntaxError("Feature flags need version 2, found version " + version.version, this.tokenizer.pos);
+          }
+          const flag = version.flags.find((flag) => !SUPPORTED_FEATURES.includes(flag));
+          if (flag !== undefined) {
+            throw unsupported(flag);
+          }
+        }
+        this.skipNewlines();
+      }
+
+      while (this.tokenizer.peek().type === "dictionary") {
+        const header = this.tokenizer.next();
+        const values = header.frontCoded ? decodeFrontCoded(header.values, header.pos) : header.values;
+        doc.dictionaries.set(header.name, values);
+        this.skipNewlines();
+      }
+      while (this.tokenizer.peek().type === "schema") {
+        doc.schema.push(this.tokenizer.next().value);
+      }
+      this.skipNewlines();
+
+      if (doc.schema.length > 0) {
+        doc.streams = this.streams(doc.schema.length);
+      }
+      return doc;
+    }
+
+    streams(expected) {
+      const streams = [];
+      let current = { ctx: false, operators: [] };
+      for (;;) {
+        const token = this.tokenizer.next();
+        if (token.type === "eof") {
+          // A document with no rows still has a stream per column
+          if (current.operators.length > 0 || streams.length < Math.max(expected, 1)) {
+            streams.push(current);
+          }
+          break;
+        } else if (token.type === "separator") {
+          streams.push(current);
+          current = { ctx: false, operators: [] };
+        } else if (token.type === "newline") {
+          continue;
+        } else if (token.type === "version" && token.ctx && current.operators.length === 0 && !current.ctx) {
+          // Column stored verbatim
+          current.ctx = true;
+        } else {
+          const operator = this.element(token, 0);
+          if (current.ctx && operator.type !== "raw") {
+            throw syntaxError("Only raw values are allowed in a CTX column", this.tokenizer.pos);
+          }
+          current.operators.push(operator);
+        }
+      }
+
+      if (streams.length !== expected) {
+        throw new AlsDecodeError(
+          "column_mismatch",
+          "Column count mismatch: schema has " + expected + " columns, data has " + streams.length + " columns"
+        );
+      }
+      return streams;
+    }
+
+    element(token, depth) {
+      switch (token.type) {
+        case "int": {
+          if (this.tokenizer.peek().type === "range") {
+            this.tokenizer.next();
+            return this.range(token.value);
+          }
+          return this.repeatable({ type: "raw", value: token.text }, token.text);
+        }
+        case "value":
+          return this.repeatable({ type: "raw", value: token.value }, token.value);
+        case "dictref":
+          return this.multiplied({ type: "dictref", index: token.index });
+        case "open":
+          return this.group(depth + 1);
+        default:
+          throw syntaxError("Unexpected token: " + describe(token), this.tokenizer.pos);
+      }
+    }
+
+    // A value, repeated with `*count` or alternating with others with `~`
+    repeatable(operator, value) {
+      if (this.tokenizer.peek().type !== "toggle") {
+        return this.multiplied(operator);
+      }
+      this.tokenizer.next();
+      const values = [value, this.value()];
+      while (this.tokenizer.peek().type === "toggle") {
+        this.tokenizer.next();
+        values.push(this.value());
+      }
+      let count = values.length;
+      if (this.tokenizer.peek().type === "multiply") {
+        this.tokenizer.next();
+        count = this.count();
+      }
+      return { type: "toggle", values, count };
+    }
+
+    multiplied(operator) {
+      if (this.tokenizer.peek().type !== "multiply") {
+        return operator;
+      }
+      this.tokenizer.next();
+      return { type: "multiply", value: operator, count: this.count() };
+    }
+
+    range(start) {
+      const end = this.integer();
+      let step = end >= start ? 1n : -1n;
+      if (this.tokenizer.peek().type === "step") {
+        this.tokenizer.next();
+        step = this.integer();
+      }
+      if (step === 0n || rangeCount(start, end, step) > BigInt(this.options.maxRangeExpansion)) {
+        throw new AlsDecodeError(
+          "range_overflow",
+          "Range overflow: " + start + " to " + end + " with step " + step + " would produce too many values"
+        );
+      }
+      return this.multiplied({ type: "range", start, end, step });
+    }
+
+    group(depth) {
+      if (depth > this.options.maxNestingDepth) {
+        throw syntaxError("Operators nested more than " + this.options.maxNestingDepth + " levels deep", this.tokenizer.pos);
+      }
+      const inner = this.element(this.tokenizer.next(), depth);
+      const close = this.tokenizer.next();
+      if (close.type !== "close") {
+        throw syntaxError("Expected ')' but found " + describe(close), this.tokenizer.pos);
+      }
+      return this.multiplied(inner);
+    }
+
+    integer() {
+      const token = this.tokenizer.next();
+      if (token.type !== "int") {
+        throw syntaxError("Expected integer but found " + describe(token), this.tokenizer.pos);
+      }
+      return token.value;
+    }
+
+    count() {
+      const count = this.integer();
+      if (count < 0n || count > BigInt(Number.MAX_SAFE_INTEGER)) {
+        throw syntaxError("Invalid count: " + count, this.tokenizer.pos);
+      }
+      return Number(count);
+    }
+
+    value() {
+      const token = this.tokenizer.next();
+      if (token.type === "int") {
+        return token.text;
+      } else if (token.type === "value") {
+        return token.value;
+      }
+      throw syntaxError("Expected value but found " + describe(token), this.tokenizer.pos);
+    }
+  }
+
+  // Number of values a range produces; a step pointing away from the end
+  // counts the start alone
+  function rangeCount(start, end, step) {
+    if (end >= start !== step > 0n) {
+      return 1n;
+    }
+    const diff = end >= start ? end - start : start - end;
+    return diff / (step > 0n ? step : -step) + 1n;
+  }
+
+  // Entries after the first are the number of bytes shared with the
+  // previous entry, a `~`, and the rest
+  function decodeFrontCoded(entries, position) {
+    const encoder = new TextEncoder();
+    const decoder = new TextDecoder("utf-8", { fatal: true });
+    const values = entries.slice(0, 1);
+    for (const entry of entries.slice(1)) {
+      const split = entry.indexOf("~");
+      const shared = split >= 0 && /^\+?[0-9]+$/.test(entry.slice(0, split)) ? Number(entry.slice(0, split)) : NaN;
+      const previous = encoder.encode(values[values.length - 1]);
+      let prefix = null;
+      if (shared <= previous.length) {
+        try {
+          prefix = decoder.decode(previous.subarray(0, shared));
+        } catch (e) {
+          prefix = null;
+        }
+      }
+      if (prefix === null) {
+        throw syntaxError("Invalid front-coded dictionary entry: " + entry, position);
+      }
+      values.push(prefix + entry.slice(split + 1));
+    }
+    return values;
+  }
+
+  function expand(operator, dictionary, out) {
+    switch (operator.type) {
+      case "raw":
+        out.push(operator.value);
+        break;
+      case "range":
+        for (
+          let value = operator.start;
+          operator.step > 0n ? value <= operator.end : value >= operator.end;
+          value += operator.step
+        ) {
+          out.push(value.toString());
+        }
+        break;
+      case "multiply": {
+        const values = [];
+        expand(operator.value, dictionary, values);
+        for (let i = 0; i < operator.count; i++) {
+          for (const value of values) {
+            out.push(value);
+          }
+        }
+        break;
+      }
+      case "toggle":
+        for (let i = 0; i < operator.count; i++) {
+          out.push(operator.values[i % operator.values.length]);
+        }
+        break;
+      case "dictref": {
+        const size = dictionary ? dictionary.length : 0;
+        if (operator.index >= size) {
+          throw new AlsDecodeError(
+            "invalid_dict_ref",
+            "Invalid dictionary reference: _" + operator.index + " (dictionary has " + size + " entries)"
+          );
+        }
+        out.push(dictionary[operator.index]);
+        break;
+      }
+    }
+  }
+
+  // Remove a signature line and the lines after the column streams,
+  // failing for those that change the values
+  function splitTrailer(text) {
+    let body = text.endsWith("\n") ? text.slice(0, -1) : text;
+    body = body.endsWith("\r") ? body.slice(0, -1) : body;
+    const last = body.lastIndexOf("\n") + 1;
+    if (body.startsWith(SIGNATURE_PREFIX, last)) {
+      text = text.slice(0, Math.max(last - 1, 0));
+    }
+
+    const prefixes = [DELTA_PREFIX, NFC_LINE].concat(IGNORED_TRAILERS);
+    for (let i = text.indexOf("\n!"); i >= 0; i = text.indexOf("\n!", i + 1)) {
+      if (!prefixes.some((prefix) => text.startsWith(prefix, i + 1))) {
+        continue;
+      }
+      for (let line of text.slice(i + 1).split("\n")) {
+        line = line.endsWith("\r") ? line.slice(0, -1) : line;
+        if (line.startsWith(DELTA_PREFIX)) {
+          throw unsupported("delta");
+        } else if (line !== "" && line !== NFC_LINE && !IGNORED_TRAILERS.some((prefix) => line.startsWith(prefix))) {
+          throw syntaxError("Unexpected line after column streams: " + line, i + 1);
+        }
+      }
+      return text.slice(0, i + 1);
+    }
+    return text;
+  }
+
+  function toValue(value) {
+    if (value === NULL_TOKEN) {
+      return null;
+    }
+    return value === EMPTY_TOKEN ? "" : value;
+  }
+
+  /**
+   * Expand an ALS document into its columns, each an array of values.
+   *
+   * Values are strings as written, with `null` for nulls.
+   */
+  function decodeColumns(text, options) {
+    options = Object.assign({}, DEFAULT_OPTIONS, options);
+    const doc = new Parser(new Tokenizer(splitTrailer(text)), options).document();
+    const dictionary = doc.dictionaries.get("default");
+    const values = doc.streams.map((stream) => {
+      const column = [];
+      for (const operator of stream.operators) {
+        expand(operator, dictionary, column);
+      }
+      return column.map(toValue);
+    });
+
+    const rowCount = values.length > 0 ? values[0].length : 0;
+    const ragged = values.find((column) => column.length !== rowCount);
+    if (ragged !== undefined) {
+      throw new AlsDecodeError(
+        "column_mismatch",
+        "Column count mismatch: schema has " + rowCount + " columns, data has " + ragged.length + " columns"
+      );
+    }
+    return { columns: doc.schema, values };
+  }
+
+  /**
+   * Expand an ALS document into rows, each an array with a value per column.
+   *
+   * Values are strings as written, with `null` for nulls.
+   */
+  function decode(text, options) {
+    const { columns, values } = decodeColumns(text, options);
+    const rowCount = values.length > 0 ? values[0].length : 0;
+    const rows = [];
+    for (let row = 0; row < rowCount; row++) {
+      rows.push(values.map((column) => column[row]));
+    }
+    return { columns, rows };
+  }
+
+  return { decode, decodeColumns, AlsDecodeError, MEDIA_TYPE, SUPPORTED_FEATURES };
+});
@@ -0,0 +1,536 @@
+//! Aggregations over ALS documents.
+//!
+//! Archived metrics are mostly counted, summed and bounded, and their
+//! operators already hold most of the answer: a range sums in closed form,
+//! a repeat scales the aggregate of what it repeats, and a toggle, calendar
+//! cycle or boolean run only needs how often each label occurs.
+//! [`AlsQuery::aggregate`] folds the operators of a column without
+//! expanding them, and [`AlsQuery::aggregate_by`] does the same for each
+//! value of a grouping column, cutting the aggregated column at the runs of
+//! the grouping column rather than visiting it row by row.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::als::parser::typed_value;
+use crate::als::slice::slice_operators;
+use crate::als::{AlsOperator, ColumnStream, ZoneOrder, NULL_TOKEN};
+use crate::convert::Value;
+use crate::error::Result;
+use crate::query::{value_order, AlsQuery};
+
+/// An aggregate function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    /// Number of values that are not null
+    Count,
+    /// Sum of the values that are numbers
+    Sum,
+    /// Smallest value
+    Min,
+    /// Largest value
+    Max,
+}
+
+impl Aggregate {
+    /// All aggregate functions.
+    pub const ALL: [Aggregate; 4] = [Aggregate::Count, Aggregate::Sum, Aggregate::Min, Aggregate::Max];
+
+    /// Get the name of the function, as written on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+
+    /// Look up a function by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|aggregate| aggregate.name() == name)
+    }
+}
+
+impl AlsQuery {
+    /// Aggregate the values of `column`.
+    ///
+    /// Values are read in their ALS string form and nulls are skipped.
+    /// `Sum` also skips values that are not numbers, and is an integer
+    /// unless a value has a fraction or the sum overflows an `i64`. `Min`
+    /// and `Max` compare as [`find_range`](Self::find_range) does, and come
+    /// from the zone maps when the document has them. Aggregates of no
+    /// values are null, except for `Count`, which is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{Aggregate, AlsQuery, Value};
+    ///
+    /// let query = AlsQuery::parse("#ts #bytes\n1>1000000|512*999999 2048").unwrap();
+    /// assert_eq!(query.aggregate("ts", Aggregate::Sum).unwrap(), Value::Integer(500000500000));
+    /// assert_eq!(query.aggregate("bytes", Aggregate::Max).unwrap(), Value::Integer(2048));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the document has no such column,
+    /// or a parse error if an operator that has to be expanded cannot be.
+    pub fn aggregate(&self, column: &str, aggregate: Aggregate) -> Result<Value<'static>> {
+        let position = self.position(column)?;
+        if let Some(zone) = self.document().column_zone(position) {
+            let bound = match aggregate {
+                Aggregate::Min => Some(zone.min),
+                Aggregate::Max => Some(zone.max),
+                Aggregate::Count | Aggregate::Sum => None,
+            };
+            if let Some(bound) = bound {
+                return Ok(bound.map_or(Value::Null, |value| typed_value(value, false)));
+            }
+        }
+
+        let dictionary = self.document().default_dictionary().map(|d| d.as_slice());
+        let stream = self.aggregated_stream(position)?;
+        let mut accumulator = Accumulator::new(column_order(&stream.operators, dictionary)?);
+        accumulator.add_operators(&stream.operators, dictionary)?;
+        Ok(accumulator.finish(aggregate))
+    }
+
+    /// Aggregate the values of `column` for each value of `group`.
+    ///
+    /// Returns each value of `group`, nulls included, with the aggregate of
+    /// `column` over its rows, as in [`aggregate`](Self::aggregate). Groups
+    /// are ordered by their value, numerically if every group is a number,
+    /// with the null group last. Grouping by a column of dictionary
+    /// references or repeats aggregates whole runs of rows at once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{Aggregate, AlsQuery, Value};
+    ///
+    /// let query = AlsQuery::parse("$default:api|web\n#host #ms\n_1*3 _0*2|40 60 50 5>6").unwrap();
+    /// let sums = query.aggregate_by("host", "ms", Aggregate::Sum).unwrap();
+    /// assert_eq!(sums, vec![
+    ///     (Value::String("api".into()), Value::Integer(11)),
+    ///     (Value::String("web".into()), Value::Integer(150)),
+    /// ]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the document lacks either
+    /// column, or a parse error if an operator that has to be expanded
+    /// cannot be.
+    pub fn aggregate_by(
+        &self,
+        group: &str,
+        column: &str,
+        aggregate: Aggregate,
+    ) -> Result<Vec<(Value<'static>, Value<'static>)>> {
+        let groups = self.aggregated_stream(self.position(group)?)?;
+        let stream = self.aggregated_stream(self.position(column)?)?;
+        let dictionary = self.document().default_dictionary().map(|d| d.as_slice());
+        let order = column_order(&stream.operators, dictionary)?;
+
+        let mut accumulators: HashMap<String, Accumulator> = HashMap::new();
+        let mut cursor = Cursor {
+            operators: &stream.operators,
+            index: 0,
+            start: 0,
+        };
+        for (key, rows) in group_runs(&groups.operators, dictionary)? {
+            let operators = cursor.slice(rows);
+            accumulators
+                .entry(key)
+                .or_insert_with(|| Accumulator::new(order))
+                .add_operators(&operators, dictionary)?;
+        }
+
+        let mut results: Vec<(String, Accumulator)> = accumulators.into_iter().collect();
+        let keys: Vec<&str> = results.iter().map(|(key, _)| key.as_str()).filter(|key| *key != NULL_TOKEN).collect();
+        let key_order = value_order(&keys);
+        results.sort_by(|(a, _), (b, _)| {
+            (a == NULL_TOKEN)
+                .cmp(&(b == NULL_TOKEN))
+                .then_with(|| key_order.compare(a, b).unwrap_or(Ordering::Equal))
+        });
+        Ok(results
+            .into_iter()
+            .map(|(key, accumulator)| (typed_value(key, false), accumulator.finish(aggregate)))
+            .collect())
+    }
+
+    /// Get the stream of the column at `position` to aggregate.
+    ///
+    /// Columns with references to other columns are expanded into raw
+    /// values, which aggregate row by row.
+    fn aggregated_stream(&self, position: usize) -> Result<Cow<'_, ColumnStream<'static>>> {
+        let stream = &self.document().streams[position];
+        if !stream.has_column_refs() {
+            return Ok(Cow::Borrowed(stream));
+        }
+        let values = self.expand_column(position)?;
+        Ok(Cow::Owned(values.into_iter().map(AlsOperator::raw).collect()))
+    }
+}
+
+/// Values an operator produces, without expanding repeats or ranges.
+enum Part<'v> {
+    /// A value occurring some number of times
+    Value(&'v str, usize),
+    /// The `len` values of a range from `start` by `step`, each occurring
+    /// `times` times
+    Range {
+        start: i64,
+        step: i64,
+        len: usize,
+        times: usize,
+    },
+}
+
+/// Pass the parts of an operator repeated `times` times to `f`.
+fn visit(op: &AlsOperator, dictionary: Option<&[String]>, times: usize, f: &mut dyn FnMut(Part<'_>)) -> Result<()> {
+    match op {
+        AlsOperator::Raw(value) => f(Part::Value(value, times)),
+        AlsOperator::DictRef(index) => match dictionary.and_then(|d| d.get(*index)) {
+            Some(value) => f(Part::Value(value, times)),
+            // Expanding reports the bad reference
+            None => {
+                op.expand(dictionary)?;
+            }
+        },
+        AlsOperator::Range { start, step, .. } => {
+            let len = op.expanded_count();
+            if len > 0 {
+                f(Part::Range {
+                    start: *start,
+                    step: *step,
+                    len,
+                    times,
+                });
+            }
+        }
+        AlsOperator::Multiply { value, count } => visit(value, dictionary, times.saturating_mul(*count), f)?,
+        AlsOperator::Toggle { values, count } if !values.is_empty() => {
+            for (i, value) in values.iter().enumerate() {
+                let occurrences = count / values.len() + usize::from(i < count % values.len());
+                if occurrences > 0 {
+                    f(Part::Value(value, occurrences.saturating_mul(times)));
+                }
+            }
+        }
+        AlsOperator::Cycle { cycle, offset, run, count } if *run > 0 => {
+            let labels = cycle.values();
+            // The rest of the run the offset falls in, then whole runs from
+            // label `start` on
+            let lead = ((run - offset % run) % run).min(*count);
+            let start = (offset / run % labels.len() + usize::from(lead > 0)) % labels.len();
+            let (runs, rest) = ((count - lead) / run, (count - lead) % run);
+            for (i, label) in labels.iter().enumerate() {
+                // Runs r with (start + r) % labels == i, then the partial runs
+                let first = (i + labels.len() - start) % labels.len();
+                let mut occurrences = if first < runs {
+                    ((runs - 1 - first) / labels.len() + 1) * run
+                } else {
+                    0
+                };
+                if rest > 0 && (start + runs % labels.len()) % labels.len() == i {
+                    occurrences += rest;
+                }
+                if lead > 0 && (offset / run) % labels.len() == i {
+                    occurrences += lead;
+                }
+                if occurrences > 0 {
+                    f(Part::Value(label, occurrences.saturating_mul(times)));
+                }
+            }
+        }
+        AlsOperator::BoolRuns { labels, runs } => {
+            let mut occurrences = [0usize; 2];
+            for (i, run) in runs.iter().enumerate() {
+                occurrences[i % 2] += run;
+            }
+            for (label, occurrences) in labels.iter().zip(occurrences) {
+                if occurrences > 0 {
+                    f(Part::Value(label, occurrences.saturating_mul(times)));
+                }
+            }
+        }
+        _ => {
+            for value in op.expand(dictionary)? {
+                f(Part::Value(&value, times));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Order the values of some operators compare in: numeric if every
+/// non-null value is a number.
+fn column_order(operators: &[AlsOperator], dictionary: Option<&[String]>) -> Result<ZoneOrder> {
+    let mut numeric = true;
+    for op in operators {
+        visit(op, dictionary, 1, &mut |part| {
+            if let Part::Value(value, _) = part {
+                numeric &= value == NULL_TOKEN || value.parse::<f64>().is_ok();
+            }
+        })?;
+    }
+    Ok(if numeric { ZoneOrder::Numeric } else { ZoneOrder::Text })
+}
+
+/// Running count, sum and bounds of some values.
+struct Accumulator {
+    order: ZoneOrder,
+    count: usize,
+    /// Exact sum, while every number is an integer and the sum fits
+    sum: Option<i128>,
+    float_sum: f64,
+    summed: bool,
+    min: Option<String>,
+    max: Option<String>,
+}
+
+impl Accumulator {
+    fn new(order: ZoneOrder) -> Self {
+        Self {
+            order,
+            count: 0,
+            sum: Some(0),
+            float_sum: 0.0,
+            summed: false,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn add_operators(&mut self, operators: &[AlsOperator], dictionary: Option<&[String]>) -> Result<()> {
+        for op in operators {
+            visit(op, dictionary, 1, &mut |part| self.add(part))?;
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, part: Part) {
+        match part {
+            Part::Value(value, times) => {
+                if value == NULL_TOKEN {
+                    return;
+                }
+                self.count = self.count.saturating_add(times);
+                if let Ok(number) = value.parse::<i64>() {
+                    let exact = i128::from(number).checked_mul(times as i128);
+                    self.add_sum(exact, number as f64 * times as f64);
+                } else if let Ok(number) = value.parse::<f64>() {
+                    self.add_sum(None, number * times as f64);
+                }
+                self.bound(value);
+            }
+            Part::Range { start, step, len, times } => {
+                self.count = self.count.saturating_add(len.saturating_mul(times));
+                // len * start + step * len * (len - 1) / 2, repeated
+                let exact = {
+                    let (n, first, step) = (len as i128, i128::from(start), i128::from(step));
+                    (n * (n - 1) / 2)
+                        .checked_mul(step)
+                        .and_then(|steps| steps.checked_add(n.checked_mul(first)?))
+                        .and_then(|sum| sum.checked_mul(times as i128))
+                };
+                let last = AlsOperator::range_value_at(start, step, len - 1);
+                self.add_sum(exact, (start as f64 + last as f64) / 2.0 * len as f64 * times as f64);
+                match self.order {
+                    ZoneOrder::Numeric => {
+                        self.bound(&start.to_string());
+                        self.bound(&last.to_string());
+                    }
+                    ZoneOrder::Text => {
+                        for row in 0..len {
+                            self.bound(&AlsOperator::range_value_at(start, step, row).to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_sum(&mut self, exact: Option<i128>, float: f64) {
+        self.sum = self.sum.zip(exact).and_then(|(sum, exact)| sum.checked_add(exact));
+        self.float_sum += float;
+        self.summed = true;
+    }
+
+    fn bound(&mut self, value: &str) {
+        let order = self.order;
+        if self.min.as_deref().is_none_or(|min| order.compare(value, min) == Some(Ordering::Less)) {
+            self.min = Some(value.to_string());
+        }
+        if self.max.as_deref().is_none_or(|max| order.compare(value, max) == Some(Ordering::Greater)) {
+            self.max = Some(value.to_string());
+        }
+    }
+
+    fn finish(self, aggregate: Aggregate) -> Value<'static> {
+        match aggregate {
+            Aggregate::Count => Value::Integer(i64::try_from(self.count).unwrap_or(i64::MAX)),
+            Aggregate::Sum if !self.summed => Value::Null,
+            Aggregate::Sum => match self.sum.and_then(|sum| i64::try_from(sum).ok()) {
+                Some(sum) => Value::Integer(sum),
+                None => Value::Float(self.float_sum),
+            },
+            Aggregate::Min => self.min.map_or(Value::Null, |value| typed_value(value, false)),
+            Aggregate::Max => self.max.map_or(Value::Null, |value| typed_value(value, false)),
+        }
+    }
+}
+
+/// Split the rows of a grouping column into runs of one value, in order.
+fn group_runs(operators: &[AlsOperator], dictionary: Option<&[String]>) -> Result<Vec<(String, Range<usize>)>> {
+    let mut runs: Vec<(String, Range<usize>)> = Vec::new();
+    let mut position = 0;
+    let mut push = |value: &str, len: usize| {
+        if len == 0 {
+            return;
+        }
+        let rows = position..position + len;
+        position += len;
+        match runs.last_mut() {
+            Some((last, last_rows)) if last == value => last_rows.end = rows.end,
+            _ => runs.push((value.to_string(), rows)),
+        }
+    };
+
+    for op in operators {
+        match op {
+            AlsOperator::Multiply { value, count } if value.expanded_count() == 1 => {
+                push(&value.expand(dictionary)?[0], *count);
+            }
+            AlsOperator::Cycle { cycle, offset, run, count } if *run > 0 => {
+                let labels = cycle.values();
+                let mut row = 0;
+                while row < *count {
+                    let within = (offset % run + row % run) % run;
+                    let len = (*run - within).min(count - row);
+                    push(labels[cycle.label_position(*offset, *run, row)], len);
+                    row += len;
+                }
+            }
+            AlsOperator::BoolRuns { labels, runs: lengths } => {
+                for (i, &len) in lengths.iter().enumerate() {
+                    push(&labels[i % 2], len);
+                }
+            }
+            _ => {
+                for value in op.expand(dictionary)? {
+                    push(&value, 1);
+                }
+            }
+        }
+    }
+    Ok(runs)
+}
+
+/// Walks the operators of a stream, cutting out consecutive row ranges.
+struct Cursor<'s, 'a> {
+    operators: &'s [AlsOperator<'a>],
+    /// Operator holding the next rows
+    index: usize,
+    /// First row of that operator
+    start: usize,
+}
+
+impl<'a> Cursor<'_, 'a> {
+    /// Get the operators producing `rows`, which must come after the rows
+    /// of the previous call.
+    fn slice(&mut self, rows: Range<usize>) -> Vec<AlsOperator<'a>> {
+        while let Some(op) = self.operators.get(self.index) {
+            let len = op.expanded_count();
+            if self.start + len > rows.start {
+                break;
+            }
+            self.start += len;
+            self.index += 1;
+        }
+        if rows.is_empty() || self.index >= self.operators.len() {
+            return Vec::new();
+        }
+        slice_operators(&self.operators[self.index..], rows.start - self.start, rows.end - self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsParser;
+
+    /// Check every aggregate of every column, alone and grouped by every
+    /// column, against aggregating the expanded values.
+    fn assert_aggregates(als: &str) {
+        let query = AlsQuery::parse(als).unwrap();
+        let doc = query.document();
+        let columns = AlsParser::new().expand_columns(doc).unwrap();
+        let expected = |column: usize, rows: &[usize], aggregate: Aggregate| {
+            let operators: Vec<AlsOperator> = rows.iter().map(|&row| AlsOperator::raw(columns[column][row].as_str())).collect();
+            let mut accumulator = Accumulator::new(value_order(&columns[column]));
+            accumulator.add_operators(&operators, None).unwrap();
+            accumulator.finish(aggregate)
+        };
+
+        let all: Vec<usize> = (0..doc.row_count()).collect();
+        for (column, name) in doc.schema.iter().enumerate() {
+            for aggregate in Aggregate::ALL {
+                assert_eq!(query.aggregate(name, aggregate).unwrap(), expected(column, &all, aggregate), "{aggregate:?} of {name}");
+                for (group, group_name) in doc.schema.iter().enumerate() {
+                    let mut rows: HashMap<&str, Vec<usize>> = HashMap::new();
+                    for (row, value) in columns[group].iter().enumerate() {
+                        rows.entry(value.as_str()).or_default().push(row);
+                    }
+                    let grouped = query.aggregate_by(group_name, name, aggregate).unwrap();
+                    assert_eq!(grouped.len(), rows.len(), "{aggregate:?} of {name} by {group_name}");
+                    for (key, value) in grouped {
+                        let key = rows.keys().find(|k| typed_value(k.to_string(), false) == key).unwrap();
+                        assert_eq!(value, expected(column, &rows[key], aggregate), "{aggregate:?} of {name} by {group_name} = {key}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_aggregate_operators() {
+        assert_aggregates("$default:a|b\n#id #n #tag #flag #day\n1>12|10>0:-2 (7>8)*3|_0*4 _1*4 x~ya*4|?T~F:5.3.4|&dow+5:3*12");
+        assert_aggregates("#k #v #w\n1 1 2 2 \\\\0 3|1.5 2 \\\\0 -4 x*2|-3>2");
+    }
+
+    #[test]
+    fn test_aggregate_derived_columns() {
+        assert_aggregates("#side #sign #ts\nbuy sell*2 buy*3|@0(buy:+1 sell:-1)*6|%{%Y-%m-%d}1704067200+86400*6");
+    }
+
+    #[test]
+    fn test_aggregate_sums() {
+        let query = AlsQuery::parse("#n #x #big\n1>4|0.5*4|9223372036854775807*4").unwrap();
+        assert_eq!(query.aggregate("n", Aggregate::Sum).unwrap(), Value::Integer(10));
+        assert_eq!(query.aggregate("x", Aggregate::Sum).unwrap(), Value::Float(2.0));
+        assert_eq!(query.aggregate("big", Aggregate::Sum).unwrap(), Value::Float(9223372036854775807.0 * 4.0));
+
+        let empty = AlsQuery::parse("#n\n\\\\0*3").unwrap();
+        assert_eq!(empty.aggregate("n", Aggregate::Sum).unwrap(), Value::Null);
+        assert_eq!(empty.aggregate("n", Aggregate::Count).unwrap(), Value::Integer(0));
+        assert!(empty.aggregate("missing", Aggregate::Count).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_from_zone_maps() {
+        let config = crate::CompressorConfig::new().with_zone_maps(true).with_block_size(2);
+        let doc = crate::AlsCompressor::with_config(config)
+            .compress(&crate::convert::csv::parse_csv("n,s\n5,b\n50,\n500,a").unwrap())
+            .unwrap();
+        let query = AlsQuery::new(doc);
+        assert_eq!(query.aggregate("n", Aggregate::Min).unwrap(), Value::Integer(5));
+        assert_eq!(query.aggregate("n", Aggregate::Max).unwrap(), Value::Integer(500));
+        assert_eq!(query.aggregate("n", Aggregate::Count).unwrap(), Value::Integer(3));
+    }
+}
@@ -0,0 +1,133 @@
+//! Bit-level packing of integers.
+//!
+//! Plain integer columns, such as byte counts or user IDs, defeat the range
+//! and repeat detectors and are left as raw values, spelled out digit by
+//! digit. Packed frame of reference stores a block of them as offsets from
+//! the block's minimum, each in just enough bits for the largest offset, as
+//! Parquet and ORC do: a block of 128 latencies between 1000 and 1900 takes
+//! ten bits apiece rather than four digits.
+
+/// Most integers packed into one block.
+pub const PACKED_BLOCK_SIZE: usize = 128;
+
+/// Pack integers as offsets from their minimum.
+///
+/// Returns the minimum, the bits per offset and the packed offsets. The
+/// bits per offset is 0 when the values are all equal.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::als::{bit_pack, bit_unpack};
+///
+/// let latencies = [1042, 1310, 1877, 1005];
+/// let (reference, width, bytes) = bit_pack(&latencies);
+/// assert_eq!((reference, width, bytes.len()), (1005, 10, 5));
+/// assert_eq!(bit_unpack(reference, width, &bytes, latencies.len()).unwrap(), latencies);
+/// ```
+pub fn bit_pack(values: &[i64]) -> (i64, u32, Vec<u8>) {
+    let Some(&reference) = values.iter().min() else {
+        return (0, 0, Vec::new());
+    };
+    let offsets: Vec<u64> = values.iter().map(|value| value.wrapping_sub(reference) as u64).collect();
+    let width = 64 - offsets.iter().max().map_or(0, |max| max.leading_zeros());
+    let mut writer = BitWriter::default();
+    for offset in offsets {
+        writer.write(offset, width);
+    }
+    (reference, width, writer.bytes)
+}
+
+/// Unpack `count` integers written by [`bit_pack`].
+///
+/// Returns `None` if the width is over 64 bits or the bytes end before
+/// `count` offsets are read.
+pub fn bit_unpack(reference: i64, width: u32, bytes: &[u8], count: usize) -> Option<Vec<i64>> {
+    if width > 64 || (count as u128) * u128::from(width) > (bytes.len() as u128) * 8 {
+        return None;
+    }
+    let mut reader = BitReader::new(bytes);
+    (0..count)
+        .map(|_| reader.read(width).map(|offset| reference.wrapping_add(offset as i64)))
+        .collect()
+}
+
+/// Writes bits most significant first.
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    pub(crate) bytes: Vec<u8>,
+    /// Bits used in the last byte, 0 when a new byte is needed
+    used: u32,
+}
+
+impl BitWriter {
+    /// Write the low `count` bits of `value`.
+    pub(crate) fn write(&mut self, value: u64, count: u32) {
+        for shift in (0..count).rev() {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> shift) & 1) as u8;
+            *self.bytes.last_mut().expect("a byte was pushed") |= bit << (7 - self.used);
+            self.used = (self.used + 1) % 8;
+        }
+    }
+}
+
+/// Reads bits most significant first.
+pub(crate) struct BitReader<'b> {
+    bytes: &'b [u8],
+    /// Bits read so far
+    position: usize,
+}
+
+impl<'b> BitReader<'b> {
+    /// Start reading at the first bit of `bytes`.
+    pub(crate) fn new(bytes: &'b [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// Read `count` bits, at most 64, or `None` past the end.
+    pub(crate) fn read(&mut self, count: u32) -> Option<u64> {
+        let mut value = 0u64;
+        for _ in 0..count {
+            let byte = self.bytes.get(self.position / 8)?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | u64::from(bit);
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let values: Vec<i64> = (0..PACKED_BLOCK_SIZE as i64).map(|i| 5000 + (i * 7919) % 1024).collect();
+        let (reference, width, bytes) = bit_pack(&values);
+        assert_eq!((reference, width), (5000, 10));
+        assert_eq!(bytes.len(), PACKED_BLOCK_SIZE * 10 / 8);
+        assert_eq!(bit_unpack(reference, width, &bytes, values.len()).unwrap(), values);
+    }
+
+    #[test]
+    fn test_pack_extremes() {
+        for values in [vec![i64::MIN, i64::MAX, 0, -1], vec![-7; 5], vec![42], Vec::new()] {
+            let (reference, width, bytes) = bit_pack(&values);
+            assert_eq!(bit_unpack(reference, width, &bytes, values.len()).unwrap(), values);
+        }
+        assert_eq!(bit_pack(&[i64::MIN, i64::MAX]).1, 64);
+        assert_eq!(bit_pack(&[-7; 5]), (-7, 0, Vec::new()));
+    }
+
+    #[test]
+    fn test_unpack_truncated() {
+        let (reference, width, bytes) = bit_pack(&[1, 200, 3000]);
+        assert!(bit_unpack(reference, width, &bytes[..bytes.len() - 1], 3).is_none());
+        assert!(bit_unpack(reference, 65, &bytes, 1).is_none());
+        assert!(bit_unpack(reference, width, &bytes, usize::MAX).is_none());
+    }
+}
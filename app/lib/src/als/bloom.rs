@@ -0,0 +1,214 @@
+//! Per-block Bloom filters.
+//!
+//! In block mode, each column can carry one Bloom filter per block holding
+//! the block's distinct values. A query for a value then only expands the
+//! blocks whose filter may contain it. Filters are appended to the document
+//! as one line per column:
+//!
+//! ```text
+//! !bloom 0 7 8a0140c2d8110e92,21c0049a3b0e58f1
+//! ```
+//!
+//! The line holds the column position, the number of hash functions, and
+//! the hex-encoded bits of each block's filter, separated by `,`.
+
+use serde::{Deserialize, Serialize};
+
+use super::trailer::TrailerLine;
+use crate::error::Result;
+
+/// Prefix of a Bloom filter line.
+pub const BLOOM_PREFIX: &str = "!bloom ";
+
+/// Bits stored per distinct value, for a false positive rate near 1%.
+const BITS_PER_VALUE: usize = 10;
+
+/// Hash functions per value, optimal for `BITS_PER_VALUE`.
+const HASH_COUNT: u32 = 7;
+
+/// Probabilistic set of the distinct values in a block.
+///
+/// `may_contain` never returns false for an inserted value, and returns
+/// true for other values about 1% of the time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Create an empty filter sized for `capacity` distinct values.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let bytes = (capacity.max(1) * BITS_PER_VALUE).div_ceil(8);
+        Self {
+            bits: vec![0; bytes],
+            hashes: HASH_COUNT,
+        }
+    }
+
+    /// Build a filter holding the distinct values of a block.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::als::BloomFilter;
+    ///
+    /// let filter = BloomFilter::build(&["GET", "POST", "GET"]);
+    /// assert!(filter.may_contain("GET"));
+    /// assert!(filter.may_contain("POST"));
+    /// ```
+    pub fn build<S: AsRef<str>>(values: &[S]) -> Self {
+        let mut distinct: Vec<&str> = values.iter().map(|v| v.as_ref()).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+
+        let mut filter = Self::with_capacity(distinct.len());
+        for value in distinct {
+            filter.insert(value);
+        }
+        filter
+    }
+
+    /// Add a value to the filter.
+    pub fn insert(&mut self, value: &str) {
+        for bit in self.bit_positions(value) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Check if the filter may contain a value.
+    pub fn may_contain(&self, value: &str) -> bool {
+        self.bit_positions(value)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Get the size of the filter in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Bit positions for a value, by double hashing.
+    fn bit_positions(&self, value: &str) -> impl Iterator<Item = usize> {
+        let bit_count = (self.bits.len() * 8) as u64;
+        let h1 = fnv1a(value.as_bytes());
+        // Odd, so the probe sequence visits distinct bits
+        let h2 = mix(h1) | 1;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize)
+    }
+
+    /// Write the Bloom filter line for the blocks of `column`, without a
+    /// trailing newline.
+    pub(crate) fn write_line(output: &mut String, column: usize, filters: &[BloomFilter]) {
+        output.push_str(BLOOM_PREFIX);
+        output.push_str(&column.to_string());
+        output.push(' ');
+        output.push_str(&filters.first().map_or(HASH_COUNT, |f| f.hashes).to_string());
+        output.push(' ');
+        for (i, filter) in filters.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            for byte in &filter.bits {
+                output.push_str(&format!("{:02x}", byte));
+            }
+        }
+    }
+
+    /// Read a `!bloom` line at offset `position` back into the number of
+    /// the column it covers and its filters, one per block.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(usize, Vec<BloomFilter>)> {
+        let line = TrailerLine::new(line, BLOOM_PREFIX, "Bloom filter", position)?;
+        let mut fields = line.body().split(' ');
+        let column = fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(|| line.error("Bloom filter line is missing its column"))?;
+        let hashes = fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .filter(|&hashes| hashes > 0)
+            .ok_or_else(|| line.error("Bloom filter line is missing its hash count"))?;
+        let blocks = fields
+            .next()
+            .ok_or_else(|| line.error("Bloom filter line has no filters"))?;
+        if fields.next().is_some() {
+            return Err(line.error("Unexpected field in Bloom filter line"));
+        }
+
+        let filters = blocks
+            .split(',')
+            .map(|hex| {
+                let bits = decode_hex(hex).ok_or_else(|| line.error("Invalid Bloom filter bits"))?;
+                Ok(Self { bits, hashes })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((column, filters))
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// SplitMix64 finalizer, used to derive the second hash.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Decode non-empty lowercase or uppercase hex.
+pub(super) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let values: Vec<String> = (0..500).map(|i| format!("user-{}", i)).collect();
+        let filter = BloomFilter::build(&values);
+        assert!(values.iter().all(|v| filter.may_contain(v)));
+        assert_eq!(filter.byte_len(), 625);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let values: Vec<String> = (0..1000).map(|i| format!("in-{}", i)).collect();
+        let filter = BloomFilter::build(&values);
+        let false_positives = (0..10_000).filter(|i| filter.may_contain(&format!("out-{}", i))).count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_line_roundtrip() {
+        let filters = vec![BloomFilter::build(&["a", "b"]), BloomFilter::build(&["c"])];
+        let mut line = String::new();
+        BloomFilter::write_line(&mut line, 2, &filters);
+        assert!(line.starts_with("!bloom 2 7 "));
+
+        let (column, parsed) = BloomFilter::parse_line(&line, 0).unwrap();
+        assert_eq!(column, 2);
+        assert_eq!(parsed, filters);
+        assert!(parsed[1].may_contain("c"));
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert!(BloomFilter::parse_line("!bloom 0 7", 0).is_err());
+        assert!(BloomFilter::parse_line("!bloom 0 0 ff", 0).is_err());
+        assert!(BloomFilter::parse_line("!bloom 0 7 fg", 0).is_err());
+        assert!(BloomFilter::parse_line("!bloom 0 7 f", 0).is_err());
+    }
+}
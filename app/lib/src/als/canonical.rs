@@ -0,0 +1,434 @@
+//! Canonical forms of ALS documents.
+//!
+//! The same values can be encoded many ways: `1>3 4>5` and `1>5`, `x*1`
+//! and `x`, or a dictionary in any order. Canonicalizing rewrites a
+//! document into one chosen form, so documents that differ only by these
+//! rewrites compare equal:
+//!
+//! - Repeats of one value are folded, `x*1` becomes `x`, and nested
+//!   repeats multiply out.
+//! - Adjacent operators that one operator can express are merged, as in
+//!   [`AlsOperator::merge_adjacent`], except across block boundaries.
+//! - Ranges end on their last value, and single-value ranges become raw
+//!   values.
+//! - Operators producing no values are dropped.
+//! - The default dictionary holds only referenced entries, sorted, and
+//!   references are renumbered to match.
+//! - File metadata is dropped, as it describes where the data came from
+//!   rather than the data.
+//! - The version is the one the header is written with, so a version 1
+//!   document using a [`Feature`](super::Feature) becomes version 2.
+//!
+//! CTX streams, which hold verbatim values, are left unchanged.
+//!
+//! Documents and streams compare and hash by their canonical form, so
+//! deduplicating documents never needs to expand them.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::document::{AlsDocument, ColumnStream};
+use super::operator::AlsOperator;
+
+impl<'a> AlsDocument<'a> {
+    /// Rewrite the document into its canonical form.
+    ///
+    /// The document expands to the same rows afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, AlsSerializer};
+    ///
+    /// let parser = AlsParser::new();
+    /// let mut a = parser.parse("$default:b|a\n#x #y\n1>3 4>5|_0*2 _1 _1*1 _1").unwrap();
+    /// let mut b = parser.parse("$default:a|b\n#x #y\n1>5|_1*2 _0*3").unwrap();
+    ///
+    /// a.canonicalize();
+    /// b.canonicalize();
+    /// let serializer = AlsSerializer::new();
+    /// assert_eq!(serializer.serialize(&a), serializer.serialize(&b));
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.canonicalize_dictionary();
+        self.file_metadata = None;
+        self.version = self.header_version();
+
+        let block_size = self.block_size.filter(|&size| size > 0);
+        for stream in &mut self.streams {
+            if stream.is_ctx() {
+                continue;
+            }
+            stream.operators = match block_size {
+                Some(size) => canonical_blocks(std::mem::take(&mut stream.operators), size),
+                None => canonical_operators(std::mem::take(&mut stream.operators)),
+            };
+        }
+    }
+
+    /// Hash the canonical form of the document, without expanding it.
+    ///
+    /// Equal documents have equal hashes. The hash may change between
+    /// library versions, so it should not be persisted.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Get a canonical copy of the document.
+    fn canonical(&self) -> AlsDocument<'a> {
+        let mut doc = self.clone();
+        doc.canonicalize();
+        doc
+    }
+
+    /// Keep only the referenced entries of the default dictionary, sorted,
+    /// and renumber references to match.
+    ///
+    /// Documents with references past the end of the dictionary are left
+    /// unchanged.
+    fn canonicalize_dictionary(&mut self) {
+        let Some(dictionary) = self.dictionaries.get("default") else {
+            return;
+        };
+
+        let mut used = BTreeSet::new();
+        for stream in &mut self.streams {
+            for operator in &mut stream.operators {
+                visit_dict_refs(operator, &mut |index| {
+                    used.insert(*index);
+                });
+            }
+        }
+        if used.iter().any(|&index| index >= dictionary.len()) {
+            return;
+        }
+
+        let mut entries: Vec<String> = used.iter().map(|&index| dictionary[index].clone()).collect();
+        entries.sort();
+        entries.dedup();
+        let renumbered: Vec<usize> = dictionary
+            .iter()
+            .map(|entry| entries.binary_search(entry).unwrap_or(usize::MAX))
+            .collect();
+
+        for stream in &mut self.streams {
+            for operator in &mut stream.operators {
+                visit_dict_refs(operator, &mut |index| *index = renumbered[*index]);
+            }
+        }
+        if entries.is_empty() {
+            self.dictionaries.remove("default");
+        } else {
+            self.dictionaries.insert("default".to_string(), entries);
+        }
+    }
+}
+
+impl ColumnStream<'_> {
+    /// Rewrite the operators of the stream into their canonical form.
+    ///
+    /// Operators are merged without regard to blocks; use
+    /// [`AlsDocument::canonicalize`] for block-mode documents.
+    pub fn canonicalize(&mut self) {
+        if !self.is_ctx() {
+            self.operators = canonical_operators(std::mem::take(&mut self.operators));
+        }
+    }
+}
+
+impl PartialEq for AlsDocument<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.canonical(), other.canonical());
+        a.version == b.version
+            && a.dictionaries == b.dictionaries
+            && a.schema == b.schema
+            && a.format_indicator == b.format_indicator
+            && a.indexes == b.indexes
+            && a.block_size == b.block_size
+            && a.blooms == b.blooms
+            && a.zone_maps == b.zone_maps
+            && a.delta == b.delta
+            && a.nfc_normalized == b.nfc_normalized
+            && a.redactions == b.redactions
+            && a.quantizations == b.quantizations
+            && a.truncations == b.truncations
+            && a.foreign_keys == b.foreign_keys
+            && a.streams.len() == b.streams.len()
+            && a.streams.iter().zip(&b.streams).all(|(a, b)| {
+                a.format_indicator == b.format_indicator && a.operators == b.operators
+            })
+    }
+}
+
+impl Eq for AlsDocument<'_> {}
+
+impl Hash for AlsDocument<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let doc = self.canonical();
+        doc.version.hash(state);
+        let mut dictionaries: Vec<_> = doc.dictionaries.iter().collect();
+        dictionaries.sort();
+        dictionaries.hash(state);
+        doc.schema.hash(state);
+        doc.format_indicator.hash(state);
+        doc.block_size.hash(state);
+        doc.nfc_normalized.hash(state);
+        doc.redactions.hash(state);
+        doc.quantizations.hash(state);
+        doc.truncations.hash(state);
+        doc.foreign_keys.hash(state);
+        doc.streams.len().hash(state);
+        for stream in &doc.streams {
+            stream.format_indicator.hash(state);
+            stream.operators.hash(state);
+        }
+    }
+}
+
+impl<'a> ColumnStream<'a> {
+    /// Get the operators of the stream in canonical form.
+    fn canonical_operators(&self) -> Cow<'_, [AlsOperator<'a>]> {
+        if self.is_ctx() {
+            Cow::Borrowed(&self.operators)
+        } else {
+            Cow::Owned(canonical_operators(self.operators.clone()))
+        }
+    }
+}
+
+impl PartialEq for ColumnStream<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.format_indicator == other.format_indicator && self.canonical_operators() == other.canonical_operators()
+    }
+}
+
+impl Eq for ColumnStream<'_> {}
+
+impl Hash for ColumnStream<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.format_indicator.hash(state);
+        self.canonical_operators().hash(state);
+    }
+}
+
+impl<'a> AlsOperator<'a> {
+    /// Rewrite the operator into its canonical form.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsOperator;
+    ///
+    /// let nested = AlsOperator::multiply(AlsOperator::multiply(AlsOperator::raw("x"), 2), 3);
+    /// assert_eq!(nested.canonicalize(), AlsOperator::multiply(AlsOperator::raw("x"), 6));
+    /// assert_eq!(AlsOperator::range_with_step(1, 10, 4).canonicalize(), AlsOperator::range_with_step(1, 9, 4));
+    /// ```
+    pub fn canonicalize(self) -> AlsOperator<'a> {
+        match self {
+            AlsOperator::Range { start, end, step } => canonical_range(start, end, step),
+            AlsOperator::Multiply { value, count } => match (value.canonicalize(), count) {
+                (value, 1) => value,
+                (AlsOperator::Multiply { value, count: inner }, count) => match inner.checked_mul(count) {
+                    Some(total) => AlsOperator::multiply(*value, total),
+                    None => AlsOperator::multiply(AlsOperator::multiply(*value, inner), count),
+                },
+                (value, count) => AlsOperator::multiply(value, count),
+            },
+            AlsOperator::Toggle { mut values, count } => {
+                if count == 1 && !values.is_empty() {
+                    return AlsOperator::raw(values.swap_remove(0));
+                }
+                // Values past the count are never produced
+                if count >= 2 {
+                    values.truncate(count);
+                }
+                AlsOperator::Toggle { values, count }
+            }
+            AlsOperator::ColumnRef { column, mapping, count } => {
+                // Lookups use the first entry for each source value
+                let mut canonical: Vec<(String, String)> = Vec::with_capacity(mapping.len());
+                for (from, to) in mapping {
+                    if !canonical.iter().any(|(seen, _)| *seen == from) {
+                        canonical.push((from, to));
+                    }
+                }
+                canonical.sort();
+                AlsOperator::ColumnRef {
+                    column,
+                    mapping: canonical,
+                    count,
+                }
+            }
+            AlsOperator::Transform { value, transform } => AlsOperator::Transform {
+                value: Box::new(value.canonicalize()),
+                transform,
+            },
+            AlsOperator::Split { separator, parts } => AlsOperator::Split {
+                separator,
+                parts: parts.into_iter().map(canonical_operators).collect(),
+            },
+            other => other,
+        }
+    }
+}
+
+/// Canonicalize a range, leaving ranges whose step runs away from their
+/// end unchanged.
+fn canonical_range(start: i64, end: i64, step: i64) -> AlsOperator<'static> {
+    if start == end {
+        return AlsOperator::raw(start.to_string());
+    }
+    if step == 0 || (end > start) != (step > 0) {
+        return AlsOperator::Range { start, end, step };
+    }
+
+    let steps = (end as i128 - start as i128) / step as i128;
+    if steps == 0 {
+        return AlsOperator::raw(start.to_string());
+    }
+    // The last value lies between start and end, so it fits in an i64
+    let last = (start as i128 + steps * step as i128) as i64;
+    AlsOperator::Range { start, end: last, step }
+}
+
+/// Canonicalize a sequence of operators, merging neighbours.
+fn canonical_operators(operators: Vec<AlsOperator<'_>>) -> Vec<AlsOperator<'_>> {
+    let mut canonical: Vec<AlsOperator<'_>> = Vec::with_capacity(operators.len());
+    for operator in operators.into_iter().map(AlsOperator::canonicalize) {
+        if operator.expanded_count() == 0 {
+            continue;
+        }
+        let merged = match canonical.last() {
+            Some(last) if *last == operator && matches!(operator, AlsOperator::Raw(_) | AlsOperator::DictRef(_)) => {
+                Some(AlsOperator::multiply(operator.clone(), 2))
+            }
+            Some(last) => last.try_merge(&operator),
+            None => None,
+        };
+        match merged {
+            Some(merged) => *canonical.last_mut().expect("merged with last operator") = merged,
+            None => canonical.push(operator),
+        }
+    }
+    canonical
+}
+
+/// Canonicalize the operators of each block of `size` rows on their own,
+/// so no operator comes to cross a block boundary.
+fn canonical_blocks(operators: Vec<AlsOperator<'_>>, size: usize) -> Vec<AlsOperator<'_>> {
+    let mut canonical = Vec::with_capacity(operators.len());
+    let mut block = Vec::new();
+    let mut rows = 0;
+    for operator in operators {
+        rows += operator.expanded_count();
+        block.push(operator);
+        if rows % size == 0 {
+            canonical.extend(canonical_operators(std::mem::take(&mut block)));
+        }
+    }
+    canonical.extend(canonical_operators(block));
+    canonical
+}
+
+/// Call `f` with every dictionary reference in `operator`.
+fn visit_dict_refs(operator: &mut AlsOperator, f: &mut impl FnMut(&mut usize)) {
+    match operator {
+        AlsOperator::DictRef(index) => f(index),
+        AlsOperator::Multiply { value, .. } | AlsOperator::Transform { value, .. } => visit_dict_refs(value, f),
+        AlsOperator::Split { parts, .. } => {
+            for operator in parts.iter_mut().flatten() {
+                visit_dict_refs(operator, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::{AlsParser, AlsSerializer};
+    use crate::compress::AlsCompressor;
+    use crate::config::CompressorConfig;
+    use crate::convert::csv::parse_csv;
+
+    fn canonical(als: &str) -> String {
+        let mut doc = AlsParser::new().parse(als).unwrap();
+        doc.canonicalize();
+        AlsSerializer::new().serialize(&doc)
+    }
+
+    #[test]
+    fn test_equivalent_encodings_agree() {
+        for (a, b) in [
+            ("#x\n1>3 4>5", "#x\n1>5"),
+            ("#x\nx*1 x x*2", "#x\nx*4"),
+            ("#x\n1>10:4 5>5", "#x\n1>9:4 5"),
+            ("#x\na~b~c*2 c", "#x\na~b*2 c"),
+            ("$default:z|y|x\n#x\n_2 _0 _0", "$default:x|z\n#x\n_0 _1*2"),
+        ] {
+            assert_eq!(canonical(a), canonical(b), "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_unused_dictionary_is_dropped() {
+        let mut doc = AlsParser::new().parse("$default:a|b\n#x\nc d").unwrap();
+        doc.canonicalize();
+        assert!(doc.default_dictionary().is_none());
+    }
+
+    #[test]
+    fn test_expansion_is_preserved() {
+        let csv = "id,status,code,flag\n1,open,A1,y\n2,open,A2,y\n3,done,A3,n\n4,open,A4,n\n5,held,A5,y\n6,held,A6,y";
+        let parser = AlsParser::new();
+        for config in [CompressorConfig::new(), CompressorConfig::new().with_block_size(2)] {
+            let doc = AlsCompressor::with_config(config).compress(&parse_csv(csv).unwrap()).unwrap();
+            let mut canonical = doc.clone();
+            canonical.canonicalize();
+            assert_eq!(parser.expand(&canonical).unwrap(), parser.expand(&doc).unwrap());
+            for block in 0..canonical.block_count() {
+                for column in 0..canonical.column_count() {
+                    canonical.block_stream(column, block).unwrap();
+                }
+            }
+
+            let mut twice = canonical.clone();
+            twice.canonicalize();
+            assert_eq!(AlsSerializer::new().serialize(&twice), AlsSerializer::new().serialize(&canonical));
+            assert_eq!(canonical, doc);
+        }
+    }
+
+    #[test]
+    fn test_equality_and_hash_follow_canonical_form() {
+        let parser = AlsParser::new();
+        let a = parser.parse("$default:z|y\n#x #y\n1>3 4>5|_1 _0*4").unwrap();
+        let b = parser.parse("$default:y|z\n#x #y\n1>5|_0 _1*3 _1").unwrap();
+        let c = parser.parse("$default:y|z\n#x #y\n1>5|_0*2 _1*3").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(a, c);
+        assert_ne!(a.content_hash(), c.content_hash());
+        assert_eq!(a.streams[0], b.streams[0]);
+        assert_ne!(a.streams[1], c.streams[1]);
+
+        let unique: std::collections::HashSet<AlsDocument> = [a, b, c].into_iter().collect();
+        assert_eq!(unique.len(), 2);
+    }
+
+    #[test]
+    fn test_blocks_are_not_merged() {
+        let mut doc = AlsParser::new().parse("#x\n1>2 3>4").unwrap();
+        doc.block_size = Some(2);
+        doc.canonicalize();
+        assert_eq!(doc.streams[0].operators, vec![AlsOperator::range(1, 2), AlsOperator::range(3, 4)]);
+
+        doc.block_size = None;
+        doc.canonicalize();
+        assert_eq!(doc.streams[0].operators, vec![AlsOperator::range(1, 4)]);
+    }
+}
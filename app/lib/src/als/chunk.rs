@@ -0,0 +1,113 @@
+//! Content-defined chunking.
+//!
+//! Splits text into chunks whose boundaries depend on the content rather
+//! than on offsets, using a gear rolling hash. An edit early in a document
+//! then only changes the chunks around it, so identical segments of
+//! near-identical documents still produce identical chunks. Boundaries are
+//! also placed after column separators and newlines, so column streams
+//! that match across documents line up with chunk starts.
+
+/// Chunks are never cut shorter than this, except at the end of the text.
+const MIN_CHUNK: usize = 256;
+
+/// Chunks are cut at this size if the content gives no boundary.
+const MAX_CHUNK: usize = 8192;
+
+/// A boundary is placed where the hash has these bits clear, giving
+/// chunks of about `MIN_CHUNK + 1024` bytes.
+const BOUNDARY_MASK: u64 = (1 << 10) - 1;
+
+/// Random value per byte for the gear hash, from SplitMix64.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut x = state;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = x ^ (x >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Split text into content-defined chunks.
+///
+/// Chunks cover the whole text in order and always end on a character
+/// boundary.
+pub(super) fn content_chunks(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut hash: u64 = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        let end = i + 1;
+        let size = end - start;
+        if size < MIN_CHUNK || !text.is_char_boundary(end) {
+            continue;
+        }
+        let separator = matches!(byte, b'|' | b'\n') && bytes[i - 1] != b'\\';
+        if separator || hash & BOUNDARY_MASK == 0 || size >= MAX_CHUNK {
+            chunks.push(&text[start..end]);
+            start = end;
+            hash = 0;
+        }
+    }
+    if start < text.len() {
+        chunks.push(&text[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(seed: u64, len: usize) -> String {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (b'a' + (state >> 59) as u8 % 26) as char
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_cover_text() {
+        let text = sample(1, 50_000);
+        let chunks = content_chunks(&text);
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks.len() > 10);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= MAX_CHUNK));
+        assert!(chunks[..chunks.len() - 1].iter().all(|chunk| chunk.len() >= MIN_CHUNK));
+    }
+
+    #[test]
+    fn test_boundaries_resync_after_edit() {
+        let text = sample(2, 50_000);
+        let edited = format!("{}XYZ{}", &text[..1000], &text[1000..]);
+        let original = content_chunks(&text);
+        let shifted = content_chunks(&edited);
+        let shared = shifted.iter().filter(|chunk| original.contains(chunk)).count();
+        assert!(shared + 3 >= original.len(), "{} of {} chunks shared", shared, original.len());
+    }
+
+    #[test]
+    fn test_cuts_at_column_separators() {
+        let column = "x".repeat(300);
+        let text = format!("{}|{}", column, column);
+        let chunks = content_chunks(&text);
+        assert_eq!(chunks[0], format!("{}|", column));
+    }
+
+    #[test]
+    fn test_chunks_end_on_char_boundaries() {
+        let text = "é".repeat(10_000);
+        let chunks = content_chunks(&text);
+        assert_eq!(chunks.concat(), text);
+    }
+}
@@ -0,0 +1,925 @@
+//! Containers of named ALS documents.
+//!
+//! A container holds many ALS documents, such as one per table of a
+//! database or one per file of a log directory, followed by a table of
+//! contents giving the offset, length and CRC-32 of each:
+//!
+//! ```text
+//! !container 1
+//! #id #name
+//! 1>3|alice bob carol
+//! #ts #level
+//! ...
+//! !toc
+//! users 13 30 5d2a7c1f
+//! logs 44 212 0be41a93
+//! !end 257
+//! ```
+//!
+//! Offsets are in bytes from the start of the container, and each document
+//! is followed by a newline that its length does not include. The last line
+//! holds the offset of the `!toc` line, so readers find the table of
+//! contents without scanning the documents, and writers can stream
+//! documents out before the table of contents is known.
+//!
+//! With deduplication, documents are split into content-defined chunks and
+//! each distinct chunk is stored once. A document made of several chunks
+//! has `~` in place of its offset and lists its chunks as `offset+length`
+//! ranges, separated by `,`:
+//!
+//! ```text
+//! day-2.csv ~ 5120 1f0e22a7 13+1290,2906+3830
+//! ```
+//!
+//! Shared dictionaries (see [`SharedDictionary`]) are stored as dictionary
+//! header lines, listed first in the table of contents with their names
+//! after an unescaped `$`, which document names always escape:
+//!
+//! ```text
+//! $user_id 13 2210 9c0b6e41
+//! ```
+//!
+//! Documents with foreign keys (see [`ForeignKey`]) name their parents by
+//! their names in the container, which resolves the row numbers they hold
+//! when they are expanded.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::chunk::content_chunks;
+use super::document::AlsDocument;
+use super::escape::{decode_als_value, encode_als_value};
+use super::foreign_key::ForeignKey;
+use super::parser::AlsParser;
+use super::serializer::AlsSerializer;
+use super::shared::{resolve_shared, SharedDictionary};
+use super::signature::split_signature;
+use super::store::StoreFrame;
+use crate::config::JsonFormat;
+use crate::convert::csv::to_csv;
+use crate::convert::json::to_json_with_format;
+use crate::error::{AlsError, Result};
+
+/// First line of a container.
+pub const CONTAINER_HEADER: &str = "!container 1";
+
+/// Line starting the table of contents.
+const TOC_LINE: &str = "!toc";
+
+/// Prefix of the last line, holding the offset of the table of contents.
+const END_PREFIX: &str = "!end ";
+
+/// Offset field of a document stored in several chunks.
+const CHUNKED_OFFSET: &str = "~";
+
+/// Prefix of the table of contents entry of a shared dictionary.
+const SHARED_PREFIX: &str = "$";
+
+/// Table of contents entry for one document of a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerEntry {
+    /// Name of the document, unique within the container.
+    pub name: String,
+    /// Length of the document in bytes.
+    pub length: usize,
+    /// CRC-32 of the document.
+    pub checksum: u32,
+    /// Byte ranges of the container holding the document, in order.
+    ///
+    /// A document stored contiguously has a single range. Deduplicated
+    /// documents may share ranges with other documents.
+    pub chunks: Vec<Range<usize>>,
+}
+
+impl ContainerEntry {
+    /// Check if the document is stored as a single range.
+    pub fn is_contiguous(&self) -> bool {
+        self.chunks.len() <= 1
+    }
+}
+
+/// Identity of a chunk: its length and two independent 64-bit hashes.
+type ChunkKey = (usize, u64, u64);
+
+/// Writes documents into a container.
+///
+/// Documents are written as they are added; the table of contents is
+/// written by [`finish`](Self::finish).
+///
+/// # Example
+///
+/// ```
+/// use als_compression::{AlsCompressor, AlsContainerReader, AlsContainerWriter};
+///
+/// let compressor = AlsCompressor::new();
+/// let mut writer = AlsContainerWriter::new(Vec::new()).unwrap();
+/// writer.add("users", &compressor.compress_csv("id,name\n1,alice\n2,bob").unwrap()).unwrap();
+/// writer.add("orders", &compressor.compress_csv("id,total\n1,10\n2,25").unwrap()).unwrap();
+/// let bytes = writer.finish().unwrap();
+///
+/// let text = String::from_utf8(bytes).unwrap();
+/// let reader = AlsContainerReader::new(&text).unwrap();
+/// assert_eq!(reader.names().collect::<Vec<_>>(), vec!["users", "orders"]);
+/// assert_eq!(reader.parse("orders").unwrap().unwrap().row_count(), 2);
+/// ```
+#[derive(Debug)]
+pub struct AlsContainerWriter<W: Write> {
+    writer: W,
+    offset: usize,
+    entries: Vec<ContainerEntry>,
+    names: HashSet<String>,
+    /// Ranges of the chunks written so far, when deduplicating.
+    chunks: Option<HashMap<ChunkKey, Range<usize>>>,
+    deduplicated: usize,
+    /// Shared dictionaries written so far, with their entries.
+    shared: Vec<(SharedDictionary, ContainerEntry)>,
+}
+
+impl<W: Write> AlsContainerWriter<W> {
+    /// Start a container, writing its header.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(CONTAINER_HEADER.as_bytes())?;
+        writer.write_all(b"\n")?;
+        Ok(Self {
+            writer,
+            offset: CONTAINER_HEADER.len() + 1,
+            entries: Vec::new(),
+            names: HashSet::new(),
+            chunks: None,
+            deduplicated: 0,
+            shared: Vec::new(),
+        })
+    }
+
+    /// Store segments repeated across documents only once.
+    ///
+    /// Documents added afterwards are split into content-defined chunks,
+    /// and chunks already in the container are referenced instead of being
+    /// written again. This pays off when archiving many near-identical
+    /// documents, such as daily exports of the same tables.
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.chunks = dedup.then(|| self.chunks.take().unwrap_or_default());
+        self
+    }
+
+    /// Add a dictionary that documents added afterwards can refer to.
+    ///
+    /// Each document added afterwards is rewritten onto whichever shared
+    /// dictionary makes it smallest, if any does; see [`SharedDictionary`].
+    /// A document can refer to one shared dictionary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsContainerReader, AlsContainerWriter, AlsParser, SharedDictionary};
+    ///
+    /// let parser = AlsParser::new();
+    /// let users = parser.parse("#user_id\nuser-1001 user-1002 user-1003").unwrap();
+    /// let orders = parser.parse("#user_id #total\nuser-1003 user-1001 user-1003|10 25 40").unwrap();
+    ///
+    /// let mut writer = AlsContainerWriter::new(Vec::new()).unwrap();
+    /// for dictionary in SharedDictionary::find(&[users.clone(), orders.clone()]).unwrap() {
+    ///     writer.add_shared_dictionary(dictionary).unwrap();
+    /// }
+    /// writer.add_document("users", &users).unwrap();
+    /// writer.add_document("orders", &orders).unwrap();
+    /// let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+    ///
+    /// let reader = AlsContainerReader::new(&text).unwrap();
+    /// let read = reader.parse("orders").unwrap().unwrap();
+    /// assert_eq!(parser.expand(&read).unwrap(), parser.expand(&orders).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::DuplicateEntry` if the container already has a
+    /// shared dictionary with the same name.
+    pub fn add_shared_dictionary(&mut self, dictionary: SharedDictionary) -> Result<()> {
+        if self.shared.iter().any(|(shared, _)| shared.name == dictionary.name) {
+            return Err(AlsError::DuplicateEntry { name: dictionary.name });
+        }
+        let line = dictionary.to_line();
+        let entry = self.write_entry(&dictionary.name, &line)?;
+        self.shared.push((dictionary, entry));
+        Ok(())
+    }
+
+    /// Add serialized ALS text under `name`.
+    ///
+    /// With shared dictionaries, the document is stored referring to one
+    /// of them if that makes it smaller. Signed documents and store frames
+    /// are always stored as they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::DuplicateEntry` if the container already has a
+    /// document called `name`.
+    pub fn add(&mut self, name: &str, als: &str) -> Result<()> {
+        if !self.names.insert(name.to_string()) {
+            return Err(AlsError::DuplicateEntry {
+                name: name.to_string(),
+            });
+        }
+
+        let shared = self.share(als);
+        let entry = self.write_entry(name, shared.as_deref().unwrap_or(als))?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Rewrite a document onto the shared dictionary that makes it
+    /// smallest, or `None` if none makes it smaller.
+    fn share(&self, als: &str) -> Option<String> {
+        if self.shared.is_empty() || split_signature(als).1.is_some() || StoreFrame::parse(als).ok()?.is_some() {
+            return None;
+        }
+        let doc = AlsParser::new().parse(als).ok()?;
+        self.shared
+            .iter()
+            .filter_map(|(dictionary, _)| Some(dictionary.serialize_rebased(&dictionary.rebase(&doc)?)))
+            .filter(|text| text.len() < als.len())
+            .min_by_key(String::len)
+    }
+
+    /// Write text to the container, followed by a newline, and return its
+    /// table of contents entry.
+    fn write_entry(&mut self, name: &str, als: &str) -> Result<ContainerEntry> {
+        let chunks = match self.chunks.take() {
+            Some(mut seen) => {
+                let chunks = self.write_chunks(als, &mut seen);
+                self.chunks = Some(seen);
+                chunks?
+            }
+            None => {
+                let chunk = self.offset..self.offset + als.len();
+                self.writer.write_all(als.as_bytes())?;
+                self.offset += als.len();
+                vec![chunk]
+            }
+        };
+        self.writer.write_all(b"\n")?;
+        self.offset += 1;
+
+        Ok(ContainerEntry {
+            name: name.to_string(),
+            length: als.len(),
+            checksum: crc32(als.as_bytes()),
+            chunks,
+        })
+    }
+
+    /// Write the chunks of `als` not yet in the container, returning the
+    /// ranges of all its chunks with adjacent ranges merged.
+    fn write_chunks(&mut self, als: &str, seen: &mut HashMap<ChunkKey, Range<usize>>) -> Result<Vec<Range<usize>>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for chunk in content_chunks(als) {
+            let bytes = chunk.as_bytes();
+            let key = (bytes.len(), fnv1a(bytes, FNV_OFFSET), fnv1a(bytes, FNV_OFFSET_ALT));
+            let range = match seen.get(&key) {
+                Some(range) => {
+                    self.deduplicated += bytes.len();
+                    range.clone()
+                }
+                None => {
+                    self.writer.write_all(bytes)?;
+                    let range = self.offset..self.offset + bytes.len();
+                    self.offset += bytes.len();
+                    seen.insert(key, range.clone());
+                    range
+                }
+            };
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        if ranges.is_empty() {
+            ranges.push(self.offset..self.offset);
+        }
+        Ok(ranges)
+    }
+
+    /// Serialize a document and add it under `name`.
+    pub fn add_document(&mut self, name: &str, doc: &AlsDocument) -> Result<()> {
+        self.add(name, &AlsSerializer::new().serialize(doc))
+    }
+
+    /// Get the entries of the documents added so far.
+    pub fn entries(&self) -> &[ContainerEntry] {
+        &self.entries
+    }
+
+    /// Get the number of document bytes that refer to chunks already in
+    /// the container instead of being written again.
+    pub fn deduplicated_bytes(&self) -> usize {
+        self.deduplicated
+    }
+
+    /// Write the table of contents and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        let mut toc = String::from(TOC_LINE);
+        toc.push('\n');
+        let shared = self.shared.iter().map(|(_, entry)| (format!("{}{}", SHARED_PREFIX, entry.name), entry));
+        let documents = self.entries.iter().map(|entry| (encode_als_value(Some(&entry.name)), entry));
+        for (name, entry) in shared.chain(documents) {
+            match entry.chunks.as_slice() {
+                [range] => toc.push_str(&format!(
+                    "{} {} {} {:08x}\n",
+                    name, range.start, entry.length, entry.checksum
+                )),
+                chunks => {
+                    let chunks: Vec<String> = chunks
+                        .iter()
+                        .map(|range| format!("{}+{}", range.start, range.len()))
+                        .collect();
+                    toc.push_str(&format!(
+                        "{} {} {} {:08x} {}\n",
+                        name,
+                        CHUNKED_OFFSET,
+                        entry.length,
+                        entry.checksum,
+                        chunks.join(",")
+                    ));
+                }
+            }
+        }
+        toc.push_str(END_PREFIX);
+        toc.push_str(&self.offset.to_string());
+        toc.push('\n');
+
+        self.writer.write_all(toc.as_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the documents of a container.
+#[derive(Debug, Clone)]
+pub struct AlsContainerReader<'a> {
+    input: &'a str,
+    entries: Vec<ContainerEntry>,
+    shared: Vec<ContainerEntry>,
+}
+
+impl<'a> AlsContainerReader<'a> {
+    /// Read the table of contents of a container.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::AlsSyntaxError` if the input is not a container
+    /// or its table of contents is malformed.
+    pub fn new(input: &'a str) -> Result<Self> {
+        let syntax_error = |position: usize, message: String| AlsError::AlsSyntaxError { position, message };
+
+        if !Self::is_container(input) {
+            return Err(syntax_error(0, "Expected container header".to_string()));
+        }
+
+        let (input, _) = split_signature(input);
+        let body = input.strip_suffix('\n').unwrap_or(input);
+        let end_start = body.rfind('\n').map_or(0, |i| i + 1);
+        let toc_offset: usize = body[end_start..]
+            .strip_prefix(END_PREFIX)
+            .and_then(|offset| offset.parse().ok())
+            .ok_or_else(|| syntax_error(end_start, "Container is missing its end line".to_string()))?;
+        let toc = body
+            .get(toc_offset..end_start)
+            .and_then(|toc| toc.strip_prefix(TOC_LINE))
+            .and_then(|toc| toc.strip_prefix('\n'))
+            .ok_or_else(|| syntax_error(toc_offset, "Invalid table of contents offset".to_string()))?;
+
+        let data_start = CONTAINER_HEADER.len() + 1;
+        let mut position = toc_offset + TOC_LINE.len() + 1;
+        let mut names = HashSet::new();
+        let mut entries = Vec::new();
+        let mut shared = Vec::new();
+        for line in toc.lines() {
+            let dictionary = line.strip_prefix(SHARED_PREFIX);
+            let entry = parse_entry(dictionary.unwrap_or(line))
+                .ok_or_else(|| syntax_error(position, format!("Invalid table of contents entry: {}", line)))?;
+            let in_bounds = entry.chunks.iter().all(|range| {
+                range.start >= data_start && range.end <= toc_offset && input.get(range.clone()).is_some()
+            });
+            let length: usize = entry.chunks.iter().map(|range| range.len()).sum();
+            if !in_bounds || length != entry.length {
+                return Err(syntax_error(
+                    position,
+                    format!("Document '{}' lies outside the container", entry.name),
+                ));
+            }
+            position += line.len() + 1;
+            if dictionary.is_some() {
+                if shared.iter().any(|other: &ContainerEntry| other.name == entry.name) {
+                    return Err(AlsError::DuplicateEntry { name: entry.name });
+                }
+                shared.push(entry);
+                continue;
+            }
+            if !names.insert(entry.name.clone()) {
+                return Err(AlsError::DuplicateEntry { name: entry.name });
+            }
+            entries.push(entry);
+        }
+
+        Ok(Self { input, entries, shared })
+    }
+
+    /// Check if text starts with a container header.
+    pub fn is_container(input: &str) -> bool {
+        input
+            .strip_prefix(CONTAINER_HEADER)
+            .is_some_and(|rest| rest.starts_with('\n'))
+    }
+
+    /// Get the table of contents, in the order documents were added.
+    pub fn entries(&self) -> &[ContainerEntry] {
+        &self.entries
+    }
+
+    /// Get the table of contents entries of the shared dictionaries.
+    pub fn shared_entries(&self) -> &[ContainerEntry] {
+        &self.shared
+    }
+
+    /// Read a shared dictionary, after checking its checksum.
+    ///
+    /// Returns `Ok(None)` if the container has no shared dictionary called
+    /// `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::ChecksumMismatch` if the dictionary is corrupt.
+    pub fn shared_dictionary(&self, name: &str) -> Result<Option<SharedDictionary>> {
+        let Some(entry) = self.shared.iter().find(|entry| entry.name == name) else {
+            return Ok(None);
+        };
+        let line = self.read(entry)?;
+        SharedDictionary::parse_line(&line, entry.chunks[0].start).map(Some)
+    }
+
+    /// Iterate over the document names.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// Get the number of documents.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the container has no documents.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the table of contents entry of a document.
+    pub fn entry(&self, name: &str) -> Option<&ContainerEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Get the ALS text of a document, after checking its checksum.
+    ///
+    /// Contiguous documents are borrowed from the container; deduplicated
+    /// ones are reassembled from their chunks, and documents referring to
+    /// a shared dictionary get its entries written back into their header.
+    /// Foreign key columns keep their row numbers, which
+    /// [`expand_columns`](Self::expand_columns) resolves.
+    ///
+    /// Returns `Ok(None)` if the container has no document called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::ChecksumMismatch` if the document or the shared
+    /// dictionary it refers to is corrupt.
+    pub fn get(&self, name: &str) -> Result<Option<Cow<'a, str>>> {
+        let Some(entry) = self.entry(name) else {
+            return Ok(None);
+        };
+        let als = self.read(entry)?;
+        match resolve_shared(&als, |name| self.shared_dictionary(name))? {
+            Some(resolved) => Ok(Some(Cow::Owned(resolved))),
+            None => Ok(Some(als)),
+        }
+    }
+
+    /// Parse a document and expand its columns, replacing the row numbers
+    /// of its foreign keys with the parent keys they point at.
+    ///
+    /// Each parent key column is expanded once per call, however many
+    /// columns refer to it.
+    ///
+    /// Returns `Ok(None)` if the container has no document called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::ChecksumMismatch` if the document, the shared
+    /// dictionary it refers to or one of its parents is corrupt, and
+    /// `AlsError::InvalidForeignKey` if a parent is missing or holds fewer
+    /// rows than the document refers to.
+    pub fn expand_columns(&self, name: &str) -> Result<Option<(AlsDocument<'static>, Vec<Vec<String>>)>> {
+        let Some(doc) = self.parse(name)? else {
+            return Ok(None);
+        };
+        let mut columns = AlsParser::new().expand_columns(&doc)?;
+        let mut parents = HashMap::new();
+        for (column, key) in &doc.foreign_keys {
+            if let Some(index) = doc.schema.iter().position(|name| name == column) {
+                let keys = self.parent_keys(column, key, &mut parents, &mut Vec::new())?;
+                ForeignKey::resolve_values(column, &mut columns[index], &keys)?;
+            }
+        }
+        Ok(Some((doc, columns)))
+    }
+
+    /// Get a document as CSV, with its foreign keys resolved.
+    ///
+    /// Returns `Ok(None)` if the container has no document called `name`.
+    pub fn to_csv(&self, name: &str) -> Result<Option<String>> {
+        self.expand_columns(name)?
+            .map(|(doc, columns)| to_csv(&AlsParser::columns_to_tabular(&doc, columns, false)))
+            .transpose()
+    }
+
+    /// Get a document as JSON, typed as [`AlsParser::to_json`] does, with
+    /// its foreign keys resolved.
+    ///
+    /// Returns `Ok(None)` if the container has no document called `name`.
+    pub fn to_json(&self, name: &str) -> Result<Option<String>> {
+        self.expand_columns(name)?
+            .map(|(doc, columns)| {
+                to_json_with_format(&AlsParser::columns_to_tabular(&doc, columns, true), JsonFormat::default())
+            })
+            .transpose()
+    }
+
+    /// Get the keys `column` refers to through `key`, in row order.
+    ///
+    /// Keys are kept in `parents` for the rest of the read. A parent column
+    /// that is itself a foreign key is resolved in turn; `visited` holds the
+    /// parent columns already on the way, to catch cycles.
+    fn parent_keys(
+        &self,
+        column: &str,
+        key: &ForeignKey,
+        parents: &mut HashMap<ForeignKey, Arc<[String]>>,
+        visited: &mut Vec<ForeignKey>,
+    ) -> Result<Arc<[String]>> {
+        if let Some(keys) = parents.get(key) {
+            return Ok(Arc::clone(keys));
+        }
+        let invalid = |message: String| AlsError::InvalidForeignKey {
+            column: column.to_string(),
+            message,
+        };
+        if visited.contains(key) {
+            return Err(invalid(format!("column '{}' of '{}' refers back to itself", key.parent_column, key.parent)));
+        }
+        let parent = self
+            .parse(&key.parent)?
+            .ok_or_else(|| invalid(format!("the container has no document '{}'", key.parent)))?;
+        let index = parent
+            .schema
+            .iter()
+            .position(|name| *name == key.parent_column)
+            .ok_or_else(|| AlsError::UnknownColumn {
+                name: key.parent_column.clone(),
+            })?;
+        let mut keys = AlsParser::new().expand_columns(&parent)?.swap_remove(index);
+
+        if let Some(grandparent) = parent.foreign_keys.get(&key.parent_column) {
+            visited.push(key.clone());
+            let grandparent_keys = self.parent_keys(&key.parent_column, grandparent, parents, visited)?;
+            visited.pop();
+            ForeignKey::resolve_values(&key.parent_column, &mut keys, &grandparent_keys)?;
+        }
+        let keys: Arc<[String]> = keys.into();
+        parents.insert(key.clone(), Arc::clone(&keys));
+        Ok(keys)
+    }
+
+    /// Parse a document.
+    ///
+    /// Foreign key columns keep their row numbers, as in [`get`](Self::get).
+    ///
+    /// Returns `Ok(None)` if the container has no document called `name`.
+    pub fn parse(&self, name: &str) -> Result<Option<AlsDocument<'static>>> {
+        self.get(name)?
+            .map(|als| AlsParser::new().parse(&als))
+            .transpose()
+    }
+
+    /// Check the checksums of every document and shared dictionary.
+    pub fn verify(&self) -> Result<()> {
+        for entry in self.shared.iter().chain(&self.entries) {
+            self.read(entry)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, entry: &ContainerEntry) -> Result<Cow<'a, str>> {
+        let input = self.input;
+        let als = match entry.chunks.as_slice() {
+            [range] => Cow::Borrowed(&input[range.clone()]),
+            chunks => Cow::Owned(chunks.iter().map(|range| &input[range.clone()]).collect()),
+        };
+        let found = crc32(als.as_bytes());
+        if found != entry.checksum {
+            return Err(AlsError::ChecksumMismatch {
+                name: entry.name.clone(),
+                expected: entry.checksum,
+                found,
+            });
+        }
+        Ok(als)
+    }
+}
+
+/// Parse a `name offset length checksum` or `name ~ length checksum chunks`
+/// table of contents line.
+fn parse_entry(line: &str) -> Option<ContainerEntry> {
+    let mut fields = super::index::split_unescaped_spaces(line).into_iter();
+    let name = decode_als_value(fields.next()?).ok()??;
+    let offset = fields.next()?;
+    let length = fields.next()?.parse().ok()?;
+    let checksum = fields.next().filter(|hex| hex.len() == 8)?;
+    let checksum = u32::from_str_radix(checksum, 16).ok()?;
+    let chunks = if offset == CHUNKED_OFFSET {
+        fields
+            .next()?
+            .split(',')
+            .map(|range| {
+                let (start, len) = range.split_once('+')?;
+                let start: usize = start.parse().ok()?;
+                Some(start..start.checked_add(len.parse().ok()?)?)
+            })
+            .collect::<Option<Vec<_>>>()?
+    } else {
+        let offset: usize = offset.parse().ok()?;
+        let range = offset..offset.checked_add(length)?;
+        vec![range]
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(ContainerEntry {
+        name,
+        length,
+        checksum,
+        chunks,
+    })
+}
+
+/// Offset basis of 64-bit FNV-1a.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Another offset basis, giving a second hash for chunk identity.
+const FNV_OFFSET_ALT: u64 = 0x6c62_272e_07bb_0142;
+
+/// 64-bit FNV-1a from the given offset basis.
+fn fnv1a(bytes: &[u8], offset: u64) -> u64 {
+    bytes.iter().fold(offset, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Lookup table for CRC-32 (IEEE 802.3).
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3), as used by zip and gzip.
+pub(super) fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0, bytes)
+}
+
+/// Continue the CRC-32 `crc` over more bytes.
+pub(super) fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(documents: &[(&str, &str)]) -> String {
+        let mut writer = AlsContainerWriter::new(Vec::new()).unwrap();
+        for (name, als) in documents {
+            writer.add(name, als).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    /// A long document with one value changed per day.
+    fn daily_export(day: usize) -> String {
+        let ids: Vec<String> = (0..2000).map(|i| i.to_string()).collect();
+        let mut status: Vec<String> = (0..2000).map(|i| format!("s{}", i * i % 1009)).collect();
+        status[1500] = format!("day{}", day);
+        format!("#id #status\n{}|{}", ids.join(" "), status.join(" "))
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32_update(crc32(b"1234"), b"56789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let text = container(&[("a b", "#x\n1>3"), ("", "#y\nz"), ("logs/app.log", "#m\n\\!")]);
+        let reader = AlsContainerReader::new(&text).unwrap();
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.names().collect::<Vec<_>>(), vec!["a b", "", "logs/app.log"]);
+        assert_eq!(reader.get("a b").unwrap().as_deref(), Some("#x\n1>3"));
+        assert_eq!(reader.get("").unwrap().as_deref(), Some("#y\nz"));
+        assert_eq!(reader.get("missing").unwrap(), None);
+        assert_eq!(reader.parse("a b").unwrap().unwrap().row_count(), 3);
+        reader.verify().unwrap();
+    }
+
+    #[test]
+    fn test_layout() {
+        let text = container(&[("t", "#x\n1")]);
+        assert_eq!(text, "!container 1\n#x\n1\n!toc\nt 13 4 62100e94\n!end 18\n");
+        assert_eq!(crc32(b"#x\n1"), 0x6210_0e94);
+    }
+
+    #[test]
+    fn test_empty_container() {
+        let text = container(&[]);
+        let reader = AlsContainerReader::new(&text).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let mut writer = AlsContainerWriter::new(Vec::new()).unwrap();
+        writer.add("t", "#x\n1").unwrap();
+        assert!(matches!(writer.add("t", "#x\n2"), Err(AlsError::DuplicateEntry { .. })));
+        assert_eq!(writer.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_corruption_is_detected() {
+        let text = container(&[("t", "#x\n1>3")]).replacen("1>3", "1>4", 1);
+        let reader = AlsContainerReader::new(&text).unwrap();
+        assert!(matches!(reader.get("t"), Err(AlsError::ChecksumMismatch { .. })));
+        assert!(reader.verify().is_err());
+    }
+
+    #[test]
+    fn test_malformed_toc() {
+        let text = container(&[("t", "#x\n1")]);
+        assert!(AlsContainerReader::new("#x\n1").is_err());
+        assert!(AlsContainerReader::new(&text.replace("!end 18", "!end 17")).is_err());
+        assert!(AlsContainerReader::new(&text.replace("t 13 4", "t 13 40")).is_err());
+        assert!(AlsContainerReader::new(&text.replace("t 13 4", "t 0 4")).is_err());
+        assert!(AlsContainerReader::new(&text.replace("62100e94", "xyz")).is_err());
+        assert!(AlsContainerReader::new(&text.replace("t 13 4 62100e94", "t ~ 4 62100e94 13+3")).is_err());
+    }
+
+    #[test]
+    fn test_dedup_stores_shared_chunks_once() {
+        let days: Vec<String> = (0..5).map(daily_export).collect();
+        let mut writer = AlsContainerWriter::new(Vec::new()).unwrap().with_dedup(true);
+        for (day, export) in days.iter().enumerate() {
+            writer.add(&format!("day-{}", day), export).unwrap();
+        }
+        assert!(writer.deduplicated_bytes() > days[0].len() * 3);
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert!(text.len() < days[0].len() * 2);
+        assert!(text.contains("day-1 ~ "));
+
+        let reader = AlsContainerReader::new(&text).unwrap();
+        reader.verify().unwrap();
+        for (day, export) in days.iter().enumerate() {
+            assert_eq!(reader.get(&format!("day-{}", day)).unwrap().unwrap(), export.as_str());
+        }
+    }
+
+    #[test]
+    fn test_shared_dictionaries() {
+        use crate::{AlsCompressor, CompressorConfig};
+        use crate::convert::csv::parse_csv;
+
+        let users: String = std::iter::once("user_id,plan\n".to_string())
+            .chain((0..300).map(|i| format!("customer-{:05},{}\n", i * 7919 % 100_000, ["free", "pro"][i % 2])))
+            .collect();
+        let orders: String = std::iter::once("order,user_id,status\n".to_string())
+            .chain((0..600).map(|i| format!("{},customer-{:05},shipped\n", i, i * 3 % 300 * 7919 % 100_000)))
+            .collect();
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_level(9));
+        let documents = [
+            compressor.compress(&parse_csv(&users).unwrap()).unwrap(),
+            compressor.compress(&parse_csv(&orders).unwrap()).unwrap(),
+        ];
+        let texts: Vec<String> = documents.iter().map(|doc| AlsSerializer::new().serialize(doc)).collect();
+        let plain = container(&[("users", &texts[0]), ("orders", &texts[1])]);
+
+        let mut writer = AlsContainerWriter::new(Vec::new()).unwrap();
+        let shared = SharedDictionary::find(&documents).unwrap();
+        assert_eq!(shared.len(), 1);
+        writer.add_shared_dictionary(shared[0].clone()).unwrap();
+        assert!(matches!(writer.add_shared_dictionary(shared[0].clone()), Err(AlsError::DuplicateEntry { .. })));
+        writer.add("users", &texts[0]).unwrap();
+        writer.add("orders", &texts[1]).unwrap();
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert!(text.len() < plain.len() * 9 / 10, "{} vs {}", text.len(), plain.len());
+        assert!(text.contains("\n!toc\n$user_id "));
+        assert!(text.contains("$default^user_id"));
+
+        let reader = AlsContainerReader::new(&text).unwrap();
+        reader.verify().unwrap();
+        assert_eq!(reader.names().collect::<Vec<_>>(), ["users", "orders"]);
+        assert_eq!(reader.shared_entries().len(), 1);
+        let parser = AlsParser::new();
+        for (name, doc) in ["users", "orders"].iter().zip(&documents) {
+            let read = parser.parse(&reader.get(name).unwrap().unwrap()).unwrap();
+            assert_eq!(parser.expand(&read).unwrap(), parser.expand(doc).unwrap());
+        }
+
+        // A corrupt dictionary is caught through the documents using it
+        let corrupt = text.replacen("customer-", "customer_", 1);
+        let reader = AlsContainerReader::new(&corrupt).unwrap();
+        assert!(matches!(reader.get("orders"), Err(AlsError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_foreign_keys() {
+        use crate::{AlsCompressor, CompressorConfig};
+        use crate::convert::csv::parse_csv;
+
+        let ids: Vec<String> = (0..200).map(|i| format!("{:08x}-{:04x}", i * 2_654_435_761u64 % (1 << 32), i * 40_503 % 65_536)).collect();
+        let users: String = std::iter::once("id,manager\n".to_string())
+            .chain(ids.iter().enumerate().map(|(i, id)| format!("{},{}\n", id, ids[i / 10])))
+            .collect();
+        let orders: String = std::iter::once("order,user_id\n".to_string())
+            .chain((0..1000).map(|i| format!("{},{}\n", i, ids[i / 5])))
+            .collect();
+        let compressor = AlsCompressor::new();
+        let plain_users = compressor.compress(&parse_csv(&users).unwrap()).unwrap();
+        let plain_orders = compressor.compress(&parse_csv(&orders).unwrap()).unwrap();
+        let with_key = |column: &str| {
+            let config = CompressorConfig::new().with_foreign_key(column, ForeignKey::new("users", "id"), ids.clone());
+            AlsCompressor::with_config(config)
+        };
+        let keyed_users = with_key("manager").compress(&parse_csv(&users).unwrap()).unwrap();
+        let keyed_orders = with_key("user_id").compress(&parse_csv(&orders).unwrap()).unwrap();
+        let text = |doc: &AlsDocument| AlsSerializer::new().serialize(doc);
+        assert!(text(&keyed_orders).len() < text(&plain_orders).len() / 2);
+        assert!(text(&keyed_orders).ends_with("\n!fk user\\_id users id"));
+
+        let keyed = container(&[("users", &text(&keyed_users)), ("orders", &text(&keyed_orders))]);
+        let reader = AlsContainerReader::new(&keyed).unwrap();
+        let parser = AlsParser::new();
+        for (name, plain) in [("users", &plain_users), ("orders", &plain_orders)] {
+            let (_, columns) = reader.expand_columns(name).unwrap().unwrap();
+            assert_eq!(columns, parser.expand_columns(plain).unwrap());
+            let plain = text(plain);
+            assert_eq!(reader.to_csv(name).unwrap().unwrap(), parser.to_csv(&plain).unwrap());
+            assert_eq!(reader.to_json(name).unwrap().unwrap(), parser.to_json(&plain).unwrap());
+        }
+        // Read as text, children keep their row numbers
+        assert_eq!(reader.get("orders").unwrap().unwrap(), text(&keyed_orders));
+
+        // Children cannot be expanded without their parent
+        let orphaned = container(&[("orders", &text(&keyed_orders))]);
+        let reader = AlsContainerReader::new(&orphaned).unwrap();
+        assert!(matches!(reader.expand_columns("orders"), Err(AlsError::InvalidForeignKey { .. })));
+        let cycle = container(&[("users", &text(&with_key("id").compress(&parse_csv(&users).unwrap()).unwrap()))]);
+        let reader = AlsContainerReader::new(&cycle).unwrap();
+        assert!(matches!(reader.expand_columns("users"), Err(AlsError::InvalidForeignKey { .. })));
+    }
+
+    #[test]
+    fn test_dedup_small_documents() {
+        let mut writer = AlsContainerWriter::new(Vec::new()).unwrap().with_dedup(true);
+        writer.add("a", "#x\n1").unwrap();
+        writer.add("b", "#x\n1").unwrap();
+        writer.add("empty", "").unwrap();
+        assert_eq!(writer.deduplicated_bytes(), 4);
+        let text = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert!(text.contains("\nb 13 4 62100e94\n"));
+
+        let reader = AlsContainerReader::new(&text).unwrap();
+        assert_eq!(reader.get("b").unwrap().as_deref(), Some("#x\n1"));
+        assert_eq!(reader.get("empty").unwrap().as_deref(), Some(""));
+    }
+}
@@ -0,0 +1,141 @@
+//! Built-in calendar cycles.
+//!
+//! Calendar columns such as day-of-week or month names repeat a fixed,
+//! well-known sequence. Referring to the sequence by name (`&dow+0*30`)
+//! avoids spelling out every label in a toggle or a dictionary.
+
+use serde::{Deserialize, Serialize};
+
+/// A named, built-in cycle of calendar labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CalendarCycle {
+    /// Abbreviated day names: `Mon` through `Sun`.
+    Weekday,
+    /// Full day names: `Monday` through `Sunday`.
+    WeekdayFull,
+    /// Abbreviated month names: `Jan` through `Dec`.
+    Month,
+    /// Full month names: `January` through `December`.
+    MonthFull,
+    /// Quarter labels: `Q1` through `Q4`.
+    Quarter,
+}
+
+const WEEKDAYS: &[&str] = &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const WEEKDAYS_FULL: &[&str] = &[
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const MONTHS: &[&str] = &[
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const MONTHS_FULL: &[&str] = &[
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const QUARTERS: &[&str] = &["Q1", "Q2", "Q3", "Q4"];
+
+impl CalendarCycle {
+    /// All built-in cycles.
+    pub const ALL: [CalendarCycle; 5] = [
+        CalendarCycle::Weekday,
+        CalendarCycle::WeekdayFull,
+        CalendarCycle::Month,
+        CalendarCycle::MonthFull,
+        CalendarCycle::Quarter,
+    ];
+
+    /// Get the name used for this cycle in ALS text.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CalendarCycle::Weekday => "dow",
+            CalendarCycle::WeekdayFull => "dayname",
+            CalendarCycle::Month => "mon",
+            CalendarCycle::MonthFull => "monthname",
+            CalendarCycle::Quarter => "qtr",
+        }
+    }
+
+    /// Look up a cycle by its ALS name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cycle| cycle.name() == name)
+    }
+
+    /// Get the labels of this cycle in order.
+    pub fn values(&self) -> &'static [&'static str] {
+        match self {
+            CalendarCycle::Weekday => WEEKDAYS,
+            CalendarCycle::WeekdayFull => WEEKDAYS_FULL,
+            CalendarCycle::Month => MONTHS,
+            CalendarCycle::MonthFull => MONTHS_FULL,
+            CalendarCycle::Quarter => QUARTERS,
+        }
+    }
+
+    /// Get the position of a label within this cycle.
+    pub fn position(&self, value: &str) -> Option<usize> {
+        self.values().iter().position(|label| *label == value)
+    }
+
+    /// Get the position of the label of element `row`, for a cycle that
+    /// starts at element `offset` and holds each label for `run` elements.
+    ///
+    /// `run` must be at least 1. Any offset and row are accepted, so
+    /// offsets read from the input cannot overflow.
+    pub(crate) fn label_position(&self, offset: usize, run: usize, row: usize) -> usize {
+        let element = offset as u128 + row as u128;
+        (element / run as u128 % self.values().len() as u128) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_roundtrip() {
+        for cycle in CalendarCycle::ALL {
+            assert_eq!(CalendarCycle::from_name(cycle.name()), Some(cycle));
+        }
+        assert_eq!(CalendarCycle::from_name("week"), None);
+    }
+
+    #[test]
+    fn test_cycle_lengths() {
+        assert_eq!(CalendarCycle::Weekday.values().len(), 7);
+        assert_eq!(CalendarCycle::WeekdayFull.values().len(), 7);
+        assert_eq!(CalendarCycle::Month.values().len(), 12);
+        assert_eq!(CalendarCycle::MonthFull.values().len(), 12);
+        assert_eq!(CalendarCycle::Quarter.values().len(), 4);
+    }
+
+    #[test]
+    fn test_label_position() {
+        assert_eq!(CalendarCycle::Quarter.label_position(3, 2, 0), 1);
+        assert_eq!(CalendarCycle::Quarter.label_position(3, 2, 5), 0);
+        assert_eq!(CalendarCycle::Weekday.label_position(usize::MAX, 1, usize::MAX), (2 * (usize::MAX as u128) % 7) as usize);
+    }
+
+    #[test]
+    fn test_position() {
+        assert_eq!(CalendarCycle::Weekday.position("Wed"), Some(2));
+        assert_eq!(CalendarCycle::MonthFull.position("December"), Some(11));
+        assert_eq!(CalendarCycle::Quarter.position("Q5"), None);
+        assert_eq!(CalendarCycle::Weekday.position("mon"), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Timestamp formatting and parsing for date range operators.
+//!
+//! Timestamps are seconds since the Unix epoch in UTC. Formats use a
+//! strftime-like subset so date columns can be reproduced exactly:
+//!
+//! | Specifier | Meaning |
+//! |-----------|---------|
+//! | `%Y` | Year, at least 4 digits |
+//! | `%m` | Month, 2 digits |
+//! | `%d` | Day of month, 2 digits |
+//! | `%H` | Hour (24h), 2 digits |
+//! | `%M` | Minute, 2 digits |
+//! | `%S` | Second, 2 digits |
+//! | `%%` | Literal `%` |
+//!
+//! All other characters are copied literally.
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Format a timestamp using the given format string.
+///
+/// Returns `None` if the format contains an unsupported specifier.
+pub(crate) fn format_timestamp(timestamp: i64, format: &str) -> Option<String> {
+    let days = timestamp.div_euclid(SECONDS_PER_DAY);
+    let seconds_of_day = timestamp.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let hour = seconds_of_day / 3600;
+    let minute = (seconds_of_day % 3600) / 60;
+    let second = seconds_of_day % 60;
+
+    let mut output = String::with_capacity(format.len() + 8);
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'Y' => output.push_str(&format!("{:04}", year)),
+            'm' => output.push_str(&format!("{:02}", month)),
+            'd' => output.push_str(&format!("{:02}", day)),
+            'H' => output.push_str(&format!("{:02}", hour)),
+            'M' => output.push_str(&format!("{:02}", minute)),
+            'S' => output.push_str(&format!("{:02}", second)),
+            '%' => output.push('%'),
+            _ => return None,
+        }
+    }
+
+    Some(output)
+}
+
+/// Parse a value according to the given format string.
+///
+/// Parsing is strict: the value must match the format exactly, with
+/// fixed-width fields, so that formatting the result reproduces it.
+pub(crate) fn parse_timestamp(value: &str, format: &str) -> Option<i64> {
+    let bytes = value.as_bytes();
+    let mut pos = 0;
+    let (mut year, mut month, mut day) = (1970i64, 1i64, 1i64);
+    let (mut hour, mut minute, mut second) = (0i64, 0i64, 0i64);
+
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            let mut buf = [0u8; 4];
+            let literal = c.encode_utf8(&mut buf).as_bytes();
+            if !bytes[pos..].starts_with(literal) {
+                return None;
+            }
+            pos += literal.len();
+            continue;
+        }
+        match chars.next()? {
+            'Y' => year = read_digits(bytes, &mut pos, 4)?,
+            'm' => month = read_digits(bytes, &mut pos, 2)?,
+            'd' => day = read_digits(bytes, &mut pos, 2)?,
+            'H' => hour = read_digits(bytes, &mut pos, 2)?,
+            'M' => minute = read_digits(bytes, &mut pos, 2)?,
+            'S' => second = read_digits(bytes, &mut pos, 2)?,
+            '%' => {
+                if bytes.get(pos) != Some(&b'%') {
+                    return None;
+                }
+                pos += 1;
+            }
+            _ => return None,
+        }
+    }
+
+    if pos != bytes.len()
+        || !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+}
+
+/// Parse a date or timestamp written in one of the common ISO 8601 forms,
+/// such as `2024-03-01`, `2024-03-01 12:30` or
+/// `2024-03-01T12:30:00.250+02:00`.
+///
+/// Fractions of a second are dropped, and offsets are applied so the
+/// result is in UTC; times without an offset are taken as UTC.
+pub(crate) fn parse_datetime(value: &str) -> Option<i64> {
+    const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d", "%Y/%m/%d"];
+    if let Some(timestamp) = DATE_FORMATS.iter().find_map(|format| parse_timestamp(value, format)) {
+        return Some(timestamp);
+    }
+
+    let seconds = value.get(..19)?;
+    let timestamp = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| parse_timestamp(seconds, format))?;
+
+    let mut rest = &value[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+    let offset = match rest.as_bytes() {
+        [] | [b'Z'] => 0,
+        [sign @ (b'+' | b'-'), ..] => {
+            let zone = rest[1..].replace(':', "");
+            let bytes = zone.as_bytes();
+            if bytes.len() != 4 {
+                return None;
+            }
+            let mut pos = 0;
+            let hours = read_digits(bytes, &mut pos, 2)?;
+            let minutes = read_digits(bytes, &mut pos, 2)?;
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+    Some(timestamp - offset)
+}
+
+/// Read exactly `width` ASCII digits.
+fn read_digits(bytes: &[u8], pos: &mut usize, width: usize) -> Option<i64> {
+    let digits = bytes.get(*pos..*pos + width)?;
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    *pos += width;
+    Some(digits.iter().fold(0i64, |acc, d| acc * 10 + (d - b'0') as i64))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        _ => 28,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date for a number of days since 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_epoch() {
+        assert_eq!(format_timestamp(0, "%Y-%m-%d %H:%M:%S").unwrap(), "1970-01-01 00:00:00");
+    }
+
+    #[test]
+    fn test_format_known_dates() {
+        assert_eq!(format_timestamp(1_704_067_200, "%Y-%m-%d").unwrap(), "2024-01-01");
+        assert_eq!(format_timestamp(1_709_164_800, "%Y/%m/%d").unwrap(), "2024/02/29");
+        assert_eq!(format_timestamp(-86_400, "%Y-%m-%d").unwrap(), "1969-12-31");
+    }
+
+    #[test]
+    fn test_format_literal_percent_and_unknown_specifier() {
+        assert_eq!(format_timestamp(0, "%Y%%").unwrap(), "1970%");
+        assert!(format_timestamp(0, "%Q").is_none());
+        assert!(format_timestamp(0, "%").is_none());
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let formats = ["%Y-%m-%d", "%Y-%m-%dT%H:%M:%SZ", "%d/%m/%Y %H:%M"];
+        for &ts in &[0i64, 951_782_400, 1_704_067_200, 1_709_210_096 - 56] {
+            for format in formats {
+                let text = format_timestamp(ts, format).unwrap();
+                let parsed = parse_timestamp(&text, format).unwrap();
+                assert_eq!(format_timestamp(parsed, format).unwrap(), text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_dates() {
+        assert!(parse_timestamp("2023-02-29", "%Y-%m-%d").is_none());
+        assert!(parse_timestamp("2024-13-01", "%Y-%m-%d").is_none());
+        assert!(parse_timestamp("2024-1-01", "%Y-%m-%d").is_none());
+        assert!(parse_timestamp("2024-01-01 ", "%Y-%m-%d").is_none());
+        assert!(parse_timestamp("2024-01-01T25:00:00", "%Y-%m-%dT%H:%M:%S").is_none());
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let noon = 1_709_294_400; // 2024-03-01 12:00:00 UTC
+        assert_eq!(parse_datetime("2024-03-01"), Some(noon - 12 * 3600));
+        assert_eq!(parse_datetime("2024/03/01"), Some(noon - 12 * 3600));
+        assert_eq!(parse_datetime("2024-03-01 12:00"), Some(noon));
+        assert_eq!(parse_datetime("2024-03-01T12:00:00"), Some(noon));
+        assert_eq!(parse_datetime("2024-03-01T12:00:00.250Z"), Some(noon));
+        assert_eq!(parse_datetime("2024-03-01T14:00:00+02:00"), Some(noon));
+        assert_eq!(parse_datetime("2024-03-01T07:30:00.5-0430"), Some(noon));
+
+        for value in ["", "yesterday", "2024-03-01T12:00:00.", "2024-03-01T12:00:00+2", "2024-03-01T12:00:00 UTC", "2024-02-30"] {
+            assert_eq!(parse_datetime(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn test_parse_leap_day() {
+        assert_eq!(parse_timestamp("2024-02-29", "%Y-%m-%d"), Some(1_709_164_800));
+    }
+}
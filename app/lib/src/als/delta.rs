@@ -0,0 +1,298 @@
+//! Delta documents.
+//!
+//! A delta holds a snapshot of a table relative to an earlier (base)
+//! snapshot. Its column streams hold only the rows that are not in the
+//! base, and an edit script says how to rebuild the new snapshot from
+//! base rows and those inserted rows:
+//!
+//! ```text
+//! !delta 1000 5d2a7c1f c0+100 i3 c103+897
+//! ```
+//!
+//! The line holds the row count and checksum of the base, then the
+//! operations in order: `c<start>+<len>` copies base rows (`c<start>` copies
+//! one), and `i<count>` takes the next rows of the delta's own streams.
+//! Base rows that are never copied were deleted; a changed row shows up as
+//! a deleted base row replaced by an inserted one.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::container::{crc32, crc32_update};
+use super::trailer::TrailerLine;
+use crate::error::{AlsError, Result};
+
+/// Prefix of the edit script line of a delta.
+pub const DELTA_PREFIX: &str = "!delta ";
+
+/// One operation of a delta edit script.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeltaOp {
+    /// Copy a range of base rows.
+    Copy(Range<usize>),
+    /// Take the next rows inserted by the delta.
+    Insert(usize),
+}
+
+/// Edit script rebuilding a snapshot from its base and inserted rows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeltaScript {
+    /// Number of rows in the base.
+    pub base_rows: usize,
+    /// Checksum of the expanded base rows, see [`DeltaScript::checksum`].
+    pub base_checksum: u32,
+    /// Operations, in the order of the new rows.
+    pub ops: Vec<DeltaOp>,
+}
+
+impl DeltaScript {
+    /// Diff expanded rows against expanded base rows.
+    ///
+    /// Rows of `new` found anywhere in `base` are copied, preferring the
+    /// base row following the previous copy so runs stay long; all other
+    /// rows are inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::als::{DeltaOp, DeltaScript};
+    ///
+    /// let row = |s: &str| vec![s.to_string()];
+    /// let base = vec![row("a"), row("b"), row("c")];
+    /// let new = vec![row("a"), row("x"), row("c")];
+    /// let script = DeltaScript::diff(&base, &new);
+    /// assert_eq!(script.ops, vec![DeltaOp::Copy(0..1), DeltaOp::Insert(1), DeltaOp::Copy(2..3)]);
+    /// assert_eq!(script.deleted_rows(), 1);
+    /// ```
+    pub fn diff(base: &[Vec<String>], new: &[Vec<String>]) -> Self {
+        let mut positions: HashMap<&[String], Vec<usize>> = HashMap::new();
+        for (i, row) in base.iter().enumerate() {
+            positions.entry(row.as_slice()).or_default().push(i);
+        }
+
+        let mut ops = Vec::new();
+        let mut cursor = 0;
+        for row in new {
+            let matched = positions.get(row.as_slice()).and_then(|rows| {
+                let next = rows.partition_point(|&r| r < cursor);
+                rows.get(next).or(rows.first()).copied()
+            });
+            match (matched, ops.last_mut()) {
+                (Some(r), Some(DeltaOp::Copy(range))) if range.end == r => range.end += 1,
+                (Some(r), _) => ops.push(DeltaOp::Copy(r..r + 1)),
+                (None, Some(DeltaOp::Insert(count))) => *count += 1,
+                (None, _) => ops.push(DeltaOp::Insert(1)),
+            }
+            if let Some(r) = matched {
+                cursor = r + 1;
+            }
+        }
+
+        Self {
+            base_rows: base.len(),
+            base_checksum: Self::checksum(base),
+            ops,
+        }
+    }
+
+    /// Checksum of expanded rows, used to tell whether a delta was made
+    /// against a given base.
+    pub fn checksum(rows: &[Vec<String>]) -> u32 {
+        rows.iter().fold(crc32(b""), |crc, row| {
+            let crc = row.iter().fold(crc, |crc, value| {
+                crc32_update(crc32_update(crc, value.as_bytes()), b"\x1f")
+            });
+            crc32_update(crc, b"\n")
+        })
+    }
+
+    /// Get the number of rows inserted by the delta.
+    pub fn inserted_rows(&self) -> usize {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Insert(count) => *count,
+                DeltaOp::Copy(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Get the number of rows copied from the base.
+    pub fn copied_rows(&self) -> usize {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DeltaOp::Copy(range) => range.len(),
+                DeltaOp::Insert(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Get the number of base rows that are not copied.
+    pub fn deleted_rows(&self) -> usize {
+        let mut copied = vec![false; self.base_rows];
+        for op in &self.ops {
+            if let DeltaOp::Copy(range) = op {
+                for row in range.clone().filter(|&row| row < self.base_rows) {
+                    copied[row] = true;
+                }
+            }
+        }
+        copied.iter().filter(|&&copied| !copied).count()
+    }
+
+    /// Rebuild the new rows from base rows and inserted rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidDelta` if `base` is not the base of this
+    /// script or `inserted` has the wrong number of rows.
+    pub fn apply<T: Clone>(&self, base: &[T], inserted: &[T]) -> Result<Vec<T>> {
+        if base.len() != self.base_rows {
+            return Err(AlsError::InvalidDelta {
+                message: format!("base has {} rows, delta expects {}", base.len(), self.base_rows),
+            });
+        }
+        if inserted.len() != self.inserted_rows() {
+            return Err(AlsError::InvalidDelta {
+                message: format!(
+                    "delta holds {} rows, its script inserts {}",
+                    inserted.len(),
+                    self.inserted_rows()
+                ),
+            });
+        }
+
+        let mut rows = Vec::with_capacity(self.copied_rows() + inserted.len());
+        let mut next = 0;
+        for op in &self.ops {
+            match op {
+                DeltaOp::Copy(range) => {
+                    let copied = base.get(range.clone()).ok_or_else(|| AlsError::InvalidDelta {
+                        message: format!("copy of rows {:?} is outside the base", range),
+                    })?;
+                    rows.extend_from_slice(copied);
+                }
+                DeltaOp::Insert(count) => {
+                    rows.extend_from_slice(&inserted[next..next + count]);
+                    next += count;
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Write the edit script line, without a trailing newline.
+    pub(crate) fn write_line(&self, output: &mut String) {
+        output.push_str(DELTA_PREFIX);
+        output.push_str(&format!("{} {:08x}", self.base_rows, self.base_checksum));
+        for op in &self.ops {
+            output.push(' ');
+            match op {
+                DeltaOp::Copy(range) if range.len() == 1 => output.push_str(&format!("c{}", range.start)),
+                DeltaOp::Copy(range) => output.push_str(&format!("c{}+{}", range.start, range.len())),
+                DeltaOp::Insert(count) => output.push_str(&format!("i{}", count)),
+            }
+        }
+    }
+
+    /// Read a `!delta` line at offset `position` back into the edit script,
+    /// with the row count and checksum of the base it applies to.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<Self> {
+        let line = TrailerLine::new(line, DELTA_PREFIX, "delta", position)?;
+        let mut fields = line.body().split(' ');
+        let base_rows = fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(|| line.error("Delta line is missing the base row count"))?;
+        let base_checksum = fields
+            .next()
+            .filter(|field| field.len() == 8)
+            .and_then(|field| u32::from_str_radix(field, 16).ok())
+            .ok_or_else(|| line.error("Delta line is missing the base checksum"))?;
+
+        let ops = fields
+            .map(|field| {
+                let op = if let Some(range) = field.strip_prefix('c') {
+                    super::index::parse_range(range).map(DeltaOp::Copy)
+                } else if let Some(count) = field.strip_prefix('i') {
+                    count.parse().ok().filter(|&count| count > 0).map(DeltaOp::Insert)
+                } else {
+                    None
+                };
+                op.ok_or_else(|| line.error(format!("Invalid delta operation: {}", field)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            base_rows,
+            base_checksum,
+            ops,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(values: &[&str]) -> Vec<Vec<String>> {
+        values.iter().map(|v| vec![v.to_string(), "x".to_string()]).collect()
+    }
+
+    #[test]
+    fn test_diff_appends_and_deletes() {
+        let base = rows(&["a", "b", "c", "d"]);
+        let new = rows(&["a", "c", "d", "e", "f"]);
+        let script = DeltaScript::diff(&base, &new);
+        assert_eq!(script.ops, vec![DeltaOp::Copy(0..1), DeltaOp::Copy(2..4), DeltaOp::Insert(2)]);
+        assert_eq!(script.copied_rows(), 3);
+        assert_eq!(script.inserted_rows(), 2);
+        assert_eq!(script.deleted_rows(), 1);
+        assert_eq!(script.apply(&base, &rows(&["e", "f"])).unwrap(), new);
+    }
+
+    #[test]
+    fn test_diff_prefers_following_rows() {
+        let base = rows(&["a", "b", "a", "b"]);
+        let script = DeltaScript::diff(&base, &rows(&["a", "b", "b", "a"]));
+        assert_eq!(script.ops, vec![DeltaOp::Copy(0..2), DeltaOp::Copy(3..4), DeltaOp::Copy(0..1)]);
+        assert_eq!(script.deleted_rows(), 1);
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_ne!(DeltaScript::checksum(&rows(&["ab"])), DeltaScript::checksum(&rows(&["a", "b"])));
+        assert_eq!(DeltaScript::checksum(&rows(&["a"])), DeltaScript::checksum(&rows(&["a"])));
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let script = DeltaScript::diff(&rows(&["a", "b"]), &rows(&["b", "c"]));
+        assert!(script.apply(&rows(&["a"]), &rows(&["c"])).is_err());
+        assert!(script.apply(&rows(&["a", "b"]), &[]).is_err());
+    }
+
+    #[test]
+    fn test_line_roundtrip() {
+        let script = DeltaScript {
+            base_rows: 1000,
+            base_checksum: 0x5d2a_7c1f,
+            ops: vec![DeltaOp::Copy(0..100), DeltaOp::Insert(3), DeltaOp::Copy(103..104)],
+        };
+        let mut line = String::new();
+        script.write_line(&mut line);
+        assert_eq!(line, "!delta 1000 5d2a7c1f c0+100 i3 c103");
+        assert_eq!(DeltaScript::parse_line(&line, 0).unwrap(), script);
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert!(DeltaScript::parse_line("!delta 10", 0).is_err());
+        assert!(DeltaScript::parse_line("!delta 10 zz", 0).is_err());
+        assert!(DeltaScript::parse_line("!delta 10 00000000 i0", 0).is_err());
+        assert!(DeltaScript::parse_line("!delta 10 00000000 x3", 0).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Dictionary reference numbering.
+//!
+//! References to the first ten entries are written in decimal (`_0` to
+//! `_9`). Beyond that, a reference is a letter followed by base-62 digits
+//! (`0-9a-zA-Z`), numbered on from where the shorter ones end:
+//!
+//! ```text
+//! _9    entry 9
+//! _a    entry 10
+//! _Z    entry 61
+//! _a0   entry 62
+//! _ZZ   entry 3285
+//! _a00  entry 3286
+//! ```
+//!
+//! so a dictionary of 200,000 hostnames still only needs three-character
+//! references. Decimal references to any entry (`_1200`) remain valid
+//! input, and the serializer writes them when they are no longer (`_62`).
+//! Version 1 parsers read a base-62 reference as raw values, so documents
+//! using them declare the `ref62` feature.
+//!
+//! A reference may also be written relative to the previous reference of
+//! its stream: `_=` repeats it, `_+` and `_+N` refer to the entry one or
+//! `N` after it. Documents using them declare the `relative-refs` feature.
+
+/// Base-62 digits in value order.
+const DIGITS: &[u8; 62] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Number of letters a base-62 reference can start with.
+const LEADING: usize = 52;
+
+/// Encode a dictionary index as the shortest reference body (the part
+/// after `_`).
+///
+/// Ties go to the decimal spelling.
+pub(crate) fn encode(index: usize) -> String {
+    let decimal = index.to_string();
+    match encode_base62(index) {
+        Some(base62) if base62.len() < decimal.len() => base62,
+        _ => decimal,
+    }
+}
+
+/// Check whether [`encode`] writes `index` in base 62.
+pub(crate) fn is_base62(index: usize) -> bool {
+    encode_base62(index).is_some_and(|base62| base62.len() < index.to_string().len())
+}
+
+/// Encode `index` relative to the previous reference `last` (`=`, `+` or
+/// `+N`), if that is shorter than [`encode`].
+pub(crate) fn relative(index: usize, last: Option<usize>) -> Option<String> {
+    let relative = match index.checked_sub(last?)? {
+        0 => "=".to_string(),
+        1 => "+".to_string(),
+        delta => format!("+{}", delta),
+    };
+    (relative.len() < len(index)).then_some(relative)
+}
+
+/// Length of the reference body [`encode`] writes for `index`.
+pub(crate) fn len(index: usize) -> usize {
+    encode(index).len()
+}
+
+/// Decode a reference body made of ASCII letters and digits.
+///
+/// Bodies starting with a digit are decimal, those starting with a letter
+/// base-62. Returns `None` for anything else or on overflow.
+pub(crate) fn decode(body: &str) -> Option<usize> {
+    let first = *body.as_bytes().first()?;
+    if first.is_ascii_digit() {
+        return body.bytes().all(|b| b.is_ascii_digit()).then(|| body.parse().ok()).flatten();
+    }
+
+    let mut value = digit_value(first)?.checked_sub(10)?;
+    let mut offset = 10usize;
+    let mut block = LEADING;
+    for byte in body.bytes().skip(1) {
+        offset = offset.checked_add(block)?;
+        block = block.saturating_mul(62);
+        value = value.checked_mul(62)?.checked_add(digit_value(byte)?)?;
+    }
+    offset.checked_add(value)
+}
+
+/// Number of dictionary entries whose references fit in `width`
+/// characters after the `_`.
+pub(crate) fn capacity(width: usize) -> usize {
+    if width == 0 {
+        return 0;
+    }
+    let mut capacity = 10usize;
+    let mut block = LEADING;
+    for _ in 0..width {
+        capacity = capacity.saturating_add(block);
+        block = block.saturating_mul(62);
+    }
+    capacity
+}
+
+/// Base-62 spelling of `index`, or `None` for the decimal-only indices.
+fn encode_base62(index: usize) -> Option<String> {
+    let mut rest = index.checked_sub(10)?;
+    let mut block = LEADING;
+    let mut width = 1;
+    while rest >= block {
+        rest -= block;
+        block = block.saturating_mul(62);
+        width += 1;
+    }
+
+    let mut digits = vec![0u8; width];
+    for digit in digits.iter_mut().skip(1).rev() {
+        *digit = DIGITS[rest % 62];
+        rest /= 62;
+    }
+    digits[0] = DIGITS[10 + rest];
+    Some(digits.into_iter().map(char::from).collect())
+}
+
+fn digit_value(byte: u8) -> Option<usize> {
+    match byte {
+        b'0'..=b'9' => Some((byte - b'0') as usize),
+        b'a'..=b'z' => Some((byte - b'a') as usize + 10),
+        b'A'..=b'Z' => Some((byte - b'A') as usize + 36),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_picks_shortest() {
+        assert_eq!(encode(0), "0");
+        assert_eq!(encode(9), "9");
+        assert_eq!(encode(10), "a");
+        assert_eq!(encode(61), "Z");
+        assert_eq!(encode(62), "62");
+        assert_eq!(encode(99), "99");
+        assert_eq!(encode(100), "aC");
+        assert_eq!(encode(124), "b0");
+        assert_eq!(encode(3285), "ZZ");
+        assert_eq!(encode(3286), "a00");
+        assert_eq!(len(199_999), 3);
+
+        assert!(!is_base62(9) && is_base62(10) && is_base62(61));
+        assert!(!is_base62(62) && !is_base62(99) && is_base62(100));
+    }
+
+    #[test]
+    fn test_relative() {
+        assert_eq!(relative(62, Some(62)), Some("=".to_string()));
+        assert_eq!(relative(63, Some(62)), Some("+".to_string()));
+        assert_eq!(relative(5005, Some(5000)), Some("+5".to_string()));
+        assert_eq!(relative(1205, Some(1200)), None);
+        assert_eq!(relative(4, Some(3)), None);
+        assert_eq!(relative(62, Some(63)), None);
+        assert_eq!(relative(62, None), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("0"), Some(0));
+        assert_eq!(decode("1200"), Some(1200));
+        assert_eq!(decode("a"), Some(10));
+        assert_eq!(decode("ZZ"), Some(3285));
+        assert_eq!(decode("1a"), None);
+        assert_eq!(decode("a-"), None);
+        assert_eq!(decode(""), None);
+        assert_eq!(decode("ZZZZZZZZZZZZZZZZZZZZ"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for index in (0..250_000).chain([usize::MAX / 2, usize::MAX]) {
+            assert_eq!(decode(&encode(index)), Some(index), "index {}", index);
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        assert_eq!(capacity(0), 0);
+        assert_eq!(capacity(1), 62);
+        assert_eq!(capacity(2), 3286);
+        assert!(len(capacity(3) - 1) == 3 && len(capacity(3)) == 4);
+        assert_eq!(capacity(64), usize::MAX);
+    }
+}
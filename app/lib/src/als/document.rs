@@ -3,9 +3,20 @@
 //! This module defines the `AlsDocument` struct which represents a complete
 //! ALS compressed document, including dictionaries, schema, and column streams.
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 
-use super::AlsOperator;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::{AlsOperator, BloomFilter, ColumnIndex, DeltaScript, FileMetadata, ForeignKey, Quantization, Redaction, TimeTruncation, ZoneMap};
+
+/// Prefix of the line recording the rows per block.
+pub(crate) const BLOCKS_PREFIX: &str = "!blocks ";
+
+/// Line recording that text was normalized to Unicode NFC.
+pub(crate) const NFC_LINE: &str = "!nfc";
 
 /// Represents a complete ALS document.
 ///
@@ -24,9 +35,25 @@ use super::AlsOperator;
 ///
 /// Note that while the document can be shared, concurrent mutation requires
 /// external synchronization. For read-only access, no synchronization is needed.
-#[derive(Debug, Clone, PartialEq)]
-pub struct AlsDocument {
+///
+/// # Equality
+///
+/// Documents compare and hash by their canonical form (see
+/// [`canonicalize`](Self::canonicalize)), so `1>5` equals `1>3 4>5` and
+/// dictionary order does not matter.
+///
+/// # Serde
+///
+/// Documents implement `Serialize` and `Deserialize` field for field, so
+/// they can be embedded in JSON or CBOR envelopes, cached, or compared in
+/// snapshot tests without going through the ALS text syntax. Deserialized
+/// documents own their values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlsDocument<'a> {
     /// ALS format version (currently 1).
+    ///
+    /// Documents using any [`Feature`](super::Feature) are serialized as
+    /// version 2, listing the features in their header.
     pub version: u8,
 
     /// Dictionaries for string deduplication.
@@ -43,13 +70,74 @@ pub struct AlsDocument {
     /// Column streams containing compressed data.
     ///
     /// Each stream corresponds to a column in the schema.
-    pub streams: Vec<ColumnStream>,
+    pub streams: Vec<ColumnStream<'a>>,
 
     /// Format indicator distinguishing ALS from CTX fallback.
     pub format_indicator: FormatIndicator,
+
+    /// Value indexes, keyed by column position.
+    ///
+    /// Indexed columns can answer equality lookups without being expanded.
+    pub indexes: BTreeMap<usize, ColumnIndex>,
+
+    /// Rows per block, when the document was compressed in block mode.
+    ///
+    /// Each block is encoded on its own, so no operator spans a block
+    /// boundary and blocks can be expanded independently.
+    pub block_size: Option<usize>,
+
+    /// Per-block Bloom filters, keyed by column position.
+    ///
+    /// Only present in block mode; holds one filter per block.
+    pub blooms: BTreeMap<usize, Vec<BloomFilter>>,
+
+    /// Per-block min/max zone maps, keyed by column position.
+    ///
+    /// Holds one zone map per block, or a single one outside block mode.
+    pub zone_maps: BTreeMap<usize, Vec<ZoneMap>>,
+
+    /// Edit script against a base document, when this document is a delta.
+    ///
+    /// The streams of a delta hold only the rows it inserts.
+    pub delta: Option<DeltaScript>,
+
+    /// Whether column names and string values were normalized to Unicode
+    /// NFC when the document was compressed.
+    pub nfc_normalized: bool,
+
+    /// Redactions the compressor was configured with, keyed by column name.
+    ///
+    /// Dropped columns are absent from the schema; hashed and masked
+    /// columns hold the redacted values.
+    pub redactions: BTreeMap<String, Redaction>,
+
+    /// Quantizations the compressor was configured with, keyed by column
+    /// name.
+    ///
+    /// The floats of these columns were rounded and are not the originals.
+    pub quantizations: BTreeMap<String, Quantization>,
+
+    /// Timestamp truncations the compressor was configured with, keyed by
+    /// column name.
+    ///
+    /// The times of these columns were rounded down to this resolution.
+    pub truncations: BTreeMap<String, TimeTruncation>,
+
+    /// Foreign keys the compressor was configured with, keyed by the name
+    /// of the child column.
+    ///
+    /// These columns hold row numbers into the parent's key column rather
+    /// than the keys themselves.
+    pub foreign_keys: BTreeMap<String, ForeignKey>,
+
+    /// Metadata of the file the document was compressed from, if recorded.
+    ///
+    /// It describes where the data came from, so documents compare equal
+    /// without regard to it.
+    pub file_metadata: Option<FileMetadata>,
 }
 
-impl AlsDocument {
+impl<'a> AlsDocument<'a> {
     /// Current ALS format version.
     pub const CURRENT_VERSION: u8 = 1;
 
@@ -61,6 +149,17 @@ impl AlsDocument {
             schema: Vec::new(),
             streams: Vec::new(),
             format_indicator: FormatIndicator::Als,
+            indexes: BTreeMap::new(),
+            block_size: None,
+            blooms: BTreeMap::new(),
+            zone_maps: BTreeMap::new(),
+            delta: None,
+            nfc_normalized: false,
+            redactions: BTreeMap::new(),
+            quantizations: BTreeMap::new(),
+            truncations: BTreeMap::new(),
+            foreign_keys: BTreeMap::new(),
+            file_metadata: None,
         }
     }
 
@@ -76,6 +175,42 @@ impl AlsDocument {
             schema: schema.into_iter().map(|s| s.into()).collect(),
             streams: Vec::new(),
             format_indicator: FormatIndicator::Als,
+            indexes: BTreeMap::new(),
+            block_size: None,
+            blooms: BTreeMap::new(),
+            zone_maps: BTreeMap::new(),
+            delta: None,
+            nfc_normalized: false,
+            redactions: BTreeMap::new(),
+            quantizations: BTreeMap::new(),
+            truncations: BTreeMap::new(),
+            foreign_keys: BTreeMap::new(),
+            file_metadata: None,
+        }
+    }
+
+    /// Convert into a document that owns all of its values.
+    ///
+    /// Documents built by compression may borrow raw values from the input
+    /// data; this copies them so the document outlives the input.
+    pub fn into_owned(self) -> AlsDocument<'static> {
+        AlsDocument {
+            version: self.version,
+            dictionaries: self.dictionaries,
+            schema: self.schema,
+            streams: self.streams.into_iter().map(ColumnStream::into_owned).collect(),
+            format_indicator: self.format_indicator,
+            indexes: self.indexes,
+            block_size: self.block_size,
+            blooms: self.blooms,
+            zone_maps: self.zone_maps,
+            delta: self.delta,
+            nfc_normalized: self.nfc_normalized,
+            redactions: self.redactions,
+            quantizations: self.quantizations,
+            truncations: self.truncations,
+            foreign_keys: self.foreign_keys,
+            file_metadata: self.file_metadata,
         }
     }
 
@@ -94,10 +229,87 @@ impl AlsDocument {
     /// # Arguments
     ///
     /// * `stream` - The column stream to add
-    pub fn add_stream(&mut self, stream: ColumnStream) {
+    pub fn add_stream(&mut self, stream: ColumnStream<'a>) {
         self.streams.push(stream);
     }
 
+    /// Add a value index for the column at `column`.
+    pub fn add_index(&mut self, column: usize, index: ColumnIndex) {
+        self.indexes.insert(column, index);
+    }
+
+    /// Get the value index of the column at `column`, if it has one.
+    pub fn index(&self, column: usize) -> Option<&ColumnIndex> {
+        self.indexes.get(&column)
+    }
+
+    /// Get the number of blocks, or 1 outside block mode.
+    pub fn block_count(&self) -> usize {
+        match self.block_size {
+            Some(size) if size > 0 => self.row_count().div_ceil(size).max(1),
+            _ => 1,
+        }
+    }
+
+    /// Get the rows covered by `block`.
+    pub fn block_rows(&self, block: usize) -> Range<usize> {
+        let row_count = self.row_count();
+        match self.block_size {
+            Some(size) if size > 0 => (block * size).min(row_count)..((block + 1) * size).min(row_count),
+            _ => 0..row_count,
+        }
+    }
+
+    /// Get the operators of the column at `column` that encode `block`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::AlsSyntaxError` if an operator crosses the block
+    /// boundary, which means the document was not written in block mode.
+    pub fn block_stream(&self, column: usize, block: usize) -> crate::error::Result<ColumnStream<'a>> {
+        let stream = self.streams.get(column).ok_or(crate::error::AlsError::InvalidColumnRef {
+            index: column,
+            columns: self.streams.len(),
+        })?;
+        let rows = self.block_rows(block);
+        let misaligned = || crate::error::AlsError::AlsSyntaxError {
+            position: 0,
+            message: format!("Column {} has an operator crossing block {}", column, block),
+        };
+
+        let mut row = 0;
+        let mut operators = Vec::new();
+        for op in &stream.operators {
+            if row >= rows.end {
+                break;
+            }
+            let next = row + op.expanded_count();
+            if next > rows.start {
+                if row < rows.start || next > rows.end {
+                    return Err(misaligned());
+                }
+                operators.push(op.clone());
+            }
+            row = next;
+        }
+
+        Ok(ColumnStream {
+            operators,
+            format_indicator: stream.format_indicator,
+        })
+    }
+
+    /// Get the zone map of the whole column at `column`, merged from the
+    /// zone maps of its blocks.
+    pub fn column_zone(&self, column: usize) -> Option<ZoneMap> {
+        let (first, rest) = self.zone_maps.get(&column)?.split_first()?;
+        let mut zone = first.clone();
+        for block in rest {
+            zone.merge(block);
+        }
+        Some(zone)
+    }
+
     /// Get the number of columns in the document.
     pub fn column_count(&self) -> usize {
         self.schema.len()
@@ -171,7 +383,7 @@ impl AlsDocument {
     }
 }
 
-impl Default for AlsDocument {
+impl Default for AlsDocument<'_> {
     fn default() -> Self {
         Self::new()
     }
@@ -181,27 +393,60 @@ impl Default for AlsDocument {
 ///
 /// Contains a sequence of operators that, when expanded, produce
 /// the column's values.
-#[derive(Debug, Clone, PartialEq)]
-pub struct ColumnStream {
+///
+/// Streams compare and hash by their canonical operators.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnStream<'a> {
     /// Operators that produce this column's values when expanded.
-    pub operators: Vec<AlsOperator>,
+    pub operators: Vec<AlsOperator<'a>>,
+    /// Format of this column.
+    ///
+    /// A CTX column stores its values verbatim, as raw values only. It is
+    /// written with a leading `!ctx` marker inside an ALS document.
+    pub format_indicator: FormatIndicator,
 }
 
-impl ColumnStream {
+impl<'a> ColumnStream<'a> {
     /// Create a new empty column stream.
     pub fn new() -> Self {
+        Self::from_operators(Vec::new())
+    }
+
+    /// Create a column stream from a vector of operators.
+    pub fn from_operators(operators: Vec<AlsOperator<'a>>) -> Self {
         Self {
-            operators: Vec::new(),
+            operators,
+            format_indicator: FormatIndicator::Als,
         }
     }
 
-    /// Create a column stream from a vector of operators.
-    pub fn from_operators(operators: Vec<AlsOperator>) -> Self {
-        Self { operators }
+    /// Create a CTX column stream holding the given values verbatim.
+    pub fn ctx<I, S>(values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Cow<'a, str>>,
+    {
+        Self {
+            operators: values.into_iter().map(AlsOperator::raw).collect(),
+            format_indicator: FormatIndicator::Ctx,
+        }
+    }
+
+    /// Convert into a stream whose operators own all of their values.
+    pub fn into_owned(self) -> ColumnStream<'static> {
+        ColumnStream {
+            operators: self.operators.into_iter().map(AlsOperator::into_owned).collect(),
+            format_indicator: self.format_indicator,
+        }
+    }
+
+    /// Check if the column stores its values verbatim.
+    pub fn is_ctx(&self) -> bool {
+        self.format_indicator == FormatIndicator::Ctx
     }
 
     /// Add an operator to the stream.
-    pub fn push(&mut self, operator: AlsOperator) {
+    pub fn push(&mut self, operator: AlsOperator<'a>) {
         self.operators.push(operator);
     }
 
@@ -212,7 +457,33 @@ impl ColumnStream {
 
     /// Calculate the total number of values this stream will produce when expanded.
     pub fn expanded_count(&self) -> usize {
-        self.operators.iter().map(|op| op.expanded_count()).sum()
+        self.operators.iter().fold(0, |total, op| total.saturating_add(op.expanded_count()))
+    }
+
+    /// Calculate the number of values the operators `start..end` produce
+    /// when expanded, without expanding them.
+    ///
+    /// Bounds past the last operator are clamped, so
+    /// `expanded_len_between(0, i)` is the row operator `i` starts at.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let doc = AlsParser::new().parse("#level\ninfo*50 warn debug~trace*4").unwrap();
+    /// let stream = &doc.streams[0];
+    /// assert_eq!(stream.expanded_len_between(1, 3), 5);
+    /// assert_eq!(stream.expanded_len_between(0, 2), 51);
+    /// assert_eq!(stream.expanded_len_between(2, 10), 4);
+    /// ```
+    pub fn expanded_len_between(&self, start: usize, end: usize) -> usize {
+        let end = end.min(self.operators.len());
+        self.operators
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            .fold(0, |total, op| total.saturating_add(op.expanded_count()))
     }
 
     /// Check if the stream is empty.
@@ -236,26 +507,62 @@ impl ColumnStream {
         }
         Ok(result)
     }
+
+    /// Check if any operator in the stream references another column.
+    pub fn has_column_refs(&self) -> bool {
+        self.operators.iter().any(|op| op.is_column_ref())
+    }
+
+    /// Expand all operators, resolving column references.
+    ///
+    /// `columns` holds the already expanded values of the columns that
+    /// precede this one; a `ColumnRef` may only point into that slice.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidColumnRef` if a reference points outside
+    /// `columns`, or if the referenced column is too short.
+    pub fn expand_with_columns(
+        &self,
+        dictionary: Option<&[String]>,
+        columns: &[Vec<String>],
+    ) -> crate::error::Result<Vec<String>> {
+        let mut result = Vec::with_capacity(self.expanded_count());
+        for op in &self.operators {
+            if let AlsOperator::ColumnRef { column, count, .. } = op {
+                let invalid = || crate::error::AlsError::InvalidColumnRef {
+                    index: *column,
+                    columns: columns.len(),
+                };
+                let rows = columns
+                    .get(*column)
+                    .and_then(|source| source.get(result.len()..result.len() + count))
+                    .ok_or_else(invalid)?;
+                result.extend(op.expand_column_ref(rows, dictionary)?);
+            } else {
+                result.extend(op.expand(dictionary)?);
+            }
+        }
+        Ok(result)
+    }
 }
 
-impl Default for ColumnStream {
+impl Default for ColumnStream<'_> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl FromIterator<AlsOperator> for ColumnStream {
-    fn from_iter<I: IntoIterator<Item = AlsOperator>>(iter: I) -> Self {
-        Self {
-            operators: iter.into_iter().collect(),
-        }
+impl<'a> FromIterator<AlsOperator<'a>> for ColumnStream<'a> {
+    fn from_iter<I: IntoIterator<Item = AlsOperator<'a>>>(iter: I) -> Self {
+        Self::from_operators(iter.into_iter().collect())
     }
 }
 
-/// Format indicator for ALS documents.
+/// Format indicator for ALS documents and their columns.
 ///
 /// Distinguishes between full ALS compression and CTX fallback format.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub enum FormatIndicator {
     /// Full ALS compression with pattern operators.
     #[default]
@@ -280,6 +587,31 @@ impl FormatIndicator {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_serde_roundtrip() {
+        use crate::als::{AlsParser, AlsSerializer};
+        use crate::{AlsCompressor, CompressorConfig};
+
+        let csv = "id,level,email\n1,info,ann@example.com\n2,warn,bob@example.com\n3,info,cy@example.com";
+        let data = crate::convert::csv::parse_csv(csv).unwrap();
+        let config = CompressorConfig::new().with_build_indexes(true).with_column_redaction("email", Redaction::Mask);
+        let indexed = AlsCompressor::with_config(config.clone()).compress(&data).unwrap();
+        let blocked = AlsCompressor::with_config(config.with_block_size(2).with_zone_maps(true)).compress(&data).unwrap();
+        let delta = AlsCompressor::new().compress_delta(&indexed, &data).unwrap();
+        assert!(!indexed.indexes.is_empty() && !indexed.redactions.is_empty());
+        assert!(!blocked.blooms.is_empty() && !blocked.zone_maps.is_empty());
+
+        let spec = AlsParser::new().parse("$default:x|y\n#a #b #c\n^ID{U3}(1>3)|&dow+1:2*3|_0 _1*2").unwrap();
+        let docs = [indexed, blocked, delta, spec];
+
+        for doc in docs {
+            let json = serde_json::to_string(&doc).unwrap();
+            let decoded: AlsDocument = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, doc);
+            assert_eq!(AlsSerializer::new().serialize(&decoded), AlsSerializer::new().serialize(&doc));
+        }
+    }
+
     #[test]
     fn test_als_document_new() {
         let doc = AlsDocument::new();
@@ -382,6 +714,14 @@ mod tests {
         assert_eq!(stream.expanded_count(), 4); // 3 from range + 1 from raw
     }
 
+    #[test]
+    fn test_column_stream_ctx() {
+        let stream = ColumnStream::ctx(["a", "b"]);
+        assert!(stream.is_ctx());
+        assert_eq!(stream.operators, vec![AlsOperator::raw("a"), AlsOperator::raw("b")]);
+        assert!(!ColumnStream::new().is_ctx());
+    }
+
     #[test]
     fn test_column_stream_push() {
         let mut stream = ColumnStream::new();
@@ -423,6 +763,46 @@ mod tests {
         assert_eq!(stream.operator_count(), 2);
     }
 
+    #[test]
+    fn test_column_stream_expand_with_columns() {
+        let stream = ColumnStream::from_operators(vec![
+            AlsOperator::raw("first"),
+            AlsOperator::column_ref(0, vec![("b".to_string(), "B".to_string())], 2),
+        ]);
+        assert!(stream.has_column_refs());
+        assert_eq!(stream.expanded_count(), 3);
+
+        let columns = vec![vec!["a".to_string(), "b".to_string(), "c".to_string()]];
+        let values = stream.expand_with_columns(None, &columns).unwrap();
+        assert_eq!(values, vec!["first", "B", "c"]);
+    }
+
+    #[test]
+    fn test_column_stream_expanded_len_between() {
+        let stream = ColumnStream::from_operators(vec![
+            AlsOperator::range(1, 3),
+            AlsOperator::multiply(AlsOperator::raw("x"), 2),
+            AlsOperator::toggle("a", "b", 4),
+        ]);
+        for start in 0..=4 {
+            for end in 0..=4 {
+                let expected: usize = stream.operators.iter().take(end).skip(start).map(|op| op.expanded_count()).sum();
+                assert_eq!(stream.expanded_len_between(start, end), expected);
+            }
+        }
+        assert_eq!(stream.expanded_len_between(0, usize::MAX), stream.expanded_count());
+        assert_eq!(stream.expanded_len_between(3, 1), 0);
+        assert_eq!(ColumnStream::new().expanded_len_between(0, 1), 0);
+    }
+
+    #[test]
+    fn test_column_stream_expand_with_missing_column() {
+        let stream = ColumnStream::from_operators(vec![AlsOperator::column_ref(1, Vec::new(), 1)]);
+        let columns = vec![vec!["a".to_string()]];
+        assert!(stream.expand_with_columns(None, &columns).is_err());
+        assert!(stream.expand(None).is_err());
+    }
+
     #[test]
     fn test_format_indicator_version_prefix() {
         assert_eq!(FormatIndicator::Als.version_prefix(), "!v");
@@ -443,8 +823,8 @@ mod tests {
     #[test]
     fn test_types_are_send_sync() {
         fn assert_send_sync<T: Send + Sync>() {}
-        assert_send_sync::<AlsDocument>();
-        assert_send_sync::<ColumnStream>();
+        assert_send_sync::<AlsDocument<'static>>();
+        assert_send_sync::<ColumnStream<'static>>();
         assert_send_sync::<FormatIndicator>();
     }
 }
@@ -0,0 +1,161 @@
+//! Serializing documents for older parsers.
+//!
+//! Archives outlive the software reading them, and a consumer still running
+//! a version 1 parser cannot read date ranges, column references or any
+//! other [`Feature`]. [`AlsSerializer::serialize_as_version`] writes a
+//! document as an older version would have, expanding the operators that
+//! version lacks into raw values and spelling dictionaries and references
+//! plainly: the output is larger but any parser of that version reads the
+//! same values.
+
+use super::document::{AlsDocument, ColumnStream};
+use super::feature::{operator_uses_features, Feature};
+use super::operator::AlsOperator;
+use super::parser::AlsParser;
+use super::scale::repeat;
+use super::serializer::AlsSerializer;
+use crate::config::ParserConfig;
+use crate::error::{AlsError, Result};
+
+impl AlsSerializer {
+    /// Serialize a document for parsers of format `version`.
+    ///
+    /// Version 2 lists the document's feature flags in its header. Version
+    /// 1 has no feature flags, so operators needing one are expanded into
+    /// raw values, with runs of a value written as repeats, dictionaries
+    /// are written without front-coding, references as absolute decimal
+    /// indices (`_12`), and the lines
+    /// after the streams are left out: block layout, Bloom filters, zone
+    /// maps and indexes only speed up queries, and the values already are
+    /// as normalized, redacted, quantized or truncated as the lines say.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, AlsSerializer};
+    ///
+    /// let doc = AlsParser::new().parse("#day #n\n%{%Y-%m-%d}1704067200+86400*3|?T~F:1.2\n!blocks 2").unwrap();
+    /// let v1 = AlsSerializer::new().serialize_as_version(&doc, 1).unwrap();
+    /// assert_eq!(v1, "!v1\n#day #n\n2024-01-01 2024-01-02 2024-01-03|T F*2");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::VersionMismatch` for a version other than 1 up to
+    /// `AlsParser::MAX_SUPPORTED_VERSION`, `AlsError::InvalidDelta` for a
+    /// delta written as version 1, whose edit script only makes sense with
+    /// its base, and any error from expanding the operators.
+    pub fn serialize_as_version(&self, doc: &AlsDocument, version: u8) -> Result<String> {
+        if !(1..=AlsParser::MAX_SUPPORTED_VERSION).contains(&version) {
+            return Err(AlsError::VersionMismatch {
+                expected: AlsParser::MAX_SUPPORTED_VERSION,
+                found: version,
+            });
+        }
+        if version >= 2 {
+            let mut doc = doc.clone();
+            doc.version = version;
+            return Ok(self.serialize(&doc));
+        }
+        if doc.features().contains(&Feature::Delta) {
+            return Err(AlsError::InvalidDelta {
+                message: "a delta cannot be written as version 1".to_string(),
+            });
+        }
+
+        let mut downgraded = AlsDocument::with_schema(doc.schema.clone());
+        downgraded.version = version;
+        downgraded.dictionaries = doc.dictionaries.clone();
+        downgraded.format_indicator = doc.format_indicator;
+        let needs_expansion = doc.streams.iter().flat_map(|stream| &stream.operators).any(operator_uses_features);
+        if !needs_expansion {
+            downgraded.streams = doc.streams.clone();
+            return Ok(self.serialize_without_features(&downgraded));
+        }
+
+        // Column references need the columns before them, so expand them all
+        let parser = AlsParser::with_config(ParserConfig::new().with_max_range_expansion(usize::MAX));
+        let columns = parser.expand_columns(doc)?;
+        downgraded.streams = doc
+            .streams
+            .iter()
+            .zip(&columns)
+            .map(|(stream, values)| {
+                let mut operators = Vec::with_capacity(stream.operators.len());
+                let mut position = 0;
+                for op in &stream.operators {
+                    let len = op.expanded_count();
+                    if operator_uses_features(op) {
+                        let values = &values[position..position + len];
+                        for run in values.chunk_by(|a, b| a == b) {
+                            operators.push(repeat(AlsOperator::raw(run[0].clone()), run.len()));
+                        }
+                    } else {
+                        operators.push(op.clone());
+                    }
+                    position += len;
+                }
+                ColumnStream {
+                    operators,
+                    format_indicator: stream.format_indicator,
+                }
+            })
+            .collect();
+        Ok(self.serialize_without_features(&downgraded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downgrade_expands_features() {
+        let als = "$default:buy|sell\n#id #side #sign #code #ts\n\
+                   1>4|_0 _1*2 _0|@1(buy:+1 sell:-1)*4|^ID{U4}(7>10)|&dow+0:2*4\n!blocks 2\n!nfc";
+        let parser = AlsParser::new();
+        let doc = parser.parse(als).unwrap();
+
+        let v1 = AlsSerializer::new().serialize_as_version(&doc, 1).unwrap();
+        assert_eq!(v1, "!v1\n$default:buy|sell\n#id #side #sign #code #ts\n1>4|_0 _1*2 _0|+1 -1*2 +1|ID0007 ID0008 ID0009 ID0010|Mon*2 Tue*2");
+        let reparsed = parser.parse(&v1).unwrap();
+        assert!(reparsed.features().is_empty());
+        assert_eq!(parser.expand_columns(&reparsed).unwrap(), parser.expand_columns(&doc).unwrap());
+    }
+
+    #[test]
+    fn test_downgrade_respells_dictionaries_and_references() {
+        let hosts: Vec<String> = (0..40).map(|i| format!("web-{:02}.eu-west-1.example.com", i)).collect();
+        let mut doc = AlsDocument::with_schema(vec!["host"]);
+        doc.dictionaries.insert("default".to_string(), hosts.clone());
+        doc.add_stream(ColumnStream::from_operators(
+            [0, 12, 12, 13, 39].into_iter().map(AlsOperator::dict_ref).collect(),
+        ));
+        let serializer = AlsSerializer::new();
+        assert!(serializer.serialize(&doc).starts_with("!v2 +ref62 +front-coding\n$default~:"));
+
+        let v1 = serializer.serialize_as_version(&doc, 1).unwrap();
+        assert_eq!(v1, format!("!v1\n$default:{}\n#host\n_0 _12 _12 _13 _39", hosts.join("|")));
+        let parser = AlsParser::new();
+        assert_eq!(parser.expand_columns(&parser.parse(&v1).unwrap()).unwrap(), parser.expand_columns(&doc).unwrap());
+    }
+
+    #[test]
+    fn test_downgrade_versions() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#id #ok\n1>3|?T~F:1.2").unwrap();
+        let serializer = AlsSerializer::new();
+
+        assert_eq!(serializer.serialize_as_version(&doc, 2).unwrap(), "!v2 +bool-runs\n#id #ok\n1>3|?T~F:1.2");
+        assert!(matches!(serializer.serialize_as_version(&doc, 3), Err(AlsError::VersionMismatch { found: 3, .. })));
+        assert!(matches!(serializer.serialize_as_version(&doc, 0), Err(AlsError::VersionMismatch { found: 0, .. })));
+
+        // Documents without features only lose their trailer lines
+        let plain = parser.parse("#id\n1>3\n!blocks 2").unwrap();
+        assert_eq!(serializer.serialize_as_version(&plain, 1).unwrap(), "!v1\n#id\n1>3");
+
+        let delta = parser.parse("#id\n4\n!delta 3 9d4944ef c0+3 i1").unwrap();
+        assert!(matches!(serializer.serialize_as_version(&delta, 1), Err(AlsError::InvalidDelta { .. })));
+        assert!(serializer.serialize_as_version(&delta, 2).unwrap().starts_with("!v2 +delta\n"));
+    }
+}
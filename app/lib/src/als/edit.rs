@@ -0,0 +1,219 @@
+//! Editing rows in compressed space.
+//!
+//! Erasure requests against an archive touch a handful of rows out of
+//! millions. [`AlsDocument::delete_rows`] and [`AlsDocument::update_cell`]
+//! rewrite only the operators covering those rows, cutting them the way
+//! [`ColumnStream::slice`] does, so a range loses one value or a repeat
+//! splits around a patched value while everything else is left as it was.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::document::{AlsDocument, ColumnStream};
+use super::operator::AlsOperator;
+use super::parser::AlsParser;
+use crate::error::{AlsError, Result};
+
+impl AlsDocument<'_> {
+    /// Delete rows, given by index in any order.
+    ///
+    /// Indexes, Bloom filters and zone maps describe the old rows and are
+    /// dropped, and so is the block size, as blocks no longer line up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, AlsSerializer};
+    ///
+    /// let mut doc = AlsParser::new().parse("#id #level\n1>1000|info*1000").unwrap();
+    /// doc.delete_rows(&[499, 0]).unwrap();
+    ///
+    /// assert_eq!(doc.row_count(), 998);
+    /// assert_eq!(AlsSerializer::new().serialize(&doc), "!v1\n#id #level\n2>499 501>1000|info*498 info*500");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidRow` for a row past the end of the document,
+    /// and `AlsError::InvalidDelta` for a delta, whose rows are tied to its
+    /// edit script.
+    pub fn delete_rows(&mut self, rows: &[usize]) -> Result<()> {
+        self.check_editable()?;
+        let row_count = self.row_count();
+        let mut deleted = rows.to_vec();
+        deleted.sort_unstable();
+        deleted.dedup();
+        if let Some(&row) = deleted.last().filter(|&&row| row >= row_count) {
+            return Err(AlsError::InvalidRow { row, rows: row_count });
+        }
+        if deleted.is_empty() {
+            return Ok(());
+        }
+
+        let mut kept: Vec<Range<usize>> = Vec::with_capacity(deleted.len() + 1);
+        let mut start = 0;
+        for row in deleted.into_iter().chain(std::iter::once(row_count)) {
+            if start < row {
+                kept.push(start..row);
+            }
+            start = row + 1;
+        }
+
+        for stream in &mut self.streams {
+            let operators = kept.iter().flat_map(|range| stream.slice(range.clone()).operators).collect();
+            stream.operators = operators;
+        }
+        self.indexes.clear();
+        self.blooms.clear();
+        self.zone_maps.clear();
+        self.block_size = None;
+        Ok(())
+    }
+
+    /// Set the value of one cell.
+    ///
+    /// `value` is an expanded value, so `NULL_TOKEN` sets a null. Columns
+    /// deriving their value at `row` from this column keep their old value.
+    /// The index, Bloom filters and zone maps of the column are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, AlsSerializer};
+    ///
+    /// let mut doc = AlsParser::new().parse("#id #email\n1>5|a@x.io b@x.io c@x.io d@x.io e@x.io").unwrap();
+    /// doc.update_cell(2, 1, "erased").unwrap();
+    ///
+    /// let csv = AlsParser::new().to_csv(&AlsSerializer::new().serialize(&doc)).unwrap();
+    /// assert_eq!(csv.lines().nth(3), Some("3,erased"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidRow` for a row past the end of the document,
+    /// `AlsError::InvalidColumnRef` for a column it does not have, and
+    /// `AlsError::InvalidDelta` for a delta.
+    pub fn update_cell(&mut self, row: usize, column: usize, value: &str) -> Result<()> {
+        self.check_editable()?;
+        let rows = self.row_count();
+        if row >= rows {
+            return Err(AlsError::InvalidRow { row, rows });
+        }
+        if column >= self.streams.len() {
+            return Err(AlsError::InvalidColumnRef {
+                index: column,
+                columns: self.streams.len(),
+            });
+        }
+
+        // Pin derived values to what they were before their source changes
+        let derived: Vec<usize> = (column + 1..self.streams.len())
+            .filter(|&index| {
+                matches!(
+                    operator_at(&self.streams[index], row),
+                    Some(AlsOperator::ColumnRef { column: source, .. }) if *source == column
+                )
+            })
+            .collect();
+        if !derived.is_empty() {
+            let old = AlsParser::new().expand_columns(&self.slice_rows(row..row + 1))?;
+            for index in derived {
+                let value = old[index][0].clone();
+                replace_row(&mut self.streams[index], row, value);
+            }
+        }
+
+        replace_row(&mut self.streams[column], row, value.to_string());
+        self.indexes.remove(&column);
+        self.blooms.remove(&column);
+        self.zone_maps.remove(&column);
+        Ok(())
+    }
+
+    fn check_editable(&self) -> Result<()> {
+        if self.delta.is_some() {
+            return Err(AlsError::InvalidDelta {
+                message: "rows of a delta cannot be edited".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Get the operator producing a row of a stream.
+fn operator_at<'s, 'a>(stream: &'s ColumnStream<'a>, row: usize) -> Option<&'s AlsOperator<'a>> {
+    let mut position = 0;
+    stream.operators.iter().find(|op| {
+        position += op.expanded_count();
+        position > row
+    })
+}
+
+/// Replace the value of one row of a stream with a literal value.
+fn replace_row(stream: &mut ColumnStream, row: usize, value: String) {
+    let rows = stream.expanded_count();
+    let mut operators = stream.slice(0..row).operators;
+    operators.push(AlsOperator::Raw(Cow::Owned(value)));
+    operators.extend(stream.slice(row + 1..rows).operators);
+    stream.operators = operators;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsSerializer;
+
+    fn columns(doc: &AlsDocument) -> Vec<Vec<String>> {
+        let parser = AlsParser::new();
+        parser.expand_columns(&parser.parse(&AlsSerializer::new().serialize(doc)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_delete_rows() {
+        let parser = AlsParser::new();
+        let mut doc = parser.parse("$default:a|b\n#id #tag #flag #day\n1>6|_0*3 _1*3|x~y~z*6|&dow+0:2*6").unwrap();
+        let mut expected = columns(&doc);
+        doc.delete_rows(&[4, 1, 4, 5]).unwrap();
+
+        for column in &mut expected {
+            for row in [5, 4, 1] {
+                column.remove(row);
+            }
+        }
+        assert_eq!(columns(&doc), expected);
+        assert_eq!(doc.row_count(), 3);
+
+        assert!(matches!(doc.delete_rows(&[3]), Err(AlsError::InvalidRow { row: 3, rows: 3 })));
+        doc.delete_rows(&[0, 1, 2]).unwrap();
+        assert_eq!(doc.row_count(), 0);
+    }
+
+    #[test]
+    fn test_delete_rows_drops_block_metadata() {
+        let config = crate::CompressorConfig::new().with_block_size(2).with_build_indexes(true).with_zone_maps(true);
+        let mut doc = crate::AlsCompressor::with_config(config)
+            .compress(&crate::convert::csv::parse_csv("id,name\n1,a\n2,b\n3,c\n4,d").unwrap())
+            .unwrap();
+        doc.delete_rows(&[1]).unwrap();
+        assert!(doc.block_size.is_none() && doc.blooms.is_empty() && doc.zone_maps.is_empty());
+        assert_eq!(columns(&doc)[1], vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_update_cell() {
+        let parser = AlsParser::new();
+        let mut doc = parser.parse("#id #side #sign\n1>4|buy sell*2 buy|@1(buy:+1 sell:-1)*4").unwrap();
+        doc.update_cell(1, 1, "buy").unwrap();
+        doc.update_cell(3, 0, "40").unwrap();
+
+        let columns = columns(&doc);
+        assert_eq!(columns[0], vec!["1", "2", "3", "40"]);
+        assert_eq!(columns[1], vec!["buy", "buy", "sell", "buy"]);
+        // The derived sign keeps its value for the edited row only
+        assert_eq!(columns[2], vec!["+1", "-1", "-1", "+1"]);
+        assert_eq!(doc.streams[0].operators[0], AlsOperator::Range { start: 1, end: 3, step: 1 });
+
+        assert!(matches!(doc.update_cell(4, 0, "x"), Err(AlsError::InvalidRow { row: 4, rows: 4 })));
+        assert!(matches!(doc.update_cell(0, 3, "x"), Err(AlsError::InvalidColumnRef { index: 3, .. })));
+    }
+}
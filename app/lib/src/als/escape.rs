@@ -21,12 +21,24 @@
 //! | `_` | `\_` | Dictionary reference prefix |
 //! | `#` | `\#` | Schema prefix |
 //! | `$` | `\$` | Dictionary header prefix |
+//! | `@` | `\@` | Column reference prefix |
+//! | `%` | `\%` | Date range prefix |
+//! | `&` | `\&` | Calendar cycle prefix |
+//! | `^` | `\^` | Transform prefix |
+//! | `?` | `\?` | Boolean run prefix |
+//! | `<` | `\<` | Split prefix |
+//! | `!` | `\!` | Version and CTX column prefix |
 //! | `:` | `\:` | Step separator in ranges |
+//! | `(` | `\(` | Group and operator argument start |
+//! | `)` | `\)` | Group and operator argument end |
 //! | `\` | `\\` | Escape character itself |
 //! | newline | `\n` | Line break |
 //! | tab | `\t` | Tab character |
 //! | carriage return | `\r` | Carriage return |
 //! | space | `\ ` | Preserved space (in delimiter contexts) |
+//!
+//! `\{` and `\}` are also accepted. They are only written where a brace
+//! would end the surrounding token, such as before a transform spec.
 
 use crate::error::{AlsError, Result};
 
@@ -97,7 +109,16 @@ pub fn escape_als_string(s: &str) -> String {
             '_' => result.push_str("\\_"),
             '#' => result.push_str("\\#"),
             '$' => result.push_str("\\$"),
+            '@' => result.push_str("\\@"),
+            '%' => result.push_str("\\%"),
+            '&' => result.push_str("\\&"),
+            '^' => result.push_str("\\^"),
+            '?' => result.push_str("\\?"),
+            '<' => result.push_str("\\<"),
+            '!' => result.push_str("\\!"),
             ':' => result.push_str("\\:"),
+            '(' => result.push_str("\\("),
+            ')' => result.push_str("\\)"),
             '\\' => result.push_str("\\\\"),
             '\n' => result.push_str("\\n"),
             '\t' => result.push_str("\\t"),
@@ -159,7 +180,18 @@ pub fn unescape_als_string(s: &str) -> Result<String> {
                 Some('_') => result.push('_'),
                 Some('#') => result.push('#'),
                 Some('$') => result.push('$'),
+                Some('@') => result.push('@'),
+                Some('%') => result.push('%'),
+                Some('&') => result.push('&'),
+                Some('^') => result.push('^'),
+                Some('?') => result.push('?'),
+                Some('<') => result.push('<'),
+                Some('!') => result.push('!'),
                 Some(':') => result.push(':'),
+                Some('(') => result.push('('),
+                Some(')') => result.push(')'),
+                Some('{') => result.push('{'),
+                Some('}') => result.push('}'),
                 Some('\\') => result.push('\\'),
                 Some('n') => result.push('\n'),
                 Some('t') => result.push('\t'),
@@ -343,7 +375,7 @@ pub fn decode_als_value(s: &str) -> Result<Option<String>> {
 /// ```
 pub fn needs_escaping(s: &str) -> bool {
     s.chars().any(|c| matches!(c, 
-        '>' | '*' | '~' | '|' | '_' | '#' | '$' | ':' | '\\' | '\n' | '\t' | '\r' | ' '
+        '>' | '*' | '~' | '|' | '_' | '#' | '$' | '@' | '%' | '&' | '^' | '?' | '<' | '!' | ':' | '(' | ')' | '\\' | '\n' | '\t' | '\r' | ' '
     ))
 }
 
@@ -396,6 +428,12 @@ mod tests {
         assert_eq!(escape_als_string("$"), "\\$");
     }
 
+    #[test]
+    fn test_escape_column_ref_prefix() {
+        assert_eq!(escape_als_string("@0"), "\\@0");
+        assert_eq!(escape_als_string("a@b.com"), "a\\@b.com");
+    }
+
     #[test]
     fn test_escape_backslash() {
         assert_eq!(escape_als_string("a\\b"), "a\\\\b");
@@ -496,6 +534,64 @@ mod tests {
         assert_eq!(unescape_als_string("\\$").unwrap(), "$");
     }
 
+    #[test]
+    fn test_unescape_column_ref_prefix() {
+        assert_eq!(unescape_als_string("\\@0").unwrap(), "@0");
+    }
+
+    #[test]
+    fn test_escape_bool_runs_prefix() {
+        assert_eq!(escape_als_string("why?"), "why\\?");
+        assert_eq!(unescape_als_string("\\?a").unwrap(), "?a");
+    }
+
+    #[test]
+    fn test_escape_split_prefix() {
+        assert_eq!(escape_als_string("a<b"), "a\\<b");
+        assert_eq!(unescape_als_string("\\</2").unwrap(), "</2");
+    }
+
+    #[test]
+    fn test_escape_version_prefix() {
+        assert_eq!(escape_als_string("!ctx"), "\\!ctx");
+        assert_eq!(unescape_als_string("hi\\!").unwrap(), "hi!");
+        assert!(needs_escaping("!"));
+    }
+
+    #[test]
+    fn test_escape_transform_prefix() {
+        assert_eq!(escape_als_string("2^8"), "2\\^8");
+        assert_eq!(unescape_als_string("\\^{U}").unwrap(), "^{U}");
+    }
+
+    #[test]
+    fn test_escape_cycle_prefix() {
+        assert_eq!(escape_als_string("R&D"), "R\\&D");
+        assert_eq!(unescape_als_string("\\&dow").unwrap(), "&dow");
+        assert!(needs_escaping("a&b"));
+    }
+
+    #[test]
+    fn test_escape_date_range_prefix() {
+        assert_eq!(escape_als_string("50%"), "50\\%");
+        assert_eq!(unescape_als_string("\\%{x}").unwrap(), "%{x}");
+    }
+
+    #[test]
+    fn test_escape_parentheses() {
+        assert_eq!(escape_als_string("f(x)"), "f\\(x\\)");
+        assert_eq!(unescape_als_string("\\(a\\)").unwrap(), "(a)");
+        assert!(needs_escaping("("));
+        assert!(needs_escaping(")"));
+    }
+
+    #[test]
+    fn test_unescape_braces() {
+        // Braces are only escaped where they would end a token
+        assert_eq!(escape_als_string("{a}"), "{a}");
+        assert_eq!(unescape_als_string("\\{a\\}").unwrap(), "{a}");
+    }
+
     #[test]
     fn test_unescape_backslash() {
         assert_eq!(unescape_als_string("a\\\\b").unwrap(), "a\\b");
@@ -575,7 +671,7 @@ mod tests {
 
     #[test]
     fn test_roundtrip_with_operators() {
-        let original = "a>b*c~d|e_f#g$h";
+        let original = "a>b*c~d|e_f#g$h(i)j:k";
         let escaped = escape_als_string(original);
         let unescaped = unescape_als_string(&escaped).unwrap();
         assert_eq!(original, unescaped);
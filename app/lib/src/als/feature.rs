@@ -0,0 +1,320 @@
+//! Feature flags of version 2 documents.
+//!
+//! A parser that meets an operator or trailer line it does not know can
+//! only report a syntax error, and bumping the version for every addition
+//! would lock older parsers out of documents that never use it. Version 2
+//! headers instead list the features a document uses beyond the ranges,
+//! repeats, toggles and dictionary references of version 1:
+//!
+//! ```text
+//! !v2 +blocks +date-range +bloom
+//! ```
+//!
+//! A parser reading a flag it does not know fails with
+//! `AlsError::UnsupportedFeature` naming it, and new features only need a
+//! new flag, not a new version. The serializer writes any document using a
+//! feature as version 2 with its flags, whatever the document's `version`.
+//!
+//! Dictionary runs (`_3*4`) only respell references, and are version 1
+//! syntax without a flag. Base-62 references (`_a0`), relative references
+//! (`_=`, `_+2`) and front-coded dictionary headers (`$default~:…`) respell
+//! references and dictionaries too, but a version 1 parser misreads or
+//! rejects them, so documents using them declare `+ref62`,
+//! `+relative-refs` and `+front-coding`.
+
+use std::collections::BTreeSet;
+
+use super::dict_ref;
+use super::document::AlsDocument;
+use super::operator::AlsOperator;
+use super::serializer::front_coded_entries;
+
+/// Feature a version 2 document can declare in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    /// Rows split into blocks (`!blocks`)
+    Blocks,
+    /// Bloom filters per block (`!bloom`)
+    Bloom,
+    /// Zone maps per block (`!zone`)
+    ZoneMaps,
+    /// Value indexes (`!idx`)
+    Index,
+    /// Edit script against a base document (`!delta`)
+    Delta,
+    /// Text normalized to NFC (`!nfc`)
+    Nfc,
+    /// Redacted columns (`!redact`)
+    Redact,
+    /// Quantized columns (`!quantize`)
+    Quantize,
+    /// Truncated time columns (`!truncate`)
+    Truncate,
+    /// Metadata of the source file (`!meta`)
+    Meta,
+    /// Foreign keys into another document (`!fk`)
+    ForeignKey,
+    /// Column references (`@0(a:b)*3`)
+    ColumnRef,
+    /// Date ranges (`%{%Y-%m-%d}1704067200+86400*3`)
+    DateRange,
+    /// Calendar cycles (`&dow+0*7`)
+    Cycle,
+    /// Transforms (`^ID{U4}(1>3)`)
+    Transform,
+    /// Boolean runs (`?T~F:3.2`)
+    BoolRuns,
+    /// Splits (`</2(a b)(x y)`, or `<2(a b)(x y)` to concatenate)
+    Split,
+    /// Float deltas (`%.2:2153+3-1`)
+    FloatDelta,
+    /// Base-62 dictionary references (`_a`, `_a0`)
+    Base62Refs,
+    /// Dictionary references relative to the previous one (`_=`, `_+2`)
+    RelativeRefs,
+    /// Front-coded dictionary headers (`$default~:/var/log/a|9~b`)
+    FrontCoding,
+}
+
+impl Feature {
+    /// All features this parser supports, in header order.
+    pub const ALL: [Feature; 21] = [
+        Feature::Blocks,
+        Feature::Bloom,
+        Feature::ZoneMaps,
+        Feature::Index,
+        Feature::Delta,
+        Feature::Nfc,
+        Feature::Redact,
+        Feature::Quantize,
+        Feature::Truncate,
+        Feature::Meta,
+        Feature::ForeignKey,
+        Feature::ColumnRef,
+        Feature::DateRange,
+        Feature::Cycle,
+        Feature::Transform,
+        Feature::BoolRuns,
+        Feature::Split,
+        Feature::FloatDelta,
+        Feature::Base62Refs,
+        Feature::RelativeRefs,
+        Feature::FrontCoding,
+    ];
+
+    /// Get the name of the feature's flag, without its `+`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Blocks => "blocks",
+            Feature::Bloom => "bloom",
+            Feature::ZoneMaps => "zones",
+            Feature::Index => "index",
+            Feature::Delta => "delta",
+            Feature::Nfc => "nfc",
+            Feature::Redact => "redact",
+            Feature::Quantize => "quantize",
+            Feature::Truncate => "truncate",
+            Feature::Meta => "meta",
+            Feature::ForeignKey => "fk",
+            Feature::ColumnRef => "column-ref",
+            Feature::DateRange => "date-range",
+            Feature::Cycle => "cycle",
+            Feature::Transform => "transform",
+            Feature::BoolRuns => "bool-runs",
+            Feature::Split => "split",
+            Feature::FloatDelta => "float-delta",
+            Feature::Base62Refs => "ref62",
+            Feature::RelativeRefs => "relative-refs",
+            Feature::FrontCoding => "front-coding",
+        }
+    }
+
+    /// Look up a feature by the name of its flag.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|feature| feature.name() == name)
+    }
+
+    /// Get the feature an operator needs, if it is not part of version 1.
+    fn of_operator(op: &AlsOperator) -> Option<Self> {
+        match op {
+            AlsOperator::ColumnRef { .. } => Some(Feature::ColumnRef),
+            AlsOperator::DateRange { .. } => Some(Feature::DateRange),
+            AlsOperator::Cycle { .. } => Some(Feature::Cycle),
+            AlsOperator::Transform { .. } => Some(Feature::Transform),
+            AlsOperator::BoolRuns { .. } => Some(Feature::BoolRuns),
+            AlsOperator::Split { .. } => Some(Feature::Split),
+            AlsOperator::FloatDelta { .. } => Some(Feature::FloatDelta),
+            AlsOperator::Raw(_)
+            | AlsOperator::Range { .. }
+            | AlsOperator::Multiply { .. }
+            | AlsOperator::Toggle { .. }
+            | AlsOperator::DictRef(_) => None,
+        }
+    }
+}
+
+impl AlsDocument<'_> {
+    /// Get the features the document uses beyond version 1.
+    ///
+    /// These are the flags a version 2 header lists.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, Feature};
+    ///
+    /// let doc = AlsParser::new().parse("#day #n\n%{%Y-%m-%d}1704067200+86400*3|1>3\n!blocks 2").unwrap();
+    /// assert_eq!(doc.features().into_iter().collect::<Vec<_>>(), vec![Feature::Blocks, Feature::DateRange]);
+    /// ```
+    pub fn features(&self) -> BTreeSet<Feature> {
+        let mut features = BTreeSet::new();
+        let sections = [
+            (self.block_size.is_some(), Feature::Blocks),
+            (!self.blooms.is_empty(), Feature::Bloom),
+            (!self.zone_maps.is_empty(), Feature::ZoneMaps),
+            (!self.indexes.is_empty(), Feature::Index),
+            (self.delta.is_some(), Feature::Delta),
+            (self.nfc_normalized, Feature::Nfc),
+            (!self.redactions.is_empty(), Feature::Redact),
+            (!self.quantizations.is_empty(), Feature::Quantize),
+            (!self.truncations.is_empty(), Feature::Truncate),
+            (self.file_metadata.as_ref().is_some_and(|metadata| !metadata.is_empty()), Feature::Meta),
+            (!self.foreign_keys.is_empty(), Feature::ForeignKey),
+            (self.dictionaries.values().any(|values| front_coded_entries(values).is_some()), Feature::FrontCoding),
+        ];
+        features.extend(sections.into_iter().filter(|(used, _)| *used).map(|(_, feature)| feature));
+        for stream in &self.streams {
+            collect_operator_features(&stream.operators, &mut features);
+            // The serializer writes relative references only when shorter
+            let mut last = None;
+            visit_dict_refs(&stream.operators, &mut |index| {
+                if dict_ref::is_base62(index) {
+                    features.insert(Feature::Base62Refs);
+                }
+                if dict_ref::relative(index, last).is_some() {
+                    features.insert(Feature::RelativeRefs);
+                }
+                last = Some(index);
+            });
+        }
+        features
+    }
+
+    /// Get the version the document's header is written with.
+    ///
+    /// A document using any feature is written as version 2 whatever its
+    /// `version` says, so parsers that lack one of its features report it
+    /// instead of failing on syntax they do not know.
+    pub(crate) fn header_version(&self) -> u8 {
+        self.header_version_with(&self.features())
+    }
+
+    /// Get the version the document's header is written with when it
+    /// declares `features`.
+    pub(crate) fn header_version_with(&self, features: &BTreeSet<Feature>) -> u8 {
+        if self.version < 2 && !features.is_empty() {
+            2
+        } else {
+            self.version
+        }
+    }
+}
+
+/// Check if an operator, or any operator inside it, needs a feature.
+pub(crate) fn operator_uses_features(op: &AlsOperator) -> bool {
+    let mut features = BTreeSet::new();
+    collect_operator_features(std::slice::from_ref(op), &mut features);
+    !features.is_empty()
+}
+
+/// Add the features of a sequence of operators and the operators inside them.
+fn collect_operator_features(operators: &[AlsOperator], features: &mut BTreeSet<Feature>) {
+    for op in operators {
+        features.extend(Feature::of_operator(op));
+        match op {
+            AlsOperator::Multiply { value, .. } | AlsOperator::Transform { value, .. } => {
+                collect_operator_features(std::slice::from_ref(value), features);
+            }
+            AlsOperator::Split { parts, .. } => {
+                for part in parts {
+                    collect_operator_features(part, features);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Visit the dictionary references of a sequence of operators in the order
+/// they are written.
+fn visit_dict_refs(operators: &[AlsOperator], visit: &mut impl FnMut(usize)) {
+    for op in operators {
+        match op {
+            AlsOperator::DictRef(index) => visit(*index),
+            AlsOperator::Multiply { value, .. } | AlsOperator::Transform { value, .. } => {
+                visit_dict_refs(std::slice::from_ref(value), visit);
+            }
+            AlsOperator::Split { parts, .. } => {
+                for part in parts {
+                    visit_dict_refs(part, visit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsParser;
+
+    #[test]
+    fn test_feature_names() {
+        for feature in Feature::ALL {
+            assert_eq!(Feature::from_name(feature.name()), Some(feature));
+        }
+        assert_eq!(Feature::from_name("crc"), None);
+        assert!(Feature::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_document_features() {
+        let doc = AlsParser::new().parse("#a #b\n1>3|(^{U}(</2(a b c)(&dow+0*3)))*1").unwrap();
+        assert_eq!(
+            doc.features().into_iter().collect::<Vec<_>>(),
+            vec![Feature::Cycle, Feature::Transform, Feature::Split]
+        );
+        assert!(AlsParser::new().parse("$default:a\n#a\n1>3 _0 x~y*2").unwrap().features().is_empty());
+    }
+
+    #[test]
+    fn test_base62_refs_feature() {
+        let dictionary: Vec<String> = (0..12).map(|i| format!("v{}", i)).collect();
+        let header = format!("$default:{}\n#c\n", dictionary.join("|"));
+        let parser = AlsParser::new();
+        assert!(parser.parse(&format!("{}_9 _9*2", header)).unwrap().features().is_empty());
+        let doc = parser.parse(&format!("{}_9 (_11)*2", header)).unwrap();
+        assert_eq!(doc.features().into_iter().collect::<Vec<_>>(), vec![Feature::Base62Refs]);
+    }
+
+    #[test]
+    fn test_front_coding_feature() {
+        let parser = AlsParser::new();
+        assert!(parser.parse("$default:a/x|a/y\n#c\n_0 _1").unwrap().features().is_empty());
+        let doc = parser.parse("$default:/var/log/access.log|/var/log/error.log\n#c\n_0 _1").unwrap();
+        assert_eq!(doc.features().into_iter().collect::<Vec<_>>(), vec![Feature::FrontCoding]);
+    }
+
+    #[test]
+    fn test_relative_refs_feature() {
+        let dictionary: Vec<String> = (0..70).map(|i| format!("v{}", i)).collect();
+        let header = format!("$default:{}\n#c #d\n", dictionary.join("|"));
+        let parser = AlsParser::new();
+
+        // `_=` after `_3` is no shorter, and streams start afresh
+        assert!(parser.parse(&format!("{}_3 _3 _62|_63 _0", header)).unwrap().features().is_empty());
+        let doc = parser.parse(&format!("{}_3 _62 _63|x y", header)).unwrap();
+        assert_eq!(doc.features().into_iter().collect::<Vec<_>>(), vec![Feature::RelativeRefs]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Decimal floats as scaled integers, and their XOR encoding.
+//!
+//! Measurements such as `21.53` are taken to a fixed number of decimal
+//! places, so a column of them is a column of integers in disguise: `2153`
+//! at two places, and `21.5` is `2150`. The text format stores such columns
+//! as the differences between consecutive scaled integers, which stay short
+//! while the values drift slowly. The protobuf encoding stores the same
+//! values as floats XORed with their predecessor, as in Facebook's Gorilla,
+//! where neighbouring readings share their sign, exponent and leading
+//! mantissa bits and XOR down to a few meaningful bits.
+
+use super::bitpack::{BitReader, BitWriter};
+
+/// Most decimal places of a float delta column.
+pub const MAX_FLOAT_DELTA_PLACES: u8 = 15;
+
+/// Scaled integers are kept below 10^15, so every value has at most 15
+/// significant digits and survives a round trip through an `f64`.
+const MAX_SCALED: i64 = 1_000_000_000_000_000;
+
+/// Format a scaled integer as a decimal with up to `places` digits after
+/// the point, as floats print: `2153` at two places is `21.53`, `2150` is
+/// `21.5` and `2100` is `21`.
+pub(crate) fn format_scaled(scaled: i64, places: u8) -> String {
+    let unit = 10u64.pow(u32::from(places));
+    let magnitude = scaled.unsigned_abs();
+    let sign = if scaled < 0 { "-" } else { "" };
+    let fraction = format!("{:0width$}", magnitude % unit, width = usize::from(places));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}{}", sign, magnitude / unit)
+    } else {
+        format!("{}{}.{}", sign, magnitude / unit, fraction)
+    }
+}
+
+/// Get the number of digits after the decimal point of `text`.
+pub(crate) fn decimal_places(text: &str) -> usize {
+    text.split_once('.').map_or(0, |(_, fraction)| fraction.len())
+}
+
+/// Parse a decimal such as `-0.25` into an integer scaled by `10^places`.
+///
+/// Returns `None` unless [`format_scaled`] writes the result back as
+/// exactly `text`, so leading zeros, trailing zeros after the point, a
+/// leading `+` or a negative zero are refused, as are values with more
+/// than 15 significant digits.
+pub(crate) fn parse_scaled(text: &str, places: u8) -> Option<i64> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if fraction.len() > usize::from(places) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let negative = whole.starts_with('-');
+    let digits = whole.strip_prefix('-').unwrap_or(whole);
+    if digits.is_empty() || digits.len() > 15 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let padding = "0".repeat(usize::from(places) - fraction.len());
+    let magnitude: i64 = format!("{}{}{}", digits, fraction, padding).parse().ok()?;
+    let scaled = if negative { -magnitude } else { magnitude };
+    (magnitude < MAX_SCALED && format_scaled(scaled, places) == text).then_some(scaled)
+}
+
+/// Encode floats with Gorilla XOR compression.
+///
+/// The first value is stored whole. Each later value is XORed with the one
+/// before it: an identical value costs a single `0` bit, and otherwise the
+/// meaningful bits of the XOR are stored, reusing the previous window of
+/// leading and trailing zeros when they fit in it.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::als::{xor_decode, xor_encode};
+///
+/// let readings = [21.53, 21.56, 21.56, 21.55];
+/// let bytes = xor_encode(&readings);
+/// assert!(bytes.len() < readings.len() * 8);
+/// assert_eq!(xor_decode(&bytes, readings.len()).unwrap(), readings);
+/// ```
+pub fn xor_encode(values: &[f64]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    let Some((first, rest)) = values.split_first() else {
+        return writer.bytes;
+    };
+    let mut previous = first.to_bits();
+    writer.write(previous, 64);
+
+    // Leading and trailing zeros of the last stored XOR
+    let mut window: Option<(u32, u32)> = None;
+    for value in rest {
+        let bits = value.to_bits();
+        let xor = bits ^ previous;
+        previous = bits;
+        if xor == 0 {
+            writer.write(0, 1);
+            continue;
+        }
+
+        // Five bits hold the leading zeros, so longer runs store some zeros
+        let leading = xor.leading_zeros().min(31);
+        let trailing = xor.trailing_zeros();
+        match window {
+            Some((window_leading, window_trailing)) if leading >= window_leading && trailing >= window_trailing => {
+                writer.write(0b10, 2);
+                writer.write(xor >> window_trailing, 64 - window_leading - window_trailing);
+            }
+            _ => {
+                let meaningful = 64 - leading - trailing;
+                writer.write(0b11, 2);
+                writer.write(u64::from(leading), 5);
+                // Six bits hold the length, with 64 written as 0
+                writer.write(u64::from(meaningful % 64), 6);
+                writer.write(xor >> trailing, meaningful);
+                window = Some((leading, trailing));
+            }
+        }
+    }
+    writer.bytes
+}
+
+/// Decode `count` floats written by [`xor_encode`].
+///
+/// Returns `None` if the bytes end before `count` values are read or
+/// describe an impossible window of meaningful bits.
+pub fn xor_decode(bytes: &[u8], count: usize) -> Option<Vec<f64>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    // Every value after the first takes at least a bit
+    if count - 1 > bytes.len().saturating_mul(8) {
+        return None;
+    }
+
+    let mut reader = BitReader::new(bytes);
+    let mut previous = reader.read(64)?;
+    let mut values = Vec::with_capacity(count);
+    values.push(f64::from_bits(previous));
+
+    // Leading zeros and length of the last stored XOR
+    let mut window: Option<(u32, u32)> = None;
+    while values.len() < count {
+        if reader.read(1)? == 1 {
+            let (leading, meaningful) = if reader.read(1)? == 0 {
+                window?
+            } else {
+                let leading = reader.read(5)? as u32;
+                let meaningful = match reader.read(6)? as u32 {
+                    0 => 64,
+                    meaningful => meaningful,
+                };
+                if leading + meaningful > 64 {
+                    return None;
+                }
+                window = Some((leading, meaningful));
+                (leading, meaningful)
+            };
+            previous ^= reader.read(meaningful)? << (64 - leading - meaningful);
+        }
+        values.push(f64::from_bits(previous));
+    }
+    Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_roundtrip() {
+        assert_eq!(parse_scaled("21.53", 2), Some(2153));
+        assert_eq!(parse_scaled("21.5", 2), Some(2150));
+        assert_eq!(parse_scaled("21", 2), Some(2100));
+        assert_eq!(parse_scaled("-0.05", 3), Some(-50));
+        assert_eq!(parse_scaled("0", 1), Some(0));
+        for text in ["21.53", "-0.05", "0", "-7.5", "123456789012.345"] {
+            let places = decimal_places(text).max(1) as u8;
+            assert_eq!(format_scaled(parse_scaled(text, places).unwrap(), places), text);
+        }
+        for text in ["21.50", "021.5", "+1.5", "-0", "1.", ".5", "1.5e3", "1.2.3", "--1.5", "1234567890123.456", "1.1234"] {
+            assert_eq!(parse_scaled(text, 3), None, "{}", text);
+        }
+        assert_eq!(format_scaled(-7, 0), "-7");
+        assert_eq!(format_scaled(-70, 1), "-7");
+        assert_eq!(format_scaled(5, 3), "0.005");
+    }
+
+    #[test]
+    fn test_xor_roundtrip() {
+        let mut values = vec![21.53, 21.53, 21.56, -0.0, 0.0, f64::NAN, f64::INFINITY, 1e300, f64::MIN_POSITIVE];
+        values.extend((0..100).map(|i| 20.0 + f64::from(i % 7) * 0.01));
+        let bytes = xor_encode(&values);
+        let decoded = xor_decode(&bytes, values.len()).unwrap();
+        let bits = |values: &[f64]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&decoded), bits(&values));
+
+        // A steady reading costs a bit per repeat
+        assert_eq!(xor_encode(&[1.5; 17]).len(), 10);
+        assert_eq!(xor_decode(&[], 0), Some(Vec::new()));
+        assert_eq!(xor_encode(&[]), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_xor_decode_truncated() {
+        let bytes = xor_encode(&[1.0, 2.0, 3.0]);
+        assert!(xor_decode(&bytes[..bytes.len() - 1], 3).is_none());
+        assert!(xor_decode(&bytes[..7], 1).is_none());
+        assert!(xor_decode(&bytes, usize::MAX).is_none());
+    }
+}
@@ -0,0 +1,203 @@
+//! Foreign keys between the tables of a relational export.
+//!
+//! A database dump repeats the keys of a parent table in every child row
+//! that refers to it, and compressing each table on its own finds no
+//! pattern in them when the keys are UUIDs or codes. Declaring the
+//! relationship lets a child column hold row numbers into the parent's key
+//! column instead, which are small integers that compress well. The
+//! relationship is recorded in the child, one line per column:
+//!
+//! ```text
+//! !fk customer customers.csv id
+//! ```
+//!
+//! The parent is the name of a document of the same container, which
+//! resolves the row numbers back to keys when the child is expanded (see
+//! [`AlsContainerReader::expand_columns`](super::AlsContainerReader::expand_columns)).
+//! Read on its own, the child column holds the row numbers, counting from 0.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::escape::{escape_als_string, unescape_als_string, NULL_TOKEN};
+use super::policy::apply_policy;
+use super::trailer::TrailerLine;
+use crate::convert::{Column, TabularData, Value};
+use crate::error::{AlsError, Result};
+
+/// Prefix of a foreign key line.
+pub const FOREIGN_KEY_PREFIX: &str = "!fk ";
+
+/// Parent table column that a child column refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ForeignKey {
+    /// Name of the parent document in its container.
+    pub parent: String,
+    /// Name of the key column of the parent.
+    pub parent_column: String,
+}
+
+impl ForeignKey {
+    /// Refer to the `parent_column` column of the `parent` document.
+    pub fn new<P: Into<String>, C: Into<String>>(parent: P, parent_column: C) -> Self {
+        Self {
+            parent: parent.into(),
+            parent_column: parent_column.into(),
+        }
+    }
+
+    /// Replace the row numbers of the child column `column` with the
+    /// parent keys they point at.
+    ///
+    /// Nulls are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidForeignKey` for a value that is not a row
+    /// of `parent_keys`.
+    pub(crate) fn resolve_values(column: &str, values: &mut [String], parent_keys: &[String]) -> Result<()> {
+        for value in values.iter_mut().filter(|value| *value != NULL_TOKEN) {
+            let key = value.parse::<usize>().ok().and_then(|row| parent_keys.get(row)).ok_or_else(|| {
+                AlsError::InvalidForeignKey {
+                    column: column.to_string(),
+                    message: format!("row {} is not in the parent ({} rows)", value, parent_keys.len()),
+                }
+            })?;
+            value.clone_from(key);
+        }
+        Ok(())
+    }
+
+    /// Append the foreign key lines of a document to `output`, one per
+    /// column, each preceded by a newline.
+    pub(crate) fn write_lines(output: &mut String, foreign_keys: &BTreeMap<String, ForeignKey>) {
+        for (column, key) in foreign_keys {
+            output.push('\n');
+            output.push_str(FOREIGN_KEY_PREFIX);
+            output.push_str(&escape_als_string(column));
+            output.push(' ');
+            output.push_str(&escape_als_string(&key.parent));
+            output.push(' ');
+            output.push_str(&escape_als_string(&key.parent_column));
+        }
+    }
+
+    /// Read a `!fk <column> <parent> <parent column>` line at offset
+    /// `position` back into the child column and the parent column it
+    /// refers to.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(String, ForeignKey)> {
+        let line = TrailerLine::new(line, FOREIGN_KEY_PREFIX, "foreign key", position)?;
+        let fields = line.fields();
+        let [column, parent, parent_column] = fields.as_slice() else {
+            return Err(line.error("Foreign key line needs a column, a parent and a parent column"));
+        };
+        let key = ForeignKey::new(unescape_als_string(parent)?, unescape_als_string(parent_column)?);
+        Ok((unescape_als_string(column)?, key))
+    }
+}
+
+/// Replace the values of the columns of `data` named in `policy` with
+/// their row numbers in the parent keys they are declared against, the
+/// first row for keys the parent repeats.
+///
+/// Returns `None` if `policy` names no column of `data`.
+///
+/// # Errors
+///
+/// Returns `AlsError::InvalidForeignKey` for a value missing from the
+/// parent keys.
+pub(crate) fn encode_foreign_keys<'a>(
+    data: &TabularData<'a>,
+    policy: &HashMap<String, (ForeignKey, Arc<[String]>)>,
+) -> Result<Option<TabularData<'a>>> {
+    apply_policy(data, policy, |column, (key, parent_keys)| {
+        let mut rows: HashMap<&str, usize> = HashMap::with_capacity(parent_keys.len());
+        for (row, parent_key) in parent_keys.iter().enumerate() {
+            rows.entry(parent_key.as_str()).or_insert(row);
+        }
+        let values = column
+            .values
+            .iter()
+            .map(|value| match value {
+                Value::Null => Ok(Value::Null),
+                value => match rows.get(value.to_string_repr().as_ref()) {
+                    Some(&row) => Ok(Value::Integer(row as i64)),
+                    None => Err(AlsError::InvalidForeignKey {
+                        column: column.name.to_string(),
+                        message: format!(
+                            "{} is not a key of column '{}' of '{}'",
+                            value.to_string_repr(),
+                            key.parent_column,
+                            key.parent
+                        ),
+                    }),
+                },
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Column::new(column.name.clone(), values)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::csv::parse_csv;
+
+    fn policy(column: &str, keys: &[&str]) -> HashMap<String, (ForeignKey, Arc<[String]>)> {
+        let keys: Arc<[String]> = keys.iter().map(|key| key.to_string()).collect();
+        HashMap::from([(column.to_string(), (ForeignKey::new("users", "id"), keys))])
+    }
+
+    #[test]
+    fn test_encode_and_resolve() {
+        let data = parse_csv("order,user\n1,u-b\n2,\n3,u-a\n4,u-b").unwrap();
+        let policy = policy("user", &["u-a", "u-b", "u-a"]);
+        let encoded = encode_foreign_keys(&data, &policy).unwrap().unwrap();
+        assert_eq!(encoded.columns[0].values, data.columns[0].values);
+        assert_eq!(
+            encoded.columns[1].values,
+            vec![Value::Integer(1), Value::Null, Value::Integer(0), Value::Integer(1)]
+        );
+
+        let keys = &policy["user"].1;
+        let mut values: Vec<String> = encoded.columns[1].values.iter().map(|value| value.to_string_repr().into_owned()).collect();
+        ForeignKey::resolve_values("user", &mut values, keys).unwrap();
+        assert_eq!(values, ["u-b", NULL_TOKEN, "u-a", "u-b"]);
+
+        let mut past_end = vec!["3".to_string()];
+        assert!(matches!(
+            ForeignKey::resolve_values("user", &mut past_end, keys),
+            Err(AlsError::InvalidForeignKey { .. })
+        ));
+    }
+
+    #[test]
+    fn test_orphans_are_refused() {
+        let data = parse_csv("order,user\n1,u-c").unwrap();
+        assert!(matches!(
+            encode_foreign_keys(&data, &policy("user", &["u-a"])),
+            Err(AlsError::InvalidForeignKey { .. })
+        ));
+        assert!(encode_foreign_keys(&data, &policy("owner", &["u-a"])).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut foreign_keys = BTreeMap::new();
+        foreign_keys.insert("user id".to_string(), ForeignKey::new("users.csv", "id"));
+        foreign_keys.insert("sku".to_string(), ForeignKey::new("products", "sku"));
+        let mut output = String::new();
+        ForeignKey::write_lines(&mut output, &foreign_keys);
+        assert_eq!(output, "\n!fk sku products sku\n!fk user\\ id users.csv id");
+
+        let parsed: Vec<(String, ForeignKey)> = output
+            .lines()
+            .skip(1)
+            .map(|line| ForeignKey::parse_line(line, 0).unwrap())
+            .collect();
+        assert_eq!(parsed, foreign_keys.into_iter().collect::<Vec<_>>());
+        assert!(ForeignKey::parse_line("!fk user users", 0).is_err());
+    }
+}
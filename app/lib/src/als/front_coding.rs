@@ -0,0 +1,146 @@
+//! Front-coding for dictionary headers.
+//!
+//! Dictionaries of URLs or file paths repeat long prefixes from one entry
+//! to the next. A front-coded header (`$name~:first|N~suffix|...`) stores
+//! each entry after the first as the number of bytes it shares with the
+//! previous entry, a `~`, and the remaining suffix:
+//!
+//! ```text
+//! $default~:/var/log/app/error.log|17~access.log|13~db/slow.log
+//! ```
+//!
+//! decodes to `/var/log/app/error.log`, `/var/log/app/access.log` and
+//! `/var/log/app/db/slow.log`.
+
+use crate::error::{AlsError, Result};
+
+/// Split each entry into the bytes shared with the previous entry and the
+/// remaining suffix.
+///
+/// The first entry never shares a prefix. Shared lengths always fall on a
+/// character boundary.
+pub(crate) fn encode(values: &[String]) -> Vec<(usize, &str)> {
+    let mut entries = Vec::with_capacity(values.len());
+    let mut previous = "";
+    for value in values {
+        let shared = shared_prefix_len(previous, value);
+        entries.push((shared, &value[shared..]));
+        previous = value;
+    }
+    entries
+}
+
+/// Decode front-coded entries as read from a dictionary header.
+///
+/// The first entry is taken literally; every other entry must have the
+/// form `N~suffix`.
+pub(crate) fn decode(entries: Vec<String>, position: usize) -> Result<Vec<String>> {
+    let mut values: Vec<String> = Vec::with_capacity(entries.len());
+    let mut entries = entries.into_iter();
+
+    if let Some(first) = entries.next() {
+        values.push(first);
+    }
+
+    for entry in entries {
+        let previous = values.last().map_or("", String::as_str);
+        let prefix = entry
+            .split_once('~')
+            .and_then(|(shared, suffix)| {
+                let shared = shared.parse::<usize>().ok()?;
+                Some((previous.get(..shared)?, suffix))
+            });
+        let Some((prefix, suffix)) = prefix else {
+            return Err(AlsError::AlsSyntaxError {
+                position,
+                message: format!("Invalid front-coded dictionary entry: {}", entry),
+            });
+        };
+        values.push(format!("{}{}", prefix, suffix));
+    }
+
+    Ok(values)
+}
+
+/// Estimate the length of a dictionary header's entries, front-coded or
+/// plain, whichever is shorter. Escaping is not accounted for.
+pub(crate) fn header_len(values: &[String]) -> usize {
+    let separators = values.len().saturating_sub(1);
+    let plain: usize = values.iter().map(String::len).sum();
+    let front_coded: usize = encode(values)
+        .iter()
+        .enumerate()
+        .map(|(i, (shared, suffix))| match i {
+            0 => suffix.len(),
+            _ => shared.to_string().len() + 1 + suffix.len(),
+        })
+        .sum::<usize>()
+        + 1;
+    plain.min(front_coded) + separators
+}
+
+/// Length in bytes of the longest common prefix, on a character boundary.
+fn shared_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .take_while(|((_, x), y)| x == y)
+        .last()
+        .map_or(0, |((i, c), _)| i + c.len_utf8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_encode_shared_prefixes() {
+        let values = strings(&["/api/v1/users", "/api/v1/orders", "/health"]);
+        assert_eq!(
+            encode(&values),
+            vec![(0, "/api/v1/users"), (8, "orders"), (1, "health")]
+        );
+    }
+
+    #[test]
+    fn test_encode_respects_char_boundaries() {
+        let values = strings(&["café", "cafè"]);
+        assert_eq!(encode(&values)[1], (3, "è"));
+    }
+
+    #[test]
+    fn test_decode_roundtrip() {
+        let values = strings(&["/var/log/app/error.log", "/var/log/app/access.log", "x", ""]);
+        let entries: Vec<String> = encode(&values)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (shared, suffix))| {
+                if i == 0 {
+                    suffix.to_string()
+                } else {
+                    format!("{}~{}", shared, suffix)
+                }
+            })
+            .collect();
+        assert_eq!(decode(entries, 0).unwrap(), values);
+    }
+
+    #[test]
+    fn test_header_len() {
+        assert_eq!(header_len(&strings(&["ab", "cd"])), 5);
+        // First entry, `7~y.gz`, the `~` flag and one separator
+        let values = strings(&["/srv/a/x.gz", "/srv/a/y.gz"]);
+        assert_eq!(header_len(&values), 11 + 6 + 1 + 1);
+    }
+
+    #[test]
+    fn test_decode_invalid_entries() {
+        assert!(decode(strings(&["abc", "def"]), 0).is_err());
+        assert!(decode(strings(&["abc", "9~def"]), 0).is_err());
+        assert!(decode(strings(&["abc", "x~def"]), 0).is_err());
+        assert_eq!(decode(strings(&["abc", "1~~"]), 0).unwrap(), strings(&["abc", "a~"]));
+    }
+}
@@ -0,0 +1,243 @@
+//! Test data generation from hand-written ALS.
+//!
+//! ALS operators describe how values are produced rather than listing them,
+//! which makes a short document a convenient way to specify test data:
+//!
+//! ```text
+//! $default:info|warn|error
+//! #id #level #active
+//! 1>1000|_0*8 _1 _2|T~F*2
+//! ```
+//!
+//! [`AlsGenerator`] expands such a spec into any number of rows. Columns
+//! shorter than the requested row count repeat from their start, longer
+//! ones are cut. With random sampling, columns built from dictionary
+//! references draw each row at random from their own values instead, so
+//! `_0*8 _1 _2` gives roughly 80% of the first entry in shuffled order.
+
+use super::document::{AlsDocument, ColumnStream};
+use super::operator::AlsOperator;
+use super::parser::AlsParser;
+use crate::convert::TabularData;
+use crate::error::{AlsError, Result};
+use crate::rng::Rng;
+
+/// Expands an ALS spec into rows of test data.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::AlsGenerator;
+/// use als_compression::convert::csv::to_csv;
+///
+/// let data = AlsGenerator::parse("#id #state\n1>3|open~closed*2").unwrap().with_rows(5).generate().unwrap();
+/// assert_eq!(to_csv(&data).unwrap().trim_end(), "id,state\n1,open\n2,closed\n3,open\n1,closed\n2,open");
+/// ```
+#[derive(Debug, Clone)]
+pub struct AlsGenerator<'a> {
+    doc: AlsDocument<'a>,
+    rows: Option<usize>,
+    seed: u64,
+    random_sampling: bool,
+}
+
+impl<'a> AlsGenerator<'a> {
+    /// Create a generator for a parsed spec.
+    pub fn new(doc: AlsDocument<'a>) -> Self {
+        Self {
+            doc,
+            rows: None,
+            seed: 0,
+            random_sampling: false,
+        }
+    }
+
+    /// Set the number of rows to generate.
+    ///
+    /// Defaults to the length of the longest column of the spec.
+    pub fn with_rows(mut self, rows: usize) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Set the seed of random sampling. The same spec, row count and seed
+    /// always give the same data.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Draw the rows of columns built from dictionary references at random
+    /// from the values of the column, rather than repeating them in order.
+    pub fn with_random_sampling(mut self, random_sampling: bool) -> Self {
+        self.random_sampling = random_sampling;
+        self
+    }
+
+    /// Generate the rows. Value types are inferred as when converting ALS
+    /// to JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column fails to expand, or if a column has no
+    /// values to repeat while rows are requested.
+    pub fn generate(&self) -> Result<TabularData<'static>> {
+        let dictionary = self.doc.default_dictionary().map(Vec::as_slice);
+        let rows = self
+            .rows
+            .unwrap_or_else(|| self.doc.streams.iter().map(ColumnStream::expanded_count).max().unwrap_or(0));
+        let mut rng = Rng::new(self.seed);
+
+        let mut columns: Vec<Vec<String>> = Vec::with_capacity(self.doc.streams.len());
+        for (index, stream) in self.doc.streams.iter().enumerate() {
+            let values = if stream.has_column_refs() {
+                expand_derived(stream, dictionary, &columns, rows)?
+            } else {
+                stream.expand(dictionary)?
+            };
+            if values.is_empty() && rows > 0 {
+                return Err(AlsError::AlsSyntaxError {
+                    position: 0,
+                    message: format!("Column '{}' has no values to generate from", self.column_name(index)),
+                });
+            }
+
+            let values = if self.random_sampling && !stream.has_column_refs() && uses_dictionary(stream) {
+                (0..rows).map(|_| values[rng.below(values.len() as u64) as usize].clone()).collect()
+            } else {
+                values.iter().cycle().take(rows).cloned().collect()
+            };
+            columns.push(values);
+        }
+
+        Ok(AlsParser::columns_to_tabular(&self.doc, columns, true))
+    }
+
+    fn column_name(&self, index: usize) -> &str {
+        self.doc.schema.get(index).map_or("?", String::as_str)
+    }
+}
+
+impl AlsGenerator<'static> {
+    /// Create a generator for ALS spec text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spec is not valid ALS.
+    pub fn parse(spec: &str) -> Result<Self> {
+        Ok(Self::new(AlsParser::new().parse(spec)?))
+    }
+}
+
+/// Expand a stream with column references over `rows` generated rows.
+///
+/// The stream is expanded once per stretch of its own length, each time
+/// against the matching stretch of the columns it refers to, so derived
+/// values follow their source however it was repeated or sampled.
+fn expand_derived(
+    stream: &ColumnStream,
+    dictionary: Option<&[String]>,
+    columns: &[Vec<String>],
+    rows: usize,
+) -> Result<Vec<String>> {
+    let len = stream.expanded_count();
+    if len == 0 || rows == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut values = Vec::with_capacity(rows + len);
+    for start in (0..rows).step_by(len) {
+        // Source rows past the end wrap around, so the last stretch is full
+        let window: Vec<Vec<String>> = columns
+            .iter()
+            .map(|column| (start..start + len).map(|row| column[row % rows].clone()).collect())
+            .collect();
+        values.extend(stream.expand_with_columns(dictionary, &window)?);
+    }
+    values.truncate(rows);
+    Ok(values)
+}
+
+/// Check if every value of the stream comes from the dictionary or from
+/// repeating it.
+fn uses_dictionary(stream: &ColumnStream) -> bool {
+    fn from_dictionary(op: &AlsOperator) -> bool {
+        match op {
+            AlsOperator::DictRef(_) => true,
+            AlsOperator::Multiply { value, .. } => from_dictionary(value),
+            _ => false,
+        }
+    }
+    stream.operators.iter().any(from_dictionary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::Value;
+
+    fn strings(data: &TabularData, column: usize) -> Vec<String> {
+        data.columns[column].values.iter().map(|v| v.to_string_repr().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_generate_repeats_and_cuts_columns() {
+        let generator = AlsGenerator::parse("#id #level\n1>4|info~warn*2").unwrap();
+
+        let data = generator.generate().unwrap();
+        assert_eq!(data.row_count, 4);
+        assert_eq!(strings(&data, 1), vec!["info", "warn", "info", "warn"]);
+
+        let data = generator.clone().with_rows(6).generate().unwrap();
+        assert_eq!(data.columns[0].values[4..], [Value::Integer(1), Value::Integer(2)]);
+
+        let data = generator.with_rows(3).generate().unwrap();
+        assert_eq!(strings(&data, 0), vec!["1", "2", "3"]);
+        assert_eq!(data.row_count, 3);
+    }
+
+    #[test]
+    fn test_generate_random_sampling() {
+        let spec = "$default:a|b\n#id #tag\n1>2|_0*9 _1";
+        let generator = AlsGenerator::parse(spec).unwrap().with_rows(1000).with_random_sampling(true);
+
+        let data = generator.clone().with_seed(7).generate().unwrap();
+        let tags = strings(&data, 1);
+        let a = tags.iter().filter(|tag| *tag == "a").count();
+        assert!((800..1000).contains(&a), "{} of 1000 rows drew 'a'", a);
+        // Not just the spec repeated in order
+        assert!(tags.iter().enumerate().any(|(row, tag)| tag == "b" && row % 10 != 9));
+        // Columns without dictionary references still repeat in order
+        assert_eq!(strings(&data, 0)[..4], ["1", "2", "1", "2"]);
+
+        assert_eq!(strings(&generator.clone().with_seed(7).generate().unwrap(), 1), tags);
+        assert_ne!(strings(&generator.with_seed(8).generate().unwrap(), 1), tags);
+    }
+
+    #[test]
+    fn test_generate_column_refs_follow_source() {
+        let spec = "$default:buy|sell\n#side #sign\n_0 _1 _1|@0(buy:+1 sell:-1)*3";
+        let data = AlsGenerator::parse(spec)
+            .unwrap()
+            .with_rows(200)
+            .with_random_sampling(true)
+            .generate()
+            .unwrap();
+
+        for (side, sign) in strings(&data, 0).iter().zip(strings(&data, 1)) {
+            assert_eq!(sign, if side == "buy" { "1" } else { "-1" });
+        }
+    }
+
+    #[test]
+    fn test_generate_empty_column() {
+        let mut doc = AlsDocument::with_schema(vec!["a"]);
+        doc.add_stream(ColumnStream::new());
+        let generator = AlsGenerator::new(doc);
+        assert_eq!(generator.generate().unwrap().row_count, 0);
+        assert!(matches!(
+            generator.with_rows(3).generate(),
+            Err(AlsError::AlsSyntaxError { message, .. }) if message.contains("'a'")
+        ));
+    }
+}
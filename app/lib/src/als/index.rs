@@ -0,0 +1,229 @@
+//! Per-column value indexes.
+//!
+//! An index maps each distinct value of a column to the row ranges holding
+//! it, so equality lookups don't need to expand the column. Indexes are
+//! appended to the document, one line per indexed column:
+//!
+//! ```text
+//! !idx 1 done:0+2,5 pending:2+3
+//! ```
+//!
+//! Each entry is an escaped value, a `:`, and its row ranges separated by
+//! `,`. A range is written as `start+len`, or just `start` for a single row.
+//! Entries are sorted by value.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::escape::{escape_als_string, unescape_als_string};
+use super::trailer::TrailerLine;
+use crate::error::Result;
+
+/// Prefix of an index line.
+pub const INDEX_PREFIX: &str = "!idx ";
+
+/// Sorted mapping from the values of a column to the rows holding them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnIndex {
+    entries: Vec<(String, Vec<Range<usize>>)>,
+}
+
+impl ColumnIndex {
+    /// Build an index over the expanded values of a column.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::als::ColumnIndex;
+    ///
+    /// let index = ColumnIndex::build(&["a", "a", "b", "a"]);
+    /// assert_eq!(index.lookup("a"), Some(&[0..2, 3..4][..]));
+    /// assert_eq!(index.rows("b"), vec![2]);
+    /// assert_eq!(index.lookup("c"), None);
+    /// ```
+    pub fn build<S: AsRef<str>>(values: &[S]) -> Self {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by(|&a, &b| values[a].as_ref().cmp(values[b].as_ref()).then(a.cmp(&b)));
+
+        let mut entries: Vec<(String, Vec<Range<usize>>)> = Vec::new();
+        for row in order {
+            let value = values[row].as_ref();
+            match entries.last_mut() {
+                Some((last, ranges)) if last == value => match ranges.last_mut() {
+                    Some(range) if range.end == row => range.end += 1,
+                    _ => ranges.push(row..row + 1),
+                },
+                _ => entries.push((value.to_string(), std::iter::once(row..row + 1).collect())),
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Get the row ranges holding `value`.
+    pub fn lookup(&self, value: &str) -> Option<&[Range<usize>]> {
+        self.entries
+            .binary_search_by(|(entry, _)| entry.as_str().cmp(value))
+            .ok()
+            .map(|i| self.entries[i].1.as_slice())
+    }
+
+    /// Get the rows holding `value`, in ascending order.
+    pub fn rows(&self, value: &str) -> Vec<usize> {
+        self.lookup(value)
+            .map(|ranges| ranges.iter().cloned().flatten().collect())
+            .unwrap_or_default()
+    }
+
+    /// Count the rows holding `value`.
+    pub fn count(&self, value: &str) -> usize {
+        self.lookup(value)
+            .map(|ranges| ranges.iter().map(|range| range.len()).sum())
+            .unwrap_or(0)
+    }
+
+    /// Iterate over the indexed values and their row ranges, in value order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[Range<usize>])> {
+        self.entries.iter().map(|(value, ranges)| (value.as_str(), ranges.as_slice()))
+    }
+
+    /// Get the number of distinct values in the index.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the index has no values.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the index line for `column`, without a trailing newline.
+    pub(crate) fn write_line(&self, output: &mut String, column: usize) {
+        output.push_str(INDEX_PREFIX);
+        output.push_str(&column.to_string());
+        for (value, ranges) in &self.entries {
+            output.push(' ');
+            output.push_str(&escape_als_string(value));
+            output.push(':');
+            for (i, range) in ranges.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                output.push_str(&range.start.to_string());
+                if range.len() > 1 {
+                    output.push('+');
+                    output.push_str(&range.len().to_string());
+                }
+            }
+        }
+    }
+
+    /// Read an `!idx` line at offset `position` back into the number of
+    /// the column it indexes and the index, refusing unsorted entries.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(usize, Self)> {
+        let line = TrailerLine::new(line, INDEX_PREFIX, "index", position)?;
+        let mut fields = line.fields().into_iter();
+        let column = fields
+            .next()
+            .and_then(|field| field.parse().ok())
+            .ok_or_else(|| line.error("Index line is missing its column"))?;
+
+        let mut entries = Vec::new();
+        for field in fields {
+            let (value, ranges) = field
+                .rsplit_once(':')
+                .ok_or_else(|| line.error(format!("Index entry has no row ranges: {}", field)))?;
+            let ranges = ranges
+                .split(',')
+                .map(|range| parse_range(range).ok_or_else(|| line.error(format!("Invalid row range: {}", range))))
+                .collect::<Result<Vec<_>>>()?;
+            entries.push((unescape_als_string(value)?, ranges));
+        }
+
+        if entries.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(line.error(format!("Index entries of column {} are not sorted", column)));
+        }
+
+        Ok((column, Self { entries }))
+    }
+}
+
+/// Split on spaces that are not escaped.
+pub(super) fn split_unescaped_spaces(s: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ' ' => {
+                if i > start {
+                    fields.push(&s[start..i]);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < s.len() {
+        fields.push(&s[start..]);
+    }
+    fields
+}
+
+/// Parse a `start+len` or `start` row range.
+pub(super) fn parse_range(s: &str) -> Option<Range<usize>> {
+    let (start, len) = match s.split_once('+') {
+        Some((start, len)) => (start.parse::<usize>().ok()?, len.parse::<usize>().ok()?),
+        None => (s.parse::<usize>().ok()?, 1),
+    };
+    if len == 0 {
+        return None;
+    }
+    Some(start..start.checked_add(len)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_merges_adjacent_rows() {
+        let index = ColumnIndex::build(&["x", "y", "y", "x", "y"]);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.lookup("x"), Some(&[0..1, 3..4][..]));
+        assert_eq!(index.lookup("y"), Some(&[1..3, 4..5][..]));
+        assert_eq!(index.count("y"), 3);
+        assert_eq!(index.rows("z"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_line_roundtrip() {
+        let index = ColumnIndex::build(&["a b", "x:y", "", "a b", "!", "x:y", "x:y"]);
+        let mut line = String::new();
+        index.write_line(&mut line, 3);
+        assert!(line.starts_with("!idx 3 "));
+
+        let (column, parsed) = ColumnIndex::parse_line(&line, 0).unwrap();
+        assert_eq!(column, 3);
+        assert_eq!(parsed, index);
+        assert_eq!(parsed.rows("x:y"), vec![1, 5, 6]);
+    }
+
+    #[test]
+    fn test_line_format() {
+        let mut line = String::new();
+        ColumnIndex::build(&["done", "done", "pending", "pending", "pending", "done"]).write_line(&mut line, 1);
+        assert_eq!(line, "!idx 1 done:0+2,5 pending:2+3");
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert!(ColumnIndex::parse_line("!idx x a:0", 0).is_err());
+        assert!(ColumnIndex::parse_line("!idx 0 a", 0).is_err());
+        assert!(ColumnIndex::parse_line("!idx 0 a:1+0", 0).is_err());
+        assert!(ColumnIndex::parse_line("!idx 0 b:0 a:1", 0).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Metadata of the file a document was compressed from.
+//!
+//! Users coming from gzip and zstd expect an archive to remember the file
+//! it came from, so that decompressing restores its modification time. A
+//! document can record the file's name, modification time, permissions and
+//! size in a line after the streams:
+//!
+//! ```text
+//! !meta name=events.csv mtime=1704067200 mode=644 size=5120
+//! ```
+//!
+//! Each field is optional. The name is escaped as values are, the time is
+//! in seconds since the Unix epoch and the permissions are in octal.
+
+use std::fs::Metadata;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::escape::{escape_als_string, unescape_als_string};
+use super::trailer::TrailerLine;
+use crate::error::Result;
+
+/// Prefix of a file metadata line.
+pub const META_PREFIX: &str = "!meta ";
+
+/// Facts about the file a document was compressed from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// Name of the file, without its directory.
+    pub name: Option<String>,
+    /// Last modification time, in seconds since the Unix epoch.
+    pub modified: Option<i64>,
+    /// Unix permission bits, such as `0o644`.
+    pub mode: Option<u32>,
+    /// Size of the file in bytes.
+    pub size: Option<u64>,
+}
+
+impl FileMetadata {
+    /// Describe a file from its name and what the filesystem reports.
+    ///
+    /// Permissions are only recorded on Unix.
+    pub fn from_fs(name: Option<String>, metadata: &Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        let modified = metadata.modified().ok().map(|time| match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        });
+        Self {
+            name,
+            modified,
+            mode,
+            size: Some(metadata.len()),
+        }
+    }
+
+    /// Get the modification time, if recorded.
+    pub fn modified_time(&self) -> Option<SystemTime> {
+        self.modified.map(|seconds| match u64::try_from(seconds) {
+            Ok(after) => UNIX_EPOCH + Duration::from_secs(after),
+            Err(_) => UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()),
+        })
+    }
+
+    /// Check if nothing is recorded.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Read the metadata line of a document without parsing the rest.
+    ///
+    /// Returns `None` if the document records no file metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metadata line can't be parsed.
+    pub fn find(input: &str) -> Result<Option<Self>> {
+        // Values escape `!`, so only a trailer line can start with it
+        let Some(start) = input.rfind(&format!("\n{}", META_PREFIX)).map(|start| start + 1) else {
+            return Ok(None);
+        };
+        let line = input[start..].lines().next().unwrap_or_default();
+        Self::parse_line(line.trim_end_matches('\r'), start).map(Some)
+    }
+
+    /// Append the metadata line to `output`, preceded by a newline, unless
+    /// nothing is recorded.
+    pub(crate) fn write_line(&self, output: &mut String) {
+        let fields = [
+            self.name.as_ref().map(|name| format!("name={}", escape_als_string(name))),
+            self.modified.map(|modified| format!("mtime={}", modified)),
+            self.mode.map(|mode| format!("mode={:o}", mode)),
+            self.size.map(|size| format!("size={}", size)),
+        ];
+        let fields: Vec<String> = fields.into_iter().flatten().collect();
+        if !fields.is_empty() {
+            output.push('\n');
+            output.push_str(META_PREFIX);
+            output.push_str(&fields.join(" "));
+        }
+    }
+
+    /// Read a `!meta` line at offset `position` back into the metadata of
+    /// the source file, refusing fields it does not know.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<Self> {
+        let line = TrailerLine::new(line, META_PREFIX, "file metadata", position)?;
+        let mut metadata = Self::default();
+        for field in line.fields() {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| line.error(format!("Invalid file metadata field: {}", field)))?;
+            let invalid = || line.error(format!("Invalid file metadata {}: {}", key, value));
+            match key {
+                "name" => metadata.name = Some(unescape_als_string(value)?),
+                "mtime" => metadata.modified = Some(value.parse().map_err(|_| invalid())?),
+                "mode" => metadata.mode = Some(u32::from_str_radix(value, 8).map_err(|_| invalid())?),
+                "size" => metadata.size = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(line.error(format!("Unknown file metadata field: {}", key))),
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::{AlsParser, AlsSerializer, Feature};
+
+    #[test]
+    fn test_line_round_trip() {
+        let metadata = FileMetadata {
+            name: Some("daily events.csv".to_string()),
+            modified: Some(1_704_067_200),
+            mode: Some(0o640),
+            size: Some(5120),
+        };
+        let mut line = String::new();
+        metadata.write_line(&mut line);
+        assert_eq!(line, "\n!meta name=daily\\ events.csv mtime=1704067200 mode=640 size=5120");
+        assert_eq!(FileMetadata::parse_line(&line[1..], 0).unwrap(), metadata);
+
+        let partial = FileMetadata::parse_line("!meta mtime=-86400", 0).unwrap();
+        assert_eq!(partial.modified_time(), Some(UNIX_EPOCH - Duration::from_secs(86_400)));
+        assert_eq!(partial.name, None);
+
+        assert!(FileMetadata::parse_line("!meta mode=9", 0).is_err());
+        assert!(FileMetadata::parse_line("!meta owner=root", 0).is_err());
+        assert!(FileMetadata::parse_line("!meta size", 0).is_err());
+
+        let mut line = String::new();
+        FileMetadata::default().write_line(&mut line);
+        assert!(line.is_empty());
+    }
+
+    #[test]
+    fn test_document_metadata() {
+        let parser = AlsParser::new();
+        let mut doc = parser.parse("!v2 +meta\n#id\n1>3\n!meta name=a.csv size=8").unwrap();
+        let metadata = doc.file_metadata.clone().unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("a.csv"));
+        assert_eq!(metadata.size, Some(8));
+        assert!(doc.features().contains(&Feature::Meta));
+        assert_eq!(AlsSerializer::new().serialize(&doc), "!v2 +meta\n#id\n1>3\n!meta name=a.csv size=8");
+
+        let found = FileMetadata::find("!v2 +meta\n#id\n1>3\n!meta name=a.csv size=8\n!zone 0 1 3").unwrap();
+        assert_eq!(found.as_ref(), Some(&metadata));
+        assert_eq!(FileMetadata::find("!v2\n#id\n1>3").unwrap(), None);
+
+        // Equal data compares equal wherever it came from
+        let plain = parser.parse("!v2\n#id\n1>3").unwrap();
+        assert_eq!(doc, plain);
+        doc.canonicalize();
+        assert!(doc.file_metadata.is_none());
+    }
+
+    #[test]
+    fn test_from_fs() {
+        let path = std::env::temp_dir().join(format!("als-meta-{}.csv", std::process::id()));
+        std::fs::write(&path, "id\n1\n").unwrap();
+        let metadata = FileMetadata::from_fs(Some("a.csv".to_string()), &std::fs::metadata(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(metadata.size, Some(5));
+        let age = SystemTime::now().duration_since(metadata.modified_time().unwrap()).unwrap();
+        assert!(age < Duration::from_secs(3600));
+        #[cfg(unix)]
+        assert!(metadata.mode.is_some());
+    }
+}
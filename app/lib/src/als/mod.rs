@@ -3,19 +3,79 @@
 //! This module contains the core data structures for representing ALS documents,
 //! including operators, column streams, and document structures.
 
+pub(crate) mod bitpack;
+mod bloom;
+mod canonical;
+mod chunk;
+mod container;
+mod cycle;
+mod delta;
+mod downgrade;
+pub(crate) mod datetime;
+pub(crate) mod dict_ref;
 mod document;
+mod edit;
+mod feature;
+pub(crate) mod foreign_key;
+pub(crate) mod float;
+mod generate;
+pub(crate) mod front_coding;
 pub mod escape;
+mod index;
+mod meta;
 mod operator;
-mod parser;
+pub(crate) mod parser;
+mod partial;
+mod policy;
+pub(crate) mod quantize;
+pub(crate) mod redact;
+mod scale;
+pub(crate) mod search;
+mod schema;
 mod serializer;
+mod shared;
+mod signature;
+mod size;
+pub(crate) mod slice;
+mod store;
 mod tokenizer;
+mod trailer;
+mod transform;
+pub(crate) mod transpose;
+pub(crate) mod truncate;
+mod zone;
 
+pub use bitpack::{bit_pack, bit_unpack, PACKED_BLOCK_SIZE};
+pub use bloom::{BloomFilter, BLOOM_PREFIX};
+pub use container::{AlsContainerReader, AlsContainerWriter, ContainerEntry, CONTAINER_HEADER};
+pub use cycle::CalendarCycle;
+pub use delta::{DeltaOp, DeltaScript, DELTA_PREFIX};
 pub use document::{AlsDocument, ColumnStream, FormatIndicator};
+pub use feature::Feature;
+pub use foreign_key::{ForeignKey, FOREIGN_KEY_PREFIX};
+pub use generate::AlsGenerator;
+pub use float::{xor_decode, xor_encode, MAX_FLOAT_DELTA_PLACES};
 pub use escape::{
     decode_als_value, encode_als_value, escape_als_string, is_empty_token, is_null_token,
     needs_escaping, unescape_als_string, EMPTY_TOKEN, NULL_TOKEN,
 };
+pub use index::{ColumnIndex, INDEX_PREFIX};
+pub use meta::{FileMetadata, META_PREFIX};
 pub use operator::AlsOperator;
-pub use parser::AlsParser;
+pub use parser::{AlsParser, Expander};
+pub use partial::PartialDocument;
+#[cfg(feature = "parallel")]
+pub use parser::ParExpand;
+pub use quantize::{Quantization, MAX_QUANTIZATION_DIGITS, QUANTIZE_PREFIX};
+pub use redact::{PseudonymKey, Redaction, REDACT_PREFIX};
+pub use schema::{ColumnContract, ColumnDescriptor, SchemaDescriptor, SchemaValidator, SchemaViolation};
 pub use serializer::{AlsPrettyPrinter, AlsSerializer};
+pub use shared::SharedDictionary;
+pub use signature::{split_signature, AlsSignature, SIGNATURE_PREFIX};
+#[cfg(feature = "signing")]
+pub use signature::{parse_signing_key, parse_verifying_key, sign_document, verify_document, SigningKey, VerifyingKey};
+pub use store::{StoreFrame, StoredFormat, STORE_PREFIX};
 pub use tokenizer::{Token, Tokenizer, VersionType};
+pub use truncate::{TimeTruncation, TRUNCATE_PREFIX};
+pub use transform::{CaseTransform, ValueTransform, GROUP_SEPARATORS, MAX_TRANSFORM_WIDTH};
+pub use zone::{ZoneMap, ZoneOrder, ZONE_PREFIX};
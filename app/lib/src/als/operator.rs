@@ -3,12 +3,25 @@
 //! This module defines the `AlsOperator` enum which represents the various
 //! compression operators used in the ALS format.
 
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::cycle::CalendarCycle;
+use super::escape::EMPTY_TOKEN;
+use super::datetime::format_timestamp;
+use super::float::{format_scaled, MAX_FLOAT_DELTA_PLACES};
+use super::transform::ValueTransform;
 use crate::config::CompressorConfig;
 use crate::error::{AlsError, Result};
 
 /// Default maximum range expansion limit.
 const DEFAULT_MAX_RANGE_EXPANSION: usize = 10_000_000;
 
+/// Most values reserved up front when an operator's count comes straight
+/// from the document; larger expansions grow as they go.
+const MAX_EXPAND_RESERVE: usize = 1 << 16;
+
 /// Represents a single ALS compression operator.
 ///
 /// ALS uses several operators to compress data:
@@ -17,19 +30,32 @@ const DEFAULT_MAX_RANGE_EXPANSION: usize = 10_000_000;
 /// - `Multiply`: Repeated values (`val*n`)
 /// - `Toggle`: Alternating patterns (`val1~val2*n`)
 /// - `DictRef`: Dictionary references (`_i`)
+/// - `ColumnRef`: Values derived from another column (`@i(a:b)*n`)
+/// - `DateRange`: Evenly spaced timestamps (`%{fmt}start+step*n`)
+/// - `Cycle`: Built-in calendar cycles (`&dow+0*n`)
+/// - `Transform`: Case and padding applied to another operator (`^ID{3}(1>5)`)
+/// - `BoolRuns`: Run lengths of a two-valued column (`?true~false:3.1.2`)
+/// - `Split`: Delimited values stored as one sub-stream per segment (`</2(a b)(x y)`)
+/// - `FloatDelta`: Decimals stored as differences of scaled integers (`%.2:2153+3-1`)
 ///
 /// # Serialization
 ///
 /// This type supports zero-copy serialization via rkyv for the non-recursive
 /// variants. The `Multiply` variant uses `Box<AlsOperator>` which requires
 /// special handling during serialization.
-#[derive(Debug, Clone, PartialEq)]
-pub enum AlsOperator {
+///
+/// # Equality
+///
+/// Operators compare structurally, so `x*1` differs from `x`. Compare
+/// [`canonicalize`](Self::canonicalize)d operators, or whole streams, to
+/// ignore the encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AlsOperator<'a> {
     /// Raw value: uncompressed literal string.
     ///
     /// Used when no pattern is detected or when compression would not
     /// provide benefit.
-    Raw(String),
+    Raw(Cow<'a, str>),
 
     /// Range operator: `start>end` or `start>end:step`.
     ///
@@ -62,7 +88,7 @@ pub enum AlsOperator {
     /// - `(1>3)*2` expands to `1, 2, 3, 1, 2, 3`
     Multiply {
         /// The value to repeat (can be any operator)
-        value: Box<AlsOperator>,
+        value: Box<AlsOperator<'a>>,
         /// Number of times to repeat the value
         count: usize,
     },
@@ -95,11 +121,156 @@ pub enum AlsOperator {
     /// - `_0` references the first dictionary entry
     /// - `_5` references the sixth dictionary entry
     DictRef(usize),
+
+    /// Column reference: `@i*n` or `@i(src:dst ...)*n`.
+    ///
+    /// Produces `count` values by reading the same rows of an earlier
+    /// column and translating them through `mapping`. Values without a
+    /// mapping entry are copied unchanged. Used when two columns move in
+    /// lockstep, so the second stream can be derived from the first.
+    ///
+    /// # Examples
+    ///
+    /// - `@0(buy:+1 sell:-1)*4` with column 0 `buy, sell, sell, buy`
+    ///   expands to `+1, -1, -1, +1`
+    /// - `@2*3` copies the first three values of column 2
+    ColumnRef {
+        /// Index of the referenced column in the schema
+        column: usize,
+        /// Source value to output value translations
+        mapping: Vec<(String, String)>,
+        /// Number of rows covered by the reference
+        count: usize,
+    },
+
+    /// Date range operator: `%{format}start+step*count`.
+    ///
+    /// Represents `count` evenly spaced timestamps starting at `start`
+    /// (seconds since the Unix epoch, UTC), each rendered with `format`.
+    /// The format uses the strftime-like specifiers `%Y`, `%m`, `%d`,
+    /// `%H`, `%M`, `%S` and `%%`.
+    ///
+    /// # Examples
+    ///
+    /// - `%{%Y-%m-%d}1704067200+86400*3` expands to
+    ///   `2024-01-01, 2024-01-02, 2024-01-03`
+    /// - `%{%H:%M}0+1800*2` expands to `00:00, 00:30`
+    DateRange {
+        /// First timestamp in seconds since the Unix epoch
+        start: i64,
+        /// Seconds between consecutive timestamps (can be negative)
+        step: i64,
+        /// Number of timestamps to generate
+        count: usize,
+        /// Format used to render each timestamp
+        format: String,
+    },
+
+    /// Calendar cycle operator: `&name+offset*count` or `&name+offset:run*count`.
+    ///
+    /// Cycles through the labels of a built-in `CalendarCycle`, emitting
+    /// each label `run` times in a row before advancing, for `count` total
+    /// elements. The first element is element `offset` of the repeated
+    /// cycle, so columns can start part way through a run.
+    ///
+    /// # Examples
+    ///
+    /// - `&dow+5*4` expands to `Sat, Sun, Mon, Tue`
+    /// - `&qtr+0:2*4` expands to `Q1, Q1, Q2, Q2`
+    /// - `&qtr+3:2*4` expands to `Q2, Q3, Q3, Q4`
+    Cycle {
+        /// The built-in cycle of labels
+        cycle: CalendarCycle,
+        /// Position of the first element in the cycle, counting each label
+        /// `run` times
+        offset: usize,
+        /// Number of consecutive rows sharing each label
+        run: usize,
+        /// Total number of elements to generate
+        count: usize,
+    },
+
+    /// Transform operator: `^prefix{spec}suffix(inner)`.
+    ///
+    /// Applies a `ValueTransform` (case conversion, zero-padding, prefix
+    /// and suffix) to every value produced by the inner operator.
+    ///
+    /// # Examples
+    ///
+    /// - `^USER{3}(1>3)` expands to `USER001, USER002, USER003`
+    /// - `^{U}(&dow+0*2)` expands to `MON, TUE`
+    Transform {
+        /// The operator producing the untransformed values
+        value: Box<AlsOperator<'a>>,
+        /// The transform applied to each value
+        transform: ValueTransform,
+    },
+
+    /// Boolean run operator: `?a~b:r1.r2.r3`.
+    ///
+    /// Encodes a two-valued column as alternating runs. The first run uses
+    /// `labels[0]`, the second `labels[1]`, the third `labels[0]` again,
+    /// and so on. Used for boolean columns, where runs are far cheaper
+    /// than spelling out every value.
+    ///
+    /// # Examples
+    ///
+    /// - `?true~false:2.1.3` expands to
+    ///   `true, true, false, true, true, true`
+    /// - `?Y~N:1.1` expands to `Y, N`
+    BoolRuns {
+        /// The two labels, in order of first appearance
+        labels: [String; 2],
+        /// Lengths of the alternating runs
+        runs: Vec<usize>,
+    },
+
+    /// Split operator: `<sepN(part1)(part2)...`.
+    ///
+    /// Stores delimited values such as URLs or paths as `N` sub-streams,
+    /// one per segment. Row `i` joins the `i`-th value of every part with
+    /// the separator; the last part holds the remainder of each value,
+    /// including any further separators. Without a separator (`<N`) the
+    /// parts are concatenated, which stores hierarchical enums such as
+    /// availability zones as a parent and a child reference.
+    ///
+    /// # Examples
+    ///
+    /// - `</2(a b)(x y)` expands to `a/x, b/y`
+    /// - `</2(srv*2)(1>2)` expands to `srv/1, srv/2`
+    /// - `<2(us-east-1*2)(a b)` expands to `us-east-1a, us-east-1b`
+    Split {
+        /// Separator placed between segments, or `None` to concatenate them
+        separator: Option<char>,
+        /// Operators producing each segment, in order
+        parts: Vec<Vec<AlsOperator<'a>>>,
+    },
+
+    /// Float delta operator: `%.places:start+d1-d2...`.
+    ///
+    /// Encodes decimals of up to `places` places as integers scaled by
+    /// `10^places`: the first value, then the difference from each value to
+    /// the next. Values are written as floats print, without trailing
+    /// zeros. Used for measurement columns, where readings drift by a few
+    /// units of the last place.
+    ///
+    /// # Examples
+    ///
+    /// - `%.2:2153+3-1` expands to `21.53, 21.56, 21.55`
+    /// - `%.1:-5+10+205` expands to `-0.5, 0.5, 21`
+    FloatDelta {
+        /// Digits after the decimal point, from 1 to 15
+        places: u8,
+        /// First value, scaled
+        start: i64,
+        /// Differences between consecutive scaled values
+        deltas: Vec<i64>,
+    },
 }
 
-impl AlsOperator {
+impl<'a> AlsOperator<'a> {
     /// Create a new Raw operator with the given value.
-    pub fn raw<S: Into<String>>(value: S) -> Self {
+    pub fn raw<S: Into<Cow<'a, str>>>(value: S) -> Self {
         AlsOperator::Raw(value.into())
     }
 
@@ -253,13 +424,21 @@ impl AlsOperator {
         count as u64
     }
 
+    /// Get the value of row `row` of a range from `start` by `step`.
+    ///
+    /// `row` must be within the range: its values fit in an i64, though
+    /// the offset from `start` may not.
+    pub(crate) fn range_value_at(start: i64, step: i64, row: usize) -> i64 {
+        (i128::from(start) + row as i128 * i128::from(step)) as i64
+    }
+
     /// Create a new Multiply operator.
     ///
     /// # Arguments
     ///
     /// * `value` - The operator to repeat
     /// * `count` - Number of times to repeat
-    pub fn multiply(value: AlsOperator, count: usize) -> Self {
+    pub fn multiply(value: AlsOperator<'a>, count: usize) -> Self {
         AlsOperator::Multiply {
             value: Box::new(value),
             count,
@@ -302,6 +481,101 @@ impl AlsOperator {
         AlsOperator::DictRef(index)
     }
 
+    /// Create a new ColumnRef operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `column` - Index of the referenced column
+    /// * `mapping` - Source value to output value translations
+    /// * `count` - Number of rows covered by the reference
+    pub fn column_ref(column: usize, mapping: Vec<(String, String)>, count: usize) -> Self {
+        AlsOperator::ColumnRef {
+            column,
+            mapping,
+            count,
+        }
+    }
+
+    /// Create a new DateRange operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - First timestamp in seconds since the Unix epoch
+    /// * `step` - Seconds between consecutive timestamps
+    /// * `count` - Number of timestamps to generate
+    /// * `format` - Format used to render each timestamp
+    pub fn date_range<S: Into<String>>(start: i64, step: i64, count: usize, format: S) -> Self {
+        AlsOperator::DateRange {
+            start,
+            step,
+            count,
+            format: format.into(),
+        }
+    }
+
+    /// Create a new Cycle operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycle` - The built-in cycle of labels
+    /// * `offset` - Position of the first label in the cycle
+    /// * `run` - Number of consecutive rows sharing each label
+    /// * `count` - Total number of elements to generate
+    pub fn cycle(cycle: CalendarCycle, offset: usize, run: usize, count: usize) -> Self {
+        AlsOperator::Cycle {
+            cycle,
+            offset,
+            run,
+            count,
+        }
+    }
+
+    /// Create a new Transform operator wrapping another operator.
+    pub fn transform(value: AlsOperator<'a>, transform: ValueTransform) -> Self {
+        AlsOperator::Transform {
+            value: Box::new(value),
+            transform,
+        }
+    }
+
+    /// Create a new BoolRuns operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - Label of the first, third, fifth, ... run
+    /// * `second` - Label of the second, fourth, ... run
+    /// * `runs` - Lengths of the alternating runs
+    pub fn bool_runs<S: Into<String>, T: Into<String>>(first: S, second: T, runs: Vec<usize>) -> Self {
+        AlsOperator::BoolRuns {
+            labels: [first.into(), second.into()],
+            runs,
+        }
+    }
+
+    /// Create a new Split operator joining `parts` with `separator`.
+    pub fn split(separator: char, parts: Vec<Vec<AlsOperator<'a>>>) -> Self {
+        AlsOperator::Split {
+            separator: Some(separator),
+            parts,
+        }
+    }
+
+    /// Create a new Split operator concatenating `parts` without a separator.
+    pub fn concat(parts: Vec<Vec<AlsOperator<'a>>>) -> Self {
+        AlsOperator::Split { separator: None, parts }
+    }
+
+    /// Create a new FloatDelta operator.
+    ///
+    /// # Arguments
+    ///
+    /// * `places` - Digits after the decimal point
+    /// * `start` - First value, scaled by `10^places`
+    /// * `deltas` - Differences between consecutive scaled values
+    pub fn float_delta(places: u8, start: i64, deltas: Vec<i64>) -> Self {
+        AlsOperator::FloatDelta { places, start, deltas }
+    }
+
     /// Expand this operator into a vector of string values.
     ///
     /// This method recursively expands all operators to produce the
@@ -314,10 +588,12 @@ impl AlsOperator {
     /// # Errors
     ///
     /// Returns `AlsError::InvalidDictRef` if a DictRef references an
-    /// index that doesn't exist in the dictionary.
+    /// index that doesn't exist in the dictionary, and
+    /// `AlsError::InvalidColumnRef` for a ColumnRef, which can only be
+    /// expanded with the referenced column via `expand_column_ref`.
     pub fn expand(&self, dictionary: Option<&[String]>) -> Result<Vec<String>> {
         match self {
-            AlsOperator::Raw(value) => Ok(vec![value.clone()]),
+            AlsOperator::Raw(value) => Ok(vec![value.to_string()]),
 
             AlsOperator::Range { start, end, step } => {
                 let mut values = Vec::new();
@@ -379,22 +655,167 @@ impl AlsOperator {
                         size: dict.len(),
                     })
             }
+
+            AlsOperator::ColumnRef { column, .. } => Err(AlsError::InvalidColumnRef {
+                index: *column,
+                columns: 0,
+            }),
+
+            AlsOperator::DateRange { start, step, count, format } => {
+                let mut result = Vec::with_capacity(*count);
+                for i in 0..*count {
+                    let timestamp = (i as i64)
+                        .checked_mul(*step)
+                        .and_then(|offset| start.checked_add(offset))
+                        .ok_or(AlsError::RangeOverflow {
+                            start: *start,
+                            end: i64::MAX,
+                            step: *step,
+                        })?;
+                    let value = format_timestamp(timestamp, format).ok_or_else(|| {
+                        AlsError::AlsSyntaxError {
+                            position: 0,
+                            message: format!("Unsupported date format: {}", format),
+                        }
+                    })?;
+                    result.push(value);
+                }
+                Ok(result)
+            }
+
+            AlsOperator::Cycle { cycle, offset, run, count } => {
+                if *run == 0 {
+                    return Err(AlsError::AlsSyntaxError {
+                        position: 0,
+                        message: "Cycle run length must be at least 1".to_string(),
+                    });
+                }
+                let labels = cycle.values();
+                Ok((0..*count)
+                    .map(|i| labels[cycle.label_position(*offset, *run, i)].to_string())
+                    .collect())
+            }
+
+            AlsOperator::Transform { value, transform } => Ok(value
+                .expand(dictionary)?
+                .iter()
+                .map(|v| transform.apply(v))
+                .collect()),
+
+            AlsOperator::BoolRuns { labels, runs } => {
+                let total = runs.iter().fold(0usize, |total, &run| total.saturating_add(run));
+                let mut result = Vec::with_capacity(total.min(MAX_EXPAND_RESERVE));
+                for (i, &run) in runs.iter().enumerate() {
+                    let label = &labels[i % 2];
+                    result.extend(std::iter::repeat_n(label, run).cloned());
+                }
+                Ok(result)
+            }
+
+            AlsOperator::Split { separator, parts } => {
+                let mut rows: Option<Vec<String>> = None;
+                for part in parts {
+                    let mut values = Vec::with_capacity(self.expanded_count());
+                    for op in part {
+                        values.extend(op.expand(dictionary)?);
+                    }
+
+                    rows = Some(match rows {
+                        None => values
+                            .into_iter()
+                            .map(|v| if v == EMPTY_TOKEN { String::new() } else { v })
+                            .collect(),
+                        Some(mut rows) => {
+                            if rows.len() != values.len() {
+                                return Err(AlsError::AlsSyntaxError {
+                                    position: 0,
+                                    message: format!(
+                                        "Split parts expand to different lengths: {} and {}",
+                                        rows.len(),
+                                        values.len()
+                                    ),
+                                });
+                            }
+                            for (row, value) in rows.iter_mut().zip(values) {
+                                if let Some(separator) = separator {
+                                    row.push(*separator);
+                                }
+                                // Empty segments are stored as the empty token
+                                if value != EMPTY_TOKEN {
+                                    row.push_str(&value);
+                                }
+                            }
+                            rows
+                        }
+                    });
+                }
+                Ok(rows.unwrap_or_default())
+            }
+
+            AlsOperator::FloatDelta { places, start, deltas } => {
+                if *places == 0 || *places > MAX_FLOAT_DELTA_PLACES {
+                    return Err(AlsError::AlsSyntaxError {
+                        position: 0,
+                        message: format!("Float delta needs 1 to {} places, found {}", MAX_FLOAT_DELTA_PLACES, places),
+                    });
+                }
+                let mut result = Vec::with_capacity(deltas.len() + 1);
+                let mut value = *start;
+                result.push(format_scaled(value, *places));
+                for delta in deltas {
+                    value = value.checked_add(*delta).ok_or_else(|| AlsError::AlsSyntaxError {
+                        position: 0,
+                        message: "Float delta overflows".to_string(),
+                    })?;
+                    result.push(format_scaled(value, *places));
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    /// Expand a ColumnRef operator against the referenced column's values.
+    ///
+    /// `source` must hold the referenced column's values for exactly the
+    /// rows this operator covers. Other operators expand as with `expand`.
+    pub fn expand_column_ref(&self, source: &[String], dictionary: Option<&[String]>) -> Result<Vec<String>> {
+        match self {
+            AlsOperator::ColumnRef { mapping, .. } => Ok(source
+                .iter()
+                .map(|value| {
+                    mapping
+                        .iter()
+                        .find(|(from, _)| from == value)
+                        .map_or_else(|| value.clone(), |(_, to)| to.clone())
+                })
+                .collect()),
+            other => other.expand(dictionary),
         }
     }
 
     /// Returns the number of values this operator will produce when expanded.
     ///
     /// This is useful for pre-allocating buffers or validating that
-    /// expansion won't exceed limits.
+    /// expansion won't exceed limits. Counts too large for a `usize`
+    /// saturate.
     pub fn expanded_count(&self) -> usize {
         match self {
             AlsOperator::Raw(_) => 1,
             AlsOperator::Range { start, end, step } => {
                 Self::calculate_range_count(*start, *end, *step) as usize
             }
-            AlsOperator::Multiply { value, count } => value.expanded_count() * count,
+            AlsOperator::Multiply { value, count } => value.expanded_count().saturating_mul(*count),
             AlsOperator::Toggle { count, .. } => *count,
             AlsOperator::DictRef(_) => 1,
+            AlsOperator::ColumnRef { count, .. } => *count,
+            AlsOperator::DateRange { count, .. } => *count,
+            AlsOperator::Cycle { count, .. } => *count,
+            AlsOperator::Transform { value, .. } => value.expanded_count(),
+            AlsOperator::BoolRuns { runs, .. } => runs.iter().fold(0, |total, &run| total.saturating_add(run)),
+            AlsOperator::Split { parts, .. } => parts
+                .first()
+                .map_or(0, |part| part.iter().fold(0, |total, op| total.saturating_add(op.expanded_count()))),
+            AlsOperator::FloatDelta { deltas, .. } => deltas.len() + 1,
         }
     }
 
@@ -422,6 +843,147 @@ impl AlsOperator {
     pub fn is_dict_ref(&self) -> bool {
         matches!(self, AlsOperator::DictRef(_))
     }
+
+    /// Returns true if this operator is a ColumnRef.
+    pub fn is_column_ref(&self) -> bool {
+        matches!(self, AlsOperator::ColumnRef { .. })
+    }
+
+    /// Returns true if this operator is a DateRange.
+    pub fn is_date_range(&self) -> bool {
+        matches!(self, AlsOperator::DateRange { .. })
+    }
+
+    /// Returns true if this operator is a Cycle.
+    pub fn is_cycle(&self) -> bool {
+        matches!(self, AlsOperator::Cycle { .. })
+    }
+
+    /// Returns true if this operator is a Transform.
+    pub fn is_transform(&self) -> bool {
+        matches!(self, AlsOperator::Transform { .. })
+    }
+
+    /// Returns true if this operator is a BoolRuns.
+    pub fn is_bool_runs(&self) -> bool {
+        matches!(self, AlsOperator::BoolRuns { .. })
+    }
+
+    /// Returns true if this operator is a Split.
+    pub fn is_split(&self) -> bool {
+        matches!(self, AlsOperator::Split { .. })
+    }
+
+    /// Returns true if this operator is a FloatDelta.
+    pub fn is_float_delta(&self) -> bool {
+        matches!(self, AlsOperator::FloatDelta { .. })
+    }
+
+    /// Convert into an operator that owns all of its values.
+    ///
+    /// Raw values borrowed from the input are copied; everything else is
+    /// moved.
+    pub fn into_owned(self) -> AlsOperator<'static> {
+        match self {
+            AlsOperator::Raw(value) => AlsOperator::Raw(Cow::Owned(value.into_owned())),
+            AlsOperator::Range { start, end, step } => AlsOperator::Range { start, end, step },
+            AlsOperator::Multiply { value, count } => AlsOperator::Multiply { value: Box::new(value.into_owned()), count },
+            AlsOperator::Toggle { values, count } => AlsOperator::Toggle { values, count },
+            AlsOperator::DictRef(index) => AlsOperator::DictRef(index),
+            AlsOperator::ColumnRef { column, mapping, count } => AlsOperator::ColumnRef { column, mapping, count },
+            AlsOperator::DateRange { start, step, count, format } => AlsOperator::DateRange { start, step, count, format },
+            AlsOperator::Cycle { cycle, offset, run, count } => AlsOperator::Cycle { cycle, offset, run, count },
+            AlsOperator::Transform { value, transform } => AlsOperator::Transform { value: Box::new(value.into_owned()), transform },
+            AlsOperator::BoolRuns { labels, runs } => AlsOperator::BoolRuns { labels, runs },
+            AlsOperator::Split { separator, parts } => AlsOperator::Split {
+                separator,
+                parts: parts
+                    .into_iter()
+                    .map(|part| part.into_iter().map(AlsOperator::into_owned).collect())
+                    .collect(),
+            },
+            AlsOperator::FloatDelta { places, start, deltas } => AlsOperator::FloatDelta { places, start, deltas },
+        }
+    }
+
+    /// Combine this operator with the one that follows it, if a single
+    /// operator can express both.
+    ///
+    /// Handles contiguous ranges and date ranges with the same step,
+    /// repeats of the same value, and toggles that continue in phase.
+    /// Returns `None` when the operators must stay separate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::AlsOperator;
+    ///
+    /// let merged = AlsOperator::range(1, 5).try_merge(&AlsOperator::range(6, 9));
+    /// assert_eq!(merged, Some(AlsOperator::range(1, 9)));
+    /// ```
+    pub fn try_merge(&self, next: &AlsOperator<'a>) -> Option<AlsOperator<'a>> {
+        match (self, next) {
+            (
+                AlsOperator::Range { start, end, step },
+                AlsOperator::Range { start: next_start, end: next_end, step: next_step },
+            ) if step == next_step
+                && end.checked_add(*step) == Some(*next_start)
+                // An end off the step grid is not the last value produced
+                && (i128::from(*end) - i128::from(*start)).checked_rem(i128::from(*step)) == Some(0) =>
+            {
+                Some(AlsOperator::Range { start: *start, end: *next_end, step: *step })
+            }
+            (
+                AlsOperator::DateRange { start, step, count, format },
+                AlsOperator::DateRange { start: next_start, step: next_step, count: next_count, format: next_format },
+            ) if step == next_step
+                && format == next_format
+                && i64::try_from(*count)
+                    .ok()
+                    .and_then(|count| step.checked_mul(count))
+                    .and_then(|offset| start.checked_add(offset))
+                    == Some(*next_start) =>
+            {
+                Some(AlsOperator::date_range(*start, *step, count + next_count, format.clone()))
+            }
+            (
+                AlsOperator::Multiply { value, count },
+                AlsOperator::Multiply { value: next_value, count: next_count },
+            ) if value == next_value => Some(AlsOperator::multiply(value.as_ref().clone(), count + next_count)),
+            (AlsOperator::Multiply { value, count }, single) if Self::is_single_value(single) && value.as_ref() == single => {
+                Some(AlsOperator::multiply(single.clone(), count + 1))
+            }
+            (single, AlsOperator::Multiply { value, count }) if Self::is_single_value(single) && value.as_ref() == single => {
+                Some(AlsOperator::multiply(single.clone(), count + 1))
+            }
+            (
+                AlsOperator::Toggle { values, count },
+                AlsOperator::Toggle { values: next_values, count: next_count },
+            ) if values == next_values && !values.is_empty() && count % values.len() == 0 => {
+                Some(AlsOperator::toggle_multi(values.clone(), count + next_count))
+            }
+            _ => None,
+        }
+    }
+
+    /// Merge every pair of adjacent operators that [`try_merge`](Self::try_merge) can combine.
+    ///
+    /// The merged sequence expands to exactly the same values.
+    pub fn merge_adjacent(operators: Vec<AlsOperator<'a>>) -> Vec<AlsOperator<'a>> {
+        let mut merged: Vec<AlsOperator<'a>> = Vec::with_capacity(operators.len());
+        for operator in operators {
+            match merged.last().and_then(|last| last.try_merge(&operator)) {
+                Some(combined) => *merged.last_mut().expect("merged with last operator") = combined,
+                None => merged.push(operator),
+            }
+        }
+        merged
+    }
+
+    /// Returns true for operators that expand to exactly one literal value.
+    fn is_single_value(&self) -> bool {
+        matches!(self, AlsOperator::Raw(_) | AlsOperator::DictRef(_))
+    }
 }
 
 #[cfg(test)]
@@ -436,6 +998,15 @@ mod tests {
         assert_eq!(op.expanded_count(), 1);
     }
 
+    #[test]
+    fn test_into_owned() {
+        let value = String::from("hello");
+        let op = AlsOperator::split('-', vec![vec![AlsOperator::multiply(AlsOperator::raw(value.as_str()), 3)], vec![AlsOperator::range(1, 3)]]);
+        let owned: AlsOperator<'static> = AlsOperator::multiply(op, 2).into_owned();
+        drop(value);
+        assert_eq!(owned.expand(None).unwrap(), vec!["hello-1", "hello-2", "hello-3", "hello-1", "hello-2", "hello-3"]);
+    }
+
     #[test]
     fn test_range_ascending() {
         let op = AlsOperator::range(1, 5);
@@ -585,6 +1156,195 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_column_ref_with_mapping() {
+        let op = AlsOperator::column_ref(
+            0,
+            vec![("buy".to_string(), "+1".to_string()), ("sell".to_string(), "-1".to_string())],
+            4,
+        );
+        assert!(op.is_column_ref());
+        assert_eq!(op.expanded_count(), 4);
+
+        let source: Vec<String> = vec!["buy", "sell", "sell", "buy"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            op.expand_column_ref(&source, None).unwrap(),
+            vec!["+1", "-1", "-1", "+1"]
+        );
+    }
+
+    #[test]
+    fn test_column_ref_unmapped_values_copied() {
+        let op = AlsOperator::column_ref(1, Vec::new(), 2);
+        let source = vec!["x".to_string(), "y".to_string()];
+        assert_eq!(op.expand_column_ref(&source, None).unwrap(), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn test_column_ref_expand_without_source() {
+        let op = AlsOperator::column_ref(3, Vec::new(), 2);
+        assert!(matches!(
+            op.expand(None),
+            Err(AlsError::InvalidColumnRef { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_date_range_daily() {
+        let op = AlsOperator::date_range(1_704_067_200, 86_400, 3, "%Y-%m-%d");
+        assert!(op.is_date_range());
+        assert_eq!(op.expanded_count(), 3);
+        assert_eq!(
+            op.expand(None).unwrap(),
+            vec!["2024-01-01", "2024-01-02", "2024-01-03"]
+        );
+    }
+
+    #[test]
+    fn test_date_range_descending_hourly() {
+        let op = AlsOperator::date_range(3_600, -3_600, 2, "%H:%M");
+        assert_eq!(op.expand(None).unwrap(), vec!["01:00", "00:00"]);
+    }
+
+    #[test]
+    fn test_date_range_invalid_format() {
+        let op = AlsOperator::date_range(0, 60, 2, "%Q");
+        assert!(matches!(op.expand(None), Err(AlsError::AlsSyntaxError { .. })));
+    }
+
+    #[test]
+    fn test_date_range_overflow() {
+        let op = AlsOperator::date_range(i64::MAX - 10, 86_400, 2, "%Y");
+        assert!(matches!(op.expand(None), Err(AlsError::RangeOverflow { .. })));
+    }
+
+    #[test]
+    fn test_cycle_wraps_around() {
+        let op = AlsOperator::cycle(CalendarCycle::Weekday, 5, 1, 4);
+        assert!(op.is_cycle());
+        assert_eq!(op.expanded_count(), 4);
+        assert_eq!(op.expand(None).unwrap(), vec!["Sat", "Sun", "Mon", "Tue"]);
+    }
+
+    #[test]
+    fn test_cycle_with_run() {
+        let op = AlsOperator::cycle(CalendarCycle::Quarter, 6, 2, 5);
+        assert_eq!(op.expand(None).unwrap(), vec!["Q4", "Q4", "Q1", "Q1", "Q2"]);
+
+        // A partial first run
+        let op = AlsOperator::cycle(CalendarCycle::Quarter, 3, 2, 4);
+        assert_eq!(op.expand(None).unwrap(), vec!["Q2", "Q3", "Q3", "Q4"]);
+    }
+
+    #[test]
+    fn test_cycle_zero_run() {
+        let op = AlsOperator::cycle(CalendarCycle::Month, 0, 0, 3);
+        assert!(op.expand(None).is_err());
+    }
+
+    #[test]
+    fn test_transform_padded_range() {
+        let op = AlsOperator::transform(
+            AlsOperator::range(1, 3),
+            ValueTransform::new().with_prefix("USER").with_width(3),
+        );
+        assert!(op.is_transform());
+        assert_eq!(op.expanded_count(), 3);
+        assert_eq!(op.expand(None).unwrap(), vec!["USER001", "USER002", "USER003"]);
+    }
+
+    #[test]
+    fn test_transform_uppercase_dict_ref() {
+        let dict = vec!["mon".to_string()];
+        let op = AlsOperator::transform(
+            AlsOperator::dict_ref(0),
+            ValueTransform::new().with_case(crate::als::CaseTransform::Upper),
+        );
+        assert_eq!(op.expand(Some(&dict)).unwrap(), vec!["MON"]);
+    }
+
+    #[test]
+    fn test_bool_runs_expand() {
+        let op = AlsOperator::bool_runs("true", "false", vec![2, 1, 3]);
+        assert!(op.is_bool_runs());
+        assert_eq!(op.expanded_count(), 6);
+        assert_eq!(
+            op.expand(None).unwrap(),
+            vec!["true", "true", "false", "true", "true", "true"]
+        );
+    }
+
+    #[test]
+    fn test_bool_runs_empty() {
+        let op = AlsOperator::bool_runs("Y", "N", Vec::new());
+        assert_eq!(op.expanded_count(), 0);
+        assert!(op.expand(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_expand() {
+        let op = AlsOperator::split(
+            '/',
+            vec![
+                vec![AlsOperator::multiply(AlsOperator::raw("srv"), 3)],
+                vec![AlsOperator::range(1, 2), AlsOperator::raw("x/y")],
+            ],
+        );
+        assert!(op.is_split());
+        assert_eq!(op.expanded_count(), 3);
+        assert_eq!(op.expand(None).unwrap(), vec!["srv/1", "srv/2", "srv/x/y"]);
+    }
+
+    #[test]
+    fn test_split_empty_segments() {
+        let op = AlsOperator::split(
+            '/',
+            vec![
+                vec![AlsOperator::raw(EMPTY_TOKEN)],
+                vec![AlsOperator::raw("var")],
+                vec![AlsOperator::raw(EMPTY_TOKEN)],
+            ],
+        );
+        assert_eq!(op.expand(None).unwrap(), vec!["/var/"]);
+    }
+
+    #[test]
+    fn test_concat_expand() {
+        let op = AlsOperator::concat(vec![
+            vec![AlsOperator::multiply(AlsOperator::raw("us-east-1"), 2), AlsOperator::raw("eu-west-1")],
+            vec![AlsOperator::raw("a"), AlsOperator::raw("b"), AlsOperator::raw("a")],
+        ]);
+        assert!(op.is_split());
+        assert_eq!(op.expand(None).unwrap(), vec!["us-east-1a", "us-east-1b", "eu-west-1a"]);
+    }
+
+    #[test]
+    fn test_split_mismatched_parts() {
+        let op = AlsOperator::split(
+            '/',
+            vec![vec![AlsOperator::raw("a")], vec![AlsOperator::range(1, 2)]],
+        );
+        assert!(op.expand(None).is_err());
+    }
+
+    #[test]
+    fn test_float_delta_expand() {
+        let op = AlsOperator::float_delta(2, 2153, vec![3, -1, -5, -2155]);
+        assert!(op.is_float_delta());
+        assert_eq!(op.expanded_count(), 5);
+        assert_eq!(op.expand(None).unwrap(), vec!["21.53", "21.56", "21.55", "21.5", "-0.05"]);
+    }
+
+    #[test]
+    fn test_float_delta_invalid() {
+        assert!(AlsOperator::float_delta(0, 1, vec![]).expand(None).is_err());
+        assert!(AlsOperator::float_delta(16, 1, vec![]).expand(None).is_err());
+        assert!(AlsOperator::float_delta(1, i64::MAX, vec![1]).expand(None).is_err());
+    }
+
     #[test]
     fn test_operator_equality() {
         let op1 = AlsOperator::range(1, 5);
@@ -639,4 +1399,81 @@ mod tests {
         assert!(AlsOperator::toggle("a", "b", 4).is_toggle());
         assert!(AlsOperator::dict_ref(0).is_dict_ref());
     }
+
+    #[test]
+    fn test_try_merge_ranges() {
+        let merged = AlsOperator::range_with_step(10, 30, 10).try_merge(&AlsOperator::range_with_step(40, 60, 10));
+        assert_eq!(merged, Some(AlsOperator::range_with_step(10, 60, 10)));
+
+        // Gap, different step, or overflow keep the ranges apart
+        assert_eq!(AlsOperator::range(1, 5).try_merge(&AlsOperator::range(7, 9)), None);
+        assert_eq!(AlsOperator::range(1, 5).try_merge(&AlsOperator::range_with_step(6, 10, 2)), None);
+        assert_eq!(AlsOperator::range(1, i64::MAX).try_merge(&AlsOperator::range(1, 2)), None);
+
+        // 1, 3, 5 then 8, 10: the first end is off the grid
+        let first = AlsOperator::range_with_step(1, 6, 2);
+        let next = AlsOperator::range_with_step(8, 10, 2);
+        assert_eq!(first.try_merge(&next), None);
+        assert_eq!(
+            AlsOperator::merge_adjacent(vec![first, next]),
+            vec![AlsOperator::range_with_step(1, 6, 2), AlsOperator::range_with_step(8, 10, 2)]
+        );
+    }
+
+    #[test]
+    fn test_try_merge_date_ranges() {
+        let first = AlsOperator::date_range(0, 60, 3, "%H:%M");
+        let merged = first.try_merge(&AlsOperator::date_range(180, 60, 2, "%H:%M"));
+        assert_eq!(merged, Some(AlsOperator::date_range(0, 60, 5, "%H:%M")));
+        assert_eq!(first.try_merge(&AlsOperator::date_range(180, 60, 2, "%H")), None);
+        assert_eq!(first.try_merge(&AlsOperator::date_range(240, 60, 2, "%H:%M")), None);
+    }
+
+    #[test]
+    fn test_try_merge_multiplies() {
+        let a3 = AlsOperator::multiply(AlsOperator::raw("a"), 3);
+        assert_eq!(
+            a3.try_merge(&AlsOperator::multiply(AlsOperator::raw("a"), 2)),
+            Some(AlsOperator::multiply(AlsOperator::raw("a"), 5))
+        );
+        assert_eq!(a3.try_merge(&AlsOperator::raw("a")), Some(AlsOperator::multiply(AlsOperator::raw("a"), 4)));
+        assert_eq!(
+            AlsOperator::dict_ref(2).try_merge(&AlsOperator::multiply(AlsOperator::dict_ref(2), 2)),
+            Some(AlsOperator::multiply(AlsOperator::dict_ref(2), 3))
+        );
+        assert_eq!(a3.try_merge(&AlsOperator::raw("b")), None);
+    }
+
+    #[test]
+    fn test_try_merge_toggles() {
+        let toggle = AlsOperator::toggle("T", "F", 4);
+        assert_eq!(toggle.try_merge(&AlsOperator::toggle("T", "F", 3)), Some(AlsOperator::toggle("T", "F", 7)));
+        // An odd count leaves the next toggle out of phase
+        assert_eq!(AlsOperator::toggle("T", "F", 3).try_merge(&toggle), None);
+    }
+
+    #[test]
+    fn test_merge_adjacent_preserves_expansion() {
+        let operators = vec![
+            AlsOperator::range(1, 3),
+            AlsOperator::range(4, 6),
+            AlsOperator::range(7, 9),
+            AlsOperator::raw("x"),
+            AlsOperator::multiply(AlsOperator::raw("x"), 2),
+            AlsOperator::raw("y"),
+        ];
+        let expected: Vec<String> = operators.iter().flat_map(|op| op.expand(None).unwrap()).collect();
+
+        let merged = AlsOperator::merge_adjacent(operators);
+        assert_eq!(
+            merged,
+            vec![
+                AlsOperator::range(1, 9),
+                AlsOperator::multiply(AlsOperator::raw("x"), 3),
+                AlsOperator::raw("y"),
+            ]
+        );
+        let actual: Vec<String> = merged.iter().flat_map(|op| op.expand(None).unwrap()).collect();
+        assert_eq!(actual, expected);
+    }
 }
@@ -3,20 +3,67 @@
 //! This module provides the parser for converting ALS format text into
 //! `AlsDocument` structures and expanding them to tabular data.
 
+use crate::compress::stats::value_distribution;
+use crate::compress::ColumnProfile;
 use crate::config::ParserConfig;
 use crate::error::{AlsError, Result};
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
-use super::document::{AlsDocument, ColumnStream, FormatIndicator};
+use super::document::{AlsDocument, ColumnStream, FormatIndicator, BLOCKS_PREFIX, NFC_LINE};
+use super::feature::Feature;
+use super::front_coding;
+use super::bloom::{BloomFilter, BLOOM_PREFIX};
+use super::index::{ColumnIndex, INDEX_PREFIX};
+use super::zone::{ZoneMap, ZONE_PREFIX};
+use super::quantize::{Quantization, QUANTIZE_PREFIX};
+use super::redact::{PseudonymKey, Redaction, REDACT_PREFIX};
+use super::delta::{DeltaScript, DELTA_PREFIX};
 use super::operator::AlsOperator;
+use super::signature::split_signature;
+use super::store::StoreFrame;
 use super::tokenizer::{Token, Tokenizer, VersionType};
+use super::transpose::{transpose, transpose_into};
+use super::truncate::{TimeTruncation, TRUNCATE_PREFIX};
+use super::meta::{FileMetadata, META_PREFIX};
+use super::foreign_key::{ForeignKey, FOREIGN_KEY_PREFIX};
 
 /// Default threshold for parallel decompression (number of columns * estimated rows).
 /// Below this threshold, sequential processing is used to avoid parallel overhead.
 const PARALLEL_EXPAND_THRESHOLD: usize = 1000;
 
+/// Prefixes of the lines that may follow the column streams.
+const TRAILER_PREFIXES: [&str; 11] = [
+    DELTA_PREFIX,
+    BLOCKS_PREFIX,
+    NFC_LINE,
+    REDACT_PREFIX,
+    QUANTIZE_PREFIX,
+    TRUNCATE_PREFIX,
+    META_PREFIX,
+    FOREIGN_KEY_PREFIX,
+    BLOOM_PREFIX,
+    ZONE_PREFIX,
+    INDEX_PREFIX,
+];
+
+/// Split a document into the text holding the column streams and the
+/// trailer lines after them, with the trailer's offset.
+pub(super) fn split_trailer(input: &str) -> (&str, Option<(usize, &str)>) {
+    // Trailer lines (block layout, Bloom filters, zone maps, value indexes) start
+    // on their own line after the streams; `!` and newlines inside
+    // values are always escaped, so this can't misfire.
+    let trailer_start = input
+        .match_indices("\n!")
+        .map(|(i, _)| i + 1)
+        .find(|&i| TRAILER_PREFIXES.iter().any(|prefix| input[i..].starts_with(prefix)));
+    match trailer_start {
+        Some(start) => (&input[..start], Some((start, &input[start..]))),
+        None => (input, None),
+    }
+}
+
 /// ALS format parser.
 ///
 /// Parses ALS format text into `AlsDocument` structures and can expand
@@ -34,7 +81,10 @@ pub struct AlsParser {
 
 impl AlsParser {
     /// Current maximum supported ALS version.
-    pub const MAX_SUPPORTED_VERSION: u8 = 1;
+    ///
+    /// Version 2 documents may list [`Feature`] flags in their header;
+    /// features added later get new flags rather than new versions.
+    pub const MAX_SUPPORTED_VERSION: u8 = 2;
 
     /// Create a new parser with default configuration.
     pub fn new() -> Self {
@@ -49,18 +99,144 @@ impl AlsParser {
     }
 
     /// Parse ALS format text into an `AlsDocument`.
-    pub fn parse(&self, input: &str) -> Result<AlsDocument> {
-        let mut tokenizer = Tokenizer::new(input);
-        self.parse_document(&mut tokenizer)
+    ///
+    /// Store frames holding uncompressed input are read into a CTX document.
+    /// A signature line is skipped without being checked.
+    pub fn parse(&self, input: &str) -> Result<AlsDocument<'static>> {
+        let (input, _) = split_signature(input);
+        if let Some(frame) = StoreFrame::parse(input)? {
+            return Self::parse_store_frame(&frame);
+        }
+
+        let (body, trailer) = split_trailer(input);
+        let mut tokenizer = Tokenizer::new(body);
+        let mut doc = self.parse_document(&mut tokenizer)?;
+        if let Some((offset, trailer)) = trailer {
+            Self::parse_trailer(&mut doc, trailer, offset)?;
+        }
+        Ok(doc)
+    }
+
+    /// Parse the trailer lines that follow the column streams.
+    pub(super) fn parse_trailer(doc: &mut AlsDocument, trailer: &str, offset: usize) -> Result<()> {
+        let mut position = offset;
+        for line in trailer.split('\n') {
+            let line_start = position;
+            position += line.len() + 1;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+
+            let syntax_error = |message: String| AlsError::AlsSyntaxError {
+                position: line_start,
+                message,
+            };
+            let column = if line.starts_with(DELTA_PREFIX) {
+                doc.delta = Some(DeltaScript::parse_line(line, line_start)?);
+                continue;
+            } else if let Some(size) = line.strip_prefix(BLOCKS_PREFIX) {
+                let size = size
+                    .parse()
+                    .ok()
+                    .filter(|&size| size > 0)
+                    .ok_or_else(|| syntax_error(format!("Invalid block size: {}", size)))?;
+                doc.block_size = Some(size);
+                continue;
+            } else if line == NFC_LINE {
+                doc.nfc_normalized = true;
+                continue;
+            } else if line.starts_with(REDACT_PREFIX) {
+                let (column, redaction) = Redaction::parse_line(line, line_start)?;
+                doc.redactions.insert(column, redaction);
+                continue;
+            } else if line.starts_with(QUANTIZE_PREFIX) {
+                let (column, quantization) = Quantization::parse_line(line, line_start)?;
+                doc.quantizations.insert(column, quantization);
+                continue;
+            } else if line.starts_with(TRUNCATE_PREFIX) {
+                let (column, truncation) = TimeTruncation::parse_line(line, line_start)?;
+                doc.truncations.insert(column, truncation);
+                continue;
+            } else if line.starts_with(META_PREFIX) {
+                doc.file_metadata = Some(FileMetadata::parse_line(line, line_start)?);
+                continue;
+            } else if line.starts_with(FOREIGN_KEY_PREFIX) {
+                let (column, key) = ForeignKey::parse_line(line, line_start)?;
+                doc.foreign_keys.insert(column, key);
+                continue;
+            } else if line.starts_with(BLOOM_PREFIX) {
+                let (column, filters) = BloomFilter::parse_line(line, line_start)?;
+                doc.blooms.insert(column, filters);
+                column
+            } else if line.starts_with(ZONE_PREFIX) {
+                let (column, zones) = ZoneMap::parse_line(line, line_start)?;
+                doc.zone_maps.insert(column, zones);
+                column
+            } else if line.starts_with(INDEX_PREFIX) {
+                let (column, index) = ColumnIndex::parse_line(line, line_start)?;
+                doc.add_index(column, index);
+                column
+            } else {
+                return Err(syntax_error(format!("Unexpected line after column streams: {}", line)));
+            };
+
+            if column >= doc.schema.len() {
+                return Err(syntax_error(format!("Trailer refers to unknown column {}", column)));
+            }
+        }
+
+        let block_count = doc.block_count();
+        if !doc.blooms.is_empty()
+            && (doc.block_size.is_none() || doc.blooms.values().any(|filters| filters.len() != block_count))
+        {
+            return Err(AlsError::AlsSyntaxError {
+                position: offset,
+                message: format!("Bloom filters do not match the {} blocks of the document", block_count),
+            });
+        }
+        if doc.zone_maps.values().any(|zones| zones.len() != block_count) {
+            return Err(AlsError::AlsSyntaxError {
+                position: offset,
+                message: format!("Zone maps do not match the {} blocks of the document", block_count),
+            });
+        }
+        Ok(())
+    }
+
+    /// Read the input held by a store frame into a CTX document.
+    pub(super) fn parse_store_frame(frame: &StoreFrame<'_>) -> Result<AlsDocument<'static>> {
+        let data = frame.to_tabular()?;
+        let mut doc = AlsDocument::with_schema(data.column_names().into_iter().map(String::from).collect());
+        doc.set_ctx_format();
+        for column in &data.columns {
+            let values = column.values.iter().map(|v| v.to_string_repr().into_owned());
+            doc.add_stream(ColumnStream::from_operators(values.map(AlsOperator::raw).collect()));
+        }
+        Ok(doc)
     }
 
     /// Parse a complete ALS document from the tokenizer.
-    fn parse_document(&self, tokenizer: &mut Tokenizer) -> Result<AlsDocument> {
+    fn parse_document(&self, tokenizer: &mut Tokenizer) -> Result<AlsDocument<'static>> {
+        let mut doc = self.parse_header(tokenizer)?;
+
+        // Parse streams
+        if !doc.schema.is_empty() {
+            let streams = self.parse_streams(tokenizer, doc.schema.len())?;
+            doc.streams = streams;
+        }
+
+        Ok(doc)
+    }
+
+    /// Parse the version, dictionaries and schema that precede the column
+    /// streams.
+    pub(super) fn parse_header(&self, tokenizer: &mut Tokenizer) -> Result<AlsDocument<'static>> {
         let mut doc = AlsDocument::new();
 
         // Parse optional version
         self.skip_whitespace_tokens(tokenizer)?;
-        if let Token::Version(version_type) = tokenizer.peek_token()? {
+        if let Token::Version(version_type, features) = tokenizer.peek_token()? {
             tokenizer.next_token()?; // consume version
             match version_type {
                 VersionType::Als(v) => {
@@ -70,6 +246,15 @@ impl AlsParser {
                             found: v,
                         });
                     }
+                    if v < 2 && !features.is_empty() {
+                        return Err(AlsError::AlsSyntaxError {
+                            position: tokenizer.position(),
+                            message: format!("Feature flags need version 2, found version {}", v),
+                        });
+                    }
+                    if let Some(feature) = features.into_iter().find(|name| Feature::from_name(name).is_none()) {
+                        return Err(AlsError::UnsupportedFeature { feature });
+                    }
                     doc.version = v;
                     doc.format_indicator = FormatIndicator::Als;
                 }
@@ -81,8 +266,13 @@ impl AlsParser {
         }
 
         // Parse optional dictionaries
-        while let Token::DictionaryHeader { name, values } = tokenizer.peek_token()? {
+        while let Token::DictionaryHeader { name, values, front_coded } = tokenizer.peek_token()? {
             tokenizer.next_token()?; // consume dictionary header
+            let values = if front_coded {
+                front_coding::decode(values, tokenizer.position())?
+            } else {
+                values
+            };
             doc.dictionaries.insert(name, values);
             self.skip_whitespace_tokens(tokenizer)?;
         }
@@ -94,12 +284,6 @@ impl AlsParser {
         }
         self.skip_whitespace_tokens(tokenizer)?;
 
-        // Parse streams
-        if !doc.schema.is_empty() {
-            let streams = self.parse_streams(tokenizer, doc.schema.len())?;
-            doc.streams = streams;
-        }
-
         Ok(doc)
     }
 
@@ -117,7 +301,7 @@ impl AlsParser {
     }
 
     /// Parse column streams separated by |.
-    fn parse_streams(&self, tokenizer: &mut Tokenizer, expected_columns: usize) -> Result<Vec<ColumnStream>> {
+    fn parse_streams(&self, tokenizer: &mut Tokenizer, expected_columns: usize) -> Result<Vec<ColumnStream<'static>>> {
         let mut streams = Vec::with_capacity(expected_columns);
         let mut current_stream = ColumnStream::new();
 
@@ -126,8 +310,9 @@ impl AlsParser {
             
             match token {
                 Token::Eof => {
-                    // End of input - save current stream if not empty
-                    if !current_stream.is_empty() || streams.is_empty() {
+                    // End of input - save current stream if not empty, or
+                    // if the schema still needs it (a document with no rows)
+                    if !current_stream.is_empty() || streams.len() < expected_columns.max(1) {
                         streams.push(current_stream);
                     }
                     break;
@@ -141,9 +326,19 @@ impl AlsParser {
                     // Skip newlines in stream section
                     continue;
                 }
+                Token::Version(VersionType::Ctx, _) if current_stream.is_empty() && !current_stream.is_ctx() => {
+                    // Column stored verbatim
+                    current_stream.format_indicator = FormatIndicator::Ctx;
+                }
                 _ => {
                     // Parse an element and add to current stream
-                    let operator = self.parse_element(tokenizer, token)?;
+                    let operator = self.parse_element(tokenizer, token, 0)?;
+                    if current_stream.is_ctx() && !operator.is_raw() {
+                        return Err(AlsError::AlsSyntaxError {
+                            position: tokenizer.position(),
+                            message: "Only raw values are allowed in a CTX column".to_string(),
+                        });
+                    }
                     current_stream.push(operator);
                 }
             }
@@ -161,13 +356,78 @@ impl AlsParser {
     }
 
     /// Parse a single element (operator or value).
-    fn parse_element(&self, tokenizer: &mut Tokenizer, first_token: Token) -> Result<AlsOperator> {
+    ///
+    /// `depth` counts the groups, splits and transforms enclosing the
+    /// element, so hostile input can't nest them deep enough to overflow
+    /// the stack.
+    pub(super) fn parse_element(&self, tokenizer: &mut Tokenizer, first_token: Token, depth: usize) -> Result<AlsOperator<'static>> {
         match first_token {
             Token::Integer(n) => self.parse_integer_element(tokenizer, n),
             Token::Float(f) => self.parse_float_element(tokenizer, f),
             Token::RawValue(s) => self.parse_raw_element(tokenizer, s),
-            Token::DictRef(idx) => Ok(AlsOperator::dict_ref(idx)),
-            Token::OpenParen => self.parse_grouped_element(tokenizer),
+            // Keep the exponent spelling rather than printing the value back
+            Token::ScientificFloat { text, .. } => self.parse_raw_element(tokenizer, text),
+            Token::DictRef(idx) => self.parse_dict_ref_element(tokenizer, idx),
+            Token::ColumnRef { column, mapping } => {
+                // Column references always carry an explicit row count
+                let count = self.expect_count(tokenizer, "column reference")?;
+                self.check_expansion(Some(count))?;
+                Ok(AlsOperator::column_ref(column, mapping, count))
+            }
+            Token::DateRange { format, start, step } => {
+                let count = self.expect_count(tokenizer, "date range")?;
+                if count > self.config.max_range_expansion {
+                    return Err(AlsError::RangeOverflow {
+                        start,
+                        end: start.saturating_add(step.saturating_mul(count as i64 - 1)),
+                        step,
+                    });
+                }
+                Ok(AlsOperator::date_range(start, step, count, format))
+            }
+            Token::Cycle { cycle, offset, run } => {
+                let count = self.expect_count(tokenizer, "calendar cycle")?;
+                self.check_expansion(Some(count))?;
+                if run == 0 {
+                    return Err(AlsError::AlsSyntaxError {
+                        position: tokenizer.position(),
+                        message: "Cycle run length must be at least 1".to_string(),
+                    });
+                }
+                // Offsets repeat with the period of the cycle
+                let period = cycle.values().len().checked_mul(run).ok_or_else(|| AlsError::AlsSyntaxError {
+                    position: tokenizer.position(),
+                    message: format!("Cycle run length {} is too large", run),
+                })?;
+                Ok(AlsOperator::cycle(cycle, offset % period, run, count))
+            }
+            Token::OpenParen => self.parse_grouped_element(tokenizer, depth + 1),
+            Token::Split { separator, parts } => {
+                let parts = (0..parts)
+                    .map(|_| self.parse_split_part(tokenizer, depth + 1))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(AlsOperator::Split { separator, parts })
+            }
+            Token::BoolRuns { labels: [first, second], runs } => {
+                let total = runs.iter().try_fold(0usize, |total, &run| total.checked_add(run));
+                self.check_expansion(total)?;
+                Ok(AlsOperator::bool_runs(first, second, runs))
+            }
+            Token::FloatDelta { places, start, deltas } => Ok(AlsOperator::float_delta(places, start, deltas)),
+            Token::Transform(transform) => {
+                // The transformed operator is always grouped
+                match tokenizer.next_token()? {
+                    Token::OpenParen => {}
+                    other => {
+                        return Err(AlsError::AlsSyntaxError {
+                            position: tokenizer.position(),
+                            message: format!("Expected '(' after transform but found {:?}", other),
+                        });
+                    }
+                }
+                let inner = self.parse_grouped_element(tokenizer, depth + 1)?;
+                Ok(AlsOperator::transform(inner, transform))
+            }
             _ => Err(AlsError::AlsSyntaxError {
                 position: tokenizer.position(),
                 message: format!("Unexpected token: {:?}", first_token),
@@ -176,7 +436,7 @@ impl AlsParser {
     }
 
     /// Parse an element starting with an integer (could be range, multiply, or raw).
-    fn parse_integer_element(&self, tokenizer: &mut Tokenizer, start: i64) -> Result<AlsOperator> {
+    fn parse_integer_element(&self, tokenizer: &mut Tokenizer, start: i64) -> Result<AlsOperator<'static>> {
         match tokenizer.peek_token()? {
             Token::RangeOp => {
                 tokenizer.next_token()?; // consume >
@@ -196,7 +456,7 @@ impl AlsParser {
     }
 
     /// Parse an element starting with a float.
-    fn parse_float_element(&self, tokenizer: &mut Tokenizer, value: f64) -> Result<AlsOperator> {
+    fn parse_float_element(&self, tokenizer: &mut Tokenizer, value: f64) -> Result<AlsOperator<'static>> {
         match tokenizer.peek_token()? {
             Token::MultiplyOp => {
                 tokenizer.next_token()?; // consume *
@@ -212,7 +472,7 @@ impl AlsParser {
     }
 
     /// Parse an element starting with a raw value.
-    fn parse_raw_element(&self, tokenizer: &mut Tokenizer, value: String) -> Result<AlsOperator> {
+    fn parse_raw_element(&self, tokenizer: &mut Tokenizer, value: String) -> Result<AlsOperator<'static>> {
         match tokenizer.peek_token()? {
             Token::MultiplyOp => {
                 tokenizer.next_token()?; // consume *
@@ -227,8 +487,20 @@ impl AlsParser {
         }
     }
 
+    /// Parse an element starting with a dictionary reference (could be multiply).
+    fn parse_dict_ref_element(&self, tokenizer: &mut Tokenizer, index: usize) -> Result<AlsOperator<'static>> {
+        match tokenizer.peek_token()? {
+            Token::MultiplyOp => {
+                tokenizer.next_token()?; // consume *
+                let count = self.expect_integer(tokenizer)?;
+                Ok(AlsOperator::multiply(AlsOperator::dict_ref(index), count as usize))
+            }
+            _ => Ok(AlsOperator::dict_ref(index)),
+        }
+    }
+
     /// Parse a range expression: start>end or start>end:step
-    fn parse_range(&self, tokenizer: &mut Tokenizer, start: i64) -> Result<AlsOperator> {
+    fn parse_range(&self, tokenizer: &mut Tokenizer, start: i64) -> Result<AlsOperator<'static>> {
         let end = self.expect_integer(tokenizer)?;
         
         let step = if let Token::StepSeparator = tokenizer.peek_token()? {
@@ -256,7 +528,7 @@ impl AlsParser {
     }
 
     /// Parse a toggle expression: val1~val2[~val3...]*count
-    fn parse_toggle(&self, tokenizer: &mut Tokenizer, first_value: String) -> Result<AlsOperator> {
+    fn parse_toggle(&self, tokenizer: &mut Tokenizer, first_value: String) -> Result<AlsOperator<'static>> {
         let mut values = vec![first_value];
         
         // Parse second value
@@ -282,9 +554,10 @@ impl AlsParser {
     }
 
     /// Parse a grouped element: (element)
-    fn parse_grouped_element(&self, tokenizer: &mut Tokenizer) -> Result<AlsOperator> {
+    fn parse_grouped_element(&self, tokenizer: &mut Tokenizer, depth: usize) -> Result<AlsOperator<'static>> {
+        self.check_depth(tokenizer, depth)?;
         let inner_token = tokenizer.next_token()?;
-        let inner = self.parse_element(tokenizer, inner_token)?;
+        let inner = self.parse_element(tokenizer, inner_token, depth)?;
         
         // Expect closing paren
         match tokenizer.next_token()? {
@@ -307,6 +580,45 @@ impl AlsParser {
         }
     }
 
+    /// Parse one parenthesized part of a split: `(op op ...)`.
+    fn parse_split_part(&self, tokenizer: &mut Tokenizer, depth: usize) -> Result<Vec<AlsOperator<'static>>> {
+        self.check_depth(tokenizer, depth)?;
+        match tokenizer.next_token()? {
+            Token::OpenParen => {}
+            other => {
+                return Err(AlsError::AlsSyntaxError {
+                    position: tokenizer.position(),
+                    message: format!("Expected '(' before split part but found {:?}", other),
+                });
+            }
+        }
+
+        let mut operators = Vec::new();
+        loop {
+            match tokenizer.next_token()? {
+                Token::CloseParen => return Ok(operators),
+                token @ (Token::Eof | Token::ColumnSeparator) => {
+                    return Err(AlsError::AlsSyntaxError {
+                        position: tokenizer.position(),
+                        message: format!("Unterminated split part, found {:?}", token),
+                    });
+                }
+                token => operators.push(self.parse_element(tokenizer, token, depth)?),
+            }
+        }
+    }
+
+    /// Fail if an element is nested deeper than the configured limit.
+    fn check_depth(&self, tokenizer: &Tokenizer, depth: usize) -> Result<()> {
+        if depth > self.config.max_nesting_depth {
+            return Err(AlsError::AlsSyntaxError {
+                position: tokenizer.position(),
+                message: format!("Operators nested more than {} levels deep", self.config.max_nesting_depth),
+            });
+        }
+        Ok(())
+    }
+
     /// Expect and consume an integer token.
     fn expect_integer(&self, tokenizer: &mut Tokenizer) -> Result<i64> {
         match tokenizer.next_token()? {
@@ -318,11 +630,41 @@ impl AlsParser {
         }
     }
 
+    /// Check that an operator expands to at most `max_range_expansion`
+    /// values, reported as a range over its rows if not.
+    ///
+    /// `None` stands for a count that overflowed while adding it up.
+    fn check_expansion(&self, count: Option<usize>) -> Result<usize> {
+        match count {
+            Some(count) if count <= self.config.max_range_expansion => Ok(count),
+            _ => Err(AlsError::RangeOverflow {
+                start: 1,
+                end: count.map_or(i64::MAX, |count| i64::try_from(count).unwrap_or(i64::MAX)),
+                step: 1,
+            }),
+        }
+    }
+
+    /// Expect and consume a mandatory `*count` suffix.
+    fn expect_count(&self, tokenizer: &mut Tokenizer, what: &str) -> Result<usize> {
+        match tokenizer.next_token()? {
+            Token::MultiplyOp => {}
+            other => {
+                return Err(AlsError::AlsSyntaxError {
+                    position: tokenizer.position(),
+                    message: format!("Expected '*' after {} but found {:?}", what, other),
+                });
+            }
+        }
+        Ok(self.expect_integer(tokenizer)? as usize)
+    }
+
     /// Expect and consume a value token (integer, float, or raw).
     fn expect_value(&self, tokenizer: &mut Tokenizer) -> Result<String> {
         match tokenizer.next_token()? {
             Token::Integer(n) => Ok(n.to_string()),
             Token::Float(f) => Ok(f.to_string()),
+            Token::ScientificFloat { text, .. } => Ok(text),
             Token::RawValue(s) => Ok(s),
             other => Err(AlsError::AlsSyntaxError {
                 position: tokenizer.position(),
@@ -336,44 +678,33 @@ impl AlsParser {
     /// Each row is a vector of string values.
     ///
     /// When the `parallel` feature is enabled and the data is large enough,
-    /// columns are expanded in parallel for better performance.
+    /// columns, or the blocks of a block-mode document, are expanded in
+    /// parallel for better performance.
     pub fn expand(&self, doc: &AlsDocument) -> Result<Vec<Vec<String>>> {
         if doc.streams.is_empty() {
             return Ok(Vec::new());
         }
 
+        // Blocks expand independently, so large block-mode documents are
+        // expanded a block per thread
+        #[cfg(feature = "parallel")]
+        {
+            if doc.block_count() > 1 && self.should_use_parallel_expand(doc) {
+                let mut rows = Vec::with_capacity(doc.row_count());
+                for block in self.par_expand(doc) {
+                    rows.extend(block?);
+                }
+                return Ok(rows);
+            }
+        }
+
         // Get the default dictionary for resolving references
         let default_dict = doc.default_dictionary();
 
         // Expand all columns (parallel or sequential based on size)
         let expanded_columns = self.expand_columns_internal(doc, default_dict)?;
 
-        // Validate all columns have the same length
-        if let Some(first) = expanded_columns.first() {
-            let expected_len = first.len();
-            for col in expanded_columns.iter() {
-                if col.len() != expected_len {
-                    return Err(AlsError::ColumnMismatch {
-                        schema: expected_len,
-                        data: col.len(),
-                    });
-                }
-            }
-        }
-
-        // Transpose columns to rows
-        let row_count = expanded_columns.first().map(|c| c.len()).unwrap_or(0);
-        let mut rows = Vec::with_capacity(row_count);
-        
-        for row_idx in 0..row_count {
-            let row: Vec<String> = expanded_columns
-                .iter()
-                .map(|col| col[row_idx].clone())
-                .collect();
-            rows.push(row);
-        }
-
-        Ok(rows)
+        columns_to_rows(expanded_columns)
     }
 
     /// Determine if parallel processing should be used for expansion.
@@ -419,14 +750,48 @@ impl AlsParser {
         doc: &AlsDocument,
         default_dict: Option<&Vec<String>>,
     ) -> Result<Vec<Vec<String>>> {
+        let dict_slice = default_dict.map(|v| v.as_slice());
         let mut expanded_columns: Vec<Vec<String>> = Vec::with_capacity(doc.streams.len());
         for stream in &doc.streams {
-            let column_values = stream.expand(default_dict.map(|v| v.as_slice()))?;
+            // Column references may only point at preceding columns
+            let column_values = if stream.has_column_refs() {
+                stream.expand_with_columns(dict_slice, &expanded_columns)?
+            } else {
+                stream.expand(dict_slice)?
+            };
             expanded_columns.push(column_values);
         }
         Ok(expanded_columns)
     }
 
+    /// Expand a stream on its own, deferring streams with column references.
+    ///
+    /// Deferred streams produce an empty placeholder that
+    /// `resolve_column_refs` fills in once all other columns are expanded.
+    #[cfg(feature = "parallel")]
+    fn expand_independent(stream: &ColumnStream, dictionary: Option<&[String]>) -> Result<Vec<String>> {
+        if stream.has_column_refs() {
+            Ok(Vec::new())
+        } else {
+            stream.expand(dictionary)
+        }
+    }
+
+    /// Expand streams with column references, in column order.
+    #[cfg(feature = "parallel")]
+    fn resolve_column_refs(
+        doc: &AlsDocument,
+        dictionary: Option<&[String]>,
+        expanded_columns: &mut [Vec<String>],
+    ) -> Result<()> {
+        for (idx, stream) in doc.streams.iter().enumerate() {
+            if stream.has_column_refs() {
+                expanded_columns[idx] = stream.expand_with_columns(dictionary, &expanded_columns[..idx])?;
+            }
+        }
+        Ok(())
+    }
+
     /// Expand columns in parallel using Rayon.
     #[cfg(feature = "parallel")]
     fn expand_columns_parallel(
@@ -450,18 +815,20 @@ impl AlsParser {
             pool.install(|| {
                 doc.streams
                     .par_iter()
-                    .map(|stream| stream.expand(dict_slice))
+                    .map(|stream| Self::expand_independent(stream, dict_slice))
                     .collect()
             })
         } else {
             // Use default Rayon thread pool (auto-detect cores)
             doc.streams
                 .par_iter()
-                .map(|stream| stream.expand(dict_slice))
+                .map(|stream| Self::expand_independent(stream, dict_slice))
                 .collect()
         };
 
-        result
+        let mut expanded_columns = result?;
+        Self::resolve_column_refs(doc, dict_slice, &mut expanded_columns)?;
+        Ok(expanded_columns)
     }
 
     /// Check if parallel processing would be used for the given document.
@@ -499,32 +866,139 @@ impl AlsParser {
         let default_dict = doc.default_dictionary();
         let expanded_columns = self.expand_columns_parallel(doc, default_dict)?;
 
-        // Validate all columns have the same length
-        if let Some(first) = expanded_columns.first() {
-            let expected_len = first.len();
-            for col in expanded_columns.iter() {
-                if col.len() != expected_len {
-                    return Err(AlsError::ColumnMismatch {
-                        schema: expected_len,
-                        data: col.len(),
-                    });
+        columns_to_rows(expanded_columns)
+    }
+
+    /// Expand the rows of one block of a block-mode document.
+    ///
+    /// Outside block mode, block 0 holds every row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::AlsSyntaxError` if an operator crosses the block
+    /// boundary, or `AlsError::InvalidBlock` for a block past the end.
+    pub fn expand_block(&self, doc: &AlsDocument, block: usize) -> Result<Vec<Vec<String>>> {
+        columns_to_rows(self.expand_block_columns(doc, block)?)
+    }
+
+    /// Expand the columns of one block of a block-mode document.
+    fn expand_block_columns(&self, doc: &AlsDocument, block: usize) -> Result<Vec<Vec<String>>> {
+        if block >= doc.block_count() {
+            return Err(AlsError::InvalidBlock {
+                block,
+                blocks: doc.block_count(),
+            });
+        }
+
+        let dictionary = doc.default_dictionary().map(|d| d.as_slice());
+        let mut columns: Vec<Vec<String>> = Vec::with_capacity(doc.streams.len());
+        for column in 0..doc.streams.len() {
+            let stream = doc.block_stream(column, block)?;
+            let values = if stream.has_column_refs() {
+                stream.expand_with_columns(dictionary, &columns)?
+            } else {
+                stream.expand(dictionary)?
+            };
+            columns.push(values);
+        }
+        Ok(columns)
+    }
+
+    /// Expand a document column by column, checking the columns have the
+    /// same length.
+    ///
+    /// Conversions that build columns anyway use this rather than `expand`,
+    /// skipping the round trip through rows.
+    pub(crate) fn expand_columns(&self, doc: &AlsDocument) -> Result<Vec<Vec<String>>> {
+        if doc.streams.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            if doc.block_count() > 1 && self.should_use_parallel_expand(doc) {
+                let blocks: Vec<Vec<Vec<String>>> = (0..doc.block_count())
+                    .into_par_iter()
+                    .map(|block| self.expand_block_columns(doc, block))
+                    .collect::<Result<_>>()?;
+
+                let mut columns: Vec<Vec<String>> = Vec::with_capacity(doc.streams.len());
+                for block in blocks {
+                    check_column_lengths(&block)?;
+                    if columns.is_empty() {
+                        columns = block;
+                    } else {
+                        for (column, values) in columns.iter_mut().zip(block) {
+                            column.extend(values);
+                        }
+                    }
                 }
+                return Ok(columns);
             }
         }
 
-        // Transpose columns to rows
-        let row_count = expanded_columns.first().map(|c| c.len()).unwrap_or(0);
-        let mut rows = Vec::with_capacity(row_count);
-        
-        for row_idx in 0..row_count {
-            let row: Vec<String> = expanded_columns
-                .iter()
-                .map(|col| col[row_idx].clone())
-                .collect();
-            rows.push(row);
+        let columns = self.expand_columns_internal(doc, doc.default_dictionary())?;
+        check_column_lengths(&columns)?;
+        Ok(columns)
+    }
+
+    /// Expand the blocks of a document in parallel, yielding the rows of
+    /// each block in order.
+    ///
+    /// Blocks are expanded a batch at a time, one per thread, so memory
+    /// stays bounded by a few blocks whatever the size of the document.
+    /// Outside block mode, the single block holds every row.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, AlsParser, AlsSerializer, CompressorConfig};
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let config = CompressorConfig::new().with_block_size(2);
+    /// let data = parse_csv("id,level\n1,info\n2,warn\n3,info\n4,info\n5,error").unwrap();
+    /// let doc = AlsCompressor::with_config(config).compress(&data).unwrap();
+    ///
+    /// let parser = AlsParser::new();
+    /// let blocks: Vec<_> = parser.par_expand(&doc).collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(blocks.len(), 3);
+    /// assert_eq!(blocks[2], vec![vec!["5".to_string(), "error".to_string()]]);
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn par_expand<'a>(&'a self, doc: &'a AlsDocument<'a>) -> ParExpand<'a> {
+        let batch = match self.config.parallelism {
+            0 => rayon::current_num_threads(),
+            threads => threads,
+        };
+        ParExpand {
+            parser: self,
+            doc,
+            next_block: 0,
+            batch,
+            ready: std::collections::VecDeque::new(),
         }
+    }
 
-        Ok(rows)
+    /// Create an [`Expander`] with this parser's configuration, for
+    /// expanding many documents while reusing the row buffers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let mut expander = parser.expander();
+    /// for als in ["#id\n1>3", "#id\n7>8"] {
+    ///     let rows = expander.expand(&parser.parse(als).unwrap()).unwrap();
+    ///     assert_eq!(rows[0].len(), 1);
+    /// }
+    /// ```
+    pub fn expander(&self) -> Expander {
+        Expander {
+            parser: AlsParser::with_config(self.config.clone()),
+            rows: Vec::new(),
+        }
     }
 
     /// Parse ALS and expand directly to rows.
@@ -559,56 +1033,15 @@ impl AlsParser {
     /// ```
     pub fn to_csv(&self, input: &str) -> Result<String> {
         use crate::convert::csv::to_csv;
-        use crate::convert::{Column, TabularData, Value};
-        use std::borrow::Cow;
 
         // Parse ALS document
         let doc = self.parse(input)?;
 
-        // Expand to rows
-        let rows = self.expand(&doc)?;
-
-        // Convert to TabularData
-        let mut data = TabularData::with_capacity(doc.schema.len());
-
-        if !rows.is_empty() {
-            // Transpose rows to columns
-            for (col_idx, col_name) in doc.schema.iter().enumerate() {
-                let col_values: Vec<Value> = rows
-                    .iter()
-                    .map(|row| {
-                        let value_str = &row[col_idx];
-                        // Check for special tokens first
-                        if value_str == crate::als::NULL_TOKEN {
-                            Value::Null
-                        } else if value_str == crate::als::EMPTY_TOKEN {
-                            Value::String(Cow::Owned(String::new()))
-                        } else if value_str.is_empty() {
-                            // Empty string without token (shouldn't happen but handle it)
-                            Value::Null
-                        } else if let Ok(i) = value_str.parse::<i64>() {
-                            Value::Integer(i)
-                        } else if let Ok(f) = value_str.parse::<f64>() {
-                            Value::Float(f)
-                        } else if let Some(b) = parse_boolean_value(value_str) {
-                            Value::Boolean(b)
-                        } else {
-                            Value::String(Cow::Owned(value_str.clone()))
-                        }
-                    })
-                    .collect();
-
-                data.add_column(Column::new(Cow::Owned(col_name.clone()), col_values));
-            }
-        } else {
-            // Empty data - just add columns with no values
-            for col_name in &doc.schema {
-                data.add_column(Column::new(Cow::Owned(col_name.clone()), Vec::new()));
-            }
-        }
+        // Expand to columns, which CSV conversion works on directly
+        let columns = self.expand_columns(&doc)?;
 
         // Convert to CSV
-        to_csv(&data)
+        to_csv(&Self::columns_to_tabular(&doc, columns, false))
     }
 
     /// Parse ALS format and convert directly to JSON.
@@ -635,57 +1068,168 @@ impl AlsParser {
     /// assert!(json.contains("\"id\""));
     /// ```
     pub fn to_json(&self, input: &str) -> Result<String> {
-        use crate::convert::json::to_json;
-        use crate::convert::{Column, TabularData, Value};
-        use std::borrow::Cow;
+        use crate::convert::json::to_json_with_format;
+
+        to_json_with_format(&self.to_json_data(input)?, self.config.json_format)
+    }
+
+    /// Parse ALS format and convert directly to column-oriented JSON.
+    ///
+    /// The output is a single object mapping each column name to an array of
+    /// its values, e.g. `{"id":[1,2,3],"name":["a","b","c"]}`, with columns
+    /// in schema order. Values are typed as in `to_json`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let als = "#id #name\n1>3|Alice Bob Charlie";
+    /// let json = parser.to_json_columnar(als).unwrap();
+    /// assert_eq!(json, r#"{"id":[1,2,3],"name":["Alice","Bob","Charlie"]}"#);
+    /// ```
+    pub fn to_json_columnar(&self, input: &str) -> Result<String> {
+        use crate::convert::json::to_json_columnar_with_format;
+
+        to_json_columnar_with_format(&self.to_json_data(input)?, self.config.json_format)
+    }
+
+    /// Rebuild the data a delta was made from.
+    ///
+    /// `delta` must come from
+    /// [`AlsCompressor::compress_delta`](crate::AlsCompressor::compress_delta)
+    /// against `base`. Values are typed as in `to_json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidDelta` if `delta` is not a delta or was made
+    /// against a different base.
+    pub fn apply_delta(&self, base: &AlsDocument, delta: &AlsDocument) -> Result<crate::convert::TabularData<'static>> {
+        let invalid = |message: &str| AlsError::InvalidDelta {
+            message: message.to_string(),
+        };
+        let script = delta
+            .delta
+            .as_ref()
+            .ok_or_else(|| invalid("document has no edit script"))?;
+        if delta.schema != base.schema {
+            return Err(invalid("columns differ from the base"));
+        }
+
+        let base_rows = self.expand(base)?;
+        if base_rows.len() == script.base_rows && DeltaScript::checksum(&base_rows) != script.base_checksum {
+            return Err(invalid("base checksum does not match"));
+        }
+        let rows = script.apply(&base_rows, &self.expand(delta)?)?;
+        Ok(Self::columns_to_tabular(base, transpose(rows, base.schema.len()), true))
+    }
+
+    /// Expand a document with the tokens of its pseudonymized columns
+    /// replaced by the real values held in `key`.
+    ///
+    /// Only columns the document records as pseudonymized are touched, and
+    /// tokens missing from the key, as with a key built under another salt,
+    /// are left in place. Values are typed as in `to_json`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails to expand.
+    pub fn reveal(&self, doc: &AlsDocument, key: &PseudonymKey) -> Result<crate::convert::TabularData<'static>> {
+        let mut columns = self.expand_columns(doc)?;
+        key.reveal_columns(doc, &mut columns);
+        Ok(Self::columns_to_tabular(doc, columns, true))
+    }
 
+    /// Measure the value distribution and storage cost of each column of a
+    /// document.
+    ///
+    /// The document is expanded, so sizes and entropies describe the real
+    /// values rather than estimates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let doc = parser.parse("#id #level\n1>4|info warn*3").unwrap();
+    /// let profiles = parser.profile(&doc).unwrap();
+    ///
+    /// assert_eq!(profiles[0].distinct_count, 4);
+    /// assert_eq!(profiles[0].entropy, 2.0);
+    /// assert_eq!(profiles[1].expanded_bytes, "level".len() + "info,warn,warn,warn,".len());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails to expand.
+    pub fn profile(&self, doc: &AlsDocument) -> Result<Vec<ColumnProfile>> {
+        let columns = self.expand_columns(doc)?;
+        let serializer = super::AlsSerializer::new();
+        let mut encoded = String::new();
+
+        Ok(doc
+            .schema
+            .iter()
+            .zip(&doc.streams)
+            .zip(columns)
+            .map(|((name, stream), values)| {
+                encoded.clear();
+                serializer.serialize_stream(&mut encoded, stream, None);
+                let (distinct_count, entropy) = value_distribution(&values);
+                ColumnProfile {
+                    name: name.clone(),
+                    value_count: values.len(),
+                    distinct_count,
+                    entropy,
+                    expanded_bytes: name.len() + values.iter().map(|value| value.len() + 1).sum::<usize>(),
+                    encoded_bytes: encoded.len(),
+                }
+            })
+            .collect())
+    }
+
+    /// Parse ALS format into tabular data with JSON value types inferred.
+    fn to_json_data(&self, input: &str) -> Result<crate::convert::TabularData<'static>> {
         // Parse ALS document
         let doc = self.parse(input)?;
 
-        // Expand to rows
-        let rows = self.expand(&doc)?;
+        // Expand to columns
+        let columns = self.expand_columns(&doc)?;
+
+        Ok(Self::columns_to_tabular(&doc, columns, true))
+    }
+
+    /// Convert the expanded columns of a document to tabular data, inferring
+    /// value types.
+    ///
+    /// With `boolean_runs`, columns stored as boolean runs are restored as
+    /// booleans, as JSON output expects.
+    pub(crate) fn columns_to_tabular(
+        doc: &AlsDocument,
+        columns: Vec<Vec<String>>,
+        boolean_runs: bool,
+    ) -> crate::convert::TabularData<'static> {
+        use crate::convert::{Column, TabularData, Value};
+        use std::borrow::Cow;
 
-        // Convert to TabularData
         let mut data = TabularData::with_capacity(doc.schema.len());
 
-        if !rows.is_empty() {
-            // Transpose rows to columns
-            for (col_idx, col_name) in doc.schema.iter().enumerate() {
-                let col_values: Vec<Value> = rows
-                    .iter()
-                    .map(|row| {
-                        let value_str = &row[col_idx];
-                        // Check for special tokens first
-                        if value_str == crate::als::NULL_TOKEN {
-                            Value::Null
-                        } else if value_str == crate::als::EMPTY_TOKEN {
-                            Value::String(Cow::Owned(String::new()))
-                        } else if value_str.is_empty() {
-                            // Empty string without token (shouldn't happen but handle it)
-                            Value::Null
-                        } else if let Ok(i) = value_str.parse::<i64>() {
-                            Value::Integer(i)
-                        } else if let Ok(f) = value_str.parse::<f64>() {
-                            Value::Float(f)
-                        } else if let Some(b) = parse_boolean_value(value_str) {
-                            Value::Boolean(b)
-                        } else {
-                            Value::String(Cow::Owned(value_str.clone()))
-                        }
-                    })
-                    .collect();
+        // An empty document has no streams, so its columns have no values
+        let columns = columns.into_iter().chain(std::iter::repeat_with(Vec::new));
+        for (col_idx, (col_name, values)) in doc.schema.iter().zip(columns).enumerate() {
+            let boolean_column = boolean_runs && doc.streams.get(col_idx).is_some_and(is_boolean_stream);
 
-                data.add_column(Column::new(Cow::Owned(col_name.clone()), col_values));
-            }
-        } else {
-            // Empty data - just add columns with no values
-            for col_name in &doc.schema {
-                data.add_column(Column::new(Cow::Owned(col_name.clone()), Vec::new()));
-            }
+            let col_values: Vec<Value> = values
+                .into_iter()
+                .map(|value_str| typed_value(value_str, boolean_column))
+                .collect();
+
+            data.add_column(Column::new(Cow::Owned(col_name.clone()), col_values));
         }
 
-        // Convert to JSON
-        to_json(&data)
+        data
     }
 
     /// Parse ALS format text into an `AlsDocument` asynchronously.
@@ -719,7 +1263,7 @@ impl AlsParser {
     ///
     /// This method requires the `async` feature to be enabled.
     #[cfg(feature = "async")]
-    pub async fn parse_async(&self, input: &str) -> Result<AlsDocument> {
+    pub async fn parse_async(&self, input: &str) -> Result<AlsDocument<'static>> {
         let input = input.to_string();
         let config = self.config.clone();
         
@@ -847,7 +1391,7 @@ impl AlsParser {
     ///
     /// This method requires the `async` feature to be enabled.
     #[cfg(feature = "async")]
-    pub async fn expand_async(&self, doc: AlsDocument) -> Result<Vec<Vec<String>>> {
+    pub async fn expand_async(&self, doc: AlsDocument<'static>) -> Result<Vec<Vec<String>>> {
         let config = self.config.clone();
         
         // Spawn blocking task to avoid blocking the async executor
@@ -863,15 +1407,161 @@ impl AlsParser {
     }
 }
 
-/// Parse a string as a boolean value (helper for to_csv).
-fn parse_boolean_value(s: &str) -> Option<bool> {
-    match s.to_lowercase().as_str() {
+/// Iterator over the rows of a document, block by block, returned by
+/// [`AlsParser::par_expand`].
+#[cfg(feature = "parallel")]
+pub struct ParExpand<'a> {
+    parser: &'a AlsParser,
+    doc: &'a AlsDocument<'a>,
+    next_block: usize,
+    batch: usize,
+    ready: std::collections::VecDeque<Result<Vec<Vec<String>>>>,
+}
+
+#[cfg(feature = "parallel")]
+impl Iterator for ParExpand<'_> {
+    type Item = Result<Vec<Vec<String>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block_count = self.doc.block_count();
+        if self.ready.is_empty() && self.next_block < block_count {
+            let end = (self.next_block + self.batch).min(block_count);
+            let (parser, doc) = (self.parser, self.doc);
+            self.ready = (self.next_block..end)
+                .into_par_iter()
+                .map(|block| parser.expand_block(doc, block))
+                .collect::<Vec<_>>()
+                .into();
+            self.next_block = end;
+        }
+
+        let rows = self.ready.pop_front()?;
+        if rows.is_err() {
+            // Stop after the first error
+            self.ready.clear();
+            self.next_block = block_count;
+        }
+        Some(rows)
+    }
+}
+
+/// Expands documents to rows, keeping the row vectors from one call to the
+/// next, returned by [`AlsParser::expander`].
+///
+/// Expanding a document with `AlsParser::expand` allocates a vector per
+/// row. Services decompressing many small payloads can keep an `Expander`
+/// instead, so those vectors are allocated once and refilled by each call.
+pub struct Expander {
+    parser: AlsParser,
+    rows: Vec<Vec<String>>,
+}
+
+impl Expander {
+    /// Expand a document to rows, as `AlsParser::expand` does.
+    ///
+    /// The rows stay borrowed from the expander until the next call.
+    pub fn expand(&mut self, doc: &AlsDocument) -> Result<&[Vec<String>]> {
+        let columns = self.parser.expand_columns(doc)?;
+        let row_count = columns.first().map_or(0, Vec::len);
+        transpose_into(columns, row_count, &mut self.rows);
+        Ok(&self.rows)
+    }
+
+    /// Parse ALS text and expand it to rows.
+    pub fn parse_and_expand(&mut self, input: &str) -> Result<&[Vec<String>]> {
+        let doc = self.parser.parse(input)?;
+        self.expand(&doc)
+    }
+
+    /// Get the parser used to expand documents.
+    pub fn parser(&self) -> &AlsParser {
+        &self.parser
+    }
+}
+
+/// Check expanded columns have the same length, returning the row count.
+fn check_column_lengths(columns: &[Vec<String>]) -> Result<usize> {
+    let row_count = columns.first().map_or(0, Vec::len);
+    if let Some(column) = columns.iter().find(|column| column.len() != row_count) {
+        return Err(AlsError::ColumnMismatch {
+            schema: row_count,
+            data: column.len(),
+        });
+    }
+    Ok(row_count)
+}
+
+/// Transpose expanded columns into rows, checking they have the same
+/// length.
+fn columns_to_rows(columns: Vec<Vec<String>>) -> Result<Vec<Vec<String>>> {
+    let row_count = check_column_lengths(&columns)?;
+    Ok(transpose(columns, row_count))
+}
+
+/// Parse a string as a boolean value (helper for to_csv).
+fn parse_boolean_value(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
         "true" | "yes" | "y" | "t" => Some(true),
         "false" | "no" | "n" | "f" => Some(false),
         _ => None,
     }
 }
 
+/// Infer the type of an expanded value, as `columns_to_tabular` does.
+///
+/// In a boolean-run column, boolean labels become booleans.
+pub(crate) fn typed_value(value_str: String, boolean_column: bool) -> crate::convert::Value<'static> {
+    use crate::convert::Value;
+    use std::borrow::Cow;
+
+    if boolean_column {
+        if let Some(b) = parse_boolean_label(&value_str) {
+            return Value::Boolean(b);
+        }
+    }
+
+    // Check for special tokens first
+    if value_str == crate::als::NULL_TOKEN {
+        Value::Null
+    } else if value_str == crate::als::EMPTY_TOKEN {
+        Value::String(Cow::Owned(String::new()))
+    } else if value_str.is_empty() {
+        // Empty string without token (shouldn't happen but handle it)
+        Value::Null
+    } else if let Ok(i) = value_str.parse::<i64>() {
+        Value::Integer(i)
+    } else if let Ok(f) = value_str.parse::<f64>() {
+        Value::Float(f)
+    } else if let Some(b) = parse_boolean_value(&value_str) {
+        Value::Boolean(b)
+    } else {
+        Value::String(Cow::Owned(value_str))
+    }
+}
+
+/// Parse a boolean label as written by the boolean column encoding.
+///
+/// Accepts everything `parse_boolean_value` does plus `on`/`off`. Numeric
+/// `1`/`0` labels are left to the integer path so numeric flags keep their type.
+fn parse_boolean_label(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => parse_boolean_value(s),
+    }
+}
+
+/// Check if a column stream consists only of boolean runs with boolean labels.
+pub(crate) fn is_boolean_stream(stream: &ColumnStream) -> bool {
+    !stream.operators.is_empty()
+        && stream.operators.iter().all(|op| match op {
+            AlsOperator::BoolRuns { labels, .. } => {
+                labels.iter().all(|label| parse_boolean_label(label).is_some())
+            }
+            _ => false,
+        })
+}
+
 impl Default for AlsParser {
     fn default() -> Self {
         Self::new()
@@ -906,6 +1596,101 @@ mod tests {
         assert_eq!(doc.format_indicator, FormatIndicator::Ctx);
     }
 
+    #[test]
+    fn test_parse_store_frame() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!store csv\nid,name\n1,a b\n2,c>d").unwrap();
+        assert!(doc.is_ctx());
+        assert_eq!(doc.schema, vec!["id", "name"]);
+        assert_eq!(parser.expand(&doc).unwrap(), vec![vec!["1", "a b"], vec!["2", "c>d"]]);
+    }
+
+    #[test]
+    fn test_parse_ctx_column() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v1\n#id #note\n1>3|!ctx a \\!b c").unwrap();
+        assert!(!doc.streams[0].is_ctx());
+        assert!(doc.streams[1].is_ctx());
+        assert_eq!(doc.streams[1].expand(None).unwrap(), vec!["a", "!b", "c"]);
+    }
+
+    #[test]
+    fn test_parse_ctx_column_rejects_operators() {
+        let parser = AlsParser::new();
+        assert!(parser.parse("!v1\n#id\n!ctx 1>3").is_err());
+        assert!(parser.parse("!v1\n#id\na !ctx b").is_err());
+    }
+
+    #[test]
+    fn test_parse_indexes() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v1\n#id #status\n1>4|a b a \\!idx\n!idx 1 \\!idx:3 a:0,2 b:1").unwrap();
+        assert_eq!(doc.streams[1].expand(None).unwrap(), vec!["a", "b", "a", "!idx"]);
+        assert!(doc.index(0).is_none());
+        assert_eq!(doc.index(1).unwrap().rows("a"), vec![0, 2]);
+        assert_eq!(doc.index(1).unwrap().rows("!idx"), vec![3]);
+
+        assert!(parser.parse("!v1\n#id\n1>3\n!idx 1 a:0").is_err());
+        assert!(parser.parse("!v1\n#id\n1>3\n!idx 0 a").is_err());
+    }
+
+    #[test]
+    fn test_parse_blocks() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v1\n#id\n1>3 4>5\n!blocks 3\n!bloom 0 7 ff,ff").unwrap();
+        assert_eq!(doc.block_size, Some(3));
+        assert_eq!(doc.block_count(), 2);
+        assert_eq!(doc.block_stream(0, 1).unwrap().expand(None).unwrap(), vec!["4", "5"]);
+
+        // Filters must cover every block, and operators must not cross blocks
+        assert!(parser.parse("!v1\n#id\n1>5\n!bloom 0 7 ff").is_err());
+        assert!(parser.parse("!v1\n#id\n1>5\n!blocks 3\n!bloom 0 7 ff").is_err());
+        assert!(parser.parse("!v1\n#id\n1>5\n!blocks 0").is_err());
+        assert!(parser.parse("!v1\n#id\n1>5\n!blocks 3\nnope").is_err());
+        assert!(parser.parse("!v1\n#id\n1>5\n!blocks 3").unwrap().block_stream(0, 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_nfc_line() {
+        let parser = AlsParser::new();
+        assert!(!parser.parse("!v1\n#id\n1>3").unwrap().nfc_normalized);
+        let doc = parser.parse("!v1\n#id\n1>3\n!nfc").unwrap();
+        assert!(doc.nfc_normalized);
+        assert_eq!(crate::als::AlsSerializer::new().serialize(&doc), "!v2 +nfc\n#id\n1>3\n!nfc");
+        assert!(parser.parse("!v1\n#id\n1>3\n!nfc 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_zone_maps() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v1\n#id\n1>3 4>5\n!blocks 3\n!zone 0 n 1 3 0 4 5 0").unwrap();
+        assert_eq!(doc.zone_maps[&0].len(), 2);
+        assert_eq!(doc.column_zone(0).unwrap().max.as_deref(), Some("5"));
+
+        assert!(parser.parse("!v1\n#id\n1>3 4>5\n!blocks 3\n!zone 0 n 1 3 0").is_err());
+        assert!(parser.parse("!v1\n#id\n1>5\n!zone 1 n 1 5 0").is_err());
+    }
+
+    #[test]
+    fn test_parse_delta() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v1\n#id #name\n|\n!delta 10 9d4944ef c0+10").unwrap();
+        assert_eq!(doc.streams.len(), 2);
+        assert_eq!(doc.row_count(), 0);
+        assert_eq!(doc.delta.as_ref().unwrap().copied_rows(), 10);
+
+        assert!(parser.parse("!v1\n#id\n1\n!delta 10 9d4944ef k1").is_err());
+    }
+
+    #[test]
+    fn test_parse_skips_signature() {
+        let parser = AlsParser::new();
+        let signed = format!("#id\n1>3\n!zone 0 n 1 3 0\n!sig ed25519 {} {}\n", "ab".repeat(32), "cd".repeat(64));
+        let doc = parser.parse(&signed).unwrap();
+        assert_eq!(doc.row_count(), 3);
+        assert_eq!(doc, parser.parse("#id\n1>3\n!zone 0 n 1 3 0").unwrap());
+    }
+
     #[test]
     fn test_parse_unsupported_version() {
         let parser = AlsParser::new();
@@ -936,6 +1721,25 @@ mod tests {
         assert_eq!(doc.streams[0].expanded_count(), 3);
     }
 
+    #[test]
+    fn test_parse_digit_led_raw_values() {
+        let parser = AlsParser::new();
+        let (_, rows) = parser
+            .parse_and_expand("#date #version\n2026-10-17 2026-10-18*2|1.2.3 10\\:30 3")
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["2026-10-17", "1.2.3"],
+                vec!["2026-10-18", "10:30"],
+                vec!["2026-10-18", "3"],
+            ]
+        );
+
+        let (_, rows) = parser.parse_and_expand("#a #b\n1>2|2026-01-01 10x").unwrap();
+        assert_eq!(rows, vec![vec!["1", "2026-01-01"], vec!["2", "10x"]]);
+    }
+
     #[test]
     fn test_parse_range() {
         let parser = AlsParser::new();
@@ -976,6 +1780,22 @@ mod tests {
         assert_eq!(expanded, vec!["T", "F", "T", "F"]);
     }
 
+    #[test]
+    fn test_parse_negative_operands() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#col\n-1>-5:-2 -3*2 -1.5~-2.5*3").unwrap();
+        let expanded = doc.streams[0].expand(None).unwrap();
+        assert_eq!(expanded, vec!["-1", "-3", "-5", "-3", "-3", "-1.5", "-2.5", "-1.5"]);
+    }
+
+    #[test]
+    fn test_parse_scientific_floats_keep_spelling() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#col\n1e3 -2.5E-3*2 1e3~-1e300*3").unwrap();
+        let expanded = doc.streams[0].expand(None).unwrap();
+        assert_eq!(expanded, vec!["1e3", "-2.5E-3", "-2.5E-3", "1e3", "-1e300", "1e3"]);
+    }
+
     #[test]
     fn test_parse_dict_ref() {
         let parser = AlsParser::new();
@@ -1006,6 +1826,30 @@ mod tests {
         assert_eq!(expanded, vec!["1", "2", "3", "1", "2", "3"]);
     }
 
+    #[test]
+    fn test_nesting_depth_limit() {
+        let nested = |depth: usize| format!("#col\n{}x{}", "(".repeat(depth), ")".repeat(depth));
+        let parser = AlsParser::new();
+        assert_eq!(parser.parse(&nested(64)).unwrap().streams[0].expand(None).unwrap(), vec!["x"]);
+        assert!(matches!(parser.parse(&nested(65)), Err(AlsError::AlsSyntaxError { .. })));
+
+        let parser = AlsParser::with_config(ParserConfig::new().with_max_nesting_depth(2));
+        assert!(parser.parse("#col\n^{U}(((x)))").is_err());
+        assert!(parser.parse("#col\n</1(((x)))").is_err());
+        assert!(parser.parse("#col\n^{U}((x))").is_ok());
+        assert!(parser.parse("#col\n^{U}(x*2)").is_ok());
+    }
+
+    #[test]
+    fn test_serialized_multiply_of_counted_operators() {
+        let parser = AlsParser::new();
+        for als in ["#col\n(@0*2)*2", "#col\n(?a~b:1.2)*2", "#col\n(&dow+0*2)*2"] {
+            let doc = parser.parse(als).unwrap();
+            let reparsed = parser.parse(&crate::als::AlsSerializer::new().serialize(&doc)).unwrap();
+            assert_eq!(reparsed.streams[0].operators, doc.streams[0].operators, "{}", als);
+        }
+    }
+
     #[test]
     fn test_parse_range_multiply() {
         let parser = AlsParser::new();
@@ -1101,15 +1945,28 @@ $default:active|inactive|pending
     #[test]
     fn test_version_future_version_error() {
         let parser = AlsParser::new();
-        let result = parser.parse("!v2\n#col\n1");
-        assert!(matches!(result, Err(AlsError::VersionMismatch { expected: 1, found: 2 })));
+        let result = parser.parse("!v3\n#col\n1");
+        assert!(matches!(result, Err(AlsError::VersionMismatch { expected: 2, found: 3 })));
+    }
+
+    #[test]
+    fn test_version_feature_flags() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v2 +blocks +date-range\n#day\n%{%Y-%m-%d}1704067200+86400*2\n!blocks 1").unwrap();
+        assert_eq!(doc.version, 2);
+        assert_eq!(parser.expand_columns(&doc).unwrap()[0], vec!["2024-01-01", "2024-01-02"]);
+
+        let result = parser.parse("!v2 +blocks +crc +binary-dict\n#col\n1");
+        assert!(matches!(result, Err(AlsError::UnsupportedFeature { feature }) if feature == "crc"));
+        assert!(matches!(parser.parse("!v1 +blocks\n#col\n1"), Err(AlsError::AlsSyntaxError { .. })));
+        assert!(matches!(parser.parse("!v3 +crc\n#col\n1"), Err(AlsError::VersionMismatch { .. })));
     }
 
     #[test]
     fn test_version_very_high_version_error() {
         let parser = AlsParser::new();
         let result = parser.parse("!v255\n#col\n1");
-        assert!(matches!(result, Err(AlsError::VersionMismatch { expected: 1, found: 255 })));
+        assert!(matches!(result, Err(AlsError::VersionMismatch { expected: 2, found: 255 })));
     }
 
     #[test]
@@ -1158,6 +2015,32 @@ $default:active|inactive|pending
         assert!(array[2]["col"].is_null());
     }
 
+    #[test]
+    fn test_to_json_columnar() {
+        let parser = AlsParser::new();
+        let als = "#name #id #score\nbob alice \\\\0|3>1|1.5*3";
+        let json = parser.to_json_columnar(als).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"name":["bob","alice",null],"id":[3,2,1],"score":[1.5,1.5,1.5]}"#
+        );
+        assert_eq!(parser.to_json_columnar("").unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_to_json_uses_configured_format() {
+        use crate::config::JsonFormat;
+
+        let als = "#id\n1>2";
+        let ndjson = AlsParser::with_config(ParserConfig::new().with_json_format(JsonFormat::Ndjson));
+        assert_eq!(ndjson.to_json(als).unwrap(), "{\"id\":1}\n{\"id\":2}\n");
+
+        let pretty =
+            AlsParser::with_config(ParserConfig::new().with_json_format(JsonFormat::Pretty { indent: 1 }));
+        assert_eq!(pretty.to_json_columnar(als).unwrap(), "{\n \"id\": [\n  1,\n  2\n ]\n}");
+    }
+
     #[test]
     fn test_to_json_with_types() {
         let parser = AlsParser::new();
@@ -1174,6 +2057,21 @@ $default:active|inactive|pending
         assert_eq!(array[0]["str"], "hello");
     }
 
+    #[test]
+    fn test_to_json_restores_boolean_runs() {
+        let parser = AlsParser::new();
+        let als = "#id #active #flag\n1>4|?ON~OFF:2.2|?1~0:1.3";
+        let json = parser.to_json(als).unwrap();
+
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let array = parsed.as_array().unwrap();
+
+        assert_eq!(array.len(), 4);
+        assert_eq!(array[0]["active"], true);
+        assert_eq!(array[3]["active"], false);
+        assert_eq!(array[0]["flag"], 1);
+    }
+
     #[test]
     fn test_to_json_nested_reconstruction() {
         let parser = AlsParser::new();
@@ -1268,6 +2166,338 @@ $default:active|inactive|pending
         }
     }
 
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_expand_blocks_in_order() {
+        use crate::compress::AlsCompressor;
+        use crate::config::CompressorConfig;
+        use crate::convert::csv::parse_csv;
+
+        let mut csv = String::from("id,side,copy\n");
+        for i in 0..3000 {
+            let side = if i % 7 < 3 { "buy" } else { "sell" };
+            csv.push_str(&format!("{},{},{}\n", i * 3, side, side));
+        }
+        let data = parse_csv(&csv).unwrap();
+        let sequential = AlsParser::with_config(ParserConfig::new().with_parallelism(1));
+        let flat = AlsCompressor::new().compress(&data).unwrap();
+        let expected = sequential.expand(&flat).unwrap();
+
+        let config = CompressorConfig::new().with_block_size(256);
+        let doc = AlsCompressor::with_config(config).compress(&data).unwrap();
+        let parser = AlsParser::with_config(ParserConfig::new().with_parallelism(3));
+        let blocks: Vec<Vec<Vec<String>>> = parser.par_expand(&doc).collect::<Result<_>>().unwrap();
+        assert_eq!(blocks.len(), doc.block_count());
+        assert!(blocks[..blocks.len() - 1].iter().all(|rows| rows.len() == 256));
+        assert_eq!(blocks.concat(), expected);
+
+        assert!(parser.would_use_parallel(&doc));
+        assert_eq!(parser.expand(&doc).unwrap(), expected);
+        assert_eq!(sequential.expand(&doc).unwrap(), expected);
+        assert!(matches!(
+            parser.expand_block(&doc, doc.block_count()),
+            Err(AlsError::InvalidBlock { block, blocks }) if block == blocks
+        ));
+    }
+
+    #[test]
+    fn test_expander_matches_expand() {
+        let parser = AlsParser::new();
+        let mut expander = parser.expander();
+        for als in ["#id #name\n1>3|a b c", "#id\n5>9", "#side #copy\nbuy sell*2|@0*3"] {
+            let doc = parser.parse(als).unwrap();
+            assert_eq!(expander.expand(&doc).unwrap(), parser.expand(&doc).unwrap().as_slice());
+        }
+        assert!(expander.parse_and_expand("#id #name\n1 2|x").is_err());
+        assert_eq!(expander.parse_and_expand("#id\n1>2").unwrap(), [vec!["1".to_string()], vec!["2".to_string()]]);
+    }
+
+    #[test]
+    fn test_to_csv_and_json_of_block_document() {
+        use crate::compress::AlsCompressor;
+        use crate::config::CompressorConfig;
+        use crate::convert::csv::parse_csv;
+
+        let mut csv = String::from("id,side,flag\n");
+        for i in 0..2000 {
+            let side = if i % 5 < 2 { "buy" } else { "sell" };
+            csv.push_str(&format!("{},{},{}\n", i * 2, side, i % 3 == 0));
+        }
+        let data = parse_csv(&csv).unwrap();
+        let serializer = crate::als::AlsSerializer::new();
+        let flat = serializer.serialize(&AlsCompressor::new().compress(&data).unwrap());
+        let config = CompressorConfig::new().with_block_size(300);
+        let blocked = serializer.serialize(&AlsCompressor::with_config(config).compress(&data).unwrap());
+
+        for parallelism in [1, 3] {
+            let parser = AlsParser::with_config(ParserConfig::new().with_parallelism(parallelism));
+            assert_eq!(parser.to_csv(&blocked).unwrap(), parser.to_csv(&flat).unwrap());
+            assert_eq!(parser.to_json(&blocked).unwrap(), parser.to_json(&flat).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_and_expand_column_ref() {
+        let parser = AlsParser::new();
+        let als = "#side #sign #copy\nbuy sell*2 buy|@0(buy:+1 sell:-1)*4|@0*4";
+        let doc = parser.parse(als).unwrap();
+
+        assert_eq!(
+            doc.streams[1].operators[0],
+            AlsOperator::column_ref(
+                0,
+                vec![("buy".to_string(), "+1".to_string()), ("sell".to_string(), "-1".to_string())],
+                4
+            )
+        );
+
+        let rows = parser.expand(&doc).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], vec!["buy", "+1", "buy"]);
+        assert_eq!(rows[1], vec!["sell", "-1", "sell"]);
+        assert_eq!(rows[3], vec!["buy", "+1", "buy"]);
+    }
+
+    #[test]
+    fn test_parse_and_expand_date_range() {
+        let parser = AlsParser::new();
+        let als = "#id #day\n1>3|%{%Y-%m-%d}1704067200+86400*3";
+        let doc = parser.parse(als).unwrap();
+
+        assert_eq!(
+            doc.streams[1].operators[0],
+            AlsOperator::date_range(1_704_067_200, 86_400, 3, "%Y-%m-%d")
+        );
+
+        let rows = parser.expand(&doc).unwrap();
+        assert_eq!(rows[0], vec!["1", "2024-01-01"]);
+        assert_eq!(rows[2], vec!["3", "2024-01-03"]);
+    }
+
+    #[test]
+    fn test_parse_and_expand_cycle() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#day #q\n&dayname+6*3|&qtr+0:2*3").unwrap();
+        assert_eq!(
+            doc.streams[0].operators[0],
+            AlsOperator::cycle(crate::als::CalendarCycle::WeekdayFull, 6, 1, 3)
+        );
+
+        let rows = parser.expand(&doc).unwrap();
+        assert_eq!(rows[0], vec!["Sunday", "Q1"]);
+        assert_eq!(rows[1], vec!["Monday", "Q1"]);
+        assert_eq!(rows[2], vec!["Tuesday", "Q2"]);
+    }
+
+    #[test]
+    fn test_cycle_rejects_zero_run() {
+        let parser = AlsParser::new();
+        assert!(parser.parse("#q\n&qtr+0:0*4").is_err());
+        assert!(parser.parse("#q\n&qtr+0:18446744073709551615*4").is_err());
+    }
+
+    #[test]
+    fn test_cycle_over_limit() {
+        let parser = AlsParser::new();
+        assert!(matches!(parser.parse("#d\n&dow+0*99999999999999"), Err(AlsError::RangeOverflow { .. })));
+        let parser = AlsParser::with_config(ParserConfig::new().with_max_range_expansion(7));
+        assert!(parser.parse("#d\n&dow+0*7").is_ok());
+        assert!(matches!(parser.parse("#d\n&dow+0*8"), Err(AlsError::RangeOverflow { end: 8, .. })));
+    }
+
+    #[test]
+    fn test_cycle_reduces_large_offsets() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#d\n&dow+18446744073709551615*2").unwrap();
+        assert_eq!(doc.streams[0].operators[0], AlsOperator::cycle(crate::als::CalendarCycle::Weekday, 1, 1, 2));
+        assert_eq!(parser.expand(&doc).unwrap(), vec![vec!["Tue"], vec!["Wed"]]);
+
+        // Operators built directly are not reduced, but still expand
+        let op = AlsOperator::cycle(crate::als::CalendarCycle::Weekday, usize::MAX, 1, 2);
+        assert_eq!(op.expand(None).unwrap(), ["Tue", "Wed"]);
+    }
+
+    #[test]
+    fn test_parse_and_expand_transform() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#user #day\n^USER{3}(1>3)|^{U}(&dow+6*3)").unwrap();
+        let rows = parser.expand(&doc).unwrap();
+        assert_eq!(rows[0], vec!["USER001", "SUN"]);
+        assert_eq!(rows[1], vec!["USER002", "MON"]);
+        assert_eq!(rows[2], vec!["USER003", "TUE"]);
+    }
+
+    #[test]
+    fn test_transform_roundtrip_inside_multiply() {
+        let op = AlsOperator::multiply(
+            AlsOperator::transform(
+                AlsOperator::range(8, 10),
+                crate::als::ValueTransform::new().with_width(2).with_suffix("h"),
+            ),
+            2,
+        );
+        let mut doc = AlsDocument::with_schema(vec!["hour"]);
+        doc.add_stream(ColumnStream::from_operators(vec![op]));
+
+        let text = crate::als::AlsSerializer::new().serialize(&doc);
+        let parsed = AlsParser::new().parse(&text).unwrap();
+        assert_eq!(parsed.streams, doc.streams);
+        assert_eq!(
+            parsed.streams[0].expand(None).unwrap(),
+            vec!["08h", "09h", "10h", "08h", "09h", "10h"]
+        );
+    }
+
+    #[test]
+    fn test_parse_and_expand_bool_runs() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#id #ok\n1>5|?yes~no:2.2.1").unwrap();
+        let rows = parser.expand(&doc).unwrap();
+        let ok: Vec<&str> = rows.iter().map(|row| row[1].as_str()).collect();
+        assert_eq!(ok, vec!["yes", "yes", "no", "no", "yes"]);
+    }
+
+    #[test]
+    fn test_parse_bool_runs_over_limit() {
+        let parser = AlsParser::new();
+        assert!(matches!(
+            parser.to_csv("#a\n?a~b:18446744073709551615.1"),
+            Err(AlsError::RangeOverflow { .. })
+        ));
+        let parser = AlsParser::with_config(ParserConfig::new().with_max_range_expansion(5));
+        assert!(parser.parse("#a\n?a~b:3.2").is_ok());
+        assert!(matches!(parser.parse("#a\n?a~b:3.3"), Err(AlsError::RangeOverflow { start: 1, end: 6, step: 1 })));
+    }
+
+    #[test]
+    fn test_parse_and_expand_split() {
+        let parser = AlsParser::new();
+        let als = "$default:api.example.com\n#url\n</4(https\\:*3)(\\\\e*3)(_0*3)(v1/users v1/orders health)";
+        let doc = parser.parse(als).unwrap();
+        let rows = parser.expand(&doc).unwrap();
+        let urls: Vec<&str> = rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://api.example.com/v1/users",
+                "https://api.example.com/v1/orders",
+                "https://api.example.com/health",
+            ]
+        );
+
+        let doc = parser.parse("$default:us-east-1|eu-west-1\n#zone\n<2(_0*2 _1)(a b a)").unwrap();
+        let rows = parser.expand(&doc).unwrap();
+        let zones: Vec<&str> = rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(zones, vec!["us-east-1a", "us-east-1b", "eu-west-1a"]);
+
+        assert!(parser.parse("#url\n</2(a b)").is_err());
+        assert!(parser.parse("#url\n</2(a)(b").is_err());
+    }
+
+    #[test]
+    fn test_parse_front_coded_dictionary() {
+        let parser = AlsParser::new();
+        let als = "$default~:/api/v1/users|8~orders|1~health\n#path\n_0 _1 _2";
+        let doc = parser.parse(als).unwrap();
+        assert_eq!(
+            doc.default_dictionary().unwrap(),
+            &vec!["/api/v1/users".to_string(), "/api/v1/orders".to_string(), "/health".to_string()]
+        );
+        assert!(parser.parse("$default~:abc|def\n#path\n_0").is_err());
+    }
+
+    #[test]
+    fn test_parse_relative_dict_refs() {
+        let parser = AlsParser::new();
+        let dict: Vec<String> = (0..12).map(|i| format!("s{}", i)).collect();
+        let als = format!("$default:{}\n#status\n_10*2 _= _+*3", dict.join("|"));
+        let doc = parser.parse(&als).unwrap();
+        assert_eq!(
+            doc.streams[0].operators,
+            vec![
+                AlsOperator::multiply(AlsOperator::dict_ref(10), 2),
+                AlsOperator::dict_ref(10),
+                AlsOperator::multiply(AlsOperator::dict_ref(11), 3),
+            ]
+        );
+
+        let rows = parser.expand(&doc).unwrap();
+        let status: Vec<&str> = rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(status, vec!["s10", "s10", "s10", "s11", "s11", "s11"]);
+    }
+
+    #[test]
+    fn test_operator_heads_without_their_syntax_are_values() {
+        let parser = AlsParser::new();
+        let values = ["^up", "&dow", "&qtr+0", "?ok", "<b", "%{x}", "%{%Y}0+86400"];
+        let doc = parser.parse(&format!("#v\n{}", values.join(" "))).unwrap();
+        let rows = parser.expand(&doc).unwrap();
+        assert_eq!(rows.iter().map(|row| row[0].as_str()).collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn test_date_range_roundtrip() {
+        let mut doc = AlsDocument::with_schema(vec!["ts"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::date_range(
+            1_700_000_000,
+            3_600,
+            48,
+            "%Y-%m-%dT%H:%M:%SZ",
+        )]));
+        let text = crate::als::AlsSerializer::new().serialize(&doc);
+        let parsed = AlsParser::new().parse(&text).unwrap();
+        assert_eq!(parsed.streams, doc.streams);
+    }
+
+    #[test]
+    fn test_column_ref_requires_count() {
+        let parser = AlsParser::new();
+        assert!(parser.parse("#a #b\nx y|@0(x:1)").is_err());
+    }
+
+    #[test]
+    fn test_column_ref_over_limit() {
+        let parser = AlsParser::new();
+        assert!(matches!(
+            parser.parse("#a #b\nx*99999999999999|@0(1:x)*99999999999999"),
+            Err(AlsError::RangeOverflow { .. })
+        ));
+        let parser = AlsParser::with_config(ParserConfig::new().with_max_range_expansion(3));
+        assert!(parser.parse("#a #b\nx y z|@0*3").is_ok());
+        assert!(matches!(parser.parse("#a #b\nx y z w|@0*4"), Err(AlsError::RangeOverflow { end: 4, .. })));
+    }
+
+    #[test]
+    fn test_at_led_raw_value() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#a #b\n@me y|1 2").unwrap();
+        assert_eq!(parser.expand(&doc).unwrap(), vec![vec!["@me", "1"], vec!["y", "2"]]);
+    }
+
+    #[test]
+    fn test_column_ref_to_later_column_fails() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#a #b\n@1*2|x y").unwrap();
+        assert!(matches!(
+            parser.expand(&doc),
+            Err(AlsError::InvalidColumnRef { index: 1, columns: 0 })
+        ));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_expand_parallel_column_ref() {
+        let parser = AlsParser::new();
+        let als = "#side #id #sign\nbuy~sell*40|1>40|@0(buy:B sell:S)*40";
+        let doc = parser.parse(als).unwrap();
+
+        let sequential_result = parser.expand(&doc).unwrap();
+        let parallel_result = parser.expand_parallel(&doc).unwrap();
+
+        assert_eq!(sequential_result, parallel_result);
+        assert_eq!(parallel_result[1], vec!["sell", "2", "S"]);
+    }
+
     #[cfg(feature = "parallel")]
     #[test]
     fn test_expand_parallel_empty_doc() {
@@ -0,0 +1,296 @@
+//! Recovering what can be read of damaged documents.
+//!
+//! A process that crashes while writing a document leaves it cut short,
+//! and a failing disk can leave garbage in its tail. Columns are written
+//! one after another, so the damage falls in one of them: the columns
+//! before it are whole, the columns after it are lost, and the one it falls
+//! in holds the values written before it. [`AlsParser::parse_partial`]
+//! keeps the whole columns and the readable start of the damaged one, cut
+//! to the rows all of them cover.
+
+use super::document::{AlsDocument, ColumnStream, FormatIndicator};
+use super::parser::{split_trailer, AlsParser};
+use super::signature::split_signature;
+use super::store::StoreFrame;
+use super::tokenizer::{Token, Tokenizer, VersionType};
+use crate::error::{AlsError, Result};
+
+/// A document read by [`AlsParser::parse_partial`].
+#[derive(Debug)]
+pub struct PartialDocument {
+    /// The columns and rows that could be recovered.
+    pub document: AlsDocument<'static>,
+    /// Names of the columns that were lost, in schema order.
+    pub lost_columns: Vec<String>,
+    /// Rows held by the columns that were read whole, or `None` if the
+    /// damage falls in the first column.
+    pub expected_rows: Option<usize>,
+    /// The error that kept the input from being read whole, or `None` if
+    /// it was intact.
+    pub error: Option<AlsError>,
+}
+
+impl PartialDocument {
+    /// Get the number of rows recovered.
+    pub fn rows(&self) -> usize {
+        self.document.row_count()
+    }
+
+    /// Check if the input was read whole.
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl AlsParser {
+    /// Parse as much of a truncated or damaged document as can be read.
+    ///
+    /// An intact document is read as by [`parse`](Self::parse). Otherwise
+    /// the columns read whole are kept, along with the values of the
+    /// damaged column that come before the damage, and every column is cut
+    /// to the rows they all cover. The last operator of the damaged column
+    /// is dropped too, since it may have been cut short: `1>1000` cut to
+    /// `1>10` still reads as a range. A damaged trailer is skipped, along
+    /// with its Bloom filters and zone maps.
+    ///
+    /// A cut in the last operator of the last column that leaves it as many
+    /// rows as the others, such as `1>100|a*99 bc` cut to `1>100|a*99 b`,
+    /// can't be told from the end of the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let partial = parser.parse_partial("#id #level #n\n1>100|info*50 warn*50|1>1").unwrap();
+    ///
+    /// assert!(!partial.is_complete());
+    /// assert_eq!(partial.rows(), 100);
+    /// assert_eq!(partial.lost_columns, ["n"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header, dictionaries or schema can't be
+    /// read, or if a column read whole can't be expanded.
+    pub fn parse_partial(&self, input: &str) -> Result<PartialDocument> {
+        let (input, _) = split_signature(input);
+        if let Some(frame) = StoreFrame::parse(input)? {
+            let document = Self::parse_store_frame(&frame)?;
+            let rows = document.row_count();
+            return Ok(PartialDocument {
+                document,
+                lost_columns: Vec::new(),
+                expected_rows: Some(rows),
+                error: None,
+            });
+        }
+
+        let (body, trailer) = split_trailer(input);
+        let mut tokenizer = Tokenizer::new(body);
+        let mut doc = self.parse_header(&mut tokenizer)?;
+        let columns = doc.schema.len();
+        let (mut streams, error) = if columns == 0 {
+            (Vec::new(), None)
+        } else {
+            self.read_streams(&mut tokenizer, columns)
+        };
+
+        let whole = error.is_none()
+            && streams.len() == columns
+            && streams.windows(2).all(|pair| pair[0].expanded_count() == pair[1].expanded_count());
+        if whole {
+            doc.streams = streams;
+            let error = trailer.and_then(|(offset, trailer)| Self::parse_trailer(&mut doc, trailer, offset).err());
+            if error.is_some() {
+                // Only checked against the blocks once every line is read
+                doc.blooms.clear();
+                doc.zone_maps.clear();
+            }
+            let rows = doc.row_count();
+            return Ok(PartialDocument {
+                document: doc,
+                lost_columns: Vec::new(),
+                expected_rows: Some(rows),
+                error,
+            });
+        }
+
+        // Separators past the last column are garbage too
+        streams.truncate(columns);
+        let error = error.unwrap_or_else(|| AlsError::AlsSyntaxError {
+            position: body.len(),
+            message: format!("Input ends in column {} of {}", streams.len(), columns),
+        });
+        let damaged = streams.len() - 1;
+        streams[damaged].operators.pop();
+        let expected_rows = streams[..damaged].first().map(ColumnStream::expanded_count);
+        if streams[damaged].is_empty() {
+            streams.pop();
+        }
+
+        let mut lost_columns = doc.schema.split_off(streams.len());
+        let kept_damaged = streams.len() > damaged;
+        doc.streams = streams;
+        let mut salvaged = salvage(&doc);
+        if let Err(e) = self.expand_columns(&salvaged) {
+            if !kept_damaged {
+                return Err(e);
+            }
+            // Garbage that still reads as operators, such as a dictionary
+            // reference past the end of the dictionary
+            doc.streams.pop();
+            lost_columns.insert(0, doc.schema.pop().expect("schema has the damaged column"));
+            salvaged = salvage(&doc);
+            self.expand_columns(&salvaged)?;
+        }
+
+        Ok(PartialDocument {
+            document: salvaged,
+            lost_columns,
+            expected_rows,
+            error: Some(error),
+        })
+    }
+
+    /// Read column streams until the input ends or an operator can't be
+    /// read, returning the streams and the error that stopped them.
+    fn read_streams(&self, tokenizer: &mut Tokenizer, columns: usize) -> (Vec<ColumnStream<'static>>, Option<AlsError>) {
+        let mut streams = Vec::with_capacity(columns);
+        let mut current = ColumnStream::new();
+        let error = loop {
+            let token = match tokenizer.next_token() {
+                Ok(token) => token,
+                Err(e) => break Some(e),
+            };
+            match token {
+                Token::Eof => break None,
+                Token::ColumnSeparator => streams.push(std::mem::take(&mut current)),
+                Token::Newline => {}
+                Token::Version(VersionType::Ctx, _) if current.is_empty() && !current.is_ctx() => {
+                    current.format_indicator = FormatIndicator::Ctx;
+                }
+                token => match self.parse_element(tokenizer, token, 0) {
+                    Ok(operator) if current.is_ctx() && !operator.is_raw() => {
+                        break Some(AlsError::AlsSyntaxError {
+                            position: tokenizer.position(),
+                            message: "Only raw values are allowed in a CTX column".to_string(),
+                        });
+                    }
+                    Ok(operator) => current.push(operator),
+                    Err(e) => break Some(e),
+                },
+            }
+        };
+
+        // As when parsing, a separator ending the last column adds no column
+        if error.is_some() || !current.is_empty() || streams.len() < columns {
+            streams.push(current);
+        }
+        (streams, error)
+    }
+}
+
+/// Cut the columns of a document to the rows they all cover.
+fn salvage(doc: &AlsDocument<'static>) -> AlsDocument<'static> {
+    let rows = doc.streams.iter().map(ColumnStream::expanded_count).min().unwrap_or(0);
+    doc.slice_rows(0..rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlsCompressor;
+
+    #[test]
+    fn test_intact_document() {
+        let parser = AlsParser::new();
+        let partial = parser.parse_partial("!v1\n#id #level\n1>10|info*5 warn*5\n!blocks 5").unwrap();
+
+        assert!(partial.is_complete());
+        assert_eq!(partial.rows(), 10);
+        assert_eq!(partial.expected_rows, Some(10));
+        assert!(partial.lost_columns.is_empty());
+        assert_eq!(partial.document.block_size, Some(5));
+    }
+
+    #[test]
+    fn test_truncated_document() {
+        let parser = AlsParser::new();
+
+        // Cut inside the last column
+        let partial = parser.parse_partial("#id #level #n\n1>100|info*50 warn*50|1>1").unwrap();
+        assert_eq!(partial.document.schema, ["id", "level"]);
+        assert_eq!(partial.rows(), 100);
+        assert_eq!(partial.expected_rows, Some(100));
+        assert_eq!(partial.lost_columns, ["n"]);
+
+        // The operator the cut falls in is dropped
+        let partial = parser.parse_partial("#id #level #n\n1>100|info*50 warn*5").unwrap();
+        assert_eq!(partial.rows(), 50);
+        assert_eq!(partial.lost_columns, ["n"]);
+        let rows = parser.expand(&partial.document).unwrap();
+        assert_eq!(rows[49], ["50", "info"]);
+
+        // Cut inside the first column
+        let partial = parser.parse_partial("#id #level\n1>10 11>2").unwrap();
+        assert_eq!(partial.document.schema, ["id"]);
+        assert_eq!(partial.rows(), 10);
+        assert_eq!(partial.expected_rows, None);
+
+        // Cut before the first column
+        let partial = parser.parse_partial("#id #level\n").unwrap();
+        assert_eq!(partial.rows(), 0);
+        assert_eq!(partial.lost_columns, ["id", "level"]);
+        assert!(partial.error.unwrap().to_string().contains("Input ends in column 1 of 2"));
+    }
+
+    #[test]
+    fn test_damaged_tail() {
+        let parser = AlsParser::new();
+
+        // Garbage in the trailer leaves the columns whole
+        let partial = parser.parse_partial("#id #level\n1>10|info*10\n!zone 0 x").unwrap();
+        assert!(!partial.is_complete());
+        assert_eq!(partial.rows(), 10);
+        assert!(partial.lost_columns.is_empty());
+
+        // Garbage reading as a dictionary reference loses its column
+        let partial = parser.parse_partial("$default:a|b\n#id #v\n1>5|_0*3 _7 _1*2").unwrap();
+        assert_eq!(partial.document.schema, ["id"]);
+        assert_eq!(partial.rows(), 5);
+        assert_eq!(partial.lost_columns, ["v"]);
+
+        // A damaged header leaves nothing to recover
+        assert!(parser.parse_partial("!v9\n#id\n1>10").is_err());
+    }
+
+    #[test]
+    fn test_every_truncation_recovers_leading_rows() {
+        let csv: String = std::iter::once("id,level,host,latency\n".to_string())
+            .chain((0..200).map(|i| {
+                let level = ["info", "warn", "error"][i % 7 % 3];
+                format!("{},{},web-{:02},{}.{}\n", 1000 + i, level, i / 40, i * 37 % 500, i % 10)
+            }))
+            .collect();
+        let als = AlsCompressor::new().compress_csv(&csv).unwrap();
+        let parser = AlsParser::new();
+        let original = parser.expand(&parser.parse(&als).unwrap()).unwrap();
+        let header_len = als.find("\n#").unwrap();
+
+        for cut in (header_len + 1..als.len()).filter(|&cut| als.is_char_boundary(cut)) {
+            let partial = parser.parse_partial(&als[..cut]).unwrap();
+            if partial.is_complete() {
+                // Cut in the last value, leaving the columns the same length
+                continue;
+            }
+            let columns = partial.document.column_count();
+            let rows = parser.expand(&partial.document).unwrap();
+            assert_eq!(rows.len(), partial.rows());
+            for (row, expected) in rows.iter().zip(&original) {
+                assert_eq!(row[..], expected[..columns], "cut at {}", cut);
+            }
+        }
+    }
+}
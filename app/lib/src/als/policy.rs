@@ -0,0 +1,120 @@
+//! Per-column policies.
+//!
+//! Redactions, quantizations, time truncations and foreign keys change the
+//! values of the columns they name as the input is compressed. All but
+//! foreign keys are recorded as `<prefix><policy> <column>` trailer lines,
+//! such as `!redact hash email`, which this module writes and parses for
+//! them; each policy module only knows how to name its policies and how to
+//! change one column.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::escape::{escape_als_string, unescape_als_string};
+use super::trailer::TrailerLine;
+use crate::convert::{Column, TabularData};
+use crate::error::Result;
+
+/// Append a `<prefix><policy> <column>` line to `output` for each column
+/// of `policies`, each preceded by a newline, writing policies as `name`
+/// gives them.
+pub(crate) fn write_policy_lines<P>(
+    output: &mut String,
+    prefix: &str,
+    policies: &BTreeMap<String, P>,
+    name: impl Fn(&P) -> String,
+) {
+    for (column, policy) in policies {
+        output.push('\n');
+        output.push_str(prefix);
+        output.push_str(&name(policy));
+        output.push(' ');
+        output.push_str(&escape_als_string(column));
+    }
+}
+
+/// Parse a `<prefix><policy> <column>` line into the column name and its
+/// policy, which `parse` reads from its name.
+///
+/// `kind` names the policy in errors, such as "redaction", and `position`
+/// is the offset of the line in the input.
+pub(crate) fn parse_policy_line<P>(
+    line: &str,
+    prefix: &str,
+    kind: &str,
+    position: usize,
+    parse: impl FnOnce(&str) -> Option<P>,
+) -> Result<(String, P)> {
+    let line = TrailerLine::new(line, prefix, kind, position)?;
+    let fields = line.fields();
+    let [name, column] = fields.as_slice() else {
+        return Err(line.error(format!("Expected a {} and a column", kind)));
+    };
+    let policy = parse(name).ok_or_else(|| line.error(format!("Unknown {}: {}", kind, name)))?;
+    Ok((unescape_als_string(column)?, policy))
+}
+
+/// Rebuild `data` with each column named in `policy` replaced by what
+/// `apply` makes of it, or left out where `apply` gives `None`.
+///
+/// Returns `None` if no column of `data` is named in the policy, so the
+/// input is used as is.
+pub(crate) fn apply_policy<'a, P>(
+    data: &TabularData<'a>,
+    policy: &HashMap<String, P>,
+    mut apply: impl FnMut(&Column<'a>, &P) -> Result<Option<Column<'a>>>,
+) -> Result<Option<TabularData<'a>>> {
+    if !data.columns.iter().any(|column| policy.contains_key(column.name.as_ref())) {
+        return Ok(None);
+    }
+
+    let mut applied = TabularData::with_capacity(data.column_count());
+    for column in &data.columns {
+        match policy.get(column.name.as_ref()) {
+            None => applied.add_column(column.clone()),
+            Some(rule) => {
+                if let Some(column) = apply(column, rule)? {
+                    applied.add_column(column);
+                }
+            }
+        }
+    }
+    // Keep the row count when every column was left out
+    applied.row_count = data.row_count;
+    Ok(Some(applied))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::csv::parse_csv;
+    use crate::error::AlsError;
+
+    #[test]
+    fn test_policy_lines() {
+        let policies = BTreeMap::from([("unit price".to_string(), 2u8), ("qty".to_string(), 0)]);
+        let mut output = String::new();
+        write_policy_lines(&mut output, "!round ", &policies, |digits| digits.to_string());
+        assert_eq!(output, "\n!round 0 qty\n!round 2 unit\\ price");
+
+        let parse = |line: &str| parse_policy_line(line, "!round ", "rounding", 0, |name| name.parse::<u8>().ok());
+        let parsed: BTreeMap<String, u8> = output.lines().skip(1).map(|line| parse(line).unwrap()).collect();
+        assert_eq!(parsed, policies);
+        assert!(matches!(parse("!round x qty"), Err(AlsError::AlsSyntaxError { .. })));
+        assert!(matches!(parse("!round 2"), Err(AlsError::AlsSyntaxError { .. })));
+    }
+
+    #[test]
+    fn test_apply_policy() {
+        let data = parse_csv("a,b\n1,x\n2,y").unwrap();
+        let keep_b = HashMap::from([("b".to_string(), ())]);
+        let applied = apply_policy(&data, &keep_b, |column, _| Ok(Some(column.clone()))).unwrap().unwrap();
+        assert_eq!(applied.column_count(), 2);
+
+        let drop_all = HashMap::from([("a".to_string(), ()), ("b".to_string(), ())]);
+        let applied = apply_policy(&data, &drop_all, |_, _| Ok(None)).unwrap().unwrap();
+        assert_eq!((applied.column_count(), applied.row_count), (0, 2));
+
+        let other = HashMap::from([("c".to_string(), ())]);
+        assert!(apply_policy(&data, &other, |_, _| Ok(None)).unwrap().is_none());
+    }
+}
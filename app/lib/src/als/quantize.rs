@@ -0,0 +1,184 @@
+//! Lossy float quantization.
+//!
+//! Sensor readings often carry fifteen digits of which only a few mean
+//! anything, and the noisy tail defeats every detector. Columns can be
+//! rounded as they are compressed, to a number of significant digits or of
+//! decimal places, trading precision nobody needs for runs and repeats.
+//! The rounding is recorded in the document, one line per column, so
+//! readers know the values are not the originals:
+//!
+//! ```text
+//! !quantize sig:3 temperature
+//! !quantize dp:2 price
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::policy::{apply_policy, parse_policy_line, write_policy_lines};
+use crate::convert::{Column, TabularData, Value};
+use crate::error::Result;
+
+/// Prefix of a quantization line.
+pub const QUANTIZE_PREFIX: &str = "!quantize ";
+
+/// Most digits a quantization keeps; an `f64` holds no more than 17.
+pub const MAX_QUANTIZATION_DIGITS: u8 = 17;
+
+/// How the floats of a column are rounded before compression.
+///
+/// Integers, strings and non-finite floats are left as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Quantization {
+    /// Round to this many significant digits, at least one.
+    SignificantDigits(u8),
+    /// Round to this many digits after the decimal point.
+    DecimalPlaces(u8),
+}
+
+impl Quantization {
+    /// Parse a quantization written as `sig:N` or `dp:N`, as in documents.
+    ///
+    /// Returns `None` for other text, and for digit counts above
+    /// [`MAX_QUANTIZATION_DIGITS`] or zero significant digits.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (kind, digits) = spec.split_once(':')?;
+        let digits: u8 = digits.parse().ok().filter(|&d| d <= MAX_QUANTIZATION_DIGITS)?;
+        match kind {
+            "sig" if digits > 0 => Some(Quantization::SignificantDigits(digits)),
+            "dp" => Some(Quantization::DecimalPlaces(digits)),
+            _ => None,
+        }
+    }
+
+    /// Round one value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{Quantization, Value};
+    ///
+    /// let reading = Value::Float(21.874_361_209_553_4);
+    /// assert_eq!(Quantization::SignificantDigits(3).quantize_value(&reading), Value::Float(21.9));
+    /// assert_eq!(Quantization::DecimalPlaces(1).quantize_value(&reading), Value::Float(21.9));
+    /// assert_eq!(Quantization::DecimalPlaces(1).quantize_value(&Value::Integer(7)), Value::Integer(7));
+    /// ```
+    pub fn quantize_value<'a>(&self, value: &Value<'a>) -> Value<'a> {
+        match value {
+            Value::Float(x) if x.is_finite() => Value::Float(self.quantize(*x)),
+            other => other.clone(),
+        }
+    }
+
+    /// Round a float, going through its decimal text so the result is the
+    /// float closest to the rounded decimal rather than a near miss.
+    fn quantize(&self, x: f64) -> f64 {
+        let digits = usize::from(self.digits().min(MAX_QUANTIZATION_DIGITS));
+        let text = match self {
+            Quantization::SignificantDigits(_) => format!("{:.*e}", digits.max(1) - 1, x),
+            Quantization::DecimalPlaces(_) => format!("{:.*}", digits, x),
+        };
+        // Adding zero turns a negative zero from rounding into zero
+        text.parse::<f64>().map_or(x, |rounded| rounded + 0.0)
+    }
+
+    /// Get the number of digits kept.
+    pub fn digits(&self) -> u8 {
+        match self {
+            Quantization::SignificantDigits(digits) | Quantization::DecimalPlaces(digits) => *digits,
+        }
+    }
+
+    /// Append a `!quantize <quantization> <column>` line to `output` for
+    /// each rounded column, each preceded by a newline, with the
+    /// quantization written as `sig:N` or `dp:N`.
+    pub(crate) fn write_lines(output: &mut String, quantizations: &BTreeMap<String, Quantization>) {
+        write_policy_lines(output, QUANTIZE_PREFIX, quantizations, Quantization::to_string);
+    }
+
+    /// Read a `!quantize` line at offset `position` back into the column
+    /// and the rounding its floats went through, refusing digit counts
+    /// [`parse`](Self::parse) refuses.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(String, Quantization)> {
+        parse_policy_line(line, QUANTIZE_PREFIX, "quantization", position, Quantization::parse)
+    }
+}
+
+impl fmt::Display for Quantization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quantization::SignificantDigits(digits) => write!(f, "sig:{}", digits),
+            Quantization::DecimalPlaces(digits) => write!(f, "dp:{}", digits),
+        }
+    }
+}
+
+/// Round the floats of the columns of `data` named in `policy`, keeping
+/// the type inferred for each column.
+///
+/// Returns `None` if `policy` names no column of `data`.
+pub(crate) fn quantize<'a>(
+    data: &TabularData<'a>,
+    policy: &HashMap<String, Quantization>,
+) -> Result<Option<TabularData<'a>>> {
+    apply_policy(data, policy, |column, quantization| {
+        let values = column.values.iter().map(|value| quantization.quantize_value(value)).collect();
+        Ok(Some(Column::with_type(column.name.clone(), values, column.inferred_type)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantize_value() {
+        let sig = |digits, x| Quantization::SignificantDigits(digits).quantize_value(&Value::Float(x));
+        let dp = |digits, x| Quantization::DecimalPlaces(digits).quantize_value(&Value::Float(x));
+        assert_eq!(sig(2, 0.012_345), Value::Float(0.012));
+        assert_eq!(sig(1, 987.6), Value::Float(1000.0));
+        assert_eq!(sig(3, -1.234_5e20), Value::Float(-1.23e20));
+        assert_eq!(dp(2, 1.005_000_1), Value::Float(1.01));
+        assert_eq!(dp(0, 2.5), Value::Float(2.0));
+        assert_eq!(dp(1, -0.01), Value::Float(0.0));
+        assert!(dp(1, -0.01).as_float().unwrap().is_sign_positive());
+        assert!(dp(2, f64::NAN).as_float().unwrap().is_nan());
+        assert_eq!(dp(2, f64::INFINITY), Value::Float(f64::INFINITY));
+        assert_eq!(sig(2, 1.0).as_float(), Some(1.0));
+        assert_eq!(Quantization::DecimalPlaces(1).quantize_value(&Value::Null), Value::Null);
+        assert_eq!(Quantization::DecimalPlaces(1).quantize_value(&Value::string("1.234")), Value::string("1.234"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Quantization::parse("sig:3"), Some(Quantization::SignificantDigits(3)));
+        assert_eq!(Quantization::parse("dp:0"), Some(Quantization::DecimalPlaces(0)));
+        for spec in ["sig:0", "dp:18", "dp", "dp:-1", "round:2", "sig:x"] {
+            assert_eq!(Quantization::parse(spec), None, "{}", spec);
+        }
+        for quantization in [Quantization::SignificantDigits(4), Quantization::DecimalPlaces(2)] {
+            assert_eq!(Quantization::parse(&quantization.to_string()), Some(quantization));
+        }
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut quantizations = BTreeMap::new();
+        quantizations.insert("room temp".to_string(), Quantization::SignificantDigits(3));
+        quantizations.insert("price".to_string(), Quantization::DecimalPlaces(2));
+        let mut output = String::new();
+        Quantization::write_lines(&mut output, &quantizations);
+        assert_eq!(output, "\n!quantize dp:2 price\n!quantize sig:3 room\\ temp");
+
+        let parsed: Vec<(String, Quantization)> = output
+            .lines()
+            .skip(1)
+            .map(|line| Quantization::parse_line(line, 0).unwrap())
+            .collect();
+        assert_eq!(parsed, quantizations.into_iter().collect::<Vec<_>>());
+        assert!(Quantization::parse_line("!quantize sig:3", 0).is_err());
+        assert!(Quantization::parse_line("!quantize sig:0 temp", 0).is_err());
+    }
+}
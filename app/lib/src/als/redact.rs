@@ -0,0 +1,361 @@
+//! Column redaction.
+//!
+//! Columns holding personal data can be neutralized as they are compressed,
+//! instead of in a separate pre-processing script: dropped entirely,
+//! replaced by a salted hash that still groups and joins, or masked down to
+//! their last four characters. The policy is recorded in the document, one
+//! line per column, so readers know which values are not the originals:
+//!
+//! ```text
+//! !redact hash email
+//! !redact drop ssn
+//! ```
+//!
+//! The salt is never written to the document.
+//!
+//! Pseudonymized columns get the same tokens as hashed ones, but the real
+//! value behind each token is also collected into a [`PseudonymKey`], kept
+//! apart from the document. Analysts work on the tokens, and whoever holds
+//! the key can turn them back into the original values.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::document::AlsDocument;
+use super::policy::{apply_policy, parse_policy_line, write_policy_lines};
+use crate::convert::{Column, TabularData, Value};
+use crate::error::Result;
+
+/// Prefix of a redaction line.
+pub const REDACT_PREFIX: &str = "!redact ";
+
+/// Characters left readable by [`Redaction::Mask`].
+const MASK_KEEP: usize = 4;
+
+/// Bytes of the SHA-256 digest kept by [`Redaction::Hash`].
+const HASH_BYTES: usize = 16;
+
+/// How a column is redacted before compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Redaction {
+    /// Leave the column out of the document.
+    Drop,
+    /// Replace each value by a salted SHA-256 hash, so equal values still
+    /// map to equal tokens.
+    Hash,
+    /// Replace every character but the last four by `*`. Values of four
+    /// characters or fewer are masked entirely.
+    Mask,
+    /// Replace each value by the token `Hash` would give, and let the real
+    /// values be recovered with a [`PseudonymKey`].
+    Pseudonymize,
+}
+
+impl Redaction {
+    /// All redactions.
+    pub const ALL: [Redaction; 4] = [Redaction::Drop, Redaction::Hash, Redaction::Mask, Redaction::Pseudonymize];
+
+    /// Get the name used for this redaction in configuration and documents.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Redaction::Drop => "drop",
+            Redaction::Hash => "hash",
+            Redaction::Mask => "mask",
+            Redaction::Pseudonymize => "pseudonymize",
+        }
+    }
+
+    /// Look up a redaction by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|redaction| redaction.name() == name)
+    }
+
+    /// Redact one value. Nulls and empty strings are kept, and `Drop`
+    /// leaves values as they are, since it applies to whole columns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{Redaction, Value};
+    ///
+    /// let masked = Redaction::Mask.redact_value(&Value::string("4111111111111111"), "");
+    /// assert_eq!(masked, Value::string("************1111"));
+    ///
+    /// let a = Redaction::Hash.redact_value(&Value::string("ada@example.com"), "pepper");
+    /// let b = Redaction::Hash.redact_value(&Value::string("ada@example.com"), "pepper");
+    /// assert_eq!(a, b);
+    /// assert_ne!(a, Redaction::Hash.redact_value(&Value::string("ada@example.com"), "salt"));
+    /// ```
+    pub fn redact_value<'a>(&self, value: &Value<'a>, salt: &str) -> Value<'a> {
+        if value.is_null() || value.as_str() == Some("") {
+            return value.clone();
+        }
+        match self {
+            Redaction::Drop => value.clone(),
+            Redaction::Hash | Redaction::Pseudonymize => {
+                // Prefix the salt length so salt and value cannot trade bytes
+                let mut hasher = Sha256::new();
+                hasher.update((salt.len() as u64).to_le_bytes());
+                hasher.update(salt.as_bytes());
+                hasher.update(value.to_string_repr().as_bytes());
+                let mut token = String::with_capacity(HASH_BYTES * 2);
+                for byte in &hasher.finalize()[..HASH_BYTES] {
+                    write!(token, "{:02x}", byte).unwrap();
+                }
+                Value::string_owned(token)
+            }
+            Redaction::Mask => {
+                let text = value.to_string_repr();
+                let count = text.chars().count();
+                // Short values are masked entirely rather than left whole
+                let keep_from = if count > MASK_KEEP { count - MASK_KEEP } else { count };
+                let masked: String = text
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| if i < keep_from { '*' } else { c })
+                    .collect();
+                Value::string_owned(masked)
+            }
+        }
+    }
+
+    /// Append a `!redact <redaction> <column>` line to `output` for each
+    /// redacted column, each preceded by a newline.
+    pub(crate) fn write_lines(output: &mut String, redactions: &BTreeMap<String, Redaction>) {
+        write_policy_lines(output, REDACT_PREFIX, redactions, |redaction| redaction.name().to_string());
+    }
+
+    /// Read a `!redact` line at offset `position` back into the column and
+    /// the redaction it was compressed with.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(String, Redaction)> {
+        parse_policy_line(line, REDACT_PREFIX, "redaction", position, Redaction::from_name)
+    }
+}
+
+/// Tokens of pseudonymized columns mapped back to their real values.
+///
+/// Built by [`AlsCompressor::pseudonym_key`] from the same input and
+/// configuration as the document, and stored as JSON next to it. Tokens
+/// depend only on the value and the salt, so keys built from several
+/// inputs with one salt can be merged into one.
+///
+/// [`AlsCompressor::pseudonym_key`]: crate::AlsCompressor::pseudonym_key
+///
+/// # Example
+///
+/// ```
+/// use als_compression::{AlsCompressor, AlsParser, CompressorConfig, PseudonymKey, Redaction};
+///
+/// let config = CompressorConfig::new()
+///     .with_column_redaction("dept", Redaction::Pseudonymize)
+///     .with_redaction_salt("pepper");
+/// let compressor = AlsCompressor::with_config(config);
+/// let csv = "id,dept\n1,sales\n2,legal\n3,sales";
+///
+/// let als = compressor.compress_csv(csv).unwrap();
+/// assert!(!als.contains("sales"));
+///
+/// let data = als_compression::convert::csv::parse_csv(csv).unwrap();
+/// let key = PseudonymKey::from_json(&compressor.pseudonym_key(&data).unwrap().to_json()).unwrap();
+///
+/// let parser = AlsParser::new();
+/// let revealed = parser.reveal(&parser.parse(&als).unwrap(), &key).unwrap();
+/// assert_eq!(als_compression::convert::csv::to_csv(&revealed).unwrap().trim_end(), csv);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PseudonymKey {
+    /// Real value of each token, by column name.
+    columns: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl PseudonymKey {
+    /// Create an empty key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of tokens in the key, over all columns.
+    pub fn len(&self) -> usize {
+        self.columns.values().map(BTreeMap::len).sum()
+    }
+
+    /// Check whether the key holds no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Look up the real value behind `token` in `column`.
+    pub fn lookup(&self, column: &str, token: &str) -> Option<&str> {
+        self.columns.get(column)?.get(token).map(String::as_str)
+    }
+
+    /// Add the tokens of `other` to this key.
+    pub fn merge(&mut self, other: PseudonymKey) {
+        for (column, tokens) in other.columns {
+            self.columns.entry(column).or_default().extend(tokens);
+        }
+    }
+
+    /// Write the key as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a key serializes to JSON")
+    }
+
+    /// Read a key written by [`to_json`](Self::to_json).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::JsonParseError` if `input` is not a key.
+    pub fn from_json(input: &str) -> Result<Self> {
+        Ok(serde_json::from_str(input)?)
+    }
+
+    /// Record the token of every value in the columns `policy`
+    /// pseudonymizes.
+    pub(crate) fn collect(&mut self, data: &TabularData, policy: &HashMap<String, Redaction>, salt: &str) {
+        for column in &data.columns {
+            if policy.get(column.name.as_ref()) != Some(&Redaction::Pseudonymize) {
+                continue;
+            }
+            let tokens = self.columns.entry(column.name.to_string()).or_default();
+            for value in &column.values {
+                let token = Redaction::Pseudonymize.redact_value(value, salt);
+                // Nulls and empty strings are kept as they are
+                if let Some(token) = token.as_str().filter(|token| !token.is_empty()) {
+                    tokens
+                        .entry(token.to_string())
+                        .or_insert_with(|| value.to_string_repr().into_owned());
+                }
+            }
+        }
+    }
+
+    /// Replace the tokens of the columns `doc` records as pseudonymized by
+    /// their real values. Tokens missing from the key are left in place.
+    pub(crate) fn reveal_columns(&self, doc: &AlsDocument, columns: &mut [Vec<String>]) {
+        for (name, values) in doc.schema.iter().zip(columns.iter_mut()) {
+            if doc.redactions.get(name.as_str()) != Some(&Redaction::Pseudonymize) {
+                continue;
+            }
+            let Some(tokens) = self.columns.get(name.as_str()) else {
+                continue;
+            };
+            for value in values.iter_mut() {
+                if let Some(real) = tokens.get(value.as_str()) {
+                    value.clone_from(real);
+                }
+            }
+        }
+    }
+}
+
+/// Redact the columns of `data` named in `policy` with `salt`, leaving
+/// out the dropped ones.
+///
+/// Returns `None` if `policy` names no column of `data`.
+pub(crate) fn redact<'a>(
+    data: &TabularData<'a>,
+    policy: &HashMap<String, Redaction>,
+    salt: &str,
+) -> Result<Option<TabularData<'a>>> {
+    apply_policy(data, policy, |column, redaction| {
+        Ok(match redaction {
+            Redaction::Drop => None,
+            redaction => {
+                let values = column.values.iter().map(|value| redaction.redact_value(value, salt)).collect();
+                Some(Column::new(column.name.clone(), values))
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask() {
+        let mask = |s: &'static str| Redaction::Mask.redact_value(&Value::string(s), "");
+        assert_eq!(mask("555-0199"), Value::string("****0199"));
+        assert_eq!(mask("Zoë12345"), Value::string("****2345"));
+        assert_eq!(mask("1234"), Value::string("****"));
+        assert_eq!(mask(""), Value::string(""));
+        assert_eq!(Redaction::Mask.redact_value(&Value::Integer(123456), ""), Value::string("**3456"));
+        assert_eq!(Redaction::Mask.redact_value(&Value::Null, ""), Value::Null);
+    }
+
+    #[test]
+    fn test_hash() {
+        let hash = |s: &'static str, salt: &str| Redaction::Hash.redact_value(&Value::string(s), salt);
+        let token = hash("ada", "salt");
+        assert_eq!(token.as_str().unwrap().len(), HASH_BYTES * 2);
+        assert_eq!(token, hash("ada", "salt"));
+        assert_ne!(token, hash("ada", "pepper"));
+        // The salt length is hashed, so bytes cannot move between the two
+        assert_ne!(hash("bc", "a"), hash("c", "ab"));
+    }
+
+    #[test]
+    fn test_redact_data() {
+        let mut data = TabularData::new();
+        data.add_column(Column::new("id", vec![Value::Integer(1), Value::Integer(2)]));
+        data.add_column(Column::new("ssn", vec![Value::string("123-45-6789"), Value::Null]));
+        data.add_column(Column::new("card", vec![Value::string("4111111111111111"), Value::string("42")]));
+
+        let policy = HashMap::from([
+            ("ssn".to_string(), Redaction::Drop),
+            ("card".to_string(), Redaction::Mask),
+            ("missing".to_string(), Redaction::Hash),
+        ]);
+        let redacted = redact(&data, &policy, "").unwrap().unwrap();
+        assert_eq!(redacted.column_names(), vec!["id", "card"]);
+        assert_eq!(redacted.columns[1].values, vec![Value::string("************1111"), Value::string("**")]);
+
+        assert!(redact(&data, &HashMap::from([("other".to_string(), Redaction::Drop)]), "").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pseudonym_key() {
+        let mut data = TabularData::new();
+        data.add_column(Column::new("id", vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]));
+        data.add_column(Column::new("dept", vec![Value::string("sales"), Value::Null, Value::string("sales")]));
+
+        let policy = HashMap::from([("dept".to_string(), Redaction::Pseudonymize)]);
+        let mut key = PseudonymKey::new();
+        key.collect(&data, &policy, "salt");
+        assert_eq!(key.len(), 1);
+
+        let token = Redaction::Pseudonymize.redact_value(&Value::string("sales"), "salt");
+        assert_eq!(token, Redaction::Hash.redact_value(&Value::string("sales"), "salt"));
+        assert_eq!(key.lookup("dept", token.as_str().unwrap()), Some("sales"));
+        assert_eq!(key.lookup("id", token.as_str().unwrap()), None);
+
+        let mut other = PseudonymKey::new();
+        data.columns[1].values[1] = Value::string("legal");
+        other.collect(&data, &policy, "salt");
+        key.merge(other);
+        assert_eq!(key.len(), 2);
+        assert_eq!(PseudonymKey::from_json(&key.to_json()).unwrap(), key);
+        assert!(PseudonymKey::from_json("[1]").is_err());
+    }
+
+    #[test]
+    fn test_line_round_trip() {
+        let redactions = BTreeMap::from([
+            ("e mail".to_string(), Redaction::Hash),
+            ("ssn".to_string(), Redaction::Drop),
+        ]);
+        let mut output = String::new();
+        Redaction::write_lines(&mut output, &redactions);
+        assert_eq!(output, "\n!redact hash e\\ mail\n!redact drop ssn");
+
+        let parsed: BTreeMap<String, Redaction> =
+            output.lines().skip(1).map(|line| Redaction::parse_line(line, 0).unwrap()).collect();
+        assert_eq!(parsed, redactions);
+        assert!(Redaction::parse_line("!redact blur ssn", 0).is_err());
+        assert!(Redaction::parse_line("!redact drop", 0).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Scaling documents to more rows.
+//!
+//! Load tests want data shaped like production, only more of it.
+//! [`AlsDocument::scale`] grows a document by a whole factor without
+//! expanding it, by growing each operator in place:
+//!
+//! - Ranges, date ranges, toggles, cycles and column references run
+//!   `factor` times longer, so IDs and timestamps keep counting.
+//! - Repeats repeat `factor` times as often, and boolean runs become
+//!   `factor` times as long.
+//! - Runs of raw values and dictionary references are cycled `factor`
+//!   times, keeping their order and value frequencies.
+//!
+//! Every operator covering rows `a..b` ends up covering `a * factor..b *
+//! factor`, so columns stay aligned and block boundaries still fall between
+//! operators.
+
+use super::document::{AlsDocument, ColumnStream};
+use super::operator::AlsOperator;
+use crate::error::{AlsError, Result};
+
+impl<'a> AlsDocument<'a> {
+    /// Build a document with `factor` times as many rows, continuing the
+    /// patterns of each column.
+    ///
+    /// Dictionaries, redactions, quantizations, truncations, foreign keys
+    /// and the block size (scaled by `factor`) carry over; indexes, Bloom
+    /// filters and zone maps describe the old values and are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, AlsSerializer};
+    ///
+    /// let doc = AlsParser::new().parse("$default:GET|POST\n#id #method\n1>3|_0 _0 _1").unwrap();
+    /// let scaled = doc.scale(2).unwrap();
+    ///
+    /// assert_eq!(scaled.row_count(), 6);
+    /// let csv = AlsParser::new().to_csv(&AlsSerializer::new().serialize(&scaled)).unwrap();
+    /// assert_eq!(csv.lines().nth(6), Some("6,POST"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::RangeOverflow` if the scaled row count does not
+    /// fit in a `usize`, and `AlsError::InvalidDelta` for a delta, whose
+    /// rows only make sense against its base.
+    pub fn scale(&self, factor: usize) -> Result<AlsDocument<'a>> {
+        if self.delta.is_some() {
+            return Err(AlsError::InvalidDelta {
+                message: "a delta cannot be scaled".to_string(),
+            });
+        }
+        let rows = self.row_count();
+        if rows.checked_mul(factor).is_none() {
+            return Err(AlsError::RangeOverflow {
+                start: 0,
+                end: rows as i64,
+                step: factor as i64,
+            });
+        }
+
+        let mut scaled = AlsDocument::with_schema(self.schema.clone());
+        scaled.version = self.version;
+        scaled.dictionaries = self.dictionaries.clone();
+        scaled.format_indicator = self.format_indicator;
+        scaled.block_size = self.block_size.map(|size| size * factor);
+        scaled.nfc_normalized = self.nfc_normalized;
+        scaled.redactions = self.redactions.clone();
+        scaled.quantizations = self.quantizations.clone();
+        scaled.truncations = self.truncations.clone();
+        scaled.foreign_keys = self.foreign_keys.clone();
+        scaled.streams = self
+            .streams
+            .iter()
+            .map(|stream| ColumnStream {
+                operators: scale_operators(&stream.operators, factor),
+                format_indicator: stream.format_indicator,
+            })
+            .collect();
+        Ok(scaled)
+    }
+}
+
+/// Scale a sequence of operators by `factor`.
+fn scale_operators<'a>(operators: &[AlsOperator<'a>], factor: usize) -> Vec<AlsOperator<'a>> {
+    if factor == 0 {
+        return Vec::new();
+    }
+
+    let mut scaled = Vec::with_capacity(operators.len());
+    let mut rest = operators;
+    while let Some(op) = rest.first() {
+        let singles = rest.iter().take_while(|op| is_single(op)).count();
+        match singles {
+            0 => {
+                scaled.push(scale_operator(op, factor));
+                rest = &rest[1..];
+            }
+            1 => {
+                scaled.push(repeat(op.clone(), factor));
+                rest = &rest[1..];
+            }
+            _ => {
+                let (run, tail) = rest.split_at(singles);
+                for _ in 0..factor {
+                    scaled.extend_from_slice(run);
+                }
+                rest = tail;
+            }
+        }
+    }
+    scaled
+}
+
+/// Scale one operator that is not a single value by `factor`.
+fn scale_operator<'a>(op: &AlsOperator<'a>, factor: usize) -> AlsOperator<'a> {
+    match op {
+        AlsOperator::Range { start, step, .. } => {
+            // Ranges running past i64 repeat instead
+            let count = (op.expanded_count() as u64).saturating_mul(factor as u64);
+            let Some(last) = count.checked_sub(1) else {
+                return op.clone();
+            };
+            i64::try_from(last)
+                .ok()
+                .and_then(|last| last.checked_mul(*step))
+                .and_then(|offset| start.checked_add(offset))
+                .map_or_else(
+                    || repeat(op.clone(), factor),
+                    |end| AlsOperator::Range { start: *start, end, step: *step },
+                )
+        }
+        AlsOperator::Multiply { value, count } => AlsOperator::Multiply {
+            value: value.clone(),
+            count: count * factor,
+        },
+        AlsOperator::Toggle { values, count } => AlsOperator::Toggle {
+            values: values.clone(),
+            count: count * factor,
+        },
+        AlsOperator::ColumnRef { column, mapping, count } => AlsOperator::ColumnRef {
+            column: *column,
+            mapping: mapping.clone(),
+            count: count * factor,
+        },
+        AlsOperator::DateRange { start, step, count, format } => AlsOperator::DateRange {
+            start: *start,
+            step: *step,
+            count: count * factor,
+            format: format.clone(),
+        },
+        AlsOperator::Cycle { cycle, offset, run, count } => AlsOperator::Cycle {
+            cycle: *cycle,
+            offset: *offset,
+            run: *run,
+            count: count * factor,
+        },
+        AlsOperator::Transform { value, transform } => AlsOperator::Transform {
+            value: Box::new(scale_operator(value, factor)),
+            transform: transform.clone(),
+        },
+        AlsOperator::BoolRuns { labels, runs } => AlsOperator::BoolRuns {
+            labels: labels.clone(),
+            runs: runs.iter().map(|run| run * factor).collect(),
+        },
+        AlsOperator::Split { separator, parts } => AlsOperator::Split {
+            separator: *separator,
+            parts: parts.iter().map(|part| scale_operators(part, factor)).collect(),
+        },
+        AlsOperator::Raw(_) | AlsOperator::DictRef(_) | AlsOperator::FloatDelta { .. } => repeat(op.clone(), factor),
+    }
+}
+
+/// Check if the operator is a single literal value.
+fn is_single(op: &AlsOperator) -> bool {
+    match op {
+        AlsOperator::Raw(_) | AlsOperator::DictRef(_) => true,
+        AlsOperator::Transform { value, .. } => is_single(value),
+        _ => false,
+    }
+}
+
+/// Repeat an operator `factor` times, without a repeat for a factor of 1.
+pub(super) fn repeat(op: AlsOperator<'_>, factor: usize) -> AlsOperator<'_> {
+    if factor == 1 {
+        op
+    } else {
+        AlsOperator::multiply(op, factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::{AlsParser, AlsSerializer};
+
+    fn scaled_columns(als: &str, factor: usize) -> Vec<Vec<String>> {
+        let parser = AlsParser::new();
+        let scaled = parser.parse(als).unwrap().scale(factor).unwrap();
+        parser.expand_columns(&scaled).unwrap()
+    }
+
+    #[test]
+    fn test_scale_continues_patterns() {
+        let columns = scaled_columns("$default:a|b\n#id #flag #tag #n\n1>3|T~F*3|_0 _1 _1|x*3", 2);
+        assert_eq!(columns[0], vec!["1", "2", "3", "4", "5", "6"]);
+        assert_eq!(columns[1], vec!["T", "F", "T", "F", "T", "F"]);
+        assert_eq!(columns[2], vec!["a", "b", "b", "a", "b", "b"]);
+        assert_eq!(columns[3], vec!["x"; 6]);
+    }
+
+    #[test]
+    fn test_scale_keeps_columns_aligned() {
+        let als = "#id #side #sign\n10>30:10 7|buy sell*2 buy|@1(buy:+1 sell:-1)*4";
+        let columns = scaled_columns(als, 3);
+        assert!(columns.iter().all(|column| column.len() == 12));
+        assert_eq!(columns[0][..6], ["10", "20", "30", "40", "50", "60"]);
+        assert_eq!(columns[0][9..], ["7", "7", "7"]);
+        for (side, sign) in columns[1].iter().zip(&columns[2]) {
+            assert_eq!(sign, if side == "buy" { "+1" } else { "-1" });
+        }
+    }
+
+    #[test]
+    fn test_scale_round_trips() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("#ts #ok\n%{%Y-%m-%d}1704067200+86400*2|?Y~N:1.1").unwrap();
+        let scaled = doc.scale(4).unwrap();
+        let reparsed = parser.parse(&AlsSerializer::new().serialize(&scaled)).unwrap();
+        assert_eq!(reparsed, scaled);
+        assert_eq!(parser.expand_columns(&reparsed).unwrap()[0][7], "2024-01-08");
+
+        assert_eq!(doc.scale(1).unwrap(), doc);
+        assert_eq!(doc.scale(0).unwrap().row_count(), 0);
+    }
+
+    #[test]
+    fn test_scale_overflow() {
+        let doc = AlsParser::new().parse("#id\n9223372036854775804>9223372036854775806:2").unwrap();
+        let columns = AlsParser::new().expand_columns(&doc.scale(2).unwrap()).unwrap();
+        assert_eq!(columns[0].len(), 4);
+
+        assert!(matches!(doc.scale(usize::MAX), Err(AlsError::RangeOverflow { .. })));
+    }
+}
@@ -0,0 +1,612 @@
+//! Schema descriptors derived from the values of a document.
+//!
+//! ALS records only column names, so the type of each column is inferred
+//! from its values the same way decompression to JSON types them. A
+//! descriptor can be rendered as a JSON Schema for the rows, or as a SQL
+//! `CREATE TABLE` statement.
+//!
+//! A [`SchemaValidator`] checks input against a declared contract before it
+//! is compressed. Contracts are read from the same JSON Schema, so the
+//! schema of one archive can gate the next.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use super::document::AlsDocument;
+use super::parser::AlsParser;
+use crate::convert::{ColumnType, TabularData, Value};
+use crate::error::{AlsError, Result};
+
+/// Name, type and value statistics of one column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDescriptor {
+    /// Column name.
+    pub name: String,
+    /// Type inferred from the non-null values.
+    pub column_type: ColumnType,
+    /// Whether any value is null.
+    pub nullable: bool,
+    /// Number of null values.
+    pub null_count: usize,
+    /// Number of distinct non-null values.
+    pub cardinality: usize,
+}
+
+/// Description of the columns of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaDescriptor {
+    /// One descriptor per column, in schema order.
+    pub columns: Vec<ColumnDescriptor>,
+    /// Number of rows in the document.
+    pub row_count: usize,
+}
+
+impl AlsDocument<'_> {
+    /// Describe the columns of the document: their names, inferred types,
+    /// nullability and cardinality.
+    ///
+    /// The document is expanded to find the types, so this costs about as
+    /// much as decompressing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails to expand.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, ColumnType};
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let data = parse_csv("id,name\n1,alice\n2,\n3,alice").unwrap();
+    /// let doc = AlsCompressor::new().compress(&data).unwrap();
+    /// let schema = doc.schema_descriptor().unwrap();
+    /// assert_eq!(schema.columns[0].column_type, ColumnType::Integer);
+    /// assert!(!schema.columns[0].nullable);
+    /// assert!(schema.columns[1].nullable);
+    /// assert_eq!(schema.columns[1].cardinality, 1);
+    /// ```
+    pub fn schema_descriptor(&self) -> Result<SchemaDescriptor> {
+        let parser = AlsParser::new();
+        let columns = parser.expand_columns(self)?;
+        let data = AlsParser::columns_to_tabular(self, columns, true);
+
+        let columns = data
+            .columns
+            .iter()
+            .map(|column| {
+                let null_count = column.values.iter().filter(|value| value.is_null()).count();
+                let distinct: HashSet<_> = column
+                    .values
+                    .iter()
+                    .filter(|value| !value.is_null())
+                    .map(Value::to_string_repr)
+                    .collect();
+                ColumnDescriptor {
+                    name: column.name.to_string(),
+                    column_type: column.inferred_type,
+                    nullable: null_count > 0,
+                    null_count,
+                    cardinality: distinct.len(),
+                }
+            })
+            .collect();
+
+        Ok(SchemaDescriptor {
+            columns,
+            row_count: data.row_count,
+        })
+    }
+}
+
+impl SchemaDescriptor {
+    /// Render a JSON Schema (draft 2020-12) for the rows, as decompression
+    /// to JSON writes them: an array of objects keyed by column name.
+    ///
+    /// Nullable columns also accept `null`. Mixed columns accept any value.
+    pub fn to_json_schema(&self) -> String {
+        let properties: serde_json::Map<String, serde_json::Value> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let json_type = match column.column_type {
+                    ColumnType::Integer => Some("integer"),
+                    ColumnType::Float => Some("number"),
+                    ColumnType::Boolean => Some("boolean"),
+                    ColumnType::String => Some("string"),
+                    ColumnType::Mixed => None,
+                };
+                let property = match json_type {
+                    Some(json_type) if column.nullable => serde_json::json!({ "type": [json_type, "null"] }),
+                    Some(json_type) => serde_json::json!({ "type": json_type }),
+                    None => serde_json::json!({}),
+                };
+                (column.name.clone(), property)
+            })
+            .collect();
+        let required: Vec<&str> = self.columns.iter().map(|column| column.name.as_str()).collect();
+
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            },
+        });
+        serde_json::to_string_pretty(&schema).expect("schema is valid JSON")
+    }
+
+    /// Render a SQL `CREATE TABLE` statement for a table named `table`.
+    ///
+    /// Identifiers are double-quoted, and columns without nulls are
+    /// declared `NOT NULL`. Mixed columns are stored as `TEXT`.
+    pub fn to_sql_ddl(&self, table: &str) -> String {
+        let mut ddl = String::new();
+        write!(ddl, "CREATE TABLE {} (", quote_identifier(table)).unwrap();
+        for (position, column) in self.columns.iter().enumerate() {
+            let sql_type = match column.column_type {
+                ColumnType::Integer => "BIGINT",
+                ColumnType::Float => "DOUBLE PRECISION",
+                ColumnType::Boolean => "BOOLEAN",
+                ColumnType::String | ColumnType::Mixed => "TEXT",
+            };
+            let separator = if position == 0 { "" } else { "," };
+            write!(ddl, "{}\n  {} {}", separator, quote_identifier(&column.name), sql_type).unwrap();
+            if !column.nullable {
+                ddl.push_str(" NOT NULL");
+            }
+        }
+        ddl.push_str("\n);\n");
+        ddl
+    }
+}
+
+/// What a contract expects of one column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnContract {
+    /// Column name.
+    pub name: String,
+    /// Expected type, or `None` to accept any value.
+    ///
+    /// `String` accepts any value, since text formats such as CSV give
+    /// strings like `"123"` a number type.
+    pub column_type: Option<ColumnType>,
+    /// Whether the column may hold nulls.
+    pub nullable: bool,
+    /// Whether the column must be present.
+    pub required: bool,
+}
+
+/// A problem found by [`SchemaValidator::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaViolation {
+    /// A required column is absent.
+    MissingColumn {
+        /// Column name
+        column: String,
+    },
+    /// A column the contract does not declare, when it allows no others.
+    UnexpectedColumn {
+        /// Column name
+        column: String,
+    },
+    /// Values that do not have the expected type.
+    WrongType {
+        /// Column name
+        column: String,
+        /// Type the contract expects
+        expected: ColumnType,
+        /// Index of the first offending row
+        row: usize,
+        /// Number of offending values
+        count: usize,
+    },
+    /// Nulls in a column that may not hold them.
+    UnexpectedNull {
+        /// Column name
+        column: String,
+        /// Index of the first null
+        row: usize,
+        /// Number of nulls
+        count: usize,
+    },
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaViolation::MissingColumn { column } => write!(f, "column '{}' is missing", column),
+            SchemaViolation::UnexpectedColumn { column } => write!(f, "column '{}' is not in the contract", column),
+            SchemaViolation::WrongType { column, expected, row, count } => write!(
+                f,
+                "column '{}' has {} values that are not {:?}, first at row {}",
+                column, count, expected, row
+            ),
+            SchemaViolation::UnexpectedNull { column, row, count } => {
+                write!(f, "column '{}' has {} nulls, first at row {}", column, count, row)
+            }
+        }
+    }
+}
+
+/// Checks tabular data against a contract of column names, types and
+/// nullability.
+///
+/// Set one on a compressor with
+/// [`CompressorConfig::with_schema_contract`](crate::CompressorConfig::with_schema_contract)
+/// to reject input that does not match before it is compressed.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::SchemaValidator;
+/// use als_compression::convert::csv::parse_csv;
+///
+/// let contract = r#"{
+///     "type": "array",
+///     "items": {
+///         "type": "object",
+///         "properties": { "id": { "type": "integer" }, "note": { "type": ["string", "null"] } },
+///         "required": ["id"]
+///     }
+/// }"#;
+/// let validator = SchemaValidator::from_json_schema(contract).unwrap();
+///
+/// assert!(validator.validate(&parse_csv("id,note\n1,ok\n2,").unwrap()).is_empty());
+/// let violations = validator.validate(&parse_csv("id\n1\nx").unwrap());
+/// assert_eq!(violations[0].to_string(), "column 'id' has 1 values that are not Integer, first at row 1");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaValidator {
+    columns: Vec<ColumnContract>,
+    allow_additional: bool,
+}
+
+impl SchemaValidator {
+    /// Create a validator expecting exactly `columns`.
+    pub fn new(columns: Vec<ColumnContract>) -> Self {
+        Self {
+            columns,
+            allow_additional: false,
+        }
+    }
+
+    /// Set whether columns the contract does not declare are allowed.
+    pub fn with_additional_columns(mut self, allow: bool) -> Self {
+        self.allow_additional = allow;
+        self
+    }
+
+    /// Create a validator expecting the columns of a document as they are
+    /// now: every column required, with its type and nullability.
+    pub fn from_descriptor(schema: &SchemaDescriptor) -> Self {
+        Self::new(
+            schema
+                .columns
+                .iter()
+                .map(|column| ColumnContract {
+                    name: column.name.clone(),
+                    column_type: Some(column.column_type).filter(|&column_type| column_type != ColumnType::Mixed),
+                    nullable: column.nullable,
+                    required: true,
+                })
+                .collect(),
+        )
+    }
+
+    /// Read a contract from a JSON Schema for the rows, such as the one
+    /// written by [`SchemaDescriptor::to_json_schema`].
+    ///
+    /// The schema may describe an array of row objects or a single row
+    /// object. Each property's `type` may be one of `integer`, `number`,
+    /// `boolean` or `string`, optionally alongside `null`; a property
+    /// without a type accepts any value. Columns listed in `required` must
+    /// be present, and other columns are rejected only when
+    /// `additionalProperties` is `false`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::JsonParseError` if the text is not JSON, or
+    /// `AlsError::InvalidSchema` if it does not describe rows this way.
+    pub fn from_json_schema(text: &str) -> Result<Self> {
+        let schema: serde_json::Value = serde_json::from_str(text)?;
+        let invalid = |message: String| AlsError::InvalidSchema { message };
+
+        let row = match schema.get("type").and_then(|t| t.as_str()) {
+            Some("array") => schema
+                .get("items")
+                .ok_or_else(|| invalid("array schema has no 'items'".to_string()))?,
+            _ => &schema,
+        };
+        let properties = row
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .ok_or_else(|| invalid("row schema has no 'properties' object".to_string()))?;
+        let required: HashSet<&str> = match row.get("required") {
+            None => HashSet::new(),
+            Some(required) => required
+                .as_array()
+                .and_then(|names| names.iter().map(|name| name.as_str()).collect())
+                .ok_or_else(|| invalid("'required' must be an array of column names".to_string()))?,
+        };
+        if let Some(name) = required.iter().find(|name| !properties.contains_key(**name)) {
+            return Err(invalid(format!("required column '{}' has no property", name)));
+        }
+
+        let mut columns = Vec::with_capacity(properties.len());
+        for (name, property) in properties {
+            let types: Vec<&str> = match property.get("type") {
+                None => Vec::new(),
+                Some(serde_json::Value::String(t)) => vec![t.as_str()],
+                Some(serde_json::Value::Array(types)) => types
+                    .iter()
+                    .map(|t| t.as_str())
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid(format!("column '{}' has a non-string type", name)))?,
+                Some(_) => return Err(invalid(format!("column '{}' has an invalid type", name))),
+            };
+
+            let mut column_type = None;
+            for t in types.iter().filter(|&&t| t != "null") {
+                let parsed = match *t {
+                    "integer" => ColumnType::Integer,
+                    "number" => ColumnType::Float,
+                    "boolean" => ColumnType::Boolean,
+                    "string" => ColumnType::String,
+                    other => return Err(invalid(format!("column '{}' has unsupported type '{}'", name, other))),
+                };
+                if column_type.replace(parsed).is_some() {
+                    return Err(invalid(format!("column '{}' has more than one non-null type", name)));
+                }
+            }
+
+            columns.push(ColumnContract {
+                name: name.clone(),
+                column_type,
+                nullable: types.is_empty() || types.contains(&"null"),
+                required: required.contains(name.as_str()),
+            });
+        }
+
+        let allow_additional = row.get("additionalProperties").and_then(|a| a.as_bool()) != Some(false);
+        Ok(Self::new(columns).with_additional_columns(allow_additional))
+    }
+
+    /// Get the columns of the contract.
+    pub fn columns(&self) -> &[ColumnContract] {
+        &self.columns
+    }
+
+    /// Find every way `data` breaks the contract.
+    ///
+    /// Returns an empty vector if the data meets it.
+    pub fn validate(&self, data: &TabularData) -> Vec<SchemaViolation> {
+        let mut violations = Vec::new();
+
+        for contract in &self.columns {
+            let Some(column) = data.get_column_by_name(&contract.name) else {
+                if contract.required {
+                    violations.push(SchemaViolation::MissingColumn {
+                        column: contract.name.clone(),
+                    });
+                }
+                continue;
+            };
+
+            let nulls = column.values.iter().enumerate().filter(|(_, value)| value.is_null());
+            if !contract.nullable {
+                if let Some((count, row)) = count_first(nulls.map(|(row, _)| row)) {
+                    violations.push(SchemaViolation::UnexpectedNull {
+                        column: contract.name.clone(),
+                        row,
+                        count,
+                    });
+                }
+            }
+
+            if let Some(expected) = contract.column_type {
+                let wrong = column
+                    .values
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| !accepts(expected, value))
+                    .map(|(row, _)| row);
+                if let Some((count, row)) = count_first(wrong) {
+                    violations.push(SchemaViolation::WrongType {
+                        column: contract.name.clone(),
+                        expected,
+                        row,
+                        count,
+                    });
+                }
+            }
+        }
+
+        if !self.allow_additional {
+            for column in &data.columns {
+                if !self.columns.iter().any(|contract| contract.name == column.name) {
+                    violations.push(SchemaViolation::UnexpectedColumn {
+                        column: column.name.to_string(),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Check that `data` meets the contract.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::SchemaViolation` with every violation found.
+    pub fn check(&self, data: &TabularData) -> Result<()> {
+        let violations = self.validate(data);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(AlsError::SchemaViolation { violations })
+        }
+    }
+}
+
+/// Check if a column of type `expected` accepts `value`. Nulls are checked
+/// separately.
+fn accepts(expected: ColumnType, value: &Value) -> bool {
+    match (expected, value) {
+        (_, Value::Null) | (ColumnType::String | ColumnType::Mixed, _) => true,
+        (ColumnType::Float, Value::Integer(_)) => true,
+        (expected, value) => expected.can_represent(value),
+    }
+}
+
+/// Count the rows yielded and get the first of them.
+fn count_first(mut rows: impl Iterator<Item = usize>) -> Option<(usize, usize)> {
+    let first = rows.next()?;
+    Some((1 + rows.count(), first))
+}
+
+/// Quote a SQL identifier, doubling any quotes inside it.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress::AlsCompressor;
+    use crate::convert::csv::parse_csv;
+
+    fn descriptor(csv: &str) -> SchemaDescriptor {
+        let doc = AlsCompressor::new().compress(&parse_csv(csv).unwrap()).unwrap();
+        doc.schema_descriptor().unwrap()
+    }
+
+    #[test]
+    fn test_schema_descriptor() {
+        let schema = descriptor("id,score,ok,tag\n1,1.5,true,a\n2,2,false,b\n3,,true,1\n4,3,true,a");
+        assert_eq!(schema.row_count, 4);
+
+        let types: Vec<ColumnType> = schema.columns.iter().map(|column| column.column_type).collect();
+        assert_eq!(types, vec![ColumnType::Integer, ColumnType::Float, ColumnType::Boolean, ColumnType::String]);
+
+        let score = &schema.columns[1];
+        assert!(score.nullable);
+        assert_eq!(score.null_count, 1);
+        assert_eq!(score.cardinality, 3);
+        assert!(!schema.columns[0].nullable);
+        assert_eq!(schema.columns[3].cardinality, 3);
+    }
+
+    #[test]
+    fn test_schema_descriptor_of_empty_document() {
+        let schema = AlsDocument::with_schema(vec!["a", "b"]).schema_descriptor().unwrap();
+        assert_eq!(schema.row_count, 0);
+        assert_eq!(schema.columns.len(), 2);
+        assert!(schema.columns.iter().all(|column| column.column_type == ColumnType::String && !column.nullable));
+    }
+
+    #[test]
+    fn test_to_json_schema() {
+        let schema = descriptor("id,name\n1,x\n2,");
+        let json: serde_json::Value = serde_json::from_str(&schema.to_json_schema()).unwrap();
+        assert_eq!(json["items"]["properties"]["id"]["type"], "integer");
+        assert_eq!(json["items"]["properties"]["name"]["type"], serde_json::json!(["string", "null"]));
+        assert_eq!(json["items"]["required"], serde_json::json!(["id", "name"]));
+    }
+
+    #[test]
+    fn test_validator_round_trips_json_schema() {
+        let schema = descriptor("id,name,score\n1,x,1.5\n2,,2");
+        let validator = SchemaValidator::from_json_schema(&schema.to_json_schema()).unwrap();
+        assert_eq!(validator, SchemaValidator::from_descriptor(&schema));
+
+        assert_eq!(validator.validate(&parse_csv("id,name,score\n7,y,3\n8,z,").unwrap()).len(), 1);
+        assert!(validator.validate(&parse_csv("score,id,name\n1,7,\n2.5,8,y").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_validator_reports_every_violation() {
+        let validator = SchemaValidator::from_descriptor(&descriptor("id,name,ok\n1,x,true\n2,y,false"));
+        let data = parse_csv("id,ok,extra\n1,true,a\nx,,b\n3,maybe,c\ny,false,d").unwrap();
+
+        assert_eq!(
+            validator.validate(&data),
+            vec![
+                SchemaViolation::WrongType {
+                    column: "id".to_string(),
+                    expected: ColumnType::Integer,
+                    row: 1,
+                    count: 2,
+                },
+                SchemaViolation::MissingColumn {
+                    column: "name".to_string(),
+                },
+                SchemaViolation::UnexpectedNull {
+                    column: "ok".to_string(),
+                    row: 1,
+                    count: 1,
+                },
+                SchemaViolation::WrongType {
+                    column: "ok".to_string(),
+                    expected: ColumnType::Boolean,
+                    row: 2,
+                    count: 1,
+                },
+                SchemaViolation::UnexpectedColumn {
+                    column: "extra".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validator_optional_and_additional_columns() {
+        let contract = r#"{"properties": {"id": {"type": "integer"}, "note": {}}, "required": ["id"]}"#;
+        let validator = SchemaValidator::from_json_schema(contract).unwrap();
+        assert!(validator.validate(&parse_csv("id,other\n1,a").unwrap()).is_empty());
+        assert!(validator.validate(&parse_csv("id,note\n1,\n2,true").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_json_schema() {
+        for contract in [
+            r#"{"type": "array"}"#,
+            r#"{"properties": {"a": {"type": "date"}}}"#,
+            r#"{"properties": {"a": {"type": ["integer", "string"]}}}"#,
+            r#"{"properties": {}, "required": ["a"]}"#,
+        ] {
+            assert!(matches!(
+                SchemaValidator::from_json_schema(contract),
+                Err(AlsError::InvalidSchema { .. })
+            ));
+        }
+        assert!(matches!(SchemaValidator::from_json_schema("{"), Err(AlsError::JsonParseError(_))));
+    }
+
+    #[test]
+    fn test_compressor_checks_contract() {
+        let validator = SchemaValidator::from_descriptor(&descriptor("id\n1\n2"));
+        let compressor = AlsCompressor::with_config(crate::CompressorConfig::new().with_schema_contract(validator));
+
+        assert!(compressor.compress_csv("id\n5\n6").is_ok());
+        let err = compressor.compress_csv("id\n5\nsix").unwrap_err();
+        assert!(matches!(err, AlsError::SchemaViolation { ref violations } if violations.len() == 1));
+        assert_eq!(
+            err.to_string(),
+            "Input violates the schema contract: column 'id' has 1 values that are not Integer, first at row 1"
+        );
+    }
+
+    #[test]
+    fn test_to_sql_ddl() {
+        let schema = descriptor("id,\"say \"\"hi\"\"\"\n1,x\n2,");
+        assert_eq!(
+            schema.to_sql_ddl("events"),
+            "CREATE TABLE \"events\" (\n  \"id\" BIGINT NOT NULL,\n  \"say \"\"hi\"\"\" TEXT\n);\n"
+        );
+    }
+}
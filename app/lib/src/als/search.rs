@@ -0,0 +1,308 @@
+//! Searching documents for text.
+//!
+//! [`AlsParser::search`] finds the cells containing a pattern, and
+//! [`AlsParser::search_regex`] those matching a regular expression, without
+//! expanding the document where it can: dictionary entries are checked
+//! once each, a repeat is searched once and its matches replicated, a
+//! toggle, calendar cycle or boolean run only checks its labels, and a
+//! range is skipped outright unless the pattern could occur in a number.
+//! Other operators are expanded one at a time.
+
+use std::ops::Range;
+
+use regex::Regex;
+
+use super::document::AlsDocument;
+use super::escape::{EMPTY_TOKEN, NULL_TOKEN};
+use super::operator::AlsOperator;
+use super::parser::AlsParser;
+use crate::error::{AlsError, Result};
+
+impl AlsParser {
+    /// Find the cells of a document whose value contains `pattern`.
+    ///
+    /// Returns `(row, column)` pairs ordered by row, then column. Nulls
+    /// never match, and empty strings only match an empty pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let doc = parser.parse("$default:GET /index|POST /login\n#id #request\n1>4|_0*3 _1").unwrap();
+    ///
+    /// assert_eq!(parser.search(&doc, "login").unwrap(), vec![(3, 1)]);
+    /// assert_eq!(parser.search(&doc, "3").unwrap(), vec![(2, 0)]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an operator that has to be expanded cannot be,
+    /// such as a dictionary reference past the end of the dictionary.
+    pub fn search(&self, doc: &AlsDocument, pattern: &str) -> Result<Vec<(usize, usize)>> {
+        self.search_matching(doc, pattern, 0..doc.streams.len())
+    }
+
+    /// Find the cells of a document with a match of the regular expression
+    /// `pattern`.
+    ///
+    /// The expression is unanchored, as in `grep`, and is tested once per
+    /// dictionary entry and per label of a repeat, toggle or run, so
+    /// searching logs with a small vocabulary stays fast. Results are
+    /// ordered as in [`search`](Self::search).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let doc = parser.parse("$default:GET 200|GET 503|POST 500\n#id #line\n1>5|_0*2 _1 _0 _2").unwrap();
+    ///
+    /// assert_eq!(parser.search_regex(&doc, r"5\d\d$").unwrap(), vec![(2, 1), (4, 1)]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidPattern` if `pattern` is not a valid
+    /// regular expression, or an error if an operator that has to be
+    /// expanded cannot be.
+    pub fn search_regex(&self, doc: &AlsDocument, pattern: &str) -> Result<Vec<(usize, usize)>> {
+        self.search_matching(doc, &compile_regex(pattern)?, 0..doc.streams.len())
+    }
+
+    /// Find the rows where the column at `position` has a match of `regex`.
+    pub(crate) fn search_column_regex(&self, doc: &AlsDocument, position: usize, regex: &Regex) -> Result<Vec<usize>> {
+        let cells = self.search_matching(doc, regex, position..position + 1)?;
+        Ok(cells.into_iter().map(|(row, _)| row).collect())
+    }
+
+    /// Find the cells of some columns of a document whose value is matched
+    /// by `matcher`.
+    fn search_matching<M: Matcher + ?Sized>(
+        &self,
+        doc: &AlsDocument,
+        matcher: &M,
+        columns: Range<usize>,
+    ) -> Result<Vec<(usize, usize)>> {
+        let dictionary = doc.default_dictionary().map(|d| d.as_slice());
+        let searcher = Searcher {
+            matcher,
+            dictionary,
+            entries: dictionary.map_or_else(Vec::new, |d| d.iter().map(|v| matches(matcher, v)).collect()),
+        };
+
+        let mut expanded: Option<Vec<Vec<String>>> = None;
+        let mut cells = Vec::new();
+        for (column, stream) in doc.streams.iter().enumerate().take(columns.end).skip(columns.start) {
+            if stream.has_column_refs() {
+                // Derived values depend on other columns, so expand them all once
+                let columns = match &mut expanded {
+                    Some(columns) => columns,
+                    None => expanded.insert(self.expand_columns(doc)?),
+                };
+                cells.extend(
+                    columns[column]
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, value)| matches(matcher, value))
+                        .map(|(row, _)| (row, column)),
+                );
+                continue;
+            }
+
+            let mut position = 0;
+            for op in &stream.operators {
+                cells.extend(searcher.rows(op)?.into_iter().map(|row| (position + row, column)));
+                position += op.expanded_count();
+            }
+        }
+        cells.sort_unstable();
+        Ok(cells)
+    }
+}
+
+/// Compile a regular expression, reporting errors as `InvalidPattern`.
+pub(crate) fn compile_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| AlsError::InvalidPattern {
+        pattern: pattern.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// A test of expanded values.
+pub(crate) trait Matcher {
+    /// Check whether a value, with special tokens already resolved, matches.
+    fn is_match(&self, text: &str) -> bool;
+
+    /// Check whether an integer could match, to skip ranges when not.
+    fn may_match_integers(&self) -> bool;
+}
+
+impl Matcher for str {
+    fn is_match(&self, text: &str) -> bool {
+        text.contains(self)
+    }
+
+    fn may_match_integers(&self) -> bool {
+        self.chars().all(|c| c.is_ascii_digit() || c == '-')
+    }
+}
+
+impl Matcher for Regex {
+    fn is_match(&self, text: &str) -> bool {
+        Regex::is_match(self, text)
+    }
+
+    fn may_match_integers(&self) -> bool {
+        true
+    }
+}
+
+/// Check an expanded value, in its ALS string form, against a matcher.
+pub(crate) fn matches<M: Matcher + ?Sized>(matcher: &M, value: &str) -> bool {
+    match value {
+        NULL_TOKEN => false,
+        EMPTY_TOKEN => matcher.is_match(""),
+        value => matcher.is_match(value),
+    }
+}
+
+/// Finds the rows of operators matching a pattern.
+struct Searcher<'s, M: ?Sized> {
+    matcher: &'s M,
+    dictionary: Option<&'s [String]>,
+    /// Whether each dictionary entry matches
+    entries: Vec<bool>,
+}
+
+impl<M: Matcher + ?Sized> Searcher<'_, M> {
+    /// Get the rows of an operator that match, counting from its first row.
+    fn rows(&self, op: &AlsOperator) -> Result<Vec<usize>> {
+        let rows = match op {
+            AlsOperator::Raw(value) => (matches(self.matcher, value)).then_some(0).into_iter().collect(),
+            AlsOperator::DictRef(index) if *index < self.entries.len() => {
+                self.entries[*index].then_some(0).into_iter().collect()
+            }
+            AlsOperator::Range { start, step, .. } => {
+                if !self.matcher.may_match_integers() {
+                    return Ok(Vec::new());
+                }
+                (0..op.expanded_count())
+                    .filter(|&row| self.matcher.is_match(&AlsOperator::range_value_at(*start, *step, row).to_string()))
+                    .collect()
+            }
+            AlsOperator::Multiply { value, count } => {
+                let hits = self.rows(value)?;
+                if hits.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let period = value.expanded_count();
+                (0..*count)
+                    .flat_map(|repeat| hits.iter().map(move |row| repeat * period + row))
+                    .collect()
+            }
+            AlsOperator::Toggle { values, count } if !values.is_empty() => {
+                let hits: Vec<bool> = values.iter().map(|v| matches(self.matcher, v)).collect();
+                labelled_rows(&hits, *count, |row| row % values.len())
+            }
+            AlsOperator::Cycle { cycle, offset, run, count } if *run > 0 => {
+                let hits: Vec<bool> = cycle.values().iter().map(|v| matches(self.matcher, v)).collect();
+                labelled_rows(&hits, *count, |row| cycle.label_position(*offset, *run, row))
+            }
+            AlsOperator::BoolRuns { labels, runs } => {
+                let hits = [matches(self.matcher, &labels[0]), matches(self.matcher, &labels[1])];
+                let mut rows = Vec::new();
+                let mut position = 0;
+                for (i, &run) in runs.iter().enumerate() {
+                    if hits[i % 2] {
+                        rows.extend(position..position + run);
+                    }
+                    position += run;
+                }
+                rows
+            }
+            _ => op
+                .expand(self.dictionary)?
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| matches(self.matcher, value))
+                .map(|(row, _)| row)
+                .collect(),
+        };
+        Ok(rows)
+    }
+}
+
+/// Get the rows among `count` whose label matches, given which labels match
+/// and the label of each row.
+fn labelled_rows(hits: &[bool], count: usize, label: impl Fn(usize) -> usize) -> Vec<usize> {
+    if !hits.contains(&true) {
+        return Vec::new();
+    }
+    (0..count).filter(|&row| hits[label(row)]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check a search against scanning the expanded columns.
+    fn assert_search(als: &str, pattern: &str) {
+        let parser = AlsParser::new();
+        let doc = parser.parse(als).unwrap();
+        let columns = parser.expand_columns(&doc).unwrap();
+        let mut expected = Vec::new();
+        for row in 0..doc.row_count() {
+            for (column, values) in columns.iter().enumerate() {
+                if matches(pattern, &values[row]) {
+                    expected.push((row, column));
+                }
+            }
+        }
+        assert_eq!(parser.search(&doc, pattern).unwrap(), expected, "{:?} in {:?}", pattern, als);
+    }
+
+    #[test]
+    fn test_search_operators() {
+        let als = "$default:alpha|beta\n#id #n #tag #flag #day\n1>12|10>0:-2 (7>8)*3|_0*4 _1*4 x~ya*4|?T~F:5.3.4|&dow+5:3*12";
+        for pattern in ["1", "-1", "a", "ya", "T", "Mon", "", "zzz"] {
+            assert_search(als, pattern);
+        }
+    }
+
+    #[test]
+    fn test_search_expanded_operators() {
+        let als = "#ts #side #sign #path #code\n%{%Y-%m-%d}1704067200+86400*6|buy sell*2 buy*3|@1(buy:+1 sell:-1)*6|</2(a*3 b*3)(x~y*6)|^ID{U3}(1>2)*3";
+        for pattern in ["2024-01-03", "sell", "+1", "b/y", "ID002", "\\0", "1"] {
+            assert_search(als, pattern);
+        }
+        assert_search("#a #b\n\\0 \\e x|y \\e \\0", "");
+    }
+
+    #[test]
+    fn test_search_regex() {
+        let parser = AlsParser::new();
+        let als = "$default:GET 200|POST 503\n#id #line #day\n1>6|_0*3 _1 x~503*2|&dow+0:2*6";
+        let doc = parser.parse(als).unwrap();
+        let columns = parser.expand_columns(&doc).unwrap();
+        for pattern in [r"5\d\d", "^[A-Z]+ 2", "^(Sun|Tue)$", "^3$", "^$"] {
+            let regex = compile_regex(pattern).unwrap();
+            let expected: Vec<(usize, usize)> = (0..doc.row_count())
+                .flat_map(|row| (0..columns.len()).map(move |column| (row, column)))
+                .filter(|&(row, column)| matches(&regex, &columns[column][row]))
+                .collect();
+            assert_eq!(parser.search_regex(&doc, pattern).unwrap(), expected, "{:?}", pattern);
+        }
+        assert!(matches!(parser.search_regex(&doc, "[a-"), Err(AlsError::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_search_skips_ranges() {
+        // Expanding these columns would take far too long
+        let parser = AlsParser::with_config(crate::ParserConfig::new().with_max_range_expansion(usize::MAX));
+        let doc = parser.parse("#id #name\n1>4000000000000000000|x*4000000000000000000").unwrap();
+        assert!(parser.search(&doc, "y").unwrap().is_empty());
+    }
+}
@@ -4,9 +4,23 @@
 //! into ALS format text. It handles version headers, dictionaries, schema,
 //! and column streams with proper escaping.
 
-use super::document::{AlsDocument, ColumnStream, FormatIndicator};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use super::bloom::BloomFilter;
+use super::zone::ZoneMap;
+use super::quantize::Quantization;
+use super::truncate::TimeTruncation;
+use super::redact::Redaction;
+use super::foreign_key::ForeignKey;
+use super::document::{AlsDocument, ColumnStream, FormatIndicator, BLOCKS_PREFIX, NFC_LINE};
 use super::escape::escape_als_string;
+use super::dict_ref;
+use super::feature::Feature;
+use super::front_coding;
 use super::operator::AlsOperator;
+use super::slice::slice_operators;
+use crate::config::SerializerConfig;
 
 /// ALS format serializer.
 ///
@@ -52,27 +66,107 @@ impl AlsSerializer {
     /// ```
     pub fn serialize(&self, doc: &AlsDocument) -> String {
         let mut output = String::new();
+        self.serialize_into(&mut output, doc);
+        output
+    }
+
+    /// Serialize an `AlsDocument`, appending to `output`.
+    ///
+    /// Lets callers serializing many documents reuse one buffer.
+    pub fn serialize_into(&self, output: &mut String, doc: &AlsDocument) {
+        self.write_document(output, doc, &SerializerConfig::default());
+    }
+
+    /// Serialize an `AlsDocument` with the options in `config`.
+    ///
+    /// # Example
+    ///
+    /// Parsers refuse ranges longer than their `max_range_expansion`, so
+    /// documents built in code can limit how many values one operator
+    /// expands to:
+    ///
+    /// ```
+    /// use als_compression::als::{AlsDocument, AlsOperator, AlsSerializer, ColumnStream};
+    /// use als_compression::SerializerConfig;
+    ///
+    /// let mut doc = AlsDocument::with_schema(vec!["id"]);
+    /// doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::range(1, 25_000_000)]));
+    ///
+    /// let config = SerializerConfig::new().with_max_operator_expansion(10_000_000);
+    /// let als = AlsSerializer::new().serialize_with_config(&doc, &config);
+    /// assert_eq!(als, "!v1\n#id\n1>10000000 10000001>20000000 20000001>25000000");
+    /// ```
+    pub fn serialize_with_config(&self, doc: &AlsDocument, config: &SerializerConfig) -> String {
+        let mut output = String::new();
+        self.write_document(&mut output, doc, config);
+        output
+    }
 
+    /// Serialize a document with the options in `config`, appending to
+    /// `output`.
+    fn write_document(&self, output: &mut String, doc: &AlsDocument, config: &SerializerConfig) {
+        self.write_document_with(output, doc, config, &doc.features());
+    }
+
+    /// Serialize a document without any feature flag, with plain dictionary
+    /// headers and absolute decimal references, as version 1 parsers read
+    /// them.
+    pub(super) fn serialize_without_features(&self, doc: &AlsDocument) -> String {
+        let mut output = String::new();
+        self.write_document_with(&mut output, doc, &SerializerConfig::default(), &BTreeSet::new());
+        output
+    }
+
+    /// Serialize a document declaring `features`, respelling dictionaries
+    /// and references only as far as they allow.
+    fn write_document_with(
+        &self,
+        output: &mut String,
+        doc: &AlsDocument,
+        config: &SerializerConfig,
+        features: &BTreeSet<Feature>,
+    ) {
         // Serialize version header
-        self.serialize_version(&mut output, doc);
+        self.serialize_version(output, doc, features);
 
         // Serialize dictionaries
-        self.serialize_dictionaries(&mut output, doc);
+        self.write_dictionaries(output, doc, features.contains(&Feature::FrontCoding));
 
         // Serialize schema
-        self.serialize_schema(&mut output, doc);
+        self.serialize_schema(output, doc);
 
         // Serialize column streams
-        self.serialize_streams(&mut output, doc);
+        self.write_streams(output, doc, config.max_operator_expansion, features);
 
-        output
+        // Serialize delta edit script, normalization, redactions,
+        // quantizations, truncations, file metadata, foreign keys, block
+        // layout, Bloom filters, zone maps and value indexes
+        if let Some(delta) = &doc.delta {
+            output.push('\n');
+            delta.write_line(output);
+        }
+        if doc.nfc_normalized {
+            output.push('\n');
+            output.push_str(NFC_LINE);
+        }
+        Redaction::write_lines(output, &doc.redactions);
+        Quantization::write_lines(output, &doc.quantizations);
+        TimeTruncation::write_lines(output, &doc.truncations);
+        if let Some(metadata) = &doc.file_metadata {
+            metadata.write_line(output);
+        }
+        ForeignKey::write_lines(output, &doc.foreign_keys);
+        self.serialize_blocks(output, doc);
+        self.serialize_indexes(output, doc);
     }
 
-    /// Serialize the version header.
-    fn serialize_version(&self, output: &mut String, doc: &AlsDocument) {
+    /// Serialize the version header, with the feature flags of a document
+    /// using any.
+    fn serialize_version(&self, output: &mut String, doc: &AlsDocument, features: &BTreeSet<Feature>) {
         match doc.format_indicator {
             FormatIndicator::Als => {
-                output.push_str(&format!("!v{}\n", doc.version));
+                write_version(output, doc, features);
+                output.push('\n');
             }
             FormatIndicator::Ctx => {
                 output.push_str("!ctx\n");
@@ -81,7 +175,13 @@ impl AlsSerializer {
     }
 
     /// Serialize dictionary headers.
-    fn serialize_dictionaries(&self, output: &mut String, doc: &AlsDocument) {
+    pub(super) fn serialize_dictionaries(&self, output: &mut String, doc: &AlsDocument) {
+        self.write_dictionaries(output, doc, true);
+    }
+
+    /// Serialize dictionary headers, front-coding them where shorter if
+    /// `front_coding` is set.
+    fn write_dictionaries(&self, output: &mut String, doc: &AlsDocument, front_coding: bool) {
         // Sort dictionary names for deterministic output
         let mut dict_names: Vec<_> = doc.dictionaries.keys().collect();
         dict_names.sort();
@@ -90,15 +190,16 @@ impl AlsSerializer {
             if let Some(values) = doc.dictionaries.get(name) {
                 output.push('$');
                 output.push_str(name);
-                output.push(':');
-
-                for (i, value) in values.iter().enumerate() {
-                    if i > 0 {
-                        output.push('|');
+                // Escape special characters in dictionary values
+                let entries = match front_coding.then(|| front_coded_entries(values)).flatten() {
+                    Some(front_coded) => {
+                        output.push('~');
+                        front_coded
                     }
-                    // Escape special characters in dictionary values
-                    output.push_str(&escape_dict_value(value));
-                }
+                    None => values.iter().map(|v| escape_dict_value(v)).collect(),
+                };
+                output.push(':');
+                output.push_str(&entries.join("|"));
                 output.push('\n');
             }
         }
@@ -120,26 +221,119 @@ impl AlsSerializer {
 
     /// Serialize column streams.
     pub fn serialize_streams(&self, output: &mut String, doc: &AlsDocument) {
+        self.write_streams(output, doc, None, &doc.features());
+    }
+
+    /// Serialize column streams, splitting operators expanding to more than
+    /// `max_expansion` values.
+    ///
+    /// References are only written in base 62 or relative to each other
+    /// when `features` declares `ref62` or `relative-refs`, which cutting
+    /// operators never adds.
+    fn write_streams(
+        &self,
+        output: &mut String,
+        doc: &AlsDocument,
+        max_expansion: Option<usize>,
+        features: &BTreeSet<Feature>,
+    ) {
         for (i, stream) in doc.streams.iter().enumerate() {
             if i > 0 {
                 output.push('|');
             }
-            self.serialize_stream(output, stream);
+            let refs = RefWriter {
+                base62: features.contains(&Feature::Base62Refs),
+                relative: features.contains(&Feature::RelativeRefs),
+                last: None,
+            };
+            self.write_stream(output, stream, max_expansion, refs);
+        }
+    }
+
+    /// Serialize the rows per block, Bloom filters and zone maps.
+    fn serialize_blocks(&self, output: &mut String, doc: &AlsDocument) {
+        if let Some(block_size) = doc.block_size {
+            output.push('\n');
+            output.push_str(BLOCKS_PREFIX);
+            output.push_str(&block_size.to_string());
+        }
+        for (&column, filters) in &doc.blooms {
+            output.push('\n');
+            BloomFilter::write_line(output, column, filters);
+        }
+        for (&column, zones) in &doc.zone_maps {
+            output.push('\n');
+            ZoneMap::write_line(output, column, zones);
+        }
+    }
+
+    /// Serialize value indexes, one line per indexed column.
+    fn serialize_indexes(&self, output: &mut String, doc: &AlsDocument) {
+        for (&column, index) in &doc.indexes {
+            output.push('\n');
+            index.write_line(output, column);
         }
     }
 
     /// Serialize a single column stream.
-    fn serialize_stream(&self, output: &mut String, stream: &ColumnStream) {
-        for (i, op) in stream.operators.iter().enumerate() {
-            if i > 0 {
-                output.push(' ');
+    ///
+    /// Dictionary references are written relative to the previous reference
+    /// in the stream (`_=`, `_+`) when that is shorter than the index, and
+    /// in base-62 (`_aZ3`) when that is shorter than decimal. Operators
+    /// expanding to more than `max_expansion` values are written as
+    /// consecutive operators of at most that many values each, cut the way
+    /// [`ColumnStream::slice`] cuts them.
+    pub(crate) fn serialize_stream(&self, output: &mut String, stream: &ColumnStream, max_expansion: Option<usize>) {
+        let refs = RefWriter {
+            base62: true,
+            relative: true,
+            last: None,
+        };
+        self.write_stream(output, stream, max_expansion, refs);
+    }
+
+    /// Serialize a single column stream, spelling references as `refs`
+    /// allows.
+    fn write_stream(&self, output: &mut String, stream: &ColumnStream, max_expansion: Option<usize>, mut refs: RefWriter) {
+        if stream.is_ctx() {
+            output.push_str("!ctx ");
+        }
+        let mut first = true;
+        for op in &stream.operators {
+            let pieces: Cow<[AlsOperator]> = match max_expansion {
+                Some(max) if op.expanded_count() > max => {
+                    let len = op.expanded_count();
+                    Cow::Owned(
+                        (0..len)
+                            .step_by(max)
+                            .flat_map(|start| slice_operators(std::slice::from_ref(op), start, len.min(start + max)))
+                            .collect(),
+                    )
+                }
+                _ => Cow::Borrowed(std::slice::from_ref(op)),
+            };
+            for piece in pieces.iter() {
+                if !first {
+                    output.push(' ');
+                }
+                first = false;
+                self.write_operator(output, piece, &mut refs);
             }
-            self.serialize_operator(output, op);
         }
     }
 
     /// Serialize a single operator.
     pub fn serialize_operator(&self, output: &mut String, op: &AlsOperator) {
+        let mut refs = RefWriter {
+            base62: true,
+            relative: false,
+            last: None,
+        };
+        self.write_operator(output, op, &mut refs);
+    }
+
+    /// Serialize an operator, tracking the last dictionary reference written.
+    fn write_operator(&self, output: &mut String, op: &AlsOperator, refs: &mut RefWriter) {
         match op {
             AlsOperator::Raw(value) => {
                 output.push_str(&escape_als_string(value));
@@ -156,19 +350,16 @@ impl AlsSerializer {
                 }
             }
             AlsOperator::Multiply { value, count } => {
-                // Check if inner value needs parentheses
-                let needs_parens = matches!(value.as_ref(), 
-                    AlsOperator::Range { .. } | 
-                    AlsOperator::Toggle { .. } |
-                    AlsOperator::Multiply { .. }
-                );
+                // Only single values can take a count directly; other
+                // operators may end in a count of their own
+                let needs_parens = !matches!(value.as_ref(), AlsOperator::Raw(_) | AlsOperator::DictRef(_));
                 
                 if needs_parens {
                     output.push('(');
-                    self.serialize_operator(output, value);
+                    self.write_operator(output, value, refs);
                     output.push(')');
                 } else {
-                    self.serialize_operator(output, value);
+                    self.write_operator(output, value, refs);
                 }
                 output.push('*');
                 output.push_str(&count.to_string());
@@ -184,8 +375,107 @@ impl AlsSerializer {
                 output.push_str(&count.to_string());
             }
             AlsOperator::DictRef(index) => {
+                let relative = refs.relative.then(|| dict_ref::relative(*index, refs.last)).flatten();
                 output.push('_');
-                output.push_str(&index.to_string());
+                let absolute = || if refs.base62 { dict_ref::encode(*index) } else { index.to_string() };
+                output.push_str(&relative.unwrap_or_else(absolute));
+                refs.last = Some(*index);
+            }
+            AlsOperator::ColumnRef { column, mapping, count } => {
+                output.push('@');
+                output.push_str(&column.to_string());
+                if !mapping.is_empty() {
+                    output.push('(');
+                    for (i, (from, to)) in mapping.iter().enumerate() {
+                        if i > 0 {
+                            output.push(' ');
+                        }
+                        output.push_str(&escape_als_string(from));
+                        output.push(':');
+                        output.push_str(&escape_als_string(to));
+                    }
+                    output.push(')');
+                }
+                output.push('*');
+                output.push_str(&count.to_string());
+            }
+            AlsOperator::DateRange { start, step, count, format } => {
+                output.push_str("%{");
+                output.push_str(&escape_date_format(format));
+                output.push('}');
+                output.push_str(&start.to_string());
+                if *step >= 0 {
+                    output.push('+');
+                }
+                output.push_str(&step.to_string());
+                output.push('*');
+                output.push_str(&count.to_string());
+            }
+            AlsOperator::Cycle { cycle, offset, run, count } => {
+                output.push('&');
+                output.push_str(cycle.name());
+                output.push('+');
+                output.push_str(&offset.to_string());
+                if *run != 1 {
+                    output.push(':');
+                    output.push_str(&run.to_string());
+                }
+                output.push('*');
+                output.push_str(&count.to_string());
+            }
+            AlsOperator::Transform { value, transform } => {
+                output.push('^');
+                // A brace in the prefix would start the spec early
+                output.push_str(&escape_als_string(&transform.prefix).replace('{', "\\{"));
+                output.push('{');
+                output.push_str(&transform.spec());
+                output.push('}');
+                output.push_str(&escape_als_string(&transform.suffix));
+                output.push('(');
+                self.write_operator(output, value, refs);
+                output.push(')');
+            }
+            AlsOperator::Split { separator, parts } => {
+                output.push('<');
+                if let Some(separator) = separator {
+                    output.push(*separator);
+                }
+                output.push_str(&parts.len().to_string());
+                for part in parts {
+                    output.push('(');
+                    for (i, op) in part.iter().enumerate() {
+                        if i > 0 {
+                            output.push(' ');
+                        }
+                        self.write_operator(output, op, refs);
+                    }
+                    output.push(')');
+                }
+            }
+            AlsOperator::BoolRuns { labels, runs } => {
+                output.push('?');
+                output.push_str(&escape_als_string(&labels[0]));
+                output.push('~');
+                output.push_str(&escape_als_string(&labels[1]));
+                output.push(':');
+                for (i, run) in runs.iter().enumerate() {
+                    if i > 0 {
+                        output.push('.');
+                    }
+                    output.push_str(&run.to_string());
+                }
+            }
+            AlsOperator::FloatDelta { places, start, deltas } => {
+                output.push_str("%.");
+                output.push_str(&places.to_string());
+                output.push(':');
+                output.push_str(&start.to_string());
+                for delta in deltas {
+                    if *delta >= 0 {
+                        output.push('+');
+                    }
+                    output.push_str(&delta.to_string());
+                }
             }
         }
     }
@@ -283,7 +573,8 @@ impl AlsPrettyPrinter {
     fn format_version(&self, output: &mut String, doc: &AlsDocument) {
         match doc.format_indicator {
             FormatIndicator::Als => {
-                output.push_str(&format!("!v{}  # ALS format version {}\n", doc.version, doc.version));
+                let version = write_version(output, doc, &doc.features());
+                output.push_str(&format!("  # ALS format version {}\n", version));
             }
             FormatIndicator::Ctx => {
                 output.push_str("!ctx  # CTX fallback format\n");
@@ -353,6 +644,9 @@ impl AlsPrettyPrinter {
 
     /// Format a single column stream.
     fn format_stream(&self, output: &mut String, stream: &ColumnStream, dictionary: Option<&[String]>) {
+        if stream.is_ctx() {
+            output.push_str("!ctx ");
+        }
         for (i, op) in stream.operators.iter().enumerate() {
             if i > 0 {
                 output.push(' ');
@@ -394,6 +688,48 @@ impl Default for AlsPrettyPrinter {
         Self::new()
     }
 }
+/// Write `!v<version>` and the feature flags in `features`, returning the
+/// version written.
+fn write_version(output: &mut String, doc: &AlsDocument, features: &BTreeSet<Feature>) -> u8 {
+    let version = doc.header_version_with(features);
+    output.push_str(&format!("!v{}", version));
+    if version >= 2 {
+        for feature in features {
+            output.push_str(" +");
+            output.push_str(feature.name());
+        }
+    }
+    version
+}
+
+/// Dictionary reference state while writing a stream.
+struct RefWriter {
+    /// Whether references may be written in base 62.
+    base62: bool,
+    /// Whether references may be written relative to the previous one.
+    relative: bool,
+    /// Index of the last reference written.
+    last: Option<usize>,
+}
+
+/// Front-code the entries of a dictionary header, escaped, if that beats
+/// the plain header including the `~` flag.
+///
+/// Version 1 parsers do not read front-coded headers, so documents with
+/// one declare the `front-coding` feature.
+pub(crate) fn front_coded_entries(values: &[String]) -> Option<Vec<String>> {
+    let front_coded: Vec<String> = front_coding::encode(values)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (shared, suffix))| match i {
+            0 => escape_dict_value(suffix),
+            _ => format!("{}~{}", shared, escape_dict_value(suffix)),
+        })
+        .collect();
+    let plain_len: usize = values.iter().map(|v| escape_dict_value(v).len()).sum();
+    let front_coded_len: usize = front_coded.iter().map(String::len).sum::<usize>() + 1;
+    (front_coded_len < plain_len).then_some(front_coded)
+}
 
 /// Escape a dictionary value for serialization.
 ///
@@ -415,6 +751,27 @@ fn escape_dict_value(s: &str) -> String {
     result
 }
 
+/// Escape a date range format for serialization.
+///
+/// The format ends at the first unescaped `}`, so closing braces,
+/// backslashes and line breaks in it are escaped.
+fn escape_date_format(s: &str) -> Cow<'_, str> {
+    if !s.contains(['}', '\\', '\n', '\r']) {
+        return Cow::Borrowed(s);
+    }
+    let mut result = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '}' => result.push_str("\\}"),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            _ => result.push(c),
+        }
+    }
+    Cow::Owned(result)
+}
+
 /// Escape a schema column name for serialization.
 ///
 /// Schema names are separated by spaces, so we need to escape spaces
@@ -441,10 +798,25 @@ fn escape_schema_name(s: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::als::AlsDocument;
+    use crate::als::{AlsDocument, AlsParser, CalendarCycle, CaseTransform, ColumnIndex, ValueTransform};
 
     // ==================== AlsSerializer tests ====================
 
+    #[test]
+    fn test_serialize_indexes() {
+        let mut doc = AlsDocument::with_schema(vec!["id", "status"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::range(1, 3)]));
+        doc.add_stream(ColumnStream::from_operators(vec![
+            AlsOperator::raw("on"),
+            AlsOperator::raw("off"),
+            AlsOperator::raw("on"),
+        ]));
+        doc.add_index(1, ColumnIndex::build(&["on", "off", "on"]));
+
+        let serializer = AlsSerializer::new();
+        assert_eq!(serializer.serialize(&doc), "!v2 +index\n#id #status\n1>3|on off on\n!idx 1 off:1 on:0,2");
+    }
+
     #[test]
     fn test_serialize_empty_document() {
         let doc = AlsDocument::new();
@@ -461,6 +833,20 @@ mod tests {
         assert!(result.starts_with("!v1\n"));
     }
 
+    #[test]
+    fn test_serialize_version_features() {
+        let parser = AlsParser::new();
+        let mut doc = parser.parse("#day #n\n%{%Y-%m-%d}1704067200+86400*2|1>2\n!blocks 1").unwrap();
+        assert_eq!(doc.version, 1);
+        let serialized = AlsSerializer::new().serialize(&doc);
+        assert!(serialized.starts_with("!v2 +blocks +date-range\n#day"));
+        assert_eq!(parser.parse(&serialized).unwrap(), doc);
+
+        doc.streams[0] = ColumnStream::from_operators(vec![AlsOperator::raw("2024-01-01"), AlsOperator::raw("2024-01-02")]);
+        doc.block_size = None;
+        assert!(AlsSerializer::new().serialize(&doc).starts_with("!v1\n#day"));
+    }
+
     #[test]
     fn test_serialize_version_ctx() {
         let mut doc = AlsDocument::new();
@@ -470,6 +856,15 @@ mod tests {
         assert!(result.starts_with("!ctx\n"));
     }
 
+    #[test]
+    fn test_serialize_ctx_column() {
+        let mut doc = AlsDocument::with_schema(vec!["id".to_string(), "note".to_string()]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::range(1, 2)]));
+        doc.add_stream(ColumnStream::ctx(["x y", "!z"]));
+        let result = AlsSerializer::new().serialize(&doc);
+        assert!(result.ends_with("1>2|!ctx x\\ y \\!z"), "{result}");
+    }
+
     #[test]
     fn test_serialize_dictionary() {
         let mut doc = AlsDocument::new();
@@ -479,6 +874,29 @@ mod tests {
         assert!(result.contains("$default:apple|banana|cherry\n"));
     }
 
+    #[test]
+    fn test_serialize_front_coded_dictionary() {
+        let paths = vec![
+            "/var/log/nginx/access.log".to_string(),
+            "/var/log/nginx/error.log".to_string(),
+            "/var/log/postgresql/main.log".to_string(),
+        ];
+        let mut doc = AlsDocument::with_schema(vec!["path"]);
+        doc.add_dictionary("default", paths.clone());
+        doc.add_stream(ColumnStream::from_operators(vec![
+            AlsOperator::dict_ref(0),
+            AlsOperator::dict_ref(1),
+            AlsOperator::dict_ref(2),
+        ]));
+
+        let result = AlsSerializer::new().serialize(&doc);
+        assert!(result.starts_with("!v2 +front-coding\n"), "{}", result);
+        assert!(result.contains("$default~:/var/log/nginx/access.log|15~error.log|9~postgresql/main.log\n"));
+
+        let parsed = crate::als::AlsParser::new().parse(&result).unwrap();
+        assert_eq!(parsed.default_dictionary(), Some(&paths));
+    }
+
     #[test]
     fn test_serialize_multiple_dictionaries() {
         let mut doc = AlsDocument::new();
@@ -601,6 +1019,82 @@ mod tests {
         assert!(result.contains("A~B~C*6"));
     }
 
+    #[test]
+    fn test_serialize_split() {
+        let mut doc = AlsDocument::with_schema(vec!["path"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::split(
+            '/',
+            vec![
+                vec![AlsOperator::multiply(AlsOperator::raw("var"), 2)],
+                vec![AlsOperator::raw("log"), AlsOperator::raw("a b")],
+            ],
+        )]));
+        let result = AlsSerializer::new().serialize(&doc);
+        assert!(result.ends_with("</2(var*2)(log a\\ b)"));
+
+        let parser = crate::als::AlsParser::new();
+        let rows = parser.expand(&parser.parse(&result).unwrap()).unwrap();
+        assert_eq!(rows[1][0], "var/a b");
+
+        let mut doc = AlsDocument::with_schema(vec!["zone"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::concat(vec![
+            vec![AlsOperator::multiply(AlsOperator::raw("us-east-1"), 2)],
+            vec![AlsOperator::raw("a"), AlsOperator::raw("b")],
+        ])]));
+        let result = AlsSerializer::new().serialize(&doc);
+        assert!(result.ends_with("<2(us-east-1*2)(a b)"));
+        assert_eq!(parser.parse(&result).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_serialize_relative_dict_refs() {
+        let mut doc = AlsDocument::with_schema(vec!["col"]);
+        doc.add_stream(ColumnStream::from_operators(vec![
+            AlsOperator::multiply(AlsOperator::dict_ref(62), 4),
+            AlsOperator::dict_ref(62),
+            AlsOperator::multiply(AlsOperator::dict_ref(63), 2),
+            AlsOperator::dict_ref(3),
+            AlsOperator::dict_ref(4),
+            AlsOperator::dict_ref(1200),
+            AlsOperator::dict_ref(1205),
+            AlsOperator::dict_ref(5000),
+            AlsOperator::dict_ref(5005),
+        ]));
+        let result = AlsSerializer::new().serialize(&doc);
+        assert!(result.starts_with("!v2 +ref62 +relative-refs\n"), "{}", result);
+        // Short indices stay absolute since `_=` and `_+` would not be shorter
+        assert!(result.ends_with("_62*4 _= _+*2 _3 _4 _sm _sr _arE _+5"), "{}", result);
+
+        // Cutting an operator never makes references relative undeclared
+        let mut doc = AlsDocument::with_schema(vec!["col"]);
+        doc.dictionaries.insert("default".to_string(), (0..100).map(|i| i.to_string()).collect());
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::multiply(AlsOperator::dict_ref(62), 4)]));
+        let config = SerializerConfig::new().with_max_operator_expansion(2);
+        let result = AlsSerializer::new().serialize_with_config(&doc, &config);
+        assert!(result.starts_with("!v1\n"), "{}", result);
+        assert!(result.ends_with("_62*2 _62*2"), "{}", result);
+    }
+
+    #[test]
+    fn test_serialize_base62_dict_refs() {
+        let mut doc = AlsDocument::with_schema(vec!["col"]);
+        let dictionary: Vec<String> = (0..5000).map(|i| format!("host-{}", i)).collect();
+        doc.dictionaries.insert("default".to_string(), dictionary);
+        doc.add_stream(ColumnStream::from_operators(vec![
+            AlsOperator::dict_ref(9),
+            AlsOperator::dict_ref(10),
+            AlsOperator::multiply(AlsOperator::dict_ref(4999), 2),
+        ]));
+        let result = AlsSerializer::new().serialize(&doc);
+        assert!(result.starts_with("!v2 +ref62 +front-coding\n"), "{}", result);
+        assert!(result.ends_with("_9 _a _arD*2"), "{}", result);
+
+        let parser = crate::als::AlsParser::new();
+        let rows = parser.expand(&parser.parse(&result).unwrap()).unwrap();
+        let column: Vec<&str> = rows.iter().map(|row| row[0].as_str()).collect();
+        assert_eq!(column, ["host-9", "host-10", "host-4999", "host-4999"]);
+    }
+
     #[test]
     fn test_serialize_dict_ref() {
         let mut doc = AlsDocument::with_schema(vec!["col"]);
@@ -614,6 +1108,109 @@ mod tests {
         assert!(result.contains("_0 _1"));
     }
 
+    #[test]
+    fn test_serialize_column_ref() {
+        let mut doc = AlsDocument::with_schema(vec!["side", "sign", "copy"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::toggle("buy", "sell", 4)]));
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::column_ref(
+            0,
+            vec![("buy".to_string(), "+1".to_string()), ("sell".to_string(), "-1".to_string())],
+            4,
+        )]));
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::column_ref(0, Vec::new(), 4)]));
+        let serializer = AlsSerializer::new();
+        let result = serializer.serialize(&doc);
+        assert!(result.ends_with("buy~sell*4|@0(buy:+1 sell:-1)*4|@0*4"));
+    }
+
+    #[test]
+    fn test_serialize_date_range() {
+        let mut doc = AlsDocument::with_schema(vec!["day", "hour"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::date_range(
+            1_704_067_200,
+            86_400,
+            30,
+            "%Y-%m-%d",
+        )]));
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::date_range(
+            1_704_067_200,
+            -3_600,
+            30,
+            "%H:%M",
+        )]));
+        let serializer = AlsSerializer::new();
+        let result = serializer.serialize(&doc);
+        assert!(result.ends_with("%{%Y-%m-%d}1704067200+86400*30|%{%H:%M}1704067200-3600*30"));
+    }
+
+    #[test]
+    fn test_serialize_date_range_escapes_format() {
+        let mut doc = AlsDocument::with_schema(vec!["day"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::date_range(
+            1_704_067_200,
+            86_400,
+            3,
+            "{%Y}\\\n",
+        )]));
+        let serialized = AlsSerializer::new().serialize(&doc);
+        assert!(serialized.ends_with("%{{%Y\\}\\\\\\n}1704067200+86400*3"), "{}", serialized);
+
+        let parser = AlsParser::new();
+        let reparsed = parser.parse(&serialized).unwrap();
+        assert_eq!(reparsed, doc);
+        assert_eq!(parser.expand(&reparsed).unwrap()[1], vec!["{2024}\\\n"]);
+        assert!(parser.parse("#day\n%{%Y\\q}1704067200+86400*3").is_err());
+    }
+
+    #[test]
+    fn test_serialize_cycle() {
+        let mut doc = AlsDocument::with_schema(vec!["day", "quarter"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::cycle(
+            CalendarCycle::Weekday,
+            2,
+            1,
+            14,
+        )]));
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::cycle(
+            CalendarCycle::Quarter,
+            0,
+            7,
+            14,
+        )]));
+        let serializer = AlsSerializer::new();
+        let result = serializer.serialize(&doc);
+        assert!(result.ends_with("&dow+2*14|&qtr+0:7*14"));
+    }
+
+    #[test]
+    fn test_serialize_transform() {
+        let mut doc = AlsDocument::with_schema(vec!["user", "day"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::transform(
+            AlsOperator::range(1, 100),
+            ValueTransform::new().with_prefix("USER").with_width(3),
+        )]));
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::transform(
+            AlsOperator::cycle(CalendarCycle::Weekday, 0, 1, 100),
+            ValueTransform::new().with_case(CaseTransform::Lower).with_suffix(" x"),
+        )]));
+        let serializer = AlsSerializer::new();
+        let result = serializer.serialize(&doc);
+        assert!(result.ends_with("^USER{3}(1>100)|^{L}\\ x(&dow+0*100)"));
+    }
+
+    #[test]
+    fn test_serialize_bool_runs() {
+        let mut doc = AlsDocument::with_schema(vec!["active"]);
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::bool_runs(
+            "true",
+            "false",
+            vec![3, 1, 12],
+        )]));
+        let serializer = AlsSerializer::new();
+        let result = serializer.serialize(&doc);
+        assert!(result.ends_with("?true~false:3.1.12"));
+    }
+
     #[test]
     fn test_serialize_multiple_columns() {
         let mut doc = AlsDocument::with_schema(vec!["id", "name"]);
@@ -645,6 +1242,35 @@ mod tests {
         assert!(result.contains("e\\~f"));
     }
 
+    #[test]
+    fn test_serialize_special_characters_round_trip() {
+        let specials = ["f(x)", "(", ")", "a b", "x>y*z~w", "_0", "#c", "$d", "@1", "a|b", "1:2", "\\e", "{a}", "-("];
+        let parser = crate::als::AlsParser::new();
+        for value in specials {
+            let mut doc = AlsDocument::with_schema(vec!["a", "b"]);
+            doc.add_stream(ColumnStream::from_operators(vec![
+                AlsOperator::raw(value),
+                AlsOperator::multiply(AlsOperator::raw(value), 2),
+                AlsOperator::toggle(value, "z", 2),
+                AlsOperator::bool_runs(value, "z", vec![1]),
+                AlsOperator::transform(
+                    AlsOperator::raw("v"),
+                    ValueTransform::new().with_prefix(value).with_suffix(value),
+                ),
+                AlsOperator::split('-', vec![vec![AlsOperator::raw(value)], vec![AlsOperator::raw("q")]]),
+            ]));
+            doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::column_ref(
+                0,
+                vec![(value.to_string(), "m".to_string())],
+                8,
+            )]));
+
+            let text = AlsSerializer::new().serialize(&doc);
+            let parsed = parser.parse(&text).unwrap_or_else(|e| panic!("{:?} does not parse: {}\n{}", value, e, text));
+            assert_eq!(parser.expand(&parsed).unwrap(), parser.expand(&doc).unwrap(), "{:?}", value);
+        }
+    }
+
     #[test]
     fn test_serialize_complete_document() {
         let mut doc = AlsDocument::with_schema(vec!["id", "name", "status"]);
@@ -688,12 +1314,44 @@ mod tests {
         assert_eq!(escape_schema_name("a#b"), "a\\#b");
     }
 
+    #[test]
+    fn test_serialize_max_operator_expansion() {
+        use crate::{ParserConfig, SerializerConfig};
+
+        let als = "#id #tag #ts #ok #code\n\
+                   1>25|x*9 a~b*16|%{%Y-%m-%d}1704067200+86400*25|?T~F:12.13|^ID{U3}(7>31)";
+        let parser = AlsParser::new();
+        let doc = parser.parse(als).unwrap();
+
+        let serializer = AlsSerializer::new();
+        let limited = serializer.serialize_with_config(&doc, &SerializerConfig::new().with_max_operator_expansion(10));
+        assert!(limited.contains("1>10 11>20 21>25|x*9 a~b*10 a~b*6|"));
+        assert!(limited.contains("?T~F:10 ?T~F:2.8 ?T~F:0.5|"));
+
+        // Every operator fits under a parser limit of 10, and expands as before
+        let strict = AlsParser::with_config(ParserConfig::new().with_max_range_expansion(10));
+        let reparsed = strict.parse(&limited).unwrap();
+        assert!(reparsed.streams.iter().flat_map(|s| &s.operators).all(|op| op.expanded_count() <= 10));
+        assert_eq!(strict.expand_columns(&reparsed).unwrap(), parser.expand_columns(&doc).unwrap());
+        assert!(strict.parse(&AlsSerializer::new().serialize(&doc)).is_err());
+
+        // A limit of 0 is taken as 1
+        let single = serializer.serialize_with_config(&doc, &SerializerConfig::new().with_max_operator_expansion(0));
+        assert!(single.contains("|x x x x x x x x x a b a "));
+    }
+
     #[test]
     fn test_serializer_is_send_sync() {
         fn assert_send_sync<T: Send + Sync>() {}
         assert_send_sync::<AlsSerializer>();
     }
 
+    #[test]
+    fn test_serializer_is_unit_struct() {
+        let serializer = AlsSerializer;
+        assert_eq!(serializer.serialize(&AlsDocument::new()), "!v1\n");
+    }
+
     // ==================== AlsPrettyPrinter tests ====================
 
     #[test]
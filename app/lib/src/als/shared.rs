@@ -0,0 +1,438 @@
+//! Dictionaries shared by the documents of a container.
+//!
+//! Tables exported from one database often hold the same values: the
+//! `user_id` of a users table turns up again in its orders and sessions.
+//! Compressed on their own, each document carries those values in its own
+//! dictionary. A container can instead store them once, as a shared
+//! dictionary, and documents refer to it from their dictionary header:
+//!
+//! ```text
+//! $default^user_id:pending|shipped
+//! ```
+//!
+//! The default dictionary of such a document is the shared dictionary
+//! `user_id` followed by the entries after the `:`, if any. The reference
+//! only means something inside a container: [`AlsContainerReader::get`]
+//! replaces it with the entries it stands for, so documents read from a
+//! container are complete on their own.
+//!
+//! [`AlsContainerReader::get`]: super::AlsContainerReader::get
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::dict_ref;
+use super::document::{AlsDocument, FormatIndicator};
+use super::escape::{escape_als_string, EMPTY_TOKEN, NULL_TOKEN};
+use super::front_coding;
+use super::operator::AlsOperator;
+use super::parser::AlsParser;
+use super::serializer::AlsSerializer;
+use super::tokenizer::{Token, Tokenizer};
+use crate::error::{AlsError, Result};
+
+/// Marker between `$default` and the name of the shared dictionary it
+/// starts with.
+const SHARED_MARKER: &str = "$default^";
+
+/// Most entries a shared dictionary holds, as for a document's own.
+const MAX_SHARED_ENTRIES: usize = 65_536;
+
+/// Shortest value worth sharing; shorter ones are no longer than a
+/// reference.
+const MIN_SHARED_LEN: usize = 3;
+
+/// Dictionary stored once in a container and referenced by its documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedDictionary {
+    /// Name of the dictionary, unique within the container.
+    pub name: String,
+    /// Entries, most frequent first.
+    pub entries: Vec<String>,
+}
+
+impl SharedDictionary {
+    /// Create a shared dictionary.
+    ///
+    /// Characters of `name` other than letters, digits, `_` and `.` are
+    /// replaced with `_`.
+    pub fn new(name: &str, entries: Vec<String>) -> Self {
+        let name = name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' || c == '.' { c } else { '_' })
+            .collect();
+        Self { name, entries }
+    }
+
+    /// Find the value domains documents have in common and build a shared
+    /// dictionary for each.
+    ///
+    /// Columns of different documents share a domain if they have the same
+    /// name, or if at least half the distinct values of one appear in the
+    /// other. A domain spanning two or more documents gets a dictionary of
+    /// the values found in more than one of them, named after its first
+    /// column.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, SharedDictionary};
+    ///
+    /// let parser = AlsParser::new();
+    /// let users = parser.parse("#user_id #name\nu-100 u-101 u-102|ann bob cy").unwrap();
+    /// let orders = parser.parse("#order #user_id\n1>3|u-102 u-100 u-102").unwrap();
+    ///
+    /// let shared = SharedDictionary::find(&[users, orders]).unwrap();
+    /// assert_eq!(shared[0].name, "user_id");
+    /// assert_eq!(shared[0].entries, ["u-102", "u-100"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a document fails to expand.
+    pub fn find(documents: &[AlsDocument]) -> Result<Vec<Self>> {
+        let parser = AlsParser::new();
+
+        // Value counts of every column
+        let mut columns: Vec<(usize, &str, HashMap<String, usize>)> = Vec::new();
+        for (doc_idx, doc) in documents.iter().enumerate() {
+            for (name, values) in doc.schema.iter().zip(parser.expand_columns(doc)?) {
+                let mut counts = HashMap::new();
+                for value in values {
+                    if value.chars().count() >= MIN_SHARED_LEN && value != NULL_TOKEN && value != EMPTY_TOKEN {
+                        *counts.entry(value).or_insert(0) += 1;
+                    }
+                }
+                if !counts.is_empty() {
+                    columns.push((doc_idx, name, counts));
+                }
+            }
+        }
+
+        // Group columns of different documents into domains
+        let mut domain: Vec<usize> = (0..columns.len()).collect();
+        for a in 0..columns.len() {
+            for b in a + 1..columns.len() {
+                let ((doc_a, name_a, values_a), (doc_b, name_b, values_b)) = (&columns[a], &columns[b]);
+                if doc_a == doc_b {
+                    continue;
+                }
+                let (small, large) = if values_a.len() <= values_b.len() { (values_a, values_b) } else { (values_b, values_a) };
+                let common = small.keys().filter(|value| large.contains_key(*value)).count();
+                if name_a == name_b || common * 2 >= small.len() {
+                    let (root_a, root_b) = (find_root(&mut domain, a), find_root(&mut domain, b));
+                    domain[root_a.max(root_b)] = root_a.min(root_b);
+                }
+            }
+        }
+        let mut domains: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for column in 0..columns.len() {
+            domains.entry(find_root(&mut domain, column)).or_default().push(column);
+        }
+
+        let mut names = HashSet::new();
+        let mut shared = Vec::new();
+        for members in domains.values() {
+            // Documents holding each value, and how often it occurs
+            let mut spread: HashMap<&str, (HashSet<usize>, usize)> = HashMap::new();
+            for &column in members {
+                let (doc_idx, _, counts) = &columns[column];
+                for (value, count) in counts {
+                    let (docs, total) = spread.entry(value).or_default();
+                    docs.insert(*doc_idx);
+                    *total += count;
+                }
+            }
+            let mut entries: Vec<(&str, usize)> = spread
+                .into_iter()
+                .filter(|(_, (docs, _))| docs.len() > 1)
+                .map(|(value, (_, total))| (value, total))
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            entries.truncate(MAX_SHARED_ENTRIES);
+
+            let mut dictionary = Self::new(columns[members[0]].1, entries.into_iter().map(|(value, _)| value.to_string()).collect());
+            let base = dictionary.name.clone();
+            let mut suffix = 1;
+            while !names.insert(dictionary.name.clone()) {
+                suffix += 1;
+                dictionary.name = format!("{}_{}", base, suffix);
+            }
+            shared.push(dictionary);
+        }
+        Ok(shared)
+    }
+
+    /// Rewrite a document so its default dictionary starts with these
+    /// entries.
+    ///
+    /// References are renumbered, and raw values found in the dictionary
+    /// become references where that is shorter. CTX documents and columns,
+    /// whose values are all raw, become ALS ones to hold the references.
+    /// Returns `None` for documents with references past the end of their
+    /// dictionary.
+    pub(super) fn rebase(&self, doc: &AlsDocument<'static>) -> Option<AlsDocument<'static>> {
+        let positions: HashMap<&str, usize> = self
+            .entries
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(index, entry)| (entry.as_str(), index))
+            .collect();
+
+        let own = doc.dictionaries.get("default").map(Vec::as_slice).unwrap_or_default();
+        let mut dictionary = self.entries.clone();
+        let renumbered: Vec<usize> = own
+            .iter()
+            .map(|entry| match positions.get(entry.as_str()) {
+                Some(&index) => index,
+                None => {
+                    dictionary.push(entry.clone());
+                    dictionary.len() - 1
+                }
+            })
+            .collect();
+
+        let mut rebased = doc.clone();
+        rebased.format_indicator = FormatIndicator::Als;
+        let mut valid = true;
+        for stream in &mut rebased.streams {
+            stream.format_indicator = FormatIndicator::Als;
+            for operator in &mut stream.operators {
+                rebase_operator(operator, &mut |operator| {
+                    let shared = match operator {
+                        AlsOperator::DictRef(index) => {
+                            match renumbered.get(*index) {
+                                Some(&new) => *index = new,
+                                None => valid = false,
+                            }
+                            None
+                        }
+                        AlsOperator::Raw(value) => positions
+                            .get(value.as_ref())
+                            .copied()
+                            .filter(|&index| dict_ref::len(index) + 1 < escape_als_string(value).len()),
+                        _ => None,
+                    };
+                    if let Some(index) = shared {
+                        *operator = AlsOperator::DictRef(index);
+                    }
+                });
+            }
+        }
+        if !valid {
+            return None;
+        }
+        rebased.dictionaries.insert("default".to_string(), dictionary);
+        Some(rebased)
+    }
+
+    /// Serialize a document rebased onto this dictionary, writing only the
+    /// entries after the shared ones in its header.
+    pub(super) fn serialize_rebased(&self, doc: &AlsDocument) -> String {
+        let mut own = doc.clone();
+        let extra = own
+            .dictionaries
+            .get("default")
+            .map(|entries| entries[self.entries.len().min(entries.len())..].to_vec())
+            .unwrap_or_default();
+        if extra.is_empty() {
+            own.dictionaries.remove("default");
+        } else {
+            own.dictionaries.insert("default".to_string(), extra.clone());
+        }
+        let text = AlsSerializer::new().serialize(&own);
+
+        let header_end = text.find('\n').map_or(text.len(), |end| end + 1);
+        let reference = format!("{}{}", SHARED_MARKER, self.name);
+        if extra.is_empty() {
+            return format!("{}{}\n{}", &text[..header_end], reference, &text[header_end..]);
+        }
+        let start = header_lines(&text)
+            .find(|&(_, line)| line.starts_with("$default:") || line.starts_with("$default~"))
+            .map(|(start, _)| start)
+            .expect("serialized document has its default dictionary");
+        format!("{}{}{}", &text[..start], reference, &text[start + "$default".len()..])
+    }
+
+    /// Serialize the dictionary as a dictionary header line, without a
+    /// trailing newline.
+    pub(super) fn to_line(&self) -> String {
+        let mut doc = AlsDocument::new();
+        doc.dictionaries.insert(self.name.clone(), self.entries.clone());
+        let mut line = String::new();
+        AlsSerializer::new().serialize_dictionaries(&mut line, &doc);
+        line.truncate(line.trim_end_matches('\n').len());
+        line
+    }
+
+    /// Parse the dictionary header line a container stores for a shared
+    /// dictionary, which starts at offset `position` of the container.
+    pub(super) fn parse_line(line: &str, position: usize) -> Result<Self> {
+        let (name, entries) = parse_dictionary_header(line, position)?;
+        Ok(Self { name, entries })
+    }
+}
+
+/// Replace a reference to a shared dictionary in the header of a document
+/// with the entries it stands for, looking the dictionary up by name with
+/// `lookup`.
+///
+/// Returns `None` if the document refers to no shared dictionary.
+///
+/// # Errors
+///
+/// Returns `AlsError::AlsSyntaxError` if `lookup` doesn't find the
+/// dictionary or the header is malformed, and errors from `lookup`.
+pub(super) fn resolve_shared(
+    text: &str,
+    lookup: impl FnOnce(&str) -> Result<Option<SharedDictionary>>,
+) -> Result<Option<String>> {
+    let Some((start, line)) = header_lines(text).find(|(_, line)| line.starts_with(SHARED_MARKER)) else {
+        return Ok(None);
+    };
+    let syntax_error = |message: String| AlsError::AlsSyntaxError { position: start, message };
+
+    let rest = &line[SHARED_MARKER.len()..];
+    let name_end = rest.find([':', '~']).unwrap_or(rest.len());
+    let name = &rest[..name_end];
+    let dictionary = lookup(name)?.ok_or_else(|| syntax_error(format!("Unknown shared dictionary: {}", name)))?;
+
+    let mut entries = dictionary.entries.clone();
+    if name_end < rest.len() {
+        let (_, extra) = parse_dictionary_header(&format!("$default{}", &rest[name_end..]), start)?;
+        entries.extend(extra);
+    }
+    let mut doc = AlsDocument::new();
+    doc.dictionaries.insert("default".to_string(), entries);
+    let mut resolved = String::with_capacity(text.len() + dictionary.entries.iter().map(|entry| entry.len() + 1).sum::<usize>());
+    resolved.push_str(&text[..start]);
+    AlsSerializer::new().serialize_dictionaries(&mut resolved, &doc);
+    resolved.push_str(text[start + line.len()..].strip_prefix('\n').unwrap_or(&text[start + line.len()..]));
+    Ok(Some(resolved))
+}
+
+/// Iterate over the lines between the version line and the schema of a
+/// document, with their offsets.
+fn header_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    text.split('\n')
+        .map(move |line| {
+            let start = offset;
+            offset += line.len() + 1;
+            (start, line)
+        })
+        .skip_while(|(_, line)| line.starts_with('!'))
+        .take_while(|(_, line)| line.starts_with('$'))
+}
+
+/// Parse a `$name:entries` line into its name and entries.
+fn parse_dictionary_header(line: &str, position: usize) -> Result<(String, Vec<String>)> {
+    match Tokenizer::new(line).next_token()? {
+        Token::DictionaryHeader { name, values, front_coded } => {
+            let values = if front_coded { front_coding::decode(values, position)? } else { values };
+            Ok((name, values))
+        }
+        _ => Err(AlsError::AlsSyntaxError {
+            position,
+            message: "Expected a dictionary header".to_string(),
+        }),
+    }
+}
+
+/// Call `f` on every operator that may hold a dictionary reference.
+fn rebase_operator(operator: &mut AlsOperator<'static>, f: &mut impl FnMut(&mut AlsOperator<'static>)) {
+    match operator {
+        AlsOperator::Multiply { value, .. } | AlsOperator::Transform { value, .. } => rebase_operator(value, f),
+        AlsOperator::Split { parts, .. } => {
+            for part in parts.iter_mut().flatten() {
+                rebase_operator(part, f);
+            }
+        }
+        _ => f(operator),
+    }
+}
+
+/// Find the representative of a column's domain, shortening the path.
+fn find_root(domain: &mut [usize], mut column: usize) -> usize {
+    while domain[column] != column {
+        domain[column] = domain[domain[column]];
+        column = domain[column];
+    }
+    column
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared(name: &str, entries: &[&str]) -> SharedDictionary {
+        SharedDictionary::new(name, entries.iter().map(|entry| entry.to_string()).collect())
+    }
+
+    #[test]
+    fn test_find_groups_domains() {
+        let parser = AlsParser::new();
+        let documents = [
+            parser.parse("#user_id #country\nu-100 u-101 u-102 u-103|fra fra deu esp").unwrap(),
+            parser.parse("#id #customer\n1>4|u-101 u-101 u-103 u-999").unwrap(),
+            parser.parse("#user_id #country\nu-103 u-104|esp ita").unwrap(),
+        ];
+        let found = SharedDictionary::find(&documents).unwrap();
+        assert_eq!(found.len(), 2);
+
+        // `customer` joins the domain by its values, not its name
+        assert_eq!(found[0].name, "user_id");
+        assert_eq!(found[0].entries, ["u-101", "u-103"]);
+        assert_eq!(found[1].name, "country");
+        assert_eq!(found[1].entries, ["esp"]);
+
+        assert!(SharedDictionary::find(&documents[..1]).unwrap().is_empty());
+        assert_eq!(SharedDictionary::new("user id/v2", Vec::new()).name, "user_id_v2");
+    }
+
+    #[test]
+    fn test_rebase_and_resolve() {
+        let parser = AlsParser::new();
+        let doc = parser
+            .parse("$default:pending|u-102\n#user_id #status\nu-100 _1*3 u-101|_0*4 shipped")
+            .unwrap();
+        let dictionary = shared("user_id", &["u-100", "u-101", "u-102"]);
+
+        let rebased = dictionary.rebase(&doc).unwrap();
+        assert_eq!(rebased.dictionaries["default"], ["u-100", "u-101", "u-102", "pending"]);
+        assert_eq!(parser.expand(&rebased).unwrap(), parser.expand(&doc).unwrap());
+
+        let text = dictionary.serialize_rebased(&rebased);
+        assert!(text.contains("\n$default^user_id:pending\n"), "{}", text);
+        assert!(parser.parse(&text).is_err());
+
+        let lookup = |_: &str| Ok(Some(dictionary.clone()));
+        let resolved = resolve_shared(&text, lookup).unwrap().unwrap();
+        assert_eq!(parser.expand(&parser.parse(&resolved).unwrap()).unwrap(), parser.expand(&doc).unwrap());
+        assert_eq!(resolve_shared(&resolved, lookup).unwrap(), None);
+        assert!(resolve_shared(&text, |_| Ok(None)).is_err());
+    }
+
+    #[test]
+    fn test_rebase_without_own_entries() {
+        let parser = AlsParser::new();
+        let doc = parser.parse("!v2 +meta\n#user_id\nu-100 u-101 u-100\n!meta size=3").unwrap();
+        let dictionary = shared("user_id", &["u-100", "u-101"]);
+
+        let text = dictionary.serialize_rebased(&dictionary.rebase(&doc).unwrap());
+        assert_eq!(text, "!v2 +meta\n$default^user_id\n#user_id\n_0 _1 _0\n!meta size=3");
+        let resolved = resolve_shared(&text, |_| Ok(Some(dictionary.clone()))).unwrap().unwrap();
+        assert_eq!(parser.expand(&parser.parse(&resolved).unwrap()).unwrap(), parser.expand(&doc).unwrap());
+
+        let line = dictionary.to_line();
+        assert_eq!(SharedDictionary::parse_line(&line, 0).unwrap(), dictionary);
+
+        // Verbatim values become references too
+        let ctx = parser.parse("!ctx\n#user_id #n\nu-101 u-102|1 2").unwrap();
+        let text = dictionary.serialize_rebased(&dictionary.rebase(&ctx).unwrap());
+        assert_eq!(text, "!v1\n$default^user_id\n#user_id #n\n_1 u-102|1 2");
+    }
+}
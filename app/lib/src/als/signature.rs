@@ -0,0 +1,279 @@
+//! Document signatures.
+//!
+//! A signed document ends with a signature line holding the Ed25519 public
+//! key of the signer and the signature of everything before the line, both
+//! in hex:
+//!
+//! ```text
+//! #id #name
+//! 1>3|alice bob carol
+//! !sig ed25519 d75a9801...f707511a e5564300...8e7a100b
+//! ```
+//!
+//! The newline joining the document and the signature line is not signed.
+//! Saved on its own, the same line is a detached signature of a whole file.
+//!
+//! Parsers skip the signature line, so signed documents read like unsigned
+//! ones. Signing and checking signatures need the `signing` feature.
+
+use crate::error::{AlsError, Result};
+
+#[cfg(feature = "signing")]
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+/// Prefix of a signature line.
+pub const SIGNATURE_PREFIX: &str = "!sig ";
+
+/// Signature scheme named in signature lines.
+const ALGORITHM: &str = "ed25519";
+
+/// Ed25519 signature of a document, with the public key of its signer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlsSignature {
+    /// Public key of the signer.
+    pub public_key: [u8; 32],
+    /// Signature of the document.
+    pub signature: [u8; 64],
+}
+
+impl AlsSignature {
+    /// Sign bytes.
+    #[cfg(feature = "signing")]
+    pub fn sign(content: &[u8], key: &SigningKey) -> Self {
+        use ed25519_dalek::Signer;
+
+        Self {
+            public_key: key.verifying_key().to_bytes(),
+            signature: key.sign(content).to_bytes(),
+        }
+    }
+
+    /// Check that this signature was made over `content` by `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidSignature` if the signature was made with
+    /// another key or `content` was altered.
+    #[cfg(feature = "signing")]
+    pub fn verify(&self, content: &[u8], key: &VerifyingKey) -> Result<()> {
+        if self.public_key != key.to_bytes() {
+            return Err(AlsError::InvalidSignature {
+                message: format!("signed by another key ({})", encode_hex(&self.public_key)),
+            });
+        }
+        let signature = ed25519_dalek::Signature::from_bytes(&self.signature);
+        key.verify_strict(content, &signature)
+            .map_err(|_| AlsError::InvalidSignature {
+                message: "content does not match its signature".to_string(),
+            })
+    }
+
+    /// Format the signature line, without a trailing newline.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}{} {} {}",
+            SIGNATURE_PREFIX,
+            ALGORITHM,
+            encode_hex(&self.public_key),
+            encode_hex(&self.signature)
+        )
+    }
+
+    /// Parse a signature line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidSignature` if the line is malformed or
+    /// names another signature scheme.
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let invalid = |message: &str| AlsError::InvalidSignature {
+            message: message.to_string(),
+        };
+
+        let mut fields = line
+            .trim_end_matches(['\r', '\n'])
+            .strip_prefix(SIGNATURE_PREFIX)
+            .ok_or_else(|| invalid("expected a signature line"))?
+            .split(' ');
+        let algorithm = fields.next().unwrap_or_default();
+        if algorithm != ALGORITHM {
+            return Err(AlsError::InvalidSignature {
+                message: format!("unsupported signature scheme: {}", algorithm),
+            });
+        }
+        let public_key = fields
+            .next()
+            .and_then(decode_hex_array)
+            .ok_or_else(|| invalid("invalid public key"))?;
+        let signature = fields
+            .next()
+            .and_then(decode_hex_array)
+            .ok_or_else(|| invalid("invalid signature"))?;
+        if fields.next().is_some() {
+            return Err(invalid("unexpected field in signature line"));
+        }
+
+        Ok(Self {
+            public_key,
+            signature,
+        })
+    }
+}
+
+/// Split a document into its signed content and its signature line, if it
+/// has one.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::als::split_signature;
+///
+/// assert_eq!(split_signature("#x\n1>3"), ("#x\n1>3", None));
+/// assert_eq!(split_signature("#x\n1>3\n!sig ed25519 ab cd"), ("#x\n1>3", Some("!sig ed25519 ab cd")));
+/// ```
+pub fn split_signature(text: &str) -> (&str, Option<&str>) {
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let body = body.strip_suffix('\r').unwrap_or(body);
+    let start = body.rfind('\n').map_or(0, |i| i + 1);
+    if !body[start..].starts_with(SIGNATURE_PREFIX) {
+        return (text, None);
+    }
+    (&text[..start.saturating_sub(1)], Some(&body[start..]))
+}
+
+/// Sign a serialized document, embedding the signature as its last line.
+///
+/// An existing signature line is replaced.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::als::{sign_document, verify_document, SigningKey};
+///
+/// let key = SigningKey::from_bytes(&[7; 32]);
+/// let signed = sign_document("#x\n1>3", &key);
+/// assert_eq!(verify_document(&signed, &key.verifying_key()).unwrap(), "#x\n1>3");
+/// assert!(verify_document(&signed.replace("1>3", "1>4"), &key.verifying_key()).is_err());
+/// ```
+#[cfg(feature = "signing")]
+pub fn sign_document(text: &str, key: &SigningKey) -> String {
+    let (content, _) = split_signature(text);
+    let line = AlsSignature::sign(content.as_bytes(), key).to_line();
+    format!("{}\n{}", content, line)
+}
+
+/// Check the embedded signature of a document and return the signed
+/// content.
+///
+/// # Errors
+///
+/// Returns `AlsError::InvalidSignature` if the document is unsigned, was
+/// altered, or was signed with another key.
+#[cfg(feature = "signing")]
+pub fn verify_document<'a>(text: &'a str, key: &VerifyingKey) -> Result<&'a str> {
+    let (content, line) = split_signature(text);
+    let line = line.ok_or_else(|| AlsError::InvalidSignature {
+        message: "document is not signed".to_string(),
+    })?;
+    AlsSignature::parse_line(line)?.verify(content.as_bytes(), key)?;
+    Ok(content)
+}
+
+/// Read a signing key from the hex of its 32-byte secret.
+#[cfg(feature = "signing")]
+pub fn parse_signing_key(hex: &str) -> Result<SigningKey> {
+    decode_hex_array(hex.trim())
+        .map(|secret| SigningKey::from_bytes(&secret))
+        .ok_or_else(|| AlsError::InvalidSignature {
+            message: "signing key must be 64 hex digits".to_string(),
+        })
+}
+
+/// Read a public key from its hex.
+#[cfg(feature = "signing")]
+pub fn parse_verifying_key(hex: &str) -> Result<VerifyingKey> {
+    decode_hex_array(hex.trim())
+        .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+        .ok_or_else(|| AlsError::InvalidSignature {
+            message: "invalid public key".to_string(),
+        })
+}
+
+/// Encode bytes as lowercase hex.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decode hex of exactly `N` bytes.
+fn decode_hex_array<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    super::bloom::decode_hex(hex)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_signature() {
+        assert_eq!(split_signature(""), ("", None));
+        assert_eq!(split_signature("!sig ed25519 a b\n"), ("", Some("!sig ed25519 a b")));
+        assert_eq!(split_signature("#x\n1\n\n!sig ed25519 a b\r\n"), ("#x\n1\n", Some("!sig ed25519 a b")));
+        assert_eq!(split_signature("#x\n1\n!zone 0 1 1"), ("#x\n1\n!zone 0 1 1", None));
+    }
+
+    #[test]
+    fn test_line_roundtrip() {
+        let signature = AlsSignature {
+            public_key: [0xab; 32],
+            signature: [0x01; 64],
+        };
+        let line = signature.to_line();
+        assert!(line.starts_with("!sig ed25519 abab"));
+        assert_eq!(AlsSignature::parse_line(&line).unwrap(), signature);
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        let line = AlsSignature {
+            public_key: [0; 32],
+            signature: [0; 64],
+        }
+        .to_line();
+        assert!(AlsSignature::parse_line(&line.replace("ed25519", "rsa")).is_err());
+        assert!(AlsSignature::parse_line(&line[..line.len() - 2]).is_err());
+        assert!(AlsSignature::parse_line(&format!("{} 00", line)).is_err());
+        assert!(AlsSignature::parse_line("#x").is_err());
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_rfc8032_vector() {
+        let key = parse_signing_key("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+        let signature = AlsSignature::sign(b"", &key);
+        assert_eq!(
+            encode_hex(&signature.public_key),
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+        );
+        assert_eq!(
+            encode_hex(&signature.signature),
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+        );
+        signature.verify(b"", &key.verifying_key()).unwrap();
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_sign_and_verify_document() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let other = SigningKey::from_bytes(&[2; 32]);
+        let signed = sign_document("#x\n1>3", &key);
+        assert_eq!(verify_document(&signed, &key.verifying_key()).unwrap(), "#x\n1>3");
+        assert_eq!(sign_document(&signed, &key), signed);
+        assert!(matches!(
+            verify_document(&signed, &other.verifying_key()),
+            Err(AlsError::InvalidSignature { .. })
+        ));
+        assert!(verify_document("#x\n1>3", &key.verifying_key()).is_err());
+        assert!(verify_document(&signed.replacen('3', "4", 1), &key.verifying_key()).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Exact uncompressed sizes.
+//!
+//! The size of a document once converted back to CSV or JSON follows from
+//! its operators without expanding them: a repeat is its value times the
+//! count, a toggle or calendar cycle is a number of whole periods plus a
+//! remainder, and a range is a count of values per number of digits.
+//! [`AlsDocument::uncompressed_size`] adds these up, rendering each distinct
+//! value once through the same typing and escaping as the converters.
+//!
+//! Operators whose values cannot be counted this way (date ranges,
+//! transforms, split columns and column references) are expanded on their
+//! own, so a single such column never costs a full expansion of the
+//! document.
+
+use std::collections::HashMap;
+
+use super::document::{AlsDocument, ColumnStream};
+use super::operator::AlsOperator;
+use super::parser::{is_boolean_stream, typed_value, AlsParser};
+use super::store::StoredFormat;
+use crate::config::JsonFormat;
+use crate::convert::csv::value_to_csv_string;
+use crate::convert::json::{to_json_with_format, value_to_json_value};
+use crate::error::{AlsError, Result};
+
+impl AlsDocument<'_> {
+    /// Get the exact length in bytes of the document converted to `format`,
+    /// as `AlsParser::to_csv` and compact `AlsParser::to_json` write it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsParser, StoredFormat};
+    ///
+    /// let als = "#id #level\n1>1000|info~warn*1000";
+    /// let doc = AlsParser::new().parse(als).unwrap();
+    ///
+    /// let csv = AlsParser::new().to_csv(als).unwrap();
+    /// assert_eq!(doc.uncompressed_size(StoredFormat::Csv).unwrap(), csv.len());
+    /// let json = AlsParser::new().to_json(als).unwrap();
+    /// assert_eq!(doc.uncompressed_size(StoredFormat::Json).unwrap(), json.len());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an operator cannot be expanded, such as a
+    /// dictionary reference past the end of the dictionary.
+    pub fn uncompressed_size(&self, format: StoredFormat) -> Result<usize> {
+        let rows = self.row_count();
+        let columns = self.schema.len();
+        if rows == 0 || columns == 0 {
+            return Ok(match format {
+                StoredFormat::Csv => 0,
+                StoredFormat::Json => "[]".len(),
+            });
+        }
+
+        // Dotted names nest and repeated names overwrite each other, so the
+        // objects are no longer one key per column
+        let mut names = std::collections::HashSet::new();
+        if format == StoredFormat::Json && !self.schema.iter().all(|name| !name.contains('.') && names.insert(name)) {
+            let columns = AlsParser::new().expand_columns(self)?;
+            let data = AlsParser::columns_to_tabular(self, columns, true);
+            return Ok(to_json_with_format(&data, JsonFormat::Compact)?.len());
+        }
+
+        let dictionary = self.default_dictionary().map(Vec::as_slice);
+        let mut expanded: Option<Vec<Vec<String>>> = None;
+        let mut values = 0usize;
+        for (index, stream) in self.streams.iter().enumerate().take(columns) {
+            let mut measure = Measure {
+                format,
+                boolean: format == StoredFormat::Json && is_boolean_stream(stream),
+                single_column: columns == 1,
+                dictionary,
+                lengths: HashMap::new(),
+            };
+            let size = if stream.has_column_refs() {
+                if expanded.is_none() {
+                    expanded = Some(AlsParser::new().expand_columns(self)?);
+                }
+                let column = expanded.as_ref().and_then(|columns| columns.get(index));
+                column.into_iter().flatten().try_fold(0usize, |total, value| {
+                    Ok::<_, AlsError>(total.saturating_add(measure.rendered_len(value)?))
+                })?
+            } else {
+                measure.stream_len(stream)?
+            };
+            values = values.saturating_add(size);
+        }
+
+        let separators = rows.saturating_mul(columns - 1);
+        Ok(match format {
+            StoredFormat::Csv => {
+                // The header, then a comma between fields and a newline per row
+                let header = self.schema.iter().fold(columns, |total, name| {
+                    total + csv_field_len(name, columns == 1)
+                });
+                header.saturating_add(values).saturating_add(separators).saturating_add(rows)
+            }
+            StoredFormat::Json => {
+                // Keys with their colons and the braces of each row, and the
+                // brackets and commas of the array
+                let keys = self.schema.iter().try_fold(0usize, |total, name| {
+                    Ok::<_, AlsError>(total + serde_json::to_string(name)?.len() + 1)
+                })?;
+                let row = keys + 2;
+                values
+                    .saturating_add(rows.saturating_mul(row))
+                    .saturating_add(separators)
+                    .saturating_add(rows + 1)
+            }
+        })
+    }
+}
+
+/// Renders the values of one column and adds up their lengths.
+struct Measure<'d> {
+    format: StoredFormat,
+    /// Whether boolean labels are written as booleans.
+    boolean: bool,
+    /// Whether the CSV has a single column, where empty fields are quoted.
+    single_column: bool,
+    dictionary: Option<&'d [String]>,
+    /// Rendered lengths of the values seen so far.
+    lengths: HashMap<&'d str, usize>,
+}
+
+impl<'d> Measure<'d> {
+    fn stream_len(&mut self, stream: &'d ColumnStream) -> Result<usize> {
+        stream.operators.iter().try_fold(0usize, |total, op| Ok(total.saturating_add(self.operator_len(op)?)))
+    }
+
+    /// Get the total rendered length of the values of an operator.
+    fn operator_len(&mut self, op: &'d AlsOperator) -> Result<usize> {
+        match op {
+            AlsOperator::Raw(value) => self.len(value),
+            AlsOperator::DictRef(index) => {
+                let dictionary = self.dictionary.ok_or(AlsError::InvalidDictRef { index: *index, size: 0 })?;
+                let value = dictionary.get(*index).ok_or(AlsError::InvalidDictRef {
+                    index: *index,
+                    size: dictionary.len(),
+                })?;
+                self.len(value)
+            }
+            AlsOperator::Range { start, end, step } => range_len(*start, *end, *step),
+            AlsOperator::Multiply { value, count } => Ok(self.operator_len(value)?.saturating_mul(*count)),
+            AlsOperator::Toggle { values, count } => {
+                let mut total = 0usize;
+                for (i, value) in values.iter().enumerate() {
+                    let occurrences = count / values.len() + usize::from(i < count % values.len());
+                    total = total.saturating_add(self.len(value)?.saturating_mul(occurrences));
+                }
+                Ok(total)
+            }
+            AlsOperator::Cycle { cycle, offset, run, count } if *run > 0 => {
+                let labels = cycle.values();
+                // The rest of the run the offset falls in, then whole runs
+                // from label `start` on
+                let lead = ((run - offset % run) % run).min(*count);
+                let start = (offset / run % labels.len() + usize::from(lead > 0)) % labels.len();
+                let (runs, rest) = ((count - lead) / run, (count - lead) % run);
+                let mut total = self.len(labels[(offset / run) % labels.len()])?.saturating_mul(lead);
+                total = total.saturating_add(self.len(labels[(start + runs % labels.len()) % labels.len()])?.saturating_mul(rest));
+                for (i, label) in labels.iter().enumerate() {
+                    // Whole runs of this label among the first `runs`
+                    let position = (i + labels.len() - start) % labels.len();
+                    let occurrences = runs / labels.len() + usize::from(position < runs % labels.len());
+                    total = total.saturating_add(self.len(label)?.saturating_mul(occurrences).saturating_mul(*run));
+                }
+                Ok(total)
+            }
+            AlsOperator::BoolRuns { labels, runs } => {
+                let lengths = [self.len(&labels[0])?, self.len(&labels[1])?];
+                Ok(runs
+                    .iter()
+                    .enumerate()
+                    .fold(0usize, |total, (i, run)| total.saturating_add(lengths[i % 2].saturating_mul(*run))))
+            }
+            _ => op
+                .expand(self.dictionary)?
+                .iter()
+                .try_fold(0usize, |total, value| Ok(total.saturating_add(self.rendered_len(value)?))),
+        }
+    }
+
+    /// Get the rendered length of a value, remembering it for next time.
+    fn len(&mut self, value: &'d str) -> Result<usize> {
+        if let Some(&len) = self.lengths.get(value) {
+            return Ok(len);
+        }
+        let len = self.rendered_len(value)?;
+        self.lengths.insert(value, len);
+        Ok(len)
+    }
+
+    /// Get the length of a value as the converter writes it.
+    fn rendered_len(&self, value: &str) -> Result<usize> {
+        let typed = typed_value(value.to_string(), self.boolean);
+        Ok(match self.format {
+            StoredFormat::Csv => csv_field_len(&value_to_csv_string(&typed), self.single_column),
+            StoredFormat::Json => serde_json::to_string(&value_to_json_value(&typed))?.len(),
+        })
+    }
+}
+
+/// Get the length of a CSV field, quoted if it needs to be.
+///
+/// A record of a single empty field is written as `""`, so that it is not
+/// read back as an empty line.
+fn csv_field_len(field: &str, single_column: bool) -> usize {
+    if field.is_empty() && single_column {
+        2
+    } else if field.bytes().any(|b| matches!(b, b',' | b'"' | b'\r' | b'\n')) {
+        field.len() + 2 + field.matches('"').count()
+    } else {
+        field.len()
+    }
+}
+
+/// Get the total number of characters of the values of a range.
+///
+/// Values are counted per number of digits, for the positive and negative
+/// values of each width.
+fn range_len(start: i64, end: i64, step: i64) -> Result<usize> {
+    if step == 0 {
+        return Err(AlsError::RangeOverflow { start, end, step });
+    }
+    if (step > 0 && start > end) || (step < 0 && start < end) {
+        return Ok(0);
+    }
+    let (start, step) = (i128::from(start), i128::from(step));
+    let last = (i128::from(end) - start) / step;
+
+    let mut total: i128 = 0;
+    let mut low: i128 = 1;
+    for digits in 1..=19 {
+        let high = low * 10 - 1;
+        let positive = values_between(start, step, last, if digits == 1 { 0 } else { low }, high);
+        let negative = values_between(start, step, last, -high, -low);
+        total += digits * positive + (digits + 1) * negative;
+        low *= 10;
+    }
+    Ok(usize::try_from(total).unwrap_or(usize::MAX))
+}
+
+/// Count the values `start + i * step` for `i` in `0..=last` that fall
+/// within `low..=high`.
+fn values_between(start: i128, step: i128, last: i128, low: i128, high: i128) -> i128 {
+    let (first, final_) = if step > 0 {
+        (div_ceil(low - start, step), div_floor(high - start, step))
+    } else {
+        (div_ceil(high - start, step), div_floor(low - start, step))
+    };
+    (final_.min(last) - first.max(0) + 1).max(0)
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let q = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    let q = a / b;
+    if a % b != 0 && (a < 0) == (b < 0) {
+        q + 1
+    } else {
+        q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlsCompressor;
+
+    fn assert_exact(als: &str) {
+        let parser = AlsParser::new();
+        let doc = parser.parse(als).unwrap();
+        let csv = parser.to_csv(als).unwrap();
+        assert_eq!(doc.uncompressed_size(StoredFormat::Csv).unwrap(), csv.len(), "CSV of {:?}", als);
+        let json = parser.to_json(als).unwrap();
+        assert_eq!(doc.uncompressed_size(StoredFormat::Json).unwrap(), json.len(), "JSON of {:?}", als);
+    }
+
+    #[test]
+    fn test_uncompressed_size_matches_conversion() {
+        assert_exact("#id #level\n1>1000|info~warn~error*1000");
+        assert_exact("#n\n-12>12:5 -1000000>-990000:999 9223372036854775800>9223372036854775806");
+        assert_exact("#n\n100>-100:-7 5>1 7");
+        assert_exact("$default:GET|POST|\"quoted, value\"\n#method\n_0*5 _1 _2*3 _0");
+        assert_exact("#day #month #quarter\n&dow+3:2*17|&monthname+11*17|&qtr+0:5*17");
+        assert_exact("#ok #flag\n?T~F:3.2.5|?on~off:4.6");
+        assert_exact("#x #y\n1.5*2 T yes|a~b*4");
+        assert_exact("#ts #side #sign\n%{%Y-%m-%d}1704067200+86400*3|buy sell*2|@1(buy:+1 sell:-1)*3");
+    }
+
+    #[test]
+    fn test_uncompressed_size_special_values() {
+        let data = "a\n1\n\n\"x\"\"y\"\nline\rbreak\n";
+        let compressed = AlsCompressor::new().compress_csv(data).unwrap();
+        assert_exact(&compressed);
+
+        let data = "id,name,note\n1,,\"a, b\"\n2,\"say \"\"hi\"\"\",\n3,Ünïcödé,\n";
+        let compressed = AlsCompressor::new().compress_csv(data).unwrap();
+        assert_exact(&compressed);
+    }
+
+    #[test]
+    fn test_uncompressed_size_nested_and_empty() {
+        assert_exact("#user.name #user.id #id\nann bob|1>2|3*2");
+        assert_exact("#a #a\n1 2|3 4");
+
+        let doc = AlsDocument::with_schema(vec!["a", "b"]);
+        assert_eq!(doc.uncompressed_size(StoredFormat::Csv).unwrap(), 0);
+        assert_eq!(doc.uncompressed_size(StoredFormat::Json).unwrap(), 2);
+
+        let doc = AlsParser::new().parse("$default:a\n#x\n_3").unwrap();
+        assert!(matches!(doc.uncompressed_size(StoredFormat::Csv), Err(AlsError::InvalidDictRef { index: 3, .. })));
+    }
+
+    #[test]
+    fn test_range_len() {
+        assert_eq!(range_len(1, 1_000_000, 1).unwrap(), 5_888_896);
+        assert_eq!(range_len(-10, 10, 1).unwrap(), 3 + 9 * 2 + 1 + 9 + 2);
+        assert_eq!(range_len(5, 1, 1).unwrap(), 0);
+        assert_eq!(range_len(i64::MIN, i64::MAX, i64::MAX).unwrap(), 20 + 2 + 19);
+        assert!(range_len(1, 5, 0).is_err());
+    }
+}
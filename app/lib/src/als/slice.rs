@@ -0,0 +1,360 @@
+//! Slicing streams by rows.
+//!
+//! [`ColumnStream::slice`] keeps the rows of a range without expanding
+//! anything: operators inside the range are kept as they are, and the ones
+//! crossing its ends are cut down to the rows inside, so a range starts at
+//! a later value, a repeat repeats fewer times, and a toggle or calendar
+//! cycle starts further into its period. Block splitting, `head`, `tail` and
+//! pagination all stay in compressed space, and [`ColumnStream::value_at`]
+//! reads a single cell by cutting its operator down to one row.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::document::{AlsDocument, ColumnStream};
+use super::operator::AlsOperator;
+use super::scale::repeat;
+use crate::error::Result;
+
+impl<'a> ColumnStream<'a> {
+    /// Get a stream holding only the rows in `range`.
+    ///
+    /// Rows past the end of the stream are ignored. A stream with column
+    /// references must be sliced along with the columns it refers to, as
+    /// [`AlsDocument::slice_rows`] does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let doc = parser.parse("#id #level\n1>100|info*50 warn*50").unwrap();
+    ///
+    /// let page = doc.streams[1].slice(45..55);
+    /// assert_eq!(page.operator_count(), 2);
+    /// assert_eq!(page.expand(None).unwrap()[4..6], ["info", "warn"]);
+    /// ```
+    pub fn slice(&self, range: Range<usize>) -> ColumnStream<'a> {
+        let end = range.end.min(self.expanded_count());
+        let operators = if range.start < end {
+            slice_operators(&self.operators, range.start, end)
+        } else {
+            Vec::new()
+        };
+        ColumnStream {
+            operators,
+            format_indicator: self.format_indicator,
+        }
+    }
+
+    /// Get the value of one row without expanding the rest of the stream.
+    ///
+    /// Operators before the row are skipped by their lengths and the one
+    /// holding it is cut down to that row as by [`slice`](Self::slice), so
+    /// the cost grows with the number of operators rather than rows.
+    ///
+    /// Returns `Ok(None)` for a row past the end of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidDictRef` if the row refers past the end of
+    /// `dictionary`, and `AlsError::InvalidColumnRef` if it comes from a
+    /// column reference, which needs the column it refers to.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let doc = AlsParser::new().parse("#id #level\n1>1000000|info*500000 warn*500000").unwrap();
+    /// assert_eq!(doc.streams[0].value_at(765_432, None).unwrap().as_deref(), Some("765433"));
+    /// assert_eq!(doc.streams[1].value_at(765_432, None).unwrap().as_deref(), Some("warn"));
+    /// assert_eq!(doc.streams[1].value_at(1_000_000, None).unwrap(), None);
+    /// ```
+    pub fn value_at(&self, row: usize, dictionary: Option<&[String]>) -> Result<Option<String>> {
+        if row >= self.expanded_count() {
+            return Ok(None);
+        }
+        let mut values = Vec::with_capacity(1);
+        for op in slice_operators(&self.operators, row, row + 1) {
+            values.extend(op.expand(dictionary)?);
+        }
+        Ok(values.pop())
+    }
+}
+
+impl<'a> AlsDocument<'a> {
+    /// Get a document holding only the rows in `range`.
+    ///
+    /// Dictionaries, redactions, quantizations, truncations, foreign keys
+    /// and flags carry over; indexes, Bloom filters, zone maps, the block
+    /// size and a delta's edit script describe the old rows and are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsParser;
+    ///
+    /// let parser = AlsParser::new();
+    /// let doc = parser.parse("#id #day\n1>1000|&dow+0*1000").unwrap();
+    ///
+    /// let tail = doc.slice_rows(997..1000);
+    /// let csv = parser.to_csv(&als_compression::AlsSerializer::new().serialize(&tail)).unwrap();
+    /// assert_eq!(csv, "id,day\n998,Thu\n999,Fri\n1000,Sat\n");
+    /// ```
+    pub fn slice_rows(&self, range: Range<usize>) -> AlsDocument<'a> {
+        let mut sliced = AlsDocument::with_schema(self.schema.clone());
+        sliced.version = self.version;
+        sliced.dictionaries = self.dictionaries.clone();
+        sliced.format_indicator = self.format_indicator;
+        sliced.nfc_normalized = self.nfc_normalized;
+        sliced.redactions = self.redactions.clone();
+        sliced.quantizations = self.quantizations.clone();
+        sliced.truncations = self.truncations.clone();
+        sliced.foreign_keys = self.foreign_keys.clone();
+        sliced.streams = self.streams.iter().map(|stream| stream.slice(range.clone())).collect();
+        sliced
+    }
+}
+
+/// Get the operators producing rows `start..end` of a sequence of operators.
+///
+/// `start..end` must be a non-empty range within the expanded operators.
+pub(crate) fn slice_operators<'a>(operators: &[AlsOperator<'a>], start: usize, end: usize) -> Vec<AlsOperator<'a>> {
+    let mut sliced = Vec::new();
+    let mut position = 0;
+    for op in operators {
+        let len = op.expanded_count();
+        let (from, to) = (start.max(position), end.min(position + len));
+        if from < to {
+            sliced.extend(slice_operator(op, from - position, to - position));
+        }
+        position += len;
+        if position >= end {
+            break;
+        }
+    }
+    sliced
+}
+
+/// Get the operators producing rows `start..end` of one operator.
+///
+/// `start..end` must be a non-empty range within the expanded operator.
+fn slice_operator<'a>(op: &AlsOperator<'a>, start: usize, end: usize) -> Vec<AlsOperator<'a>> {
+    let len = end - start;
+    if start == 0 && len == op.expanded_count() {
+        return vec![op.clone()];
+    }
+
+    match op {
+        AlsOperator::Range { start: first, step, .. } => {
+            let value = |row: usize| AlsOperator::range_value_at(*first, *step, row);
+            if len == 1 {
+                vec![AlsOperator::Raw(Cow::Owned(value(start).to_string()))]
+            } else {
+                vec![AlsOperator::Range {
+                    start: value(start),
+                    end: value(end - 1),
+                    step: *step,
+                }]
+            }
+        }
+        AlsOperator::Multiply { value, .. } => {
+            let period = value.expanded_count();
+            let (first, last) = (start / period, (end - 1) / period);
+            if first == last {
+                return slice_operator(value, start % period, end - first * period);
+            }
+
+            let mut sliced = Vec::new();
+            let mut whole = first..last + 1;
+            if !start.is_multiple_of(period) {
+                sliced.extend(slice_operator(value, start % period, period));
+                whole.start += 1;
+            }
+            let tail = end - last * period;
+            if tail != period {
+                whole.end -= 1;
+            }
+            if !whole.is_empty() {
+                sliced.push(repeat((**value).clone(), whole.len()));
+            }
+            if tail != period {
+                sliced.extend(slice_operator(value, 0, tail));
+            }
+            sliced
+        }
+        AlsOperator::Toggle { values, .. } if !values.is_empty() => {
+            let first = start % values.len();
+            if len == 1 {
+                vec![AlsOperator::Raw(Cow::Owned(values[first].clone()))]
+            } else {
+                let mut values = values.clone();
+                values.rotate_left(first);
+                vec![AlsOperator::Toggle { values, count: len }]
+            }
+        }
+        AlsOperator::ColumnRef { column, mapping, .. } => vec![AlsOperator::ColumnRef {
+            column: *column,
+            mapping: mapping.clone(),
+            count: len,
+        }],
+        AlsOperator::DateRange {
+            start: first,
+            step,
+            format,
+            ..
+        } => vec![AlsOperator::DateRange {
+            start: first.saturating_add((start as i64).saturating_mul(*step)),
+            step: *step,
+            count: len,
+            format: format.clone(),
+        }],
+        AlsOperator::Cycle { cycle, offset, run, .. } if *run > 0 => {
+            // The offset may start part way through a run, so the slice
+            // only moves it on, wrapping at the period without overflowing
+            let period = cycle.values().len().saturating_mul(*run);
+            let (offset, start) = (offset % period, start % period);
+            let offset = if start >= period - offset { start - (period - offset) } else { offset + start };
+            vec![AlsOperator::Cycle {
+                cycle: *cycle,
+                offset,
+                run: *run,
+                count: len,
+            }]
+        }
+        AlsOperator::Transform { value, transform } => slice_operator(value, start, end)
+            .into_iter()
+            .map(|value| AlsOperator::Transform {
+                value: Box::new(value),
+                transform: transform.clone(),
+            })
+            .collect(),
+        AlsOperator::BoolRuns { labels, runs } => {
+            let mut position = 0;
+            let overlaps: Vec<usize> = runs
+                .iter()
+                .map(|&run| {
+                    let overlap = end.min(position + run).saturating_sub(start.max(position));
+                    position += run;
+                    overlap
+                })
+                .collect();
+            // Keep empty runs between the first and last, so labels still
+            // alternate, and start with an empty run to begin on the second
+            let first = overlaps.iter().position(|&run| run > 0).unwrap_or(0);
+            let last = overlaps.iter().rposition(|&run| run > 0).unwrap_or(0);
+            let mut sliced = if first % 2 == 1 { vec![0] } else { Vec::new() };
+            sliced.extend_from_slice(&overlaps[first..=last]);
+            vec![AlsOperator::BoolRuns {
+                labels: labels.clone(),
+                runs: sliced,
+            }]
+        }
+        AlsOperator::Split { separator, parts } => vec![AlsOperator::Split {
+            separator: *separator,
+            parts: parts.iter().map(|part| slice_operators(part, start, end)).collect(),
+        }],
+        AlsOperator::FloatDelta { places, start: first, deltas } => {
+            let mut value = *first;
+            for (i, delta) in deltas[..start].iter().enumerate() {
+                let Some(next) = value.checked_add(*delta) else {
+                    // Start at the overflowing delta, so the slice fails to
+                    // expand with the same error as the whole operator
+                    return vec![AlsOperator::float_delta(*places, value, deltas[i..i + len.max(2) - 1].to_vec())];
+                };
+                value = next;
+            }
+            vec![AlsOperator::float_delta(*places, value, deltas[start..end - 1].to_vec())]
+        }
+        // Fails to expand either way, so only the count matters
+        AlsOperator::Toggle { .. } | AlsOperator::Cycle { .. } => vec![with_count(op, len)],
+        AlsOperator::Raw(_) | AlsOperator::DictRef(_) => vec![op.clone()],
+    }
+}
+
+/// Copy a toggle or cycle with a new count.
+fn with_count<'a>(op: &AlsOperator<'a>, len: usize) -> AlsOperator<'a> {
+    let mut op = op.clone();
+    if let AlsOperator::Toggle { count, .. } | AlsOperator::Cycle { count, .. } = &mut op {
+        *count = len;
+    }
+    op
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::{AlsParser, AlsSerializer};
+
+    /// Check every slice of every column against slicing the expanded values.
+    fn assert_slices(als: &str) {
+        let parser = AlsParser::new();
+        let doc = parser.parse(als).unwrap();
+        let columns = parser.expand_columns(&doc).unwrap();
+        let rows = doc.row_count();
+        for start in 0..=rows {
+            for end in start..=rows + 1 {
+                let sliced = doc.slice_rows(start..end);
+                let reparsed = parser.parse(&AlsSerializer::new().serialize(&sliced)).unwrap();
+                let expected: Vec<Vec<String>> =
+                    columns.iter().map(|column| column[start..end.min(rows)].to_vec()).collect();
+                assert_eq!(parser.expand_columns(&reparsed).unwrap(), expected, "{:?} of {:?}", start..end, als);
+            }
+        }
+
+        let dictionary = doc.default_dictionary().map(Vec::as_slice);
+        for (stream, column) in doc.streams.iter().zip(&columns).filter(|(stream, _)| !stream.has_column_refs()) {
+            for (row, value) in column.iter().enumerate() {
+                assert_eq!(stream.value_at(row, dictionary).unwrap().as_ref(), Some(value), "row {} of {:?}", row, als);
+            }
+            assert_eq!(stream.value_at(rows, dictionary).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_slice_operators() {
+        assert_slices("#id #n #flag #tag\n1>5 10>2:-4|x*3 y~z*5|T~F~N*8|a b c d e f g h");
+        assert_slices("#day #mon #ok\n&dow+5:3*8 &mon*2|?T~F:2.3.0.4 1|?yes~no:0.3.6 no");
+        assert_slices("$default:p|q\n#m #t\n_0*3 _1*2 _0|^ID{U3}(1>2)*2 ^{L}(A~B*2)");
+    }
+
+    #[test]
+    fn test_slice_derived_columns() {
+        assert_slices("#ts #side #sign\n%{%Y-%m-%d}1704067200+86400*6|buy sell*2 buy*3|@1(buy:+1 sell:-1)*6");
+        assert_slices("#path\n</2(a*3 b*2)(x~y*5)");
+        assert_slices("#temp #load\n%.2:2153+3-1+0 %.1:5-10|%.3:-125+65+0+0+0+1");
+    }
+
+    #[test]
+    fn test_slice_keeps_operators() {
+        let doc = AlsParser::new().parse("#id\n1>1000000").unwrap();
+        let sliced = doc.streams[0].slice(999_990..2_000_000);
+        assert_eq!(sliced.operators, vec![AlsOperator::Range { start: 999_991, end: 1_000_000, step: 1 }]);
+        assert!(doc.streams[0].slice(5..5).is_empty());
+        assert!(doc.streams[0].slice(2_000_000..3_000_000).is_empty());
+    }
+
+    #[test]
+    fn test_value_at_errors() {
+        let doc = AlsParser::new().parse("#side #sign\nbuy sell|@0(buy:+1 sell:-1)*2").unwrap();
+        assert!(matches!(doc.streams[1].value_at(1, None), Err(crate::AlsError::InvalidColumnRef { .. })));
+        let doc = AlsParser::new().parse("$default:a\n#x\n_0 _1").unwrap();
+        assert_eq!(doc.streams[0].value_at(0, doc.default_dictionary().map(Vec::as_slice)).unwrap().as_deref(), Some("a"));
+        assert!(matches!(doc.streams[0].value_at(1, None), Err(crate::AlsError::InvalidDictRef { .. })));
+    }
+
+    #[test]
+    fn test_slice_float_delta_overflow() {
+        let stream = ColumnStream::from_operators(vec![AlsOperator::float_delta(1, i64::MAX - 1, vec![1, 1, -5, 2])]);
+        let expanded = stream.expand(None).unwrap_err().to_string();
+        for (start, end) in [(2, 3), (2, 5), (3, 4), (4, 5)] {
+            let sliced = stream.slice(start..end);
+            assert_eq!(sliced.expand(None).unwrap_err().to_string(), expanded, "rows {}..{}", start, end);
+        }
+        assert_eq!(stream.value_at(3, None).unwrap_err().to_string(), expanded);
+        let head = AlsOperator::float_delta(1, i64::MAX - 1, vec![1]);
+        assert_eq!(stream.slice(0..2).expand(None).unwrap(), head.expand(None).unwrap());
+    }
+}
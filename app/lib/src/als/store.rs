@@ -0,0 +1,185 @@
+//! Store frames for incompressible input.
+//!
+//! When ALS output would be bigger than its input, the compressor can emit
+//! the input unchanged behind a one-line header instead:
+//!
+//! ```text
+//! !store csv
+//! id,name
+//! 1,Alice
+//! ```
+//!
+//! JSON input records the pointer to its records, if any, after the format
+//! (`!store json /data/items`). The parser reads store frames like any other
+//! document, so callers don't need to know which kind they got.
+
+use crate::convert::csv::parse_csv;
+use crate::convert::json::parse_json_with_config;
+use crate::config::JsonConfig;
+use crate::convert::TabularData;
+use crate::error::{AlsError, Result};
+
+/// Prefix of the store frame header.
+pub const STORE_PREFIX: &str = "!store";
+
+/// Format of the input held by a store frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StoredFormat {
+    /// CSV text.
+    Csv,
+    /// JSON text.
+    Json,
+}
+
+impl StoredFormat {
+    /// Get the name used for this format in the frame header.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoredFormat::Csv => "csv",
+            StoredFormat::Json => "json",
+        }
+    }
+
+    /// Look up a format by its header name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [StoredFormat::Csv, StoredFormat::Json]
+            .into_iter()
+            .find(|format| format.name() == name)
+    }
+}
+
+/// Input stored verbatim instead of being compressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreFrame<'a> {
+    /// Format of the stored input.
+    pub format: StoredFormat,
+    /// JSON Pointer to the records within stored JSON input.
+    pub records_path: Option<String>,
+    /// The original input.
+    pub payload: &'a str,
+}
+
+impl<'a> StoreFrame<'a> {
+    /// Create a store frame for the given input.
+    pub fn new(format: StoredFormat, payload: &'a str) -> Self {
+        Self {
+            format,
+            records_path: None,
+            payload,
+        }
+    }
+
+    /// Set the JSON Pointer to the records within the stored input.
+    pub fn with_records_path(mut self, path: Option<String>) -> Self {
+        self.records_path = path;
+        self
+    }
+
+    /// Check if text is a store frame.
+    pub fn is_store_frame(input: &str) -> bool {
+        input
+            .strip_prefix(STORE_PREFIX)
+            .is_some_and(|rest| rest.starts_with(' '))
+    }
+
+    /// Read a store frame.
+    ///
+    /// Returns `Ok(None)` if the input is not a store frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::AlsSyntaxError` if the frame header is malformed.
+    pub fn parse(input: &'a str) -> Result<Option<Self>> {
+        if !Self::is_store_frame(input) {
+            return Ok(None);
+        }
+
+        let (header, payload) = input.split_once('\n').unwrap_or((input, ""));
+        let header = header[STORE_PREFIX.len()..].trim_start().trim_end_matches('\r');
+        let (format, records_path) = match header.split_once(' ') {
+            Some((format, path)) => (format, Some(path.to_string())),
+            None => (header, None),
+        };
+        let format = StoredFormat::from_name(format).ok_or_else(|| AlsError::AlsSyntaxError {
+            position: STORE_PREFIX.len(),
+            message: format!("Unknown store frame format: {}", format),
+        })?;
+
+        Ok(Some(Self {
+            format,
+            records_path,
+            payload,
+        }))
+    }
+
+    /// Serialize the frame: the header line followed by the input.
+    pub fn serialize(&self) -> String {
+        let mut output = String::with_capacity(self.header_len() + self.payload.len());
+        output.push_str(STORE_PREFIX);
+        output.push(' ');
+        output.push_str(self.format.name());
+        if let Some(path) = &self.records_path {
+            output.push(' ');
+            output.push_str(path);
+        }
+        output.push('\n');
+        output.push_str(self.payload);
+        output
+    }
+
+    /// Length of the header line, newline included.
+    pub fn header_len(&self) -> usize {
+        STORE_PREFIX.len()
+            + 1
+            + self.format.name().len()
+            + self.records_path.as_ref().map_or(0, |path| path.len() + 1)
+            + 1
+    }
+
+    /// Parse the stored input into tabular data.
+    pub fn to_tabular(&self) -> Result<TabularData<'static>> {
+        match self.format {
+            StoredFormat::Csv => parse_csv(self.payload),
+            StoredFormat::Json => {
+                let config = JsonConfig {
+                    records_path: self.records_path.clone(),
+                    ..JsonConfig::default()
+                };
+                parse_json_with_config(self.payload, &config)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_frame_roundtrip() {
+        let frame = StoreFrame::new(StoredFormat::Csv, "id\n1\n2");
+        let text = frame.serialize();
+        assert_eq!(text, "!store csv\nid\n1\n2");
+        assert_eq!(text.len(), frame.header_len() + frame.payload.len());
+        assert_eq!(StoreFrame::parse(&text).unwrap(), Some(frame));
+    }
+
+    #[test]
+    fn test_store_frame_records_path() {
+        let json = r#"{"data": [{"id": 1}, {"id": 2}]}"#;
+        let frame = StoreFrame::new(StoredFormat::Json, json).with_records_path(Some("/data".to_string()));
+        let text = frame.serialize();
+        assert!(text.starts_with("!store json /data\n"));
+
+        let parsed = StoreFrame::parse(&text).unwrap().unwrap();
+        assert_eq!(parsed.records_path.as_deref(), Some("/data"));
+        assert_eq!(parsed.to_tabular().unwrap().row_count, 2);
+    }
+
+    #[test]
+    fn test_store_frame_detection() {
+        assert!(StoreFrame::parse("!v1\n#id\n1>3").unwrap().is_none());
+        assert!(StoreFrame::parse("!storage\n").unwrap().is_none());
+        assert!(StoreFrame::parse("!store xml\n<a/>").is_err());
+    }
+}
@@ -5,27 +5,39 @@
 //!
 //! # Token Types
 //!
-//! - Version prefix: `!v1` or `!ctx`
+//! - Version prefix: `!v1`, `!v2 +blocks +split` or `!ctx`
 //! - Dictionary header: `$name:val1|val2`
 //! - Schema prefix: `#column_name`
 //! - Operators: `>`, `*`, `~`
 //! - Column separator: `|`
 //! - Dictionary reference: `_0`, `_1`, etc.
+//! - Column reference: `@0`, `@1(src:dst ...)`, etc.
+//! - Date range: `%{%Y-%m-%d}1704067200+86400`
+//! - Calendar cycle: `&dow+0`, `&mon+3:24`, etc.
+//! - Transform head: `^USER{3}`, `^{U}`, etc.
+//! - Boolean runs: `?true~false:3.1.2`
 //! - Numbers and raw values
 
+use super::cycle::CalendarCycle;
+use super::dict_ref;
+use super::float::MAX_FLOAT_DELTA_PLACES;
+use super::transform::{CaseTransform, ValueTransform, GROUP_SEPARATORS, MAX_TRANSFORM_WIDTH};
 use crate::error::{AlsError, Result};
 
 /// Token types produced by the ALS tokenizer.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    /// Version indicator: `!v1` (ALS) or `!ctx` (CTX fallback)
-    Version(VersionType),
-    /// Dictionary header: `$name:val1|val2|val3`
+    /// Version indicator: `!v1` (ALS) or `!ctx` (CTX fallback), with the
+    /// feature flags following an ALS version (`!v2 +blocks +split`)
+    Version(VersionType, Vec<String>),
+    /// Dictionary header: `$name:val1|val2|val3` or front-coded `$name~:val1|N~suffix`
     DictionaryHeader {
         /// Dictionary name
         name: String,
-        /// Dictionary values
+        /// Dictionary values, still front-coded if `front_coded` is set
         values: Vec<String>,
+        /// Whether entries after the first are written as `N~suffix`
+        front_coded: bool,
     },
     /// Schema column: `#column_name`
     SchemaColumn(String),
@@ -33,6 +45,13 @@ pub enum Token {
     Integer(i64),
     /// Float literal
     Float(f64),
+    /// Float literal in scientific notation, such as `-2.5e-3`
+    ScientificFloat {
+        /// Parsed value
+        value: f64,
+        /// Literal as written, so values expand to their original spelling
+        text: String,
+    },
     /// Raw string value (possibly escaped)
     RawValue(String),
     /// Range operator: `>`
@@ -44,7 +63,60 @@ pub enum Token {
     /// Column separator: `|`
     ColumnSeparator,
     /// Dictionary reference: `_0`, `_1`, etc.
+    ///
+    /// Relative references (`_=`, `_+`, `_+N`) are resolved against the
+    /// previous reference in the same stream and also produce this token.
     DictRef(usize),
+    /// Column reference with optional value mapping: `@0` or `@0(a:b c:d)`
+    ColumnRef {
+        /// Referenced column index
+        column: usize,
+        /// Source value to output value translations
+        mapping: Vec<(String, String)>,
+    },
+    /// Date range head: `%{format}start+step` or `%{format}start-step`
+    DateRange {
+        /// Timestamp format, with `\}`, `\\`, `\n` and `\r` unescaped
+        format: String,
+        /// First timestamp in seconds since the Unix epoch
+        start: i64,
+        /// Seconds between consecutive timestamps
+        step: i64,
+    },
+    /// Calendar cycle head: `&name+offset` or `&name+offset:run`
+    Cycle {
+        /// Built-in cycle
+        cycle: CalendarCycle,
+        /// Position of the first element, counting each label `run` times
+        offset: usize,
+        /// Number of consecutive rows sharing each label
+        run: usize,
+    },
+    /// Float deltas: `%.places:start+d1-d2`
+    FloatDelta {
+        /// Digits after the decimal point
+        places: u8,
+        /// First value, scaled
+        start: i64,
+        /// Differences between consecutive scaled values
+        deltas: Vec<i64>,
+    },
+    /// Split head: `<sepN` or `<N`, followed by `N` groups of operators
+    Split {
+        /// Separator placed between segments, `None` to concatenate them
+        separator: Option<char>,
+        /// Number of parts that follow
+        parts: usize,
+    },
+    /// Transform head: `^prefix{spec}suffix`, followed by a group
+    Transform(ValueTransform),
+    /// Boolean runs: `?a~b:r1.r2.r3`
+    BoolRuns {
+        /// The two labels, in order of first appearance
+        labels: [String; 2],
+        /// Lengths of the alternating runs
+        runs: Vec<usize>,
+    },
     /// Step separator in ranges: `:`
     StepSeparator,
     /// Open parenthesis for grouping: `(`
@@ -73,6 +145,8 @@ pub struct Tokenizer<'a> {
     position: usize,
     /// Whether we're in the header section (before streams)
     in_header: bool,
+    /// Index of the last dictionary reference in the current stream
+    last_dict_ref: Option<usize>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -83,6 +157,7 @@ impl<'a> Tokenizer<'a> {
             chars: input.char_indices().peekable(),
             position: 0,
             in_header: true,
+            last_dict_ref: None,
         }
     }
 
@@ -137,7 +212,18 @@ impl<'a> Tokenizer<'a> {
                     Some('_') => result.push('_'),
                     Some('#') => result.push('#'),
                     Some('$') => result.push('$'),
+                    Some('@') => result.push('@'),
+                    Some('%') => result.push('%'),
+                    Some('&') => result.push('&'),
+                    Some('^') => result.push('^'),
+                    Some('?') => result.push('?'),
+                    Some('<') => result.push('<'),
+                    Some('!') => result.push('!'),
                     Some(':') => result.push(':'),
+                    Some('(') => result.push('('),
+                    Some(')') => result.push(')'),
+                    Some('{') => result.push('{'),
+                    Some('}') => result.push('}'),
                     Some('\\') => result.push('\\'),
                     Some('n') => result.push('\n'),
                     Some('t') => result.push('\t'),
@@ -208,7 +294,8 @@ impl<'a> Tokenizer<'a> {
                     num_str.push(c);
                     self.next_char();
                 }
-                'e' | 'E' if !has_exp => {
+                // An exponent needs a mantissa, so `-e3` stays a raw value
+                'e' | 'E' if !has_exp && num_str.bytes().any(|b| b.is_ascii_digit()) => {
                     // Only treat as exponent if followed by digit or sign+digit
                     // We need to look ahead without consuming
                     let chars_vec: Vec<char> = self.input[self.position..].chars().take(3).collect();
@@ -244,26 +331,40 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        if has_dot || has_exp {
-            num_str
+        // Digits followed by anything but a delimiter start a raw value,
+        // such as a date or a version
+        if self.peek_char().is_some_and(|c| !matches!(c, ' ' | '\t' | '\n' | '\r' | '|' | '>' | '*' | '~' | ':' | '(' | ')')) {
+            let rest = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|', '>', '*', '~', ':', '(', ')'])?;
+            return Ok(Token::RawValue(num_str + &rest));
+        }
+
+        if has_exp {
+            return num_str
                 .parse::<f64>()
-                .map(Token::Float)
+                .map(|value| Token::ScientificFloat { value, text: num_str.clone() })
                 .map_err(|_| AlsError::AlsSyntaxError {
                     position: start_pos,
                     message: format!("Invalid float: {}", num_str),
-                })
+                });
+        }
+
+        // Numbers that would not print back the same, such as `007`, `1.`
+        // or a lone `-`, are raw values so their text survives
+        if has_dot {
+            match num_str.parse::<f64>() {
+                Ok(value) if value.to_string() == num_str => Ok(Token::Float(value)),
+                _ => Ok(Token::RawValue(num_str)),
+            }
         } else {
-            num_str
-                .parse::<i64>()
-                .map(Token::Integer)
-                .map_err(|_| AlsError::AlsSyntaxError {
-                    position: start_pos,
-                    message: format!("Invalid integer: {}", num_str),
-                })
+            match num_str.parse::<i64>() {
+                Ok(value) if value.to_string() == num_str => Ok(Token::Integer(value)),
+                _ => Ok(Token::RawValue(num_str)),
+            }
         }
     }
 
-    /// Parse a version prefix (!v1 or !ctx).
+    /// Parse a version prefix (!v1 or !ctx) and any feature flags after an
+    /// ALS version.
     fn parse_version(&mut self) -> Result<Token> {
         let start_pos = self.position;
         
@@ -279,7 +380,8 @@ impl<'a> Tokenizer<'a> {
         }
 
         if version_str == "ctx" {
-            Ok(Token::Version(VersionType::Ctx))
+            // Raw values of a CTX column may start with `+`, so CTX takes no flags
+            Ok(Token::Version(VersionType::Ctx, Vec::new()))
         } else if version_str.starts_with('v') {
             let version_num = version_str[1..]
                 .parse::<u8>()
@@ -287,7 +389,28 @@ impl<'a> Tokenizer<'a> {
                     position: start_pos,
                     message: format!("Invalid version number: {}", version_str),
                 })?;
-            Ok(Token::Version(VersionType::Als(version_num)))
+            let mut features = Vec::new();
+            loop {
+                self.skip_whitespace();
+                if self.peek_char() != Some('+') {
+                    break;
+                }
+                self.next_char(); // consume '+'
+                let flag_pos = self.position;
+                let mut feature = String::new();
+                while let Some(c) = self.peek_char().filter(|c| c.is_ascii_alphanumeric() || *c == '-') {
+                    feature.push(c);
+                    self.next_char();
+                }
+                if feature.is_empty() {
+                    return Err(AlsError::AlsSyntaxError {
+                        position: flag_pos,
+                        message: "Expected a feature name after '+'".to_string(),
+                    });
+                }
+                features.push(feature);
+            }
+            Ok(Token::Version(VersionType::Als(version_num), features))
         } else {
             Err(AlsError::AlsSyntaxError {
                 position: start_pos,
@@ -299,6 +422,11 @@ impl<'a> Tokenizer<'a> {
     /// Parse a dictionary header ($name:val1|val2).
     fn parse_dictionary_header(&mut self) -> Result<Token> {
         let name = self.read_identifier();
+
+        let front_coded = self.peek_char() == Some('~');
+        if front_coded {
+            self.next_char(); // consume '~'
+        }
         
         // Expect colon
         if self.peek_char() != Some(':') {
@@ -322,24 +450,86 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        Ok(Token::DictionaryHeader { name, values })
+        Ok(Token::DictionaryHeader {
+            name,
+            values,
+            front_coded,
+        })
     }
 
     /// Parse a schema column (#column_name).
     fn parse_schema_column(&mut self) -> Result<Token> {
+        // Names that are not simple identifiers, such as `a-b` or ones with
+        // combining marks, continue as an escaped value
         let name = self.read_identifier();
-        if name.is_empty() {
-            // Read as escaped value if not a simple identifier
-            let value = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|'])?;
-            Ok(Token::SchemaColumn(value))
-        } else {
-            Ok(Token::SchemaColumn(name))
-        }
+        let rest = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|'])?;
+        Ok(Token::SchemaColumn(name + &rest))
     }
 
-    /// Parse a dictionary reference (_0, _1, etc.).
+    /// Parse a dictionary reference (_0, _1, _aZ3, etc.).
+    ///
+    /// Indices past 9 may be written in base-62 (see the `dict_ref` module).
+    ///
+    /// Also accepts references relative to the previous one in the stream:
+    /// `_=` repeats it, `_+` increments it by one and `_+N` by `N`.
     fn parse_dict_ref(&mut self) -> Result<Token> {
         let start_pos = self.position;
+
+        let delta = match self.peek_char() {
+            Some('=') => {
+                self.next_char();
+                Some(0)
+            }
+            Some('+') => {
+                self.next_char();
+                let delta = match self.peek_char() {
+                    Some(c) if c.is_ascii_digit() => self.read_unsigned(start_pos)?,
+                    _ => 1,
+                };
+                Some(delta)
+            }
+            _ => None,
+        };
+
+        if let Some(delta) = delta {
+            let index = self
+                .last_dict_ref
+                .and_then(|prev| prev.checked_add(delta))
+                .ok_or_else(|| AlsError::AlsSyntaxError {
+                    position: start_pos,
+                    message: "Relative dictionary reference without a preceding reference".to_string(),
+                })?;
+            self.last_dict_ref = Some(index);
+            return Ok(Token::DictRef(index));
+        }
+
+        let mut body = String::new();
+
+        while let Some(c) = self.peek_char() {
+            if c.is_ascii_alphanumeric() {
+                body.push(c);
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+
+        if body.is_empty() {
+            // Not a dict ref, treat underscore as part of a raw value
+            return Ok(Token::RawValue("_".to_string()));
+        }
+
+        let index = dict_ref::decode(&body).ok_or_else(|| AlsError::AlsSyntaxError {
+            position: start_pos,
+            message: format!("Invalid dictionary reference index: {}", body),
+        })?;
+        self.last_dict_ref = Some(index);
+        Ok(Token::DictRef(index))
+    }
+
+    /// Parse a column reference (@0, @1(a:b c:d), etc.).
+    fn parse_column_ref(&mut self) -> Result<Token> {
+        let start_pos = self.position;
         let mut num_str = String::new();
 
         while let Some(c) = self.peek_char() {
@@ -352,26 +542,359 @@ impl<'a> Tokenizer<'a> {
         }
 
         if num_str.is_empty() {
-            // Not a dict ref, treat underscore as part of a raw value
-            return Ok(Token::RawValue("_".to_string()));
+            // Not a column ref, treat @ as part of a raw value
+            let rest = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|', '>', '*', '~', ':', '(', ')'])?;
+            return Ok(Token::RawValue(format!("@{}", rest)));
+        }
+
+        let column = num_str.parse::<usize>().map_err(|_| AlsError::AlsSyntaxError {
+            position: start_pos,
+            message: format!("Invalid column reference index: {}", num_str),
+        })?;
+
+        // The mapping must follow the index directly
+        let mut mapping = Vec::new();
+        if self.peek_char() == Some('(') {
+            self.next_char(); // consume '('
+            loop {
+                self.skip_whitespace();
+                match self.peek_char() {
+                    Some(')') => {
+                        self.next_char();
+                        break;
+                    }
+                    None | Some('\n') | Some('|') => {
+                        return Err(AlsError::AlsSyntaxError {
+                            position: self.position,
+                            message: "Unterminated column reference mapping".to_string(),
+                        });
+                    }
+                    _ => {}
+                }
+
+                let from = self.read_escaped_value(&[':', ' ', '\t', '\n', '\r', '|', ')'])?;
+                if self.peek_char() != Some(':') {
+                    return Err(AlsError::AlsSyntaxError {
+                        position: self.position,
+                        message: "Expected ':' in column reference mapping".to_string(),
+                    });
+                }
+                self.next_char(); // consume ':'
+                let to = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|', ')'])?;
+                mapping.push((from, to));
+            }
+        }
+
+        Ok(Token::ColumnRef { column, mapping })
+    }
+
+    /// Parse a date range head (%{format}start+step) or float deltas
+    /// (%.places:start+d1-d2), or `None` if the text after `%` is neither.
+    fn parse_date_range(&mut self) -> Result<Option<Token>> {
+        let start_pos = self.position;
+
+        if self.peek_char() == Some('.') {
+            self.next_char(); // consume '.'
+            return self.parse_float_delta(start_pos);
+        }
+        if self.next_char() != Some('{') {
+            return Ok(None);
+        }
+
+        let mut format = String::new();
+        loop {
+            match self.next_char() {
+                Some('}') => break,
+                Some('\\') => match self.next_char() {
+                    Some(c @ ('}' | '\\')) => format.push(c),
+                    Some('n') => format.push('\n'),
+                    Some('r') => format.push('\r'),
+                    _ => return Ok(None),
+                },
+                Some('\n') | None => return Ok(None),
+                Some(c) => format.push(c),
+            }
+        }
+
+        let Ok(start) = self.read_signed_integer(false) else {
+            return Ok(None);
+        };
+        let Ok(step) = self.read_signed_integer(true) else {
+            return Ok(None);
+        };
+        // A date range always carries its row count
+        if self.peek_char() != Some('*') {
+            return Ok(None);
+        }
+
+        Ok(Some(Token::DateRange { format, start, step }))
+    }
+
+    /// Parse float deltas after their `%.`: the places, the first scaled
+    /// value and the signed differences.
+    fn parse_float_delta(&mut self, start_pos: usize) -> Result<Option<Token>> {
+        let Ok(places) = self.read_unsigned(start_pos) else {
+            return Ok(None);
+        };
+        if self.next_char() != Some(':') {
+            return Ok(None);
+        }
+
+        let Ok(start) = self.read_signed_integer(false) else {
+            return Ok(None);
+        };
+        let mut deltas = Vec::new();
+        while let Some('+' | '-') = self.peek_char() {
+            let Ok(delta) = self.read_signed_integer(true) else {
+                return Ok(None);
+            };
+            deltas.push(delta);
         }
 
-        num_str
-            .parse::<usize>()
-            .map(Token::DictRef)
-            .map_err(|_| AlsError::AlsSyntaxError {
+        let places = u8::try_from(places)
+            .ok()
+            .filter(|&places| (1..=MAX_FLOAT_DELTA_PLACES).contains(&places))
+            .ok_or_else(|| AlsError::AlsSyntaxError {
                 position: start_pos,
-                message: format!("Invalid dictionary reference index: {}", num_str),
-            })
+                message: format!("Float delta needs 1 to {} places, found {}", MAX_FLOAT_DELTA_PLACES, places),
+            })?;
+
+        Ok(Some(Token::FloatDelta { places, start, deltas }))
+    }
+
+    /// Parse a calendar cycle head (&name+offset or &name+offset:run), or
+    /// `None` if the text after `&` is not one.
+    fn parse_cycle(&mut self) -> Result<Option<Token>> {
+        let start_pos = self.position;
+        let name = self.read_identifier();
+
+        let Some(cycle) = CalendarCycle::from_name(&name) else {
+            return Ok(None);
+        };
+
+        let offset = if self.peek_char() == Some('+') {
+            self.next_char(); // consume '+'
+            let Ok(offset) = self.read_unsigned(start_pos) else {
+                return Ok(None);
+            };
+            offset
+        } else {
+            0
+        };
+
+        let run = if self.peek_char() == Some(':') {
+            self.next_char(); // consume ':'
+            let Ok(run) = self.read_unsigned(start_pos) else {
+                return Ok(None);
+            };
+            run
+        } else {
+            1
+        };
+
+        // A cycle always carries its row count, so `&dow` alone is a value
+        if self.peek_char() != Some('*') {
+            return Ok(None);
+        }
+
+        Ok(Some(Token::Cycle { cycle, offset, run }))
+    }
+
+    /// Parse a transform head (^prefix{spec}suffix), or `None` if the text
+    /// after `^` is not one.
+    ///
+    /// The grouped inner operator that follows is left for the parser.
+    fn parse_transform(&mut self) -> Result<Option<Token>> {
+        let start_pos = self.position;
+        let invalid = |message: &str| AlsError::AlsSyntaxError {
+            position: start_pos,
+            message: message.to_string(),
+        };
+
+        let prefix = self.read_escaped_value(&['{', '(', ' ', '\t', '\n', '\r', '|'])?;
+        if self.next_char() != Some('{') {
+            return Ok(None);
+        }
+
+        let mut spec = String::new();
+        loop {
+            match self.next_char() {
+                Some('}') => break,
+                Some('\n') | None => return Ok(None),
+                Some(c) => spec.push(c),
+            }
+        }
+
+        let suffix = self.read_escaped_value(&['(', ' ', '\t', '\n', '\r', '|'])?;
+        if self.peek_char() != Some('(') {
+            return Ok(None);
+        }
+
+        let mut transform = ValueTransform::new().with_prefix(prefix).with_suffix(suffix);
+        let mut spec_chars = spec.chars().peekable();
+        if let Some(case) = spec_chars.peek().and_then(|&c| CaseTransform::from_flag(c)) {
+            transform = transform.with_case(case);
+            spec_chars.next();
+        }
+        let mut width: String = spec_chars.collect();
+        if let Some(separator) = width.chars().last().filter(|c| GROUP_SEPARATORS.contains(c)) {
+            transform = transform.with_grouping(separator);
+            width.pop();
+        }
+        if !width.is_empty() {
+            let width = width
+                .parse::<usize>()
+                .map_err(|_| invalid(&format!("Invalid transform spec: {{{}}}", spec)))?;
+            if width > MAX_TRANSFORM_WIDTH {
+                return Err(invalid(&format!(
+                    "Transform width {} is over the limit of {}",
+                    width, MAX_TRANSFORM_WIDTH
+                )));
+            }
+            transform = transform.with_width(width);
+        }
+
+        Ok(Some(Token::Transform(transform)))
+    }
+
+    /// Parse boolean runs (?a~b:r1.r2.r3), or `None` if the text after `?`
+    /// is not one.
+    fn parse_bool_runs(&mut self) -> Result<Option<Token>> {
+        let start_pos = self.position;
+        let label_delimiters = [' ', '\t', '\n', '\r', '|'];
+
+        let first = self.read_escaped_value(&['~', ' ', '\t', '\n', '\r', '|'])?;
+        if self.next_char() != Some('~') {
+            return Ok(None);
+        }
+
+        let mut second_delimiters = label_delimiters.to_vec();
+        second_delimiters.push(':');
+        let second = self.read_escaped_value(&second_delimiters)?;
+        if self.next_char() != Some(':') {
+            return Ok(None);
+        }
+
+        let mut runs = Vec::new();
+        loop {
+            let Ok(run) = self.read_unsigned(start_pos) else {
+                return Ok(None);
+            };
+            runs.push(run);
+            if self.peek_char() != Some('.') {
+                break;
+            }
+            self.next_char(); // consume '.'
+        }
+
+        Ok(Some(Token::BoolRuns {
+            labels: [first, second],
+            runs,
+        }))
+    }
+
+    /// Parse a split head (`</3`, or `<3` to concatenate): the separator
+    /// and the number of parts, or `None` if the text after `<` is not one.
+    fn parse_split(&mut self) -> Result<Option<Token>> {
+        let start_pos = self.position;
+
+        let separator = match self.peek_char() {
+            Some(c) if c.is_ascii_digit() => None,
+            Some(c) if !c.is_whitespace() => {
+                self.next_char();
+                Some(c)
+            }
+            _ => return Ok(None),
+        };
+
+        let Ok(parts) = self.read_unsigned(start_pos) else {
+            return Ok(None);
+        };
+        // The parts always follow as groups, so `<5ms` is a value
+        if self.peek_char() != Some('(') {
+            return Ok(None);
+        }
+        if parts < 2 {
+            return Err(AlsError::AlsSyntaxError {
+                position: start_pos,
+                message: format!("Split needs at least 2 parts, found {}", parts),
+            });
+        }
+
+        Ok(Some(Token::Split { separator, parts }))
+    }
+
+    /// Read an unsigned integer directly at the current position.
+    fn read_unsigned(&mut self, start_pos: usize) -> Result<usize> {
+        let mut num_str = String::new();
+        while let Some(c) = self.peek_char() {
+            if c.is_ascii_digit() {
+                num_str.push(c);
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+
+        num_str.parse::<usize>().map_err(|_| AlsError::AlsSyntaxError {
+            position: start_pos,
+            message: format!("Invalid unsigned number: {}", num_str),
+        })
+    }
+
+    /// Read an integer directly at the current position.
+    ///
+    /// When `sign_required` is set, the integer must begin with `+` or `-`.
+    fn read_signed_integer(&mut self, sign_required: bool) -> Result<i64> {
+        let start_pos = self.position;
+        let mut num_str = String::new();
+
+        if let Some(sign @ ('+' | '-')) = self.peek_char() {
+            num_str.push(sign);
+            self.next_char();
+        } else if sign_required {
+            return Err(AlsError::AlsSyntaxError {
+                position: start_pos,
+                message: "Expected '+' or '-' before step".to_string(),
+            });
+        }
+
+        while let Some(c) = self.peek_char() {
+            if c.is_ascii_digit() {
+                num_str.push(c);
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+
+        num_str.parse::<i64>().map_err(|_| AlsError::AlsSyntaxError {
+            position: start_pos,
+            message: format!("Invalid number: {}", num_str),
+        })
     }
 
     /// Get the next token from the input.
     pub fn next_token(&mut self) -> Result<Token> {
+        // Values that read as empty are skipped in a loop rather than by
+        // recursing, so long runs of them can't overflow the stack
+        loop {
+            match self.read_token()? {
+                Some(token) => return Ok(token),
+                None => {
+                    self.next_char();
+                }
+            }
+        }
+    }
+
+    /// Read the next token, or `None` if the next value reads as empty.
+    fn read_token(&mut self) -> Result<Option<Token>> {
         self.skip_whitespace();
 
         let c = match self.peek_char() {
             Some(c) => c,
-            None => return Ok(Token::Eof),
+            None => return Ok(Some(Token::Eof)),
         };
 
         match c {
@@ -391,6 +914,34 @@ impl<'a> Tokenizer<'a> {
                 self.next_char();
                 self.parse_dict_ref()
             }
+            '@' => {
+                self.next_char();
+                self.parse_column_ref()
+            }
+            '%' | '&' | '^' | '?' | '<' => {
+                // Heads that don't match their operator's syntax (`^up`,
+                // `&dow`, `<5ms`) are plain values, read again from the prefix
+                let saved_chars = self.chars.clone();
+                let saved_position = self.position;
+                self.next_char();
+                let head = match c {
+                    '%' => self.parse_date_range(),
+                    '&' => self.parse_cycle(),
+                    '^' => self.parse_transform(),
+                    '?' => self.parse_bool_runs(),
+                    _ => self.parse_split(),
+                }?;
+                match head {
+                    Some(token) => Ok(token),
+                    None => {
+                        self.chars = saved_chars;
+                        self.position = saved_position;
+                        self.next_char();
+                        let rest = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|', '>', '*', '~', ':', '(', ')'])?;
+                        Ok(Token::RawValue(format!("{}{}", c, rest)))
+                    }
+                }
+            }
             '>' => {
                 self.next_char();
                 Ok(Token::RangeOp)
@@ -406,6 +957,7 @@ impl<'a> Tokenizer<'a> {
             '|' => {
                 self.next_char();
                 self.in_header = false; // After first |, we're in streams
+                self.last_dict_ref = None;
                 Ok(Token::ColumnSeparator)
             }
             ':' => {
@@ -433,33 +985,32 @@ impl<'a> Tokenizer<'a> {
                 let value = self.read_escaped_value(&[' ', '\t', '\n', '\r', '|', '>', '*', '~', ':', '(', ')'])?;
                 if value.is_empty() {
                     // Skip and try again
-                    self.next_char();
-                    self.next_token()
-                } else {
-                    Ok(Token::RawValue(value))
+                    return Ok(None);
                 }
+                Ok(Token::RawValue(value))
             }
         }
+        .map(Some)
     }
 
     /// Peek at the next token without consuming it.
     pub fn peek_token(&mut self) -> Result<Token> {
+        // Restoring a copy of the iterator keeps peeking constant time;
+        // replaying the input from the start made parsing quadratic
+        let saved_chars = self.chars.clone();
         let saved_position = self.position;
-        
-        let token = self.next_token()?;
-        
+        let saved_dict_ref = self.last_dict_ref;
+        let saved_in_header = self.in_header;
+
+        let token = self.next_token();
+
         // Restore state
+        self.chars = saved_chars;
         self.position = saved_position;
-        self.chars = self.input.char_indices().peekable();
-        // Advance to saved position
-        while let Some((pos, _)) = self.chars.peek() {
-            if *pos >= saved_position {
-                break;
-            }
-            self.chars.next();
-        }
-        
-        Ok(token)
+        self.last_dict_ref = saved_dict_ref;
+        self.in_header = saved_in_header;
+
+        token
     }
 
     /// Tokenize the entire input and return all tokens.
@@ -484,13 +1035,25 @@ mod tests {
     #[test]
     fn test_tokenize_version_als() {
         let mut tokenizer = Tokenizer::new("!v1");
-        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Als(1)));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Als(1), Vec::new()));
+    }
+
+    #[test]
+    fn test_tokenize_version_features() {
+        let mut tokenizer = Tokenizer::new("!v2 +blocks +date-range\n#a");
+        let features = vec!["blocks".to_string(), "date-range".to_string()];
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Als(2), features));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Newline);
+
+        let mut tokenizer = Tokenizer::new("!ctx +1");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Ctx, Vec::new()));
+        assert!(Tokenizer::new("!v2 + blocks").next_token().is_err());
     }
 
     #[test]
     fn test_tokenize_version_ctx() {
         let mut tokenizer = Tokenizer::new("!ctx");
-        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Ctx));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Ctx, Vec::new()));
     }
 
     #[test]
@@ -502,6 +1065,20 @@ mod tests {
             Token::DictionaryHeader {
                 name: "colors".to_string(),
                 values: vec!["red".to_string(), "green".to_string(), "blue".to_string()],
+                front_coded: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokenize_front_coded_dictionary_header() {
+        let mut tokenizer = Tokenizer::new("$paths~:/var/log/a.log|9~b.log");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::DictionaryHeader {
+                name: "paths".to_string(),
+                values: vec!["/var/log/a.log".to_string(), "9~b.log".to_string()],
+                front_coded: true,
             }
         );
     }
@@ -514,6 +1091,14 @@ mod tests {
         assert_eq!(tokenizer.next_token().unwrap(), Token::SchemaColumn("city".to_string()));
     }
 
+    #[test]
+    fn test_tokenize_schema_column_non_identifier() {
+        let mut tokenizer = Tokenizer::new("#cafe\u{301} #first-name #\u{65e5}\u{672c} #a\\ b");
+        for name in ["cafe\u{301}", "first-name", "\u{65e5}\u{672c}", "a b"] {
+            assert_eq!(tokenizer.next_token().unwrap(), Token::SchemaColumn(name.to_string()));
+        }
+    }
+
     #[test]
     fn test_tokenize_dict_ref() {
         let mut tokenizer = Tokenizer::new("_0 _1 _42");
@@ -522,6 +1107,299 @@ mod tests {
         assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(42));
     }
 
+    #[test]
+    fn test_tokenize_relative_dict_refs() {
+        let mut tokenizer = Tokenizer::new("_12 _= _+ _+5*2 | _3 _+");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(12));
+        assert_eq!(tokenizer.peek_token().unwrap(), Token::DictRef(12));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(12));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(13));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(18));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(2));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::ColumnSeparator);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(3));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(4));
+    }
+
+    #[test]
+    fn test_tokenize_base62_dict_refs() {
+        let mut tokenizer = Tokenizer::new("_a _Z*3 _aZ3 _+ _1200");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(10));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(61));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(3));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(3286 + 61 * 62 + 3));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(3286 + 61 * 62 + 4));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::DictRef(1200));
+
+        assert!(Tokenizer::new("_1a").next_token().is_err());
+    }
+
+    #[test]
+    fn test_tokenize_relative_dict_ref_needs_previous() {
+        assert!(Tokenizer::new("_=").next_token().is_err());
+        let mut tokenizer = Tokenizer::new("_1 | _+");
+        tokenizer.next_token().unwrap();
+        tokenizer.next_token().unwrap();
+        assert!(tokenizer.next_token().is_err());
+    }
+
+    #[test]
+    fn test_tokenize_column_ref() {
+        let mut tokenizer = Tokenizer::new("@0*3 @2(buy:+1 sell:-1)*4");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::ColumnRef {
+                column: 0,
+                mapping: Vec::new()
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(3));
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::ColumnRef {
+                column: 2,
+                mapping: vec![
+                    ("buy".to_string(), "+1".to_string()),
+                    ("sell".to_string(), "-1".to_string()),
+                ]
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(4));
+    }
+
+    #[test]
+    fn test_tokenize_column_ref_unterminated_mapping() {
+        let mut tokenizer = Tokenizer::new("@0(a:b|1");
+        assert!(tokenizer.next_token().is_err());
+    }
+
+    #[test]
+    fn test_tokenize_at_raw_values() {
+        let mut tokenizer = Tokenizer::new("@me @ x");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("@me".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("@".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("x".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize_escaped_at_sign() {
+        let mut tokenizer = Tokenizer::new("\\@home");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("@home".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize_date_range() {
+        let mut tokenizer = Tokenizer::new("%{%Y-%m-%d}1704067200+86400*30 %{%H:%M}-60-60*2");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::DateRange {
+                format: "%Y-%m-%d".to_string(),
+                start: 1_704_067_200,
+                step: 86_400,
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(30));
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::DateRange {
+                format: "%H:%M".to_string(),
+                start: -60,
+                step: -60,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokenize_date_range_raw_values() {
+        for raw in ["%{%Y", "%{%Y}100", "%{%Y}+5", "%{%Y}0+1", "%{x}"] {
+            assert_eq!(Tokenizer::new(raw).next_token().unwrap(), Token::RawValue(raw.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_tokenize_percent_raw_values() {
+        let mut tokenizer = Tokenizer::new("%abc \\%{x}");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("%abc".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("%{x}".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize_cycle() {
+        let mut tokenizer = Tokenizer::new("&dow+3*10 &qtr+1:90*360 &mon*12");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Cycle {
+                cycle: CalendarCycle::Weekday,
+                offset: 3,
+                run: 1,
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(10));
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Cycle {
+                cycle: CalendarCycle::Quarter,
+                offset: 1,
+                run: 90,
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::MultiplyOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(360));
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Cycle {
+                cycle: CalendarCycle::Month,
+                offset: 0,
+                run: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokenize_cycle_raw_values() {
+        assert_eq!(
+            Tokenizer::new("&dow+*7").next_token().unwrap(),
+            Token::RawValue("&dow+".to_string())
+        );
+        assert_eq!(Tokenizer::new("&dow").next_token().unwrap(), Token::RawValue("&dow".to_string()));
+        assert_eq!(
+            Tokenizer::new("&week+0*7").next_token().unwrap(),
+            Token::RawValue("&week+0".to_string())
+        );
+        assert_eq!(
+            Tokenizer::new("& ").next_token().unwrap(),
+            Token::RawValue("&".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tokenize_transform() {
+        let mut tokenizer = Tokenizer::new("^USER{3}(1>5) ^{U}-x(_0)");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Transform(ValueTransform::new().with_prefix("USER").with_width(3))
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::OpenParen);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(1));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RangeOp);
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Integer(5));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::CloseParen);
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Transform(
+                ValueTransform::new()
+                    .with_case(CaseTransform::Upper)
+                    .with_suffix("-x")
+            )
+        );
+        let mut tokenizer = Tokenizer::new("^{4,}(1>2)");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Transform(ValueTransform::new().with_width(4).with_grouping(','))
+        );
+    }
+
+    #[test]
+    fn test_tokenize_transform_errors() {
+        assert!(Tokenizer::new("^{X}(1)").next_token().is_err());
+        assert!(Tokenizer::new("^{,3}(1)").next_token().is_err());
+        assert!(Tokenizer::new("^{255}(1)").next_token().is_ok());
+        assert!(Tokenizer::new("^{256}(1)").next_token().is_err());
+        assert!(Tokenizer::new("^{4000000000}(1>3)").next_token().is_err());
+        assert!(Tokenizer::new("^{99999999999999999999}(1)").next_token().is_err());
+        for (input, raw) in [("^USER(1>5)", "^USER"), ("^{U3", "^{U3"), ("^{3}x", "^{3}x"), ("^up", "^up")] {
+            assert_eq!(Tokenizer::new(input).next_token().unwrap(), Token::RawValue(raw.to_string()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_bool_runs() {
+        let mut tokenizer = Tokenizer::new("?true~false:3.1.12|?\\0~N:2");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::BoolRuns {
+                labels: ["true".to_string(), "false".to_string()],
+                runs: vec![3, 1, 12],
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::ColumnSeparator);
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::BoolRuns {
+                labels: ["\0".to_string(), "N".to_string()],
+                runs: vec![2],
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokenize_float_delta() {
+        let mut tokenizer = Tokenizer::new("%.2:2153+3-1+0|%.1:-5|%{%Y}0+1*3");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::FloatDelta {
+                places: 2,
+                start: 2153,
+                deltas: vec![3, -1, 0],
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::ColumnSeparator);
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::FloatDelta {
+                places: 1,
+                start: -5,
+                deltas: Vec::new(),
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::ColumnSeparator);
+        assert!(matches!(tokenizer.next_token().unwrap(), Token::DateRange { .. }));
+
+        for invalid in ["%.0:1", "%.16:1"] {
+            assert!(Tokenizer::new(invalid).next_token().is_err(), "{}", invalid);
+        }
+        for input in ["%.2", "%.2:x", "%.2:1+"] {
+            assert_eq!(Tokenizer::new(input).next_token().unwrap(), Token::RawValue("%.2".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_tokenize_split() {
+        let mut tokenizer = Tokenizer::new("</3(a)");
+        assert_eq!(
+            tokenizer.next_token().unwrap(),
+            Token::Split {
+                separator: Some('/'),
+                parts: 3
+            }
+        );
+        assert_eq!(tokenizer.next_token().unwrap(), Token::OpenParen);
+        assert_eq!(
+            Tokenizer::new("<2(a)").next_token().unwrap(),
+            Token::Split {
+                separator: None,
+                parts: 2
+            }
+        );
+        assert!(Tokenizer::new("</1(a)").next_token().is_err());
+        for (input, raw) in [("</1", "</1"), ("< 2", "<"), ("</", "</"), ("<b", "<b"), ("<5ms", "<5ms")] {
+            assert_eq!(Tokenizer::new(input).next_token().unwrap(), Token::RawValue(raw.to_string()), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_bool_run_raw_values() {
+        for (input, raw) in [("?ok", "?ok"), ("?true:3", "?true"), ("?a~b", "?a"), ("?a~b:3.", "?a")] {
+            assert_eq!(Tokenizer::new(input).next_token().unwrap(), Token::RawValue(raw.to_string()), "{}", input);
+        }
+    }
+
     #[test]
     fn test_tokenize_operators() {
         let mut tokenizer = Tokenizer::new("> * ~ | : ( )");
@@ -545,11 +1423,53 @@ mod tests {
 
     #[test]
     fn test_tokenize_floats() {
-        let mut tokenizer = Tokenizer::new("3.14 -2.5 1e10 2.5e-3");
+        let mut tokenizer = Tokenizer::new("3.14 -2.5");
         assert_eq!(tokenizer.next_token().unwrap(), Token::Float(3.14));
         assert_eq!(tokenizer.next_token().unwrap(), Token::Float(-2.5));
-        assert_eq!(tokenizer.next_token().unwrap(), Token::Float(1e10));
-        assert_eq!(tokenizer.next_token().unwrap(), Token::Float(2.5e-3));
+    }
+
+    #[test]
+    fn test_tokenize_scientific_floats() {
+        let mut tokenizer = Tokenizer::new("1e10 2.5e-3 -1.5E+3 -7e2 1.e3");
+        for (value, text) in [(1e10, "1e10"), (2.5e-3, "2.5e-3"), (-1.5e3, "-1.5E+3"), (-7e2, "-7e2"), (1e3, "1.e3")] {
+            assert_eq!(
+                tokenizer.next_token().unwrap(),
+                Token::ScientificFloat { value, text: text.to_string() }
+            );
+        }
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Eof);
+    }
+
+    #[test]
+    fn test_tokenize_malformed_exponents() {
+        let mut tokenizer = Tokenizer::new("-e3 1e 1e- 1e+x 1e3x");
+        for raw in ["-e3", "1e", "1e-", "1e+x", "1e3x"] {
+            assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue(raw.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_tokenize_negative_operands() {
+        let tokens = Tokenizer::new("-5>-1:-2 -3*4 -1.5~-2.5e1*4").tokenize_all().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Integer(-5),
+                Token::RangeOp,
+                Token::Integer(-1),
+                Token::StepSeparator,
+                Token::Integer(-2),
+                Token::Integer(-3),
+                Token::MultiplyOp,
+                Token::Integer(4),
+                Token::Float(-1.5),
+                Token::ToggleOp,
+                Token::ScientificFloat { value: -25.0, text: "-2.5e1".to_string() },
+                Token::MultiplyOp,
+                Token::Integer(4),
+                Token::Eof,
+            ]
+        );
     }
 
     #[test]
@@ -559,6 +1479,25 @@ mod tests {
         assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("world".to_string()));
     }
 
+    #[test]
+    fn test_tokenize_digit_led_raw_values() {
+        let mut tokenizer = Tokenizer::new("2026-10-17 10x 1.2.3 10\\:30>5");
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("2026-10-17".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("10x".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("1.2.3".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue("10:30".to_string()));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::RangeOp);
+    }
+
+    #[test]
+    fn test_tokenize_non_canonical_numbers() {
+        let mut tokenizer = Tokenizer::new("007 1. - -0 99999999999999999999 1.5");
+        for raw in ["007", "1.", "-", "-0", "99999999999999999999"] {
+            assert_eq!(tokenizer.next_token().unwrap(), Token::RawValue(raw.to_string()));
+        }
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Float(1.5));
+    }
+
     #[test]
     fn test_tokenize_escaped_values() {
         let mut tokenizer = Tokenizer::new("hello\\>world a\\*b");
@@ -607,7 +1546,7 @@ mod tests {
         let input = "!v1\n$d:a|b\n#col1 #col2\n1>3|_0 _1";
         let mut tokenizer = Tokenizer::new(input);
         
-        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Als(1)));
+        assert_eq!(tokenizer.next_token().unwrap(), Token::Version(VersionType::Als(1), Vec::new()));
         assert_eq!(tokenizer.next_token().unwrap(), Token::Newline);
         assert!(matches!(tokenizer.next_token().unwrap(), Token::DictionaryHeader { .. }));
         assert_eq!(tokenizer.next_token().unwrap(), Token::Newline);
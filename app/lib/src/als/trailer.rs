@@ -0,0 +1,68 @@
+//! Trailer lines.
+//!
+//! Indexes, Bloom filters, zone maps, edit scripts, file metadata and the
+//! per-column policies are written after the streams of a document, one
+//! line each, starting with a prefix such as `!idx ` and followed by
+//! space-separated fields. [`TrailerLine`] holds what their parsers share:
+//! checking the prefix and reporting errors at the offset of the line.
+
+use super::index::split_unescaped_spaces;
+use crate::error::{AlsError, Result};
+
+/// The body of a trailer line, after its prefix.
+pub(crate) struct TrailerLine<'a> {
+    body: &'a str,
+    position: usize,
+}
+
+impl<'a> TrailerLine<'a> {
+    /// Strip `prefix` from `line`, found at offset `position` of the input,
+    /// which locates the errors about the line.
+    ///
+    /// `kind` names the line in the error for a missing prefix, such as
+    /// "index" in "Expected index line".
+    pub(crate) fn new(line: &'a str, prefix: &str, kind: &str, position: usize) -> Result<Self> {
+        match line.strip_prefix(prefix) {
+            Some(body) => Ok(Self { body, position }),
+            None => Err(AlsError::AlsSyntaxError {
+                position,
+                message: format!("Expected {} line, found: {}", kind, line),
+            }),
+        }
+    }
+
+    /// Get the text after the prefix.
+    pub(crate) fn body(&self) -> &'a str {
+        self.body
+    }
+
+    /// Split the text after the prefix at unescaped spaces, skipping empty
+    /// fields.
+    pub(crate) fn fields(&self) -> Vec<&'a str> {
+        split_unescaped_spaces(self.body)
+    }
+
+    /// Get a syntax error about the line.
+    pub(crate) fn error(&self, message: impl Into<String>) -> AlsError {
+        AlsError::AlsSyntaxError {
+            position: self.position,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailer_line() {
+        let line = TrailerLine::new("!idx 2 a\\ b:0-1  c:2-3", "!idx ", "index", 40).unwrap();
+        assert_eq!(line.body(), "2 a\\ b:0-1  c:2-3");
+        assert_eq!(line.fields(), ["2", "a\\ b:0-1", "c:2-3"]);
+        assert!(matches!(line.error("bad"), AlsError::AlsSyntaxError { position: 40, .. }));
+
+        let missing = TrailerLine::new("!bloom 0", "!idx ", "index", 7);
+        assert!(matches!(missing, Err(AlsError::AlsSyntaxError { position: 7, message }) if message.contains("index")));
+    }
+}
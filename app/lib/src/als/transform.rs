@@ -0,0 +1,288 @@
+//! Value transforms applied on top of another operator.
+//!
+//! A transform rewrites every value produced by an inner operator, so a
+//! column such as `USER001, USER002, ...` can be stored as a padded,
+//! prefixed range (`^USER{3}(1>100)`) instead of raw strings.
+//!
+//! # Template Syntax
+//!
+//! The transform is written as a template with a single `{spec}`
+//! placeholder for the inner value. The spec holds an optional case flag,
+//! an optional zero-padding width and an optional thousands separator:
+//!
+//! | Spec | Meaning |
+//! |------|---------|
+//! | `{}` | Value unchanged |
+//! | `{U}` | Uppercase |
+//! | `{L}` | Lowercase |
+//! | `{T}` | Titlecase (first letter of each word) |
+//! | `{3}` | Zero-pad to width 3 |
+//! | `{U4}` | Uppercase, then zero-pad to width 4 |
+//! | `{,}` | Group thousands with `,` (e.g., `1,000`) |
+//!
+//! Widths above [`MAX_TRANSFORM_WIDTH`] are rejected, so a short spec can't
+//! pad every value to gigabytes.
+
+use serde::{Deserialize, Serialize};
+
+/// Widest zero-padding a transform may declare.
+pub const MAX_TRANSFORM_WIDTH: usize = 255;
+
+/// Characters accepted as thousands separators.
+pub const GROUP_SEPARATORS: &[char] = &[',', '.', '\'', '_'];
+
+/// Case conversion applied by a `ValueTransform`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CaseTransform {
+    /// Convert to uppercase.
+    Upper,
+    /// Convert to lowercase.
+    Lower,
+    /// Capitalize the first letter of each word and lowercase the rest.
+    Title,
+}
+
+impl CaseTransform {
+    /// Get the flag used for this case in a transform spec.
+    pub fn flag(&self) -> char {
+        match self {
+            CaseTransform::Upper => 'U',
+            CaseTransform::Lower => 'L',
+            CaseTransform::Title => 'T',
+        }
+    }
+
+    /// Look up a case transform by its spec flag.
+    pub fn from_flag(flag: char) -> Option<Self> {
+        match flag {
+            'U' => Some(CaseTransform::Upper),
+            'L' => Some(CaseTransform::Lower),
+            'T' => Some(CaseTransform::Title),
+            _ => None,
+        }
+    }
+
+    /// Apply this case conversion to a value.
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            CaseTransform::Upper => value.to_uppercase(),
+            CaseTransform::Lower => value.to_lowercase(),
+            CaseTransform::Title => {
+                let mut result = String::with_capacity(value.len());
+                let mut at_word_start = true;
+                for c in value.chars() {
+                    if at_word_start {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.extend(c.to_lowercase());
+                    }
+                    at_word_start = c.is_whitespace();
+                }
+                result
+            }
+        }
+    }
+}
+
+/// A formatting transform applied to each value of an inner operator.
+///
+/// The steps run in a fixed order: case conversion, zero-padding,
+/// thousands grouping, then the prefix and suffix are attached. Padding
+/// and grouping only apply to integers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct ValueTransform {
+    /// Optional case conversion
+    pub case: Option<CaseTransform>,
+    /// Zero-padding width (0 disables padding)
+    pub width: usize,
+    /// Thousands separator for integers
+    pub grouping: Option<char>,
+    /// Text placed before each value
+    pub prefix: String,
+    /// Text placed after each value
+    pub suffix: String,
+}
+
+impl ValueTransform {
+    /// Create an identity transform.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the case conversion.
+    pub fn with_case(mut self, case: CaseTransform) -> Self {
+        self.case = Some(case);
+        self
+    }
+
+    /// Set the zero-padding width.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the thousands separator.
+    pub fn with_grouping(mut self, separator: char) -> Self {
+        self.grouping = Some(separator);
+        self
+    }
+
+    /// Set the prefix.
+    pub fn with_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set the suffix.
+    pub fn with_suffix<S: Into<String>>(mut self, suffix: S) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+
+    /// Returns true if this transform leaves values unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.case.is_none()
+            && self.width == 0
+            && self.grouping.is_none()
+            && self.prefix.is_empty()
+            && self.suffix.is_empty()
+    }
+
+    /// Apply this transform to a single value.
+    pub fn apply(&self, value: &str) -> String {
+        let cased = match self.case {
+            Some(case) => case.apply(value),
+            None => value.to_string(),
+        };
+
+        let number = cased.parse::<i64>().ok();
+        let padded = match number {
+            // Keep the sign in front of the padding for numbers
+            Some(n) if self.width > 0 => format!("{:0width$}", n, width = self.width),
+            None if cased.chars().count() < self.width => {
+                let padding = self.width - cased.chars().count();
+                format!("{}{}", "0".repeat(padding), cased)
+            }
+            _ => cased,
+        };
+
+        let padded = match (number, self.grouping) {
+            (Some(_), Some(separator)) => Self::group_thousands(&padded, separator),
+            _ => padded,
+        };
+
+        let mut result = String::with_capacity(self.prefix.len() + padded.len() + self.suffix.len());
+        result.push_str(&self.prefix);
+        result.push_str(&padded);
+        result.push_str(&self.suffix);
+        result
+    }
+
+    /// Insert a separator between each group of three digits.
+    fn group_thousands(number: &str, separator: char) -> String {
+        let (sign, digits) = match number.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", number),
+        };
+
+        let mut result = String::with_capacity(number.len() + digits.len() / 3);
+        result.push_str(sign);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                result.push(separator);
+            }
+            result.push(digit);
+        }
+        result
+    }
+
+    /// Get the placeholder spec, without braces (e.g., `U3`).
+    pub fn spec(&self) -> String {
+        let mut spec = String::new();
+        if let Some(case) = self.case {
+            spec.push(case.flag());
+        }
+        if self.width > 0 {
+            spec.push_str(&self.width.to_string());
+        }
+        if let Some(separator) = self.grouping {
+            spec.push(separator);
+        }
+        spec
+    }
+
+    /// Number of characters this transform adds around its inner operator
+    /// when serialized (`^prefix{spec}suffix(...)`).
+    pub fn overhead(&self) -> usize {
+        // ^ { } ( ) plus an escape allowance for the affixes
+        5 + self.prefix.len() + self.spec().len() + self.suffix.len() + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_transforms() {
+        assert_eq!(CaseTransform::Upper.apply("Mon"), "MON");
+        assert_eq!(CaseTransform::Lower.apply("Mon"), "mon");
+        assert_eq!(CaseTransform::Title.apply("hello WORLD"), "Hello World");
+    }
+
+    #[test]
+    fn test_case_flag_roundtrip() {
+        for case in [CaseTransform::Upper, CaseTransform::Lower, CaseTransform::Title] {
+            assert_eq!(CaseTransform::from_flag(case.flag()), Some(case));
+        }
+        assert_eq!(CaseTransform::from_flag('X'), None);
+    }
+
+    #[test]
+    fn test_prefixed_padded_number() {
+        let transform = ValueTransform::new().with_prefix("USER").with_width(3);
+        assert_eq!(transform.apply("7"), "USER007");
+        assert_eq!(transform.apply("1234"), "USER1234");
+        assert_eq!(transform.spec(), "3");
+    }
+
+    #[test]
+    fn test_padding_negative_and_text() {
+        let transform = ValueTransform::new().with_width(4);
+        assert_eq!(transform.apply("-5"), "-005");
+        assert_eq!(transform.apply("ab"), "00ab");
+    }
+
+    #[test]
+    fn test_case_then_suffix() {
+        let transform = ValueTransform::new()
+            .with_case(CaseTransform::Upper)
+            .with_suffix(".log");
+        assert_eq!(transform.apply("tue"), "TUE.log");
+        assert_eq!(transform.spec(), "U");
+    }
+
+    #[test]
+    fn test_thousands_grouping() {
+        let transform = ValueTransform::new().with_grouping(',');
+        assert_eq!(transform.apply("1000"), "1,000");
+        assert_eq!(transform.apply("-1234567"), "-1,234,567");
+        assert_eq!(transform.apply("999"), "999");
+        assert_eq!(transform.apply("abc"), "abc");
+        assert_eq!(transform.spec(), ",");
+    }
+
+    #[test]
+    fn test_padding_with_grouping() {
+        let transform = ValueTransform::new().with_width(7).with_grouping('.');
+        assert_eq!(transform.apply("1000"), "0.001.000");
+        assert_eq!(transform.spec(), "7.");
+    }
+
+    #[test]
+    fn test_identity() {
+        assert!(ValueTransform::new().is_identity());
+        assert!(!ValueTransform::new().with_width(2).is_identity());
+        assert_eq!(ValueTransform::new().apply("x"), "x");
+    }
+}
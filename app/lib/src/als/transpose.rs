@@ -0,0 +1,94 @@
+//! Cache-blocked transposition between rows and columns.
+//!
+//! Streams expand a column at a time, while callers want rows, and the
+//! reverse holds when compressing. Transposing a column at a time writes
+//! one value into every row before moving on, so on a wide table each write
+//! lands on a row whose buffer was long since evicted from cache. Working
+//! through a tile of rows at a time keeps those rows, and the part of each
+//! column feeding them, in cache while every column is visited.
+
+/// Rows handled per tile, small enough for a tile of row buffers and the
+/// matching column values to stay in L1 on typical table widths.
+const TILE_ROWS: usize = 64;
+
+/// Transpose `lines` of `len` items each, moving every item.
+///
+/// Works in either direction: columns in gives rows out and vice versa.
+/// Lines shorter than `len` leave the outputs past their end short, so
+/// callers check lengths first.
+pub(crate) fn transpose<I: IntoIterator>(lines: Vec<I>, len: usize) -> Vec<Vec<I::Item>> {
+    let mut out = Vec::with_capacity(len);
+    transpose_into(lines, len, &mut out);
+    out
+}
+
+/// Like `transpose`, writing into `out` and reusing the vectors already in
+/// it.
+pub(crate) fn transpose_into<I: IntoIterator>(lines: Vec<I>, len: usize, out: &mut Vec<Vec<I::Item>>) {
+    let width = lines.len();
+    let mut lines: Vec<I::IntoIter> = lines.into_iter().map(IntoIterator::into_iter).collect();
+    out.truncate(len);
+    for line in out.iter_mut() {
+        line.clear();
+        line.reserve(width);
+    }
+    out.resize_with(len, || Vec::with_capacity(width));
+
+    for start in (0..len).step_by(TILE_ROWS) {
+        let end = (start + TILE_ROWS).min(len);
+        for line in &mut lines {
+            // The tile is zipped first so no item past it is consumed
+            for (target, item) in out[start..end].iter_mut().zip(line.by_ref()) {
+                target.push(item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_round_trip() {
+        // Spans several tiles with a partial one at the end
+        let rows = TILE_ROWS * 2 + 5;
+        let columns: Vec<Vec<String>> =
+            (0..7).map(|c| (0..rows).map(|r| format!("{c}:{r}")).collect()).collect();
+
+        let transposed = transpose(columns.clone(), rows);
+        assert_eq!(transposed.len(), rows);
+        for (r, row) in transposed.iter().enumerate() {
+            let expected: Vec<String> = (0..7).map(|c| format!("{c}:{r}")).collect();
+            assert_eq!(row, &expected);
+        }
+
+        assert_eq!(transpose(transposed, 7), columns);
+    }
+
+    #[test]
+    fn test_transpose_into_reuses_vectors() {
+        let mut out: Vec<Vec<i32>> = (0..5).map(|_| Vec::with_capacity(16)).collect();
+        out[0].push(9);
+        transpose_into(vec![vec![1, 2, 3], vec![4, 5, 6]], 3, &mut out);
+        assert_eq!(out, vec![vec![1, 4], vec![2, 5], vec![3, 6]]);
+        assert!(out.iter().all(|row| row.capacity() >= 16));
+
+        transpose_into(vec![vec![7, 8, 9, 10]], 4, &mut out);
+        assert_eq!(out, vec![vec![7], vec![8], vec![9], vec![10]]);
+    }
+
+    #[test]
+    fn test_transpose_empty() {
+        assert!(transpose(Vec::<Vec<u8>>::new(), 0).is_empty());
+        assert_eq!(transpose(Vec::<Vec<u8>>::new(), 3), vec![Vec::<u8>::new(); 3]);
+        assert!(transpose(vec![Vec::<u8>::new(); 4], 0).is_empty());
+    }
+
+    #[test]
+    fn test_transpose_borrowed_items() {
+        let columns: Vec<&[&str]> = vec![&["a", "b"], &["c", "d"]];
+        let rows = transpose(columns.iter().map(|column| column.iter().copied()).collect(), 2);
+        assert_eq!(rows, vec![vec!["a", "c"], vec!["b", "d"]]);
+    }
+}
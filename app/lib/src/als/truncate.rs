@@ -0,0 +1,193 @@
+//! Lossy timestamp truncation.
+//!
+//! Metrics archives rarely need their timestamps finer than a second or a
+//! minute, but the milliseconds in them keep consecutive values from
+//! lining up into ranges and repeats. Time columns can be truncated as they
+//! are compressed, keeping their format, and the truncation is recorded in
+//! the document, one line per column, so readers know the resolution:
+//!
+//! ```text
+//! !truncate minute ts
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::datetime::parse_timestamp;
+use super::policy::{apply_policy, parse_policy_line, write_policy_lines};
+use crate::convert::partition::MILLISECONDS_FROM;
+use crate::convert::{Column, TabularData, Value};
+use crate::error::Result;
+
+/// Prefix of a truncation line.
+pub const TRUNCATE_PREFIX: &str = "!truncate ";
+
+/// Resolution time values are truncated to before compression.
+///
+/// ISO 8601 timestamps lose their fraction, and their seconds too when
+/// truncated to minutes, but keep their offset. Integers and floats are
+/// taken as seconds since the Unix epoch, or milliseconds past the year
+/// 5000 as when partitioning, and rounded down. Other values, including
+/// timestamps already coarser than the resolution, are left as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeTruncation {
+    /// Drop fractions of a second
+    Second,
+    /// Drop seconds and fractions of a second
+    Minute,
+}
+
+impl TimeTruncation {
+    /// All truncations, finest first.
+    pub const ALL: [TimeTruncation; 2] = [TimeTruncation::Second, TimeTruncation::Minute];
+
+    /// Get the name used for this truncation in configuration and documents.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeTruncation::Second => "second",
+            TimeTruncation::Minute => "minute",
+        }
+    }
+
+    /// Look up a truncation by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|truncation| truncation.name() == name)
+    }
+
+    /// Get the length of the resolution in seconds.
+    fn seconds(&self) -> i64 {
+        match self {
+            TimeTruncation::Second => 1,
+            TimeTruncation::Minute => 60,
+        }
+    }
+
+    /// Truncate one value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{TimeTruncation, Value};
+    ///
+    /// let ts = Value::string("2024-03-01T12:34:56.789+01:00");
+    /// assert_eq!(TimeTruncation::Second.truncate_value(&ts), Value::string("2024-03-01T12:34:56+01:00"));
+    /// assert_eq!(TimeTruncation::Minute.truncate_value(&ts), Value::string("2024-03-01T12:34:00+01:00"));
+    /// assert_eq!(TimeTruncation::Minute.truncate_value(&Value::Integer(1_709_296_496)), Value::Integer(1_709_296_440));
+    /// ```
+    pub fn truncate_value<'a>(&self, value: &Value<'a>) -> Value<'a> {
+        match value {
+            Value::Integer(i) => {
+                let unit = if i.unsigned_abs() >= MILLISECONDS_FROM as u64 { 1000 } else { 1 };
+                Value::Integer(i - i.rem_euclid(self.seconds() * unit))
+            }
+            Value::Float(x) if x.is_finite() => {
+                let unit = if x.abs() >= MILLISECONDS_FROM as f64 { 1000.0 } else { 1.0 };
+                let step = (self.seconds() as f64) * unit;
+                Value::Float((x / step).floor() * step + 0.0)
+            }
+            Value::String(s) => match self.truncate_text(s) {
+                Some(truncated) => Value::string_owned(truncated),
+                None => value.clone(),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Truncate an ISO 8601 timestamp with seconds, or return `None` for
+    /// any other text.
+    fn truncate_text(&self, text: &str) -> Option<String> {
+        let seconds = text.get(..19)?;
+        ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+            .iter()
+            .find_map(|format| parse_timestamp(seconds, format))?;
+
+        let mut offset = &text[19..];
+        if let Some(fraction) = offset.strip_prefix('.') {
+            offset = fraction.trim_start_matches(|c: char| c.is_ascii_digit());
+        }
+        if !matches!(offset.as_bytes(), [] | [b'Z'] | [b'+' | b'-', ..]) {
+            return None;
+        }
+        Some(match self {
+            TimeTruncation::Second => format!("{}{}", seconds, offset),
+            TimeTruncation::Minute => format!("{}00{}", &seconds[..17], offset),
+        })
+    }
+
+    /// Append a `!truncate <resolution> <column>` line to `output` for each
+    /// truncated time column, each preceded by a newline.
+    pub(crate) fn write_lines(output: &mut String, truncations: &BTreeMap<String, TimeTruncation>) {
+        write_policy_lines(output, TRUNCATE_PREFIX, truncations, |truncation| truncation.name().to_string());
+    }
+
+    /// Read a `!truncate` line at offset `position` back into the column
+    /// and the resolution its times were truncated to.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(String, TimeTruncation)> {
+        parse_policy_line(line, TRUNCATE_PREFIX, "truncation", position, TimeTruncation::from_name)
+    }
+}
+
+/// Truncate the times of the columns of `data` named in `policy`, keeping
+/// the type inferred for each column.
+///
+/// Returns `None` if `policy` names no column of `data`.
+pub(crate) fn truncate<'a>(
+    data: &TabularData<'a>,
+    policy: &HashMap<String, TimeTruncation>,
+) -> Result<Option<TabularData<'a>>> {
+    apply_policy(data, policy, |column, truncation| {
+        let values = column.values.iter().map(|value| truncation.truncate_value(value)).collect();
+        Ok(Some(Column::with_type(column.name.clone(), values, column.inferred_type)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_text() {
+        let second = |s: &'static str| TimeTruncation::Second.truncate_value(&Value::string(s));
+        let minute = |s: &'static str| TimeTruncation::Minute.truncate_value(&Value::string(s));
+        assert_eq!(second("2024-03-01 12:34:56.123456"), Value::string("2024-03-01 12:34:56"));
+        assert_eq!(second("2024-03-01T12:34:56Z"), Value::string("2024-03-01T12:34:56Z"));
+        assert_eq!(minute("2024-03-01T12:34:56.5Z"), Value::string("2024-03-01T12:34:00Z"));
+        assert_eq!(minute("2024-03-01T12:34:56-0530"), Value::string("2024-03-01T12:34:00-0530"));
+        // Values already coarser, or not timestamps, are kept
+        for text in ["2024-03-01T12:34", "2024-03-01", "2024-03-01T12:34:56 UTC", "not a time at all!", ""] {
+            assert_eq!(minute(text), Value::string(text));
+        }
+    }
+
+    #[test]
+    fn test_truncate_numbers() {
+        let minute = |value| TimeTruncation::Minute.truncate_value(&value);
+        assert_eq!(minute(Value::Integer(1_709_296_496_789)), Value::Integer(1_709_296_440_000));
+        assert_eq!(minute(Value::Integer(-1)), Value::Integer(-60));
+        assert_eq!(TimeTruncation::Second.truncate_value(&Value::Integer(1_709_296_496_789)), Value::Integer(1_709_296_496_000));
+        assert_eq!(TimeTruncation::Second.truncate_value(&Value::Float(1_709_296_496.75)), Value::Float(1_709_296_496.0));
+        assert_eq!(minute(Value::Float(59.5)), Value::Float(0.0));
+        assert_eq!(minute(Value::Null), Value::Null);
+        assert_eq!(minute(Value::Boolean(true)), Value::Boolean(true));
+    }
+
+    #[test]
+    fn test_lines() {
+        let mut truncations = BTreeMap::new();
+        truncations.insert("ts".to_string(), TimeTruncation::Minute);
+        truncations.insert("seen at".to_string(), TimeTruncation::Second);
+        let mut output = String::new();
+        TimeTruncation::write_lines(&mut output, &truncations);
+        assert_eq!(output, "\n!truncate second seen\\ at\n!truncate minute ts");
+
+        let parsed: Vec<(String, TimeTruncation)> = output
+            .lines()
+            .skip(1)
+            .map(|line| TimeTruncation::parse_line(line, 0).unwrap())
+            .collect();
+        assert_eq!(parsed, truncations.into_iter().collect::<Vec<_>>());
+        assert!(TimeTruncation::parse_line("!truncate hour ts", 0).is_err());
+        assert_eq!(TimeTruncation::from_name("minute"), Some(TimeTruncation::Minute));
+    }
+}
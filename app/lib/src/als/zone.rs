@@ -0,0 +1,310 @@
+//! Min/max zone maps.
+//!
+//! A zone map records the smallest and largest value of a column, and how
+//! many of its values are null, much like Parquet column statistics. One
+//! zone map is kept per block (or a single one outside block mode), so
+//! range queries can skip blocks that cannot match. Zone maps are appended
+//! to the document as one line per column:
+//!
+//! ```text
+//! !zone 0 n 1 100 0 101 200 3
+//! ```
+//!
+//! The line holds the column position, how values compare (`n` numerically,
+//! `s` as strings), then the escaped minimum, maximum and null count of
+//! each block. A block without non-null values has `\0` for both bounds.
+
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+use serde::{Deserialize, Serialize};
+
+use super::escape::{escape_als_string, unescape_als_string, NULL_TOKEN};
+use super::trailer::TrailerLine;
+use crate::convert::Value;
+use crate::error::Result;
+
+/// Prefix of a zone map line.
+pub const ZONE_PREFIX: &str = "!zone ";
+
+/// How the values of a column are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ZoneOrder {
+    /// Values are numbers and compare numerically.
+    Numeric,
+    /// Values compare as strings.
+    Text,
+}
+
+impl ZoneOrder {
+    /// Compare two values.
+    ///
+    /// Returns `None` when a value is not a number under `Numeric`.
+    pub fn compare(&self, a: &str, b: &str) -> Option<Ordering> {
+        match self {
+            ZoneOrder::Numeric => Some(a.parse::<f64>().ok()?.total_cmp(&b.parse::<f64>().ok()?)),
+            ZoneOrder::Text => Some(a.cmp(b)),
+        }
+    }
+
+    /// Check if `value` lies within `range`.
+    pub fn contains<'a, R: RangeBounds<&'a str>>(&self, range: &R, value: &str) -> bool {
+        let above = match range.start_bound() {
+            Bound::Included(low) => self.compare(value, low).is_some_and(|o| o != Ordering::Less),
+            Bound::Excluded(low) => self.compare(value, low) == Some(Ordering::Greater),
+            // Still reject values that don't compare at all
+            Bound::Unbounded => self.compare(value, value).is_some(),
+        };
+        let below = match range.end_bound() {
+            Bound::Included(high) => self.compare(value, high).is_some_and(|o| o != Ordering::Greater),
+            Bound::Excluded(high) => self.compare(value, high) == Some(Ordering::Less),
+            Bound::Unbounded => true,
+        };
+        above && below
+    }
+
+    fn marker(&self) -> &'static str {
+        match self {
+            ZoneOrder::Numeric => "n",
+            ZoneOrder::Text => "s",
+        }
+    }
+}
+
+/// Minimum, maximum and null count of a column or block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneMap {
+    /// How the bounds compare.
+    pub order: ZoneOrder,
+    /// Smallest non-null value, or `None` if every value is null.
+    pub min: Option<String>,
+    /// Largest non-null value, or `None` if every value is null.
+    pub max: Option<String>,
+    /// Number of null values.
+    pub null_count: usize,
+}
+
+impl ZoneMap {
+    /// Get the order of a column's values: numeric if every non-null value
+    /// is a number.
+    pub fn order_of(values: &[Value]) -> ZoneOrder {
+        let numeric = values
+            .iter()
+            .all(|v| matches!(v, Value::Null | Value::Integer(_) | Value::Float(_)));
+        if numeric {
+            ZoneOrder::Numeric
+        } else {
+            ZoneOrder::Text
+        }
+    }
+
+    /// Build the zone map of a column or block.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::Value;
+    /// use als_compression::als::{ZoneMap, ZoneOrder};
+    ///
+    /// let values = [Value::Integer(9), Value::Null, Value::Integer(10)];
+    /// let zone = ZoneMap::build(&values, ZoneOrder::Numeric);
+    /// assert_eq!(zone.min.as_deref(), Some("9"));
+    /// assert_eq!(zone.max.as_deref(), Some("10"));
+    /// assert_eq!(zone.null_count, 1);
+    /// ```
+    pub fn build(values: &[Value], order: ZoneOrder) -> Self {
+        let mut zone = Self {
+            order,
+            min: None,
+            max: None,
+            null_count: 0,
+        };
+        for value in values {
+            if value.is_null() {
+                zone.null_count += 1;
+            } else {
+                zone.include(&value.to_string_repr());
+            }
+        }
+        zone
+    }
+
+    /// Widen the zone map to cover another one.
+    pub fn merge(&mut self, other: &ZoneMap) {
+        for bound in other.min.iter().chain(&other.max) {
+            self.include(bound);
+        }
+        self.null_count += other.null_count;
+    }
+
+    /// Check if any value in the zone may lie within `range`.
+    pub fn may_overlap<'a, R: RangeBounds<&'a str>>(&self, range: &R) -> bool {
+        let (Some(min), Some(max)) = (&self.min, &self.max) else {
+            return false;
+        };
+        let after_start = match range.start_bound() {
+            Bound::Included(low) => self.order.compare(max, low) != Some(Ordering::Less),
+            Bound::Excluded(low) => !matches!(self.order.compare(max, low), Some(Ordering::Less | Ordering::Equal)),
+            Bound::Unbounded => true,
+        };
+        let before_end = match range.end_bound() {
+            Bound::Included(high) => self.order.compare(min, high) != Some(Ordering::Greater),
+            Bound::Excluded(high) => !matches!(self.order.compare(min, high), Some(Ordering::Greater | Ordering::Equal)),
+            Bound::Unbounded => true,
+        };
+        // Bounds that don't compare (e.g. text against a numeric column) prune nothing
+        after_start && before_end
+    }
+
+    fn include(&mut self, value: &str) {
+        let order = self.order;
+        let less = |a: &str, b: &str| order.compare(a, b) == Some(Ordering::Less);
+        if self.min.as_deref().is_none_or(|min| less(value, min)) {
+            self.min = Some(value.to_string());
+        }
+        if self.max.as_deref().is_none_or(|max| less(max, value)) {
+            self.max = Some(value.to_string());
+        }
+    }
+
+    /// Write the zone map line for the blocks of `column`, without a
+    /// trailing newline.
+    pub(crate) fn write_line(output: &mut String, column: usize, zones: &[ZoneMap]) {
+        output.push_str(ZONE_PREFIX);
+        output.push_str(&column.to_string());
+        output.push(' ');
+        output.push_str(zones.first().map_or(ZoneOrder::Text, |z| z.order).marker());
+        for zone in zones {
+            for bound in [&zone.min, &zone.max] {
+                output.push(' ');
+                match bound {
+                    Some(value) => output.push_str(&escape_als_string(value)),
+                    None => output.push_str(NULL_TOKEN),
+                }
+            }
+            output.push(' ');
+            output.push_str(&zone.null_count.to_string());
+        }
+    }
+
+    /// Read a `!zone` line at offset `position` back into the number of the
+    /// column it covers and its zone maps, one per block, in the order the
+    /// line gives for their bounds.
+    pub(crate) fn parse_line(line: &str, position: usize) -> Result<(usize, Vec<ZoneMap>)> {
+        let line = TrailerLine::new(line, ZONE_PREFIX, "zone map", position)?;
+        let fields = line.fields();
+        let (column, order, blocks) = match fields.as_slice() {
+            [column, order, blocks @ ..] if !blocks.is_empty() && blocks.len().is_multiple_of(3) => {
+                (column, order, blocks)
+            }
+            _ => return Err(line.error("Zone map line needs a column, an order and whole blocks")),
+        };
+        let column = column
+            .parse()
+            .map_err(|_| line.error("Invalid zone map column"))?;
+        let order = match *order {
+            "n" => ZoneOrder::Numeric,
+            "s" => ZoneOrder::Text,
+            _ => return Err(line.error("Unknown zone map order")),
+        };
+
+        let bound = |field: &str| -> Result<Option<String>> {
+            match field {
+                NULL_TOKEN => Ok(None),
+                _ => unescape_als_string(field).map(Some),
+            }
+        };
+        let zones = blocks
+            .chunks(3)
+            .map(|block| {
+                Ok(ZoneMap {
+                    order,
+                    min: bound(block[0])?,
+                    max: bound(block[1])?,
+                    null_count: block[2]
+                        .parse()
+                        .map_err(|_| line.error("Invalid zone map null count"))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((column, zones))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn text(values: &[&'static str]) -> Vec<Value<'static>> {
+        values.iter().map(|v| Value::String(Cow::Borrowed(*v))).collect()
+    }
+
+    #[test]
+    fn test_numeric_order() {
+        let values = [Value::Integer(9), Value::Float(10.5), Value::Integer(-3)];
+        assert_eq!(ZoneMap::order_of(&values), ZoneOrder::Numeric);
+        let zone = ZoneMap::build(&values, ZoneOrder::Numeric);
+        assert_eq!(zone.min.as_deref(), Some("-3"));
+        assert_eq!(zone.max.as_deref(), Some("10.5"));
+
+        assert!(zone.may_overlap(&("10"..)));
+        assert!(!zone.may_overlap(&("11"..)));
+        assert!(!zone.may_overlap(&(.."-3")));
+        assert!(zone.may_overlap(&(..="-3")));
+    }
+
+    #[test]
+    fn test_text_order() {
+        let values = text(&["pear", "apple", "fig"]);
+        assert_eq!(ZoneMap::order_of(&values), ZoneOrder::Text);
+        let zone = ZoneMap::build(&values, ZoneOrder::Text);
+        assert_eq!(zone.min.as_deref(), Some("apple"));
+        assert_eq!(zone.max.as_deref(), Some("pear"));
+        assert!(zone.may_overlap(&("b".."c")));
+        assert!(!zone.may_overlap(&("q"..)));
+    }
+
+    #[test]
+    fn test_merge_and_nulls() {
+        let mut zone = ZoneMap::build(&[Value::Null, Value::Null], ZoneOrder::Numeric);
+        assert_eq!(zone.min, None);
+        assert!(!zone.may_overlap(&(..)));
+
+        zone.merge(&ZoneMap::build(&[Value::Integer(4), Value::Null], ZoneOrder::Numeric));
+        assert_eq!(zone.min.as_deref(), Some("4"));
+        assert_eq!(zone.max.as_deref(), Some("4"));
+        assert_eq!(zone.null_count, 3);
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(ZoneOrder::Numeric.contains(&("2".."10"), "9"));
+        assert!(!ZoneOrder::Numeric.contains(&("2".."10"), "10"));
+        assert!(!ZoneOrder::Numeric.contains(&(..), "abc"));
+        assert!(!ZoneOrder::Text.contains(&("2".."10"), "100"));
+    }
+
+    #[test]
+    fn test_line_roundtrip() {
+        let zones = vec![
+            ZoneMap::build(&text(&["a b", "x:y"]), ZoneOrder::Text),
+            ZoneMap::build(&[Value::Null], ZoneOrder::Text),
+        ];
+        let mut line = String::new();
+        ZoneMap::write_line(&mut line, 1, &zones);
+        assert_eq!(line, "!zone 1 s a\\ b x\\:y 0 \\0 \\0 1");
+
+        let (column, parsed) = ZoneMap::parse_line(&line, 0).unwrap();
+        assert_eq!(column, 1);
+        assert_eq!(parsed, zones);
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert!(ZoneMap::parse_line("!zone 0 n", 0).is_err());
+        assert!(ZoneMap::parse_line("!zone 0 n 1 2", 0).is_err());
+        assert!(ZoneMap::parse_line("!zone 0 x 1 2 0", 0).is_err());
+        assert!(ZoneMap::parse_line("!zone 0 n 1 2 z", 0).is_err());
+    }
+}
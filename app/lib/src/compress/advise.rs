@@ -0,0 +1,441 @@
+//! Suggestions for restructuring data before compressing it.
+//!
+//! How well a table compresses depends on more than its values: sorted
+//! rows turn scattered values into runs, a composite column split apart
+//! gives each part a pattern of its own, a column placed after the one it
+//! follows can be derived from it, and wide or long layouts suit different
+//! data. `AlsCompressor::advise` picks candidate changes with the
+//! cardinality estimator and the correlation detector, tries each on a
+//! sample of the rows and reports those that would raise the ratio.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::cardinality::CardinalityEstimator;
+use super::compressor::AlsCompressor;
+use crate::als::{AlsSerializer, NULL_TOKEN};
+use crate::convert::csv::to_csv;
+use crate::convert::{Column, ColumnType, TabularData, Value};
+use crate::error::{AlsError, Result};
+use crate::pattern::CorrelationDetector;
+use crate::query::value_order;
+
+/// Number of leading rows changes are tried on.
+pub const ADVICE_SAMPLE_ROWS: usize = 10_000;
+
+/// Smallest gain in ratio, as a factor, worth suggesting a change for.
+const MIN_IMPROVEMENT: f64 = 1.05;
+
+/// Most columns tried as sort keys or pivoted columns.
+const MAX_KEY_COLUMNS: usize = 8;
+
+/// Most distinct values a column may have to be pivoted into columns.
+const MAX_PIVOT_COLUMNS: usize = 32;
+
+/// Delimiters a column may be split on, in order of preference.
+const DELIMITERS: [char; 7] = [' ', '|', ':', '/', '-', '_', '.'];
+
+/// A change to the layout of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Restructure {
+    /// Sort the rows by a column, keeping the order of equal values
+    SortBy {
+        /// Column to sort by
+        column: String,
+    },
+    /// Split a column into one column per part, named `{column}_1` and up
+    Split {
+        /// Column to split
+        column: String,
+        /// Delimiter every value has the same number of
+        delimiter: char,
+    },
+    /// Move a column after a later column it can be derived from
+    MoveAfter {
+        /// Column to move
+        column: String,
+        /// Column determining its values
+        source: String,
+    },
+    /// Pivot a long table wide, as `TabularData::pivot` does
+    Pivot {
+        /// Column whose values name the new columns
+        columns: String,
+        /// Column whose values fill them
+        values: String,
+    },
+    /// Melt a wide table long into `variable` and `value` columns, as
+    /// `TabularData::melt` does
+    Melt {
+        /// Columns kept as they are
+        ids: Vec<String>,
+    },
+}
+
+impl Restructure {
+    /// Apply the change to a table.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the table lacks a column the
+    /// change names, and `AlsError::InvalidReshape` if it cannot be pivoted
+    /// or melted.
+    pub fn apply<'a>(&self, data: TabularData<'a>) -> Result<TabularData<'a>> {
+        let position = |name: &str| {
+            data.columns
+                .iter()
+                .position(|column| column.name == name)
+                .ok_or_else(|| AlsError::UnknownColumn { name: name.to_string() })
+        };
+        match self {
+            Restructure::SortBy { column } => {
+                let position = position(column)?;
+                Ok(sort_by(data, position))
+            }
+            Restructure::Split { column, delimiter } => {
+                let position = position(column)?;
+                Ok(split(data, position, *delimiter))
+            }
+            Restructure::MoveAfter { column, source } => {
+                let (from, after) = (position(column)?, position(source)?);
+                let mut data = data;
+                let moved = data.columns.remove(from);
+                let to = if from < after { after } else { after + 1 };
+                data.columns.insert(to, moved);
+                Ok(data)
+            }
+            Restructure::Pivot { columns, values } => data.pivot(columns, values),
+            Restructure::Melt { ids } => {
+                let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+                data.melt(&ids, "variable", "value")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Restructure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Restructure::SortBy { column } => write!(f, "sort rows by {}", column),
+            Restructure::Split { column, delimiter } => write!(f, "split {} on '{}'", column, delimiter),
+            Restructure::MoveAfter { column, source } => write!(f, "move {} after {}, which determines it", column, source),
+            Restructure::Pivot { columns, values } => write!(f, "pivot {} into columns holding {}", columns, values),
+            Restructure::Melt { ids } if ids.is_empty() => write!(f, "melt all columns into variable and value"),
+            Restructure::Melt { ids } => write!(f, "melt all columns but {} into variable and value", ids.join(", ")),
+        }
+    }
+}
+
+/// A change predicted to improve compression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The change
+    pub change: Restructure,
+    /// Ratio of the sample compressed after the change
+    pub ratio: f64,
+}
+
+/// Changes that would compress a table better, best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advice {
+    /// Number of rows the changes were tried on
+    pub sample_rows: usize,
+    /// Ratio of the sample compressed as it is
+    pub ratio: f64,
+    /// Changes raising the ratio, best first
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl AlsCompressor {
+    /// Suggest changes to the layout of `data` that would compress better.
+    ///
+    /// Candidate changes are sorting by a column with repeated values,
+    /// splitting a column whose values share a delimiter, moving a column
+    /// after one that determines it, pivoting a low-cardinality column over
+    /// a numeric one, and melting the numeric columns. Each is tried on the
+    /// first [`ADVICE_SAMPLE_ROWS`] rows, and ratios compare the size of
+    /// those rows as CSV to their size compressed, so they predict rather
+    /// than guarantee the ratio of the whole table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, Restructure};
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let mut csv = String::from("host,bytes\n");
+    /// for i in 0..300 {
+    ///     let host = i * 7919 % 13 % 3;
+    ///     csv.push_str(&format!("{},{}\n", ["web", "db", "cache"][host], 100 * host));
+    /// }
+    /// let advice = AlsCompressor::new().advise(&parse_csv(&csv).unwrap()).unwrap();
+    ///
+    /// assert!(matches!(advice.suggestions[0].change, Restructure::SortBy { .. }));
+    /// assert!(advice.suggestions[0].ratio > advice.ratio);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample cannot be converted to CSV or
+    /// compressed.
+    pub fn advise(&self, data: &TabularData) -> Result<Advice> {
+        let sample = head(data, ADVICE_SAMPLE_ROWS);
+        let input_size = to_csv(&sample)?.len();
+        let ratio = |data: &TabularData| -> Result<f64> {
+            let doc = self.compress(data)?;
+            Ok(input_size as f64 / AlsSerializer::new().serialize(&doc).len().max(1) as f64)
+        };
+
+        let baseline = ratio(&sample)?;
+        let mut suggestions = Vec::new();
+        for change in candidates(&sample) {
+            // Changes that do not fit the data are simply not suggested
+            let Ok(changed) = change.apply(sample.clone()) else {
+                continue;
+            };
+            let changed_ratio = ratio(&changed)?;
+            if changed_ratio >= baseline * MIN_IMPROVEMENT {
+                suggestions.push(Suggestion {
+                    change,
+                    ratio: changed_ratio,
+                });
+            }
+        }
+        suggestions.sort_by(|a, b| b.ratio.total_cmp(&a.ratio));
+
+        Ok(Advice {
+            sample_rows: sample.row_count,
+            ratio: baseline,
+            suggestions,
+        })
+    }
+}
+
+/// Pick the changes worth trying on a sample.
+fn candidates(sample: &TabularData) -> Vec<Restructure> {
+    let values: Vec<Vec<String>> = sample
+        .columns
+        .iter()
+        .map(|column| column.values.iter().map(|v| v.to_string_repr().into_owned()).collect())
+        .collect();
+    let distinct: Vec<usize> = values.iter().map(CardinalityEstimator::estimate_distinct).collect();
+    let numeric = |column: &Column| matches!(column.inferred_type, ColumnType::Integer | ColumnType::Float);
+    let name = |i: usize| sample.columns[i].name.to_string();
+
+    // Columns with repeated values, fewest distinct values first
+    let mut keys: Vec<usize> = (0..values.len())
+        .filter(|&i| distinct[i] > 1 && distinct[i] * 2 <= sample.row_count)
+        .collect();
+    keys.sort_by_key(|&i| distinct[i]);
+    keys.truncate(MAX_KEY_COLUMNS);
+
+    let mut changes: Vec<Restructure> = keys.iter().map(|&i| Restructure::SortBy { column: name(i) }).collect();
+
+    for (i, column) in sample.columns.iter().enumerate() {
+        if column.inferred_type != ColumnType::String {
+            continue;
+        }
+        let taken = |part: usize| sample.get_column_by_name(&format!("{}_{}", column.name, part)).is_some();
+        if let Some(delimiter) = shared_delimiter(&values[i]).filter(|_| !taken(1)) {
+            changes.push(Restructure::Split {
+                column: name(i),
+                delimiter,
+            });
+        }
+    }
+
+    // The compressor only derives a column from the ones before it
+    let detector = CorrelationDetector::new();
+    for target in 0..values.len() {
+        let source = (target + 1..values.len()).find(|&source| {
+            distinct[target] > 1
+                && distinct[source] <= detector.max_mapping_size()
+                && detector.find_mapping(&values[source], &values[target]).is_some()
+        });
+        if let Some(source) = source {
+            changes.push(Restructure::MoveAfter {
+                column: name(target),
+                source: name(source),
+            });
+        }
+    }
+
+    let measures: Vec<usize> = (0..values.len()).filter(|&i| numeric(&sample.columns[i])).collect();
+    for &columns in keys.iter().filter(|&&i| distinct[i] <= MAX_PIVOT_COLUMNS && !numeric(&sample.columns[i])) {
+        for &measure in measures.iter().take(MAX_KEY_COLUMNS) {
+            changes.push(Restructure::Pivot {
+                columns: name(columns),
+                values: name(measure),
+            });
+        }
+    }
+    if measures.len() >= 2 {
+        let ids = (0..values.len()).filter(|i| !measures.contains(i)).map(name).collect();
+        changes.push(Restructure::Melt { ids });
+    }
+    changes
+}
+
+/// Get the first `rows` rows of a table.
+fn head<'a>(data: &TabularData<'a>, rows: usize) -> TabularData<'a> {
+    let mut sample = TabularData::with_capacity(data.column_count());
+    for column in &data.columns {
+        let values = column.values.iter().take(rows).cloned().collect();
+        sample.add_column(Column::new(column.name.clone(), values));
+    }
+    sample
+}
+
+/// Find a delimiter occurring equally often, and at least once, in every
+/// value of a column but its nulls.
+fn shared_delimiter(values: &[String]) -> Option<char> {
+    let mut values = values.iter().filter(|v| *v != NULL_TOKEN).peekable();
+    let first = values.peek().copied()?;
+    let mut delimiters: Vec<(char, usize)> = DELIMITERS
+        .iter()
+        .map(|&d| (d, first.matches(d).count()))
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    for value in values {
+        delimiters.retain(|&(d, count)| value.matches(d).count() == count);
+    }
+    delimiters.first().map(|&(d, _)| d)
+}
+
+/// Stably sort the rows of a table by the column at `position`, nulls last.
+fn sort_by(data: TabularData<'_>, position: usize) -> TabularData<'_> {
+    let keys: Vec<String> = data.columns[position]
+        .values
+        .iter()
+        .map(|v| v.to_string_repr().into_owned())
+        .collect();
+    let order = value_order(&keys);
+    let mut rows: Vec<usize> = (0..data.row_count).collect();
+    rows.sort_by(|&a, &b| {
+        let (a, b) = (&keys[a], &keys[b]);
+        match (a == NULL_TOKEN, b == NULL_TOKEN) {
+            (false, false) => order.compare(a, b).unwrap_or(Ordering::Equal),
+            (a, b) => a.cmp(&b),
+        }
+    });
+
+    let mut sorted = TabularData::with_capacity(data.column_count());
+    for column in data.columns {
+        let values = rows.iter().map(|&row| column.values[row].clone()).collect();
+        sorted.add_column(Column::new(column.name, values));
+    }
+    sorted
+}
+
+/// Replace the column at `position` with one column per part of its values.
+fn split(data: TabularData<'_>, position: usize, delimiter: char) -> TabularData<'_> {
+    let parts = data.columns[position]
+        .values
+        .iter()
+        .map(|v| v.as_str().map_or(0, |s| s.matches(delimiter).count() + 1))
+        .max()
+        .unwrap_or(0);
+
+    let mut result = TabularData::with_capacity(data.column_count() + parts);
+    for (i, column) in data.columns.into_iter().enumerate() {
+        if i != position {
+            result.add_column(column);
+            continue;
+        }
+        for part in 0..parts {
+            let values = column
+                .values
+                .iter()
+                .map(|v| match v.as_str().and_then(|s| s.split(delimiter).nth(part)) {
+                    Some(s) => Value::String(s.to_string().into()),
+                    None => Value::Null,
+                })
+                .collect();
+            result.add_column(Column::new(format!("{}_{}", column.name, part + 1), values));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::csv::parse_csv;
+
+    #[test]
+    fn test_apply() {
+        let data = parse_csv("id,path,code\n3,a/x,7\n1,b/y,\n2,a/z,5").unwrap();
+        let names = |data: &TabularData| data.column_names().into_iter().map(String::from).collect::<Vec<_>>();
+
+        let sorted = Restructure::SortBy { column: "code".to_string() }.apply(data.clone()).unwrap();
+        assert_eq!(sorted.columns[0].values, vec![Value::Integer(2), Value::Integer(3), Value::Integer(1)]);
+
+        let split = Restructure::Split {
+            column: "path".to_string(),
+            delimiter: '/',
+        }
+        .apply(data.clone())
+        .unwrap();
+        assert_eq!(names(&split), ["id", "path_1", "path_2", "code"]);
+        assert_eq!(split.columns[2].values[1], Value::string("y"));
+
+        let moved = Restructure::MoveAfter {
+            column: "id".to_string(),
+            source: "code".to_string(),
+        }
+        .apply(data.clone())
+        .unwrap();
+        assert_eq!(names(&moved), ["path", "code", "id"]);
+
+        let change = Restructure::SortBy { column: "size".to_string() };
+        assert!(matches!(change.apply(data), Err(AlsError::UnknownColumn { .. })));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let data = parse_csv("kind,side,host,cpu,mem\nbuy,B,web-1,10,70\nsell,S,web-2,20,80\nbuy,B,db-1,30,90\nbuy,B,db-1,40,91")
+            .unwrap();
+        let changes = candidates(&data);
+
+        assert!(changes.contains(&Restructure::SortBy { column: "kind".to_string() }));
+        assert!(changes.contains(&Restructure::Split {
+            column: "host".to_string(),
+            delimiter: '-',
+        }));
+        assert!(changes.contains(&Restructure::MoveAfter {
+            column: "kind".to_string(),
+            source: "side".to_string(),
+        }));
+        assert!(changes.contains(&Restructure::Pivot {
+            columns: "kind".to_string(),
+            values: "cpu".to_string(),
+        }));
+        assert!(changes.contains(&Restructure::Melt {
+            ids: vec!["kind".to_string(), "side".to_string(), "host".to_string()],
+        }));
+    }
+
+    #[test]
+    fn test_advise_suggests_sorting() {
+        let mut csv = String::from("host,bytes\n");
+        for i in 0..300 {
+            let host = i * 7919 % 13 % 3;
+            csv.push_str(&format!("{},{}\n", ["web", "db", "cache"][host], 100 * host));
+        }
+        let advice = AlsCompressor::new().advise(&parse_csv(&csv).unwrap()).unwrap();
+
+        assert_eq!(advice.sample_rows, 300);
+        let best = &advice.suggestions[0];
+        assert_eq!(best.change, Restructure::SortBy { column: "host".to_string() });
+        assert!(best.ratio >= advice.ratio * MIN_IMPROVEMENT);
+        assert!(advice.suggestions.windows(2).all(|pair| pair[0].ratio >= pair[1].ratio));
+    }
+
+    #[test]
+    fn test_advise_without_suggestions() {
+        let csv: String = std::iter::once("id\n".to_string()).chain((1..=100).map(|i| format!("{}\n", i))).collect();
+        let advice = AlsCompressor::new().advise(&parse_csv(&csv).unwrap()).unwrap();
+        assert!(advice.suggestions.is_empty());
+        assert!(advice.ratio > 1.0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Storage for the string views compression and detection work on.
+//!
+//! Every column is turned into a `&[&str]` before the detectors run, and
+//! transforms produce further views of their rewritten values. Built with
+//! plain `Vec`s, that is one allocation per formatted number plus a vector
+//! per column and per candidate, all freed moments later.
+//!
+//! With the `arena` feature these temporaries live in a per-thread bump
+//! arena instead. String values are borrowed rather than copied, numbers
+//! are formatted straight into the arena, and the arena is reset rather
+//! than freed once the closure returns, so its memory is reused by the next
+//! column on the same thread. Without the feature the helpers fall back to
+//! ordinary vectors and behave the same.
+//!
+//! [`ValueStore`] holds the string form of a whole table for the length of
+//! one compression, so raw operators can borrow their values from it
+//! instead of cloning them into every stream. Its [`ValueBuffer`] can be
+//! handed on to the next compression to reuse the allocation.
+
+use std::borrow::Cow;
+
+use crate::convert::{TabularData, Value};
+
+#[cfg(feature = "arena")]
+use bumpalo::{collections::Vec as BumpVec, Bump};
+
+#[cfg(feature = "arena")]
+thread_local! {
+    /// Arena kept between calls on this thread, if one was returned.
+    static ARENA: std::cell::Cell<Option<Bump>> = const { std::cell::Cell::new(None) };
+}
+
+/// Arenas grown past this size are dropped instead of kept for reuse, so
+/// one huge column does not pin its memory for the life of the thread.
+#[cfg(feature = "arena")]
+const MAX_RETAINED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Run `f` with this thread's arena, then reset it for the next caller.
+///
+/// Nested calls get a fresh arena, since the outer one is still borrowed.
+#[cfg(feature = "arena")]
+fn with_bump<R>(f: impl FnOnce(&Bump) -> R) -> R {
+    let mut bump = ARENA.take().unwrap_or_default();
+    let result = f(&bump);
+    if bump.allocated_bytes() <= MAX_RETAINED_BYTES {
+        bump.reset();
+        ARENA.set(Some(bump));
+    }
+    result
+}
+
+/// The string form of each value, with strings borrowed from `values` and
+/// numbers formatted into `bump`.
+#[cfg(feature = "arena")]
+fn value_strs_in<'s>(bump: &'s Bump, values: &'s [Value]) -> BumpVec<'s, &'s str> {
+    let mut strs = BumpVec::with_capacity_in(values.len(), bump);
+    strs.extend(values.iter().map(|value| match value {
+        Value::Integer(i) => bumpalo::format!(in bump, "{}", i).into_bump_str(),
+        Value::Float(x) => bumpalo::format!(in bump, "{}", x).into_bump_str(),
+        value => match value.to_string_repr() {
+            Cow::Borrowed(s) => s,
+            Cow::Owned(s) => bump.alloc_str(&s),
+        },
+    }));
+    strs
+}
+
+/// Run `f` on the string form of each value, as given by
+/// [`Value::to_string_repr`].
+#[cfg(feature = "arena")]
+pub(crate) fn with_value_strs<R>(values: &[Value], f: impl FnOnce(&[&str]) -> R) -> R {
+    with_bump(|bump| f(&value_strs_in(bump, values)))
+}
+
+/// Run `f` on the string form of each value, as given by
+/// [`Value::to_string_repr`].
+#[cfg(not(feature = "arena"))]
+pub(crate) fn with_value_strs<R>(values: &[Value], f: impl FnOnce(&[&str]) -> R) -> R {
+    let strings: Vec<Cow<'_, str>> = values.iter().map(|value| value.to_string_repr()).collect();
+    let strs: Vec<&str> = strings.iter().map(|s| s.as_ref()).collect();
+    f(&strs)
+}
+
+/// Run `f` on borrowed views of `strings`.
+#[cfg(feature = "arena")]
+pub(crate) fn with_strs<R>(strings: &[String], f: impl FnOnce(&[&str]) -> R) -> R {
+    with_bump(|bump| {
+        let mut strs = BumpVec::with_capacity_in(strings.len(), bump);
+        strs.extend(strings.iter().map(String::as_str));
+        f(&strs)
+    })
+}
+
+/// Run `f` on borrowed views of `strings`.
+#[cfg(not(feature = "arena"))]
+pub(crate) fn with_strs<R>(strings: &[String], f: impl FnOnce(&[&str]) -> R) -> R {
+    let strs: Vec<&str> = strings.iter().map(String::as_str).collect();
+    f(&strs)
+}
+
+/// Text of the formatted values of a table, reusable from one compression
+/// to the next.
+#[derive(Debug, Default)]
+pub(crate) struct ValueBuffer {
+    /// Formatted numbers, back to back.
+    text: String,
+    /// End offset of each number in `text`.
+    ends: Vec<usize>,
+}
+
+/// The string form of every value of a table, kept for a whole
+/// compression.
+///
+/// Streams built while compressing borrow their raw values from these
+/// strings instead of copying them, so the store must outlive the
+/// document. String values are borrowed from the table itself; numbers
+/// are formatted once, back to back into a single buffer.
+pub(crate) struct ValueStore<'d> {
+    data: &'d TabularData<'d>,
+    buffer: ValueBuffer,
+}
+
+impl<'d> ValueStore<'d> {
+    /// Create the store for `data`.
+    pub(crate) fn new(data: &'d TabularData<'_>) -> Self {
+        Self::with_buffer(data, ValueBuffer::default())
+    }
+
+    /// Create the store for `data`, formatting into `buffer`.
+    pub(crate) fn with_buffer(data: &'d TabularData<'_>, mut buffer: ValueBuffer) -> Self {
+        use std::fmt::Write;
+
+        buffer.text.clear();
+        buffer.ends.clear();
+        for value in data.columns.iter().flat_map(|column| &column.values) {
+            match value {
+                Value::Integer(i) => write!(buffer.text, "{}", i).unwrap(),
+                Value::Float(x) => write!(buffer.text, "{}", x).unwrap(),
+                _ => continue,
+            }
+            buffer.ends.push(buffer.text.len());
+        }
+        Self { data, buffer }
+    }
+
+    /// Give back the buffer for the next store.
+    pub(crate) fn into_buffer(self) -> ValueBuffer {
+        self.buffer
+    }
+
+    /// Run `f` on the string values of every column.
+    pub(crate) fn with_columns<'s, R>(&'s self, f: impl FnOnce(&[&[&'s str]]) -> R) -> R {
+        let text = self.buffer.text.as_str();
+        let mut ends = self.buffer.ends.iter();
+        let mut start = 0;
+
+        // One flat vector holds every column, back to back
+        let mut values: Vec<&'s str> = Vec::with_capacity(self.data.columns.len() * self.data.row_count);
+        for column in &self.data.columns {
+            values.extend(column.values.iter().map(|value| match value {
+                Value::Integer(_) | Value::Float(_) => {
+                    let end = *ends.next().expect("every number was formatted");
+                    let number = &text[start..end];
+                    start = end;
+                    number
+                }
+                value => match value.to_string_repr() {
+                    Cow::Borrowed(s) => s,
+                    Cow::Owned(_) => unreachable!("only numbers are formatted"),
+                },
+            }));
+        }
+
+        let mut slices: Vec<&[&'s str]> = Vec::with_capacity(self.data.columns.len());
+        let mut rest = values.as_slice();
+        for column in &self.data.columns {
+            let (head, tail) = rest.split_at(column.values.len());
+            slices.push(head);
+            rest = tail;
+        }
+        f(&slices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_strs_match_string_repr() {
+        let values = vec![
+            Value::Integer(-42),
+            Value::Float(2.5),
+            Value::Float(1e21),
+            Value::Null,
+            Value::string(""),
+            Value::string("text"),
+            Value::Boolean(true),
+        ];
+        let expected: Vec<String> = values.iter().map(|v| v.to_string_repr().into_owned()).collect();
+
+        let strs = with_value_strs(&values, |strs| strs.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(strs, expected);
+    }
+
+    #[test]
+    fn test_nested_calls() {
+        let strings = vec!["a".to_string(), "b".to_string()];
+        let joined = with_strs(&strings, |outer| {
+            let inner = with_value_strs(&[Value::Integer(7)], |inner| inner.concat());
+            format!("{}{}", outer.concat(), inner)
+        });
+        assert_eq!(joined, "ab7");
+
+        // The arena is usable again once both calls have returned
+        assert_eq!(with_strs(&strings, |strs| strs.len()), 2);
+    }
+
+    #[test]
+    fn test_value_store_columns() {
+        let mut data = TabularData::new();
+        data.add_column(crate::convert::Column::new("id", vec![Value::Integer(1), Value::Integer(20)]));
+        data.add_column(crate::convert::Column::new("name", vec![Value::string("a"), Value::Null]));
+
+        let store = ValueStore::new(&data);
+        let columns = store.with_columns(|columns| {
+            columns.iter().map(|column| column.iter().map(|s| s.to_string()).collect::<Vec<_>>()).collect::<Vec<_>>()
+        });
+        assert_eq!(columns, vec![vec!["1", "20"], vec!["a", crate::als::NULL_TOKEN]]);
+    }
+}
@@ -4,23 +4,44 @@
 //! of tabular data to ALS format, including CTX fallback when ALS compression
 //! ratio is insufficient.
 
-use crate::als::{AlsDocument, AlsOperator, ColumnStream};
-use crate::als::AlsSerializer;
-use crate::config::CompressorConfig;
-use crate::convert::{TabularData, Value};
+use std::borrow::Cow;
+use std::time::Instant;
+
+use crate::als::{AlsDocument, AlsOperator, ColumnStream, PseudonymKey, EMPTY_TOKEN};
+use crate::als::{AlsParser, AlsSerializer, BloomFilter, ColumnIndex, DeltaOp, DeltaScript, StoreFrame, StoredFormat, ZoneMap};
+use crate::als::foreign_key::encode_foreign_keys;
+use crate::als::quantize::quantize;
+use crate::als::redact::redact;
+use crate::als::transpose::transpose;
+use crate::als::truncate::truncate;
+use crate::als::{dict_ref, front_coding};
+use crate::config::{ColumnStrategy, CompressorConfig};
+use crate::convert::{Column, TabularData, Value};
 use crate::error::{AlsError, Result};
-use crate::pattern::{PatternEngine, PatternType};
+use crate::pattern::{PatternDetector, PatternEngine, PatternType, RangeDetector, SegmentDetector};
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
-use super::dictionary::DictionaryBuilder;
-use super::stats::{ColumnStats, CompressionReport, CompressionStats};
+use super::arena::{self, ValueBuffer, ValueStore};
+use super::dictionary::{DictionaryBuilder, EnumDetector};
+use super::explain::ColumnExplanation;
+use super::metrics::{self, ColumnMetrics, CompressionMetrics, Probe};
+use super::opaque::OpaqueDetector;
+use super::scratch::ScratchBuffers;
+use super::stats::{value_distribution, ColumnStats, CompressionReport, CompressionStats};
 
 /// Default threshold for parallel processing (number of columns * rows).
 /// Below this threshold, sequential processing is used to avoid parallel overhead.
 const PARALLEL_THRESHOLD: usize = 1000;
 
+/// Length of the `!ctx ` marker written before a verbatim column.
+const CTX_COLUMN_MARKER_LEN: usize = 5;
+
+/// Number of nearest preceding columns a column may reference, so wide
+/// tables are not compared column against column.
+const MAX_COLUMN_REF_CANDIDATES: usize = 16;
+
 /// Main entry point for ALS compression.
 ///
 /// The compressor analyzes tabular data, detects patterns, builds dictionaries,
@@ -119,12 +140,38 @@ impl AlsCompressor {
         // Parse CSV to TabularData
         let data = parse_csv(input)?;
 
-        // Compress to ALS document
-        let doc = self.compress(&data)?;
+        // Compress and serialize while the document borrows from the data
+        let als = self.compress_with(&data, |doc| AlsSerializer::new().serialize(&doc))?;
+        Ok(self.store_if_larger(als, StoreFrame::new(StoredFormat::Csv, input)))
+    }
 
-        // Serialize to string
-        let serializer = AlsSerializer::new();
-        Ok(serializer.serialize(&doc))
+    /// Compress tabular data straight to ALS text, reusing `scratch`.
+    ///
+    /// Produces the same text as serializing the document from `compress`,
+    /// but keeps its working buffers in `scratch` so that repeated calls,
+    /// such as a service compressing many small payloads, stop allocating
+    /// them once they have grown to fit. The text is also left in
+    /// [`ScratchBuffers::output`] until the next call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, ScratchBuffers};
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let compressor = AlsCompressor::new();
+    /// let mut scratch = ScratchBuffers::new();
+    /// let data = parse_csv("id,name\n1,Alice\n2,Bob\n3,Charlie").unwrap();
+    /// let als = compressor.compress_into(&data, &mut scratch).unwrap();
+    /// assert!(als.contains("#id #name"));
+    /// ```
+    pub fn compress_into<'s>(&self, data: &TabularData, scratch: &'s mut ScratchBuffers) -> Result<&'s str> {
+        use crate::als::AlsSerializer;
+
+        let output = &mut scratch.output;
+        output.clear();
+        self.compress_with_buffer(data, &mut scratch.values, |doc| AlsSerializer::new().serialize_into(output, &doc))?;
+        Ok(&scratch.output)
     }
 
     /// Compress JSON text to ALS format.
@@ -134,7 +181,8 @@ impl AlsCompressor {
     ///
     /// # Arguments
     ///
-    /// * `input` - JSON text to compress (must be an array of objects)
+    /// * `input` - JSON text to compress (an array of objects, or a document
+    ///   with one at the configured `JsonConfig::records_path`)
     ///
     /// # Returns
     ///
@@ -150,18 +198,132 @@ impl AlsCompressor {
     /// let als = compressor.compress_json(json).unwrap();
     /// ```
     pub fn compress_json(&self, input: &str) -> Result<String> {
-        use crate::convert::json::parse_json;
+        use crate::convert::json::parse_json_with_config;
         use crate::als::AlsSerializer;
 
         // Parse JSON to TabularData
-        let data = parse_json(input)?;
+        let data = parse_json_with_config(input, &self.config.json)?;
+
+        // Compress and serialize while the document borrows from the data
+        let als = self.compress_with(&data, |doc| AlsSerializer::new().serialize(&doc))?;
+        let frame = StoreFrame::new(StoredFormat::Json, input)
+            .with_records_path(self.config.json.records_path.clone());
+        Ok(self.store_if_larger(als, frame))
+    }
+
+    /// Compress an OpenTelemetry OTLP JSON log or metric export to ALS
+    /// format.
+    ///
+    /// Each log record or metric data point becomes a row; see
+    /// [`convert::otlp`](crate::convert::otlp) for the columns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::AlsCompressor;
+    ///
+    /// let export = r#"{"resourceLogs": [{"scopeLogs": [{"logRecords": [
+    ///     {"timeUnixNano": "1700000000000000000", "severityText": "INFO"},
+    ///     {"timeUnixNano": "1700000001000000000", "severityText": "INFO"}
+    /// ]}]}]}"#;
+    /// let als = AlsCompressor::new().compress_otlp(export).unwrap();
+    /// ```
+    pub fn compress_otlp(&self, input: &str) -> Result<String> {
+        use crate::convert::otlp::parse_otlp_json;
+        use crate::als::AlsSerializer;
 
-        // Compress to ALS document
-        let doc = self.compress(&data)?;
+        let data = parse_otlp_json(input)?;
+        self.compress_with(&data, |doc| AlsSerializer::new().serialize(&doc))
+    }
+
+    /// Replace ALS text bigger than its input with a store frame, if
+    /// store mode is allowed and the frame is smaller.
+    ///
+    /// A frame holds the input verbatim, so redacted, quantized, truncated,
+    /// filtered or foreign key input is never stored, nor input with file
+    /// metadata to record.
+    fn store_if_larger(&self, als: String, frame: StoreFrame<'_>) -> String {
+        let frame_len = frame.header_len() + frame.payload.len();
+        let allowed = self.config.allow_store_mode
+            && self.config.column_redactions.is_empty()
+            && self.config.column_quantizations.is_empty()
+            && self.config.column_truncations.is_empty()
+            && self.config.column_foreign_keys.is_empty()
+            && self.config.row_filter.is_none()
+            && self.config.file_metadata.is_none();
+        if allowed && als.len() > frame.payload.len() && frame_len < als.len() {
+            frame.serialize()
+        } else {
+            als
+        }
+    }
 
-        // Serialize to string
-        let serializer = AlsSerializer::new();
-        Ok(serializer.serialize(&doc))
+    /// Build the key that reverses the pseudonymized columns of `data`.
+    ///
+    /// The key maps the tokens `compress` writes for columns redacted with
+    /// `Redaction::Pseudonymize` back to their values, so it must come from
+    /// the same input and configuration. Keep it apart from the document:
+    /// anyone holding both can read the original values.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the row filter names a column
+    /// `data` lacks.
+    pub fn pseudonym_key(&self, data: &TabularData) -> Result<PseudonymKey> {
+        // Filtered rows never reach the document, so their values get no token
+        let filtered = self.filter(data)?;
+        let data = filtered.as_ref().unwrap_or(data);
+        let normalized = self.normalize(data);
+        let mut key = PseudonymKey::new();
+        key.collect(
+            normalized.as_ref().unwrap_or(data),
+            &self.config.column_redactions,
+            &self.config.redaction_salt,
+        );
+        Ok(key)
+    }
+
+    /// Report the best encodings found for each column.
+    ///
+    /// Each explanation holds the winning detection plus up to `top - 1`
+    /// runners-up, best first. Only whole-column patterns are reported;
+    /// columns without one come back as raw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, PatternType};
+    ///
+    /// let compressor = AlsCompressor::new();
+    /// let report = compressor.explain_csv("id\n1\n2\n3\n4\n5", 3).unwrap();
+    /// assert_eq!(report[0].name, "id");
+    /// assert_eq!(report[0].pattern_type(), PatternType::Sequential);
+    /// ```
+    pub fn explain(&self, data: &TabularData, top: usize) -> Vec<ColumnExplanation> {
+        data.columns
+            .iter()
+            .map(|column| {
+                let detection =
+                    arena::with_value_strs(&column.values, |str_refs| self.pattern_engine.detect_n_best(str_refs, top.max(1)));
+                ColumnExplanation::new(column.name.as_ref(), detection)
+            })
+            .collect()
+    }
+
+    /// Parse CSV text and report the best encodings for each column.
+    pub fn explain_csv(&self, input: &str, top: usize) -> Result<Vec<ColumnExplanation>> {
+        use crate::convert::csv::parse_csv;
+
+        let data = parse_csv(input)?;
+        Ok(self.explain(&data, top))
+    }
+
+    /// Parse JSON text and report the best encodings for each column.
+    pub fn explain_json(&self, input: &str, top: usize) -> Result<Vec<ColumnExplanation>> {
+        use crate::convert::json::parse_json_with_config;
+
+        let data = parse_json_with_config(input, &self.config.json)?;
+        Ok(self.explain(&data, top))
     }
 
     /// Compress tabular data to an ALS document.
@@ -180,14 +342,44 @@ impl AlsCompressor {
     /// # Returns
     ///
     /// An `AlsDocument` containing the compressed data.
-    pub fn compress(&self, data: &TabularData) -> Result<AlsDocument> {
+    pub fn compress(&self, data: &TabularData) -> Result<AlsDocument<'static>> {
+        self.compress_with(data, |doc| doc.into_owned())
+    }
+
+    /// Compress tabular data and pass the document to `f`.
+    ///
+    /// Raw values of the document borrow from `data` instead of being
+    /// copied, so callers that only serialize the document avoid copying
+    /// every uncompressed value.
+    fn compress_with<R>(&self, data: &TabularData, f: impl FnOnce(AlsDocument<'_>) -> R) -> Result<R> {
+        self.compress_with_buffer(data, &mut ValueBuffer::default(), f)
+    }
+
+    /// Like `compress_with`, formatting values into `buffer`.
+    fn compress_with_buffer<R>(
+        &self,
+        data: &TabularData,
+        buffer: &mut ValueBuffer,
+        f: impl FnOnce(AlsDocument<'_>) -> R,
+    ) -> Result<R> {
+        let normalized = self.prepare(data)?;
+        let data = normalized.as_ref().unwrap_or(data);
+
         // Handle edge cases
         if data.is_empty() || data.column_count() == 0 {
-            return Ok(self.create_empty_document(data));
+            return Ok(f(self.create_empty_document(data)));
         }
 
+        let store = ValueStore::with_buffer(data, std::mem::take(buffer));
+        let result = store.with_columns(|columns| Ok(f(self.compress_document(data, columns)?)));
+        *buffer = store.into_buffer();
+        result
+    }
+
+    /// Compress non-empty, normalized data whose values are `columns`.
+    fn compress_document<'v>(&self, data: &TabularData, columns: &[&[&'v str]]) -> Result<AlsDocument<'v>> {
         // First, try ALS compression
-        let als_doc = self.compress_als(data)?;
+        let als_doc = self.compress_als(data, columns)?;
         
         // Calculate compression ratio
         let original_size = self.calculate_original_size(data);
@@ -199,18 +391,278 @@ impl AlsCompressor {
         };
 
         // Check if we should fall back to CTX
-        if compression_ratio < self.config.ctx_fallback_threshold {
-            Ok(self.compress_ctx(data))
+        let mut doc = if compression_ratio < self.config.ctx_fallback_threshold {
+            self.compress_ctx(data, columns)
+        } else {
+            als_doc
+        };
+        doc.block_size = self.config.block_size;
+        self.record_preprocessing(&mut doc);
+        self.attach_indexes(data, &mut doc);
+        self.attach_zone_maps(data, &mut doc);
+        Ok(doc)
+    }
+
+    /// Compress tabular data as a delta against a base document.
+    ///
+    /// Rows of `new` that also appear in `base` are recorded as copies of
+    /// base rows, so only added and changed rows are compressed. Rebuild
+    /// `new` with [`AlsParser::apply_delta`](crate::AlsParser::apply_delta).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::ColumnMismatch` or `AlsError::UnknownColumn` if
+    /// `new` doesn't have the columns of `base`, in the same order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, AlsParser};
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let compressor = AlsCompressor::new();
+    /// let base = compressor.compress(&parse_csv("id,status\n1,open\n2,open\n3,open").unwrap()).unwrap();
+    /// let new = parse_csv("id,status\n1,open\n2,closed\n3,open\n4,open").unwrap();
+    ///
+    /// // Only the changed row 2 and the added row 4 are stored
+    /// let delta = compressor.compress_delta(&base, &new).unwrap();
+    /// assert_eq!(delta.row_count(), 2);
+    ///
+    /// let restored = AlsParser::new().apply_delta(&base, &delta).unwrap();
+    /// assert_eq!(restored.row_count, 4);
+    /// ```
+    pub fn compress_delta(&self, base: &AlsDocument, new: &TabularData) -> Result<AlsDocument<'static>> {
+        let normalized = self.prepare(new)?;
+        let new = normalized.as_ref().unwrap_or(new);
+
+        if base.schema.len() != new.column_count() {
+            return Err(AlsError::ColumnMismatch {
+                schema: base.schema.len(),
+                data: new.column_count(),
+            });
+        }
+        if let Some(column) = new.columns.iter().zip(&base.schema).find(|(column, name)| column.name != name.as_str()) {
+            return Err(AlsError::UnknownColumn {
+                name: column.0.name.to_string(),
+            });
+        }
+
+        let base_rows = AlsParser::new().expand(base)?;
+        let new_rows: Vec<Vec<String>> = ValueStore::new(new).with_columns(|columns| {
+            transpose(columns.iter().map(|column| column.iter().map(|s| s.to_string())).collect(), new.row_count)
+        });
+        let script = DeltaScript::diff(&base_rows, &new_rows);
+
+        // Rows of `new` taken by the script's inserts, in order
+        let mut inserted = Vec::with_capacity(script.inserted_rows());
+        let mut row = 0;
+        for op in &script.ops {
+            match op {
+                DeltaOp::Copy(range) => row += range.len(),
+                DeltaOp::Insert(count) => {
+                    inserted.extend(row..row + count);
+                    row += count;
+                }
+            }
+        }
+
+        let mut data = TabularData::with_capacity(new.column_count());
+        for column in &new.columns {
+            let values = inserted.iter().map(|&row| column.values[row].clone()).collect();
+            data.add_column(Column::with_type(column.name.clone(), values, column.inferred_type));
+        }
+
+        let mut doc = self.compress(&data)?;
+        doc.delta = Some(script);
+        Ok(doc)
+    }
+
+    /// Compress the data of an existing document again.
+    ///
+    /// The document's values are expanded and run through this
+    /// compressor's detection, so archives written with other settings or
+    /// an older release can benefit from better encodings without a round
+    /// trip through CSV. Preparation such as redaction already happened
+    /// when the document was written, so it is carried over rather than
+    /// applied again, as are foreign keys, the block size, value indexes,
+    /// zone maps, edit script and file metadata.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, AlsParser, CompressorConfig};
+    ///
+    /// let parser = AlsParser::new();
+    /// let doc = parser.parse("#id #level\n1 2 3 4 5 6|info info info info warn warn").unwrap();
+    ///
+    /// let compressor = AlsCompressor::with_config(CompressorConfig::new().with_level(9));
+    /// let recompressed = compressor.recompress(&doc).unwrap();
+    /// assert_eq!(parser.expand(&recompressed).unwrap(), parser.expand(&doc).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails to expand.
+    pub fn recompress(&self, doc: &AlsDocument) -> Result<AlsDocument<'static>> {
+        let columns = AlsParser::new().expand_columns(doc)?;
+        let data = AlsParser::columns_to_tabular(doc, columns, false);
+
+        let mut config = self.config.clone();
+        config.row_filter = None;
+        config.schema_contract = None;
+        config.normalize_unicode = false;
+        config.column_redactions.clear();
+        config.column_quantizations.clear();
+        config.column_truncations.clear();
+        config.column_foreign_keys.clear();
+        config.block_size = doc.block_size;
+        config.build_indexes = !doc.indexes.is_empty() || !doc.blooms.is_empty();
+        config.build_zone_maps = !doc.zone_maps.is_empty();
+        config.file_metadata = doc.file_metadata.clone();
+
+        let mut recompressed = Self::with_config(config).compress(&data)?;
+        recompressed.delta = doc.delta.clone();
+        recompressed.nfc_normalized = doc.nfc_normalized;
+        recompressed.redactions = doc.redactions.clone();
+        recompressed.quantizations = doc.quantizations.clone();
+        recompressed.truncations = doc.truncations.clone();
+        recompressed.foreign_keys = doc.foreign_keys.clone();
+        Ok(recompressed)
+    }
+
+    /// Normalize `data` to Unicode NFC if configured, or `None` to use it
+    /// as is.
+    fn normalize<'a>(&self, data: &TabularData<'a>) -> Option<TabularData<'a>> {
+        if self.config.normalize_unicode {
+            data.to_nfc()
         } else {
-            Ok(als_doc)
+            None
+        }
+    }
+
+    /// Drop the rows failing the row filter, if one is configured, or
+    /// `None` to keep them all.
+    fn filter<'a>(&self, data: &TabularData<'a>) -> Result<Option<TabularData<'a>>> {
+        match &self.config.row_filter {
+            Some(predicate) => data.clone().filter(predicate).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Filter the rows of `data` as `filter` does and check the rest against
+    /// the schema contract, if one is configured, then normalize it as
+    /// `normalize` does, redact the configured columns, round the quantized
+    /// ones, truncate the times of the truncated ones and replace foreign
+    /// keys with row numbers.
+    fn prepare<'a>(&self, data: &TabularData<'a>) -> Result<Option<TabularData<'a>>> {
+        // Each stage replaces the table of the previous one, dropping it
+        let mut prepared = Cow::Borrowed(data);
+        if let Some(filtered) = self.filter(&prepared)? {
+            prepared = Cow::Owned(filtered);
+        }
+        if let Some(contract) = &self.config.schema_contract {
+            contract.check(&prepared)?;
+        }
+        if let Some(normalized) = self.normalize(&prepared) {
+            prepared = Cow::Owned(normalized);
+        }
+        if let Some(redacted) = redact(&prepared, &self.config.column_redactions, &self.config.redaction_salt)? {
+            prepared = Cow::Owned(redacted);
+        }
+        if let Some(quantized) = quantize(&prepared, &self.config.column_quantizations)? {
+            prepared = Cow::Owned(quantized);
+        }
+        if let Some(truncated) = truncate(&prepared, &self.config.column_truncations)? {
+            prepared = Cow::Owned(truncated);
+        }
+        if let Some(encoded) = encode_foreign_keys(&prepared, &self.config.column_foreign_keys)? {
+            prepared = Cow::Owned(encoded);
+        }
+        Ok(match prepared {
+            Cow::Owned(prepared) => Some(prepared),
+            Cow::Borrowed(_) => None,
+        })
+    }
+
+    /// Record in `doc` how the input was prepared before compression.
+    fn record_preprocessing(&self, doc: &mut AlsDocument) {
+        doc.nfc_normalized = self.config.normalize_unicode;
+        doc.redactions = self
+            .config
+            .column_redactions
+            .iter()
+            .map(|(column, &redaction)| (column.clone(), redaction))
+            .collect();
+        doc.quantizations = self
+            .config
+            .column_quantizations
+            .iter()
+            .map(|(column, &quantization)| (column.clone(), quantization))
+            .collect();
+        doc.truncations = self
+            .config
+            .column_truncations
+            .iter()
+            .map(|(column, &truncation)| (column.clone(), truncation))
+            .collect();
+        doc.foreign_keys = self
+            .config
+            .column_foreign_keys
+            .iter()
+            .map(|(column, (key, _))| (column.clone(), key.clone()))
+            .collect();
+        doc.file_metadata = self.config.file_metadata.clone();
+    }
+
+    /// Append value indexes to the document if configured.
+    ///
+    /// Block mode documents get a Bloom filter per block instead. Otherwise
+    /// columns whose values are all distinct gain nothing from an index and
+    /// are skipped.
+    fn attach_indexes(&self, data: &TabularData, doc: &mut AlsDocument) {
+        if !self.config.build_indexes {
+            return;
+        }
+
+        for (idx, column) in data.columns.iter().enumerate() {
+            let values: Vec<_> = column.values.iter().map(|v| v.to_string_repr()).collect();
+            if let Some(block_size) = doc.block_size {
+                let filters = values.chunks(block_size).map(BloomFilter::build).collect();
+                doc.blooms.insert(idx, filters);
+                continue;
+            }
+            let index = ColumnIndex::build(&values);
+            if index.len() < values.len() {
+                doc.add_index(idx, index);
+            }
         }
     }
 
     /// Compress data using ALS format with pattern detection.
-    fn compress_als(&self, data: &TabularData) -> Result<AlsDocument> {
+    fn compress_als<'v>(&self, data: &TabularData, columns: &[&[&'v str]]) -> Result<AlsDocument<'v>> {
         let mut doc = AlsDocument::with_schema(data.column_names().into_iter().map(String::from).collect());
         doc.set_als_format();
 
+        // Small payloads skip the dictionary and the costlier detectors
+        if self.is_small_payload(data) {
+            let mut streams: Vec<ColumnStream<'v>> = data
+                .columns
+                .iter()
+                .zip(columns)
+                .map(|(column, &values)| {
+                    let (operators, _) = self
+                        .encode_pinned(&column.name, values, &[])
+                        .unwrap_or_else(|| self.encode_quick(values));
+                    ColumnStream::from_operators(operators)
+                })
+                .collect();
+            self.apply_column_ctx_fallback(data, columns, &mut streams);
+            for stream in streams {
+                doc.add_stream(stream);
+            }
+            return Ok(doc);
+        }
+
         // Build dictionary for string values
         let dictionary = self.build_dictionary(data);
         if !dictionary.is_empty() {
@@ -218,7 +670,17 @@ impl AlsCompressor {
         }
 
         // Compress columns (parallel or sequential based on size and config)
-        let streams = self.compress_columns_internal(data, &dictionary)?;
+        let mut streams = match self.config.block_size {
+            Some(block_size) if data.row_count > block_size => {
+                self.compress_blocks(data, columns, &dictionary, block_size)?
+            }
+            _ => {
+                let mut streams = self.compress_columns_internal(data, columns, &dictionary)?;
+                self.link_correlated_columns(data, columns, &mut streams);
+                streams
+            }
+        };
+        self.apply_column_ctx_fallback(data, columns, &mut streams);
         for stream in streams {
             doc.add_stream(stream);
         }
@@ -226,44 +688,146 @@ impl AlsCompressor {
         Ok(doc)
     }
 
+    /// Check whether `data` is small enough for the fast path.
+    ///
+    /// Data split into several blocks, or with a column pinned to the
+    /// dictionary, always takes the full path.
+    fn is_small_payload(&self, data: &TabularData) -> bool {
+        if self.config.block_size.is_some_and(|block_size| data.row_count > block_size) {
+            return false;
+        }
+        // Columns pinned to a dictionary need one built
+        if self.config.column_strategies.values().any(|&strategy| strategy == ColumnStrategy::Dict) {
+            return false;
+        }
+        if data.row_count < self.config.small_payload_rows {
+            return true;
+        }
+
+        // Measured as in `calculate_original_size`, stopping at the limit
+        let limit = self.config.small_payload_bytes;
+        let mut size = 0;
+        for column in &data.columns {
+            size += column.name.len();
+            for value in &column.values {
+                size += value.to_string_repr().len() + 1;
+                if size >= limit {
+                    return false;
+                }
+            }
+        }
+        size < limit
+    }
+
+    /// Encode the values of a small payload with the cheap detectors only.
+    ///
+    /// Falls back to raw values, collapsing runs of the same value.
+    fn encode_quick<'v>(&self, values: &[&'v str]) -> (Vec<AlsOperator<'v>>, PatternType) {
+        let detection = self.pattern_engine.detect_quick(values);
+        if detection.pattern_type != PatternType::Raw && detection.compression_ratio > 1.0 {
+            return (vec![detection.operator], detection.pattern_type);
+        }
+
+        let operators: Vec<AlsOperator<'v>> = values
+            .chunk_by(|a, b| a == b)
+            .map(|run| match run.len() {
+                1 => AlsOperator::raw(run[0]),
+                count => AlsOperator::multiply(AlsOperator::raw(run[0]), count),
+            })
+            .collect();
+        let pattern_type = if operators.len() < values.len() {
+            PatternType::RepeatedMotif
+        } else {
+            PatternType::Raw
+        };
+        (operators, pattern_type)
+    }
+
+    /// Record min/max zone maps for every column if configured.
+    fn attach_zone_maps(&self, data: &TabularData, doc: &mut AlsDocument) {
+        if !self.config.build_zone_maps {
+            return;
+        }
+
+        let block_size = doc.block_size.unwrap_or(data.row_count).max(1);
+        for (idx, column) in data.columns.iter().enumerate() {
+            let order = ZoneMap::order_of(&column.values);
+            let zones = column
+                .values
+                .chunks(block_size)
+                .map(|block| ZoneMap::build(block, order))
+                .collect();
+            doc.zone_maps.insert(idx, zones);
+        }
+    }
+
+    /// Compress each block of rows on its own and join the blocks column by
+    /// column, so that no operator crosses a block boundary.
+    fn compress_blocks<'v>(
+        &self,
+        data: &TabularData,
+        columns: &[&[&'v str]],
+        dictionary: &[String],
+        block_size: usize,
+    ) -> Result<Vec<ColumnStream<'v>>> {
+        let mut streams = vec![ColumnStream::new(); data.column_count()];
+        for start in (0..data.row_count).step_by(block_size) {
+            let rows = start..(start + block_size).min(data.row_count);
+            let block: Vec<&[&str]> = columns.iter().map(|values| &values[rows.clone()]).collect();
+
+            let mut block_streams = self.compress_columns_internal(data, &block, dictionary)?;
+            self.link_correlated_columns(data, &block, &mut block_streams);
+            for (stream, block_stream) in streams.iter_mut().zip(block_streams) {
+                stream.operators.extend(block_stream.operators);
+            }
+        }
+        Ok(streams)
+    }
+
     /// Determine if parallel processing should be used based on data size and config.
-    fn should_use_parallel(&self, data: &TabularData) -> bool {
+    fn should_use_parallel(&self, column_count: usize, row_count: usize) -> bool {
         // Check if parallelism is explicitly disabled (parallelism = 1)
         if self.config.parallelism == 1 {
             return false;
         }
 
         // Use parallel processing if data size exceeds threshold
-        let data_size = data.column_count() * data.row_count;
-        data_size >= PARALLEL_THRESHOLD && data.column_count() > 1
+        let data_size = column_count * row_count;
+        data_size >= PARALLEL_THRESHOLD && column_count > 1
     }
 
     /// Compress columns using either parallel or sequential processing.
-    fn compress_columns_internal(
+    ///
+    /// `columns` holds the values of each column of `data`, or of one
+    /// block of its rows.
+    fn compress_columns_internal<'v>(
         &self,
         data: &TabularData,
+        columns: &[&[&'v str]],
         dictionary: &[String],
-    ) -> Result<Vec<ColumnStream>> {
+    ) -> Result<Vec<ColumnStream<'v>>> {
         #[cfg(feature = "parallel")]
         {
-            if self.should_use_parallel(data) {
-                return self.compress_columns_parallel(data, dictionary);
+            let row_count = columns.first().map_or(0, |values| values.len());
+            if self.should_use_parallel(columns.len(), row_count) {
+                return self.compress_columns_parallel(data, columns, dictionary);
             }
         }
 
         // Sequential compression
-        self.compress_columns_sequential(data, dictionary)
+        self.compress_columns_sequential(data, columns, dictionary)
     }
 
     /// Compress columns sequentially.
-    fn compress_columns_sequential(
+    fn compress_columns_sequential<'v>(
         &self,
         data: &TabularData,
+        columns: &[&[&'v str]],
         dictionary: &[String],
-    ) -> Result<Vec<ColumnStream>> {
+    ) -> Result<Vec<ColumnStream<'v>>> {
         let mut streams = Vec::with_capacity(data.column_count());
-        for column in &data.columns {
-            let stream = self.compress_column(column, dictionary)?;
+        for (column, values) in data.columns.iter().zip(columns) {
+            let stream = self.compress_column(&column.name, values, dictionary)?;
             streams.push(stream);
         }
         Ok(streams)
@@ -271,13 +835,14 @@ impl AlsCompressor {
 
     /// Compress columns in parallel using Rayon.
     #[cfg(feature = "parallel")]
-    fn compress_columns_parallel(
+    fn compress_columns_parallel<'v>(
         &self,
         data: &TabularData,
+        columns: &[&[&'v str]],
         dictionary: &[String],
-    ) -> Result<Vec<ColumnStream>> {
+    ) -> Result<Vec<ColumnStream<'v>>> {
         // Configure thread pool if parallelism is specified
-        let result: Result<Vec<ColumnStream>> = if self.config.parallelism > 1 {
+        let result: Result<Vec<ColumnStream<'v>>> = if self.config.parallelism > 1 {
             // Use a custom thread pool with specified parallelism
             let pool = rayon::ThreadPoolBuilder::new()
                 .num_threads(self.config.parallelism)
@@ -290,46 +855,132 @@ impl AlsCompressor {
             pool.install(|| {
                 data.columns
                     .par_iter()
-                    .map(|column| self.compress_column(column, dictionary))
+                    .zip(columns)
+                    .map(|(column, values)| self.compress_column(&column.name, values, dictionary))
                     .collect()
             })
         } else {
             // Use default Rayon thread pool (auto-detect cores)
             data.columns
                 .par_iter()
-                .map(|column| self.compress_column(column, dictionary))
+                .zip(columns)
+                .map(|(column, values)| self.compress_column(&column.name, values, dictionary))
                 .collect()
         };
 
         result
     }
 
+    /// Replace streams with column references where a column moves in
+    /// lockstep with an earlier one and the reference is smaller.
+    fn link_correlated_columns(&self, data: &TabularData, columns: &[&[&str]], streams: &mut [ColumnStream]) {
+        if data.column_count() < 2 {
+            return;
+        }
+
+        for target in 1..streams.len() {
+            let name = &data.columns[target].name;
+            if self.config.column_strategy(name) != ColumnStrategy::Auto || self.is_opaque(name, columns[target]) {
+                continue;
+            }
+            let current_size = self.estimate_stream_size(&streams[target]);
+            if let Some(stream) = self.find_column_ref(columns[target], &columns[..target], current_size) {
+                streams[target] = stream;
+            }
+        }
+    }
+
+    /// Store columns verbatim whose encoding misses the column CTX threshold.
+    fn apply_column_ctx_fallback<'v>(
+        &self,
+        data: &TabularData,
+        columns: &[&[&'v str]],
+        streams: &mut [ColumnStream<'v>],
+    ) {
+        if self.config.column_ctx_threshold.is_none() {
+            return;
+        }
+
+        for ((column, values), stream) in data.columns.iter().zip(columns).zip(streams.iter_mut()) {
+            if self.config.column_strategy(&column.name) != ColumnStrategy::Auto {
+                continue;
+            }
+            if let Some(ctx) = self.column_ctx_fallback(values, stream) {
+                *stream = ctx;
+            }
+        }
+    }
+
+    /// Get the verbatim CTX stream for `values` if `stream` does not
+    /// compress them by at least the column CTX threshold.
+    fn column_ctx_fallback<'v>(&self, values: &[&'v str], stream: &ColumnStream) -> Option<ColumnStream<'v>> {
+        let threshold = self.config.column_ctx_threshold?;
+        if stream.is_ctx() {
+            return None;
+        }
+
+        let ctx = ColumnStream::ctx(values.iter().copied());
+        let ctx_size = self.estimate_stream_size(&ctx) + CTX_COLUMN_MARKER_LEN;
+        let size = self.estimate_stream_size(stream);
+        let ratio = if size > 0 { ctx_size as f64 / size as f64 } else { f64::INFINITY };
+        (ratio < threshold).then_some(ctx)
+    }
+
+    /// Find the smallest column reference that reproduces `target`.
+    ///
+    /// Only the last [`MAX_COLUMN_REF_CANDIDATES`] columns of `preceding`
+    /// are tried, and only references smaller than `current_size` are
+    /// returned.
+    fn find_column_ref<T: AsRef<str>>(
+        &self,
+        target: &[T],
+        preceding: &[&[&str]],
+        current_size: usize,
+    ) -> Option<ColumnStream<'static>> {
+        let mut best: Option<(ColumnStream<'static>, usize)> = None;
+
+        let nearest = preceding.len().saturating_sub(MAX_COLUMN_REF_CANDIDATES);
+        for (source, source_values) in preceding.iter().enumerate().skip(nearest) {
+            let Some(mapping) = self.pattern_engine.detect_column_mapping(source_values, target) else {
+                continue;
+            };
+            let stream = ColumnStream::from_operators(vec![AlsOperator::column_ref(
+                source,
+                mapping,
+                target.len(),
+            )]);
+            let size = self.estimate_stream_size(&stream);
+            let best_size = best.as_ref().map_or(current_size, |(_, size)| *size);
+            if size < best_size {
+                best = Some((stream, size));
+            }
+        }
+
+        best.map(|(stream, _)| stream)
+    }
+
     /// Compress data using CTX format (no pattern compression).
-    fn compress_ctx(&self, data: &TabularData) -> AlsDocument {
+    fn compress_ctx<'v>(&self, data: &TabularData, columns: &[&[&'v str]]) -> AlsDocument<'v> {
         let mut doc = AlsDocument::with_schema(data.column_names().into_iter().map(String::from).collect());
         doc.set_ctx_format();
 
         // In CTX format, we just use raw values without pattern compression
-        for column in &data.columns {
-            let operators: Vec<AlsOperator> = column
-                .values
-                .iter()
-                .map(|v| AlsOperator::raw(v.to_string_repr().into_owned()))
-                .collect();
-            doc.add_stream(ColumnStream::from_operators(operators));
+        for values in columns {
+            doc.add_stream(values.iter().map(|&value| AlsOperator::raw(value)).collect());
         }
 
         doc
     }
 
     /// Create an empty document for empty input.
-    fn create_empty_document(&self, data: &TabularData) -> AlsDocument {
+    fn create_empty_document(&self, data: &TabularData) -> AlsDocument<'static> {
         let mut doc = AlsDocument::with_schema(data.column_names().into_iter().map(String::from).collect());
         
         // Add empty streams for each column
         for _ in 0..data.column_count() {
             doc.add_stream(ColumnStream::new());
         }
+        self.record_preprocessing(&mut doc);
         
         doc
     }
@@ -340,64 +991,312 @@ impl AlsCompressor {
 
         // Add all string values to the dictionary builder
         for column in &data.columns {
-            for value in &column.values {
-                if let Value::String(s) = value {
-                    builder.add(s.as_ref());
+            match self.config.column_strategy(&column.name) {
+                // Values of raw columns never refer to the dictionary
+                ColumnStrategy::Raw => continue,
+                // Dictionary columns contribute every value, whatever its type
+                ColumnStrategy::Dict => {
+                    let values: Vec<_> = column.values.iter().map(|v| v.to_string_repr()).collect();
+                    builder.add_all(values.iter().map(|v| v.as_ref()));
+                    continue;
+                }
+                ColumnStrategy::Auto | ColumnStrategy::Range => {}
+            }
+
+            let strings: Vec<&str> = column
+                .values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::String(s) => Some(s.as_ref()),
+                    _ => None,
+                })
+                .collect();
+            if self.is_opaque(&column.name, &strings) {
+                continue;
+            }
+
+            // URL, path and hierarchical enum columns contribute their parts
+            // instead, when they compress better part by part
+            let parts = (strings.len() == column.values.len())
+                .then(|| self.split_parts(&strings))
+                .flatten()
+                .filter(|(separator, parts)| self.split_wins(&strings, *separator, parts));
+            match parts {
+                Some((_, parts)) => builder.add_all(parts.into_iter().flatten()),
+                None => {
+                    builder.add_column(&strings);
                 }
             }
         }
 
-        builder.build()
+        Self::order_dictionary(builder.build(), &builder)
     }
 
-    /// Compress a single column.
-    fn compress_column(
-        &self,
-        column: &crate::convert::Column,
-        dictionary: &[String],
-    ) -> Result<ColumnStream> {
-        // Convert values to strings for pattern detection
-        let string_values: Vec<String> = column
-            .values
-            .iter()
-            .map(|v| v.to_string_repr().into_owned())
-            .collect();
+    /// Check whether values split into `parts` encode smaller part by part
+    /// than whole.
+    ///
+    /// Each candidate is encoded against a dictionary of its own values and
+    /// charged for that dictionary's header, so only the parts of columns
+    /// that are actually split end up in the shared dictionary.
+    fn split_wins(&self, values: &[&str], separator: Option<char>, parts: &[Vec<&str>]) -> bool {
+        let mut whole = DictionaryBuilder::with_config(&self.config);
+        whole.add_column(values);
+        let whole = whole.build();
+        let mut segments = DictionaryBuilder::with_config(&self.config);
+        segments.add_all(parts.iter().flatten().copied());
+        let segments = segments.build();
 
-        let str_refs: Vec<&str> = string_values.iter().map(|s| s.as_str()).collect();
+        let whole_size = self.estimate_stream_size(&ColumnStream::from_operators(self.encode_values(values, &whole)))
+            + front_coding::header_len(&whole);
+        let split = ColumnStream::from_operators(vec![self.encode_split_parts(separator, parts, &segments)]);
+        let split_size = self.estimate_stream_size(&split) + front_coding::header_len(&segments);
+        split_size < whole_size
+    }
 
-        // Try pattern detection
-        let detection = self.pattern_engine.detect(&str_refs);
+    /// Sort the dictionary when the shorter front-coded header outweighs
+    /// the longer references frequent values may get after sorting.
+    fn order_dictionary(dictionary: Vec<String>, builder: &DictionaryBuilder) -> Vec<String> {
+        let cost = |entries: &[String]| {
+            let references: usize = entries
+                .iter()
+                .enumerate()
+                .map(|(index, value)| builder.frequency(value) * (1 + dict_ref::len(index)))
+                .sum();
+            front_coding::header_len(entries) + references
+        };
 
-        // If pattern detection found something useful, use it
-        if detection.pattern_type != PatternType::Raw && detection.compression_ratio > 1.0 {
-            return Ok(ColumnStream::from_operators(vec![detection.operator]));
+        let mut sorted = dictionary.clone();
+        sorted.sort();
+        if cost(&sorted) < cost(&dictionary) {
+            sorted
+        } else {
+            dictionary
         }
-
-        // Otherwise, try dictionary references or raw values
-        let operators = self.encode_with_dictionary(&str_refs, dictionary);
-        Ok(ColumnStream::from_operators(operators))
     }
 
-    /// Encode values using dictionary references where beneficial.
-    fn encode_with_dictionary(&self, values: &[&str], dictionary: &[String]) -> Vec<AlsOperator> {
-        // Build a lookup map for dictionary indices
-        let dict_lookup: std::collections::HashMap<&str, usize> = dictionary
-            .iter()
-            .enumerate()
-            .map(|(i, s)| (s.as_str(), i))
-            .collect();
+    /// Compress a single column.
+    fn compress_column<'v>(&self, name: &str, values: &[&'v str], dictionary: &[String]) -> Result<ColumnStream<'v>> {
+        if let Some((operators, _)) = self.encode_pinned(name, values, dictionary) {
+            return Ok(ColumnStream::from_operators(operators));
+        }
+        if self.is_opaque(name, values) {
+            return Ok(ColumnStream::from_operators(values.iter().map(|&value| AlsOperator::raw(value)).collect()));
+        }
 
-        values
-            .iter()
-            .map(|&value| {
-                if let Some(&index) = dict_lookup.get(value) {
-                    AlsOperator::dict_ref(index)
-                } else {
-                    AlsOperator::raw(value)
-                }
-            })
-            .collect()
-    }
+        let stream = ColumnStream::from_operators(self.encode_values(values, dictionary));
+
+        // URL, path and hierarchical enum columns may compress better part
+        // by part
+        if let Some(split) = self.encode_split(values, dictionary) {
+            let split = ColumnStream::from_operators(vec![split]);
+            if self.estimate_stream_size(&split) < self.estimate_stream_size(&stream) {
+                return Ok(split);
+            }
+        }
+
+        Ok(stream)
+    }
+
+    /// Check whether an automatically encoded column looks opaque and
+    /// should be written raw without detection.
+    fn is_opaque<T: AsRef<str>>(&self, column: &str, values: &[T]) -> bool {
+        self.config.detect_opaque_columns
+            && self.config.column_strategy(column) == ColumnStrategy::Auto
+            && metrics::timed("OpaqueDetector", || OpaqueDetector::new().is_opaque(values))
+    }
+
+    /// Encode a column whose strategy is pinned in the configuration.
+    ///
+    /// Returns `None` for columns left to pattern detection.
+    fn encode_pinned<'v>(
+        &self,
+        column: &str,
+        values: &[&'v str],
+        dictionary: &[String],
+    ) -> Option<(Vec<AlsOperator<'v>>, PatternType)> {
+        match self.config.column_strategy(column) {
+            ColumnStrategy::Auto => None,
+            ColumnStrategy::Raw => {
+                let operators = values.iter().map(|&value| AlsOperator::raw(value)).collect();
+                Some((operators, PatternType::Raw))
+            }
+            ColumnStrategy::Dict => Some((self.encode_with_dictionary(values, dictionary), PatternType::Raw)),
+            ColumnStrategy::Range => {
+                let range = RangeDetector::new(self.config.min_pattern_length).detect(values);
+                Some(match range {
+                    Some(detection) => (vec![detection.operator], detection.pattern_type),
+                    None => (self.encode_with_dictionary(values, dictionary), PatternType::Raw),
+                })
+            }
+        }
+    }
+
+    /// Encode values with the best detected patterns.
+    ///
+    /// Long columns are encoded chunk by chunk when a detection chunk size
+    /// is configured, merging operators that continue across chunks.
+    fn encode_values<'v>(&self, values: &[&'v str], dictionary: &[String]) -> Vec<AlsOperator<'v>> {
+        match self.config.detection_chunk_size {
+            Some(size) if values.len() > size => {
+                #[cfg(feature = "parallel")]
+                let chunks: Vec<Vec<AlsOperator<'v>>> = values
+                    .par_chunks(size)
+                    .map(|chunk| self.encode_chunk(chunk, dictionary))
+                    .collect();
+                #[cfg(not(feature = "parallel"))]
+                let chunks: Vec<Vec<AlsOperator<'v>>> = values
+                    .chunks(size)
+                    .map(|chunk| self.encode_chunk(chunk, dictionary))
+                    .collect();
+                // Whole-chunk repeats use raw values; refer to the dictionary
+                // so they merge with the dictionary runs of mixed chunks.
+                let dict_lookup = Self::build_dict_lookup(dictionary);
+                let operators = chunks
+                    .into_iter()
+                    .flatten()
+                    .map(|operator| Self::use_dict_ref(operator, &dict_lookup))
+                    .collect();
+                AlsOperator::merge_adjacent(operators)
+            }
+            _ => self.encode_chunk(values, dictionary),
+        }
+    }
+
+    /// Encode values with the best detected pattern.
+    ///
+    /// Falls back to repeated motifs with dictionary references or raw
+    /// values in between when no single pattern covers all values.
+    fn encode_chunk<'v>(&self, values: &[&'v str], dictionary: &[String]) -> Vec<AlsOperator<'v>> {
+        let detection = self.pattern_engine.detect(values);
+
+        // If pattern detection found something useful, use it
+        if detection.pattern_type != PatternType::Raw && detection.compression_ratio > 1.0 {
+            return vec![detection.operator];
+        }
+
+        self.encode_segments(values, dictionary)
+    }
+
+    /// Encode URL, path or hierarchical enum values as one sub-stream per part.
+    fn encode_split<'v>(&self, values: &[&'v str], dictionary: &[String]) -> Option<AlsOperator<'v>> {
+        let (separator, parts) = self.split_parts(values)?;
+        Some(self.encode_split_parts(separator, &parts, dictionary))
+    }
+
+    /// Split values into the parts of a Split operator.
+    ///
+    /// URL and path values split into segments at `/`. Hierarchical enum
+    /// values such as availability zones (`us-east-1a`) split into a parent
+    /// and a child part that are concatenated without a separator.
+    fn split_parts<'v>(&self, values: &[&'v str]) -> Option<(Option<char>, Vec<Vec<&'v str>>)> {
+        if let Some(parts) = self.pattern_engine.split_segments(values) {
+            return Some((Some(SegmentDetector::SEPARATOR), parts));
+        }
+
+        let hierarchy = EnumDetector::new().split_hierarchical(values)?;
+        let mut parents = Vec::with_capacity(values.len());
+        let mut children = Vec::with_capacity(values.len());
+        for &value in values {
+            let (parent, _) = hierarchy.split(value)?;
+            let (parent, child) = value.split_at(hierarchy.parents[parent].len());
+            parents.push(parent);
+            children.push(child);
+        }
+        Some((None, vec![parents, children]))
+    }
+
+    /// Encode the parts of split values, one sub-stream per part.
+    fn encode_split_parts<'v>(
+        &self,
+        separator: Option<char>,
+        parts: &[Vec<&'v str>],
+        dictionary: &[String],
+    ) -> AlsOperator<'v> {
+        let parts = parts
+            .iter()
+            .map(|part| {
+                // Empty segments (e.g. after `https:`) use the empty token
+                let part: Vec<&'v str> = part
+                    .iter()
+                    .map(|&segment| if segment.is_empty() { EMPTY_TOKEN } else { segment })
+                    .collect();
+                self.encode_values(&part, dictionary)
+            })
+            .collect();
+        AlsOperator::Split { separator, parts }
+    }
+
+    /// Encode values that no single pattern covers.
+    ///
+    /// Repeated motifs found anywhere in the column become multiply or toggle
+    /// operators; the remaining values use dictionary references or raw values.
+    fn encode_segments<'v>(&self, values: &[&'v str], dictionary: &[String]) -> Vec<AlsOperator<'v>> {
+        let spans = self.pattern_engine.detect_motifs(values);
+        if spans.is_empty() {
+            return self.encode_with_dictionary(values, dictionary);
+        }
+
+        let dict_lookup = Self::build_dict_lookup(dictionary);
+        let mut operators = Vec::with_capacity(values.len());
+        let mut position = 0;
+
+        for span in spans {
+            operators.extend(Self::encode_with_lookup(&values[position..span.start], &dict_lookup));
+            operators.push(Self::use_dict_ref(span.to_operator(values), &dict_lookup));
+            position = span.end();
+        }
+        operators.extend(Self::encode_with_lookup(&values[position..], &dict_lookup));
+
+        operators
+    }
+
+    /// Replace the repeated value of a `val*n` operator with its dictionary reference.
+    fn use_dict_ref<'v>(operator: AlsOperator<'v>, dict_lookup: &std::collections::HashMap<&str, usize>) -> AlsOperator<'v> {
+        match operator {
+            AlsOperator::Multiply { value, count } => match value.as_ref() {
+                AlsOperator::Raw(raw) if dict_lookup.contains_key(raw.as_ref()) => {
+                    AlsOperator::multiply(AlsOperator::dict_ref(dict_lookup[raw.as_ref()]), count)
+                }
+                _ => AlsOperator::Multiply { value, count },
+            },
+            other => other,
+        }
+    }
+
+    /// Encode values using dictionary references where beneficial.
+    fn encode_with_dictionary<'v>(&self, values: &[&'v str], dictionary: &[String]) -> Vec<AlsOperator<'v>> {
+        let dict_lookup = Self::build_dict_lookup(dictionary);
+        Self::encode_with_lookup(values, &dict_lookup)
+    }
+
+    /// Build a lookup map for dictionary indices.
+    fn build_dict_lookup(dictionary: &[String]) -> std::collections::HashMap<&str, usize> {
+        dictionary
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.as_str(), i))
+            .collect()
+    }
+
+    /// Map each value to a dictionary reference or a raw operator.
+    ///
+    /// Consecutive identical dictionary references collapse into `_i*n`.
+    fn encode_with_lookup<'v>(
+        values: &[&'v str],
+        dict_lookup: &std::collections::HashMap<&str, usize>,
+    ) -> Vec<AlsOperator<'v>> {
+        let mut operators = Vec::with_capacity(values.len());
+        for run in values.chunk_by(|a, b| a == b) {
+            match dict_lookup.get(run[0]) {
+                Some(&index) if run.len() > 1 => {
+                    operators.push(AlsOperator::multiply(AlsOperator::dict_ref(index), run.len()));
+                }
+                Some(&index) => operators.push(AlsOperator::dict_ref(index)),
+                None => operators.extend(run.iter().map(|&value| AlsOperator::raw(value))),
+            }
+        }
+        operators
+    }
 
     /// Calculate the original size of the data in bytes.
     fn calculate_original_size(&self, data: &TabularData) -> usize {
@@ -448,8 +1347,11 @@ impl AlsCompressor {
             return Ok(false);
         }
 
-        let als_doc = self.compress_als(data)?;
-        let ratio = self.calculate_compression_ratio(data, &als_doc);
+        let store = ValueStore::new(data);
+        let ratio = store.with_columns(|columns| -> Result<f64> {
+            let als_doc = self.compress_als(data, columns)?;
+            Ok(self.calculate_compression_ratio(data, &als_doc))
+        })?;
         
         Ok(ratio < self.config.ctx_fallback_threshold)
     }
@@ -464,7 +1366,7 @@ impl AlsCompressor {
     pub fn would_use_parallel(&self, data: &TabularData) -> bool {
         #[cfg(feature = "parallel")]
         {
-            self.should_use_parallel(data)
+            self.should_use_parallel(data.column_count(), data.row_count)
         }
         #[cfg(not(feature = "parallel"))]
         {
@@ -492,12 +1394,23 @@ impl AlsCompressor {
     /// This method requires the `parallel` feature to be enabled.
     /// Without the feature, it falls back to sequential compression.
     #[cfg(feature = "parallel")]
-    pub fn compress_parallel(&self, data: &TabularData) -> Result<AlsDocument> {
+    pub fn compress_parallel(&self, data: &TabularData) -> Result<AlsDocument<'static>> {
+        let normalized = self.prepare(data)?;
+        let data = normalized.as_ref().unwrap_or(data);
+
         // Handle edge cases
         if data.is_empty() || data.column_count() == 0 {
             return Ok(self.create_empty_document(data));
         }
 
+        let store = ValueStore::new(data);
+        store.with_columns(|columns| Ok(self.compress_document_parallel(data, columns)?.into_owned()))
+    }
+
+    /// Compress non-empty, normalized data whose values are `columns`,
+    /// always in parallel.
+    #[cfg(feature = "parallel")]
+    fn compress_document_parallel<'v>(&self, data: &TabularData, columns: &[&[&'v str]]) -> Result<AlsDocument<'v>> {
         // Build dictionary
         let dictionary = self.build_dictionary(data);
 
@@ -512,7 +1425,7 @@ impl AlsCompressor {
         }
 
         // Force parallel compression
-        let streams = self.compress_columns_parallel(data, &dictionary)?;
+        let streams = self.compress_columns_parallel(data, columns, &dictionary)?;
         for stream in streams {
             doc.add_stream(stream);
         }
@@ -526,11 +1439,15 @@ impl AlsCompressor {
             f64::INFINITY
         };
 
-        if compression_ratio < self.config.ctx_fallback_threshold {
-            Ok(self.compress_ctx(data))
+        let mut doc = if compression_ratio < self.config.ctx_fallback_threshold {
+            self.compress_ctx(data, columns)
         } else {
-            Ok(doc)
-        }
+            doc
+        };
+        self.record_preprocessing(&mut doc);
+        self.attach_indexes(data, &mut doc);
+        self.attach_zone_maps(data, &mut doc);
+        Ok(doc)
     }
 
     /// Compress tabular data and return detailed statistics.
@@ -547,25 +1464,53 @@ impl AlsCompressor {
     ///
     /// A tuple containing the compressed `AlsDocument` and a `CompressionReport`
     /// with detailed statistics.
-    pub fn compress_with_stats(&self, data: &TabularData) -> Result<(AlsDocument, CompressionReport)> {
-        let stats = CompressionStats::new();
-        let mut column_stats = Vec::new();
+    pub fn compress_with_stats(&self, data: &TabularData) -> Result<(AlsDocument<'static>, CompressionReport)> {
+        let normalized = self.prepare(data)?;
+        let data = normalized.as_ref().unwrap_or(data);
 
         // Handle edge cases
         if data.is_empty() || data.column_count() == 0 {
             let doc = self.create_empty_document(data);
-            let snapshot = stats.snapshot();
-            let report = CompressionReport::new(snapshot, column_stats, false, 0.0);
+            let snapshot = CompressionStats::new().snapshot();
+            let report = CompressionReport::new(snapshot, Vec::new(), false, 0.0);
             return Ok((doc, report));
         }
 
+        let store = ValueStore::new(data);
+        store.with_columns(|columns| {
+            let (doc, report) = self.compress_document_with_stats(data, columns)?;
+            Ok((doc.into_owned(), report))
+        })
+    }
+
+    /// Compress non-empty, normalized data whose values are `columns`,
+    /// collecting statistics.
+    fn compress_document_with_stats<'v>(
+        &self,
+        data: &TabularData,
+        columns: &[&[&'v str]],
+    ) -> Result<(AlsDocument<'v>, CompressionReport)> {
+        let started = Instant::now();
+        let stats = CompressionStats::new();
+        let mut column_stats = Vec::new();
+
         // Calculate original size
         let original_size = self.calculate_original_size(data);
         stats.add_input_bytes(original_size as u64);
 
-        // Build dictionary
-        let dictionary = self.build_dictionary(data);
+        // Build dictionary, unless the payload is small enough for the fast path
+        let small = self.is_small_payload(data);
+        let probe = self.config.collect_metrics.then(Probe::start);
+        let dictionary = if small { Vec::new() } else { self.build_dictionary(data) };
         let dict_entries_used = dictionary.len();
+        let mut metrics = probe.map(|probe| {
+            let (dictionary_time, _, dictionary_allocations) = probe.finish();
+            CompressionMetrics {
+                dictionary_time,
+                dictionary_allocations,
+                ..CompressionMetrics::default()
+            }
+        });
 
         // Compress each column and collect stats
         let mut doc = AlsDocument::with_schema(
@@ -577,55 +1522,107 @@ impl AlsCompressor {
             doc.add_dictionary("default", dictionary.clone());
         }
 
-        for (idx, column) in data.columns.iter().enumerate() {
+        for (idx, (column, &str_refs)) in data.columns.iter().zip(columns).enumerate() {
+            let probe = metrics.is_some().then(Probe::start);
             let col_input_size = self.calculate_column_size(column);
-            
-            // Convert values to strings for pattern detection
-            let string_values: Vec<String> = column
-                .values
-                .iter()
-                .map(|v| v.to_string_repr().into_owned())
-                .collect();
-            let str_refs: Vec<&str> = string_values.iter().map(|s| s.as_str()).collect();
-
-            // Try pattern detection
-            let detection = self.pattern_engine.detect(&str_refs);
-            let pattern_type = detection.pattern_type;
 
-            // Determine the stream and track stats
-            let stream = if pattern_type != PatternType::Raw && detection.compression_ratio > 1.0 {
-                stats.record_pattern(pattern_type);
-                ColumnStream::from_operators(vec![detection.operator])
+            let pinned = self.encode_pinned(&column.name, str_refs, &dictionary);
+            let is_pinned = pinned.is_some();
+            let is_opaque = !is_pinned && !small && self.is_opaque(&column.name, str_refs);
+
+            // Determine the stream
+            let (mut stream, mut pattern_type) = if let Some((operators, pattern_type)) = pinned {
+                (ColumnStream::from_operators(operators), pattern_type)
+            } else if is_opaque {
+                let operators = str_refs.iter().map(|&value| AlsOperator::raw(value)).collect();
+                (ColumnStream::from_operators(operators), PatternType::Raw)
+            } else if small {
+                let (operators, pattern_type) = self.encode_quick(str_refs);
+                (ColumnStream::from_operators(operators), pattern_type)
             } else {
-                // Use dictionary references or raw values
-                let operators = self.encode_with_dictionary(&str_refs, &dictionary);
-                
-                // Count dict refs and raw values
-                for op in &operators {
-                    match op {
-                        AlsOperator::DictRef(_) => stats.record_dict_ref(),
-                        AlsOperator::Raw(_) => stats.record_raw_value(),
-                        _ => {}
-                    }
+                // Try pattern detection
+                let detection = self.pattern_engine.detect(str_refs);
+                if detection.pattern_type != PatternType::Raw && detection.compression_ratio > 1.0 {
+                    (ColumnStream::from_operators(vec![detection.operator]), detection.pattern_type)
+                } else {
+                    // Use repeated motifs, dictionary references, or raw values
+                    let operators = self.encode_segments(str_refs, &dictionary);
+                    let pattern_type = if operators.iter().any(|op| !op.is_raw() && !op.is_dict_ref()) {
+                        PatternType::RepeatedMotif
+                    } else {
+                        PatternType::Raw
+                    };
+                    (ColumnStream::from_operators(operators), pattern_type)
                 }
-                
-                ColumnStream::from_operators(operators)
             };
 
-            // Calculate output size for this column
-            let col_output_size = self.estimate_stream_size(&stream);
+            // URL, path and hierarchical enum columns may compress better
+            // part by part
+            let split = (!is_pinned && !is_opaque && !small)
+                .then(|| self.encode_split(str_refs, &dictionary))
+                .flatten()
+                .map(|split| ColumnStream::from_operators(vec![split]))
+                .filter(|split| self.estimate_stream_size(split) < self.estimate_stream_size(&stream));
+            if let Some(split) = split {
+                stream = split;
+                pattern_type = PatternType::Segmented;
+            }
+
+            // Prefer a reference to an earlier column moving in lockstep
+            let mut col_output_size = self.estimate_stream_size(&stream);
+            let column_ref = (!is_pinned && !is_opaque && !small)
+                .then(|| self.find_column_ref(str_refs, &columns[..idx], col_output_size))
+                .flatten();
+            if let Some(column_ref) = column_ref {
+                stream = column_ref;
+                pattern_type = PatternType::Correlated;
+                col_output_size = self.estimate_stream_size(&stream);
+            }
+
+            // Store the column verbatim when its encoding does not pay off
+            let ctx = (!is_pinned).then(|| self.column_ctx_fallback(str_refs, &stream)).flatten();
+            if let Some(ctx) = ctx {
+                stream = ctx;
+                pattern_type = PatternType::Raw;
+                col_output_size = self.estimate_stream_size(&stream) + CTX_COLUMN_MARKER_LEN;
+            }
+
+            // Count patterns, dict refs and raw values
+            for op in &stream.operators {
+                match op {
+                    AlsOperator::DictRef(_) => stats.record_dict_ref(),
+                    AlsOperator::Raw(_) => stats.record_raw_value(),
+                    _ => stats.record_pattern(pattern_type),
+                }
+            }
+
             let was_compressed = col_output_size < col_input_size;
             
             stats.record_column_processed(was_compressed);
 
-            column_stats.push(ColumnStats::new(
-                column.name.to_string(),
-                idx,
-                col_input_size,
-                col_output_size,
-                pattern_type,
-                column.values.len(),
-            ));
+            let (distinct_count, entropy) = value_distribution(str_refs);
+            column_stats.push(
+                ColumnStats::new(
+                    column.name.to_string(),
+                    idx,
+                    col_input_size,
+                    col_output_size,
+                    pattern_type,
+                    column.values.len(),
+                )
+                .with_distribution(distinct_count, entropy),
+            );
+
+            if let (Some(metrics), Some(probe)) = (metrics.as_mut(), probe) {
+                let (elapsed, detectors, allocations) = probe.finish();
+                metrics.columns.push(ColumnMetrics {
+                    name: column.name.to_string(),
+                    index: idx,
+                    elapsed,
+                    detectors,
+                    allocations,
+                });
+            }
 
             doc.add_stream(stream);
         }
@@ -643,11 +1640,14 @@ impl AlsCompressor {
 
         let used_ctx_fallback = compression_ratio < self.config.ctx_fallback_threshold;
         
-        let final_doc = if used_ctx_fallback {
-            self.compress_ctx(data)
+        let mut final_doc = if used_ctx_fallback {
+            self.compress_ctx(data, columns)
         } else {
             doc
         };
+        self.record_preprocessing(&mut final_doc);
+        self.attach_indexes(data, &mut final_doc);
+        self.attach_zone_maps(data, &mut final_doc);
 
         // Calculate dictionary utilization
         let dict_utilization = if !dictionary.is_empty() {
@@ -662,7 +1662,11 @@ impl AlsCompressor {
         };
 
         let snapshot = stats.snapshot();
-        let report = CompressionReport::new(snapshot, column_stats, used_ctx_fallback, dict_utilization);
+        let mut report = CompressionReport::new(snapshot, column_stats, used_ctx_fallback, dict_utilization);
+        if let Some(mut metrics) = metrics {
+            metrics.elapsed = started.elapsed();
+            report = report.with_metrics(metrics);
+        }
 
         Ok((final_doc, report))
     }
@@ -679,15 +1683,7 @@ impl AlsCompressor {
 
     /// Estimate the serialized size of a column stream.
     fn estimate_stream_size(&self, stream: &ColumnStream) -> usize {
-        let serializer = AlsSerializer::new();
-        let mut output = String::new();
-        for (i, op) in stream.operators.iter().enumerate() {
-            if i > 0 {
-                output.push(' ');
-            }
-            serializer.serialize_operator(&mut output, op);
-        }
-        output.len()
+        self.config.cost_model.stream_cost(&stream.operators).ceil() as usize
     }
 
     /// Compress CSV text to ALS format asynchronously.
@@ -731,7 +1727,7 @@ impl AlsCompressor {
             compressor.compress_csv(&input)
         })
         .await
-        .map_err(|e| AlsError::IoError(std::io::Error::new(
+        .map_err(|e| crate::error::AlsError::IoError(std::io::Error::new(
             std::io::ErrorKind::Other,
             format!("Task join error: {}", e),
         )))?
@@ -778,7 +1774,7 @@ impl AlsCompressor {
             compressor.compress_json(&input)
         })
         .await
-        .map_err(|e| AlsError::IoError(std::io::Error::new(
+        .map_err(|e| crate::error::AlsError::IoError(std::io::Error::new(
             std::io::ErrorKind::Other,
             format!("Task join error: {}", e),
         )))?
@@ -802,7 +1798,7 @@ impl AlsCompressor {
     ///
     /// This method requires the `async` feature to be enabled.
     #[cfg(feature = "async")]
-    pub async fn compress_async(&self, data: TabularData<'static>) -> Result<AlsDocument> {
+    pub async fn compress_async(&self, data: TabularData<'static>) -> Result<AlsDocument<'static>> {
         let config = self.config.clone();
         
         // Spawn blocking task to avoid blocking the async executor
@@ -811,7 +1807,7 @@ impl AlsCompressor {
             compressor.compress(&data)
         })
         .await
-        .map_err(|e| AlsError::IoError(std::io::Error::new(
+        .map_err(|e| crate::error::AlsError::IoError(std::io::Error::new(
             std::io::ErrorKind::Other,
             format!("Task join error: {}", e),
         )))?
@@ -828,7 +1824,7 @@ impl Default for AlsCompressor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::als::FormatIndicator;
+    use crate::als::{FormatIndicator, Quantization, Redaction, TimeTruncation};
     use crate::convert::{Column, Value};
     use std::borrow::Cow;
 
@@ -1153,6 +2149,10 @@ mod tests {
         // Check column stats
         assert_eq!(report.columns[0].name, "id");
         assert_eq!(report.columns[1].name, "status");
+        assert_eq!(report.columns[0].distinct_ratio(), 1.0);
+        assert!((report.columns[0].entropy - 10f64.log2()).abs() < 1e-9);
+        assert_eq!(report.columns[1].distinct_count, 1);
+        assert_eq!(report.columns[1].entropy, 0.0);
     }
 
     #[test]
@@ -1202,6 +2202,350 @@ mod tests {
         assert!(report.overall.patterns_detected > 0 || report.overall.raw_values > 0);
     }
 
+    fn create_test_data_with_motif() -> TabularData<'static> {
+        let mut values = vec![Value::string_owned("preamble".to_string())];
+        for _ in 0..40 {
+            for part in ["header", "body", "body", "body", "footer"] {
+                values.push(Value::string_owned(part.to_string()));
+            }
+        }
+        values.push(Value::string_owned("trailer".to_string()));
+
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("section".to_string()), values));
+        data
+    }
+
+    #[test]
+    fn test_compress_embedded_motif() {
+        let compressor = AlsCompressor::new();
+        let data = create_test_data_with_motif();
+
+        let doc = compressor.compress(&data).unwrap();
+
+        assert!(doc.is_als());
+        let operators = &doc.streams[0].operators;
+        assert_eq!(operators.len(), 3);
+        assert!(matches!(operators[1], AlsOperator::Toggle { count: 200, .. }));
+
+        let parser = crate::als::AlsParser::new();
+        let serialized = AlsSerializer::new().serialize(&doc);
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        assert_eq!(rows.len(), 202);
+        assert_eq!(rows[0][0], "preamble");
+        assert_eq!(rows[1][0], "header");
+        assert_eq!(rows[5][0], "footer");
+        assert_eq!(rows[201][0], "trailer");
+    }
+
+    #[test]
+    fn test_compress_daily_dates() {
+        let values: Vec<Value> = (0..365)
+            .map(|day| {
+                let ts = 1_704_067_200 + day * 86_400;
+                Value::string_owned(crate::als::datetime::format_timestamp(ts, "%Y-%m-%d").unwrap())
+            })
+            .collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("day".to_string()), values));
+
+        let compressor = AlsCompressor::new();
+        let doc = compressor.compress(&data).unwrap();
+        assert_eq!(
+            doc.streams[0].operators,
+            vec![AlsOperator::date_range(1_704_067_200, 86_400, 365, "%Y-%m-%d")]
+        );
+
+        let parser = crate::als::AlsParser::new();
+        let serialized = AlsSerializer::new().serialize(&doc);
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        assert_eq!(rows[0][0], "2024-01-01");
+        assert_eq!(rows[59][0], "2024-02-29");
+        assert_eq!(rows[364][0], "2024-12-30");
+    }
+
+    #[test]
+    fn test_compress_csv_declares_features() {
+        let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+        let mut csv = String::from("day\n");
+        for i in 0..60 {
+            csv.push_str(days[i % 7]);
+            csv.push('\n');
+        }
+
+        let als = AlsCompressor::new().compress_csv(&csv).unwrap();
+        assert!(als.starts_with("!v2 +cycle\n#day\n"), "{}", als);
+        let doc = crate::als::AlsParser::new().parse(&als).unwrap();
+        assert_eq!(doc.features().into_iter().collect::<Vec<_>>(), vec![crate::als::Feature::Cycle]);
+
+        let ids: String = (1..=60).map(|id| format!("{}\n", id)).collect();
+        let plain = AlsCompressor::new().compress_csv(&format!("id\n{}", ids)).unwrap();
+        assert!(plain.starts_with("!v1\n#id\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_compress_grouped_enum_uses_dict_runs() {
+        let groups = [("open", 5), ("closed", 2), ("pending", 4), ("open", 1), ("closed", 3)];
+        let values: Vec<Value> = groups
+            .iter()
+            .flat_map(|&(status, n)| std::iter::repeat_n(Value::string_owned(status.to_string()), n))
+            .collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("status".to_string()), values.clone()));
+
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_small_payload_limits(0, 0));
+        let doc = compressor.compress(&data).unwrap();
+        let serialized = AlsSerializer::new().serialize(&doc);
+        assert!(!serialized.contains("open*"), "expected dictionary runs in {}", serialized);
+
+        let parser = crate::als::AlsParser::new();
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        let expanded: Vec<String> = rows.into_iter().map(|mut row| row.remove(0)).collect();
+        let expected: Vec<String> = values.iter().map(|v| v.to_string_repr().into_owned()).collect();
+        assert_eq!(expanded, expected);
+    }
+
+    #[test]
+    fn test_compress_splits_urls() {
+        let hosts = ["api.example.com", "cdn.example.com", "auth.example.com"];
+        let paths = ["v1/users", "v1/orders", "v2/items", "health"];
+        let values: Vec<Value> = (0..60)
+            .map(|i| Value::string_owned(format!("https://{}/{}", hosts[i % 3], paths[(i / 3) % 4])))
+            .collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("url".to_string()), values.clone()));
+
+        let doc = AlsCompressor::new().compress(&data).unwrap();
+        assert!(doc.streams[0].operators[0].is_split());
+        let dictionary = doc.default_dictionary().unwrap();
+        assert!(dictionary.iter().all(|entry| !entry.contains("://")));
+
+        let (with_stats, report) = AlsCompressor::new().compress_with_stats(&data).unwrap();
+        assert!(with_stats.streams[0].operators[0].is_split());
+        assert_eq!(report.columns[0].pattern_type, PatternType::Segmented);
+
+        let serialized = AlsSerializer::new().serialize(&doc);
+        let parser = crate::als::AlsParser::new();
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        for (row, value) in rows.iter().zip(&values) {
+            assert_eq!(row[0], value.to_string_repr());
+        }
+    }
+
+    #[test]
+    fn test_compress_splits_hierarchical_enums() {
+        let regions = ["us-east-1", "us-west-2", "eu-west-1", "eu-central-1", "ap-south-1"];
+        let zones = ["a", "b", "c"];
+        let values: Vec<Value> = (0..120)
+            .map(|i| Value::string_owned(format!("{}{}", regions[(i / 8) % 5], zones[(i * 7 + i / 3) % 3])))
+            .collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("zone".to_string()), values.clone()));
+
+        let doc = AlsCompressor::new().compress(&data).unwrap();
+        assert!(matches!(doc.streams[0].operators[0], AlsOperator::Split { separator: None, .. }));
+        // One entry per region instead of one per zone; the one-letter
+        // children are shorter than references and stay inline
+        let mut dictionary = doc.default_dictionary().unwrap().to_vec();
+        dictionary.sort();
+        let mut expected = regions.to_vec();
+        expected.sort();
+        assert_eq!(dictionary, expected);
+
+        let (with_stats, report) = AlsCompressor::new().compress_with_stats(&data).unwrap();
+        assert!(with_stats.streams[0].operators[0].is_split());
+        assert_eq!(report.columns[0].pattern_type, PatternType::Segmented);
+
+        let serialized = AlsSerializer::new().serialize(&doc);
+        let parser = crate::als::AlsParser::new();
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        for (row, value) in rows.iter().zip(&values) {
+            assert_eq!(row[0], value.to_string_repr());
+        }
+    }
+
+    #[test]
+    fn test_compress_whole_urls_keep_segments_out_of_dictionary() {
+        let urls = ["https://api.example.com/v1/users/list", "https://cdn.example.com/static/img/logo.png"];
+        let values: Vec<Value> = (0..60)
+            .map(|i| Value::string_owned(urls[(i * 7 + i / 5) % 3 % 2].to_string()))
+            .collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("url".to_string()), values.clone()));
+
+        for doc in [
+            AlsCompressor::new().compress(&data).unwrap(),
+            AlsCompressor::new().compress_with_stats(&data).unwrap().0,
+        ] {
+            assert!(!doc.streams[0].operators.iter().any(AlsOperator::is_split));
+            let mut dictionary = doc.default_dictionary().unwrap().to_vec();
+            dictionary.sort();
+            assert_eq!(dictionary, urls);
+        }
+    }
+
+    #[test]
+    fn test_compress_sorts_prefixed_dictionary() {
+        let classes = [
+            "com.example.billing.InvoiceService",
+            "com.example.billing.PaymentService",
+            "com.example.catalog.ProductService",
+            "com.example.catalog.SearchService",
+            "com.example.billing.RefundService",
+        ];
+        let values: Vec<Value> = (0..40)
+            .map(|i| Value::string_owned(classes[(i * 7 + i / 3) % classes.len()].to_string()))
+            .collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("path".to_string()), values.clone()));
+
+        let config = CompressorConfig::new().with_small_payload_limits(0, 0);
+        let doc = AlsCompressor::with_config(config).compress(&data).unwrap();
+        let dictionary = doc.default_dictionary().unwrap();
+        assert!(dictionary.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let serialized = AlsSerializer::new().serialize(&doc);
+        assert!(serialized.starts_with("!v2 +front-coding\n$default~:"));
+
+        let parser = crate::als::AlsParser::new();
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        for (row, value) in rows.iter().zip(&values) {
+            assert_eq!(row[0], value.to_string_repr());
+        }
+    }
+
+    #[test]
+    fn test_compress_boolean_column() {
+        let values: Vec<Value> = (0..200).map(|i| Value::Boolean(i % 10 < 7)).collect();
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("active".to_string()), values));
+
+        let compressor = AlsCompressor::new();
+        let doc = compressor.compress(&data).unwrap();
+        assert!(doc.streams[0].operators.iter().all(|op| op.is_bool_runs()));
+
+        let serialized = AlsSerializer::new().serialize(&doc);
+        let json = crate::als::AlsParser::new().to_json(&serialized).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["active"], true);
+        assert_eq!(parsed[7]["active"], false);
+        assert_eq!(parsed[199]["active"], false);
+    }
+
+    #[test]
+    fn test_compress_with_stats_embedded_motif() {
+        let compressor = AlsCompressor::new();
+        let data = create_test_data_with_motif();
+
+        let (_doc, report) = compressor.compress_with_stats(&data).unwrap();
+
+        assert_eq!(report.columns[0].pattern_type, PatternType::RepeatedMotif);
+        assert_eq!(report.overall.patterns_detected, 1);
+    }
+
+    fn create_test_data_with_lockstep_columns() -> TabularData<'static> {
+        let sides = ["buy", "sell", "sell", "buy", "buy", "sell", "buy", "sell", "sell", "sell"];
+        let side: Vec<Value> = sides
+            .iter()
+            .cycle()
+            .take(60)
+            .map(|s| Value::string_owned(s.to_string()))
+            .collect();
+        let sign: Vec<Value> = sides
+            .iter()
+            .cycle()
+            .take(60)
+            .map(|s| Value::Integer(if *s == "buy" { 1 } else { -1 }))
+            .collect();
+
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Owned("side".to_string()), side));
+        data.add_column(Column::new(Cow::Owned("sign".to_string()), sign));
+        data
+    }
+
+    #[test]
+    fn test_compress_lockstep_columns_use_column_ref() {
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_small_payload_limits(0, 0));
+        let data = create_test_data_with_lockstep_columns();
+
+        let doc = compressor.compress(&data).unwrap();
+
+        assert_eq!(
+            doc.streams[1].operators,
+            vec![AlsOperator::column_ref(
+                0,
+                vec![("buy".to_string(), "1".to_string()), ("sell".to_string(), "-1".to_string())],
+                60
+            )]
+        );
+
+        let parser = crate::als::AlsParser::new();
+        let serialized = AlsSerializer::new().serialize(&doc);
+        let rows = parser.expand(&parser.parse(&serialized).unwrap()).unwrap();
+        assert_eq!(rows.len(), 60);
+        assert_eq!(rows[0], vec!["buy", "1"]);
+        assert_eq!(rows[1], vec!["sell", "-1"]);
+    }
+
+    #[test]
+    fn test_compress_wide_table_references_nearby_columns() {
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_small_payload_limits(0, 0));
+        let lockstep = create_test_data_with_lockstep_columns();
+        let filler = |i: usize| {
+            let values = (0..60).map(|row| Value::Integer((i * 1000 + row) as i64)).collect();
+            Column::new(Cow::Owned(format!("n{}", i)), values)
+        };
+
+        // The sign column follows side across a full window of columns
+        let mut data = TabularData::new();
+        data.add_column(lockstep.columns[0].clone());
+        for i in 0..MAX_COLUMN_REF_CANDIDATES {
+            data.add_column(filler(i));
+        }
+        data.add_column(lockstep.columns[1].clone());
+        let doc = compressor.compress(&data).unwrap();
+        assert!(!doc.streams[MAX_COLUMN_REF_CANDIDATES + 1].has_column_refs());
+
+        // Within the window it is still linked
+        let mut data = TabularData::new();
+        for i in 0..MAX_COLUMN_REF_CANDIDATES {
+            data.add_column(filler(i));
+        }
+        data.add_column(lockstep.columns[0].clone());
+        data.add_column(lockstep.columns[1].clone());
+        let doc = compressor.compress(&data).unwrap();
+        assert_eq!(
+            doc.streams[MAX_COLUMN_REF_CANDIDATES + 1].operators[0],
+            AlsOperator::column_ref(
+                MAX_COLUMN_REF_CANDIDATES,
+                vec![("buy".to_string(), "1".to_string()), ("sell".to_string(), "-1".to_string())],
+                60
+            )
+        );
+    }
+
+    #[test]
+    fn test_compress_with_stats_lockstep_columns() {
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_small_payload_limits(0, 0));
+        let data = create_test_data_with_lockstep_columns();
+
+        let (doc, report) = compressor.compress_with_stats(&data).unwrap();
+
+        assert!(doc.streams[1].has_column_refs());
+        assert_eq!(report.columns[1].pattern_type, PatternType::Correlated);
+    }
+
+    #[test]
+    fn test_compress_independent_columns_keep_own_streams() {
+        let compressor = AlsCompressor::new();
+        let data = create_test_data_with_patterns();
+
+        let doc = compressor.compress(&data).unwrap();
+
+        assert!(doc.streams.iter().all(|s| !s.has_column_refs()));
+    }
+
     #[test]
     fn test_compress_json_basic() {
         let compressor = AlsCompressor::new();
@@ -1226,6 +2570,482 @@ mod tests {
         assert!(!als.is_empty());
     }
 
+    #[test]
+    fn test_compress_chunked_detection_merges_operators() {
+        let mut csv = String::from("id,level\n");
+        for i in 1..=1000 {
+            csv.push_str(&format!("{},{}\n", i, if i <= 600 { "info" } else { "warn" }));
+        }
+
+        let config = CompressorConfig::new().with_detection_chunk_size(128);
+        let als = AlsCompressor::with_config(config).compress_csv(&csv).unwrap();
+        assert!(als.contains("1>1000|_0*600 _1*400"), "operators were not merged: {als}");
+
+        let parser = crate::als::AlsParser::new();
+        let whole = AlsCompressor::new().compress_csv(&csv).unwrap();
+        assert_eq!(parser.to_csv(&als).unwrap(), parser.to_csv(&whole).unwrap());
+    }
+
+    #[test]
+    fn test_explain_reports_runners_up() {
+        let csv = "id,flag\n1,A\n2,B\n3,A\n4,B\n5,A\n6,B";
+        let compressor = AlsCompressor::new();
+
+        let report = compressor.explain_csv(csv, 3).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].name, "id");
+        assert_eq!(report[0].pattern_type(), PatternType::Sequential);
+        assert!(report[0].detection.candidates.len() <= 2);
+        for column in &report {
+            let ratios: Vec<f64> = column.ranked().map(|r| r.compression_ratio).collect();
+            assert!(ratios.windows(2).all(|pair| pair[0] >= pair[1]));
+        }
+
+        let winners_only = compressor.explain_csv(csv, 1).unwrap();
+        assert!(winners_only.iter().all(|column| column.detection.candidates.is_empty()));
+    }
+
+    #[test]
+    fn test_compress_pinned_column_strategies() {
+        use crate::config::ColumnStrategy;
+
+        let csv = "id,status,payload,copy\n1,pending,x,1\n2,pending,x,2\n3,done,x,3\n4,pending,x,4\n5,done,x,5\n6,done,x,6";
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_column_strategy("payload", ColumnStrategy::Raw)
+            .with_column_strategy("status", ColumnStrategy::Dict)
+            .with_column_strategy("copy", ColumnStrategy::Range);
+        let compressor = AlsCompressor::with_config(config);
+        let doc = compressor.compress(&crate::convert::csv::parse_csv(csv).unwrap()).unwrap();
+
+        assert_eq!(doc.streams[0].operators, vec![AlsOperator::range(1, 6)]);
+        assert!(doc.streams[1].operators.iter().all(|op| !op.is_raw()));
+        assert_eq!(doc.streams[2].operators, vec![AlsOperator::raw("x"); 6]);
+        // Pinned columns are not replaced by references to earlier columns
+        assert_eq!(doc.streams[3].operators, vec![AlsOperator::range(1, 6)]);
+        assert!(doc.dictionaries.values().flatten().all(|entry| entry != "x"));
+
+        let (stats_doc, _) = compressor.compress_with_stats(&crate::convert::csv::parse_csv(csv).unwrap()).unwrap();
+        assert_eq!(stats_doc.streams, doc.streams);
+    }
+
+    #[test]
+    fn test_compress_pinned_range_falls_back_to_dictionary() {
+        use crate::config::ColumnStrategy;
+
+        let csv = "id\n1\n2\n4\n8";
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_column_strategy("id", ColumnStrategy::Range);
+        let als = AlsCompressor::with_config(config).compress_csv(csv).unwrap();
+
+        let parser = crate::als::AlsParser::new();
+        assert_eq!(parser.to_csv(&als).unwrap().trim_end(), csv);
+    }
+
+    #[test]
+    fn test_compress_opaque_column_stays_raw() {
+        let mut csv = String::from("id,digest,status");
+        for i in 0..200u64 {
+            let digest = i.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            csv.push_str(&format!("\n{},{:016x}{:016x},{}", i, digest, digest.rotate_left(23), ["ok", "err"][i as usize % 2]));
+        }
+        let data = crate::convert::csv::parse_csv(&csv).unwrap();
+        let compressor = AlsCompressor::new();
+
+        let doc = compressor.compress(&data).unwrap();
+        assert!(doc.streams[1].operators.iter().all(AlsOperator::is_raw));
+        assert!(!doc.streams[2].operators.iter().all(AlsOperator::is_raw));
+
+        let (stats_doc, _) = compressor.compress_with_stats(&data).unwrap();
+        assert_eq!(stats_doc.streams, doc.streams);
+
+        let parser = crate::als::AlsParser::new();
+        let als = AlsSerializer::new().serialize(&doc);
+        assert_eq!(parser.to_csv(&als).unwrap().trim_end(), csv);
+
+        let digests: Vec<_> = data.columns[1].values.iter().map(|v| v.to_string_repr()).collect();
+        assert!(compressor.is_opaque("digest", &digests));
+        let config = CompressorConfig::new().with_opaque_detection(false);
+        assert!(!AlsCompressor::with_config(config).is_opaque("digest", &digests));
+        // Pinned strategies win over detection
+        let config = CompressorConfig::new().with_column_strategy("digest", ColumnStrategy::Dict);
+        assert!(!AlsCompressor::with_config(config).is_opaque("digest", &digests));
+    }
+
+    #[test]
+    fn test_compress_with_stats_metrics() {
+        let data = create_test_data_with_patterns();
+        let (_, report) = AlsCompressor::new().compress_with_stats(&data).unwrap();
+        assert!(report.metrics.is_none());
+
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_metrics(true));
+        let (doc, report) = compressor.compress_with_stats(&data).unwrap();
+        let metrics = report.metrics.unwrap();
+        assert_eq!(metrics.columns.len(), data.column_count());
+        assert_eq!(metrics.columns[1].name, data.columns[1].name);
+        assert!(metrics.columns.iter().all(|column| !column.detectors.is_empty()));
+        assert!(metrics.columns.iter().all(|column| column.elapsed <= metrics.elapsed));
+        assert!(metrics.detector_totals().iter().any(|timing| timing.detector == "RangeDetector"));
+        // The test binary does not install the counting allocator
+        assert!(metrics.columns[0].allocations.is_none());
+
+        assert_eq!(doc.streams, AlsCompressor::new().compress_with_stats(&data).unwrap().0.streams);
+    }
+
+    #[test]
+    fn test_compress_column_ctx_fallback() {
+        let notes = ["red-fox", "lazy-dog", "quick!", "jumps-over"];
+        let mut csv = String::from("id,note");
+        for i in 0..40 {
+            csv.push_str(&format!("\n{},{}{}", i + 1, notes[i % 4], i));
+        }
+        let data = crate::convert::csv::parse_csv(&csv).unwrap();
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_column_ctx_threshold(1.5);
+        let compressor = AlsCompressor::with_config(config);
+
+        let doc = compressor.compress(&data).unwrap();
+        assert!(!doc.is_ctx());
+        assert!(!doc.streams[0].is_ctx());
+        assert!(doc.streams[1].is_ctx());
+
+        let als = AlsSerializer::new().serialize(&doc);
+        assert!(als.contains("|!ctx "), "{als}");
+        let parser = crate::als::AlsParser::new();
+        assert_eq!(parser.to_csv(&als).unwrap().trim_end(), csv);
+        assert!(als.contains("quick\\!2"));
+
+        let (stats_doc, _) = compressor.compress_with_stats(&data).unwrap();
+        assert_eq!(stats_doc.streams, doc.streams);
+
+        // Without a threshold no column is stored verbatim
+        let doc = AlsCompressor::new().compress(&data).unwrap();
+        assert!(doc.streams.iter().all(|stream| !stream.is_ctx()));
+    }
+
+    #[test]
+    fn test_borrowed_document_matches_owned() {
+        let csv = "id,name,score\n1,alice,1.5\n2,bob,2.5\n3,carol,\n4,dave,4.5";
+        let data = crate::convert::csv::parse_csv(csv).unwrap();
+        let compressor = AlsCompressor::new();
+
+        let owned = AlsSerializer::new().serialize(&compressor.compress(&data).unwrap());
+        assert_eq!(compressor.compress_csv(csv).unwrap(), owned);
+
+        // Raw values in the owned document no longer borrow from the input
+        let doc = compressor.compress(&data).unwrap();
+        drop(data);
+        assert_eq!(AlsSerializer::new().serialize(&doc), owned);
+    }
+
+    #[test]
+    fn test_compress_into_reuses_scratch() {
+        let compressor = AlsCompressor::new();
+        let mut scratch = ScratchBuffers::new();
+        let payloads = [
+            "id,score,name\n1,0.5,a\n2,1.5,b\n3,2.5,c\n4,-3,d",
+            "id\n",
+            "n,x\n10,1e21\n20,true\n30,\n40,text",
+            "id,score,name\n1,0.5,a",
+        ];
+        for csv in payloads {
+            let data = crate::convert::csv::parse_csv(csv).unwrap();
+            let expected = AlsSerializer::new().serialize(&compressor.compress(&data).unwrap());
+            assert_eq!(compressor.compress_into(&data, &mut scratch).unwrap(), expected);
+            assert_eq!(scratch.output(), expected);
+        }
+
+        scratch.shrink();
+        assert_eq!(scratch.output(), "");
+    }
+
+    #[test]
+    fn test_small_payload_fast_path() {
+        let csv = "id,status,region\n1,open,eu-west\n2,open,eu-west\n3,closed,us-east\n4,open,eu-west\n5,closed,us-east";
+        let data = crate::convert::csv::parse_csv(csv).unwrap();
+        let fast = AlsCompressor::with_config(CompressorConfig::new().with_ctx_fallback_threshold(1.0));
+
+        let doc = fast.compress(&data).unwrap();
+        assert!(doc.dictionaries.is_empty());
+        assert_eq!(doc.streams[0].operators, vec![AlsOperator::range(1, 5)]);
+        assert_eq!(doc.streams[1].operators[0], AlsOperator::multiply(AlsOperator::raw("open"), 2));
+
+        let (stats_doc, _) = fast.compress_with_stats(&data).unwrap();
+        assert_eq!(stats_doc, doc);
+
+        let parser = crate::als::AlsParser::new();
+        assert_eq!(parser.to_csv(&AlsSerializer::new().serialize(&doc)).unwrap().trim_end(), csv);
+    }
+
+    #[test]
+    fn test_small_payload_limits() {
+        let column = |value: &str| {
+            let mut data = TabularData::new();
+            data.add_column(Column::new("v", vec![Value::string_owned(value.to_string()); 60]));
+            data
+        };
+        let compressor = AlsCompressor::new();
+
+        // Past the row limit, only the byte size decides
+        assert!(compressor.is_small_payload(&column("x")));
+        assert!(!compressor.is_small_payload(&column("a value long enough to pass the byte limit")));
+
+        let rows_only = AlsCompressor::with_config(CompressorConfig::new().with_small_payload_limits(100, 0));
+        assert!(rows_only.is_small_payload(&column("a value long enough to pass the byte limit")));
+
+        let disabled = AlsCompressor::with_config(CompressorConfig::new().with_small_payload_limits(0, 0));
+        assert!(!disabled.is_small_payload(&column("x")));
+
+        let blocks = AlsCompressor::with_config(CompressorConfig::new().with_block_size(10));
+        assert!(!blocks.is_small_payload(&column("x")));
+    }
+
+    #[test]
+    fn test_compress_store_mode() {
+        // Free text that no pattern shortens; escaping makes ALS bigger
+        let csv = "note\nsee a > b\nx * y\nfoo_bar: baz";
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_allow_store_mode(true));
+
+        let stored = compressor.compress_csv(csv).unwrap();
+        assert_eq!(stored, format!("!store csv\n{csv}"));
+        assert!(AlsCompressor::new().compress_csv(csv).unwrap().len() > csv.len());
+
+        let parser = crate::als::AlsParser::new();
+        assert_eq!(parser.to_csv(&stored).unwrap().trim_end(), csv);
+
+        // Compressible input is unaffected
+        let csv = "id\n1\n2\n3\n4\n5\n6\n7\n8";
+        assert_eq!(compressor.compress_csv(csv).unwrap(), AlsCompressor::new().compress_csv(csv).unwrap());
+    }
+
+    #[test]
+    fn test_compress_json_store_mode_keeps_records_path() {
+        let json = r#"{"d":[{"n":"> > > > > > > > > > > > > > > > > > > >"}]}"#;
+        let config = CompressorConfig::new()
+            .with_allow_store_mode(true)
+            .with_json_config(crate::config::JsonConfig::new().with_records_path("/d"));
+
+        let stored = AlsCompressor::with_config(config).compress_json(json).unwrap();
+        assert_eq!(stored, format!("!store json /d\n{json}"));
+
+        let parser = crate::als::AlsParser::new();
+        let doc = parser.parse(&stored).unwrap();
+        assert_eq!(doc.schema, vec!["n"]);
+        assert_eq!(parser.expand(&doc).unwrap(), vec![vec!["> > > > > > > > > > > > > > > > > > > >"]]);
+    }
+
+    #[test]
+    fn test_compress_block_mode() {
+        let csv: String = std::iter::once("id,price\n".to_string())
+            .chain((1..=25).map(|i| format!("{},{}\n", i, i * 10)))
+            .collect();
+        let config = CompressorConfig::new()
+            .with_block_size(10)
+            .with_build_indexes(true);
+        let compressor = AlsCompressor::with_config(config);
+        let als = compressor.compress_csv(&csv).unwrap();
+        assert!(als.contains("\n!blocks 10\n!bloom 0 7 "));
+
+        let parser = crate::als::AlsParser::new();
+        let doc = parser.parse(&als).unwrap();
+        assert_eq!(doc.block_count(), 3);
+        assert_eq!(doc.blooms[&1].len(), 3);
+        assert_eq!(doc.block_rows(2), 20..25);
+
+        // Every block is encoded on its own
+        let block = doc.block_stream(0, 1).unwrap();
+        assert_eq!(block.expand(None).unwrap(), (11..=20).map(|i| i.to_string()).collect::<Vec<_>>());
+        assert_eq!(parser.to_csv(&als).unwrap(), parser.to_csv(&AlsCompressor::new().compress_csv(&csv).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_compress_normalize_unicode() {
+        // The same city, precomposed and with a combining diaeresis
+        let csv: String = std::iter::once("cafe\u{301},city\n".to_string())
+            .chain((0..20).map(|i| format!("{},{}\n", i, if i % 2 == 0 { "Z\u{fc}rich" } else { "Zu\u{308}rich" })))
+            .collect();
+        let parser = AlsParser::new();
+
+        // Left alone by default
+        let doc = parser.parse(&AlsCompressor::new().compress_csv(&csv).unwrap()).unwrap();
+        assert!(!doc.nfc_normalized);
+        assert_eq!(doc.schema[0], "cafe\u{301}");
+        assert_eq!(parser.expand(&doc).unwrap()[1][1], "Zu\u{308}rich");
+
+        let compressor = AlsCompressor::with_config(CompressorConfig::new().with_normalize_unicode(true));
+        let als = compressor.compress_csv(&csv).unwrap();
+        assert!(als.ends_with("\n!nfc"));
+        let doc = parser.parse(&als).unwrap();
+        assert!(doc.nfc_normalized);
+        assert_eq!(doc.schema[0], "caf\u{e9}");
+        assert!(parser.expand(&doc).unwrap().iter().all(|row| row[1] == "Z\u{fc}rich"));
+    }
+
+    #[test]
+    fn test_compress_column_redaction() {
+        let csv = "id,email,ssn,card\n1,ada@example.com,123-45-6789,4111111111111111\n2,bob@example.com,987-65-4321,5500000000000004\n3,ada@example.com,,4111111111111111";
+        let config = CompressorConfig::new()
+            .with_column_redaction("email", Redaction::Hash)
+            .with_column_redaction("ssn", Redaction::Drop)
+            .with_column_redaction("card", Redaction::Mask)
+            .with_redaction_salt("pepper");
+        let als = AlsCompressor::with_config(config).compress_csv(csv).unwrap();
+        assert!(!als.contains("example.com") && !als.contains("6789") && !als.contains("pepper"));
+
+        let parser = AlsParser::new();
+        let doc = parser.parse(&als).unwrap();
+        assert_eq!(doc.schema, vec!["id", "email", "card"]);
+        assert_eq!(doc.redactions.get("ssn"), Some(&Redaction::Drop));
+        assert_eq!(doc.redactions.len(), 3);
+
+        let rows = parser.expand(&doc).unwrap();
+        assert_eq!(rows[0][1], rows[2][1]);
+        assert_ne!(rows[0][1], rows[1][1]);
+        assert_eq!(rows[1][2], "************0004");
+
+        // Store mode would keep the input verbatim
+        let config = CompressorConfig::new().with_allow_store_mode(true).with_column_redaction("ssn", Redaction::Mask);
+        let als = AlsCompressor::with_config(config).compress_csv(csv).unwrap();
+        assert!(!als.contains("123-45-6789"));
+    }
+
+    #[test]
+    fn test_compress_column_quantization() {
+        let mut csv = String::from("id,temp,load\n");
+        for i in 0..60 {
+            csv.push_str(&format!("{},{},{}\n", i, 21.0 + (i % 3) as f64 * 0.000_137_5 + 0.004_2, 0.5 + i as f64 * 0.017));
+        }
+        let config = CompressorConfig::new()
+            .with_column_quantization("temp", Quantization::DecimalPlaces(1))
+            .with_column_quantization("load", Quantization::SignificantDigits(2))
+            .with_column_quantization("missing", Quantization::DecimalPlaces(0));
+        let als = AlsCompressor::with_config(config).compress_csv(&csv).unwrap();
+        assert!(als.contains("21*60"));
+        assert!(als.ends_with("\n!quantize sig:2 load\n!quantize dp:0 missing\n!quantize dp:1 temp"));
+
+        let parser = AlsParser::new();
+        let doc = parser.parse(&als).unwrap();
+        assert_eq!(doc.quantizations.get("temp"), Some(&Quantization::DecimalPlaces(1)));
+        let columns = parser.expand_columns(&doc).unwrap();
+        assert_eq!(columns[2][59], "1.5");
+        assert_eq!(AlsSerializer::new().serialize(&doc), als);
+
+        // Store mode would keep the input verbatim
+        let config = CompressorConfig::new()
+            .with_allow_store_mode(true)
+            .with_column_quantization("temp", Quantization::DecimalPlaces(1));
+        let als = AlsCompressor::with_config(config).compress_csv("temp\n21.0042\n").unwrap();
+        assert!(!als.contains("21.0042"));
+    }
+
+    #[test]
+    fn test_compress_float_deltas() {
+        let mut csv = String::from("id,temp\n");
+        for i in 0..200 {
+            // Readings drifting up by a few hundredths, written as floats print
+            let hundredths = 2095 + i * 37 % 11 + i * 3 / 10;
+            csv.push_str(&format!("{},{}\n", i, hundredths as f64 / 100.0));
+        }
+        let als = AlsCompressor::new().compress_csv(&csv).unwrap();
+        assert!(als.contains("|%.2:2095+"));
+        assert!(als.len() < csv.len() / 3);
+        assert_eq!(AlsParser::new().to_csv(&als).unwrap(), csv);
+    }
+
+    #[test]
+    fn test_compress_column_truncation() {
+        let mut csv = String::from("ts,ms\n");
+        for i in 0..60i64 {
+            csv.push_str(&format!("2024-03-01T12:{:02}:{:02}.{:03}Z,{}\n", i, i * 7 % 60, i * 37 % 1000, 1_709_294_400_000 + i * 60_000 + i * 613 % 60_000));
+        }
+        let config = CompressorConfig::new()
+            .with_column_truncation("ts", TimeTruncation::Minute)
+            .with_column_truncation("ms", TimeTruncation::Minute);
+        let als = AlsCompressor::with_config(config).compress_csv(&csv).unwrap();
+        assert!(als.contains("1709294400000>1709297940000:60000"));
+        assert!(als.ends_with("\n!truncate minute ms\n!truncate minute ts"));
+
+        let parser = AlsParser::new();
+        let doc = parser.parse(&als).unwrap();
+        assert_eq!(doc.truncations.get("ts"), Some(&TimeTruncation::Minute));
+        let columns = parser.expand_columns(&doc).unwrap();
+        assert_eq!(columns[0][59], "2024-03-01T12:59:00Z");
+        assert_eq!(AlsSerializer::new().serialize(&doc), als);
+    }
+
+    #[test]
+    fn test_compress_delta() {
+        let compressor = AlsCompressor::new();
+        let data = create_test_data_with_patterns();
+        let base = compressor.compress(&data).unwrap();
+
+        // An unchanged snapshot is all copies
+        let delta = compressor.compress_delta(&base, &data).unwrap();
+        assert_eq!(delta.row_count(), 0);
+        assert_eq!(delta.delta.as_ref().unwrap().ops, vec![DeltaOp::Copy(0..10)]);
+        let als = AlsSerializer::new().serialize(&delta);
+        let parser = AlsParser::new();
+        let restored = parser.apply_delta(&base, &parser.parse(&als).unwrap()).unwrap();
+        assert_eq!(restored.row_count, 10);
+        assert_eq!(restored.columns[0].values, data.columns[0].values);
+
+        let renamed = data.clone().rename("status", "state").unwrap();
+        assert!(matches!(compressor.compress_delta(&base, &renamed), Err(AlsError::UnknownColumn { .. })));
+        let narrowed = data.select_columns(&["id"]).unwrap();
+        assert!(matches!(compressor.compress_delta(&base, &narrowed), Err(AlsError::ColumnMismatch { .. })));
+    }
+
+    #[test]
+    fn test_recompress() {
+        use crate::convert::csv::parse_csv;
+
+        let csv: String = std::iter::once("id,level,email\n".to_string())
+            .chain((0..200).map(|i| format!("{},{},user{}@example.com\n", i, ["info", "warn"][i % 2], i % 5)))
+            .collect();
+        let writer = AlsCompressor::with_config(
+            CompressorConfig::new()
+                .with_column_strategy("id", ColumnStrategy::Raw)
+                .with_column_redaction("email", Redaction::Mask)
+                .with_block_size(50)
+                .with_zone_maps(true),
+        );
+        let doc = writer.compress(&parse_csv(&csv).unwrap()).unwrap();
+
+        // Preparation configured for recompressing is not applied
+        let recompressor = AlsCompressor::with_config(
+            CompressorConfig::new()
+                .with_level(9)
+                .with_column_redaction("level", Redaction::Drop),
+        );
+        let recompressed = recompressor.recompress(&doc).unwrap();
+        let parser = AlsParser::new();
+        assert_eq!(parser.expand(&recompressed).unwrap(), parser.expand(&doc).unwrap());
+        assert_eq!(recompressed.redactions, doc.redactions);
+        assert_eq!(recompressed.block_size, Some(50));
+        assert_eq!(recompressed.zone_maps.len(), doc.zone_maps.len());
+
+        // The pinned raw ids become a range once detected
+        let serializer = crate::als::AlsSerializer::new();
+        assert!(serializer.serialize(&recompressed).len() < serializer.serialize(&doc).len());
+    }
+
+    #[test]
+    fn test_compress_json_records_path() {
+        use crate::config::JsonConfig;
+
+        let json = r#"{"meta": {"total": 2}, "data": {"items": [{"id": 1}, {"id": 2}]}}"#;
+        assert!(AlsCompressor::new().compress_json(json).is_err());
+
+        let config = CompressorConfig::new()
+            .with_json_config(JsonConfig::new().with_records_path("/data/items"));
+        let als = AlsCompressor::with_config(config).compress_json(json).unwrap();
+
+        let parser = crate::als::AlsParser::new();
+        assert_eq!(parser.to_json(&als).unwrap(), r#"[{"id":1},{"id":2}]"#);
+    }
+
     #[test]
     fn test_compress_json_nested() {
         let compressor = AlsCompressor::new();
@@ -1414,4 +3234,11 @@ mod tests {
         // Should fall back to CTX due to high threshold
         assert!(result.is_ctx());
     }
+
+    #[test]
+    fn test_compressor_is_ref_unwind_safe() {
+        fn assert_ref_unwind_safe<T: std::panic::RefUnwindSafe>() {}
+        assert_ref_unwind_safe::<CompressorConfig>();
+        assert_ref_unwind_safe::<AlsCompressor>();
+    }
 }
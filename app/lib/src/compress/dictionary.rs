@@ -2,11 +2,15 @@
 //!
 //! This module provides the `DictionaryBuilder` which tracks string frequencies
 //! and builds optimal dictionaries for ALS compression. It also includes the
-//! `EnumDetector` for detecting columns with limited distinct values.
+//! `EnumDetector` for detecting columns with limited distinct values, including
+//! structured values that split into two smaller dictionaries.
 
 use std::collections::HashMap;
 
+use super::cardinality::CardinalityEstimator;
+use crate::als::dict_ref;
 use crate::config::CompressorConfig;
+use crate::hashmap::AdaptiveMap;
 
 /// Entry in the dictionary with frequency and compression benefit information.
 #[derive(Debug, Clone, PartialEq)]
@@ -42,7 +46,8 @@ impl DictionaryEntry {
 
     /// Calculate bytes saved by using dictionary reference.
     ///
-    /// Dictionary reference format: `_i` where i is the index.
+    /// Dictionary reference format: `_i` where i is the index, in decimal
+    /// or base-62.
     /// Bytes saved = (value_len * frequency) - (ref_len * frequency) - value_len_in_header
     fn calculate_bytes_saved(value: &str, frequency: usize, index: usize) -> i64 {
         let value_len = value.len() as i64;
@@ -62,12 +67,8 @@ impl DictionaryEntry {
 
     /// Calculate the length of a dictionary reference string.
     fn reference_length(index: usize) -> usize {
-        // Format: _i (underscore + digits)
-        1 + if index == 0 {
-            1
-        } else {
-            (index as f64).log10().floor() as usize + 1
-        }
+        // Format: _i (underscore + decimal or base-62 index)
+        1 + dict_ref::len(index)
     }
 
     /// Check if this entry provides compression benefit.
@@ -89,6 +90,13 @@ pub struct DictionaryBuilder {
 }
 
 impl DictionaryBuilder {
+    /// Columns shorter than this are always tracked; estimating their
+    /// cardinality costs about as much as counting it.
+    pub const CARDINALITY_GATE_MIN_VALUES: usize = 1024;
+    /// Estimated distinct/total ratio above which a column is treated as
+    /// an ID-like column and left out of the dictionary.
+    pub const HIGH_CARDINALITY_RATIO: f64 = 0.95;
+
     /// Create a new dictionary builder with default configuration.
     pub fn new() -> Self {
         Self {
@@ -99,9 +107,13 @@ impl DictionaryBuilder {
 
     /// Create a new dictionary builder with the given configuration.
     pub fn with_config(config: &CompressorConfig) -> Self {
+        let max_entries = match config.max_dictionary_ref_width {
+            Some(width) => config.max_dictionary_entries.min(dict_ref::capacity(width)),
+            None => config.max_dictionary_entries,
+        };
         Self {
             frequencies: HashMap::new(),
-            max_entries: config.max_dictionary_entries,
+            max_entries,
         }
     }
 
@@ -136,6 +148,30 @@ impl DictionaryBuilder {
         self.add_all(values);
     }
 
+    /// Add a column's values unless nearly all of them are distinct.
+    ///
+    /// Values that occur once never make it into the dictionary, so an
+    /// ID-like column would only grow the frequency map. Returns whether
+    /// the column was tracked.
+    pub fn add_column(&mut self, values: &[&str]) -> bool {
+        if Self::is_high_cardinality(values) {
+            return false;
+        }
+        self.add_all(values.iter().copied());
+        true
+    }
+
+    /// Check whether a column is too close to all-distinct to benefit
+    /// from a dictionary, using an approximate distinct count.
+    pub fn is_high_cardinality(values: &[&str]) -> bool {
+        if values.len() < Self::CARDINALITY_GATE_MIN_VALUES {
+            return false;
+        }
+        let mut estimator = CardinalityEstimator::with_precision(14);
+        estimator.extend(values);
+        estimator.estimate() as f64 >= values.len() as f64 * Self::HIGH_CARDINALITY_RATIO
+    }
+
     /// Get the frequency of a value.
     pub fn frequency(&self, value: &str) -> usize {
         self.frequencies.get(value).copied().unwrap_or(0)
@@ -221,6 +257,92 @@ impl Default for DictionaryBuilder {
     }
 }
 
+/// Delimiters at which enum values may be split into parent and child parts.
+const HIERARCHY_DELIMITERS: &[char] = &['-', '_', '.', '/', ':'];
+
+/// Size of the `<2()()` syntax around a column stored as parent and child
+/// references.
+const SPLIT_SYNTAX_SIZE: usize = 6;
+
+/// Estimated size of a stream of dictionary references, with repeated
+/// references collapsed into `_i*n` runs.
+fn reference_size(indices: impl IntoIterator<Item = usize>) -> usize {
+    let mut size = 0;
+    let mut indices = indices.into_iter().peekable();
+    while let Some(index) = indices.next() {
+        let mut run = 1;
+        while indices.next_if_eq(&index).is_some() {
+            run += 1;
+        }
+        // Reference, optional repeat count, and the separating space
+        size += DictionaryEntry::reference_length(index) + 1;
+        if run > 1 {
+            size += 1 + run.to_string().len();
+        }
+    }
+    size
+}
+
+/// Enum values split into a cross product of two smaller dictionaries.
+///
+/// Structured values such as availability zones (`us-east-1a`) decompose
+/// into a parent (`us-east-1`) and a child (`a`). Every value is the
+/// concatenation of one parent and one child, so `regions × zones` values
+/// only need `regions + zones` dictionary entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchicalEnum {
+    /// Distinct parent parts, sorted.
+    pub parents: Vec<String>,
+    /// Distinct child parts, sorted.
+    pub children: Vec<String>,
+}
+
+impl HierarchicalEnum {
+    /// Look up the parent and child indices that make up a value.
+    pub fn split(&self, value: &str) -> Option<(usize, usize)> {
+        self.parents.iter().enumerate().find_map(|(p, parent)| {
+            let child = value.strip_prefix(parent.as_str())?;
+            self.children.iter().position(|c| c == child).map(|c| (p, c))
+        })
+    }
+
+    /// Join a parent and a child back into the original value.
+    pub fn join(&self, parent: usize, child: usize) -> Option<String> {
+        let parent = self.parents.get(parent)?;
+        let child = self.children.get(child)?;
+        Some(format!("{}{}", parent, child))
+    }
+
+    /// Size of both dictionaries in the header, including separators.
+    pub fn dictionary_size(&self) -> usize {
+        Self::flat_size(&self.parents) + Self::flat_size(&self.children)
+    }
+
+    /// Estimated size of `values` stored as parent and child references,
+    /// including both dictionaries. Returns `None` if a value does not split.
+    pub fn encoded_size(&self, values: &[&str]) -> Option<usize> {
+        let indices = values.iter().map(|v| self.split(v)).collect::<Option<Vec<_>>>()?;
+        Some(
+            self.dictionary_size()
+                + SPLIT_SYNTAX_SIZE
+                + reference_size(indices.iter().map(|&(parent, _)| parent))
+                + reference_size(indices.iter().map(|&(_, child)| child)),
+        )
+    }
+
+    /// Size of a single dictionary holding the given entries.
+    fn flat_size(entries: &[String]) -> usize {
+        entries.iter().map(|e| e.len() + 1).sum()
+    }
+
+    /// Estimated size of `values` stored as references into a single
+    /// dictionary of `distinct` entries, including the dictionary.
+    fn flat_encoded_size(distinct: &[String], values: &[&str]) -> usize {
+        let positions: HashMap<&str, usize> = distinct.iter().enumerate().map(|(i, v)| (v.as_str(), i)).collect();
+        Self::flat_size(distinct) + reference_size(values.iter().filter_map(|v| positions.get(v).copied()))
+    }
+}
+
 /// Detector for enum-like and boolean columns.
 ///
 /// Identifies columns with limited distinct values that can benefit from
@@ -234,6 +356,9 @@ pub struct EnumDetector {
 impl EnumDetector {
     /// Default maximum distinct values for enum detection.
     pub const DEFAULT_MAX_DISTINCT: usize = 16;
+    /// Columns at least this long get a cardinality estimate before the
+    /// exact distinct count.
+    pub const CARDINALITY_GATE_MIN_VALUES: usize = 256;
 
     /// Create a new enum detector with default settings.
     pub fn new() -> Self {
@@ -254,6 +379,9 @@ impl EnumDetector {
     /// Returns the two values if the column is boolean-like, with the
     /// "true" value first if boolean normalization is possible.
     pub fn is_boolean_column(&self, values: &[&str]) -> Option<(String, String)> {
+        if self.clearly_exceeds(values, 2) {
+            return None;
+        }
         let distinct = self.get_distinct_values(values);
         
         if distinct.len() != 2 {
@@ -287,6 +415,9 @@ impl EnumDetector {
     ///
     /// Returns the distinct values if the column qualifies as enum-like.
     pub fn is_enum_column(&self, values: &[&str]) -> Option<Vec<String>> {
+        if self.clearly_exceeds(values, self.max_distinct_values) {
+            return None;
+        }
         let distinct = self.get_distinct_values(values);
         
         if distinct.len() <= self.max_distinct_values && distinct.len() > 1 {
@@ -298,22 +429,38 @@ impl EnumDetector {
         }
     }
 
+    /// Check, without counting exactly, whether a long column has well over
+    /// `limit` distinct values.
+    ///
+    /// Short columns are never gated: counting them exactly is as cheap.
+    fn clearly_exceeds(&self, values: &[&str], limit: usize) -> bool {
+        values.len() >= Self::CARDINALITY_GATE_MIN_VALUES
+            && CardinalityEstimator::estimate_distinct(values) > limit.saturating_mul(2)
+    }
+
     /// Get the distinct values in a column.
     pub fn get_distinct_values(&self, values: &[&str]) -> Vec<String> {
-        let mut seen = HashMap::new();
-        for &value in values {
-            seen.entry(value.to_string()).or_insert(());
-        }
-        seen.into_keys().collect()
+        Self::value_counts(values)
+            .into_iter()
+            .map(|(value, _)| value.to_string())
+            .collect()
     }
 
     /// Count distinct values in a column.
     pub fn count_distinct(&self, values: &[&str]) -> usize {
-        let mut seen = HashMap::new();
+        Self::value_counts(values).len()
+    }
+
+    /// Count occurrences of each value in a column.
+    ///
+    /// Starts with inline storage since most columns have only a handful of
+    /// distinct values; high-cardinality columns move to a hash map.
+    pub fn value_counts<'a>(values: &[&'a str]) -> AdaptiveMap<&'a str, usize> {
+        let mut counts = AdaptiveMap::inline();
         for &value in values {
-            seen.entry(value).or_insert(());
+            *counts.entry(value).or_insert(0) += 1;
         }
-        seen.len()
+        counts
     }
 
     /// Normalize a boolean representation to a bool value.
@@ -350,6 +497,63 @@ impl EnumDetector {
         self.is_enum_column(values)
     }
 
+    /// Split a structured enum column into two smaller dictionaries.
+    ///
+    /// Tries splitting each distinct value before a trailing letter suffix
+    /// (`us-east-1` + `a`) or before its last delimiter (`web` + `-prod`),
+    /// and returns the split that encodes the column smallest, counting
+    /// both dictionaries and the extra reference each row carries. Returns
+    /// `None` if the column is not enum-like or no split beats a single
+    /// dictionary.
+    pub fn split_hierarchical(&self, values: &[&str]) -> Option<HierarchicalEnum> {
+        let distinct = self.is_enum_column(values)?;
+        let flat_size = HierarchicalEnum::flat_encoded_size(&distinct, values);
+
+        let rules: [fn(&str) -> Option<usize>; 2] = [Self::letter_suffix_split, Self::delimiter_split];
+        rules
+            .iter()
+            .filter_map(|rule| Self::split_with(&distinct, rule))
+            .filter_map(|split| Some((split.encoded_size(values)?, split)))
+            .filter(|&(size, _)| size < flat_size)
+            .min_by_key(|&(size, _)| size)
+            .map(|(_, split)| split)
+    }
+
+    /// Split every distinct value at the index chosen by `rule`.
+    fn split_with(distinct: &[String], rule: impl Fn(&str) -> Option<usize>) -> Option<HierarchicalEnum> {
+        let mut parents = Vec::new();
+        let mut children = Vec::new();
+        for value in distinct {
+            let (parent, child) = value.split_at(rule(value)?);
+            parents.push(parent.to_string());
+            children.push(child.to_string());
+        }
+
+        for part in [&mut parents, &mut children] {
+            part.sort();
+            part.dedup();
+        }
+
+        // A single parent or child is just a shared affix, not a hierarchy
+        if parents.len() < 2 || children.len() < 2 {
+            return None;
+        }
+
+        Some(HierarchicalEnum { parents, children })
+    }
+
+    /// Split before a trailing run of letters that follows a digit.
+    fn letter_suffix_split(value: &str) -> Option<usize> {
+        let start = value.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+        let preceded_by_digit = value[..start].ends_with(|c: char| c.is_ascii_digit());
+        (start < value.len() && preceded_by_digit).then_some(start)
+    }
+
+    /// Split before the last delimiter, keeping it with the child.
+    fn delimiter_split(value: &str) -> Option<usize> {
+        value.rfind(HIERARCHY_DELIMITERS).filter(|&i| i > 0)
+    }
+
     /// Get the maximum distinct values setting.
     pub fn max_distinct_values(&self) -> usize {
         self.max_distinct_values
@@ -408,12 +612,14 @@ mod tests {
         assert_eq!(DictionaryEntry::reference_length(0), 2);
         // Index 9: "_9" = 2 chars
         assert_eq!(DictionaryEntry::reference_length(9), 2);
-        // Index 10: "_10" = 3 chars
-        assert_eq!(DictionaryEntry::reference_length(10), 3);
-        // Index 99: "_99" = 3 chars
-        assert_eq!(DictionaryEntry::reference_length(99), 3);
-        // Index 100: "_100" = 4 chars
-        assert_eq!(DictionaryEntry::reference_length(100), 4);
+        // Index 10: "_a" = 2 chars
+        assert_eq!(DictionaryEntry::reference_length(10), 2);
+        // Index 61: "_Z" = 2 chars
+        assert_eq!(DictionaryEntry::reference_length(61), 2);
+        // Index 62: "_a0" = 3 chars
+        assert_eq!(DictionaryEntry::reference_length(62), 3);
+        // Index 3286: "_a00" = 4 chars
+        assert_eq!(DictionaryEntry::reference_length(3286), 4);
     }
 
     // DictionaryBuilder tests
@@ -570,6 +776,15 @@ mod tests {
         assert_eq!(builder.max_entries, 100);
     }
 
+    #[test]
+    fn test_dictionary_builder_ref_width() {
+        let config = CompressorConfig::new().with_max_dictionary_ref_width(1);
+        assert_eq!(DictionaryBuilder::with_config(&config).max_entries, 62);
+
+        let config = config.with_max_dictionary_ref_width(3);
+        assert_eq!(DictionaryBuilder::with_config(&config).max_entries, 65_536);
+    }
+
     // EnumDetector tests
 
     #[test]
@@ -686,6 +901,71 @@ mod tests {
         assert!(detector.is_enum_column(&values).is_none());
     }
 
+    #[test]
+    fn test_enum_detector_split_availability_zones() {
+        let detector = EnumDetector::new();
+        let values = vec![
+            "us-east-1a", "us-east-1b", "us-east-1c", "us-west-2a",
+            "us-west-2b", "eu-west-1a", "eu-west-1c", "us-east-1a",
+        ];
+
+        let split = detector.split_hierarchical(&values).unwrap();
+        assert_eq!(split.parents, vec!["eu-west-1", "us-east-1", "us-west-2"]);
+        assert_eq!(split.children, vec!["a", "b", "c"]);
+
+        for value in &values {
+            let (parent, child) = split.split(value).unwrap();
+            assert_eq!(split.join(parent, child).unwrap(), *value);
+        }
+        assert_eq!(split.split("ap-south-1a"), None);
+    }
+
+    #[test]
+    fn test_enum_detector_split_at_delimiter() {
+        let detector = EnumDetector::new();
+        let values = vec![
+            "checkout-prod", "checkout-staging", "payments-prod",
+            "payments-staging", "search-prod", "search-staging",
+        ];
+
+        let split = detector.split_hierarchical(&values).unwrap();
+        assert_eq!(split.parents, vec!["checkout", "payments", "search"]);
+        assert_eq!(split.children, vec!["-prod", "-staging"]);
+
+        let distinct: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        let flat_size = HierarchicalEnum::flat_encoded_size(&distinct, &values);
+        assert_eq!(flat_size, 89 + 6 * 3);
+        assert_eq!(split.encoded_size(&values), Some(40 + 6 + 3 * 5 + 6 * 3));
+    }
+
+    #[test]
+    fn test_enum_detector_split_requires_structure() {
+        let detector = EnumDetector::new();
+        assert!(detector.split_hierarchical(&["red", "green", "blue"]).is_none());
+
+        // A shared prefix alone is not a hierarchy
+        assert!(detector.split_hierarchical(&["zone-a", "zone-b", "zone-c"]).is_none());
+
+        // Splitting must shrink the dictionary
+        assert!(detector.split_hierarchical(&["a-x", "b-y"]).is_none());
+    }
+
+    #[test]
+    fn test_enum_detector_split_counts_references() {
+        let detector = EnumDetector::new();
+        let zones = ["us-east-1a", "us-east-1b", "us-west-2a", "us-west-2b"];
+        let values: Vec<&str> = (0..10)
+            .flat_map(|_| zones.iter().flat_map(|zone| std::iter::repeat_n(*zone, 10)))
+            .collect();
+
+        // The split halves the dictionary, but every run of rows then needs
+        // a parent and a child reference instead of one
+        let distinct = detector.get_distinct_values(&values);
+        let split = EnumDetector::split_with(&distinct, EnumDetector::letter_suffix_split).unwrap();
+        assert!(split.dictionary_size() < HierarchicalEnum::flat_size(&distinct));
+        assert!(detector.split_hierarchical(&values).is_none());
+    }
+
     #[test]
     fn test_enum_detector_get_distinct_values() {
         let detector = EnumDetector::new();
@@ -703,6 +983,50 @@ mod tests {
         assert_eq!(detector.count_distinct(&values), 3);
     }
 
+    #[test]
+    fn test_dictionary_builder_skips_id_columns() {
+        let ids: Vec<String> = (0..5_000).map(|i| format!("user-{i}")).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let mut builder = DictionaryBuilder::new();
+        assert!(!builder.add_column(&ids));
+        assert!(builder.is_empty());
+
+        let statuses: Vec<&str> = (0..5_000).map(|i| if i % 3 == 0 { "error" } else { "ok" }).collect();
+        assert!(builder.add_column(&statuses));
+        assert_eq!(builder.frequency("ok"), 3_333);
+
+        // Short columns are always tracked.
+        assert!(builder.add_column(&ids[..100]));
+        assert_eq!(builder.distinct_count(), 102);
+    }
+
+    #[test]
+    fn test_enum_detector_gates_high_cardinality() {
+        let detector = EnumDetector::new();
+        let ids: Vec<String> = (0..1_000).map(|i| i.to_string()).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        assert!(detector.is_enum_column(&ids).is_none());
+        assert!(detector.is_boolean_column(&ids).is_none());
+
+        let levels: Vec<&str> = (0..1_000).map(|i| ["debug", "info", "warn"][i % 3]).collect();
+        assert_eq!(detector.is_enum_column(&levels).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_enum_detector_value_counts() {
+        let values = vec!["a", "b", "a", "c", "b", "a"];
+        let counts = EnumDetector::value_counts(&values);
+        assert!(counts.is_inline());
+        assert_eq!(counts.get("a"), Some(3));
+        assert_eq!(counts.get("c"), Some(1));
+
+        let ids: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        let counts = EnumDetector::value_counts(&ids);
+        assert!(counts.is_small());
+        assert_eq!(counts.len(), 100);
+    }
+
     #[test]
     fn test_enum_detector_normalize_boolean() {
         // True values
@@ -0,0 +1,60 @@
+//! Per-column detection reports.
+//!
+//! `AlsCompressor::explain` runs the pattern engine over each column and
+//! keeps the runners-up next to the winning encoding, so near-misses are
+//! visible instead of only the greedy choice.
+
+use crate::als::AlsOperator;
+use crate::pattern::{DetectionResult, PatternType};
+
+/// Detection outcome for a single column.
+#[derive(Debug, Clone)]
+pub struct ColumnExplanation {
+    /// Column name.
+    pub name: String,
+    /// Winning detection, with runners-up in `detection.candidates`.
+    pub detection: DetectionResult,
+}
+
+impl ColumnExplanation {
+    /// Create an explanation for a column.
+    pub fn new(name: impl Into<String>, detection: DetectionResult) -> Self {
+        Self {
+            name: name.into(),
+            detection,
+        }
+    }
+
+    /// Pattern type of the winning encoding.
+    pub fn pattern_type(&self) -> PatternType {
+        self.detection.pattern_type
+    }
+
+    /// Operator of the winning encoding.
+    pub fn operator(&self) -> &AlsOperator<'static> {
+        &self.detection.operator
+    }
+
+    /// All ranked results, winner first.
+    pub fn ranked(&self) -> impl Iterator<Item = &DetectionResult> {
+        std::iter::once(&self.detection).chain(&self.detection.candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranked_starts_with_winner() {
+        let runner_up = DetectionResult::new(AlsOperator::raw("x"), 1.5, PatternType::Raw);
+        let detection = DetectionResult::new(AlsOperator::range(1, 10), 4.0, PatternType::Sequential)
+            .with_candidates(vec![runner_up.clone()]);
+        let explanation = ColumnExplanation::new("id", detection);
+
+        assert_eq!(explanation.pattern_type(), PatternType::Sequential);
+        assert_eq!(explanation.operator(), &AlsOperator::range(1, 10));
+        let ratios: Vec<f64> = explanation.ranked().map(|r| r.compression_ratio).collect();
+        assert_eq!(ratios, vec![4.0, 1.5]);
+    }
+}
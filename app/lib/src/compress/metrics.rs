@@ -0,0 +1,275 @@
+//! Timing and allocation metrics for compression.
+//!
+//! With `CompressorConfig::with_metrics` enabled, `compress_with_stats`
+//! attaches a [`CompressionMetrics`] to its report: the time spent building
+//! the dictionary and, per column, the total time and the time each
+//! detector took. A slowdown can then be traced to the detector causing it.
+//!
+//! Allocations are only counted when the program installs
+//! [`CountingAllocator`] as its global allocator; otherwise the allocation
+//! fields are `None`.
+//!
+//! ```
+//! use als_compression::{AlsCompressor, CompressorConfig};
+//!
+//! let compressor = AlsCompressor::with_config(CompressorConfig::new().with_metrics(true));
+//! let data = als_compression::convert::csv::parse_csv("id,status\n1,ok\n2,ok\n3,err\n4,ok").unwrap();
+//! let (_, report) = compressor.compress_with_stats(&data).unwrap();
+//!
+//! let metrics = report.metrics.unwrap();
+//! assert_eq!(metrics.columns[0].name, "id");
+//! assert!(metrics.columns[0].detectors.iter().any(|timing| timing.detector == "RangeDetector"));
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// Detector timings of the column being measured on this thread, if any.
+    static RECORDER: RefCell<Option<Vec<DetectorTiming>>> = const { RefCell::new(None) };
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Global allocator that counts allocations for [`CompressionMetrics`].
+///
+/// Wraps the system allocator with two relaxed atomic additions per
+/// allocation. Install it in a binary to get allocation counts:
+///
+/// ```
+/// use als_compression::CountingAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    /// Allocations made through the allocator so far, or `None` if it is
+    /// not the global allocator.
+    pub fn counts() -> Option<AllocationCounts> {
+        INSTALLED.load(Ordering::Relaxed).then(|| AllocationCounts {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        })
+    }
+
+    fn record(size: usize) {
+        INSTALLED.store(true, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::record(new_size);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+/// Number and total size of allocations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationCounts {
+    /// Number of allocations and reallocations.
+    pub allocations: u64,
+    /// Bytes requested by those allocations.
+    pub bytes: u64,
+}
+
+impl AllocationCounts {
+    /// Allocations made between `earlier` and `self`.
+    fn since(self, earlier: AllocationCounts) -> AllocationCounts {
+        AllocationCounts {
+            allocations: self.allocations.saturating_sub(earlier.allocations),
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+        }
+    }
+}
+
+/// Time spent in one detector while compressing a column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectorTiming {
+    /// Detector type name, such as `RangeDetector`.
+    pub detector: &'static str,
+    /// Number of times the detector ran.
+    pub calls: usize,
+    /// Total time across those runs.
+    pub elapsed: Duration,
+}
+
+/// Metrics for a single column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMetrics {
+    /// Column name.
+    pub name: String,
+    /// Column index.
+    pub index: usize,
+    /// Total time spent encoding the column.
+    pub elapsed: Duration,
+    /// Time per detector, in the order the detectors first ran.
+    pub detectors: Vec<DetectorTiming>,
+    /// Allocations made while encoding the column.
+    pub allocations: Option<AllocationCounts>,
+}
+
+/// Timing and allocation metrics collected during compression.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompressionMetrics {
+    /// Total time spent compressing.
+    pub elapsed: Duration,
+    /// Time spent building the dictionary.
+    pub dictionary_time: Duration,
+    /// Allocations made while building the dictionary.
+    pub dictionary_allocations: Option<AllocationCounts>,
+    /// Per-column metrics.
+    pub columns: Vec<ColumnMetrics>,
+}
+
+impl CompressionMetrics {
+    /// Time per detector summed over all columns, slowest first.
+    pub fn detector_totals(&self) -> Vec<DetectorTiming> {
+        let mut totals: Vec<DetectorTiming> = Vec::new();
+        for timing in self.columns.iter().flat_map(|column| &column.detectors) {
+            match totals.iter_mut().find(|total| total.detector == timing.detector) {
+                Some(total) => {
+                    total.calls += timing.calls;
+                    total.elapsed += timing.elapsed;
+                }
+                None => totals.push(timing.clone()),
+            }
+        }
+        totals.sort_by_key(|timing| std::cmp::Reverse(timing.elapsed));
+        totals
+    }
+}
+
+/// Measurement of one phase of compression on the current thread.
+///
+/// While a probe is live, [`timed`] records detector runs on its thread.
+pub(crate) struct Probe {
+    started: Instant,
+    allocations: Option<AllocationCounts>,
+}
+
+impl Probe {
+    /// Start measuring and recording detector timings.
+    pub(crate) fn start() -> Self {
+        RECORDER.with(|recorder| *recorder.borrow_mut() = Some(Vec::new()));
+        Self {
+            started: Instant::now(),
+            allocations: CountingAllocator::counts(),
+        }
+    }
+
+    /// Stop measuring and return the elapsed time, the detector timings
+    /// and the allocations made.
+    pub(crate) fn finish(self) -> (Duration, Vec<DetectorTiming>, Option<AllocationCounts>) {
+        let elapsed = self.started.elapsed();
+        let detectors = RECORDER.with(|recorder| recorder.borrow_mut().take()).unwrap_or_default();
+        let allocations = CountingAllocator::counts()
+            .zip(self.allocations)
+            .map(|(now, before)| now.since(before));
+        (elapsed, detectors, allocations)
+    }
+}
+
+/// Run `f`, recording its duration under `detector` if a [`Probe`] is live
+/// on this thread.
+pub(crate) fn timed<R>(detector: &'static str, f: impl FnOnce() -> R) -> R {
+    if !RECORDER.with(|recorder| recorder.borrow().is_some()) {
+        return f();
+    }
+
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+    RECORDER.with(|recorder| {
+        if let Some(timings) = recorder.borrow_mut().as_mut() {
+            match timings.iter_mut().find(|timing| timing.detector == detector) {
+                Some(timing) => {
+                    timing.calls += 1;
+                    timing.elapsed += elapsed;
+                }
+                None => timings.push(DetectorTiming {
+                    detector,
+                    calls: 1,
+                    elapsed,
+                }),
+            }
+        }
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timed_records_only_inside_probe() {
+        assert_eq!(timed("Outside", || 1), 1);
+
+        let probe = Probe::start();
+        timed("First", || ());
+        timed("Second", || ());
+        timed("First", || ());
+        let (_, detectors, _) = probe.finish();
+
+        let calls: Vec<_> = detectors.iter().map(|timing| (timing.detector, timing.calls)).collect();
+        assert_eq!(calls, [("First", 2), ("Second", 1)]);
+
+        timed("After", || ());
+        let (_, detectors, _) = Probe::start().finish();
+        assert!(detectors.is_empty());
+    }
+
+    #[test]
+    fn test_detector_totals() {
+        let timing = |detector, millis| DetectorTiming {
+            detector,
+            calls: 1,
+            elapsed: Duration::from_millis(millis),
+        };
+        let column = |index, detectors| ColumnMetrics {
+            name: format!("c{}", index),
+            index,
+            elapsed: Duration::ZERO,
+            detectors,
+            allocations: None,
+        };
+        let metrics = CompressionMetrics {
+            columns: vec![
+                column(0, vec![timing("A", 1), timing("B", 5)]),
+                column(1, vec![timing("A", 7)]),
+            ],
+            ..CompressionMetrics::default()
+        };
+
+        let totals = metrics.detector_totals();
+        assert_eq!(totals[0].detector, "A");
+        assert_eq!(totals[0].calls, 2);
+        assert_eq!(totals[0].elapsed, Duration::from_millis(8));
+        assert_eq!(totals[1].detector, "B");
+    }
+}
@@ -1,12 +1,25 @@
 //! Compression components for ALS format.
 //!
 //! This module contains the dictionary builder, compressor, statistics tracking,
-//! and other compression utilities used to optimize ALS output.
+//! cardinality estimation, opaque column detection, scratch arenas and reusable buffers, timing metrics, detection reports, restructuring advice and other compression utilities used to optimize ALS output.
 
+mod advise;
+pub(crate) mod arena;
+mod cardinality;
 mod compressor;
 mod dictionary;
-mod stats;
+mod explain;
+pub(crate) mod metrics;
+mod opaque;
+mod scratch;
+pub(crate) mod stats;
 
+pub use advise::{Advice, Restructure, Suggestion, ADVICE_SAMPLE_ROWS};
+pub use cardinality::CardinalityEstimator;
 pub use compressor::AlsCompressor;
-pub use dictionary::{DictionaryBuilder, DictionaryEntry, EnumDetector};
-pub use stats::{ColumnStats, CompressionReport, CompressionStats, StatsSnapshot};
+pub use dictionary::{DictionaryBuilder, DictionaryEntry, EnumDetector, HierarchicalEnum};
+pub use explain::ColumnExplanation;
+pub use metrics::{AllocationCounts, ColumnMetrics, CompressionMetrics, CountingAllocator, DetectorTiming};
+pub use opaque::OpaqueDetector;
+pub use scratch::ScratchBuffers;
+pub use stats::{ColumnProfile, ColumnStats, CompressionReport, CompressionStats, StatsSnapshot};
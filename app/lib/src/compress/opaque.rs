@@ -0,0 +1,198 @@
+//! Detection of opaque columns.
+//!
+//! Columns of hashes, base64 blobs or random tokens never match a pattern
+//! and never repeat, so running every detector and counting them for the
+//! dictionary costs the most time for no gain. The `OpaqueDetector` looks at
+//! a small sample and flags such columns from their character statistics,
+//! letting the compressor write their values raw straight away.
+
+use std::collections::HashSet;
+
+/// Flags columns whose values look like hashes, base64 or random tokens.
+///
+/// A column is opaque when its sampled values are long, nearly all
+/// distinct, drawn from a token alphabet (ASCII letters and digits plus
+/// `+/=-_`) mixing letters with digits, and spread evenly over the
+/// characters they use, as measured by Shannon entropy.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::OpaqueDetector;
+///
+/// let hashes: Vec<String> = (0..64u64)
+///     .map(|i| format!("{:016x}{:016x}", i.wrapping_mul(0x9e37_79b9_7f4a_7c15), i.wrapping_mul(0xc2b2_ae3d_27d4_eb4f)))
+///     .collect();
+/// let hashes: Vec<&str> = hashes.iter().map(String::as_str).collect();
+/// assert!(OpaqueDetector::new().is_opaque(&hashes));
+///
+/// let ids: Vec<String> = (0..64).map(|i| format!("order-2024-{:06}", i)).collect();
+/// let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+/// assert!(!OpaqueDetector::new().is_opaque(&ids));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpaqueDetector {
+    /// Number of values sampled from a column.
+    sample_size: usize,
+}
+
+impl OpaqueDetector {
+    /// Default number of values sampled.
+    pub const DEFAULT_SAMPLE_SIZE: usize = 256;
+    /// Columns with fewer values are never flagged; detecting them is cheap.
+    pub const MIN_VALUES: usize = 32;
+    /// Minimum mean length of sampled values, in bytes.
+    pub const MIN_MEAN_LEN: usize = 16;
+    /// Minimum share of distinct values in the sample.
+    pub const MIN_DISTINCT_RATIO: f64 = 0.95;
+    /// Minimum character entropy, relative to the most the sample's
+    /// alphabet allows.
+    pub const MIN_ENTROPY_RATIO: f64 = 0.85;
+
+    /// Create a detector with the default sample size.
+    pub fn new() -> Self {
+        Self::with_sample_size(Self::DEFAULT_SAMPLE_SIZE)
+    }
+
+    /// Create a detector that samples up to `sample_size` values.
+    ///
+    /// The sample size is raised to `MIN_VALUES` if smaller.
+    pub fn with_sample_size(sample_size: usize) -> Self {
+        Self {
+            sample_size: sample_size.max(Self::MIN_VALUES),
+        }
+    }
+
+    /// Check whether a column looks opaque.
+    pub fn is_opaque<T: AsRef<str>>(&self, values: &[T]) -> bool {
+        if values.len() < Self::MIN_VALUES {
+            return false;
+        }
+
+        // Spread the sample over the whole column
+        let step = values.len().div_ceil(self.sample_size);
+        let sample: Vec<&str> = values.iter().step_by(step).map(AsRef::as_ref).collect();
+
+        let total_len: usize = sample.iter().map(|value| value.len()).sum();
+        if total_len < sample.len() * Self::MIN_MEAN_LEN {
+            return false;
+        }
+
+        let mut counts = [0usize; 128];
+        for byte in sample.iter().flat_map(|value| value.bytes()) {
+            if !is_token_byte(byte) {
+                return false;
+            }
+            counts[byte as usize] += 1;
+        }
+        let has_digits = counts[b'0' as usize..=b'9' as usize].iter().any(|&count| count > 0);
+        let has_letters = counts.iter().enumerate().any(|(byte, &count)| count > 0 && (byte as u8).is_ascii_alphabetic());
+        if !has_digits || !has_letters {
+            return false;
+        }
+
+        let distinct: HashSet<&str> = sample.iter().copied().collect();
+        if (distinct.len() as f64) < sample.len() as f64 * Self::MIN_DISTINCT_RATIO {
+            return false;
+        }
+
+        let alphabet = counts.iter().filter(|&&count| count > 0).count();
+        let entropy = entropy(&counts, total_len);
+        entropy >= (alphabet as f64).log2() * Self::MIN_ENTROPY_RATIO
+    }
+}
+
+impl Default for OpaqueDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Characters of hex, base64 (standard and URL-safe) and token alphabets.
+fn is_token_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'=' | b'-' | b'_')
+}
+
+/// Shannon entropy in bits per character of a character histogram.
+fn entropy(counts: &[usize], total: usize) -> f64 {
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random 64-bit values.
+    fn random_words(n: usize) -> Vec<u64> {
+        let mut state = 0x853c_49e6_748f_ea9bu64;
+        (0..n)
+            .map(|_| {
+                state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                state ^ (state >> 29)
+            })
+            .collect()
+    }
+
+    fn is_opaque(values: &[String]) -> bool {
+        OpaqueDetector::new().is_opaque(values)
+    }
+
+    #[test]
+    fn test_hex_hashes_are_opaque() {
+        let words = random_words(400);
+        let hashes: Vec<String> = words.chunks(2).map(|w| format!("{:016x}{:016x}", w[0], w[1])).collect();
+        assert!(is_opaque(&hashes));
+    }
+
+    #[test]
+    fn test_base64_tokens_are_opaque() {
+        const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let tokens: Vec<String> = random_words(500)
+            .chunks(5)
+            .map(|w| {
+                let body: String = w
+                    .iter()
+                    .flat_map(|word| (0..8).map(move |i| BASE64[((word >> (i * 6)) & 63) as usize] as char))
+                    .collect();
+                format!("sk_live_{}", body)
+            })
+            .collect();
+        assert!(is_opaque(&tokens));
+    }
+
+    #[test]
+    fn test_structured_values_are_not_opaque() {
+        let ids: Vec<String> = (0..500).map(|i| format!("user_{:012}", i)).collect();
+        assert!(!is_opaque(&ids));
+
+        let numbers: Vec<String> = random_words(500).iter().map(|w| w.to_string()).collect();
+        assert!(!is_opaque(&numbers));
+
+        let urls: Vec<String> = (0..500).map(|i| format!("https://example.com/item/{:x}", i * 7919)).collect();
+        assert!(!is_opaque(&urls));
+
+        let sentences: Vec<String> = (0..500).map(|i| format!("request {} took longer than expected", i)).collect();
+        assert!(!is_opaque(&sentences));
+    }
+
+    #[test]
+    fn test_repeated_or_short_values_are_not_opaque() {
+        let words = random_words(20);
+        let hashes: Vec<String> = words.iter().map(|w| format!("{:016x}{:016x}", w, w.rotate_left(17))).collect();
+        // Too few values to bother
+        assert!(!is_opaque(&hashes));
+
+        let repeated: Vec<String> = hashes.iter().cycle().take(500).cloned().collect();
+        assert!(!is_opaque(&repeated));
+
+        let short: Vec<String> = random_words(500).iter().map(|w| format!("{:08x}", w & 0xffff_ffff)).collect();
+        assert!(!is_opaque(&short));
+    }
+}
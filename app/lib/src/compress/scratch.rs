@@ -0,0 +1,55 @@
+//! Buffers reused across compressions.
+
+use super::arena::ValueBuffer;
+
+/// Buffers kept from one call to [`AlsCompressor::compress_into`] to the
+/// next.
+///
+/// Services compressing many small payloads spend a good part of each call
+/// allocating the formatted values of the table and the output text. Passing
+/// the same `ScratchBuffers` to every call lets those allocations grow to
+/// the largest payload once and be reused afterwards.
+///
+/// [`AlsCompressor::compress_into`]: crate::AlsCompressor::compress_into
+///
+/// # Example
+///
+/// ```
+/// use als_compression::{AlsCompressor, AlsParser, ScratchBuffers};
+/// use als_compression::convert::csv::parse_csv;
+///
+/// let compressor = AlsCompressor::new();
+/// let mut scratch = ScratchBuffers::new();
+/// for csv in ["id,level\n1,info\n2,info", "id,level\n3,warn\n4,info\n5,info"] {
+///     let als = compressor.compress_into(&parse_csv(csv).unwrap(), &mut scratch).unwrap();
+///     assert_eq!(AlsParser::new().to_csv(als).unwrap().trim_end(), csv);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ScratchBuffers {
+    /// Formatted values of the table being compressed.
+    pub(crate) values: ValueBuffer,
+    /// ALS text of the last compression.
+    pub(crate) output: String,
+}
+
+impl ScratchBuffers {
+    /// Create empty buffers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the ALS text written by the last compression.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Release the memory held by the buffers.
+    ///
+    /// Useful after an unusually large payload, so the buffers do not keep
+    /// its size for the rest of their life.
+    pub fn shrink(&mut self) {
+        self.values = ValueBuffer::default();
+        self.output = String::new();
+    }
+}
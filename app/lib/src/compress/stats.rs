@@ -61,8 +61,10 @@
 //! assert_eq!(stats.get_raw_values(), 400);
 //! ```
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
+use super::metrics::CompressionMetrics;
 use crate::pattern::PatternType;
 
 /// Thread-safe compression statistics.
@@ -176,19 +178,23 @@ impl CompressionStats {
         self.patterns_detected.fetch_add(1, Ordering::Relaxed);
         
         match pattern_type {
-            PatternType::Sequential | PatternType::Arithmetic => {
+            PatternType::Sequential | PatternType::Arithmetic | PatternType::DateRange => {
                 self.ranges_used.fetch_add(1, Ordering::Relaxed);
             }
-            PatternType::Repeat => {
+            PatternType::Repeat | PatternType::RepeatedMotif => {
                 self.multipliers_used.fetch_add(1, Ordering::Relaxed);
             }
-            PatternType::Toggle | PatternType::RepeatedToggle => {
+            PatternType::Toggle
+            | PatternType::RepeatedToggle
+            | PatternType::CalendarCycle
+            | PatternType::Boolean => {
                 self.toggles_used.fetch_add(1, Ordering::Relaxed);
             }
             PatternType::RepeatedRange => {
                 self.ranges_used.fetch_add(1, Ordering::Relaxed);
                 self.multipliers_used.fetch_add(1, Ordering::Relaxed);
             }
+            PatternType::Correlated | PatternType::Transformed | PatternType::FloatDelta | PatternType::Segmented => {}
             PatternType::Raw => {
                 self.raw_values.fetch_add(1, Ordering::Relaxed);
             }
@@ -390,6 +396,10 @@ pub struct ColumnStats {
     pub value_count: usize,
     /// Whether the column benefited from compression.
     pub was_compressed: bool,
+    /// Number of distinct values in the column.
+    pub distinct_count: usize,
+    /// Shannon entropy of the values, in bits per value.
+    pub entropy: f64,
 }
 
 impl ColumnStats {
@@ -410,9 +420,29 @@ impl ColumnStats {
             pattern_type,
             value_count,
             was_compressed: output_bytes < input_bytes,
+            distinct_count: 0,
+            entropy: 0.0,
         }
     }
 
+    /// Attach the distinct value count and entropy of the column.
+    pub fn with_distribution(mut self, distinct_count: usize, entropy: f64) -> Self {
+        self.distinct_count = distinct_count;
+        self.entropy = entropy;
+        self
+    }
+
+    /// Get the share of values that are distinct, from near 0 for a
+    /// constant column to 1 when every value is unique.
+    pub fn distinct_ratio(&self) -> f64 {
+        ratio(self.distinct_count, self.value_count)
+    }
+
+    /// Get the compressed bytes spent per row.
+    pub fn bytes_per_row(&self) -> f64 {
+        ratio(self.output_bytes, self.value_count)
+    }
+
     /// Calculate the compression ratio for this column.
     pub fn compression_ratio(&self) -> f64 {
         if self.output_bytes > 0 {
@@ -428,6 +458,75 @@ impl ColumnStats {
     }
 }
 
+/// Value distribution and storage cost of one column of an ALS document,
+/// as given by [`AlsParser::profile`](crate::AlsParser::profile).
+///
+/// # Thread Safety
+///
+/// This struct is `Send + Sync` and can be safely shared across threads.
+/// It is an immutable value type with no interior mutability.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    /// Column name.
+    pub name: String,
+    /// Number of values in the column.
+    pub value_count: usize,
+    /// Number of distinct values in the column.
+    pub distinct_count: usize,
+    /// Shannon entropy of the values, in bits per value.
+    pub entropy: f64,
+    /// Size of the column name and expanded values, one separator each, as
+    /// the input size of [`ColumnStats`] is measured.
+    pub expanded_bytes: usize,
+    /// Size of the column's stream in the serialized document.
+    pub encoded_bytes: usize,
+}
+
+impl ColumnProfile {
+    /// Get the share of values that are distinct, from near 0 for a
+    /// constant column to 1 when every value is unique.
+    pub fn distinct_ratio(&self) -> f64 {
+        ratio(self.distinct_count, self.value_count)
+    }
+
+    /// Get the encoded bytes spent per row.
+    pub fn bytes_per_row(&self) -> f64 {
+        ratio(self.encoded_bytes, self.value_count)
+    }
+
+    /// Calculate the compression ratio for this column.
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.expanded_bytes, self.encoded_bytes)
+    }
+}
+
+/// Count the distinct values and measure the Shannon entropy, in bits per
+/// value, of `values`.
+pub(crate) fn value_distribution<S: AsRef<str>>(values: &[S]) -> (usize, f64) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value.as_ref()).or_default() += 1;
+    }
+    let total = values.len() as f64;
+    let entropy = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum::<f64>();
+    // A constant column sums to -0.0
+    (counts.len(), entropy.max(0.0))
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator > 0 {
+        numerator as f64 / denominator as f64
+    } else {
+        0.0
+    }
+}
+
 /// Detailed compression report with per-column statistics.
 ///
 /// # Thread Safety
@@ -444,6 +543,8 @@ pub struct CompressionReport {
     pub used_ctx_fallback: bool,
     /// Dictionary utilization (entries used / total entries).
     pub dictionary_utilization: f64,
+    /// Timing and allocation metrics, when collection is enabled.
+    pub metrics: Option<CompressionMetrics>,
 }
 
 impl CompressionReport {
@@ -459,9 +560,16 @@ impl CompressionReport {
             columns,
             used_ctx_fallback,
             dictionary_utilization,
+            metrics: None,
         }
     }
 
+    /// Attach timing and allocation metrics to the report.
+    pub fn with_metrics(mut self, metrics: CompressionMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Get the most effective column (highest compression ratio).
     pub fn most_effective_column(&self) -> Option<&ColumnStats> {
         self.columns
@@ -695,6 +803,22 @@ mod tests {
         assert!(stats.was_compressed);
     }
 
+    #[test]
+    fn test_value_distribution() {
+        assert_eq!(value_distribution::<&str>(&[]), (0, 0.0));
+        assert_eq!(value_distribution(&["a", "a", "a"]), (1, 0.0));
+        assert_eq!(value_distribution(&["a", "b", "c", "d"]), (4, 2.0));
+
+        let (distinct, entropy) = value_distribution(&["a", "a", "a", "b"]);
+        assert_eq!(distinct, 2);
+        assert!((entropy - 0.8113).abs() < 1e-4);
+
+        let stats = ColumnStats::new("level".to_string(), 0, 100, 20, PatternType::Raw, 4)
+            .with_distribution(distinct, entropy);
+        assert_eq!(stats.distinct_ratio(), 0.5);
+        assert_eq!(stats.bytes_per_row(), 5.0);
+    }
+
     #[test]
     fn test_column_stats_compression_ratio() {
         let stats = ColumnStats::new(
@@ -3,6 +3,13 @@
 //! This module provides configuration structs for controlling compression behavior,
 //! SIMD optimization, parallelism, and security limits.
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::als::{FileMetadata, ForeignKey, Quantization, Redaction, SchemaValidator, TimeTruncation};
+use crate::pattern::{CostModel, TextCostModel};
+use crate::query::Predicate;
+
 /// Configuration for the ALS compressor.
 ///
 /// Controls compression behavior including CTX fallback, dictionary optimization,
@@ -65,6 +72,15 @@ pub struct CompressorConfig {
     /// Default: 65,536 entries
     pub max_dictionary_entries: usize,
 
+    /// Maximum width of a dictionary reference, in characters after the `_`.
+    ///
+    /// Values that would get a wider reference are left out of the
+    /// dictionary. References past `_9` are written in base-62, so a width
+    /// of 3 allows 203,174 entries.
+    ///
+    /// Default: None (only `max_dictionary_entries` applies)
+    pub max_dictionary_ref_width: Option<usize>,
+
     /// Maximum input size for non-streaming operations (in bytes).
     ///
     /// This security limit prevents memory exhaustion from very large inputs.
@@ -72,6 +88,218 @@ pub struct CompressorConfig {
     ///
     /// Default: 1,073,741,824 bytes (1 GB)
     pub max_input_size: usize,
+
+    /// JSON input configuration.
+    ///
+    /// Controls how records are located in JSON input.
+    pub json: JsonConfig,
+
+    /// Number of leading values used to choose pattern detectors.
+    ///
+    /// When set and a column is longer than this, every detector first runs
+    /// on the sample and only those that find a pattern there are run on the
+    /// full column. This trades a small risk of missing patterns that only
+    /// appear later in a column for much faster detection on very long ones.
+    ///
+    /// Default: None (every detector sees every value)
+    pub detection_sample_size: Option<usize>,
+
+    /// Number of values per chunk for per-column pattern detection.
+    ///
+    /// When set, columns longer than this are split into chunks that are
+    /// encoded independently (in parallel with the `parallel` feature) and
+    /// adjacent compatible operators are merged afterwards. This bounds the
+    /// working set of each detector at the cost of patterns that only show
+    /// across a chunk boundary.
+    ///
+    /// Default: None (columns are detected as a whole)
+    pub detection_chunk_size: Option<usize>,
+
+    /// Cost model used to compare candidate encodings.
+    ///
+    /// Default: `TextCostModel` (size of the ALS text serialization)
+    pub cost_model: Arc<dyn CostModel>,
+
+    /// Encoding strategies pinned by column name.
+    ///
+    /// Columns listed here skip pattern detection and use the given
+    /// strategy; all other columns are detected automatically.
+    ///
+    /// Default: empty
+    pub column_strategies: HashMap<String, ColumnStrategy>,
+
+    /// Minimum per-column compression ratio before storing a column verbatim.
+    ///
+    /// When set, a column whose encoding is not at least this many times
+    /// smaller than its verbatim values is emitted as a CTX column instead,
+    /// so operator framing never makes a column bigger than its values.
+    /// Columns with a pinned strategy are left alone.
+    ///
+    /// Default: None (columns are never stored verbatim on their own)
+    pub column_ctx_threshold: Option<f64>,
+
+    /// Write the values of opaque columns raw without pattern detection.
+    ///
+    /// Columns that look like hashes, base64 blobs or random tokens (see
+    /// `OpaqueDetector`) skip the detectors, the dictionary and column
+    /// references, which would spend time on them for no gain. Columns with
+    /// a pinned strategy are left alone.
+    ///
+    /// Default: true
+    pub detect_opaque_columns: bool,
+
+    /// Inputs with fewer rows than this take the small-payload fast path.
+    ///
+    /// The fast path skips the dictionary, the combined detector, the
+    /// search for patterns in reformatted values, repeated motifs, segment
+    /// splitting and column references. On a few dozen rows these cost more
+    /// time than they save, and a dictionary header often costs more bytes
+    /// than its references save. A limit of 0 disables the check.
+    ///
+    /// Default: 50
+    pub small_payload_rows: usize,
+
+    /// Inputs smaller than this many bytes of values take the
+    /// small-payload fast path, whatever their row count.
+    ///
+    /// Size is measured as for the CTX fallback: every value's text plus a
+    /// separator, and the column names. A limit of 0 disables the check.
+    ///
+    /// Default: 1024
+    pub small_payload_bytes: usize,
+
+    /// Collect timing and allocation metrics in `compress_with_stats`.
+    ///
+    /// The report then carries a `CompressionMetrics` with the dictionary
+    /// build time and the time each detector spent on each column.
+    ///
+    /// Default: false
+    pub collect_metrics: bool,
+
+    /// Emit the input unchanged when compressing it would make it bigger.
+    ///
+    /// When true, `compress_csv` and `compress_json` return a store frame
+    /// (`!store csv` or `!store json` followed by the input) whenever the
+    /// ALS text would exceed the input and the frame is smaller. Output is
+    /// then never more than a one-line header bigger than the input.
+    ///
+    /// Default: false
+    pub allow_store_mode: bool,
+
+    /// Append a value index for each column to the document.
+    ///
+    /// Indexes let `AlsQuery` answer equality lookups without expanding
+    /// the column, at the cost of a larger document. Columns whose values
+    /// are all distinct are not indexed.
+    ///
+    /// Default: false
+    pub build_indexes: bool,
+
+    /// Rows per block in block mode.
+    ///
+    /// When set, `compress` encodes each block of rows on its own so blocks
+    /// can be expanded independently, and `build_indexes` stores a Bloom
+    /// filter per block and column instead of a value index. Queries then
+    /// skip every block whose filter rules the value out.
+    ///
+    /// Default: None (the document is a single block)
+    pub block_size: Option<usize>,
+
+    /// Record min/max zone maps for each column and block.
+    ///
+    /// Zone maps let range queries skip blocks whose values all fall
+    /// outside the range, and give `info` per-column statistics.
+    ///
+    /// Default: false
+    pub build_zone_maps: bool,
+
+    /// Normalize column names and string values to Unicode NFC first.
+    ///
+    /// Text that looks the same can be encoded differently, such as a
+    /// precomposed `é` and an `e` followed by a combining accent. Normalizing
+    /// makes both the same dictionary entry and lets queries match either.
+    /// Documents record that the pass ran in an `!nfc` line.
+    ///
+    /// Default: false
+    pub normalize_unicode: bool,
+
+    /// Contract the input must meet before it is compressed.
+    ///
+    /// Compression fails with `AlsError::SchemaViolation`, listing every
+    /// column that breaks the contract, instead of archiving data whose
+    /// shape has drifted.
+    ///
+    /// Default: None
+    pub schema_contract: Option<Arc<SchemaValidator>>,
+
+    /// Redactions applied to columns before compression, keyed by column
+    /// name.
+    ///
+    /// Lets PII columns be neutralized at archive time. The policy is
+    /// recorded in the document; columns it names that the input lacks are
+    /// ignored.
+    ///
+    /// Default: empty (nothing is redacted)
+    pub column_redactions: HashMap<String, Redaction>,
+
+    /// Salt mixed into the hashes of `Redaction::Hash` and
+    /// `Redaction::Pseudonymize` columns.
+    ///
+    /// Without a secret salt, hashes of guessable values such as email
+    /// addresses can be reversed by hashing candidates.
+    ///
+    /// Default: empty
+    pub redaction_salt: String,
+
+    /// Rounding applied to the floats of columns before compression, keyed
+    /// by column name.
+    ///
+    /// Lossy: noisy sensor readings compress far better rounded to the
+    /// precision that matters. The rounding is recorded in the document;
+    /// columns it names that the input lacks are ignored.
+    ///
+    /// Default: empty (every value is kept exactly)
+    pub column_quantizations: HashMap<String, Quantization>,
+
+    /// Resolution the times of columns are truncated to before compression,
+    /// keyed by column name.
+    ///
+    /// Lossy: metrics rarely need milliseconds, and without them
+    /// consecutive timestamps line up into ranges and repeats. The
+    /// truncation is recorded in the document; columns it names that the
+    /// input lacks are ignored.
+    ///
+    /// Default: empty (every time is kept exactly)
+    pub column_truncations: HashMap<String, TimeTruncation>,
+
+    /// Foreign keys of columns, keyed by column name, with the keys of the
+    /// parent column in row order.
+    ///
+    /// These columns are stored as row numbers into the parent's keys,
+    /// which compress far better than repeated UUIDs or codes. The foreign
+    /// key is recorded in the document, and a container holding the parent
+    /// resolves the row numbers back to keys. Compression fails if a value
+    /// is missing from the parent.
+    ///
+    /// Default: empty (every column holds its own values)
+    pub column_foreign_keys: HashMap<String, (ForeignKey, Arc<[String]>)>,
+
+    /// Predicate rows must match to be kept.
+    ///
+    /// Rows that fail it, such as debug-level log lines, are dropped before
+    /// anything else looks at the input, so they never reach the archive.
+    ///
+    /// Default: None (every row is kept)
+    pub row_filter: Option<Predicate>,
+
+    /// Metadata of the file being compressed, recorded in the document.
+    ///
+    /// Lets decompression restore the file's modification time, as gzip
+    /// and zstd do. Input holding metadata is never written as a store
+    /// frame, which has nowhere to keep it.
+    ///
+    /// Default: None
+    pub file_metadata: Option<FileMetadata>,
 }
 
 impl Default for CompressorConfig {
@@ -84,17 +312,95 @@ impl Default for CompressorConfig {
             parallelism: 0, // auto-detect
             max_range_expansion: 10_000_000,
             max_dictionary_entries: 65_536,
+            max_dictionary_ref_width: None,
             max_input_size: 1_073_741_824, // 1 GB
+            json: JsonConfig::default(),
+            detection_sample_size: None,
+            detection_chunk_size: None,
+            cost_model: Arc::new(TextCostModel),
+            column_strategies: HashMap::new(),
+            column_ctx_threshold: None,
+            detect_opaque_columns: true,
+            small_payload_rows: 50,
+            small_payload_bytes: 1024,
+            collect_metrics: false,
+            allow_store_mode: false,
+            build_indexes: false,
+            block_size: None,
+            build_zone_maps: false,
+            normalize_unicode: false,
+            schema_contract: None,
+            column_redactions: HashMap::new(),
+            redaction_salt: String::new(),
+            column_quantizations: HashMap::new(),
+            column_truncations: HashMap::new(),
+            column_foreign_keys: HashMap::new(),
+            row_filter: None,
+            file_metadata: None,
         }
     }
 }
 
 impl CompressorConfig {
+    /// Fastest compression level.
+    pub const MIN_LEVEL: u8 = 1;
+
+    /// Compression level of the default configuration.
+    pub const DEFAULT_LEVEL: u8 = 6;
+
+    /// Compression level giving the smallest output.
+    pub const MAX_LEVEL: u8 = 9;
+
     /// Create a new configuration with default values.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Set the detection settings of a compression level, from
+    /// `MIN_LEVEL` (fastest) to `MAX_LEVEL` (smallest output).
+    ///
+    /// As with gzip and zstd, lower levels trade compression ratio for
+    /// speed. A level sets the detection sample size, the detection chunk
+    /// size, the small-payload row limit and opaque column detection, and
+    /// leaves every other setting alone:
+    ///
+    /// | Level | Detection |
+    /// |-------|-----------|
+    /// | 1     | the small-payload fast path for every input |
+    /// | 2-4   | detectors chosen on the first 1,000, 10,000 or 100,000 values of a column |
+    /// | 5     | columns detected in chunks of 1,048,576 values |
+    /// | 6     | every detector on every value (the default) |
+    /// | 7     | as 6, without the small-payload fast path |
+    /// | 8-9   | as 7, with opaque columns detected too |
+    ///
+    /// # Panics
+    ///
+    /// Panics if level is outside `MIN_LEVEL..=MAX_LEVEL`.
+    pub fn with_level(mut self, level: u8) -> Self {
+        assert!(
+            (Self::MIN_LEVEL..=Self::MAX_LEVEL).contains(&level),
+            "Compression level must be between {} and {}",
+            Self::MIN_LEVEL,
+            Self::MAX_LEVEL
+        );
+        let defaults = Self::default();
+        self.detection_sample_size = match level {
+            2 => Some(1_000),
+            3 => Some(10_000),
+            4 => Some(100_000),
+            _ => None,
+        };
+        self.detection_chunk_size = (level == 5).then_some(1 << 20);
+        self.small_payload_rows = match level {
+            1 => usize::MAX,
+            7.. => 0,
+            _ => defaults.small_payload_rows,
+        };
+        self.small_payload_bytes = if level >= 7 { 0 } else { defaults.small_payload_bytes };
+        self.detect_opaque_columns = level < 8;
+        self
+    }
+
     /// Set the CTX fallback threshold.
     ///
     /// # Arguments
@@ -146,11 +452,223 @@ impl CompressorConfig {
         self
     }
 
+    /// Set the maximum dictionary reference width.
+    pub fn with_max_dictionary_ref_width(mut self, width: usize) -> Self {
+        self.max_dictionary_ref_width = Some(width);
+        self
+    }
+
     /// Set the maximum input size limit.
     pub fn with_max_input_size(mut self, max: usize) -> Self {
         self.max_input_size = max;
         self
     }
+
+    /// Set the JSON input configuration.
+    pub fn with_json_config(mut self, config: JsonConfig) -> Self {
+        self.json = config;
+        self
+    }
+
+    /// Enable sample-based detector selection with the given sample size.
+    ///
+    /// A size of 0 disables sampling.
+    pub fn with_detection_sample_size(mut self, size: usize) -> Self {
+        self.detection_sample_size = (size > 0).then_some(size);
+        self
+    }
+
+    /// Set the cost model used to compare candidate encodings.
+    pub fn with_cost_model(mut self, cost_model: Arc<dyn CostModel>) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    /// Detect patterns in chunks of the given number of values.
+    ///
+    /// A size of 0 disables chunking.
+    pub fn with_detection_chunk_size(mut self, size: usize) -> Self {
+        self.detection_chunk_size = (size > 0).then_some(size);
+        self
+    }
+
+    /// Set the per-column CTX fallback threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if threshold is less than 1.0.
+    pub fn with_column_ctx_threshold(mut self, threshold: f64) -> Self {
+        assert!(threshold >= 1.0, "Column CTX threshold must be >= 1.0");
+        self.column_ctx_threshold = Some(threshold);
+        self
+    }
+
+    /// Set whether opaque columns skip pattern detection.
+    pub fn with_opaque_detection(mut self, detect: bool) -> Self {
+        self.detect_opaque_columns = detect;
+        self
+    }
+
+    /// Set the row count and byte size under which inputs take the
+    /// small-payload fast path.
+    ///
+    /// Passing 0 for both disables the fast path.
+    pub fn with_small_payload_limits(mut self, rows: usize, bytes: usize) -> Self {
+        self.small_payload_rows = rows;
+        self.small_payload_bytes = bytes;
+        self
+    }
+
+    /// Set whether `compress_with_stats` collects timing metrics.
+    pub fn with_metrics(mut self, collect: bool) -> Self {
+        self.collect_metrics = collect;
+        self
+    }
+
+    /// Set whether incompressible input may be emitted as a store frame.
+    pub fn with_allow_store_mode(mut self, allow: bool) -> Self {
+        self.allow_store_mode = allow;
+        self
+    }
+
+    /// Set whether value indexes are appended to compressed documents.
+    pub fn with_build_indexes(mut self, build: bool) -> Self {
+        self.build_indexes = build;
+        self
+    }
+
+    /// Enable block mode with the given rows per block.
+    ///
+    /// A size of 0 disables block mode.
+    pub fn with_block_size(mut self, rows: usize) -> Self {
+        self.block_size = (rows > 0).then_some(rows);
+        self
+    }
+
+    /// Set whether min/max zone maps are recorded in compressed documents.
+    pub fn with_zone_maps(mut self, build: bool) -> Self {
+        self.build_zone_maps = build;
+        self
+    }
+
+    /// Set whether input text is normalized to Unicode NFC before compressing.
+    pub fn with_normalize_unicode(mut self, normalize: bool) -> Self {
+        self.normalize_unicode = normalize;
+        self
+    }
+
+    /// Set the schema contract input is checked against before compressing.
+    pub fn with_schema_contract(mut self, contract: SchemaValidator) -> Self {
+        self.schema_contract = Some(Arc::new(contract));
+        self
+    }
+
+    /// Redact a column before compression.
+    pub fn with_column_redaction<S: Into<String>>(mut self, column: S, redaction: Redaction) -> Self {
+        self.column_redactions.insert(column.into(), redaction);
+        self
+    }
+
+    /// Set the salt of hashed columns.
+    pub fn with_redaction_salt<S: Into<String>>(mut self, salt: S) -> Self {
+        self.redaction_salt = salt.into();
+        self
+    }
+
+    /// Round the floats of a column before compression.
+    pub fn with_column_quantization<S: Into<String>>(mut self, column: S, quantization: Quantization) -> Self {
+        self.column_quantizations.insert(column.into(), quantization);
+        self
+    }
+
+    /// Truncate the times of a column before compression.
+    pub fn with_column_truncation<S: Into<String>>(mut self, column: S, truncation: TimeTruncation) -> Self {
+        self.column_truncations.insert(column.into(), truncation);
+        self
+    }
+
+    /// Store a column as row numbers into the keys of a parent column.
+    ///
+    /// `parent_keys` are the values of the parent column in row order, as
+    /// [`AlsParser`](crate::AlsParser) expands them.
+    pub fn with_foreign_key<S: Into<String>>(mut self, column: S, key: ForeignKey, parent_keys: Vec<String>) -> Self {
+        self.column_foreign_keys.insert(column.into(), (key, parent_keys.into()));
+        self
+    }
+
+    /// Record the metadata of the file being compressed.
+    pub fn with_file_metadata(mut self, metadata: FileMetadata) -> Self {
+        self.file_metadata = Some(metadata);
+        self
+    }
+
+    /// Keep only the rows matching `predicate` when compressing.
+    pub fn with_row_filter(mut self, predicate: Predicate) -> Self {
+        self.row_filter = Some(predicate);
+        self
+    }
+
+    /// Pin the encoding strategy of a column.
+    ///
+    /// Pinning `ColumnStrategy::Auto` removes an earlier pin.
+    pub fn with_column_strategy(mut self, column: impl Into<String>, strategy: ColumnStrategy) -> Self {
+        let column = column.into();
+        if strategy == ColumnStrategy::Auto {
+            self.column_strategies.remove(&column);
+        } else {
+            self.column_strategies.insert(column, strategy);
+        }
+        self
+    }
+
+    /// Get the encoding strategy of a column.
+    pub fn column_strategy(&self, column: &str) -> ColumnStrategy {
+        self.column_strategies.get(column).copied().unwrap_or_default()
+    }
+}
+
+/// Encoding strategy for a single column.
+///
+/// Pinning a strategy skips pattern detection for columns where the user
+/// knows best, such as free text that detectors would only waste time on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ColumnStrategy {
+    /// Pick the best encoding by pattern detection.
+    #[default]
+    Auto,
+    /// Store every value as is, without dictionary references.
+    Raw,
+    /// Use dictionary references where possible and raw values otherwise.
+    /// Every value of the column is considered for the dictionary.
+    Dict,
+    /// Encode the column as one arithmetic range. Columns that are not a
+    /// single range are encoded as with `Dict`.
+    Range,
+}
+
+impl ColumnStrategy {
+    /// All strategies.
+    pub const ALL: [ColumnStrategy; 4] = [
+        ColumnStrategy::Auto,
+        ColumnStrategy::Raw,
+        ColumnStrategy::Dict,
+        ColumnStrategy::Range,
+    ];
+
+    /// Get the name used for this strategy in configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnStrategy::Auto => "auto",
+            ColumnStrategy::Raw => "raw",
+            ColumnStrategy::Dict => "dict",
+            ColumnStrategy::Range => "range",
+        }
+    }
+
+    /// Look up a strategy by its configuration name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|strategy| strategy.name() == name)
+    }
 }
 
 /// Configuration for the ALS parser.
@@ -196,6 +714,19 @@ pub struct ParserConfig {
     ///
     /// Default: 1,073,741,824 bytes (1 GB)
     pub max_input_size: usize,
+
+    /// Maximum nesting of groups, splits and transforms in a stream.
+    ///
+    /// This security limit prevents stack overflows from malicious or
+    /// malformed ALS documents such as `((((...))))`.
+    ///
+    /// Default: 64 levels
+    pub max_nesting_depth: usize,
+
+    /// Output style for `AlsParser::to_json` and `to_json_columnar`.
+    ///
+    /// Default: `JsonFormat::Compact`
+    pub json_format: JsonFormat,
 }
 
 impl Default for ParserConfig {
@@ -206,6 +737,8 @@ impl Default for ParserConfig {
             max_range_expansion: 10_000_000,
             max_dictionary_entries: 65_536,
             max_input_size: 1_073_741_824, // 1 GB
+            max_nesting_depth: 64,
+            json_format: JsonFormat::default(),
         }
     }
 }
@@ -245,6 +778,107 @@ impl ParserConfig {
         self.max_input_size = max;
         self
     }
+
+    /// Set the maximum nesting depth of operators.
+    pub fn with_max_nesting_depth(mut self, depth: usize) -> Self {
+        self.max_nesting_depth = depth;
+        self
+    }
+
+    /// Set the output style for JSON conversion.
+    pub fn with_json_format(mut self, format: JsonFormat) -> Self {
+        self.json_format = format;
+        self
+    }
+}
+
+/// Configuration for JSON input.
+///
+/// Controls where the records of a JSON document are found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonConfig {
+    /// JSON Pointer (RFC 6901) to the array of records, e.g. `/data/items`.
+    ///
+    /// Use this for wrapped API responses such as
+    /// `{"meta": {...}, "data": {"items": [...]}}`. When unset, the document
+    /// itself must be the array of records.
+    ///
+    /// Default: None
+    pub records_path: Option<String>,
+
+    /// Require every record to have the same set of keys.
+    ///
+    /// When false, records with differing keys are combined by taking the
+    /// union of their keys as columns, with nulls where a record lacks a
+    /// key. When true, a record whose keys differ from the first record's
+    /// is an error.
+    ///
+    /// Default: false
+    pub strict_schema: bool,
+}
+
+impl JsonConfig {
+    /// Create a new configuration with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the JSON Pointer to the array of records.
+    pub fn with_records_path(mut self, path: impl Into<String>) -> Self {
+        self.records_path = Some(path.into());
+        self
+    }
+
+    /// Set whether all records must have the same keys.
+    pub fn with_strict_schema(mut self, strict: bool) -> Self {
+        self.strict_schema = strict;
+        self
+    }
+}
+
+/// Output style for JSON produced by the parser.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// Compact JSON on a single line.
+    #[default]
+    Compact,
+    /// Pretty-printed JSON indented by `indent` spaces per level.
+    Pretty {
+        /// Number of spaces per indentation level
+        indent: usize,
+    },
+    /// Newline-delimited JSON: one compact object per line.
+    Ndjson,
+}
+
+/// Configuration for the ALS serializer.
+///
+/// Used with `AlsSerializer::serialize_with_config`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SerializerConfig {
+    /// Maximum number of values one written operator may expand to.
+    ///
+    /// Parsers refuse ranges longer than their `max_range_expansion`, so a
+    /// document built in code with a range of a billion IDs serializes fine
+    /// but cannot be read back. With a limit, an operator expanding to more
+    /// values is written as consecutive operators of at most this many
+    /// values each, which expand to the same values.
+    ///
+    /// Default: None (no limit)
+    pub max_operator_expansion: Option<usize>,
+}
+
+impl SerializerConfig {
+    /// Create a new configuration with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum operator expansion. A limit of 0 is taken as 1.
+    pub fn with_max_operator_expansion(mut self, max: usize) -> Self {
+        self.max_operator_expansion = Some(max.max(1));
+        self
+    }
 }
 
 /// SIMD instruction set configuration.
@@ -345,6 +979,14 @@ mod tests {
         assert_eq!(config.max_range_expansion, 10_000_000);
         assert_eq!(config.max_dictionary_entries, 65_536);
         assert_eq!(config.max_input_size, 1_073_741_824);
+        assert_eq!(config.json, JsonConfig::default());
+        assert_eq!(config.detection_sample_size, None);
+        assert_eq!(config.detection_chunk_size, None);
+        assert_eq!(config.column_ctx_threshold, None);
+        assert!(!config.allow_store_mode);
+        assert!(!config.build_indexes);
+        assert_eq!(config.block_size, None);
+        assert!(!config.build_zone_maps);
     }
 
     #[test]
@@ -356,7 +998,14 @@ mod tests {
             .with_parallelism(4)
             .with_max_range_expansion(1_000_000)
             .with_max_dictionary_entries(10_000)
-            .with_max_input_size(500_000_000);
+            .with_max_input_size(500_000_000)
+            .with_detection_sample_size(4_096)
+            .with_detection_chunk_size(65_536)
+            .with_column_ctx_threshold(1.1)
+            .with_allow_store_mode(true)
+            .with_build_indexes(true)
+            .with_block_size(10_000)
+            .with_zone_maps(true);
 
         assert_eq!(config.ctx_fallback_threshold, 1.5);
         assert_eq!(config.hashmap_threshold, 5_000);
@@ -365,6 +1014,40 @@ mod tests {
         assert_eq!(config.max_range_expansion, 1_000_000);
         assert_eq!(config.max_dictionary_entries, 10_000);
         assert_eq!(config.max_input_size, 500_000_000);
+        assert_eq!(config.detection_sample_size, Some(4_096));
+        assert_eq!(config.detection_chunk_size, Some(65_536));
+        assert_eq!(config.column_ctx_threshold, Some(1.1));
+        assert!(config.allow_store_mode);
+        assert!(config.build_indexes);
+        assert_eq!(config.block_size, Some(10_000));
+        assert!(config.build_zone_maps);
+        assert_eq!(config.with_detection_sample_size(0).detection_sample_size, None);
+    }
+
+    #[test]
+    fn test_column_strategies() {
+        let config = CompressorConfig::new()
+            .with_column_strategy("id", ColumnStrategy::Range)
+            .with_column_strategy("payload", ColumnStrategy::Raw)
+            .with_column_strategy("status", ColumnStrategy::Dict);
+
+        assert_eq!(config.column_strategy("id"), ColumnStrategy::Range);
+        assert_eq!(config.column_strategy("payload"), ColumnStrategy::Raw);
+        assert_eq!(config.column_strategy("status"), ColumnStrategy::Dict);
+        assert_eq!(config.column_strategy("other"), ColumnStrategy::Auto);
+
+        let config = config.with_column_strategy("id", ColumnStrategy::Auto);
+        assert_eq!(config.column_strategy("id"), ColumnStrategy::Auto);
+        assert_eq!(config.column_strategies.len(), 2);
+    }
+
+    #[test]
+    fn test_column_strategy_names() {
+        for strategy in ColumnStrategy::ALL {
+            assert_eq!(ColumnStrategy::from_name(strategy.name()), Some(strategy));
+        }
+        assert_eq!(ColumnStrategy::from_name("dict"), Some(ColumnStrategy::Dict));
+        assert_eq!(ColumnStrategy::from_name("zstd"), None);
     }
 
     #[test]
@@ -373,6 +1056,12 @@ mod tests {
         CompressorConfig::new().with_ctx_fallback_threshold(0.5);
     }
 
+    #[test]
+    #[should_panic(expected = "Column CTX threshold must be >= 1.0")]
+    fn test_compressor_config_invalid_column_ctx_threshold() {
+        CompressorConfig::new().with_column_ctx_threshold(0.9);
+    }
+
     #[test]
     fn test_parser_config_default() {
         let config = ParserConfig::default();
@@ -396,6 +1085,25 @@ mod tests {
         assert_eq!(config.max_input_size, 2_000_000_000);
     }
 
+    #[test]
+    fn test_parser_config_json_format() {
+        assert_eq!(ParserConfig::default().json_format, JsonFormat::Compact);
+
+        let config = ParserConfig::new().with_json_format(JsonFormat::Pretty { indent: 4 });
+        assert_eq!(config.json_format, JsonFormat::Pretty { indent: 4 });
+    }
+
+    #[test]
+    fn test_json_config_builder() {
+        assert_eq!(JsonConfig::new().records_path, None);
+        assert!(!JsonConfig::new().strict_schema);
+        assert!(JsonConfig::new().with_strict_schema(true).strict_schema);
+
+        let config = CompressorConfig::new()
+            .with_json_config(JsonConfig::new().with_records_path("/data/items"));
+        assert_eq!(config.json.records_path.as_deref(), Some("/data/items"));
+    }
+
     #[test]
     fn test_simd_config_default() {
         let config = SimdConfig::default();
@@ -446,6 +1154,42 @@ mod tests {
         assert_send_sync::<SimdConfig>();
     }
 
+    #[test]
+    fn test_compression_levels() {
+        let fastest = CompressorConfig::new().with_level(CompressorConfig::MIN_LEVEL);
+        assert_eq!(fastest.small_payload_rows, usize::MAX);
+        assert_eq!(
+            CompressorConfig::new().with_level(3).detection_sample_size,
+            Some(10_000)
+        );
+        assert_eq!(
+            CompressorConfig::new().with_level(5).detection_chunk_size,
+            Some(1 << 20)
+        );
+
+        let smallest = CompressorConfig::new().with_level(CompressorConfig::MAX_LEVEL);
+        assert_eq!(smallest.small_payload_rows, 0);
+        assert_eq!(smallest.small_payload_bytes, 0);
+        assert!(!smallest.detect_opaque_columns);
+
+        // Setting the default level undoes another, leaving other settings
+        let config = CompressorConfig::new()
+            .with_block_size(100)
+            .with_level(2)
+            .with_level(CompressorConfig::DEFAULT_LEVEL);
+        let defaults = CompressorConfig::default();
+        assert_eq!(config.detection_sample_size, defaults.detection_sample_size);
+        assert_eq!(config.small_payload_rows, defaults.small_payload_rows);
+        assert!(config.detect_opaque_columns);
+        assert_eq!(config.block_size, Some(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Compression level must be between 1 and 9")]
+    fn test_invalid_compression_level() {
+        let _ = CompressorConfig::new().with_level(0);
+    }
+
     #[test]
     fn test_config_is_clone() {
         let compressor_config = CompressorConfig::default();
@@ -0,0 +1,199 @@
+//! Conformance test vectors for the ALS format.
+//!
+//! Parsers written in other languages need more than the format description
+//! to agree with this crate on edge cases such as escaped separators, ranges
+//! with steps or dictionary references out of bounds. The suite shipped in
+//! `conformance/vectors.json` pairs ALS documents with the rows they expand
+//! to, or the kind of error they must fail with, and this crate is the
+//! reference it is generated against.
+//!
+//! Each vector is a JSON object:
+//!
+//! ```json
+//! {"name": "range-step", "input": "#n\n10>0:-5", "columns": ["n"], "rows": [["10"], ["5"], ["0"]]}
+//! {"name": "dict-ref-out-of-bounds", "input": "$default:a\n#x\n_3", "error": "invalid_dict_ref"}
+//! ```
+//!
+//! Vectors using operators or lines beyond version 1 list the flags of
+//! their features, as a version 2 header would, so implementations of
+//! version 1 alone know which to skip. Rows hold the expanded values, with
+//! `null` for the null token and `""` for the empty token, and errors are
+//! named by [`error_kind`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::als::{AlsParser, EMPTY_TOKEN, NULL_TOKEN};
+use crate::error::{AlsError, Result};
+
+/// The test vectors shipped with this crate, as JSON.
+pub const VECTORS: &str = include_str!("../conformance/vectors.json");
+
+/// A suite of test vectors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceSuite {
+    /// Version of the suite's JSON layout
+    pub version: u32,
+    /// The vectors, in the order they should be run
+    pub vectors: Vec<TestVector>,
+}
+
+/// One ALS document and what parsing it must produce.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// Unique name of the vector
+    pub name: String,
+    /// ALS text to parse and expand
+    pub input: String,
+    /// Feature flags the document uses, so implementations of version 1
+    /// alone can skip it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Expected column names, for a valid document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<String>>,
+    /// Expected rows, `None` standing for a null value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<Vec<Option<String>>>>,
+    /// Expected kind of error, for an invalid document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A vector whose outcome differed from the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    /// Name of the vector
+    pub name: String,
+    /// What was expected and what happened instead
+    pub message: String,
+}
+
+/// Load the test vectors shipped with this crate.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::conformance::{run, suite};
+/// use als_compression::AlsParser;
+///
+/// let suite = suite().unwrap();
+/// assert!(run(&AlsParser::new(), &suite).is_empty());
+/// ```
+pub fn suite() -> Result<ConformanceSuite> {
+    Ok(serde_json::from_str(VECTORS)?)
+}
+
+/// Get the name a conformance vector uses for the kind of an error.
+///
+/// Errors that parsing ALS text cannot produce are all named `other`.
+pub fn error_kind(error: &AlsError) -> &'static str {
+    match error {
+        AlsError::AlsSyntaxError { .. } => "syntax",
+        AlsError::InvalidDictRef { .. } => "invalid_dict_ref",
+        AlsError::InvalidColumnRef { .. } => "invalid_column_ref",
+        AlsError::RangeOverflow { .. } => "range_overflow",
+        AlsError::VersionMismatch { .. } => "version_mismatch",
+        AlsError::UnsupportedFeature { .. } => "unsupported_feature",
+        AlsError::ColumnMismatch { .. } => "column_mismatch",
+        AlsError::InvalidDelta { .. } => "invalid_delta",
+        _ => "other",
+    }
+}
+
+/// Run every vector of a suite against `parser`.
+///
+/// Returns the vectors that failed, in suite order, so an empty list means
+/// the parser conforms.
+pub fn run(parser: &AlsParser, suite: &ConformanceSuite) -> Vec<ConformanceFailure> {
+    suite
+        .vectors
+        .iter()
+        .filter_map(|vector| {
+            check(parser, vector).err().map(|message| ConformanceFailure {
+                name: vector.name.clone(),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Run one vector against `parser`, describing the mismatch if it fails.
+pub fn check(parser: &AlsParser, vector: &TestVector) -> std::result::Result<(), String> {
+    let outcome = parser.parse(&vector.input).and_then(|doc| {
+        let rows = parser.expand(&doc)?;
+        Ok((doc.schema.clone(), rows))
+    });
+    match (outcome, &vector.error) {
+        (Ok(_), Some(kind)) => Err(format!("expected a {kind} error, parsed successfully")),
+        (Err(error), Some(kind)) if error_kind(&error) == kind => Ok(()),
+        (Err(error), Some(kind)) => Err(format!("expected a {kind} error, found {}: {error}", error_kind(&error))),
+        (Err(error), None) => Err(format!("expected rows, found {}: {error}", error_kind(&error))),
+        (Ok((columns, rows)), None) => {
+            if let Some(expected) = &vector.columns {
+                if *expected != columns {
+                    return Err(format!("expected columns {expected:?}, found {columns:?}"));
+                }
+            }
+            let rows: Vec<Vec<Option<String>>> = rows.into_iter().map(|row| row.into_iter().map(vector_value).collect()).collect();
+            match &vector.rows {
+                Some(expected) if *expected != rows => Err(format!("expected rows {expected:?}, found {rows:?}")),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// Convert an expanded value to its form in a vector.
+fn vector_value(value: String) -> Option<String> {
+    if value == NULL_TOKEN {
+        None
+    } else if value == EMPTY_TOKEN {
+        Some(String::new())
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_vectors_pass() {
+        let suite = suite().unwrap();
+        assert!(suite.vectors.len() > 40);
+        let failures = run(&AlsParser::new(), &suite);
+        assert!(failures.is_empty(), "{failures:#?}");
+
+        let mut names: Vec<&str> = suite.vectors.iter().map(|vector| vector.name.as_str()).collect();
+        names.sort_unstable();
+        assert!(names.windows(2).all(|pair| pair[0] != pair[1]), "duplicate vector names");
+        assert!(suite.vectors.iter().all(|vector| vector.error.is_some() != vector.rows.is_some()));
+
+        // Documents that parse list exactly the features they use
+        for vector in &suite.vectors {
+            if let Ok(doc) = AlsParser::new().parse(&vector.input) {
+                let features: Vec<&str> = doc.features().iter().map(|feature| feature.name()).collect();
+                assert_eq!(vector.features, features, "{}", vector.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mismatches_reported() {
+        let parser = AlsParser::new();
+        let vector = |rows: Option<Vec<Vec<Option<String>>>>, error: Option<&str>| TestVector {
+            name: "v".to_string(),
+            input: "#a\n1>2".to_string(),
+            features: Vec::new(),
+            columns: None,
+            rows,
+            error: error.map(str::to_string),
+        };
+        let row = |value: &str| vec![Some(value.to_string())];
+
+        assert!(check(&parser, &vector(Some(vec![row("1"), row("2")]), None)).is_ok());
+        assert!(check(&parser, &vector(Some(vec![row("1")]), None)).unwrap_err().starts_with("expected rows"));
+        assert!(check(&parser, &vector(None, Some("syntax"))).unwrap_err().contains("parsed successfully"));
+    }
+}
@@ -243,7 +243,7 @@ pub fn to_csv(data: &TabularData) -> Result<String> {
 }
 
 /// Convert a `Value` to its CSV string representation.
-fn value_to_csv_string(value: &Value) -> String {
+pub(crate) fn value_to_csv_string(value: &Value) -> String {
     match value {
         Value::Null => String::new(),
         Value::Integer(i) => i.to_string(),
@@ -3,13 +3,21 @@
 //! This module provides functions for converting between JSON format and
 //! `TabularData` structures. It handles JSON arrays of objects, nested
 //! object flattening with dot-notation, and null value preservation.
+//!
+//! Large inputs can be read incrementally with `JsonRecordReader`, which
+//! yields one record at a time from a reader instead of building the whole
+//! document in memory.
 
+use crate::config::{JsonConfig, JsonFormat};
 use crate::convert::{Column, TabularData, Value};
 use crate::error::{AlsError, Result};
 use serde_json;
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io;
+use std::io::{self, BufRead};
+
+/// A JSON object, as yielded by `JsonRecordReader`.
+pub type JsonRecord = serde_json::Map<String, serde_json::Value>;
 
 /// Parse JSON array of objects into `TabularData`.
 ///
@@ -36,48 +44,97 @@ use std::io;
 /// assert_eq!(data.row_count, 2);
 /// ```
 pub fn parse_json(input: &str) -> Result<TabularData<'static>> {
+    parse_json_with_config(input, &JsonConfig::default())
+}
+
+/// Parse JSON into `TabularData` using the given configuration.
+///
+/// With `JsonConfig::records_path` set, the records are taken from the array
+/// at that JSON Pointer instead of the top level.
+///
+/// # Examples
+///
+/// ```
+/// use als_compression::convert::json::parse_json_with_config;
+/// use als_compression::JsonConfig;
+///
+/// let json = r#"{"meta": {"page": 1}, "data": {"items": [{"id": 1}, {"id": 2}]}}"#;
+/// let config = JsonConfig::new().with_records_path("/data/items");
+/// let data = parse_json_with_config(json, &config).unwrap();
+/// assert_eq!(data.row_count, 2);
+/// ```
+pub fn parse_json_with_config(input: &str, config: &JsonConfig) -> Result<TabularData<'static>> {
     // Handle empty input
     if input.trim().is_empty() {
         return Ok(TabularData::new());
     }
 
     // Parse JSON
-    let json_value: serde_json::Value = serde_json::from_str(input)?;
+    let mut json_value: serde_json::Value = serde_json::from_str(input)?;
+    if let Some(path) = &config.records_path {
+        json_value = json_value
+            .pointer_mut(path)
+            .map(serde_json::Value::take)
+            .ok_or_else(|| invalid_json(&format!("JSON Pointer '{}' not found in input", path)))?;
+    }
 
     // Ensure it's an array
     let array = match json_value {
         serde_json::Value::Array(arr) => arr,
-        _ => {
-            return Err(AlsError::JsonParseError(serde_json::Error::io(
-                io::Error::new(io::ErrorKind::InvalidData, "Expected JSON array of objects"),
-            )))
-        }
+        _ => return Err(invalid_json("Expected JSON array of objects")),
     };
 
-    // Handle empty array
-    if array.is_empty() {
-        return Ok(TabularData::new());
-    }
+    let records = array
+        .into_iter()
+        .map(|item| match item {
+            serde_json::Value::Object(obj) => Ok(obj),
+            _ => Err(invalid_json("Array must contain only objects")),
+        })
+        .collect::<Result<Vec<_>>>()?;
 
+    let mut strict = config.strict_schema.then(StrictKeys::default);
+    build_table(records, strict.as_mut())
+}
+
+/// Build `TabularData` from JSON records.
+///
+/// Nested objects are flattened with dot-notation and columns are sorted by
+/// name. This is the table-building half of `parse_json`, for records that
+/// come from a `JsonRecordReader` or another source.
+pub fn records_to_tabular<I>(records: I) -> Result<TabularData<'static>>
+where
+    I: IntoIterator<Item = JsonRecord>,
+{
+    build_table(records, None)
+}
+
+/// Build `TabularData` from records, checking key sets in strict mode.
+///
+/// Records with differing key sets are combined by taking the union of
+/// their keys, with nulls for keys a record lacks. With `strict` set, a
+/// record whose keys differ from the first record's is an error instead.
+fn build_table<I>(records: I, mut strict: Option<&mut StrictKeys>) -> Result<TabularData<'static>>
+where
+    I: IntoIterator<Item = JsonRecord>,
+{
     // Flatten all objects and collect all column names
     let mut flattened_rows: Vec<HashMap<String, serde_json::Value>> = Vec::new();
     let mut all_columns: std::collections::HashSet<String> = std::collections::HashSet::new();
 
-    for item in array {
-        match item {
-            serde_json::Value::Object(obj) => {
-                let flattened = flatten_object(&obj, "");
-                for key in flattened.keys() {
-                    all_columns.insert(key.clone());
-                }
-                flattened_rows.push(flattened);
-            }
-            _ => {
-                return Err(AlsError::JsonParseError(serde_json::Error::io(
-                    io::Error::new(io::ErrorKind::InvalidData, "Array must contain only objects"),
-                )))
-            }
+    for obj in records {
+        let flattened = flatten_object(&obj, "");
+        if let Some(strict) = strict.as_deref_mut() {
+            strict.check(&flattened)?;
+        }
+        for key in flattened.keys() {
+            all_columns.insert(key.clone());
         }
+        flattened_rows.push(flattened);
+    }
+
+    // Handle empty array
+    if flattened_rows.is_empty() {
+        return Ok(TabularData::new());
     }
 
     // Sort column names for consistent ordering
@@ -111,6 +168,383 @@ pub fn parse_json(input: &str) -> Result<TabularData<'static>> {
     Ok(data)
 }
 
+/// The key set all records must share in strict mode.
+#[derive(Debug, Default)]
+struct StrictKeys {
+    /// Sorted flattened keys of the first record
+    keys: Option<Vec<String>>,
+    /// Number of records checked so far
+    checked: usize,
+}
+
+impl StrictKeys {
+    /// Check a flattened record against the first record's keys.
+    fn check(&mut self, record: &HashMap<String, serde_json::Value>) -> Result<()> {
+        let index = self.checked;
+        self.checked += 1;
+
+        let mut keys: Vec<&String> = record.keys().collect();
+        keys.sort();
+        let Some(expected) = &self.keys else {
+            self.keys = Some(keys.into_iter().cloned().collect());
+            return Ok(());
+        };
+        if keys.iter().copied().eq(expected.iter()) {
+            return Ok(());
+        }
+
+        let missing: Vec<&str> = expected
+            .iter()
+            .filter(|k| !record.contains_key(*k))
+            .map(String::as_str)
+            .collect();
+        let extra: Vec<&str> = keys
+            .iter()
+            .filter(|k| expected.binary_search(k).is_err())
+            .map(|k| k.as_str())
+            .collect();
+        Err(invalid_json(&format!(
+            "Record {} keys differ from the first record (missing: [{}], extra: [{}])",
+            index,
+            missing.join(", "),
+            extra.join(", ")
+        )))
+    }
+}
+
+/// Incremental reader for the records of a JSON document.
+///
+/// Accepts either a top-level array of objects or a sequence of objects
+/// (NDJSON). Only the bytes of the current record are buffered, so memory
+/// use is bounded by the largest record rather than the document size.
+///
+/// # Examples
+///
+/// ```
+/// use als_compression::convert::json::JsonRecordReader;
+///
+/// let json = r#"[{"id": 1}, {"id": 2}]"#;
+/// let records: Vec<_> = JsonRecordReader::new(json.as_bytes())
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(records.len(), 2);
+/// ```
+pub struct JsonRecordReader<R: BufRead> {
+    reader: R,
+    state: ReaderState,
+    buffer: Vec<u8>,
+    records_path: Option<String>,
+    /// Whether the record array is nested inside the document
+    nested: bool,
+    /// Key set checking across chunks in strict mode
+    strict: Option<StrictKeys>,
+}
+
+/// Position of a `JsonRecordReader` in the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReaderState {
+    /// Nothing read yet
+    Start,
+    /// Inside the top-level array, before the first element
+    ArrayStart,
+    /// Inside the top-level array, after an element
+    ArrayNext,
+    /// Reading a sequence of top-level objects
+    Sequence,
+    /// End of input reached, or an error occurred
+    Done,
+}
+
+impl<R: BufRead> JsonRecordReader<R> {
+    /// Create a reader over `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: ReaderState::Start,
+            buffer: Vec::new(),
+            records_path: None,
+            nested: false,
+            strict: None,
+        }
+    }
+
+    /// Create a reader using the record location from `config`.
+    pub fn with_config(reader: R, config: &JsonConfig) -> Self {
+        let mut records = Self::new(reader);
+        records.records_path = config.records_path.clone();
+        records.strict = config.strict_schema.then(StrictKeys::default);
+        records
+    }
+
+    /// Read up to `max` records into a `TabularData`.
+    ///
+    /// Returns `None` once the input is exhausted. In strict mode, every
+    /// record must have the keys of the first record of the first chunk.
+    pub fn read_chunk(&mut self, max: usize) -> Result<Option<TabularData<'static>>> {
+        let records = self.by_ref().take(max.max(1)).collect::<Result<Vec<_>>>()?;
+        if records.is_empty() {
+            return Ok(None);
+        }
+        let result = build_table(records, self.strict.as_mut());
+        if result.is_err() {
+            self.state = ReaderState::Done;
+        }
+        result.map(Some)
+    }
+
+    /// Skip whitespace and return the next byte without consuming it.
+    fn peek_non_whitespace(&mut self) -> Result<Option<u8>> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(i) => {
+                    let byte = buf[i];
+                    self.reader.consume(i);
+                    return Ok(Some(byte));
+                }
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// Read one complete value into the buffer without parsing it.
+    ///
+    /// Must be called with the reader positioned at the first byte of the value.
+    fn read_value(&mut self) -> Result<()> {
+        self.buffer.clear();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                // A scalar may be terminated by the end of input
+                if depth == 0 && !in_string && !self.buffer.is_empty() {
+                    return Ok(());
+                }
+                return Err(invalid_json("Unexpected end of input inside value"));
+            }
+
+            let mut end = None;
+            for (i, &byte) in buf.iter().enumerate() {
+                if in_string {
+                    match byte {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => {
+                            in_string = false;
+                            if depth == 0 {
+                                end = Some(i + 1);
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth > 0 => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    }
+                    // Delimiters end a scalar without being part of it
+                    b',' | b':' | b'}' | b']' if depth == 0 => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ if depth == 0 && byte.is_ascii_whitespace() => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            let taken = end.unwrap_or(buf.len());
+            self.buffer.extend_from_slice(&buf[..taken]);
+            self.reader.consume(taken);
+            if end.is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read one complete object and parse it.
+    fn read_object(&mut self) -> Result<JsonRecord> {
+        self.read_value()?;
+        Ok(serde_json::from_slice(&self.buffer)?)
+    }
+
+    /// Move into the value at `path`, leaving the reader at its first byte.
+    ///
+    /// Only the values on the way to the target are read; siblings are
+    /// skipped one at a time.
+    fn seek_path(&mut self, path: &str) -> Result<()> {
+        let not_found = || invalid_json(&format!("JSON Pointer '{}' not found in input", path));
+        if path.is_empty() {
+            return Ok(());
+        }
+        let segments = path
+            .strip_prefix('/')
+            .ok_or_else(|| invalid_json(&format!("Invalid JSON Pointer '{}'", path)))?;
+
+        for segment in segments.split('/') {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            match self.peek_non_whitespace()? {
+                Some(b'{') => {
+                    self.reader.consume(1);
+                    loop {
+                        if self.peek_non_whitespace()? != Some(b'"') {
+                            return Err(not_found());
+                        }
+                        self.read_value()?;
+                        let key: String = serde_json::from_slice(&self.buffer)?;
+                        if self.peek_non_whitespace()? != Some(b':') {
+                            return Err(invalid_json("Expected ':' after object key"));
+                        }
+                        self.reader.consume(1);
+                        if key == segment {
+                            break;
+                        }
+                        if !self.skip_element()? {
+                            return Err(not_found());
+                        }
+                    }
+                }
+                Some(b'[') => {
+                    let index: usize = segment.parse().map_err(|_| not_found())?;
+                    self.reader.consume(1);
+                    for _ in 0..index {
+                        if !self.skip_element()? {
+                            return Err(not_found());
+                        }
+                    }
+                    if matches!(self.peek_non_whitespace()?, Some(b']') | None) {
+                        return Err(not_found());
+                    }
+                }
+                _ => return Err(not_found()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Skip a value inside an object or array, and the `,` after it.
+    ///
+    /// Returns false if the value was the last one in its container.
+    fn skip_element(&mut self) -> Result<bool> {
+        if matches!(self.peek_non_whitespace()?, Some(b'}' | b']') | None) {
+            return Err(invalid_json("Expected a value"));
+        }
+        self.read_value()?;
+        match self.peek_non_whitespace()? {
+            Some(b',') => {
+                self.reader.consume(1);
+                Ok(true)
+            }
+            Some(b'}' | b']') => Ok(false),
+            _ => Err(invalid_json("Expected ',' after value")),
+        }
+    }
+
+    /// Advance to the next record, returning `None` at the end of input.
+    fn next_record(&mut self) -> Result<Option<JsonRecord>> {
+        loop {
+            let byte = self.peek_non_whitespace()?;
+            match (self.state, byte) {
+                (ReaderState::Done, _) | (ReaderState::Start, None) => return Ok(None),
+                (ReaderState::Start, _) if self.records_path.is_some() => {
+                    let path = self.records_path.take().unwrap_or_default();
+                    self.seek_path(&path)?;
+                    if self.peek_non_whitespace()? != Some(b'[') {
+                        return Err(invalid_json(&format!("JSON Pointer '{}' is not an array", path)));
+                    }
+                    self.reader.consume(1);
+                    self.state = ReaderState::ArrayStart;
+                    self.nested = true;
+                }
+                (ReaderState::Start, Some(b'[')) => {
+                    self.reader.consume(1);
+                    self.state = ReaderState::ArrayStart;
+                }
+                (ReaderState::Start, Some(b'{')) => self.state = ReaderState::Sequence,
+                (ReaderState::Start, Some(_)) => {
+                    return Err(invalid_json("Expected JSON array of objects"));
+                }
+                (ReaderState::ArrayStart, Some(b']')) | (ReaderState::ArrayNext, Some(b']')) => {
+                    self.reader.consume(1);
+                    // The rest of a wrapping document is not read
+                    if !self.nested && self.peek_non_whitespace()?.is_some() {
+                        return Err(invalid_json("Trailing characters after JSON array"));
+                    }
+                    self.state = ReaderState::Done;
+                    return Ok(None);
+                }
+                (ReaderState::ArrayNext, Some(b',')) => {
+                    self.reader.consume(1);
+                    if self.peek_non_whitespace()? != Some(b'{') {
+                        return Err(invalid_json("Array must contain only objects"));
+                    }
+                    return self.read_object().map(Some);
+                }
+                (ReaderState::ArrayStart, Some(b'{')) => {
+                    self.state = ReaderState::ArrayNext;
+                    return self.read_object().map(Some);
+                }
+                (ReaderState::ArrayStart, Some(_)) => {
+                    return Err(invalid_json("Array must contain only objects"));
+                }
+                (ReaderState::ArrayStart | ReaderState::ArrayNext, None) => {
+                    return Err(invalid_json("Unexpected end of input inside array"));
+                }
+                (ReaderState::ArrayNext, Some(_)) => {
+                    return Err(invalid_json("Expected ',' or ']' in JSON array"));
+                }
+                (ReaderState::Sequence, None) => return Ok(None),
+                (ReaderState::Sequence, Some(b'{')) => return self.read_object().map(Some),
+                (ReaderState::Sequence, Some(_)) => {
+                    return Err(invalid_json("Expected JSON object"));
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonRecordReader<R> {
+    type Item = Result<JsonRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(record) => record.map(Ok),
+            Err(e) => {
+                self.state = ReaderState::Done;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Create a JSON parse error for structurally invalid input.
+fn invalid_json(message: &str) -> AlsError {
+    AlsError::JsonParseError(serde_json::Error::io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        message.to_string(),
+    )))
+}
+
 /// Flatten a JSON object using dot-notation for nested keys.
 ///
 /// For example: `{"user": {"name": "Alice", "age": 30}}` becomes:
@@ -207,9 +641,37 @@ fn json_value_to_value(json_val: &serde_json::Value) -> Value<'static> {
 /// assert!(json.contains("\"name\""));
 /// ```
 pub fn to_json(data: &TabularData) -> Result<String> {
+    to_json_with_format(data, JsonFormat::Compact)
+}
+
+/// Convert `TabularData` to JSON rows in the given output format.
+///
+/// `JsonFormat::Ndjson` writes one row object per line instead of an array.
+///
+/// # Examples
+///
+/// ```
+/// use als_compression::convert::{TabularData, Column, Value};
+/// use als_compression::convert::json::to_json_with_format;
+/// use als_compression::JsonFormat;
+/// use std::borrow::Cow;
+///
+/// let mut data = TabularData::new();
+/// data.add_column(Column::new(
+///     Cow::Borrowed("id"),
+///     vec![Value::Integer(1), Value::Integer(2)],
+/// ));
+///
+/// let json = to_json_with_format(&data, JsonFormat::Ndjson).unwrap();
+/// assert_eq!(json, "{\"id\":1}\n{\"id\":2}\n");
+/// ```
+pub fn to_json_with_format(data: &TabularData, format: JsonFormat) -> Result<String> {
     // Handle empty data
     if data.is_empty() || data.column_count() == 0 {
-        return Ok("[]".to_string());
+        return match format {
+            JsonFormat::Ndjson => Ok(String::new()),
+            _ => Ok("[]".to_string()),
+        };
     }
 
     let mut array = Vec::new();
@@ -230,7 +692,95 @@ pub fn to_json(data: &TabularData) -> Result<String> {
     }
 
     // Serialize to JSON string
-    serde_json::to_string(&array).map_err(|e| e.into())
+    match format {
+        JsonFormat::Ndjson => write_lines(&array),
+        _ => write_json(&array, format),
+    }
+}
+
+/// Convert `TabularData` to column-oriented JSON.
+///
+/// The output is a single object mapping each column name to the array of
+/// its values, with columns in their original order. Dot-notation column
+/// names are kept as flat keys, so every column is a top-level array.
+///
+/// # Examples
+///
+/// ```
+/// use als_compression::convert::{TabularData, Column, Value};
+/// use als_compression::convert::json::to_json_columnar;
+/// use std::borrow::Cow;
+///
+/// let mut data = TabularData::new();
+/// data.add_column(Column::new(
+///     Cow::Borrowed("id"),
+///     vec![Value::Integer(1), Value::Integer(2)],
+/// ));
+/// data.add_column(Column::new(
+///     Cow::Borrowed("name"),
+///     vec![Value::string("a"), Value::string("b")],
+/// ));
+///
+/// let json = to_json_columnar(&data).unwrap();
+/// assert_eq!(json, r#"{"id":[1,2],"name":["a","b"]}"#);
+/// ```
+pub fn to_json_columnar(data: &TabularData) -> Result<String> {
+    to_json_columnar_with_format(data, JsonFormat::Compact)
+}
+
+/// Convert `TabularData` to column-oriented JSON in the given output format.
+///
+/// `JsonFormat::Ndjson` writes one single-column object per line.
+pub fn to_json_columnar_with_format(data: &TabularData, format: JsonFormat) -> Result<String> {
+    match format {
+        JsonFormat::Ndjson => {
+            let lines: Vec<ColumnsView> = data.columns.chunks(1).map(ColumnsView).collect();
+            write_lines(&lines)
+        }
+        _ => write_json(&ColumnsView(&data.columns), format),
+    }
+}
+
+/// Columns serialized as an object of value arrays, keeping column order.
+struct ColumnsView<'a, 'b>(&'a [Column<'b>]);
+
+impl serde::Serialize for ColumnsView<'_, '_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for col in self.0 {
+            let values: Vec<serde_json::Value> = col.values.iter().map(value_to_json_value).collect();
+            map.serialize_entry(col.name.as_ref(), &values)?;
+        }
+        map.end()
+    }
+}
+
+/// Serialize a value as compact or pretty JSON.
+fn write_json<T: serde::Serialize>(value: &T, format: JsonFormat) -> Result<String> {
+    match format {
+        JsonFormat::Pretty { indent } => {
+            let indent = vec![b' '; indent];
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+            let mut output = Vec::new();
+            let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+            value.serialize(&mut serializer)?;
+            // serde_json only writes valid UTF-8
+            Ok(String::from_utf8(output).expect("serde_json produced invalid UTF-8"))
+        }
+        _ => Ok(serde_json::to_string(value)?),
+    }
+}
+
+/// Serialize each item as compact JSON on its own line.
+fn write_lines<T: serde::Serialize>(items: &[T]) -> Result<String> {
+    let mut output = String::new();
+    for item in items {
+        output.push_str(&serde_json::to_string(item)?);
+        output.push('\n');
+    }
+    Ok(output)
 }
 
 /// Insert a value into a JSON object, creating nested structure for dot-notation keys.
@@ -247,6 +797,13 @@ fn insert_nested(
     if parts.len() == 1 {
         // Simple key, insert directly
         obj.insert(key.to_string(), value);
+    } else if !can_nest(obj, &parts[..parts.len() - 1]) {
+        // Records with differing shapes can have a scalar where another
+        // record has an object. A null child is a key this record lacked;
+        // anything else keeps its full dotted key.
+        if !value.is_null() {
+            obj.insert(key.to_string(), value);
+        }
     } else {
         // Nested key, create intermediate objects
         let mut current = obj;
@@ -257,19 +814,35 @@ fn insert_nested(
                 current.insert(part.to_string(), value);
                 break;
             } else {
-                // Intermediate part, ensure object exists
-                current = current
+                // Intermediate part, ensure object exists (a null parent
+                // comes from a record that lacked it and is replaced)
+                let entry = current
                     .entry(part.to_string())
-                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
-                    .as_object_mut()
-                    .expect("Expected object for nested key");
+                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+                if entry.is_null() {
+                    *entry = serde_json::Value::Object(serde_json::Map::new());
+                }
+                current = entry.as_object_mut().expect("Expected object for nested key");
             }
         }
     }
 }
 
+/// Check that no non-null scalar occupies any of the parent keys.
+fn can_nest(obj: &serde_json::Map<String, serde_json::Value>, parents: &[&str]) -> bool {
+    let mut current = obj;
+    for part in parents {
+        match current.get(*part) {
+            None | Some(serde_json::Value::Null) => return true,
+            Some(serde_json::Value::Object(nested)) => current = nested,
+            Some(_) => return false,
+        }
+    }
+    true
+}
+
 /// Convert our `Value` type to `serde_json::Value`.
-fn value_to_json_value(value: &Value) -> serde_json::Value {
+pub(crate) fn value_to_json_value(value: &Value) -> serde_json::Value {
     match value {
         Value::Null => serde_json::Value::Null,
         Value::Integer(i) => serde_json::Value::Number((*i).into()),
@@ -702,4 +1275,231 @@ mod tests {
             serde_json::json!(true)
         );
     }
+
+    #[test]
+    fn test_record_reader_array() {
+        let json = r#" [ {"id": 1, "s": "a}\"]{"}, {"id": 2, "nested": {"x": [1, {"y": 2}]}} ] "#;
+        let records: Vec<JsonRecord> = JsonRecordReader::new(json.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["s"], serde_json::json!("a}\"]{"));
+        assert_eq!(records[1]["nested"]["x"][1]["y"], serde_json::json!(2));
+    }
+
+    #[test]
+    fn test_record_reader_small_buffer() {
+        let json = r#"[{"name": "a long value \\ with \" escapes"}, {"name": "b"}]"#;
+        let reader = io::BufReader::with_capacity(3, json.as_bytes());
+        let records: Vec<JsonRecord> = JsonRecordReader::new(reader).collect::<Result<_>>().unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["name"], serde_json::json!("a long value \\ with \" escapes"));
+    }
+
+    #[test]
+    fn test_record_reader_ndjson() {
+        let json = "{\"id\": 1}\n{\"id\": 2}\n\n{\"id\": 3}\n";
+        let records: Vec<JsonRecord> = JsonRecordReader::new(json.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+    }
+
+    #[test]
+    fn test_record_reader_empty() {
+        assert_eq!(JsonRecordReader::new("".as_bytes()).count(), 0);
+        assert_eq!(JsonRecordReader::new(" [ ] ".as_bytes()).count(), 0);
+    }
+
+    #[test]
+    fn test_record_reader_errors() {
+        for json in ["42", "[1, 2]", "[{\"a\": 1} {\"a\": 2}]", "[{\"a\": 1}", "[{\"a\": 1}] x", "[{\"a\": }]"] {
+            let results: Vec<Result<JsonRecord>> = JsonRecordReader::new(json.as_bytes()).collect();
+            assert!(results.last().unwrap().is_err(), "expected error for {}", json);
+        }
+    }
+
+    #[test]
+    fn test_record_reader_chunks() {
+        let json = r#"[{"id": 1}, {"id": 2}, {"id": 3, "extra": true}]"#;
+        let mut reader = JsonRecordReader::new(json.as_bytes());
+
+        let first = reader.read_chunk(2).unwrap().unwrap();
+        assert_eq!(first.row_count, 2);
+        assert_eq!(first.column_names(), vec!["id"]);
+
+        let second = reader.read_chunk(2).unwrap().unwrap();
+        assert_eq!(second.row_count, 1);
+        assert_eq!(second.column_names(), vec!["extra", "id"]);
+
+        assert!(reader.read_chunk(2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_json_records_path() {
+        let json = r#"{"meta": {"page": 1}, "data": {"items": [{"id": 1}, {"id": 2}]}}"#;
+        let config = JsonConfig::new().with_records_path("/data/items");
+        let data = parse_json_with_config(json, &config).unwrap();
+        assert_eq!(data.row_count, 2);
+        assert_eq!(data.column_names(), vec!["id"]);
+
+        // Without a path the wrapper is rejected
+        assert!(parse_json(json).is_err());
+
+        let missing = JsonConfig::new().with_records_path("/data/rows");
+        assert!(parse_json_with_config(json, &missing).is_err());
+        let not_array = JsonConfig::new().with_records_path("/meta");
+        assert!(parse_json_with_config(json, &not_array).is_err());
+    }
+
+    #[test]
+    fn test_record_reader_records_path() {
+        let json = r#"{"meta": {"note": "a } tricky \" string", "list": [1, [2]], "n": -1.5e3, "ok": true},
+                       "pages": [{"items": []}, {"a/b": {"items~": [{"id": 1}, {"id": 2}]}}],
+                       "after": 1}"#;
+        let config = JsonConfig::new().with_records_path("/pages/1/a~1b/items~0");
+        let records: Vec<JsonRecord> = JsonRecordReader::with_config(json.as_bytes(), &config)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["id"], serde_json::json!(2));
+
+        let config = JsonConfig::new().with_records_path("/pages/0/items");
+        assert_eq!(JsonRecordReader::with_config(json.as_bytes(), &config).count(), 0);
+    }
+
+    #[test]
+    fn test_record_reader_records_path_errors() {
+        let json = r#"{"meta": 1, "data": {"items": [{"id": 1}]}}"#;
+        for path in ["/missing", "/data/rows", "/meta", "/data/items/5", "data"] {
+            let config = JsonConfig::new().with_records_path(path);
+            let mut reader = JsonRecordReader::with_config(json.as_bytes(), &config);
+            assert!(reader.next().unwrap().is_err(), "expected error for {}", path);
+            assert!(reader.next().is_none());
+        }
+    }
+
+    #[test]
+    fn test_parse_json_strict_schema() {
+        let uniform = r#"[{"id": 1, "user": {"name": "a"}}, {"user": {"name": "b"}, "id": 2}]"#;
+        let strict = JsonConfig::new().with_strict_schema(true);
+        assert_eq!(parse_json_with_config(uniform, &strict).unwrap().row_count, 2);
+
+        let mixed = r#"[{"id": 1, "name": "a"}, {"id": 2, "email": "b@example.com"}]"#;
+        let err = parse_json_with_config(mixed, &strict).unwrap_err().to_string();
+        assert!(err.contains("Record 1"), "{}", err);
+        assert!(err.contains("missing: [name]"), "{}", err);
+        assert!(err.contains("extra: [email]"), "{}", err);
+
+        // The default takes the union of keys
+        let data = parse_json(mixed).unwrap();
+        assert_eq!(data.column_names(), vec!["email", "id", "name"]);
+        assert!(data.columns[0].values[0].is_null());
+        assert!(data.columns[2].values[1].is_null());
+    }
+
+    #[test]
+    fn test_record_reader_strict_across_chunks() {
+        let json = r#"[{"id": 1}, {"id": 2}, {"id": 3, "extra": true}]"#;
+        let config = JsonConfig::new().with_strict_schema(true);
+        let mut reader = JsonRecordReader::with_config(json.as_bytes(), &config);
+
+        assert_eq!(reader.read_chunk(2).unwrap().unwrap().row_count, 2);
+        let err = reader.read_chunk(2).unwrap_err().to_string();
+        assert!(err.contains("Record 2"), "{}", err);
+        assert!(reader.read_chunk(2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_heterogeneous_shapes_roundtrip() {
+        let json = r#"[{"a": 1, "b": {"c": 2}}, {"a": {"x": 3}, "b": null}, {"d": [1, 2]}]"#;
+        let data = parse_json(json).unwrap();
+        assert_eq!(data.column_names(), vec!["a", "a.x", "b", "b.c", "d"]);
+
+        let output = to_json(&data).unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+        assert_eq!(rows[0]["a"], serde_json::json!(1));
+        assert_eq!(rows[0]["b"]["c"], serde_json::json!(2));
+        assert_eq!(rows[1]["a"]["x"], serde_json::json!(3));
+        assert_eq!(rows[2]["d"], serde_json::json!("[1,2]"));
+        assert!(rows[2]["a"]["x"].is_null());
+    }
+
+    #[test]
+    fn test_insert_nested_conflict() {
+        let mut obj = serde_json::Map::new();
+        insert_nested(&mut obj, "a", serde_json::json!(1));
+        insert_nested(&mut obj, "a.b", serde_json::Value::Null);
+        assert_eq!(serde_json::Value::Object(obj.clone()), serde_json::json!({"a": 1}));
+
+        insert_nested(&mut obj, "a.c", serde_json::json!(2));
+        assert_eq!(serde_json::Value::Object(obj), serde_json::json!({"a": 1, "a.c": 2}));
+    }
+
+    #[test]
+    fn test_to_json_columnar() {
+        let json = r#"[{"id": 1, "user": {"name": "a"}, "ok": true}, {"id": 2, "ok": null}]"#;
+        let data = parse_json(json).unwrap();
+
+        let output = to_json_columnar(&data).unwrap();
+        assert_eq!(output, r#"{"id":[1,2],"ok":[true,null],"user.name":["a",null]}"#);
+    }
+
+    #[test]
+    fn test_to_json_columnar_empty() {
+        assert_eq!(to_json_columnar(&TabularData::new()).unwrap(), "{}");
+
+        let mut data = TabularData::new();
+        data.add_column(Column::new(Cow::Borrowed("a\"b"), Vec::new()));
+        assert_eq!(to_json_columnar(&data).unwrap(), r#"{"a\"b":[]}"#);
+    }
+
+    #[test]
+    fn test_to_json_formats() {
+        let data = parse_json(r#"[{"id": 1, "user": {"name": "a"}}, {"id": 2}]"#).unwrap();
+
+        assert_eq!(
+            to_json_with_format(&data, JsonFormat::Compact).unwrap(),
+            to_json(&data).unwrap()
+        );
+        assert_eq!(
+            to_json_with_format(&data, JsonFormat::Ndjson).unwrap(),
+            "{\"id\":1,\"user\":{\"name\":\"a\"}}\n{\"id\":2,\"user\":{\"name\":null}}\n"
+        );
+        assert_eq!(
+            to_json_with_format(&data, JsonFormat::Pretty { indent: 4 }).unwrap(),
+            "[\n    {\n        \"id\": 1,\n        \"user\": {\n            \"name\": \"a\"\n        }\n    },\n    {\n        \"id\": 2,\n        \"user\": {\n            \"name\": null\n        }\n    }\n]"
+        );
+
+        let empty = TabularData::new();
+        assert_eq!(to_json_with_format(&empty, JsonFormat::Ndjson).unwrap(), "");
+        assert_eq!(to_json_with_format(&empty, JsonFormat::Pretty { indent: 2 }).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_to_json_columnar_formats() {
+        let data = parse_json(r#"[{"b": 1, "a": "x"}, {"b": 2, "a": "y"}]"#).unwrap();
+
+        assert_eq!(
+            to_json_columnar_with_format(&data, JsonFormat::Ndjson).unwrap(),
+            "{\"a\":[\"x\",\"y\"]}\n{\"b\":[1,2]}\n"
+        );
+        assert_eq!(
+            to_json_columnar_with_format(&data, JsonFormat::Pretty { indent: 2 }).unwrap(),
+            "{\n  \"a\": [\n    \"x\",\n    \"y\"\n  ],\n  \"b\": [\n    1,\n    2\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_records_to_tabular_matches_parse_json() {
+        let json = r#"[{"id": 1, "user": {"name": "Alice"}}, {"id": 2}]"#;
+        let records = JsonRecordReader::new(json.as_bytes()).collect::<Result<Vec<_>>>().unwrap();
+        let streamed = records_to_tabular(records).unwrap();
+        let parsed = parse_json(json).unwrap();
+
+        assert_eq!(streamed.column_names(), parsed.column_names());
+        assert_eq!(streamed.get_row(1), parsed.get_row(1));
+    }
 }
@@ -1,16 +1,22 @@
 //! Data conversion types and utilities.
 //!
 //! This module contains types for representing tabular data in a format-agnostic
-//! way, enabling conversion between CSV, JSON, ALS, and log formats.
+//! way, enabling conversion between CSV, JSON, ALS, and log formats,
+//! ingestion of OpenTelemetry exports, and partitioning rows by time.
 
 pub mod csv;
 pub mod json;
 pub mod log_compress;
+pub mod otlp;
+pub mod rfc5424;
 pub mod syslog;
 pub mod syslog_optimized;
+pub(crate) mod partition;
+mod reshape;
 mod tabular;
 
+pub use partition::{TimeGranularity, TimePartition};
 pub use tabular::{Column, ColumnType, TabularData, Value};
-pub use syslog::{parse_syslog, to_syslog, MessageType, SyslogEntry};
+pub use syslog::{parse_syslog, parse_syslog_with_config, to_syslog, MessageType, SyslogConfig, SyslogEntry};
 pub use syslog_optimized::parse_syslog_optimized;
 pub use log_compress::compress_syslog;
@@ -0,0 +1,330 @@
+//! OpenTelemetry OTLP JSON export parsing.
+//!
+//! OTLP exports nest records three levels deep: resources, instrumentation
+//! scopes, then log records or metric data points. This module flattens
+//! each log record and each metric data point into one row, copying the
+//! attributes of its resource and scope:
+//!
+//! | Column | Source |
+//! |--------|--------|
+//! | `resource.<key>` | Resource attributes |
+//! | `scope.name`, `scope.version`, `scope.<key>` | Instrumentation scope |
+//! | `attributes.<key>` | Record or data point attributes |
+//! | `timeUnixNano`, `severityText`, `body`, ... | Log record fields |
+//! | `name`, `unit`, `type`, `value`, ... | Metric and data point fields |
+//!
+//! Timestamps and counts, which OTLP JSON encodes as strings, become
+//! integers, so the range and enum detectors can encode them. Input may be
+//! a single export request or several, one per line, as written by the
+//! collector's file exporter.
+
+use serde_json::{Map, Value};
+
+use super::json::{records_to_tabular, JsonRecord};
+use super::TabularData;
+use crate::error::{AlsError, Result};
+
+/// Fields holding 64-bit integers, which OTLP JSON encodes as strings.
+const INTEGER_FIELDS: &[&str] = &["count", "asInt", "zeroCount"];
+
+/// Check whether JSON text looks like an OTLP log or metric export.
+///
+/// Only the start of the text is inspected.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::convert::otlp::is_otlp_json;
+///
+/// assert!(is_otlp_json(r#"{"resourceLogs": []}"#));
+/// assert!(!is_otlp_json(r#"[{"id": 1}]"#));
+/// ```
+pub fn is_otlp_json(input: &str) -> bool {
+    let trimmed = input.trim_start();
+    let end = (0..=trimmed.len().min(256)).rev().find(|&i| trimmed.is_char_boundary(i)).unwrap_or(0);
+    let head = &trimmed[..end];
+    head.starts_with('{') && (head.contains("\"resourceLogs\"") || head.contains("\"resourceMetrics\""))
+}
+
+/// Parse an OTLP JSON log or metric export into `TabularData`.
+///
+/// # Errors
+///
+/// Returns `AlsError::JsonParseError` for invalid JSON and
+/// `AlsError::LogParseError` for a document that is not an OTLP export,
+/// with the 1-based index of the document as its line.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::convert::otlp::parse_otlp_json;
+///
+/// let export = r#"{"resourceLogs": [{
+///     "resource": {"attributes": [{"key": "service.name", "value": {"stringValue": "api"}}]},
+///     "scopeLogs": [{"logRecords": [
+///         {"timeUnixNano": "1700000000000000000", "severityText": "INFO", "body": {"stringValue": "started"}}
+///     ]}]
+/// }]}"#;
+/// let data = parse_otlp_json(export).unwrap();
+/// assert_eq!(data.row_count, 1);
+/// assert!(data.column_names().contains(&"resource.service.name"));
+/// ```
+pub fn parse_otlp_json(input: &str) -> Result<TabularData<'static>> {
+    let mut records = Vec::new();
+    for (index, document) in serde_json::Deserializer::from_str(input).into_iter::<Value>().enumerate() {
+        let document = document?;
+        let line = index + 1;
+        if let Some(resources) = document.get("resourceLogs") {
+            for resource in list(resources) {
+                flatten_scopes(resource, "scopeLogs", "logRecords", &mut records, log_record);
+            }
+        } else if let Some(resources) = document.get("resourceMetrics") {
+            for resource in list(resources) {
+                flatten_scopes(resource, "scopeMetrics", "metrics", &mut records, metric_points);
+            }
+        } else {
+            return Err(AlsError::LogParseError {
+                line,
+                message: "expected an OTLP export with resourceLogs or resourceMetrics".to_string(),
+            });
+        }
+    }
+    records_to_tabular(records)
+}
+
+/// Flatten the scopes of one resource, calling `flatten` for each item of
+/// each scope with a record holding the resource and scope columns.
+fn flatten_scopes(
+    resource: &Value,
+    scopes_key: &str,
+    items_key: &str,
+    records: &mut Vec<JsonRecord>,
+    flatten: fn(&Value, JsonRecord, &mut Vec<JsonRecord>),
+) {
+    let mut base = JsonRecord::new();
+    if let Some(attributes) = resource.pointer("/resource/attributes") {
+        base.insert("resource".to_string(), Value::Object(key_values(attributes)));
+    }
+
+    for scope_items in resource.get(scopes_key).map(list).unwrap_or_default() {
+        let mut scoped = base.clone();
+        if let Some(scope) = scope_items.get("scope") {
+            let mut columns = scope.get("attributes").map(key_values).unwrap_or_default();
+            for key in ["name", "version"] {
+                if let Some(value) = scope.get(key) {
+                    columns.insert(key.to_string(), value.clone());
+                }
+            }
+            scoped.insert("scope".to_string(), Value::Object(columns));
+        }
+        for item in scope_items.get(items_key).map(list).unwrap_or_default() {
+            flatten(item, scoped.clone(), records);
+        }
+    }
+}
+
+/// Flatten a log record into one row.
+fn log_record(log: &Value, mut record: JsonRecord, records: &mut Vec<JsonRecord>) {
+    copy_fields(log, &mut record);
+    records.push(record);
+}
+
+/// Flatten a metric into one row per data point.
+fn metric_points(metric: &Value, mut base: JsonRecord, records: &mut Vec<JsonRecord>) {
+    let Some(metric) = metric.as_object() else {
+        return;
+    };
+
+    for key in ["name", "description", "unit"] {
+        if let Some(value) = metric.get(key) {
+            base.insert(key.to_string(), value.clone());
+        }
+    }
+
+    for kind in ["gauge", "sum", "histogram", "exponentialHistogram", "summary"] {
+        let Some(data) = metric.get(kind) else {
+            continue;
+        };
+        let mut typed = base.clone();
+        typed.insert("type".to_string(), kind.into());
+        for key in ["aggregationTemporality", "isMonotonic"] {
+            if let Some(value) = data.get(key) {
+                typed.insert(key.to_string(), value.clone());
+            }
+        }
+        for point in data.get("dataPoints").map(list).unwrap_or_default() {
+            let mut record = typed.clone();
+            copy_fields(point, &mut record);
+            // Gauges and sums hold one of asDouble or asInt
+            if let Some(value) = record.remove("asDouble").or_else(|| record.remove("asInt")) {
+                record.insert("value".to_string(), value);
+            }
+            records.push(record);
+        }
+    }
+}
+
+/// Copy the fields of a log record or data point into a row.
+fn copy_fields(item: &Value, record: &mut JsonRecord) {
+    let Some(item) = item.as_object() else {
+        return;
+    };
+
+    for (key, value) in item {
+        let value = match key.as_str() {
+            "attributes" => Value::Object(key_values(value)),
+            "body" => any_value(value),
+            _ if key.ends_with("UnixNano") || INTEGER_FIELDS.contains(&key.as_str()) => integer(value),
+            _ => value.clone(),
+        };
+        record.insert(key.clone(), value);
+    }
+}
+
+/// Convert a list of OTLP `KeyValue`s into an object.
+fn key_values(attributes: &Value) -> Map<String, Value> {
+    list(attributes)
+        .iter()
+        .filter_map(|attribute| {
+            let key = attribute.get("key")?.as_str()?;
+            let value = attribute.get("value").map_or(Value::Null, any_value);
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Convert an OTLP `AnyValue` into plain JSON.
+///
+/// Arrays and key-value lists become JSON text, so they fit in one column.
+fn any_value(value: &Value) -> Value {
+    let Some((kind, inner)) = value.as_object().and_then(|object| object.iter().next()) else {
+        return Value::Null;
+    };
+    match kind.as_str() {
+        "intValue" => integer(inner),
+        "arrayValue" => {
+            let values: Vec<Value> = inner.get("values").map(list).unwrap_or_default().iter().map(any_value).collect();
+            Value::String(Value::Array(values).to_string())
+        }
+        "kvlistValue" => {
+            let values = inner.get("values").map(key_values).unwrap_or_default();
+            Value::String(Value::Object(values).to_string())
+        }
+        _ => inner.clone(),
+    }
+}
+
+/// Convert a 64-bit integer encoded as a string into a number.
+fn integer(value: &Value) -> Value {
+    value
+        .as_str()
+        .and_then(|text| text.parse::<i64>().ok())
+        .map_or_else(|| value.clone(), Value::from)
+}
+
+/// Get the items of a JSON array, or none for other values.
+fn list(value: &Value) -> &[Value] {
+    value.as_array().map_or(&[], Vec::as_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::Value as CellValue;
+
+    const LOGS: &str = r#"{"resourceLogs": [{
+        "resource": {"attributes": [
+            {"key": "service.name", "value": {"stringValue": "checkout"}},
+            {"key": "host.cpus", "value": {"intValue": "8"}}
+        ]},
+        "scopeLogs": [{
+            "scope": {"name": "app.http", "version": "1.2.0"},
+            "logRecords": [
+                {
+                    "timeUnixNano": "1700000000000000000",
+                    "severityNumber": 9,
+                    "severityText": "INFO",
+                    "body": {"stringValue": "order placed"},
+                    "attributes": [{"key": "order.id", "value": {"intValue": "42"}}],
+                    "traceId": "5b8efff798038103d269b633813fc60c",
+                    "spanId": "0000000000000001"
+                },
+                {
+                    "timeUnixNano": "1700000000500000000",
+                    "severityNumber": 17,
+                    "severityText": "ERROR",
+                    "body": {"kvlistValue": {"values": [{"key": "code", "value": {"intValue": "502"}}]}}
+                }
+            ]
+        }]
+    }]}"#;
+
+    fn cell<'a>(data: &'a TabularData<'static>, column: &str, row: usize) -> &'a CellValue<'static> {
+        data.get_column_by_name(column).unwrap().get(row).unwrap()
+    }
+
+    #[test]
+    fn test_is_otlp_json() {
+        assert!(is_otlp_json(LOGS));
+        assert!(is_otlp_json("\n{\"resourceMetrics\":[]}"));
+        assert!(!is_otlp_json("[{\"resourceLogs\": 1}]"));
+        assert!(!is_otlp_json("id,name"));
+        assert!(!is_otlp_json(&"é".repeat(200)));
+    }
+
+    #[test]
+    fn test_parse_logs() {
+        let data = parse_otlp_json(LOGS).unwrap();
+        assert_eq!(data.row_count, 2);
+        assert_eq!(cell(&data, "resource.service.name", 1), &CellValue::string_owned("checkout".to_string()));
+        assert_eq!(cell(&data, "resource.host.cpus", 0), &CellValue::Integer(8));
+        assert_eq!(cell(&data, "scope.name", 0), &CellValue::string_owned("app.http".to_string()));
+        assert_eq!(cell(&data, "timeUnixNano", 1), &CellValue::Integer(1_700_000_000_500_000_000));
+        assert_eq!(cell(&data, "severityText", 1), &CellValue::string_owned("ERROR".to_string()));
+        assert_eq!(cell(&data, "body", 0), &CellValue::string_owned("order placed".to_string()));
+        assert_eq!(cell(&data, "body", 1), &CellValue::string_owned(r#"{"code":502}"#.to_string()));
+        assert_eq!(cell(&data, "attributes.order.id", 0), &CellValue::Integer(42));
+        assert_eq!(cell(&data, "attributes.order.id", 1), &CellValue::Null);
+        assert_eq!(cell(&data, "spanId", 0), &CellValue::string_owned("0000000000000001".to_string()));
+    }
+
+    #[test]
+    fn test_parse_metrics() {
+        let export = r#"{"resourceMetrics": [{"scopeMetrics": [{"metrics": [
+            {"name": "requests", "unit": "1", "sum": {
+                "aggregationTemporality": 2, "isMonotonic": true,
+                "dataPoints": [
+                    {"timeUnixNano": "10", "asInt": "5", "attributes": [{"key": "route", "value": {"stringValue": "/a"}}]},
+                    {"timeUnixNano": "10", "asInt": "7", "attributes": [{"key": "route", "value": {"stringValue": "/b"}}]}
+                ]
+            }},
+            {"name": "latency", "unit": "ms", "histogram": {"dataPoints": [
+                {"timeUnixNano": "10", "count": "3", "sum": 12.5, "bucketCounts": ["1", "2"], "explicitBounds": [5.0]}
+            ]}}
+        ]}]}]}"#;
+        let data = parse_otlp_json(export).unwrap();
+        assert_eq!(data.row_count, 3);
+        assert_eq!(cell(&data, "type", 0), &CellValue::string_owned("sum".to_string()));
+        assert_eq!(cell(&data, "value", 1), &CellValue::Integer(7));
+        assert_eq!(cell(&data, "attributes.route", 1), &CellValue::string_owned("/b".to_string()));
+        assert_eq!(cell(&data, "isMonotonic", 0), &CellValue::Boolean(true));
+        assert_eq!(cell(&data, "type", 2), &CellValue::string_owned("histogram".to_string()));
+        assert_eq!(cell(&data, "count", 2), &CellValue::Integer(3));
+        assert_eq!(cell(&data, "value", 2), &CellValue::Null);
+    }
+
+    #[test]
+    fn test_parse_multiple_documents() {
+        let input = format!("{}\n{}\n", LOGS.replace('\n', ""), LOGS.replace('\n', ""));
+        assert_eq!(parse_otlp_json(&input).unwrap().row_count, 4);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse_otlp_json("{\"resourceLogs\": []}\n{\"id\": 1}"),
+            Err(AlsError::LogParseError { line: 2, .. })
+        ));
+        assert!(matches!(parse_otlp_json("{\"resourceLogs\""), Err(AlsError::JsonParseError(_))));
+    }
+}
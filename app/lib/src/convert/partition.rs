@@ -0,0 +1,235 @@
+//! Time-based partitioning of tabular data.
+//!
+//! Data lakes lay archives out by time, one file per day or hour under
+//! paths such as `year=2024/month=03/day=01/`, so readers can skip whole
+//! periods without opening them. `TabularData::partition_by_time` splits
+//! rows into such buckets by the value of a time column, and
+//! [`TimePartition::path`] names the file of each bucket from a template.
+
+use std::collections::BTreeMap;
+
+use super::tabular::{Column, TabularData, Value};
+use crate::als::datetime::{civil_from_days, days_from_civil, format_timestamp, parse_datetime};
+use crate::error::{AlsError, Result};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Integer times above this are taken as milliseconds rather than seconds:
+/// as seconds they would fall after the year 5000.
+pub(crate) const MILLISECONDS_FROM: i64 = 100_000_000_000;
+
+/// Width of the time buckets rows are partitioned into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeGranularity {
+    /// One partition per calendar year
+    Year,
+    /// One partition per calendar month
+    Month,
+    /// One partition per day
+    Day,
+    /// One partition per hour
+    Hour,
+}
+
+impl TimeGranularity {
+    /// All granularities, widest first.
+    pub const ALL: [TimeGranularity; 4] = [
+        TimeGranularity::Year,
+        TimeGranularity::Month,
+        TimeGranularity::Day,
+        TimeGranularity::Hour,
+    ];
+
+    /// Get the name used for this granularity in configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeGranularity::Year => "year",
+            TimeGranularity::Month => "month",
+            TimeGranularity::Day => "day",
+            TimeGranularity::Hour => "hour",
+        }
+    }
+
+    /// Look up a granularity by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|granularity| granularity.name() == name)
+    }
+
+    /// Get the Hive-style path template for partitions of this width, such
+    /// as `year=%Y/month=%m/day=%d` for days.
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            TimeGranularity::Year => "year=%Y",
+            TimeGranularity::Month => "year=%Y/month=%m",
+            TimeGranularity::Day => "year=%Y/month=%m/day=%d",
+            TimeGranularity::Hour => "year=%Y/month=%m/day=%d/hour=%H",
+        }
+    }
+
+    /// Get the start of the bucket holding `timestamp`, in seconds since
+    /// the Unix epoch.
+    pub fn truncate(&self, timestamp: i64) -> i64 {
+        let days = timestamp.div_euclid(SECONDS_PER_DAY);
+        match self {
+            TimeGranularity::Hour => timestamp - timestamp.rem_euclid(3600),
+            TimeGranularity::Day => days * SECONDS_PER_DAY,
+            TimeGranularity::Month | TimeGranularity::Year => {
+                let (year, month, _) = civil_from_days(days);
+                let month = if *self == TimeGranularity::Year { 1 } else { month };
+                days_from_civil(year, month, 1) * SECONDS_PER_DAY
+            }
+        }
+    }
+}
+
+/// The rows of a table falling into one time bucket.
+#[derive(Debug, Clone)]
+pub struct TimePartition<'a> {
+    /// Start of the bucket, in seconds since the Unix epoch (UTC)
+    pub start: i64,
+    /// Rows of the bucket, in their original order
+    pub data: TabularData<'a>,
+}
+
+impl TimePartition<'_> {
+    /// Name the partition by formatting its start with `template`, using
+    /// the `%Y`, `%m`, `%d`, `%H`, `%M` and `%S` specifiers of date range
+    /// operators.
+    ///
+    /// Returns `None` if the template has an unsupported specifier.
+    pub fn path(&self, template: &str) -> Option<String> {
+        format_timestamp(self.start, template)
+    }
+}
+
+impl<'a> TabularData<'a> {
+    /// Split the rows into one partition per time bucket of `column`,
+    /// ordered by time.
+    ///
+    /// Times may be ISO 8601 dates or timestamps, with or without a
+    /// fraction and offset, or integers counting seconds (or, past the
+    /// year 5000, milliseconds) since the Unix epoch. Column types are
+    /// inferred again for each partition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::TimeGranularity;
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let data = parse_csv("ts,level\n2024-03-01T23:59:00Z,info\n2024-03-02T00:01:00Z,warn\n2024-03-01T08:00:00Z,info").unwrap();
+    /// let partitions = data.partition_by_time("ts", TimeGranularity::Day).unwrap();
+    ///
+    /// assert_eq!(partitions.len(), 2);
+    /// assert_eq!(partitions[0].data.row_count, 2);
+    /// assert_eq!(partitions[1].path("dt=%Y-%m-%d").unwrap(), "dt=2024-03-02");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if there is no such column and
+    /// `AlsError::InvalidTimestamp` for the first row whose time is null or
+    /// cannot be read.
+    pub fn partition_by_time(&self, column: &str, granularity: TimeGranularity) -> Result<Vec<TimePartition<'a>>> {
+        let times = self.get_column_by_name(column).ok_or_else(|| AlsError::UnknownColumn {
+            name: column.to_string(),
+        })?;
+
+        let mut buckets: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+        for (row, value) in times.values.iter().enumerate() {
+            let timestamp = timestamp_of(value).ok_or_else(|| AlsError::InvalidTimestamp {
+                column: column.to_string(),
+                row,
+                value: value.to_string_repr().into_owned(),
+            })?;
+            buckets.entry(granularity.truncate(timestamp)).or_default().push(row);
+        }
+
+        Ok(buckets
+            .into_iter()
+            .map(|(start, rows)| {
+                let mut data = TabularData::with_capacity(self.column_count());
+                for column in &self.columns {
+                    let values = rows.iter().map(|&row| column.values[row].clone()).collect();
+                    data.add_column(Column::new(column.name.clone(), values));
+                }
+                TimePartition { start, data }
+            })
+            .collect())
+    }
+}
+
+/// Read a time value as seconds since the Unix epoch.
+fn timestamp_of(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(i) if i.unsigned_abs() >= MILLISECONDS_FROM as u64 => Some(i.div_euclid(1000)),
+        Value::Integer(i) => Some(*i),
+        Value::Float(x) if x.is_finite() => Some(x.floor() as i64),
+        Value::String(s) => parse_datetime(s.trim()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::csv::parse_csv;
+
+    #[test]
+    fn test_truncate() {
+        let ts = parse_datetime("2024-02-29T13:45:10Z").unwrap();
+        let start = |granularity: TimeGranularity| format_timestamp(granularity.truncate(ts), "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(start(TimeGranularity::Year), "2024-01-01 00:00:00");
+        assert_eq!(start(TimeGranularity::Month), "2024-02-01 00:00:00");
+        assert_eq!(start(TimeGranularity::Day), "2024-02-29 00:00:00");
+        assert_eq!(start(TimeGranularity::Hour), "2024-02-29 13:00:00");
+
+        // Times before the epoch round down, not towards zero
+        assert_eq!(TimeGranularity::Day.truncate(-1), -SECONDS_PER_DAY);
+        assert_eq!(TimeGranularity::from_name("month"), Some(TimeGranularity::Month));
+        assert_eq!(TimeGranularity::from_name("week"), None);
+    }
+
+    #[test]
+    fn test_partition_by_time() {
+        let csv = "id,ts\n1,2024-01-31T23:00:00Z\n2,2024-02-01T01:00:00+02:00\n3,2024-02-01T00:30:00Z\n4,2024-01-05";
+        let partitions = parse_csv(csv).unwrap().partition_by_time("ts", TimeGranularity::Month).unwrap();
+
+        let paths: Vec<String> = partitions
+            .iter()
+            .map(|p| p.path(TimeGranularity::Month.default_template()).unwrap())
+            .collect();
+        assert_eq!(paths, vec!["year=2024/month=01", "year=2024/month=02"]);
+        // The +02:00 offset puts row 2 in January
+        let ids: Vec<Vec<Value>> = partitions.iter().map(|p| p.data.columns[0].values.clone()).collect();
+        assert_eq!(ids, vec![
+            vec![Value::Integer(1), Value::Integer(2), Value::Integer(4)],
+            vec![Value::Integer(3)],
+        ]);
+    }
+
+    #[test]
+    fn test_partition_epoch_times() {
+        let mut data = TabularData::new();
+        data.add_column(Column::new("ts", vec![
+            Value::Integer(1_709_294_400),
+            Value::Integer(1_709_294_400_000),
+            Value::Float(1_709_380_800.5),
+        ]));
+        let partitions = data.partition_by_time("ts", TimeGranularity::Day).unwrap();
+        assert_eq!(partitions.iter().map(|p| p.data.row_count).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_partition_errors() {
+        let data = parse_csv("ts\n2024-01-01\nsoon").unwrap();
+        assert!(matches!(
+            data.partition_by_time("ts", TimeGranularity::Day),
+            Err(AlsError::InvalidTimestamp { row: 1, value, .. }) if value == "soon"
+        ));
+        assert!(matches!(
+            data.partition_by_time("time", TimeGranularity::Day),
+            Err(AlsError::UnknownColumn { .. })
+        ));
+    }
+}
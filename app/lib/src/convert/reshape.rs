@@ -0,0 +1,251 @@
+//! Reshaping tables between wide and long layouts.
+//!
+//! How well a table compresses depends on its layout. A wide table with
+//! one column per metric gives each metric a stream of its own, while the
+//! long layout stacks them into a single value column next to a column of
+//! metric names that compresses to a handful of repeats. `TabularData::melt`
+//! turns wide tables long and `TabularData::pivot` turns them back, so
+//! both layouts can be tried without leaving the library.
+
+use std::collections::HashMap;
+
+use super::tabular::{Column, TabularData, Value};
+use crate::error::{AlsError, Result};
+
+impl<'a> TabularData<'a> {
+    /// Unpivot every column other than `ids` into `variable` and `value`
+    /// columns, turning a wide table long.
+    ///
+    /// Each unpivoted column contributes one row per input row, with its
+    /// name in `variable` and its values in `value`, after the rows of the
+    /// columns before it. The `ids` columns come first and are repeated
+    /// for each unpivoted column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let data = parse_csv("host,cpu,mem\na,10,70\nb,20,80").unwrap();
+    /// let long = data.melt(&["host"], "metric", "value").unwrap();
+    ///
+    /// assert_eq!(long.column_names(), vec!["host", "metric", "value"]);
+    /// assert_eq!(long.row_count, 4);
+    /// assert_eq!(long.get_row(2).unwrap()[1].as_str(), Some("mem"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if an id column does not exist,
+    /// and `AlsError::InvalidReshape` if `variable` or `value` names an id
+    /// column or both are the same.
+    pub fn melt(self, ids: &[&str], variable: &str, value: &str) -> Result<Self> {
+        if let Some(&name) = ids.iter().find(|&&name| self.get_column_by_name(name).is_none()) {
+            return Err(AlsError::UnknownColumn { name: name.to_string() });
+        }
+        if variable == value {
+            return Err(reshape_error(format!("variable and value columns are both named '{}'", value)));
+        }
+        if let Some(name) = [variable, value].into_iter().find(|name| ids.contains(name)) {
+            return Err(reshape_error(format!("'{}' is already an id column", name)));
+        }
+
+        let (id_columns, melted): (Vec<Column<'a>>, Vec<Column<'a>>) =
+            self.columns.into_iter().partition(|column| ids.contains(&column.name.as_ref()));
+        let rows = self.row_count * melted.len();
+
+        let mut data = Self::with_capacity(ids.len() + 2);
+        for &name in ids {
+            let column = id_columns.iter().find(|column| column.name == name).expect("id columns exist");
+            let mut values = Vec::with_capacity(rows);
+            for _ in 0..melted.len() {
+                values.extend_from_slice(&column.values);
+            }
+            data.add_column(Column::new(column.name.clone(), values));
+        }
+
+        let mut names = Vec::with_capacity(rows);
+        let mut values = Vec::with_capacity(rows);
+        for column in melted {
+            names.extend(std::iter::repeat_n(Value::String(column.name), self.row_count));
+            values.extend(column.values);
+        }
+        data.add_column(Column::new(variable.to_string(), names));
+        data.add_column(Column::new(value.to_string(), values));
+        Ok(data)
+    }
+
+    /// Spread the `values` column over one column per distinct value of
+    /// `columns`, turning a long table wide.
+    ///
+    /// Every other column identifies a row: input rows agreeing on all of
+    /// them become one output row, in order of first appearance, and the
+    /// new columns follow them in the order their names first appear.
+    /// Cells no input row fills are null. This undoes [`melt`](Self::melt)
+    /// up to the order of rows and types of values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let data = parse_csv("host,metric,value\na,cpu,10\nb,cpu,20\na,mem,70").unwrap();
+    /// let wide = data.pivot("metric", "value").unwrap();
+    ///
+    /// assert_eq!(wide.column_names(), vec!["host", "cpu", "mem"]);
+    /// assert_eq!(wide.row_count, 2);
+    /// assert!(wide.get_row(1).unwrap()[2].is_null());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if `columns` or `values` does not
+    /// exist, and `AlsError::InvalidReshape` if they are the same column, a
+    /// value of `columns` is null, empty or the name of another column, or
+    /// two rows give a value for the same cell.
+    pub fn pivot(self, columns: &str, values: &str) -> Result<Self> {
+        let position = |name: &str| {
+            self.columns
+                .iter()
+                .position(|column| column.name == name)
+                .ok_or_else(|| AlsError::UnknownColumn { name: name.to_string() })
+        };
+        let names_at = position(columns)?;
+        let values_at = position(values)?;
+        if names_at == values_at {
+            return Err(reshape_error(format!("'{}' cannot both name and fill the new columns", values)));
+        }
+        let index: Vec<usize> = (0..self.columns.len()).filter(|&i| i != names_at && i != values_at).collect();
+
+        // Name the new columns in order of first appearance
+        let mut new_columns: Vec<String> = Vec::new();
+        let mut column_of: HashMap<String, usize> = HashMap::new();
+        let mut cells: Vec<usize> = Vec::with_capacity(self.row_count);
+        for (row, value) in self.columns[names_at].values.iter().enumerate() {
+            let name = match value {
+                Value::Null => return Err(reshape_error(format!("'{}' is null at row {}", columns, row))),
+                Value::String(s) if s.is_empty() => {
+                    return Err(reshape_error(format!("'{}' is empty at row {}", columns, row)));
+                }
+                value => value.to_string_repr().into_owned(),
+            };
+            let next = new_columns.len();
+            let column = *column_of.entry(name).or_insert_with_key(|name| {
+                new_columns.push(name.clone());
+                next
+            });
+            cells.push(column);
+        }
+        if let Some(name) = new_columns.iter().find(|&name| index.iter().any(|&i| self.columns[i].name == *name)) {
+            return Err(reshape_error(format!("'{}' is already a column", name)));
+        }
+
+        // Group rows by their values in the index columns
+        let mut groups: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut first_rows: Vec<usize> = Vec::new();
+        let mut grid: Vec<Option<Value<'a>>> = Vec::new();
+        let mut source = self.columns;
+        let filled = std::mem::take(&mut source[values_at].values);
+        for (row, (value, column)) in filled.into_iter().zip(cells).enumerate() {
+            let key = index.iter().map(|&i| source[i].values[row].to_string_repr().into_owned()).collect();
+            let group = *groups.entry(key).or_insert_with(|| {
+                first_rows.push(row);
+                grid.resize(grid.len() + new_columns.len(), None);
+                first_rows.len() - 1
+            });
+            let cell = &mut grid[group * new_columns.len() + column];
+            if cell.is_some() {
+                return Err(reshape_error(format!(
+                    "row {} repeats the value of '{}' for an earlier row",
+                    row,
+                    new_columns[column]
+                )));
+            }
+            *cell = Some(value);
+        }
+
+        let width = new_columns.len();
+        let mut data = Self::with_capacity(index.len() + width);
+        for &i in &index {
+            let column = &source[i];
+            let values = first_rows.iter().map(|&row| column.values[row].clone()).collect();
+            data.add_column(Column::new(column.name.clone(), values));
+        }
+        for (column, name) in new_columns.into_iter().enumerate() {
+            let values = grid
+                .iter_mut()
+                .skip(column)
+                .step_by(width)
+                .map(|cell| cell.take().unwrap_or(Value::Null))
+                .collect();
+            data.add_column(Column::new(name, values));
+        }
+        Ok(data)
+    }
+}
+
+/// Build the error for a reshape that cannot be done.
+fn reshape_error(message: String) -> AlsError {
+    AlsError::InvalidReshape { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::csv::parse_csv;
+
+    #[test]
+    fn test_melt() {
+        let data = parse_csv("ts,host,cpu,mem\n1,a,10,70\n2,b,,80").unwrap();
+        let long = data.melt(&["host", "ts"], "metric", "value").unwrap();
+
+        assert_eq!(long.column_names(), vec!["host", "ts", "metric", "value"]);
+        let rows: Vec<Vec<String>> = long
+            .rows()
+            .map(|row| row.iter().map(|v| v.to_string_repr().into_owned()).collect())
+            .collect();
+        assert_eq!(rows, vec![
+            vec!["a", "1", "cpu", "10"],
+            vec!["b", "2", "cpu", "\\0"],
+            vec!["a", "1", "mem", "70"],
+            vec!["b", "2", "mem", "80"],
+        ]);
+
+        let data = parse_csv("ts,cpu\n1,10").unwrap();
+        assert!(matches!(data.clone().melt(&["host"], "k", "v"), Err(AlsError::UnknownColumn { name }) if name == "host"));
+        assert!(matches!(data.clone().melt(&["ts"], "ts", "v"), Err(AlsError::InvalidReshape { .. })));
+        assert!(matches!(data.melt(&["ts"], "v", "v"), Err(AlsError::InvalidReshape { .. })));
+    }
+
+    #[test]
+    fn test_pivot_undoes_melt() {
+        let data = parse_csv("ts,host,cpu,mem\n1,a,10,70\n1,b,20,\n2,a,11,72").unwrap();
+        let wide = data.clone().melt(&["ts", "host"], "metric", "value").unwrap().pivot("metric", "value").unwrap();
+
+        assert_eq!(wide.column_names(), data.column_names());
+        for (pivoted, original) in wide.columns.iter().zip(&data.columns) {
+            assert_eq!(pivoted.values, original.values, "{}", original.name);
+        }
+    }
+
+    #[test]
+    fn test_pivot_missing_cells() {
+        let data = parse_csv("day,metric,value\n1,cpu,10\n2,mem,70\n1,mem,71").unwrap();
+        let wide = data.pivot("metric", "value").unwrap();
+
+        assert_eq!(wide.column_names(), vec!["day", "cpu", "mem"]);
+        assert_eq!(wide.columns[1].values, vec![Value::Integer(10), Value::Null]);
+        assert_eq!(wide.columns[2].values, vec![Value::Integer(71), Value::Integer(70)]);
+    }
+
+    #[test]
+    fn test_pivot_errors() {
+        let pivot = |csv: &str| parse_csv(csv).unwrap().pivot("metric", "value");
+        assert!(matches!(pivot("day,metric,value\n1,cpu,10\n1,cpu,11"), Err(AlsError::InvalidReshape { .. })));
+        assert!(matches!(pivot("day,metric,value\n1,,10"), Err(AlsError::InvalidReshape { .. })));
+        assert!(matches!(pivot("day,metric,value\n1,day,10"), Err(AlsError::InvalidReshape { .. })));
+        assert!(matches!(pivot("day,metric,amount\n1,cpu,10"), Err(AlsError::UnknownColumn { name }) if name == "value"));
+        let data = parse_csv("day,metric\n1,cpu").unwrap();
+        assert!(matches!(data.pivot("metric", "metric"), Err(AlsError::InvalidReshape { .. })));
+    }
+}
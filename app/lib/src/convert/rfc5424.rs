@@ -0,0 +1,357 @@
+//! RFC 5424 syslog message parsing and formatting.
+//!
+//! RFC 5424 messages have the form:
+//!
+//! `<PRI>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA [MSG]`
+//!
+//! where any header field may be the nil value `-`, and structured data is
+//! either `-` or a sequence of `[SD-ID PARAM="value" ...]` elements. Inside
+//! parameter values, `"`, `\` and `]` are escaped with a backslash.
+//!
+//! This module only handles single lines; `convert::syslog` turns them into
+//! columns.
+
+use crate::error::{AlsError, Result};
+
+/// The nil value used for absent header fields and structured data.
+pub const NIL_VALUE: &str = "-";
+
+/// Parsed RFC 5424 message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rfc5424Entry<'a> {
+    /// Priority value (facility * 8 + severity)
+    pub priority: u8,
+    /// Protocol version (1 for RFC 5424)
+    pub version: u8,
+    /// RFC 3339 timestamp, or None for the nil value
+    pub timestamp: Option<&'a str>,
+    /// Hostname, or None for the nil value
+    pub hostname: Option<&'a str>,
+    /// Application name, or None for the nil value
+    pub app_name: Option<&'a str>,
+    /// Process ID, or None for the nil value
+    pub proc_id: Option<&'a str>,
+    /// Message type ID, or None for the nil value
+    pub msg_id: Option<&'a str>,
+    /// Structured data elements (empty for the nil value)
+    pub structured_data: Vec<SdElement>,
+    /// Free-form message, or None if the message was omitted
+    pub message: Option<&'a str>,
+}
+
+/// A structured data element: `[id name="value" ...]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdElement {
+    /// SD-ID, e.g. `exampleSDID@32473`
+    pub id: String,
+    /// Parameters in order, with unescaped values
+    pub params: Vec<(String, String)>,
+}
+
+impl<'a> Rfc5424Entry<'a> {
+    /// Facility code (priority / 8).
+    pub fn facility(&self) -> u8 {
+        self.priority / 8
+    }
+
+    /// Severity code (priority % 8).
+    pub fn severity(&self) -> u8 {
+        self.priority % 8
+    }
+}
+
+/// Check whether a line starts with an RFC 5424 `<PRI>VERSION ` header.
+///
+/// Legacy BSD lines with a `<PRI>` prefix are followed by a month name
+/// rather than a version number, so they are not matched.
+pub fn is_rfc5424(line: &str) -> bool {
+    parse_header_prefix(line).is_some()
+}
+
+/// Parse `<PRI>VERSION ` and return priority, version and the remainder.
+fn parse_header_prefix(line: &str) -> Option<(u8, u8, &str)> {
+    let rest = line.strip_prefix('<')?;
+    let close = rest.find('>')?;
+    let pri = &rest[..close];
+    if pri.is_empty() || pri.len() > 3 || !pri.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let priority: u8 = pri.parse().ok().filter(|p| *p <= 191)?;
+
+    let rest = &rest[close + 1..];
+    let version_end = rest.find(' ')?;
+    let version = &rest[..version_end];
+    if version.is_empty() || version.len() > 2 || version.starts_with('0') {
+        return None;
+    }
+    let version: u8 = version.parse().ok()?;
+
+    Some((priority, version, &rest[version_end + 1..]))
+}
+
+/// Parse a single RFC 5424 line.
+pub fn parse_rfc5424_line(line: &str) -> Result<Rfc5424Entry<'_>> {
+    let (priority, version, mut rest) = parse_header_prefix(line)
+        .ok_or_else(|| parse_error("Invalid RFC 5424 header: expected <PRI>VERSION"))?;
+
+    let mut fields = [None; 5];
+    for field in fields.iter_mut() {
+        let end = rest
+            .find(' ')
+            .ok_or_else(|| parse_error("Invalid RFC 5424 header: not enough fields"))?;
+        let value = &rest[..end];
+        if value.is_empty() {
+            return Err(parse_error("Invalid RFC 5424 header: empty field"));
+        }
+        *field = (value != NIL_VALUE).then_some(value);
+        rest = &rest[end + 1..];
+    }
+    let [timestamp, hostname, app_name, proc_id, msg_id] = fields;
+
+    let (structured_data, consumed) = parse_structured_data(rest)?;
+    let message = match &rest[consumed..] {
+        "" => None,
+        tail => Some(
+            tail.strip_prefix(' ')
+                .ok_or_else(|| parse_error("Expected space after structured data"))?,
+        ),
+    };
+
+    Ok(Rfc5424Entry {
+        priority,
+        version,
+        timestamp,
+        hostname,
+        app_name,
+        proc_id,
+        msg_id,
+        structured_data,
+        message,
+    })
+}
+
+/// Parse structured data at the start of `input`.
+///
+/// Returns the elements and the number of bytes consumed.
+pub fn parse_structured_data(input: &str) -> Result<(Vec<SdElement>, usize)> {
+    if input == NIL_VALUE || input.starts_with("- ") {
+        return Ok((Vec::new(), 1));
+    }
+    if !input.starts_with('[') {
+        return Err(parse_error("Expected structured data or '-'"));
+    }
+
+    let mut elements = Vec::new();
+    let mut pos = 0;
+    let bytes = input.as_bytes();
+
+    while bytes.get(pos) == Some(&b'[') {
+        pos += 1;
+        let id_end = find_name_end(input, pos);
+        if id_end == pos {
+            return Err(parse_error("Empty SD-ID"));
+        }
+        let mut element = SdElement {
+            id: input[pos..id_end].to_string(),
+            params: Vec::new(),
+        };
+        pos = id_end;
+
+        loop {
+            match bytes.get(pos) {
+                Some(b']') => {
+                    pos += 1;
+                    break;
+                }
+                Some(b' ') => {
+                    pos += 1;
+                    let name_end = find_name_end(input, pos);
+                    if name_end == pos || input.get(name_end..name_end + 2) != Some("=\"") {
+                        return Err(parse_error("Invalid SD-PARAM: expected name=\"value\""));
+                    }
+                    let name = input[pos..name_end].to_string();
+                    let (value, value_end) = parse_param_value(input, name_end + 2)?;
+                    element.params.push((name, value));
+                    pos = value_end;
+                }
+                _ => return Err(parse_error("Unterminated structured data element")),
+            }
+        }
+
+        elements.push(element);
+    }
+
+    Ok((elements, pos))
+}
+
+/// Find the end of an SD-NAME starting at `start`.
+fn find_name_end(input: &str, start: usize) -> usize {
+    input[start..]
+        .find([' ', '=', ']', '"'])
+        .map_or(input.len(), |i| start + i)
+}
+
+/// Parse a quoted parameter value whose content starts at `start`.
+///
+/// Returns the unescaped value and the position after the closing quote.
+fn parse_param_value(input: &str, start: usize) -> Result<(String, usize)> {
+    let mut value = String::new();
+    let mut chars = input[start..].char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, start + i + 1)),
+            '\\' => match chars.clone().next() {
+                Some((_, next @ ('"' | '\\' | ']'))) => {
+                    value.push(next);
+                    chars.next();
+                }
+                // A backslash before any other character is literal
+                _ => value.push('\\'),
+            },
+            _ => value.push(c),
+        }
+    }
+
+    Err(parse_error("Unterminated SD-PARAM value"))
+}
+
+/// Format structured data elements, or `-` if there are none.
+pub fn format_structured_data(elements: &[SdElement]) -> String {
+    if elements.is_empty() {
+        return NIL_VALUE.to_string();
+    }
+
+    let mut output = String::new();
+    for element in elements {
+        output.push('[');
+        output.push_str(&element.id);
+        for (name, value) in &element.params {
+            output.push(' ');
+            output.push_str(name);
+            output.push_str("=\"");
+            for c in value.chars() {
+                if matches!(c, '"' | '\\' | ']') {
+                    output.push('\\');
+                }
+                output.push(c);
+            }
+            output.push('"');
+        }
+        output.push(']');
+    }
+    output
+}
+
+/// Format an RFC 5424 line.
+pub fn format_rfc5424_line(entry: &Rfc5424Entry<'_>) -> String {
+    let field = |value: Option<&str>| value.filter(|v| !v.is_empty()).unwrap_or(NIL_VALUE).to_string();
+
+    let mut line = format!(
+        "<{}>{} {} {} {} {} {} {}",
+        entry.priority,
+        entry.version,
+        field(entry.timestamp),
+        field(entry.hostname),
+        field(entry.app_name),
+        field(entry.proc_id),
+        field(entry.msg_id),
+        format_structured_data(&entry.structured_data),
+    );
+    if let Some(message) = entry.message {
+        line.push(' ');
+        line.push_str(message);
+    }
+    line
+}
+
+fn parse_error(message: &str) -> AlsError {
+    AlsError::CsvParseError {
+        line: 0,
+        column: 0,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"] An application event log entry"#;
+
+    #[test]
+    fn test_is_rfc5424() {
+        assert!(is_rfc5424(EXAMPLE));
+        assert!(is_rfc5424("<34>1 - - - - - -"));
+        assert!(!is_rfc5424("<34>Oct 11 22:14:15 mymachine su: 'su root' failed"));
+        assert!(!is_rfc5424("Jun 14 15:16:01 combo sshd[1]: hello"));
+        assert!(!is_rfc5424("<192>1 - - - - - -"));
+        assert!(!is_rfc5424("<34>01 - - - - - -"));
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let entry = parse_rfc5424_line(EXAMPLE).unwrap();
+        assert_eq!(entry.priority, 165);
+        assert_eq!(entry.facility(), 20);
+        assert_eq!(entry.severity(), 5);
+        assert_eq!(entry.version, 1);
+        assert_eq!(entry.timestamp, Some("2003-10-11T22:14:15.003Z"));
+        assert_eq!(entry.hostname, Some("mymachine.example.com"));
+        assert_eq!(entry.app_name, Some("evntslog"));
+        assert_eq!(entry.proc_id, None);
+        assert_eq!(entry.msg_id, Some("ID47"));
+        assert_eq!(entry.structured_data.len(), 1);
+        assert_eq!(entry.structured_data[0].id, "exampleSDID@32473");
+        assert_eq!(
+            entry.structured_data[0].params[1],
+            ("eventSource".to_string(), "Application".to_string())
+        );
+        assert_eq!(entry.message, Some("An application event log entry"));
+    }
+
+    #[test]
+    fn test_parse_nil_fields() {
+        let entry = parse_rfc5424_line("<34>1 - - - - - -").unwrap();
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.hostname, None);
+        assert!(entry.structured_data.is_empty());
+        assert_eq!(entry.message, None);
+    }
+
+    #[test]
+    fn test_parse_multiple_elements_and_escapes() {
+        let line = r#"<13>1 - host app 42 - [a@1 x="q\"uote" y="b\\s\]"][b@1] msg"#;
+        let entry = parse_rfc5424_line(line).unwrap();
+        assert_eq!(entry.proc_id, Some("42"));
+        assert_eq!(entry.structured_data.len(), 2);
+        assert_eq!(entry.structured_data[0].params[0].1, "q\"uote");
+        assert_eq!(entry.structured_data[0].params[1].1, "b\\s]");
+        assert_eq!(entry.structured_data[1].id, "b@1");
+        assert!(entry.structured_data[1].params.is_empty());
+        assert_eq!(entry.message, Some("msg"));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_rfc5424_line("<13>1 - - -").is_err());
+        assert!(parse_rfc5424_line("<13>1 - - - - - [a@1 x=1]").is_err());
+        assert!(parse_rfc5424_line("<13>1 - - - - - [a@1 x=\"1\"").is_err());
+        assert!(parse_rfc5424_line("<13>1 - - - - - x").is_err());
+        assert!(parse_rfc5424_line("<13>1 - - - - - [a@1]msg").is_err());
+    }
+
+    #[test]
+    fn test_format_roundtrip() {
+        let lines = [
+            EXAMPLE,
+            "<34>1 - - - - - -",
+            r#"<13>1 - host app 42 - [a@1 x="q\"uote" y="b\\s\]"][b@1] msg"#,
+            "<0>1 2024-01-01T00:00:00Z h a - - - ",
+        ];
+        for line in lines {
+            let entry = parse_rfc5424_line(line).unwrap();
+            assert_eq!(format_rfc5424_line(&entry), line);
+        }
+    }
+}
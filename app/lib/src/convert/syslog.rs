@@ -7,6 +7,11 @@
 //!
 //! Standard syslog format: `<Month> <Day> <Time> <Hostname> <Service>[<PID>]: <Message>`
 //!
+//! RFC 5424 input (`<PRI>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID SD MSG`)
+//! is detected from the first line and parsed into the header fields, a
+//! `structured_data` template, and one `sd.<sd-id>.<param>` column per
+//! structured data parameter. `to_syslog` emits RFC 5424 for such data.
+//!
 //! ## Compression Optimization
 //!
 //! The parser extracts structured fields to maximize compression:
@@ -14,11 +19,144 @@
 //! - Services and hostnames are extracted for dictionary encoding
 //! - PIDs are extracted as integers for range compression
 //! - Message templates are separated from variable parameters
+//! - Optionally, `key=value` pairs in messages become their own columns
+//!   (see `SyslogConfig::with_key_values`)
+//!
+//! ## Multi-line Records
+//!
+//! Stack traces and indented follow-up lines can be joined into the message
+//! of the preceding record (see `SyslogConfig::with_multiline`), so each row
+//! is one logical record. Joined lines are separated by `\n` in the message.
 
+use crate::convert::rfc5424::{
+    format_rfc5424_line, format_structured_data, is_rfc5424, parse_rfc5424_line,
+    parse_structured_data, Rfc5424Entry,
+};
 use crate::convert::{Column, TabularData, Value};
 use crate::error::{AlsError, Result};
 use std::borrow::Cow;
 
+/// Prefix of the column names holding extracted `key=value` pairs.
+pub const KEY_VALUE_PREFIX: &str = "kv.";
+
+/// Prefix of the column names holding RFC 5424 structured data parameters.
+pub const STRUCTURED_DATA_PREFIX: &str = "sd.";
+
+/// Configuration for syslog parsing.
+#[derive(Debug, Clone)]
+pub struct SyslogConfig {
+    /// Extract `key=value` pairs from messages into extra columns.
+    ///
+    /// Each extracted value is removed from the message, leaving `key=`
+    /// behind so `to_syslog` can put it back.
+    ///
+    /// Default: false
+    pub extract_key_values: bool,
+
+    /// Maximum number of extra columns created for extracted keys.
+    ///
+    /// Keys are assigned columns in order of first appearance; pairs whose
+    /// key does not get a column stay in the message. The same limit applies
+    /// separately to RFC 5424 structured data parameters.
+    ///
+    /// Default: 16
+    pub max_key_value_columns: usize,
+
+    /// Join continuation lines into the message of the preceding record.
+    ///
+    /// Which lines count as continuations is controlled by `join_indented`,
+    /// `join_unparseable` and `continuation_prefixes`.
+    ///
+    /// Default: false
+    pub join_continuations: bool,
+
+    /// Treat lines starting with whitespace as continuations.
+    ///
+    /// Default: true
+    pub join_indented: bool,
+
+    /// Treat lines without a valid syslog header as continuations.
+    ///
+    /// Default: false
+    pub join_unparseable: bool,
+
+    /// Treat lines starting with any of these prefixes as continuations.
+    ///
+    /// Default: `DEFAULT_CONTINUATION_PREFIXES`
+    pub continuation_prefixes: Vec<String>,
+}
+
+/// Line prefixes that usually continue a multi-line record (stack traces).
+pub const DEFAULT_CONTINUATION_PREFIXES: &[&str] = &["at ", "Caused by:", "...", "Traceback "];
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            extract_key_values: false,
+            max_key_value_columns: 16,
+            join_continuations: false,
+            join_indented: true,
+            join_unparseable: false,
+            continuation_prefixes: DEFAULT_CONTINUATION_PREFIXES
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl SyslogConfig {
+    /// Create a new configuration with default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable `key=value` extraction with at most `max_columns` extra columns.
+    pub fn with_key_values(mut self, max_columns: usize) -> Self {
+        self.extract_key_values = true;
+        self.max_key_value_columns = max_columns;
+        self
+    }
+
+    /// Enable or disable joining of continuation lines.
+    pub fn with_multiline(mut self, enabled: bool) -> Self {
+        self.join_continuations = enabled;
+        self
+    }
+
+    /// Set whether lines without a valid syslog header are continuations.
+    pub fn with_join_unparseable(mut self, enabled: bool) -> Self {
+        self.join_unparseable = enabled;
+        self
+    }
+
+    /// Set the line prefixes that mark continuations.
+    pub fn with_continuation_prefixes<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.continuation_prefixes = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Check whether a line continues the preceding record.
+    fn is_continuation(&self, line: &str) -> bool {
+        if !self.join_continuations {
+            return false;
+        }
+        if self.join_indented && line.starts_with(char::is_whitespace) {
+            return true;
+        }
+        if self.continuation_prefixes.iter().any(|p| line.starts_with(p.as_str())) {
+            return true;
+        }
+        self.join_unparseable
+            && parse_syslog_line(line.trim()).is_err()
+            && parse_rfc5424_line(line.trim()).is_err()
+    }
+}
+
 /// Parsed syslog entry with all extracted fields.
 #[derive(Debug, Clone)]
 pub struct SyslogEntry<'a> {
@@ -143,12 +281,35 @@ pub struct MessageParams<'a> {
 /// assert!(data.column_count() > 0);
 /// ```
 pub fn parse_syslog(input: &str) -> Result<TabularData<'static>> {
+    parse_syslog_with_config(input, &SyslogConfig::default())
+}
+
+/// Parse a syslog-format log file into TabularData with the given configuration.
+///
+/// With `key=value` extraction enabled, a `kv.<key>` column is appended for
+/// each extracted key and the `message` column holds the message with the
+/// extracted values removed.
+///
+/// # Example
+///
+/// ```ignore
+/// use als_compression::convert::syslog::{parse_syslog_with_config, SyslogConfig};
+///
+/// let log = "Jun 14 15:16:01 combo app[1]: request user=alice status=200";
+/// let data = parse_syslog_with_config(log, &SyslogConfig::new().with_key_values(8)).unwrap();
+/// assert_eq!(data.column_count(), 12);
+/// ```
+pub fn parse_syslog_with_config(input: &str, config: &SyslogConfig) -> Result<TabularData<'static>> {
     if input.trim().is_empty() {
         return Ok(TabularData::new());
     }
 
     let lines: Vec<&str> = input.lines().collect();
-    let line_count = lines.len();
+    let records = group_records(&lines, config);
+    if records.first().is_some_and(|(line, _)| is_rfc5424(line)) {
+        return parse_rfc5424_records(&records, config);
+    }
+    let line_count = records.len();
 
     // Pre-allocate vectors for each column
     let mut months: Vec<Value<'static>> = Vec::with_capacity(line_count);
@@ -161,13 +322,9 @@ pub fn parse_syslog(input: &str) -> Result<TabularData<'static>> {
     let mut rhosts: Vec<Value<'static>> = Vec::with_capacity(line_count);
     let mut users: Vec<Value<'static>> = Vec::with_capacity(line_count);
     let mut raw_msgs: Vec<Value<'static>> = Vec::with_capacity(line_count);
+    let mut key_values = KeyValueColumns::new(config);
 
-    for (_line_num, line) in lines.iter().enumerate() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
+    for (trimmed, continuation) in records {
         match parse_syslog_line(trimmed) {
             Ok(entry) => {
                 months.push(Value::String(Cow::Owned(entry.month.to_string())));
@@ -183,7 +340,9 @@ pub fn parse_syslog(input: &str) -> Result<TabularData<'static>> {
                 users.push(entry.params.user
                     .map(|u| Value::String(Cow::Owned(u.to_string())))
                     .unwrap_or(Value::Null));
-                raw_msgs.push(Value::String(Cow::Owned(entry.message.to_string())));
+                let message = join_message(entry.message, &continuation);
+                let message = key_values.extract(&message, raw_msgs.len());
+                raw_msgs.push(Value::String(Cow::Owned(message)));
             }
             Err(_) => {
                 // For unparseable lines, store as raw with nulls for structured fields
@@ -196,7 +355,7 @@ pub fn parse_syslog(input: &str) -> Result<TabularData<'static>> {
                 msg_types.push(Value::String(Cow::Owned("parse_error".to_string())));
                 rhosts.push(Value::Null);
                 users.push(Value::Null);
-                raw_msgs.push(Value::String(Cow::Owned(trimmed.to_string())));
+                raw_msgs.push(Value::String(Cow::Owned(join_message(trimmed, &continuation))));
             }
         }
     }
@@ -217,10 +376,396 @@ pub fn parse_syslog(input: &str) -> Result<TabularData<'static>> {
     data.add_column(Column::new(Cow::Borrowed("rhost"), rhosts));
     data.add_column(Column::new(Cow::Borrowed("user"), users));
     data.add_column(Column::new(Cow::Borrowed("message"), raw_msgs));
+    for column in key_values.into_columns(data.row_count) {
+        data.add_column(column);
+    }
 
     Ok(data)
 }
 
+/// Parse RFC 5424 records into columns.
+fn parse_rfc5424_records(
+    records: &[(&str, Vec<&str>)],
+    config: &SyslogConfig,
+) -> Result<TabularData<'static>> {
+    let count = records.len();
+    let mut priorities: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut versions: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut timestamps: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut hostnames: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut app_names: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut proc_ids: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut msg_ids: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut structured: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut messages: Vec<Value<'static>> = Vec::with_capacity(count);
+    let mut sd_params = KeyValueColumns::structured_data(config);
+    let mut key_values = KeyValueColumns::new(config);
+
+    let optional = |value: Option<&str>| {
+        value.map_or(Value::Null, |v| Value::String(Cow::Owned(v.to_string())))
+    };
+
+    for (row, (line, continuation)) in records.iter().enumerate() {
+        match parse_rfc5424_line(line) {
+            Ok(mut entry) => {
+                priorities.push(Value::Integer(entry.priority as i64));
+                versions.push(Value::Integer(entry.version as i64));
+                timestamps.push(optional(entry.timestamp));
+                hostnames.push(optional(entry.hostname));
+                app_names.push(optional(entry.app_name));
+                proc_ids.push(entry.proc_id.map_or(Value::Null, |p| key_value_to_value(p.to_string())));
+                msg_ids.push(optional(entry.msg_id));
+
+                // Move parameter values into columns, keeping the rest as a template
+                if entry.structured_data.is_empty() {
+                    structured.push(Value::Null);
+                } else {
+                    let mut seen: Vec<String> = Vec::new();
+                    for element in &mut entry.structured_data {
+                        for (name, value) in &mut element.params {
+                            let key = format!("{}.{}", element.id, name);
+                            if seen.contains(&key) {
+                                continue;
+                            }
+                            if sd_params.insert(&key, row, value.clone()) {
+                                value.clear();
+                            }
+                            seen.push(key);
+                        }
+                    }
+                    let template = format_structured_data(&entry.structured_data);
+                    structured.push(Value::String(Cow::Owned(template)));
+                }
+
+                messages.push(match entry.message {
+                    Some(message) => {
+                        let message = join_message(message, continuation);
+                        Value::String(Cow::Owned(key_values.extract(&message, row)))
+                    }
+                    None => Value::Null,
+                });
+            }
+            Err(_) => {
+                // Unparseable lines keep their raw text with null header fields
+                priorities.push(Value::Null);
+                versions.push(Value::Null);
+                timestamps.push(Value::Null);
+                hostnames.push(Value::Null);
+                app_names.push(Value::Null);
+                proc_ids.push(Value::Null);
+                msg_ids.push(Value::Null);
+                structured.push(Value::Null);
+                messages.push(Value::String(Cow::Owned(join_message(line, continuation))));
+            }
+        }
+    }
+
+    let mut data = TabularData::with_capacity(9);
+    data.add_column(Column::new(Cow::Borrowed("priority"), priorities));
+    data.add_column(Column::new(Cow::Borrowed("version"), versions));
+    data.add_column(Column::new(Cow::Borrowed("timestamp"), timestamps));
+    data.add_column(Column::new(Cow::Borrowed("hostname"), hostnames));
+    data.add_column(Column::new(Cow::Borrowed("app_name"), app_names));
+    data.add_column(Column::new(Cow::Borrowed("proc_id"), proc_ids));
+    data.add_column(Column::new(Cow::Borrowed("msg_id"), msg_ids));
+    data.add_column(Column::new(Cow::Borrowed("structured_data"), structured));
+    data.add_column(Column::new(Cow::Borrowed("message"), messages));
+    for column in sd_params.into_columns(count) {
+        data.add_column(column);
+    }
+    for column in key_values.into_columns(count) {
+        data.add_column(column);
+    }
+
+    Ok(data)
+}
+
+/// Rebuild RFC 5424 lines from columns produced by `parse_rfc5424_records`.
+fn to_rfc5424(data: &TabularData) -> Result<String> {
+    let index = |name: &str| data.columns.iter().position(|c| c.name == name);
+    let priority_idx = index("priority");
+    let version_idx = index("version");
+    let timestamp_idx = index("timestamp");
+    let hostname_idx = index("hostname");
+    let app_name_idx = index("app_name");
+    let proc_id_idx = index("proc_id");
+    let msg_id_idx = index("msg_id");
+    let structured_idx = index("structured_data");
+    let message_idx = index("message");
+    let sd_columns: Vec<(&str, &Column)> = data
+        .columns
+        .iter()
+        .filter_map(|c| c.name.strip_prefix(STRUCTURED_DATA_PREFIX).map(|key| (key, c)))
+        .collect();
+    let key_value_columns: Vec<(&str, &Column)> = data
+        .columns
+        .iter()
+        .filter_map(|c| c.name.strip_prefix(KEY_VALUE_PREFIX).map(|key| (key, c)))
+        .collect();
+
+    let mut output = String::new();
+    for row in 0..data.row_count {
+        let value = |idx: Option<usize>| idx.map(|i| &data.columns[i].values[row]).filter(|v| !v.is_null());
+        let text = |idx: Option<usize>| value(idx).map(|v| v.to_string_repr().into_owned());
+
+        let message = value(message_idx).map(|v| {
+            let pairs: Vec<(&str, String)> = key_value_columns
+                .iter()
+                .filter(|(_, column)| !column.values[row].is_null())
+                .map(|(key, column)| (*key, column.values[row].to_string_repr().into_owned()))
+                .collect();
+            restore_key_values(&v.to_string_repr(), &pairs)
+        });
+
+        let Some(priority) = value(priority_idx).and_then(Value::as_integer) else {
+            // Unparseable input line, kept verbatim in the message
+            output.push_str(message.as_deref().unwrap_or(""));
+            output.push('\n');
+            continue;
+        };
+
+        let mut structured_data = match text(structured_idx) {
+            Some(template) => parse_structured_data(&template)?.0,
+            None => Vec::new(),
+        };
+        let mut seen: Vec<String> = Vec::new();
+        for element in &mut structured_data {
+            for (name, param) in &mut element.params {
+                let key = format!("{}.{}", element.id, name);
+                if seen.contains(&key) {
+                    continue;
+                }
+                if let Some((_, column)) = sd_columns.iter().find(|(k, _)| *k == key) {
+                    if !column.values[row].is_null() {
+                        *param = column.values[row].to_string_repr().into_owned();
+                    }
+                }
+                seen.push(key);
+            }
+        }
+
+        let timestamp = text(timestamp_idx);
+        let hostname = text(hostname_idx);
+        let app_name = text(app_name_idx);
+        let proc_id = text(proc_id_idx);
+        let msg_id = text(msg_id_idx);
+        let entry = Rfc5424Entry {
+            priority: priority.clamp(0, 191) as u8,
+            version: value(version_idx).and_then(Value::as_integer).map_or(1, |v| v.clamp(1, 99) as u8),
+            timestamp: timestamp.as_deref(),
+            hostname: hostname.as_deref(),
+            app_name: app_name.as_deref(),
+            proc_id: proc_id.as_deref(),
+            msg_id: msg_id.as_deref(),
+            structured_data,
+            message: message.as_deref(),
+        };
+        output.push_str(&format_rfc5424_line(&entry));
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+/// Group physical lines into records of a header line and its continuations.
+///
+/// Header lines are trimmed; continuation lines keep their leading
+/// whitespace. A continuation before the first record starts a record.
+fn group_records<'a>(lines: &[&'a str], config: &SyslogConfig) -> Vec<(&'a str, Vec<&'a str>)> {
+    let mut records: Vec<(&'a str, Vec<&'a str>)> = Vec::new();
+
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match records.last_mut() {
+            Some((_, continuation)) if config.is_continuation(line) => {
+                continuation.push(line.trim_end());
+            }
+            _ => records.push((line.trim(), Vec::new())),
+        }
+    }
+
+    records
+}
+
+/// Append continuation lines to a message, one per line.
+fn join_message(message: &str, continuation: &[&str]) -> String {
+    let mut joined = message.to_string();
+    for line in continuation {
+        joined.push('\n');
+        joined.push_str(line);
+    }
+    joined
+}
+
+/// Columns of values extracted by key, named `<prefix><key>`.
+struct KeyValueColumns {
+    enabled: bool,
+    prefix: &'static str,
+    max_columns: usize,
+    /// Keys in order of first appearance, with (row, value) pairs
+    columns: Vec<(String, Vec<(usize, String)>)>,
+}
+
+impl KeyValueColumns {
+    fn new(config: &SyslogConfig) -> Self {
+        Self {
+            enabled: config.extract_key_values,
+            prefix: KEY_VALUE_PREFIX,
+            max_columns: config.max_key_value_columns,
+            columns: Vec::new(),
+        }
+    }
+
+    /// Columns for RFC 5424 structured data parameters, keyed `<sd-id>.<name>`.
+    fn structured_data(config: &SyslogConfig) -> Self {
+        Self {
+            enabled: true,
+            prefix: STRUCTURED_DATA_PREFIX,
+            max_columns: config.max_key_value_columns,
+            columns: Vec::new(),
+        }
+    }
+
+    /// Record the value of `key` in `row`.
+    ///
+    /// Returns false if the key has no column and the column limit is reached.
+    fn insert(&mut self, key: &str, row: usize, value: String) -> bool {
+        let index = match self.columns.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None if self.columns.len() < self.max_columns => {
+                self.columns.push((key.to_string(), Vec::new()));
+                self.columns.len() - 1
+            }
+            None => return false,
+        };
+        self.columns[index].1.push((row, value));
+        true
+    }
+
+    /// Extract the pairs of a message in `row`, returning the remaining template.
+    fn extract(&mut self, message: &str, row: usize) -> String {
+        if !self.enabled {
+            return message.to_string();
+        }
+
+        let mut template = String::with_capacity(message.len());
+        let mut position = 0;
+        let mut seen: Vec<&str> = Vec::new();
+
+        for (key, value_start, value_end) in find_key_values(message) {
+            // Only the first occurrence of a key is extracted
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+
+            if !self.insert(key, row, message[value_start..value_end].to_string()) {
+                continue;
+            }
+            template.push_str(&message[position..value_start]);
+            position = value_end;
+        }
+
+        template.push_str(&message[position..]);
+        template
+    }
+
+    /// Build one nullable column per extracted key.
+    fn into_columns(self, row_count: usize) -> Vec<Column<'static>> {
+        self.columns
+            .into_iter()
+            .map(|(key, entries)| {
+                let mut values = vec![Value::Null; row_count];
+                for (row, value) in entries {
+                    values[row] = key_value_to_value(value);
+                }
+                Column::new(Cow::Owned(format!("{}{}", self.prefix, key)), values)
+            })
+            .collect()
+    }
+}
+
+/// Find `key=value` pairs in a message.
+///
+/// Returns each key with the byte range of its value. Keys start a
+/// whitespace-separated word and consist of letters, digits, `_`, `-` and
+/// `.`; values run to the next whitespace, or to the closing quote when
+/// they start with `"`. Pairs with empty values are skipped.
+fn find_key_values(message: &str) -> Vec<(&str, usize, usize)> {
+    let mut pairs = Vec::new();
+    let mut word_start = 0;
+    let bytes = message.as_bytes();
+
+    while word_start < bytes.len() {
+        if bytes[word_start].is_ascii_whitespace() {
+            word_start += 1;
+            continue;
+        }
+
+        let key_end = message[word_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+            .map_or(bytes.len(), |i| word_start + i);
+        let is_key = key_end > word_start
+            && bytes[word_start].is_ascii_alphabetic()
+            && bytes.get(key_end) == Some(&b'=');
+
+        let value_start = key_end + 1;
+        let value_end = if is_key && bytes.get(value_start) == Some(&b'"') {
+            message[value_start + 1..]
+                .find('"')
+                .map(|i| value_start + i + 2)
+        } else {
+            None
+        };
+        let word_end = |from: usize| {
+            message[from..]
+                .find(|c: char| c.is_whitespace())
+                .map_or(bytes.len(), |i| from + i)
+        };
+
+        if is_key {
+            let value_end = value_end.unwrap_or_else(|| word_end(value_start));
+            if value_end > value_start {
+                pairs.push((&message[word_start..key_end], value_start, value_end));
+            }
+            word_start = value_end.max(value_start);
+        } else {
+            word_start = word_end(word_start);
+        }
+    }
+
+    pairs
+}
+
+/// Convert an extracted value, keeping integers typed when they roundtrip.
+fn key_value_to_value(value: String) -> Value<'static> {
+    match value.parse::<i64>() {
+        Ok(i) if i.to_string() == value => Value::Integer(i),
+        _ => Value::String(Cow::Owned(value)),
+    }
+}
+
+/// Put extracted values back after the first `key=` of each key.
+fn restore_key_values(template: &str, pairs: &[(&str, String)]) -> String {
+    let mut message = template.to_string();
+    for (key, value) in pairs {
+        let marker = format!("{}=", key);
+        let found = message.match_indices(&marker).map(|(i, _)| i).find(|&i| {
+            let at_word_start = message[..i].chars().next_back().is_none_or(char::is_whitespace);
+            let value_pos = i + marker.len();
+            let empty = message[value_pos..].chars().next().is_none_or(char::is_whitespace);
+            at_word_start && empty
+        });
+        if let Some(i) = found {
+            message.insert_str(i + marker.len(), value);
+        }
+    }
+    message
+}
+
 /// Parse a single syslog line.
 fn parse_syslog_line(line: &str) -> Result<SyslogEntry<'_>> {
     // Format: "Jun 14 15:16:01 combo sshd(pam_unix)[19939]: message"
@@ -488,12 +1033,18 @@ fn extract_kerberos_source(message: &str) -> Option<&str> {
 
 /// Convert TabularData back to syslog format.
 ///
-/// This function reconstructs log lines from the structured data.
+/// This function reconstructs log lines from the structured data. Data with
+/// a `structured_data` column (parsed from RFC 5424 input) is written as
+/// RFC 5424 lines.
 pub fn to_syslog(data: &TabularData) -> Result<String> {
     if data.is_empty() || data.column_count() == 0 {
         return Ok(String::new());
     }
 
+    if data.columns.iter().any(|c| c.name == "structured_data") {
+        return to_rfc5424(data);
+    }
+
     let mut output = String::new();
     
     // Get column indices
@@ -504,6 +1055,11 @@ pub fn to_syslog(data: &TabularData) -> Result<String> {
     let service_idx = data.columns.iter().position(|c| c.name == "service");
     let pid_idx = data.columns.iter().position(|c| c.name == "pid");
     let message_idx = data.columns.iter().position(|c| c.name == "message");
+    let key_value_columns: Vec<(&str, &Column)> = data
+        .columns
+        .iter()
+        .filter_map(|c| c.name.strip_prefix(KEY_VALUE_PREFIX).map(|key| (key, c)))
+        .collect();
 
     for row_idx in 0..data.row_count {
         let month = month_idx.and_then(|i| data.columns[i].values[row_idx].as_str()).unwrap_or("");
@@ -513,6 +1069,14 @@ pub fn to_syslog(data: &TabularData) -> Result<String> {
         let service = service_idx.and_then(|i| data.columns[i].values[row_idx].as_str()).unwrap_or("");
         let pid = pid_idx.and_then(|i| data.columns[i].values[row_idx].as_integer());
         let message = message_idx.and_then(|i| data.columns[i].values[row_idx].as_str()).unwrap_or("");
+        let pairs: Vec<(&str, String)> = key_value_columns
+            .iter()
+            .filter_map(|(key, column)| match &column.values[row_idx] {
+                Value::Null => None,
+                value => Some((*key, value.to_string_repr().into_owned())),
+            })
+            .collect();
+        let message = restore_key_values(message, &pairs);
 
         // Reconstruct line
         if let Some(p) = pid {
@@ -636,6 +1200,181 @@ Jun 15 04:06:18 combo su(pam_unix)[21416]: session opened for user cyrus by (uid
         assert!(output.contains("19939"));
     }
 
+    #[test]
+    fn test_key_values_disabled_by_default() {
+        let log = "Jun 14 15:16:01 combo app[1]: request user=alice status=200";
+        let data = parse_syslog(log).unwrap();
+        assert_eq!(data.column_count(), 10);
+        assert_eq!(data.columns[9].values[0].as_str(), Some("request user=alice status=200"));
+    }
+
+    #[test]
+    fn test_extract_key_values() {
+        let log = r#"Jun 14 15:16:01 combo app[1]: request user=alice status=200 path="/a b"
+Jun 14 15:16:02 combo app[1]: request status=404 user=bob
+Jun 14 15:16:03 combo app[1]: idle"#;
+        let config = SyslogConfig::new().with_key_values(8);
+        let data = parse_syslog_with_config(log, &config).unwrap();
+
+        assert_eq!(data.column_count(), 13);
+        assert_eq!(data.columns[10].name, "kv.user");
+        assert_eq!(data.columns[11].name, "kv.status");
+        assert_eq!(data.columns[12].name, "kv.path");
+        assert_eq!(data.columns[9].values[0].as_str(), Some("request user= status= path="));
+        assert_eq!(data.columns[10].values[1].as_str(), Some("bob"));
+        assert_eq!(data.columns[11].values[0].as_integer(), Some(200));
+        assert_eq!(data.columns[12].values[0].as_str(), Some("\"/a b\""));
+        assert!(data.columns[12].values[1].is_null());
+        assert!(data.columns[10].values[2].is_null());
+    }
+
+    #[test]
+    fn test_key_value_column_limit() {
+        let log = "Jun 14 15:16:01 combo app[1]: a=1 b=2 c=3";
+        let data = parse_syslog_with_config(log, &SyslogConfig::new().with_key_values(2)).unwrap();
+
+        assert_eq!(data.column_count(), 12);
+        assert_eq!(data.columns[9].values[0].as_str(), Some("a= b= c=3"));
+    }
+
+    #[test]
+    fn test_key_values_roundtrip() {
+        let log = r#"Jun 14 15:16:01 combo app[1]: request user=alice status=200 path="/a b" retry=007
+Jun 14 15:16:02 combo app[1]: logname= uid=0 user=bob user=eve (pid=3)
+Jun 14 15:16:03 combo app: idle"#;
+        let config = SyslogConfig::new().with_key_values(8);
+        let data = parse_syslog_with_config(log, &config).unwrap();
+        let output = to_syslog(&data).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], r#"Jun 14 15:16:01 combo app[1]: request user=alice status=200 path="/a b" retry=007"#);
+        assert_eq!(lines[1], "Jun 14 15:16:02 combo app[1]: logname= uid=0 user=bob user=eve (pid=3)");
+        assert_eq!(lines[2], "Jun 14 15:16:03 combo app: idle");
+    }
+
+    #[test]
+    fn test_find_key_values() {
+        let message = r#"x=1 (y=2) ruser= 9z=3 q="a b" z=4"#;
+        let pairs: Vec<(&str, &str)> = find_key_values(message)
+            .into_iter()
+            .map(|(key, start, end)| (key, &message[start..end]))
+            .collect();
+        assert_eq!(pairs, vec![("x", "1"), ("q", "\"a b\""), ("z", "4")]);
+    }
+
+    #[test]
+    fn test_multiline_disabled_by_default() {
+        let log = "Jun 14 15:16:01 combo app[1]: failed\n    at Main.run(Main.java:10)";
+        let data = parse_syslog(log).unwrap();
+        assert_eq!(data.row_count, 2);
+        assert_eq!(data.columns[6].values[1].as_str(), Some("parse_error"));
+    }
+
+    #[test]
+    fn test_join_continuation_lines() {
+        let log = "Jun 14 15:16:01 combo app[1]: java.lang.IllegalStateException: boom
+    at Main.run(Main.java:10)
+\tat Main.main(Main.java:3)
+Caused by: java.io.IOException
+Jun 14 15:16:02 combo app[1]: recovered";
+        let data = parse_syslog_with_config(log, &SyslogConfig::new().with_multiline(true)).unwrap();
+
+        assert_eq!(data.row_count, 2);
+        assert_eq!(
+            data.columns[9].values[0].as_str(),
+            Some("java.lang.IllegalStateException: boom\n    at Main.run(Main.java:10)\n\tat Main.main(Main.java:3)\nCaused by: java.io.IOException")
+        );
+        assert_eq!(data.columns[2].values[1].as_str(), Some("15:16:02"));
+        assert_eq!(data.columns[9].values[1].as_str(), Some("recovered"));
+    }
+
+    #[test]
+    fn test_join_unparseable_lines() {
+        let log = "Jun 14 15:16:01 combo app[1]: dump follows\nline two\nJun 14 15:16:02 combo app[1]: done";
+
+        let config = SyslogConfig::new().with_multiline(true);
+        assert_eq!(parse_syslog_with_config(log, &config).unwrap().row_count, 3);
+
+        let config = config.with_join_unparseable(true).with_continuation_prefixes(Vec::<String>::new());
+        let data = parse_syslog_with_config(log, &config).unwrap();
+        assert_eq!(data.row_count, 2);
+        assert_eq!(data.columns[9].values[0].as_str(), Some("dump follows\nline two"));
+    }
+
+    #[test]
+    fn test_leading_continuation_starts_record() {
+        let log = "  orphan line\nJun 14 15:16:01 combo app[1]: ok";
+        let data = parse_syslog_with_config(log, &SyslogConfig::new().with_multiline(true)).unwrap();
+        assert_eq!(data.row_count, 2);
+        assert_eq!(data.columns[9].values[0].as_str(), Some("orphan line"));
+    }
+
+    #[test]
+    fn test_multiline_roundtrip() {
+        let log = "Jun 14 15:16:01 combo app[1]: failed\n    at Main.run(Main.java:10)\nJun 14 15:16:02 combo app[1]: ok\n";
+        let data = parse_syslog_with_config(log, &SyslogConfig::new().with_multiline(true)).unwrap();
+        assert_eq!(to_syslog(&data).unwrap(), log);
+    }
+
+    const RFC5424_LOG: &str = r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"] An application event log entry
+<165>1 2003-10-11T22:14:16.003Z mymachine.example.com evntslog 812 ID47 [exampleSDID@32473 iut="4" eventSource="Application" eventID="1012"][origin ip="192.0.2.1"] Second entry
+<34>1 - - su - - -
+"#;
+
+    #[test]
+    fn test_parse_rfc5424() {
+        let data = parse_syslog(RFC5424_LOG).unwrap();
+
+        assert_eq!(data.row_count, 3);
+        let names: Vec<&str> = data.columns.iter().map(|c| c.name.as_ref()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "priority", "version", "timestamp", "hostname", "app_name", "proc_id", "msg_id",
+                "structured_data", "message", "sd.exampleSDID@32473.iut",
+                "sd.exampleSDID@32473.eventSource", "sd.exampleSDID@32473.eventID", "sd.origin.ip",
+            ]
+        );
+        assert_eq!(data.columns[0].values[0].as_integer(), Some(165));
+        assert_eq!(data.columns[2].values[1].as_str(), Some("2003-10-11T22:14:16.003Z"));
+        assert!(data.columns[5].values[0].is_null());
+        assert_eq!(data.columns[5].values[1].as_integer(), Some(812));
+        assert_eq!(
+            data.columns[7].values[0].as_str(),
+            Some(r#"[exampleSDID@32473 iut="" eventSource="" eventID=""]"#)
+        );
+        assert!(data.columns[7].values[2].is_null());
+        assert!(data.columns[8].values[2].is_null());
+        assert_eq!(data.columns[9].values[1].as_integer(), Some(4));
+        assert_eq!(data.columns[10].values[0].as_str(), Some("Application"));
+        assert_eq!(data.columns[12].values[1].as_str(), Some("192.0.2.1"));
+        assert!(data.columns[12].values[0].is_null());
+    }
+
+    #[test]
+    fn test_rfc5424_roundtrip() {
+        let data = parse_syslog(RFC5424_LOG).unwrap();
+        assert_eq!(to_syslog(&data).unwrap(), RFC5424_LOG);
+    }
+
+    #[test]
+    fn test_rfc5424_parameter_limit_and_escapes() {
+        let log = r#"<13>1 - host app - - [a@1 x="q\"uote" y="2" x="again"] user=bob
+not a syslog line
+"#;
+        let config = SyslogConfig::new().with_key_values(1);
+        let data = parse_syslog_with_config(log, &config).unwrap();
+
+        assert_eq!(data.column_count(), 11);
+        assert_eq!(data.columns[9].name, "sd.a@1.x");
+        assert_eq!(data.columns[9].values[0].as_str(), Some("q\"uote"));
+        assert_eq!(data.columns[7].values[0].as_str(), Some(r#"[a@1 x="" y="2" x="again"]"#));
+        assert_eq!(data.columns[10].name, "kv.user");
+        assert!(data.columns[0].values[1].is_null());
+        assert_eq!(data.columns[8].values[1].as_str(), Some("not a syslog line"));
+        assert_eq!(to_syslog(&data).unwrap(), log);
+    }
+
     #[test]
     fn test_message_type_as_str() {
         assert_eq!(MessageType::AuthFailure.as_str(), "auth_fail");
@@ -5,6 +5,11 @@
 
 use std::borrow::Cow;
 
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::error::{AlsError, Result};
+use crate::query::Predicate;
+
 /// Zero-copy tabular data representation.
 ///
 /// `TabularData` represents structured data as a collection of columns,
@@ -32,6 +37,14 @@ use std::borrow::Cow;
 ///
 /// assert_eq!(data.row_count, 3);
 /// assert_eq!(data.column_count(), 2);
+///
+/// // Light transformations between parsing and compression
+/// let data = data
+///     .filter_rows(|row| row[0].as_integer() != Some(2))
+///     .rename("name", "first_name")
+///     .unwrap();
+/// assert_eq!(data.column_names(), vec!["id", "first_name"]);
+/// assert_eq!(data.row_count, 2);
 /// ```
 #[derive(Debug, Clone)]
 pub struct TabularData<'a> {
@@ -130,6 +143,196 @@ impl<'a> TabularData<'a> {
         })
     }
 
+    /// Keep only the named columns, in the given order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if a name does not match a column.
+    pub fn select_columns(self, names: &[&str]) -> Result<Self> {
+        let mut data = Self::with_capacity(names.len());
+        for &name in names {
+            let column = self
+                .get_column_by_name(name)
+                .ok_or_else(|| unknown_column(name))?;
+            data.add_column(column.clone());
+        }
+        Ok(data)
+    }
+
+    /// Keep only the rows for which `predicate` returns true.
+    ///
+    /// The predicate sees each row's values in column order. Column types
+    /// are inferred again from the remaining values.
+    pub fn filter_rows<F>(self, mut predicate: F) -> Self
+    where
+        F: FnMut(&[&Value<'a>]) -> bool,
+    {
+        let keep: Vec<bool> = self.rows().map(|row| predicate(&row)).collect();
+        let mut data = Self::with_capacity(self.column_count());
+        for column in self.columns {
+            let mut keep = keep.iter();
+            let values = column
+                .values
+                .into_iter()
+                .filter(|_| keep.next().copied().unwrap_or(false))
+                .collect();
+            data.add_column(Column::new(column.name, values));
+        }
+        data
+    }
+
+    /// Keep only the rows matching `predicate`.
+    ///
+    /// Values are compared as [`AlsQuery::find`](crate::AlsQuery::find)
+    /// compares them, so a predicate selects the same rows here as on the
+    /// compressed document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::Predicate;
+    /// use als_compression::convert::csv::parse_csv;
+    ///
+    /// let data = parse_csv("id,level\n1,debug\n2,info\n3,debug").unwrap();
+    /// let kept = data.filter(&Predicate::parse(r#"level != "debug""#).unwrap()).unwrap();
+    /// assert_eq!(kept.row_count, 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if there is no column with the
+    /// predicate's name, and `AlsError::InvalidPattern` if the value of a
+    /// `~` predicate is not a valid regular expression.
+    pub fn filter(self, predicate: &Predicate) -> Result<Self> {
+        let column = self
+            .get_column_by_name(&predicate.column)
+            .ok_or_else(|| unknown_column(&predicate.column))?;
+        let values: Vec<Cow<'_, str>> = column.values.iter().map(Value::to_string_repr).collect();
+        let mut keep = predicate.matches(&values)?.into_iter();
+        Ok(self.filter_rows(|_| keep.next().unwrap_or(false)))
+    }
+
+    /// Replace each value of the named column with `f(value)`.
+    ///
+    /// The column type is inferred again from the new values.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if there is no column with that name.
+    pub fn map_column<F>(mut self, name: &str, f: F) -> Result<Self>
+    where
+        F: FnMut(Value<'a>) -> Value<'a>,
+    {
+        let column = self
+            .columns
+            .iter_mut()
+            .find(|c| c.name == name)
+            .ok_or_else(|| unknown_column(name))?;
+        let values = std::mem::take(&mut column.values).into_iter().map(f).collect();
+        *column = Column::new(std::mem::take(&mut column.name), values);
+        Ok(self)
+    }
+
+    /// Rename a column.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if there is no column named `from`.
+    pub fn rename<S: Into<Cow<'a, str>>>(mut self, from: &str, to: S) -> Result<Self> {
+        let column = self
+            .columns
+            .iter_mut()
+            .find(|c| c.name == from)
+            .ok_or_else(|| unknown_column(from))?;
+        column.name = to.into();
+        Ok(self)
+    }
+
+    /// Append the rows of `other` below these rows.
+    ///
+    /// Columns are matched by name, so `other` may list them in a different
+    /// order. Data without columns takes the columns of the other side.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::ColumnMismatch` if the column counts differ and
+    /// `AlsError::UnknownColumn` if a column of `other` is missing here.
+    pub fn concat(mut self, mut other: TabularData<'a>) -> Result<Self> {
+        if self.columns.is_empty() {
+            return Ok(other);
+        }
+        if other.columns.is_empty() {
+            return Ok(self);
+        }
+        if self.column_count() != other.column_count() {
+            return Err(AlsError::ColumnMismatch {
+                schema: self.column_count(),
+                data: other.column_count(),
+            });
+        }
+        if let Some(column) = other.columns.iter().find(|c| self.get_column_by_name(&c.name).is_none()) {
+            return Err(unknown_column(&column.name));
+        }
+
+        let mut data = Self::with_capacity(self.column_count());
+        for column in &mut self.columns {
+            let tail = other.columns.iter_mut().find(|c| c.name == column.name);
+            let mut values = std::mem::take(&mut column.values);
+            if let Some(tail) = tail {
+                values.append(&mut tail.values);
+            }
+            data.add_column(Column::new(std::mem::take(&mut column.name), values));
+        }
+        Ok(data)
+    }
+
+    /// Normalize column names and string values to Unicode NFC.
+    ///
+    /// Returns `None` if the data is already in NFC, so callers can keep
+    /// using it as is. Strings that are already normalized stay borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::convert::{Column, TabularData, Value};
+    ///
+    /// let mut data = TabularData::new();
+    /// data.add_column(Column::new("city", vec![Value::string("Zu\u{308}rich")]));
+    ///
+    /// let normalized = data.to_nfc().unwrap();
+    /// assert_eq!(normalized.columns[0].values[0], Value::string("Z\u{fc}rich"));
+    /// assert!(normalized.to_nfc().is_none());
+    /// ```
+    pub fn to_nfc(&self) -> Option<TabularData<'a>> {
+        let all_normalized = self.columns.iter().all(|column| {
+            is_nfc(&column.name) && column.values.iter().all(|value| value.as_str().is_none_or(is_nfc))
+        });
+        if all_normalized {
+            return None;
+        }
+
+        let nfc = |s: &Cow<'a, str>| -> Cow<'a, str> {
+            if is_nfc(s) {
+                s.clone()
+            } else {
+                Cow::Owned(s.nfc().collect())
+            }
+        };
+        let mut data = Self::with_capacity(self.column_count());
+        for column in &self.columns {
+            let values = column
+                .values
+                .iter()
+                .map(|value| match value {
+                    Value::String(s) => Value::String(nfc(s)),
+                    other => other.clone(),
+                })
+                .collect();
+            data.add_column(Column::with_type(nfc(&column.name), values, column.inferred_type));
+        }
+        Some(data)
+    }
+
     /// Convert to owned data (removes lifetime dependency).
     pub fn into_owned(self) -> TabularData<'static> {
         TabularData {
@@ -139,6 +342,13 @@ impl<'a> TabularData<'a> {
     }
 }
 
+/// Build the error for a missing column.
+fn unknown_column(name: &str) -> AlsError {
+    AlsError::UnknownColumn {
+        name: name.to_string(),
+    }
+}
+
 impl Default for TabularData<'_> {
     fn default() -> Self {
         Self::new()
@@ -541,6 +751,90 @@ mod tests {
         assert_eq!(data.column_names(), vec!["id", "name"]);
     }
 
+    fn sample() -> TabularData<'static> {
+        let mut data = TabularData::new();
+        data.add_column(Column::new("id", vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]));
+        data.add_column(Column::new("name", vec![
+            Value::string("Alice"),
+            Value::string("Bob"),
+            Value::string("Carol"),
+        ]));
+        data
+    }
+
+    #[test]
+    fn test_tabular_data_select_columns() {
+        let data = sample().select_columns(&["name", "id"]).unwrap();
+        assert_eq!(data.column_names(), vec!["name", "id"]);
+        assert_eq!(data.row_count, 3);
+
+        let err = sample().select_columns(&["id", "age"]).unwrap_err();
+        assert!(matches!(err, AlsError::UnknownColumn { name } if name == "age"));
+    }
+
+    #[test]
+    fn test_tabular_data_filter_rows() {
+        let data = sample().filter_rows(|row| row[0].as_integer() != Some(2));
+        assert_eq!(data.row_count, 2);
+        assert_eq!(data.get_row(1).unwrap()[1].as_str(), Some("Carol"));
+
+        let none = sample().filter_rows(|_| false);
+        assert!(none.is_empty());
+        assert_eq!(none.column_count(), 2);
+    }
+
+    #[test]
+    fn test_tabular_data_filter() {
+        let data = sample().filter(&Predicate::parse("id >= 2").unwrap()).unwrap();
+        assert_eq!(data.column_names(), vec!["id", "name"]);
+        assert_eq!(data.get_row(0).unwrap()[1].as_str(), Some("Bob"));
+        assert_eq!(data.row_count, 2);
+
+        let data = sample().filter(&Predicate::parse("name != Bob").unwrap()).unwrap();
+        assert_eq!(data.get_column_by_name("id").unwrap().values, vec![Value::Integer(1), Value::Integer(3)]);
+
+        let err = sample().filter(&Predicate::parse("age > 3").unwrap()).unwrap_err();
+        assert!(matches!(err, AlsError::UnknownColumn { name } if name == "age"));
+    }
+
+    #[test]
+    fn test_tabular_data_map_column() {
+        let data = sample()
+            .map_column("id", |v| Value::Float(v.as_integer().unwrap() as f64 / 2.0))
+            .unwrap();
+        let id = data.get_column_by_name("id").unwrap();
+        assert_eq!(id.inferred_type, ColumnType::Float);
+        assert_eq!(id.values[1], Value::Float(1.0));
+
+        assert!(sample().map_column("age", |v| v).is_err());
+    }
+
+    #[test]
+    fn test_tabular_data_rename() {
+        let data = sample().rename("name", "first_name").unwrap();
+        assert_eq!(data.column_names(), vec!["id", "first_name"]);
+        assert!(sample().rename("age", "years").is_err());
+    }
+
+    #[test]
+    fn test_tabular_data_concat() {
+        let mut more = TabularData::new();
+        more.add_column(Column::new("name", vec![Value::string("Dave")]));
+        more.add_column(Column::new("id", vec![Value::Integer(4)]));
+
+        let data = sample().concat(more).unwrap();
+        assert_eq!(data.row_count, 4);
+        assert_eq!(data.column_names(), vec!["id", "name"]);
+        assert_eq!(data.get_row(3).unwrap()[0].as_integer(), Some(4));
+
+        assert_eq!(TabularData::new().concat(sample()).unwrap().row_count, 3);
+
+        let renamed = sample().rename("name", "label").unwrap();
+        assert!(matches!(sample().concat(renamed), Err(AlsError::UnknownColumn { .. })));
+        let narrow = sample().select_columns(&["id"]).unwrap();
+        assert!(matches!(sample().concat(narrow), Err(AlsError::ColumnMismatch { schema: 2, data: 1 })));
+    }
+
     #[test]
     fn test_column_new() {
         let col = Column::new("test", vec![
@@ -0,0 +1,418 @@
+//! Serving compression to other processes over a socket.
+//!
+//! Scripts that call the `als` binary thousands of times an hour pay for
+//! process startup, thread pool creation and allocator warm-up on every
+//! call. A [`Daemon`] does that once and answers requests over a stream
+//! socket for as long as it runs.
+//!
+//! # Protocol
+//!
+//! Every message is a frame: a 4-byte big-endian length followed by that
+//! many bytes of payload. A request payload is a command line ending in
+//! `\n` followed by the body:
+//!
+//! ```text
+//! compress csv       CSV body, answered with ALS
+//! compress json      JSON body, answered with ALS
+//! decompress csv     ALS body, answered with CSV
+//! decompress json    ALS body, answered with JSON
+//! ping               no body, answered with nothing
+//! ```
+//!
+//! A response payload is `ok\n` followed by the output, or `error\n`
+//! followed by a message. A connection can send any number of requests,
+//! each answered in order, and closing it ends the session.
+//!
+//! Payloads are limited to [`MAX_FRAME_LEN`] bytes, and a daemon serves at
+//! most [`DEFAULT_MAX_CONNECTIONS`] connections at once unless configured
+//! otherwise; further clients wait until one closes.
+
+use std::io::{self, Read, Write};
+
+use crate::als::{AlsParser, StoredFormat};
+use crate::compress::AlsCompressor;
+use crate::error::Result;
+
+/// Largest payload a frame may carry.
+pub const MAX_FRAME_LEN: usize = 1 << 28;
+
+/// Connections a [`Daemon`] serves at once by default.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+/// A request to a [`Daemon`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Compress CSV or JSON text to ALS
+    Compress(StoredFormat),
+    /// Decompress ALS to CSV or JSON text
+    Decompress(StoredFormat),
+    /// Check that the daemon is answering
+    Ping,
+}
+
+impl Command {
+    /// Parse a command line, without its newline.
+    pub fn parse(line: &str) -> Option<Self> {
+        match line.split_once(' ') {
+            Some(("compress", format)) => StoredFormat::from_name(format).map(Command::Compress),
+            Some(("decompress", format)) => StoredFormat::from_name(format).map(Command::Decompress),
+            None if line == "ping" => Some(Command::Ping),
+            _ => None,
+        }
+    }
+
+    /// Get the command line, without its newline.
+    pub fn line(&self) -> String {
+        match self {
+            Command::Compress(format) => format!("compress {}", format.name()),
+            Command::Decompress(format) => format!("decompress {}", format.name()),
+            Command::Ping => "ping".to_string(),
+        }
+    }
+}
+
+/// Read a frame's payload, or `None` if the reader is at its end.
+///
+/// The payload buffer grows as bytes arrive, so a header announcing a
+/// large frame costs nothing until the frame is sent.
+///
+/// # Errors
+///
+/// Returns an `io::ErrorKind::InvalidData` error for a frame longer than
+/// [`MAX_FRAME_LEN`], and `io::ErrorKind::UnexpectedEof` if the reader ends
+/// inside a frame.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len[..1]) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    reader.read_exact(&mut len[1..])?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {} bytes is over the limit of {}", len, MAX_FRAME_LEN),
+        ));
+    }
+    let mut payload = Vec::new();
+    reader.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("Frame ended after {} of {} bytes", payload.len(), len),
+        ));
+    }
+    Ok(Some(payload))
+}
+
+/// Write a payload as a frame.
+///
+/// # Errors
+///
+/// Returns an `io::ErrorKind::InvalidInput` error for a payload longer than
+/// [`MAX_FRAME_LEN`].
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Frame of {} bytes is over the limit of {}", payload.len(), MAX_FRAME_LEN),
+        ));
+    }
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Build a request payload.
+pub fn request(command: Command, body: &[u8]) -> Vec<u8> {
+    let line = command.line();
+    let mut payload = Vec::with_capacity(line.len() + 1 + body.len());
+    payload.extend_from_slice(line.as_bytes());
+    payload.push(b'\n');
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// Split a response payload into the output, or the error message as `Err`.
+pub fn response(payload: &[u8]) -> std::result::Result<&[u8], String> {
+    if let Some(output) = payload.strip_prefix(b"ok\n") {
+        Ok(output)
+    } else if let Some(message) = payload.strip_prefix(b"error\n") {
+        Err(String::from_utf8_lossy(message).into_owned())
+    } else {
+        Err("Malformed response".to_string())
+    }
+}
+
+/// Long-running compression service.
+///
+/// The compressor and parser, and the thread pools they use, are shared
+/// by every connection.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::daemon::{read_frame, request, response, write_frame, Command, Daemon};
+/// use als_compression::StoredFormat;
+/// use std::io::Cursor;
+///
+/// let csv: String = (1..=100).map(|id| format!("{id}\n")).collect();
+/// let mut session = Vec::new();
+/// write_frame(&mut session, &request(Command::Compress(StoredFormat::Csv), format!("id\n{csv}").as_bytes())).unwrap();
+///
+/// let mut answers = Vec::new();
+/// Daemon::new().serve_connection(Cursor::new(session), &mut answers).unwrap();
+///
+/// let payload = read_frame(&mut Cursor::new(answers)).unwrap().unwrap();
+/// assert_eq!(response(&payload).unwrap(), b"!v1\n#id\n1>100");
+/// ```
+pub struct Daemon {
+    compressor: AlsCompressor,
+    parser: AlsParser,
+    max_connections: usize,
+}
+
+impl Default for Daemon {
+    fn default() -> Self {
+        Self {
+            compressor: AlsCompressor::default(),
+            parser: AlsParser::default(),
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+        }
+    }
+}
+
+impl Daemon {
+    /// Create a daemon with the default compressor and parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve at most `max` connections at once, at least one.
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max.max(1);
+        self
+    }
+
+    /// Use `compressor` for compress requests.
+    pub fn with_compressor(mut self, compressor: AlsCompressor) -> Self {
+        self.compressor = compressor;
+        self
+    }
+
+    /// Use `parser` for decompress requests.
+    pub fn with_parser(mut self, parser: AlsParser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// The parser used for decompress requests.
+    pub fn parser(&self) -> &AlsParser {
+        &self.parser
+    }
+
+    /// Compress `input` in `format` to ALS, as a compress request would.
+    pub fn compress(&self, format: StoredFormat, input: &str) -> Result<String> {
+        match format {
+            StoredFormat::Csv => self.compressor.compress_csv(input),
+            StoredFormat::Json => self.compressor.compress_json(input),
+        }
+    }
+
+    /// Decompress ALS `input` to `format`, as a decompress request would.
+    pub fn decompress(&self, format: StoredFormat, input: &str) -> Result<String> {
+        match format {
+            StoredFormat::Csv => self.parser.to_csv(input),
+            StoredFormat::Json => self.parser.to_json(input),
+        }
+    }
+
+    /// Answer a request payload with a response payload.
+    pub fn handle(&self, payload: &[u8]) -> Vec<u8> {
+        match self.run(payload) {
+            Ok(output) => [b"ok\n".as_slice(), output.as_bytes()].concat(),
+            Err(e) => format!("error\n{}", e).into_bytes(),
+        }
+    }
+
+    /// Run a request, returning its output or the error message.
+    fn run(&self, payload: &[u8]) -> std::result::Result<String, String> {
+        let split = payload.iter().position(|&b| b == b'\n').unwrap_or(payload.len());
+        let line = std::str::from_utf8(&payload[..split]).map_err(|_| "Command is not UTF-8".to_string())?;
+        let command = Command::parse(line).ok_or_else(|| format!("Unknown command: {}", line))?;
+        let body = payload.get(split + 1..).unwrap_or_default();
+        let body = std::str::from_utf8(body).map_err(|_| "Body is not UTF-8".to_string())?;
+
+        let output: Result<String> = match command {
+            Command::Compress(format) => self.compress(format, body),
+            Command::Decompress(format) => self.decompress(format, body),
+            Command::Ping => Ok(String::new()),
+        };
+        output.map_err(|e| e.to_string())
+    }
+
+    /// Answer the requests read from `reader` on `writer` until `reader`
+    /// ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reading or writing a frame. Failed requests are
+    /// answered with an error response instead.
+    pub fn serve_connection<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> io::Result<()> {
+        while let Some(payload) = read_frame(&mut reader)? {
+            write_frame(&mut writer, &self.handle(&payload))?;
+        }
+        Ok(())
+    }
+
+    /// Answer connections to a Unix socket, each on its own thread, until
+    /// accepting one fails.
+    ///
+    /// A connection failing, such as a client sending an oversized frame,
+    /// only closes that connection. With the most connections the daemon
+    /// serves at once open, new ones are not accepted until one closes.
+    #[cfg(unix)]
+    pub fn serve(&self, listener: &std::os::unix::net::UnixListener) -> io::Result<()> {
+        use std::sync::{Condvar, Mutex, PoisonError};
+
+        let open = Mutex::new(0usize);
+        let closed = Condvar::new();
+        std::thread::scope(|scope| {
+            loop {
+                {
+                    let mut count = open.lock().unwrap_or_else(PoisonError::into_inner);
+                    while *count >= self.max_connections {
+                        count = closed.wait(count).unwrap_or_else(PoisonError::into_inner);
+                    }
+                    *count += 1;
+                }
+                let stream = listener.accept()?.0;
+                let (open, closed) = (&open, &closed);
+                scope.spawn(move || {
+                    let reader = io::BufReader::new(&stream);
+                    // The client is gone, so there is no one to report to
+                    let _ = self.serve_connection(reader, io::BufWriter::new(&stream));
+                    *open.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+                    closed.notify_one();
+                });
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_commands() {
+        for command in [
+            Command::Compress(StoredFormat::Csv),
+            Command::Compress(StoredFormat::Json),
+            Command::Decompress(StoredFormat::Csv),
+            Command::Decompress(StoredFormat::Json),
+            Command::Ping,
+        ] {
+            assert_eq!(Command::parse(&command.line()), Some(command));
+        }
+        assert_eq!(Command::parse("compress xml"), None);
+        assert_eq!(Command::parse("ping now"), None);
+    }
+
+    #[test]
+    fn test_requests() {
+        let daemon = Daemon::new();
+        let run = |command: Command, body: &str| {
+            let payload = daemon.handle(&request(command, body.as_bytes()));
+            response(&payload).map(|output| String::from_utf8(output.to_vec()).unwrap())
+        };
+
+        let als = run(Command::Compress(StoredFormat::Csv), "id,name\n1,a\n2,b\n").unwrap();
+        assert_eq!(run(Command::Decompress(StoredFormat::Csv), &als).unwrap(), "id,name\n1,a\n2,b\n");
+        let als = run(Command::Compress(StoredFormat::Json), r#"[{"id":1},{"id":2}]"#).unwrap();
+        assert_eq!(run(Command::Decompress(StoredFormat::Json), &als).unwrap(), r#"[{"id":1},{"id":2}]"#);
+        assert_eq!(run(Command::Ping, "").unwrap(), "");
+
+        assert!(run(Command::Decompress(StoredFormat::Csv), "#a #b\n1>3").unwrap_err().contains("Column count mismatch"));
+        assert!(response(&daemon.handle(b"shrink csv\nid\n1")).unwrap_err().contains("Unknown command"));
+        assert!(response(&daemon.handle(b"ping")).is_ok());
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut session = Vec::new();
+        write_frame(&mut session, b"ping\n").unwrap();
+        write_frame(&mut session, &request(Command::Decompress(StoredFormat::Csv), b"#x\n1>2")).unwrap();
+        let mut answers = Vec::new();
+        Daemon::new().serve_connection(Cursor::new(session), &mut answers).unwrap();
+
+        let mut answers = Cursor::new(answers);
+        assert_eq!(read_frame(&mut answers).unwrap().unwrap(), b"ok\n");
+        assert_eq!(read_frame(&mut answers).unwrap().unwrap(), b"ok\nx\n1\n2\n");
+        assert_eq!(read_frame(&mut answers).unwrap(), None);
+
+        // A frame cut short, or longer than allowed, ends the connection
+        let mut truncated = Vec::new();
+        write_frame(&mut truncated, b"ping\n").unwrap();
+        truncated.pop();
+        assert_eq!(read_frame(&mut Cursor::new(truncated)).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        let oversized = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes();
+        assert_eq!(read_frame(&mut Cursor::new(oversized)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // A large frame announced but never sent fails without reserving it
+        let announced = (MAX_FRAME_LEN as u32).to_be_bytes();
+        assert_eq!(read_frame(&mut Cursor::new(announced)).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket() {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        let path = std::env::temp_dir().join(format!("als-daemon-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let daemon: &'static Daemon = Box::leak(Box::new(Daemon::new()));
+        std::thread::spawn(move || daemon.serve(&listener));
+
+        let csv: String = (1..=100).map(|n| format!("{n}\n")).collect();
+        for _ in 0..2 {
+            let mut stream = UnixStream::connect(&path).unwrap();
+            write_frame(&mut stream, &request(Command::Compress(StoredFormat::Csv), format!("n\n{csv}").as_bytes())).unwrap();
+            let payload = read_frame(&mut stream).unwrap().unwrap();
+            assert_eq!(response(&payload).unwrap(), b"!v1\n#n\n1>100");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket_connection_limit() {
+        use std::os::unix::net::{UnixListener, UnixStream};
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join(format!("als-daemon-limit-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let daemon: &'static Daemon = Box::leak(Box::new(Daemon::new().with_max_connections(1)));
+        std::thread::spawn(move || daemon.serve(&listener));
+
+        let mut first = UnixStream::connect(&path).unwrap();
+        write_frame(&mut first, b"ping\n").unwrap();
+        assert_eq!(read_frame(&mut first).unwrap().unwrap(), b"ok\n");
+
+        // The second client waits for the first to close
+        let mut second = UnixStream::connect(&path).unwrap();
+        second.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        write_frame(&mut second, b"ping\n").unwrap();
+        assert!(read_frame(&mut second).is_err());
+
+        drop(first);
+        second.set_read_timeout(None).unwrap();
+        assert_eq!(read_frame(&mut second).unwrap().unwrap(), b"ok\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Synthetic datasets for benchmarking.
+//!
+//! Enabled by the `datasets` feature. Each [`Dataset`] generates a table of
+//! any number of rows with the shape of a common workload, so compression
+//! speed and ratio can be measured without shipping sample files. The crate
+//! benchmarks (`cargo bench --features datasets`) run over these tables, and
+//! users can generate the same ones to compare their own hardware.
+//!
+//! Generation is deterministic: the same dataset and row count always give
+//! the same table.
+//!
+//! # Examples
+//!
+//! ```
+//! use als_compression::datasets::Dataset;
+//! use als_compression::AlsCompressor;
+//!
+//! let data = Dataset::Logs.generate(1_000);
+//! assert_eq!(data.row_count, 1_000);
+//!
+//! let doc = AlsCompressor::new().compress(&data).unwrap();
+//! assert_eq!(doc.column_count(), data.column_count());
+//! ```
+
+use crate::als::datetime::format_timestamp;
+use crate::convert::csv::to_csv;
+use crate::convert::{Column, TabularData, Value};
+use crate::rng::Rng;
+
+/// First timestamp of generated time columns (2024-01-01T00:00:00Z).
+const START_TIMESTAMP: i64 = 1_704_067_200;
+
+/// A synthetic workload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dataset {
+    /// Metrics-style table: sequential IDs, regular timestamps, counters,
+    /// random walks and noisy readings.
+    Numeric,
+    /// Categorical table: status codes, regions, tiers, flags and weekdays
+    /// with skewed frequencies.
+    Enum,
+    /// Application logs: irregular timestamps, levels, hosts, request IDs,
+    /// URL paths, status codes, latencies and templated messages.
+    Logs,
+}
+
+impl Dataset {
+    /// All datasets.
+    pub const ALL: [Dataset; 3] = [Dataset::Numeric, Dataset::Enum, Dataset::Logs];
+
+    /// Short name, suitable for benchmark IDs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dataset::Numeric => "numeric",
+            Dataset::Enum => "enum",
+            Dataset::Logs => "logs",
+        }
+    }
+
+    /// Generate a table with `rows` rows.
+    pub fn generate(&self, rows: usize) -> TabularData<'static> {
+        match self {
+            Dataset::Numeric => numeric(rows),
+            Dataset::Enum => enums(rows),
+            Dataset::Logs => logs(rows),
+        }
+    }
+
+    /// Generate a table with `rows` rows as CSV text.
+    pub fn csv(&self, rows: usize) -> String {
+        // Generated values never contain characters CSV cannot hold
+        to_csv(&self.generate(rows)).expect("generated data converts to CSV")
+    }
+}
+
+impl Rng {
+    /// Uniform float in `0.0..1.0`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Pick from `choices` with the given relative weights.
+    fn weighted<'c>(&mut self, choices: &[(&'c str, u64)]) -> &'c str {
+        let total: u64 = choices.iter().map(|(_, weight)| weight).sum();
+        let mut pick = self.below(total);
+        for &(choice, weight) in choices {
+            if pick < weight {
+                return choice;
+            }
+            pick -= weight;
+        }
+        choices[choices.len() - 1].0
+    }
+}
+
+fn string_column(name: &'static str, values: impl Iterator<Item = String>) -> Column<'static> {
+    Column::new(name, values.map(Value::string_owned).collect())
+}
+
+fn numeric(rows: usize) -> TabularData<'static> {
+    let mut rng = Rng::new(1);
+    let mut data = TabularData::with_capacity(6);
+
+    data.add_column(Column::new("id", (1..=rows as i64).map(Value::Integer).collect()));
+    data.add_column(Column::new(
+        "timestamp",
+        (0..rows as i64).map(|i| Value::Integer(START_TIMESTAMP + i * 60)).collect(),
+    ));
+    data.add_column(Column::new("counter", (0..rows as i64).map(|i| Value::Integer(i * 5)).collect()));
+
+    let mut price = 100.0f64;
+    let prices = (0..rows)
+        .map(|_| {
+            price = (price + (rng.unit() - 0.5) * 2.0).max(1.0);
+            Value::Float((price * 100.0).round() / 100.0)
+        })
+        .collect();
+    data.add_column(Column::new("price", prices));
+
+    let quantities = (0..rows).map(|_| Value::Integer(1 + rng.below(100) as i64)).collect();
+    data.add_column(Column::new("quantity", quantities));
+
+    let readings = (0..rows)
+        .map(|i| Value::Float(((20.0 + (i as f64 / 50.0).sin() * 5.0 + rng.unit()) * 1000.0).round() / 1000.0))
+        .collect();
+    data.add_column(Column::new("reading", readings));
+
+    data
+}
+
+fn enums(rows: usize) -> TabularData<'static> {
+    const REGIONS: [&str; 8] = [
+        "us-east-1", "us-west-2", "eu-west-1", "eu-central-1", "ap-south-1", "ap-northeast-1", "sa-east-1", "ca-central-1",
+    ];
+    const COUNTRIES: [&str; 20] = [
+        "US", "GB", "DE", "FR", "JP", "IN", "BR", "CA", "AU", "NL", "SE", "ES", "IT", "MX", "KR", "SG", "CH", "PL", "BE", "IE",
+    ];
+    const DAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+    let mut rng = Rng::new(2);
+    let mut data = TabularData::with_capacity(7);
+
+    data.add_column(Column::new("id", (1..=rows as i64).map(Value::Integer).collect()));
+    data.add_column(string_column(
+        "status",
+        (0..rows).map(|_| rng.weighted(&[("active", 70), ("pending", 20), ("suspended", 7), ("closed", 3)]).to_string()),
+    ));
+    // Rows arrive grouped by region
+    data.add_column(string_column(
+        "region",
+        (0..rows).map(|i| REGIONS[(i * REGIONS.len() / rows.max(1)).min(REGIONS.len() - 1)].to_string()),
+    ));
+    data.add_column(string_column(
+        "tier",
+        (0..rows).map(|_| rng.weighted(&[("free", 60), ("pro", 30), ("enterprise", 10)]).to_string()),
+    ));
+    data.add_column(Column::new("verified", (0..rows).map(|_| Value::Boolean(rng.below(4) != 0)).collect()));
+    data.add_column(string_column(
+        "country",
+        (0..rows).map(|_| COUNTRIES[(rng.below(20) * rng.below(20) / 19) as usize].to_string()),
+    ));
+    data.add_column(string_column("weekday", (0..rows).map(|i| DAYS[(i / 24) % 7].to_string())));
+
+    data
+}
+
+fn logs(rows: usize) -> TabularData<'static> {
+    const SERVICES: [&str; 4] = ["api", "auth", "billing", "search"];
+    const PATHS: [&str; 5] = ["/api/v1/users", "/api/v1/orders", "/api/v1/search", "/login", "/health"];
+
+    let mut rng = Rng::new(3);
+    let mut data = TabularData::with_capacity(10);
+
+    let mut timestamp = START_TIMESTAMP;
+    let timestamps = (0..rows)
+        .map(|_| {
+            timestamp += rng.below(3) as i64;
+            format_timestamp(timestamp, "%Y-%m-%dT%H:%M:%SZ").unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    data.add_column(string_column("timestamp", timestamps.into_iter()));
+
+    let levels: Vec<&str> = (0..rows)
+        .map(|_| rng.weighted(&[("INFO", 80), ("DEBUG", 10), ("WARN", 7), ("ERROR", 3)]))
+        .collect();
+    data.add_column(string_column("level", levels.iter().map(|level| level.to_string())));
+    data.add_column(string_column("host", (0..rows).map(|_| format!("web-{:02}.prod.internal", 1 + rng.below(12)))));
+    data.add_column(string_column("service", (0..rows).map(|_| SERVICES[rng.below(4) as usize].to_string())));
+    data.add_column(string_column(
+        "request_id",
+        (0..rows).map(|_| format!("{:016x}{:016x}", rng.next_u64(), rng.next_u64())),
+    ));
+    data.add_column(string_column(
+        "path",
+        (0..rows).map(|_| match PATHS[rng.below(5) as usize] {
+            path @ ("/login" | "/health") => path.to_string(),
+            path => format!("{}/{}", path, rng.below(10_000)),
+        }),
+    ));
+
+    let statuses: Vec<i64> = levels
+        .iter()
+        .map(|level| match *level {
+            "ERROR" => 500,
+            "WARN" => [404, 429][rng.below(2) as usize],
+            _ => 200,
+        })
+        .collect();
+    data.add_column(Column::new("status", statuses.iter().map(|&status| Value::Integer(status)).collect()));
+    data.add_column(Column::new(
+        "latency_ms",
+        (0..rows).map(|_| Value::Integer(5 + (rng.unit() * rng.unit() * 500.0) as i64)).collect(),
+    ));
+    data.add_column(string_column(
+        "message",
+        statuses.iter().map(|status| match status {
+            200 => "request completed".to_string(),
+            500 => format!("upstream error after {} retries", rng.below(4)),
+            status => format!("client error {}", status),
+        }),
+    ));
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::{AlsParser, AlsSerializer};
+    use crate::compress::AlsCompressor;
+
+    #[test]
+    fn test_datasets_are_deterministic() {
+        for dataset in Dataset::ALL {
+            assert_eq!(dataset.csv(200), dataset.csv(200), "{}", dataset.name());
+            assert_eq!(dataset.generate(0).row_count, 0);
+        }
+    }
+
+    #[test]
+    fn test_datasets_round_trip_and_compress() {
+        for dataset in Dataset::ALL {
+            let csv = dataset.csv(2_000);
+            let als = AlsCompressor::new().compress_csv(&csv).unwrap();
+            assert!(als.len() < csv.len(), "{} compresses {} -> {}", dataset.name(), csv.len(), als.len());
+
+            let parser = AlsParser::new();
+            assert_eq!(parser.to_csv(&als).unwrap(), csv, "{}", dataset.name());
+            let doc = parser.parse(&als).unwrap();
+            assert_eq!(parser.parse(&AlsSerializer::new().serialize(&doc)).unwrap(), doc);
+        }
+    }
+}
@@ -5,6 +5,8 @@
 
 use thiserror::Error;
 
+use crate::als::SchemaViolation;
+
 /// Main error type for the ALS compression library.
 ///
 /// All operations that can fail return `Result<T, AlsError>`.
@@ -64,6 +66,18 @@ pub enum AlsError {
         size: usize,
     },
 
+    /// Invalid column reference.
+    ///
+    /// Occurs when a column reference points at a column that doesn't
+    /// exist or that appears at or after the referencing column.
+    #[error("Invalid column reference: @{index} (document has {columns} preceding columns)")]
+    InvalidColumnRef {
+        /// The invalid column index that was referenced
+        index: usize,
+        /// The number of columns that may be referenced
+        columns: usize,
+    },
+
     /// Range expansion would overflow.
     ///
     /// Occurs when a range operator would produce too many values,
@@ -90,6 +104,16 @@ pub enum AlsError {
         found: u8,
     },
 
+    /// Feature flag the parser does not support.
+    ///
+    /// Occurs when a version 2 header such as `!v2 +blocks +crc` lists a
+    /// feature this parser does not know.
+    #[error("Unsupported feature: {feature}")]
+    UnsupportedFeature {
+        /// Name of the feature, without its `+`
+        feature: String,
+    },
+
     /// Column count mismatch.
     ///
     /// Occurs when the number of columns in the schema doesn't match
@@ -102,6 +126,155 @@ pub enum AlsError {
         data: usize,
     },
 
+    /// Unknown column name.
+    ///
+    /// Occurs when an operation refers to a column that the data does not have.
+    #[error("Unknown column: {name}")]
+    UnknownColumn {
+        /// Name of the missing column
+        name: String,
+    },
+
+    /// Container checksum mismatch.
+    ///
+    /// Occurs when a document read from a container doesn't match the
+    /// checksum recorded in its table of contents.
+    #[error("Checksum mismatch in '{name}': expected {expected:08x}, found {found:08x}")]
+    ChecksumMismatch {
+        /// Name of the corrupt document
+        name: String,
+        /// Checksum recorded in the table of contents
+        expected: u32,
+        /// Checksum of the document as read
+        found: u32,
+    },
+
+    /// Duplicate container entry.
+    ///
+    /// Occurs when a container would hold two documents with the same name.
+    #[error("Duplicate container entry: {name}")]
+    DuplicateEntry {
+        /// Name shared by the documents
+        name: String,
+    },
+
+    /// Delta that does not fit its base.
+    ///
+    /// Occurs when a delta is applied to a document other than the one it
+    /// was made against, or its edit script is inconsistent.
+    #[error("Invalid delta: {message}")]
+    InvalidDelta {
+        /// Description of the mismatch
+        message: String,
+    },
+
+    /// Signature that is malformed or does not match the document.
+    ///
+    /// Occurs when a signed document was altered after signing, or was
+    /// signed with a key other than the trusted one.
+    #[error("Invalid signature: {message}")]
+    InvalidSignature {
+        /// Description of the problem
+        message: String,
+    },
+
+    /// Input that breaks a schema contract.
+    ///
+    /// Occurs when a compressor with a schema contract is given data whose
+    /// column names, types or nulls do not match it.
+    #[error("Input violates the schema contract: {}", join_violations(.violations))]
+    SchemaViolation {
+        /// Every violation found, in column order
+        violations: Vec<SchemaViolation>,
+    },
+
+    /// Schema contract that cannot be read.
+    #[error("Invalid schema contract: {message}")]
+    InvalidSchema {
+        /// Description of the problem
+        message: String,
+    },
+
+    /// Row predicate that cannot be read.
+    ///
+    /// Occurs when a predicate such as `status != "debug"` lacks a column
+    /// name or a comparison operator.
+    #[error("Invalid predicate: {message}")]
+    InvalidPredicate {
+        /// Description of the problem
+        message: String,
+    },
+
+    /// Regular expression that cannot be compiled.
+    ///
+    /// Occurs when a search pattern or a `~` predicate is not a valid
+    /// regular expression.
+    #[error("Invalid regular expression '{pattern}': {message}")]
+    InvalidPattern {
+        /// Pattern as given
+        pattern: String,
+        /// Description of the problem
+        message: String,
+    },
+
+    /// Time value that cannot be read.
+    ///
+    /// Occurs when partitioning by a time column meets a null or a value
+    /// that is neither a date, a timestamp nor an epoch time.
+    #[error("Unreadable time in column '{column}' at row {row}: {value}")]
+    InvalidTimestamp {
+        /// Name of the time column
+        column: String,
+        /// Row of the value, counting from 0
+        row: usize,
+        /// The value as found
+        value: String,
+    },
+
+    /// Row index past the end of a document.
+    ///
+    /// Occurs when editing a row that the document does not have.
+    #[error("Row {row} is out of range (document has {rows} rows)")]
+    InvalidRow {
+        /// Row asked for, counting from 0
+        row: usize,
+        /// Number of rows in the document
+        rows: usize,
+    },
+
+    /// Block index past the end of a document.
+    ///
+    /// Occurs when expanding a block that the document does not have.
+    #[error("Block {block} is out of range (document has {blocks} blocks)")]
+    InvalidBlock {
+        /// Block asked for, counting from 0
+        block: usize,
+        /// Number of blocks in the document
+        blocks: usize,
+    },
+
+    /// Table that cannot be pivoted or melted.
+    ///
+    /// Occurs when the columns a reshape would create clash with existing
+    /// ones, or two rows of a pivot fill the same cell.
+    #[error("Invalid reshape: {message}")]
+    InvalidReshape {
+        /// Description of the problem
+        message: String,
+    },
+
+    /// Foreign key that does not match its parent table.
+    ///
+    /// Occurs when a child column holds a value missing from the parent's
+    /// keys, or a row number past the end of the parent.
+    #[error("Invalid foreign key '{column}': {message}")]
+    InvalidForeignKey {
+        /// Name of the child column
+        column: String,
+        /// Description of the mismatch
+        message: String,
+    },
+
     /// I/O error.
     ///
     /// Wraps errors from standard I/O operations.
@@ -109,9 +282,78 @@ pub enum AlsError {
     IoError(#[from] std::io::Error),
 }
 
+fn join_violations(violations: &[SchemaViolation]) -> String {
+    violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+}
+
 /// Type alias for Results using `AlsError`.
 pub type Result<T> = std::result::Result<T, AlsError>;
 
+impl AlsError {
+    /// Line and column of a syntax error in the ALS text it came from.
+    ///
+    /// Returns `None` for errors that don't point into the input.
+    pub fn location(&self, input: &str) -> Option<SourceLocation> {
+        match self {
+            AlsError::AlsSyntaxError { position, .. } => Some(SourceLocation::new(input, *position)),
+            _ => None,
+        }
+    }
+}
+
+/// Line and column of a byte position in ALS text.
+///
+/// Positions in [`AlsError::AlsSyntaxError`] are byte offsets, which are
+/// hard to relate to the text once it holds multi-byte characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Column number (1-indexed), counted in characters
+    pub column: usize,
+}
+
+impl SourceLocation {
+    /// Locate the byte `position` in `input`.
+    ///
+    /// Combining marks share the column of the character they modify, so
+    /// `é` counts as one column whether or not it is precomposed. Positions
+    /// inside a character or past the end of the input are moved back to
+    /// the nearest character boundary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use als_compression::SourceLocation;
+    ///
+    /// let input = "#name\nJose\u{301} Zoe *";
+    /// let location = SourceLocation::new(input, input.len() - 1);
+    /// assert_eq!(location, SourceLocation { line: 2, column: 10 });
+    /// ```
+    pub fn new(input: &str, position: usize) -> Self {
+        let mut position = position.min(input.len());
+        while !input.is_char_boundary(position) {
+            position -= 1;
+        }
+        let before = &input[..position];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..]
+            .chars()
+            .filter(|&c| !unicode_normalization::char::is_combining_mark(c))
+            .count();
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: column + 1,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +382,23 @@ mod tests {
         assert!(display.contains("expected '>' but found '*'"));
     }
 
+    #[test]
+    fn test_syntax_error_location() {
+        // Precomposed and decomposed accents take one column each
+        let input = "#a\n\u{e9}\u{65}\u{301}\u{65e5} )";
+        let error = crate::AlsParser::new().parse(input).unwrap_err();
+        let location = error.location(input).unwrap();
+        assert_eq!(location, SourceLocation { line: 2, column: 6 });
+        assert_eq!(location.to_string(), "line 2, column 6");
+
+        // Positions inside a character move back to its start
+        assert_eq!(SourceLocation::new("\u{65e5}\u{672c}", 4), SourceLocation { line: 1, column: 2 });
+        assert_eq!(SourceLocation::new("ab", 99), SourceLocation { line: 1, column: 3 });
+
+        let error = AlsError::InvalidDictRef { index: 1, size: 0 };
+        assert!(error.location(input).is_none());
+    }
+
     #[test]
     fn test_invalid_dict_ref_display() {
         let error = AlsError::InvalidDictRef {
@@ -151,6 +410,25 @@ mod tests {
         assert!(display.contains("3 entries"));
     }
 
+    #[test]
+    fn test_invalid_column_ref_display() {
+        let error = AlsError::InvalidColumnRef {
+            index: 4,
+            columns: 2,
+        };
+        let display = format!("{}", error);
+        assert!(display.contains("@4"));
+        assert!(display.contains("2 preceding columns"));
+    }
+
+    #[test]
+    fn test_invalid_block_display() {
+        let error = AlsError::InvalidBlock { block: 5, blocks: 3 };
+        let display = format!("{}", error);
+        assert!(display.contains("Block 5"));
+        assert!(display.contains("3 blocks"));
+    }
+
     #[test]
     fn test_range_overflow_display() {
         let error = AlsError::RangeOverflow {
@@ -163,6 +441,27 @@ mod tests {
         assert!(display.contains("step 1"));
     }
 
+    #[test]
+    fn test_unknown_column_display() {
+        let error = AlsError::UnknownColumn {
+            name: "price".to_string(),
+        };
+        assert_eq!(format!("{}", error), "Unknown column: price");
+    }
+
+    #[test]
+    fn test_checksum_mismatch_display() {
+        let error = AlsError::ChecksumMismatch {
+            name: "users".to_string(),
+            expected: 0xcbf43926,
+            found: 0x1a,
+        };
+        assert_eq!(
+            format!("{}", error),
+            "Checksum mismatch in 'users': expected cbf43926, found 0000001a"
+        );
+    }
+
     #[test]
     fn test_version_mismatch_display() {
         let error = AlsError::VersionMismatch {
@@ -174,6 +473,14 @@ mod tests {
         assert!(display.contains("found 2"));
     }
 
+    #[test]
+    fn test_unsupported_feature_display() {
+        let error = AlsError::UnsupportedFeature {
+            feature: "crc".to_string(),
+        };
+        assert_eq!(format!("{}", error), "Unsupported feature: crc");
+    }
+
     #[test]
     fn test_column_mismatch_display() {
         let error = AlsError::ColumnMismatch {
@@ -3,19 +3,52 @@
 //! This module provides `AdaptiveMap`, which automatically selects the optimal
 //! hashmap implementation based on the expected dataset size:
 //!
+//! - For tiny datasets (opt-in via `AdaptiveMap::inline`): Scans a fixed-size
+//!   inline array, skipping hashing entirely
 //! - For small datasets (below threshold): Uses standard `HashMap` for lower overhead
 //! - For large datasets (above threshold): Uses `DashMap` for concurrent access performance
 //!
 //! The selection is transparent to the user and maintains consistent API behavior.
+//!
+//! # Switching Thresholds
+//!
+//! The variant is chosen when the map is created: a capacity below the
+//! threshold ([`DEFAULT_THRESHOLD`] unless given explicitly) yields `Small`,
+//! anything else yields `Large`. After that, a `Small` map is promoted to
+//! `Large` only when it is asked to grow in bulk — through
+//! [`reserve`](AdaptiveMap::reserve), [`Extend`] or [`FromIterator`] — to at
+//! least [`DEFAULT_THRESHOLD`] entries. Single inserts never switch variants,
+//! so the cost of an insert stays predictable in hot loops, and a `Large` map
+//! is never demoted.
+//!
+//! `Inline` maps are the exception: they hold at most [`INLINE_CAPACITY`]
+//! entries, and any insert that needs another slot moves them to `Small`.
+//! Linear scans beat hashing only for a handful of keys, which is exactly the
+//! shape of most per-column value counts.
 
+use std::borrow::Borrow;
+use std::collections::hash_map;
 use std::collections::HashMap;
 use std::hash::Hash;
-
+use std::iter::Flatten;
+use std::ops::{Deref, DerefMut};
+use std::{array, slice};
+
+use dashmap::iter::{Iter as DashIter, IterMut as DashIterMut, OwningIter};
+use dashmap::mapref::entry::Entry as DashEntry;
+use dashmap::mapref::multiple::{RefMulti, RefMutMulti};
+use dashmap::mapref::one::RefMut;
 use dashmap::DashMap;
 
 /// Default threshold for switching from HashMap to DashMap.
+///
+/// Maps created with a capacity below this use `HashMap`; bulk growth past it
+/// promotes a `HashMap`-backed map to `DashMap`.
 pub const DEFAULT_THRESHOLD: usize = 10_000;
 
+/// Number of entries an `Inline` map holds before it switches to HashMap.
+pub const INLINE_CAPACITY: usize = 8;
+
 /// An adaptive map that selects between HashMap and DashMap based on capacity.
 ///
 /// This enum provides a unified interface over two hashmap implementations:
@@ -92,6 +125,8 @@ pub enum AdaptiveMap<K, V>
 where
     K: Eq + Hash,
 {
+    /// Tiny variant storing entries inline and looking keys up by linear scan.
+    Inline(InlineMap<K, V>),
     /// Small dataset variant using standard HashMap.
     Small(HashMap<K, V>),
     /// Large dataset variant using concurrent DashMap.
@@ -109,6 +144,15 @@ where
         Self::Small(HashMap::new())
     }
 
+    /// Create a new empty AdaptiveMap with inline storage.
+    ///
+    /// Up to [`INLINE_CAPACITY`] entries are kept in a fixed-size array and
+    /// found by comparing keys, without hashing or allocating. The map moves
+    /// to the `Small` variant when it needs room for more.
+    pub fn inline() -> Self {
+        Self::Inline(InlineMap::new())
+    }
+
     /// Create a new AdaptiveMap with the given capacity, using the default threshold.
     ///
     /// If `capacity` is below `DEFAULT_THRESHOLD`, creates a `Small` variant.
@@ -134,6 +178,11 @@ where
         }
     }
 
+    /// Check if this map is using the Inline (array) variant.
+    pub fn is_inline(&self) -> bool {
+        matches!(self, Self::Inline(_))
+    }
+
     /// Check if this map is using the Small (HashMap) variant.
     pub fn is_small(&self) -> bool {
         matches!(self, Self::Small(_))
@@ -147,6 +196,7 @@ where
     /// Returns the number of entries in the map.
     pub fn len(&self) -> usize {
         match self {
+            Self::Inline(map) => map.len,
             Self::Small(map) => map.len(),
             Self::Large(map) => map.len(),
         }
@@ -154,116 +204,251 @@ where
 
     /// Returns true if the map contains no entries.
     pub fn is_empty(&self) -> bool {
-        match self {
-            Self::Small(map) => map.is_empty(),
-            Self::Large(map) => map.is_empty(),
-        }
+        self.len() == 0
     }
 
     /// Clears the map, removing all entries.
     pub fn clear(&mut self) {
         match self {
+            Self::Inline(map) => map.clear(),
             Self::Small(map) => map.clear(),
             Self::Large(map) => map.clear(),
         }
     }
-}
 
-impl<K, V> AdaptiveMap<K, V>
-where
-    K: Eq + Hash + Clone,
-    V: Clone,
-{
-    /// Insert a key-value pair into the map.
+    /// Returns the number of entries the map can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::Inline(_) => INLINE_CAPACITY,
+            Self::Small(map) => map.capacity(),
+            Self::Large(map) => map.capacity(),
+        }
+    }
+
+    /// Reserve capacity for at least `additional` more entries.
     ///
-    /// If the map already contained this key, the old value is returned.
-    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+    /// An `Inline` map that cannot hold the extra entries moves to `Small`,
+    /// and a `Small` map that would grow to [`DEFAULT_THRESHOLD`] entries or
+    /// more is promoted to `Large`.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len().saturating_add(additional);
+        if needed > INLINE_CAPACITY {
+            self.promote_inline(additional);
+        }
         match self {
-            Self::Small(map) => map.insert(key, value),
-            Self::Large(map) => map.insert(key, value),
+            Self::Inline(_) => {}
+            Self::Small(map) if needed >= DEFAULT_THRESHOLD => {
+                let large = DashMap::with_capacity(needed);
+                for (key, value) in std::mem::take(map) {
+                    large.insert(key, value);
+                }
+                *self = Self::Large(large);
+            }
+            Self::Small(map) => map.reserve(additional),
+            // DashMap grows its shards on demand and has no reserve.
+            Self::Large(_) => {}
         }
     }
 
-    /// Get a clone of the value associated with the key.
+    /// Shrink the capacity of the map as much as possible.
+    pub fn shrink_to_fit(&mut self) {
+        match self {
+            Self::Inline(_) => {}
+            Self::Small(map) => map.shrink_to_fit(),
+            Self::Large(map) => map.shrink_to_fit(),
+        }
+    }
+
+    /// Insert a key-value pair into the map.
     ///
-    /// Returns `None` if the key is not present.
-    pub fn get(&self, key: &K) -> Option<V> {
+    /// If the map already contained this key, the old value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.make_room_for(&key);
         match self {
-            Self::Small(map) => map.get(key).cloned(),
-            Self::Large(map) => map.get(key).map(|v| v.value().clone()),
+            Self::Inline(map) => map.insert(key, value),
+            Self::Small(map) => map.insert(key, value),
+            Self::Large(map) => map.insert(key, value),
         }
     }
 
     /// Check if the map contains the given key.
-    pub fn contains_key(&self, key: &K) -> bool {
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         match self {
+            Self::Inline(map) => map.position(key).is_some(),
             Self::Small(map) => map.contains_key(key),
             Self::Large(map) => map.contains_key(key),
         }
     }
 
     /// Remove a key from the map, returning the value if it was present.
-    pub fn remove(&mut self, key: &K) -> Option<V> {
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         match self {
+            Self::Inline(map) => map.remove(key),
             Self::Small(map) => map.remove(key),
             Self::Large(map) => map.remove(key).map(|(_, v)| v),
         }
     }
 
-    /// Get all keys in the map.
+    /// Get a mutable reference to the value associated with the key.
     ///
-    /// Note: For the Large variant, this requires iterating and cloning all keys.
-    pub fn keys(&self) -> Vec<K> {
+    /// For the Large variant the returned guard holds a shard lock; drop it
+    /// before touching the map again.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<ValueMut<'_, K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         match self {
-            Self::Small(map) => map.keys().cloned().collect(),
-            Self::Large(map) => map.iter().map(|entry| entry.key().clone()).collect(),
+            Self::Inline(map) => map
+                .position(key)
+                .map(|index| ValueMut::Small(map.value_mut(index))),
+            Self::Small(map) => map.get_mut(key).map(ValueMut::Small),
+            Self::Large(map) => map.get_mut(key).map(ValueMut::Large),
         }
     }
 
-    /// Get all values in the map.
+    /// Apply a function to the value associated with a key, if present.
     ///
-    /// Note: This requires cloning all values.
-    pub fn values(&self) -> Vec<V> {
+    /// Returns the result of the function, or `None` if the key is not present.
+    pub fn get_and_modify<Q, F, R>(&mut self, key: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.get_mut(key).map(|mut value| f(&mut value))
+    }
+
+    /// Get the entry for a key, for in-place insertion or update.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::hashmap::AdaptiveMap;
+    ///
+    /// let mut counts: AdaptiveMap<&str, usize> = AdaptiveMap::new();
+    /// for word in ["a", "b", "a"] {
+    ///     *counts.entry(word).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts.get("a"), Some(2));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        self.make_room_for(&key);
         match self {
-            Self::Small(map) => map.values().cloned().collect(),
-            Self::Large(map) => map.iter().map(|entry| entry.value().clone()).collect(),
+            Self::Inline(map) => {
+                let index = map.position(&key);
+                Entry::Inline(InlineEntry { map, key, index })
+            }
+            Self::Small(map) => Entry::Small(map.entry(key)),
+            Self::Large(map) => Entry::Large(map.entry(key)),
         }
     }
 
-    /// Get all key-value pairs in the map.
-    ///
-    /// Note: This requires cloning all entries.
-    pub fn entries(&self) -> Vec<(K, V)> {
+    /// Retain only the entries for which the predicate returns `true`.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
         match self {
-            Self::Small(map) => map.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
-            Self::Large(map) => map
-                .iter()
-                .map(|entry| (entry.key().clone(), entry.value().clone()))
-                .collect(),
+            Self::Inline(map) => map.retain(f),
+            Self::Small(map) => map.retain(f),
+            Self::Large(map) => map.retain(f),
         }
     }
 
-    /// Apply a function to the value associated with a key, if present.
+    /// Iterate over the entries of the map in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        match self {
+            Self::Inline(map) => Iter::Inline(map.slots[..map.len].iter()),
+            Self::Small(map) => Iter::Small(map.iter()),
+            Self::Large(map) => Iter::Large(map.iter()),
+        }
+    }
+
+    /// Iterate over the entries of the map with mutable access to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        match self {
+            Self::Inline(map) => IterMut::Inline(map.slots[..map.len].iter_mut()),
+            Self::Small(map) => IterMut::Small(map.iter_mut()),
+            Self::Large(map) => IterMut::Large(map.iter_mut()),
+        }
+    }
+
+    /// Move a full `Inline` map to `Small` if `key` would need a new slot.
+    fn make_room_for(&mut self, key: &K) {
+        if let Self::Inline(map) = self {
+            if map.len == INLINE_CAPACITY && map.position(key).is_none() {
+                self.promote_inline(1);
+            }
+        }
+    }
+
+    /// Move an `Inline` map's entries into a `HashMap` with room for `additional` more.
+    fn promote_inline(&mut self, additional: usize) {
+        if let Self::Inline(map) = self {
+            let mut small = HashMap::with_capacity(map.len.saturating_add(additional));
+            small.extend(map.slots[..map.len].iter_mut().filter_map(Option::take));
+            *self = Self::Small(small);
+        }
+    }
+}
+
+impl<K, V> AdaptiveMap<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Get a clone of the value associated with the key.
     ///
-    /// Returns the result of the function, or `None` if the key is not present.
-    pub fn get_and_modify<F, R>(&mut self, key: &K, f: F) -> Option<R>
+    /// Returns `None` if the key is not present.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
     where
-        F: FnOnce(&mut V) -> R,
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
     {
         match self {
-            Self::Small(map) => map.get_mut(key).map(f),
-            Self::Large(map) => map.get_mut(key).map(|mut entry| f(entry.value_mut())),
+            Self::Inline(map) => map.position(key).map(|index| map.value(index).clone()),
+            Self::Small(map) => map.get(key).cloned(),
+            Self::Large(map) => map.get(key).map(|v| v.value().clone()),
         }
     }
 
+    /// Get all keys in the map.
+    ///
+    /// Note: For the Large variant, this requires iterating and cloning all keys.
+    pub fn keys(&self) -> Vec<K> {
+        self.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    /// Get all values in the map.
+    ///
+    /// Note: This requires cloning all values.
+    pub fn values(&self) -> Vec<V> {
+        self.iter().map(|entry| entry.value().clone()).collect()
+    }
+
+    /// Get all key-value pairs in the map.
+    ///
+    /// Note: This requires cloning all entries.
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
     /// Insert a key-value pair if the key is not already present.
     ///
     /// Returns a clone of the value (either existing or newly inserted).
     pub fn entry_or_insert(&mut self, key: K, default: V) -> V {
-        match self {
-            Self::Small(map) => map.entry(key).or_insert(default).clone(),
-            Self::Large(map) => map.entry(key).or_insert(default).clone(),
-        }
+        self.entry(key).or_insert(default).clone()
     }
 
     /// Insert a key-value pair if the key is not already present, using a function to create the default.
@@ -273,10 +458,7 @@ where
     where
         F: FnOnce() -> V,
     {
-        match self {
-            Self::Small(map) => map.entry(key).or_insert_with(default).clone(),
-            Self::Large(map) => map.entry(key).or_insert_with(default).clone(),
-        }
+        self.entry(key).or_insert_with(default).clone()
     }
 }
 
@@ -296,6 +478,7 @@ where
 {
     fn clone(&self) -> Self {
         match self {
+            Self::Inline(map) => Self::Inline(map.clone()),
             Self::Small(map) => Self::Small(map.clone()),
             Self::Large(map) => {
                 let new_map = DashMap::with_capacity(map.len());
@@ -308,22 +491,399 @@ where
     }
 }
 
-// Note: AdaptiveMap is Send + Sync only when using the Large variant,
-// or when the Small variant is not accessed concurrently.
-// The Large (DashMap) variant is inherently thread-safe.
-// The Small (HashMap) variant requires external synchronization.
-unsafe impl<K, V> Send for AdaptiveMap<K, V>
+impl<K, V> FromIterator<(K, V)> for AdaptiveMap<K, V>
 where
-    K: Eq + Hash + Send,
-    V: Send,
+    K: Eq + Hash,
 {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
 }
 
-unsafe impl<K, V> Sync for AdaptiveMap<K, V>
+impl<K, V> Extend<(K, V)> for AdaptiveMap<K, V>
 where
-    K: Eq + Hash + Send + Sync,
-    V: Send + Sync,
+    K: Eq + Hash,
 {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+        // Iterators without a useful size hint still promote once the
+        // collected map has crossed the threshold.
+        self.reserve(0);
+    }
+}
+
+impl<K, V> IntoIterator for AdaptiveMap<K, V>
+where
+    K: Eq + Hash,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Self::Inline(map) => IntoIter::Inline(map.slots.into_iter().flatten()),
+            Self::Small(map) => IntoIter::Small(map.into_iter()),
+            Self::Large(map) => IntoIter::Large(map.into_iter()),
+        }
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a AdaptiveMap<K, V>
+where
+    K: Eq + Hash,
+{
+    type Item = EntryRef<'a, K, V>;
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Fixed-size entry storage backing the `Inline` variant of [`AdaptiveMap`].
+///
+/// Occupied slots are kept packed at the front of the array.
+#[derive(Debug, Clone)]
+pub struct InlineMap<K, V> {
+    slots: [Option<(K, V)>; INLINE_CAPACITY],
+    len: usize,
+}
+
+impl<K: Eq, V> InlineMap<K, V> {
+    fn new() -> Self {
+        Self {
+            slots: std::array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.slots[..self.len]
+            .iter()
+            .position(|slot| matches!(slot, Some((k, _)) if k.borrow() == key))
+    }
+
+    fn value(&self, index: usize) -> &V {
+        &self.slots[index].as_ref().expect("occupied inline slot").1
+    }
+
+    fn value_mut(&mut self, index: usize) -> &mut V {
+        &mut self.slots[index].as_mut().expect("occupied inline slot").1
+    }
+
+    /// Append a new entry; the caller guarantees a free slot.
+    fn push(&mut self, key: K, value: V) -> &mut V {
+        let index = self.len;
+        self.slots[index] = Some((key, value));
+        self.len += 1;
+        self.value_mut(index)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.position(&key) {
+            Some(index) => Some(std::mem::replace(self.value_mut(index), value)),
+            None => {
+                self.push(key, value);
+                None
+            }
+        }
+    }
+
+    /// Take the entry at `index`, moving the last entry into its slot.
+    fn swap_remove(&mut self, index: usize) -> Option<(K, V)> {
+        self.len -= 1;
+        self.slots.swap(index, self.len);
+        self.slots[self.len].take()
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let index = self.position(key)?;
+        self.swap_remove(index).map(|(_, value)| value)
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let mut index = 0;
+        while index < self.len {
+            let keep = match &mut self.slots[index] {
+                Some((key, value)) => f(key, value),
+                None => false,
+            };
+            if keep {
+                index += 1;
+            } else {
+                self.swap_remove(index);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.slots[..self.len].iter_mut().for_each(|slot| *slot = None);
+        self.len = 0;
+    }
+}
+
+/// A view into a single entry of an [`AdaptiveMap`], vacant or occupied.
+///
+/// Returned by [`AdaptiveMap::entry`]. For the Large variant the entry holds
+/// a shard lock until it (or the value guard it returns) is dropped.
+pub enum Entry<'a, K, V> {
+    /// Entry of the Inline (array) variant.
+    Inline(InlineEntry<'a, K, V>),
+    /// Entry of the Small (HashMap) variant.
+    Small(hash_map::Entry<'a, K, V>),
+    /// Entry of the Large (DashMap) variant.
+    Large(DashEntry<'a, K, V>),
+}
+
+/// Entry of the Inline variant; a free slot is guaranteed when vacant.
+pub struct InlineEntry<'a, K, V> {
+    map: &'a mut InlineMap<K, V>,
+    key: K,
+    index: Option<usize>,
+}
+
+impl<'a, K, V> Entry<'a, K, V>
+where
+    K: Eq + Hash,
+{
+    /// The key this entry was created for.
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Inline(entry) => &entry.key,
+            Self::Small(entry) => entry.key(),
+            Self::Large(entry) => entry.key(),
+        }
+    }
+
+    /// Apply a function to the value if the entry is occupied.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Self::Inline(entry) => {
+                if let Some(index) = entry.index {
+                    f(entry.map.value_mut(index));
+                }
+                Self::Inline(entry)
+            }
+            Self::Small(entry) => Self::Small(entry.and_modify(f)),
+            Self::Large(entry) => Self::Large(entry.and_modify(f)),
+        }
+    }
+
+    /// Insert `default` if the entry is vacant and return the value.
+    pub fn or_insert(self, default: V) -> ValueMut<'a, K, V> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Insert the result of `default` if the entry is vacant and return the value.
+    pub fn or_insert_with<F>(self, default: F) -> ValueMut<'a, K, V>
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Self::Inline(entry) => match entry.index {
+                Some(index) => ValueMut::Small(entry.map.value_mut(index)),
+                None => ValueMut::Small(entry.map.push(entry.key, default())),
+            },
+            Self::Small(entry) => ValueMut::Small(entry.or_insert_with(default)),
+            Self::Large(entry) => ValueMut::Large(entry.or_insert_with(default)),
+        }
+    }
+
+    /// Insert `V::default()` if the entry is vacant and return the value.
+    pub fn or_default(self) -> ValueMut<'a, K, V>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+/// Mutable access to a value stored in an [`AdaptiveMap`].
+///
+/// Dereferences to the value. For the Large variant this is a DashMap guard
+/// holding the shard's write lock.
+pub enum ValueMut<'a, K, V> {
+    /// Value in the Inline or Small variant.
+    Small(&'a mut V),
+    /// Value in the Large (DashMap) variant.
+    Large(RefMut<'a, K, V>),
+}
+
+impl<K: Eq + Hash, V> Deref for ValueMut<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        match self {
+            Self::Small(value) => value,
+            Self::Large(value) => value.value(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> DerefMut for ValueMut<'_, K, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        match self {
+            Self::Small(value) => value,
+            Self::Large(value) => value.value_mut(),
+        }
+    }
+}
+
+/// A borrowed key-value pair yielded by [`AdaptiveMap::iter`].
+pub enum EntryRef<'a, K, V> {
+    /// Entry of the Inline or Small variant.
+    Small(&'a K, &'a V),
+    /// Entry of the Large (DashMap) variant.
+    Large(RefMulti<'a, K, V>),
+}
+
+impl<K: Eq + Hash, V> EntryRef<'_, K, V> {
+    /// The entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Small(key, _) => key,
+            Self::Large(entry) => entry.key(),
+        }
+    }
+
+    /// The entry's value.
+    pub fn value(&self) -> &V {
+        match self {
+            Self::Small(_, value) => value,
+            Self::Large(entry) => entry.value(),
+        }
+    }
+
+    /// The entry as a `(key, value)` pair.
+    pub fn pair(&self) -> (&K, &V) {
+        (self.key(), self.value())
+    }
+}
+
+/// A key-value pair with a mutable value, yielded by [`AdaptiveMap::iter_mut`].
+pub enum EntryMut<'a, K, V> {
+    /// Entry of the Inline or Small variant.
+    Small(&'a K, &'a mut V),
+    /// Entry of the Large (DashMap) variant.
+    Large(RefMutMulti<'a, K, V>),
+}
+
+impl<K: Eq + Hash, V> EntryMut<'_, K, V> {
+    /// The entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Small(key, _) => key,
+            Self::Large(entry) => entry.key(),
+        }
+    }
+
+    /// The entry's value.
+    pub fn value(&self) -> &V {
+        match self {
+            Self::Small(_, value) => value,
+            Self::Large(entry) => entry.value(),
+        }
+    }
+
+    /// Mutable access to the entry's value.
+    pub fn value_mut(&mut self) -> &mut V {
+        match self {
+            Self::Small(_, value) => value,
+            Self::Large(entry) => entry.value_mut(),
+        }
+    }
+}
+
+/// Iterator over the entries of an [`AdaptiveMap`].
+pub enum Iter<'a, K, V> {
+    /// Iterator over the Inline (array) variant.
+    Inline(slice::Iter<'a, Option<(K, V)>>),
+    /// Iterator over the Small (HashMap) variant.
+    Small(hash_map::Iter<'a, K, V>),
+    /// Iterator over the Large (DashMap) variant.
+    Large(DashIter<'a, K, V>),
+}
+
+impl<'a, K: Eq + Hash, V> Iterator for Iter<'a, K, V> {
+    type Item = EntryRef<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Inline(iter) => iter
+                .next()
+                .and_then(Option::as_ref)
+                .map(|(k, v)| EntryRef::Small(k, v)),
+            Self::Small(iter) => iter.next().map(|(k, v)| EntryRef::Small(k, v)),
+            Self::Large(iter) => iter.next().map(EntryRef::Large),
+        }
+    }
+}
+
+/// Mutable iterator over the entries of an [`AdaptiveMap`].
+pub enum IterMut<'a, K, V> {
+    /// Iterator over the Inline (array) variant.
+    Inline(slice::IterMut<'a, Option<(K, V)>>),
+    /// Iterator over the Small (HashMap) variant.
+    Small(hash_map::IterMut<'a, K, V>),
+    /// Iterator over the Large (DashMap) variant.
+    Large(DashIterMut<'a, K, V>),
+}
+
+impl<'a, K: Eq + Hash, V> Iterator for IterMut<'a, K, V> {
+    type Item = EntryMut<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Inline(iter) => iter
+                .next()
+                .and_then(Option::as_mut)
+                .map(|(k, v)| EntryMut::Small(&*k, v)),
+            Self::Small(iter) => iter.next().map(|(k, v)| EntryMut::Small(k, v)),
+            Self::Large(iter) => iter.next().map(EntryMut::Large),
+        }
+    }
+}
+
+/// Owning iterator over the entries of an [`AdaptiveMap`].
+pub enum IntoIter<K, V> {
+    /// Iterator over the Inline (array) variant.
+    Inline(Flatten<array::IntoIter<Option<(K, V)>, INLINE_CAPACITY>>),
+    /// Iterator over the Small (HashMap) variant.
+    Small(hash_map::IntoIter<K, V>),
+    /// Iterator over the Large (DashMap) variant.
+    Large(OwningIter<K, V>),
+}
+
+impl<K: Eq + Hash, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Inline(iter) => iter.next(),
+            Self::Small(iter) => iter.next(),
+            Self::Large(iter) => iter.next(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -627,4 +1187,194 @@ mod tests {
         map.clear();
         assert!(map.is_empty());
     }
+
+    #[test]
+    fn test_entry_counts_in_both_variants() {
+        for mut map in [
+            AdaptiveMap::<String, usize>::with_capacity_threshold(10, 100),
+            AdaptiveMap::<String, usize>::with_capacity_threshold(200, 100),
+        ] {
+            for word in ["a", "b", "a", "c", "a"] {
+                *map.entry(word.to_string()).or_insert(0) += 1;
+            }
+            assert_eq!(map.get("a"), Some(3));
+            assert_eq!(map.get("b"), Some(1));
+
+            map.entry("b".to_string()).and_modify(|v| *v *= 10).or_default();
+            *map.entry("d".to_string()).or_default() += 4;
+            assert_eq!(map.get("b"), Some(10));
+            assert_eq!(map.get("d"), Some(4));
+            assert_eq!(map.entry("z".to_string()).key(), "z");
+        }
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut map: AdaptiveMap<String, i32> = AdaptiveMap::with_capacity(20_000);
+        map.insert("x".to_string(), 1);
+        *map.get_mut("x").unwrap() += 5;
+        assert_eq!(map.get("x"), Some(6));
+        assert!(map.get_mut("missing").is_none());
+    }
+
+    #[test]
+    fn test_retain() {
+        for mut map in [
+            AdaptiveMap::<i32, i32>::with_capacity_threshold(10, 100),
+            AdaptiveMap::<i32, i32>::with_capacity_threshold(200, 100),
+        ] {
+            for i in 0..10 {
+                map.insert(i, i * 2);
+            }
+            map.retain(|k, v| {
+                *v += 1;
+                k % 2 == 0
+            });
+            assert_eq!(map.len(), 5);
+            assert_eq!(map.get(&4), Some(9));
+            assert!(!map.contains_key(&3));
+        }
+    }
+
+    #[test]
+    fn test_iter_and_iter_mut() {
+        for mut map in [
+            AdaptiveMap::<i32, i32>::with_capacity_threshold(10, 100),
+            AdaptiveMap::<i32, i32>::with_capacity_threshold(200, 100),
+        ] {
+            map.extend((1..=4).map(|i| (i, i)));
+            for mut entry in map.iter_mut() {
+                let key = *entry.key();
+                *entry.value_mut() *= key;
+            }
+            let sum: i32 = map.iter().map(|entry| *entry.value()).sum();
+            assert_eq!(sum, 1 + 4 + 9 + 16);
+            assert_eq!((&map).into_iter().count(), 4);
+
+            let mut owned: Vec<(i32, i32)> = map.into_iter().collect();
+            owned.sort();
+            assert_eq!(owned, vec![(1, 1), (2, 4), (3, 9), (4, 16)]);
+        }
+    }
+
+    #[test]
+    fn test_from_iterator_and_extend() {
+        let map: AdaptiveMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        assert!(map.is_small());
+        assert_eq!(map.len(), 10);
+
+        let map: AdaptiveMap<usize, usize> = (0..DEFAULT_THRESHOLD).map(|i| (i, i)).collect();
+        assert!(map.is_large());
+        assert_eq!(map.len(), DEFAULT_THRESHOLD);
+
+        // No size hint: promotion happens once the threshold is crossed.
+        let mut map: AdaptiveMap<usize, usize> = AdaptiveMap::new();
+        map.extend((0..DEFAULT_THRESHOLD).filter(|_| true).map(|i| (i, i)));
+        assert!(map.is_large());
+    }
+
+    #[test]
+    fn test_reserve_promotes_and_keeps_entries() {
+        let mut map: AdaptiveMap<String, i32> = AdaptiveMap::new();
+        map.insert("kept".to_string(), 7);
+        map.reserve(16);
+        assert!(map.is_small());
+        assert!(map.capacity() >= 17);
+
+        map.reserve(DEFAULT_THRESHOLD);
+        assert!(map.is_large());
+        assert_eq!(map.get("kept"), Some(7));
+
+        map.shrink_to_fit();
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_never_switches_variant() {
+        let mut map: AdaptiveMap<usize, usize> = AdaptiveMap::with_capacity_threshold(0, 1);
+        assert!(map.is_small());
+        for i in 0..100 {
+            map.insert(i, i);
+        }
+        assert!(map.is_small());
+    }
+
+    #[test]
+    fn test_non_clone_values() {
+        struct Counter(usize);
+
+        let mut map: AdaptiveMap<&str, Counter> = AdaptiveMap::new();
+        map.entry("a").or_insert_with(|| Counter(0)).0 += 1;
+        map.entry("a").or_insert_with(|| Counter(0)).0 += 1;
+        assert_eq!(map.get_mut("a").map(|c| c.0), Some(2));
+        assert!(map.remove("a").is_some());
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_inline_basic_operations() {
+        let mut map: AdaptiveMap<String, i32> = AdaptiveMap::inline();
+        assert!(map.is_inline());
+        assert_eq!(map.capacity(), INLINE_CAPACITY);
+
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("b".to_string(), 2), None);
+        assert_eq!(map.insert("a".to_string(), 3), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("a"), Some(3));
+        assert!(map.contains_key("b"));
+
+        assert_eq!(map.remove("a"), Some(3));
+        assert_eq!(map.remove("a"), None);
+        assert_eq!(map.keys(), vec!["b".to_string()]);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert!(map.is_inline());
+    }
+
+    #[test]
+    fn test_inline_promotes_when_full() {
+        let mut map: AdaptiveMap<usize, usize> = AdaptiveMap::inline();
+        for i in 0..INLINE_CAPACITY {
+            *map.entry(i).or_insert(0) += i;
+        }
+        assert!(map.is_inline());
+
+        // Updating an existing key never needs a new slot.
+        map.insert(0, 100);
+        *map.entry(1).or_default() += 1;
+        assert!(map.is_inline());
+
+        *map.entry(INLINE_CAPACITY).or_default() += 1;
+        assert!(map.is_small());
+        assert_eq!(map.len(), INLINE_CAPACITY + 1);
+        assert_eq!(map.get(&0), Some(100));
+        assert_eq!(map.get(&1), Some(2));
+
+        let mut map: AdaptiveMap<usize, usize> = AdaptiveMap::inline();
+        map.extend((0..INLINE_CAPACITY + 1).map(|i| (i, i)));
+        assert!(map.is_small());
+    }
+
+    #[test]
+    fn test_inline_retain_and_iterators() {
+        let mut map: AdaptiveMap<i32, i32> = AdaptiveMap::inline();
+        map.extend((1..=6).map(|i| (i, i)));
+        map.retain(|k, _| k % 3 != 0);
+        assert_eq!(map.len(), 4);
+
+        for mut entry in map.iter_mut() {
+            *entry.value_mut() *= 10;
+        }
+        let mut pairs: Vec<(i32, i32)> = map.iter().map(|e| (*e.key(), *e.value())).collect();
+        pairs.sort();
+        assert_eq!(pairs, vec![(1, 10), (2, 20), (4, 40), (5, 50)]);
+
+        let clone = map.clone();
+        assert!(clone.is_inline());
+        let mut owned: Vec<(i32, i32)> = clone.into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, pairs);
+    }
 }
@@ -3,7 +3,12 @@
 //! This module provides an `AdaptiveMap` that automatically selects between
 //! a standard `HashMap` and a concurrent `DashMap` based on the expected
 //! dataset size, providing optimal performance for both small and large datasets.
+//! An opt-in inline mode covers the very common case of a few distinct keys
+//! without hashing at all.
 
 mod adaptive;
 
-pub use adaptive::AdaptiveMap;
+pub use adaptive::{
+    AdaptiveMap, Entry, EntryMut, EntryRef, InlineEntry, InlineMap, IntoIter, Iter, IterMut, ValueMut,
+    DEFAULT_THRESHOLD, INLINE_CAPACITY,
+};
@@ -0,0 +1,245 @@
+//! Exchanging ALS over HTTP.
+//!
+//! Services sending tables to each other agree on a format through the
+//! `Content-Type` and `Accept` headers. This module gives ALS its media type,
+//! `application/vnd.als`, picks a response format from an `Accept` header
+//! and encodes or decodes request and response bodies as [`Bytes`], so every
+//! service speaks the same dialect whatever HTTP stack it runs on.
+//!
+//! # Examples
+//!
+//! ```
+//! use als_compression::convert::csv::parse_csv;
+//! use als_compression::http::{decode, encode, negotiate, MediaType};
+//!
+//! let data = parse_csv("id,level\n1,info\n2,warn\n3,info").unwrap();
+//!
+//! // Prefer ALS, but fall back to JSON for clients that do not know it
+//! let accept = "application/json;q=0.5, application/vnd.als";
+//! let media_type = negotiate(accept, &MediaType::ALL).unwrap();
+//! assert_eq!(media_type, MediaType::Als);
+//!
+//! let body = encode(&data, media_type).unwrap();
+//! let content_type = media_type.mime_type();
+//!
+//! let received = decode(&body, MediaType::from_content_type(content_type).unwrap()).unwrap();
+//! assert_eq!(received.row_count, 3);
+//! ```
+
+use std::io;
+
+use bytes::Bytes;
+
+use crate::als::{AlsParser, AlsSerializer, StoreFrame};
+use crate::compress::AlsCompressor;
+use crate::convert::csv::{parse_csv, to_csv};
+use crate::convert::json::{parse_json, to_json};
+use crate::convert::TabularData;
+use crate::error::{AlsError, Result};
+
+/// Media type of ALS documents.
+pub const ALS_MIME_TYPE: &str = "application/vnd.als";
+
+/// Media type of CSV text.
+pub const CSV_MIME_TYPE: &str = "text/csv";
+
+/// Media type of JSON text.
+pub const JSON_MIME_TYPE: &str = "application/json";
+
+/// A format a table can be exchanged in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaType {
+    /// An ALS document.
+    Als,
+    /// CSV text.
+    Csv,
+    /// JSON text, as an array of row objects.
+    Json,
+}
+
+impl MediaType {
+    /// All media types, ALS first.
+    pub const ALL: [MediaType; 3] = [MediaType::Als, MediaType::Csv, MediaType::Json];
+
+    /// Get the MIME type, as sent in a `Content-Type` header.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            MediaType::Als => ALS_MIME_TYPE,
+            MediaType::Csv => CSV_MIME_TYPE,
+            MediaType::Json => JSON_MIME_TYPE,
+        }
+    }
+
+    /// Look up the media type of a `Content-Type` header.
+    ///
+    /// Parameters such as `charset` are ignored, and the type is matched
+    /// case-insensitively.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type.split(';').next().unwrap_or("").trim();
+        MediaType::ALL
+            .into_iter()
+            .find(|media_type| media_type.mime_type().eq_ignore_ascii_case(essence))
+    }
+}
+
+/// Choose the media type to answer a request with.
+///
+/// Each of `available`, in order of preference, is given the quality of
+/// the most specific range of `accept` matching it, so `text/*;q=0.2,
+/// text/csv` accepts CSV fully. The best quality wins and ties go to the
+/// earlier of `available`. A missing or empty `Accept` header accepts
+/// anything.
+///
+/// Returns `None` if the client accepts none of `available`, in which case
+/// a server would answer `406 Not Acceptable`.
+pub fn negotiate(accept: &str, available: &[MediaType]) -> Option<MediaType> {
+    if accept.trim().is_empty() {
+        return available.first().copied();
+    }
+
+    let ranges: Vec<MediaRange> = accept.split(',').filter_map(MediaRange::parse).collect();
+    let mut best: Option<(MediaType, f32)> = None;
+    for &media_type in available {
+        let quality = ranges
+            .iter()
+            .filter_map(|range| range.specificity(media_type.mime_type()).map(|specificity| (specificity, range.quality)))
+            .max_by_key(|&(specificity, _)| specificity)
+            .map_or(0.0, |(_, quality)| quality);
+        if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((media_type, quality));
+        }
+    }
+    best.map(|(media_type, _)| media_type)
+}
+
+/// Encode a table as a body of the given media type.
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be compressed or converted.
+pub fn encode(data: &TabularData, media_type: MediaType) -> Result<Bytes> {
+    let text = match media_type {
+        MediaType::Als => AlsSerializer::new().serialize(&AlsCompressor::new().compress(data)?),
+        MediaType::Csv => to_csv(data)?,
+        MediaType::Json => to_json(data)?,
+    };
+    Ok(Bytes::from(text))
+}
+
+/// Decode a body of the given media type into a table.
+///
+/// ALS bodies may hold a document or a store frame. Values of ALS
+/// documents are typed as when converting ALS to JSON.
+///
+/// # Errors
+///
+/// Returns `AlsError::IoError` if the body is not UTF-8, and a parse error
+/// if it is not valid in its media type.
+pub fn decode(body: &Bytes, media_type: MediaType) -> Result<TabularData<'static>> {
+    let text = std::str::from_utf8(body).map_err(|e| AlsError::IoError(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+    match media_type {
+        MediaType::Als => {
+            if let Some(frame) = StoreFrame::parse(text)? {
+                return frame.to_tabular();
+            }
+            let parser = AlsParser::new();
+            let doc = parser.parse(text)?;
+            let columns = parser.expand_columns(&doc)?;
+            Ok(AlsParser::columns_to_tabular(&doc, columns, true))
+        }
+        MediaType::Csv => parse_csv(text),
+        MediaType::Json => parse_json(text),
+    }
+}
+
+/// One media range of an `Accept` header, such as `text/*;q=0.5`.
+struct MediaRange<'a> {
+    range: &'a str,
+    quality: f32,
+}
+
+impl<'a> MediaRange<'a> {
+    /// Parse a media range, skipping ranges with an unreadable quality.
+    fn parse(text: &'a str) -> Option<Self> {
+        let mut parts = text.split(';');
+        let range = parts.next()?.trim();
+        if range.is_empty() {
+            return None;
+        }
+        let mut quality = 1.0;
+        for param in parts {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+            if name.trim().eq_ignore_ascii_case("q") {
+                quality = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+            }
+        }
+        Some(Self { range, quality })
+    }
+
+    /// Check how specifically the range matches a MIME type: 2 for the type
+    /// itself, 1 for `type/*` and 0 for `*/*`.
+    fn specificity(&self, mime_type: &str) -> Option<u8> {
+        if self.range == "*/*" {
+            return Some(0);
+        }
+        if self.range.eq_ignore_ascii_case(mime_type) {
+            return Some(2);
+        }
+        let (kind, _) = mime_type.split_once('/')?;
+        let (range_kind, range_subtype) = self.range.split_once('/')?;
+        (range_subtype == "*" && range_kind.eq_ignore_ascii_case(kind)).then_some(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_content_type() {
+        assert_eq!(MediaType::from_content_type("application/vnd.als"), Some(MediaType::Als));
+        assert_eq!(MediaType::from_content_type("Text/CSV; charset=utf-8"), Some(MediaType::Csv));
+        assert_eq!(MediaType::from_content_type("application/xml"), None);
+        for media_type in MediaType::ALL {
+            assert_eq!(MediaType::from_content_type(media_type.mime_type()), Some(media_type));
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let all = &MediaType::ALL;
+        assert_eq!(negotiate("", all), Some(MediaType::Als));
+        assert_eq!(negotiate("*/*", all), Some(MediaType::Als));
+        assert_eq!(negotiate("application/json", all), Some(MediaType::Json));
+        assert_eq!(negotiate("text/*, application/json;q=0.9", all), Some(MediaType::Csv));
+        assert_eq!(negotiate("*/*;q=0.1, application/vnd.als;q=0", all), Some(MediaType::Csv));
+        assert_eq!(negotiate("text/*;q=0.2, text/csv, */*;q=0.5", all), Some(MediaType::Csv));
+        assert_eq!(negotiate("application/xml", all), None);
+        assert_eq!(negotiate("application/json;q=oops, text/csv;q=0.3", all), Some(MediaType::Csv));
+        assert_eq!(negotiate("*/*", &[MediaType::Json, MediaType::Als]), Some(MediaType::Json));
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        // JSON objects list their keys in order, so keep the columns sorted
+        let data = parse_csv("active,id,name\ntrue,1,ann\nfalse,2,bob\ntrue,3,").unwrap();
+        for media_type in MediaType::ALL {
+            let body = encode(&data, media_type).unwrap();
+            let decoded = decode(&body, media_type).unwrap();
+            assert_eq!(decoded.row_count, 3, "{:?}", media_type);
+            assert_eq!(to_csv(&decoded).unwrap(), to_csv(&data).unwrap(), "{:?}", media_type);
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let body = Bytes::from_static(b"\xff\xfe");
+        assert!(matches!(decode(&body, MediaType::Csv), Err(AlsError::IoError(_))));
+        assert!(decode(&Bytes::from_static(b"[1, 2"), MediaType::Json).is_err());
+
+        let frame = Bytes::from_static(b"!store csv\nid\n1\n2");
+        assert_eq!(decode(&frame, MediaType::Als).unwrap().row_count, 2);
+    }
+}
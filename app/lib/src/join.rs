@@ -0,0 +1,181 @@
+//! Joining ALS documents on a key column.
+//!
+//! Dimension tables are often archived apart from the facts that refer to
+//! them. [`join`] links two documents back together with a hash join: only
+//! the key columns are expanded to find matching rows, and every other
+//! column is then expanded one at a time and only if some rows match, so
+//! joining a large document keeps at most one of its columns in memory.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::als::parser::{is_boolean_stream, typed_value};
+use crate::als::{AlsDocument, AlsParser, NULL_TOKEN};
+use crate::compress::AlsCompressor;
+use crate::convert::{Column, TabularData};
+use crate::error::{AlsError, Result};
+
+/// Suffix given to columns of the right document whose name the left
+/// document already uses.
+pub const RIGHT_SUFFIX: &str = "_right";
+
+/// Join the rows of two documents with equal values in the column `on`.
+///
+/// This is an inner join: each row of `left` is paired with each row of
+/// `right` having the same key, in the order of `left` and then `right`,
+/// and rows without a match are dropped. Keys are compared in their ALS
+/// string form, and null keys never match. The result has the columns of
+/// `left` followed by those of `right` other than `on`, renamed with
+/// [`RIGHT_SUFFIX`] if `left` has a column of the same name, and is
+/// compressed with `compressor`.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::{join, AlsCompressor, AlsParser};
+///
+/// let parser = AlsParser::new();
+/// let orders = parser.parse("#order #customer\n1>4|7 9 7 8").unwrap();
+/// let customers = parser.parse("#customer #name\n7>8|ann bob").unwrap();
+///
+/// let compressor = AlsCompressor::new();
+/// let joined = join(&orders, &customers, "customer", &compressor).unwrap();
+/// assert_eq!(joined.schema, ["order", "customer", "name"]);
+/// assert_eq!(parser.expand(&joined).unwrap()[1], ["3", "7", "ann"]);
+/// ```
+///
+/// # Errors
+///
+/// Returns `AlsError::UnknownColumn` if either document lacks the column
+/// `on`, or an error if a column cannot be expanded or the result cannot
+/// be compressed.
+pub fn join(left: &AlsDocument, right: &AlsDocument, on: &str, compressor: &AlsCompressor) -> Result<AlsDocument<'static>> {
+    let left_key = key_position(left, on)?;
+    let right_key = key_position(right, on)?;
+
+    let mut right_rows: HashMap<String, Vec<usize>> = HashMap::new();
+    for (row, key) in expand_column(right, right_key)?.into_iter().enumerate() {
+        if key != NULL_TOKEN {
+            right_rows.entry(key).or_default().push(row);
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for (row, key) in expand_column(left, left_key)?.iter().enumerate() {
+        if let Some(matches) = right_rows.get(key) {
+            pairs.extend(matches.iter().map(|&right_row| (row, right_row)));
+        }
+    }
+
+    let mut data = TabularData::with_capacity(left.schema.len() + right.schema.len() - 1);
+    for position in 0..left.schema.len() {
+        let rows = pairs.iter().map(|&(row, _)| row);
+        data.add_column(gather(left, position, Cow::Owned(left.schema[position].clone()), rows)?);
+    }
+    for position in (0..right.schema.len()).filter(|&position| position != right_key) {
+        let name = &right.schema[position];
+        let name = if left.schema.contains(name) {
+            format!("{}{}", name, RIGHT_SUFFIX)
+        } else {
+            name.clone()
+        };
+        let rows = pairs.iter().map(|&(_, row)| row);
+        data.add_column(gather(right, position, Cow::Owned(name), rows)?);
+    }
+    compressor.compress(&data)
+}
+
+/// Get the position of the key column of a document.
+fn key_position(doc: &AlsDocument, on: &str) -> Result<usize> {
+    doc.schema.iter().position(|name| name == on).ok_or_else(|| AlsError::UnknownColumn {
+        name: on.to_string(),
+    })
+}
+
+/// Expand the column at `position`, along with the columns it refers to.
+fn expand_column(doc: &AlsDocument, position: usize) -> Result<Vec<String>> {
+    let stream = &doc.streams[position];
+    if stream.has_column_refs() {
+        return Ok(AlsParser::new().expand_columns(doc)?.swap_remove(position));
+    }
+    stream.expand(doc.default_dictionary().map(|d| d.as_slice()))
+}
+
+/// Build a column from the given rows of the column at `position`,
+/// expanding it only if there are any.
+fn gather(
+    doc: &AlsDocument,
+    position: usize,
+    name: Cow<'static, str>,
+    rows: impl ExactSizeIterator<Item = usize>,
+) -> Result<Column<'static>> {
+    if rows.len() == 0 {
+        return Ok(Column::new(name, Vec::new()));
+    }
+    let boolean_column = is_boolean_stream(&doc.streams[position]);
+    let values = expand_column(doc, position)?;
+    let values = rows.map(|row| typed_value(values[row].clone(), boolean_column)).collect();
+    Ok(Column::new(name, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(doc: &AlsDocument) -> Vec<Vec<String>> {
+        AlsParser::new().expand(doc).unwrap()
+    }
+
+    #[test]
+    fn test_join() {
+        let parser = AlsParser::new();
+        let events = parser.parse("#ts #user #kind\n1>6|3 1 \\\\0 2 3 9|click view*2 click*3").unwrap();
+        let users = parser.parse("#user #name #kind\n1>3 3|ann bob cy dee|admin*2 guest*2").unwrap();
+
+        let joined = join(&events, &users, "user", &AlsCompressor::new()).unwrap();
+        assert_eq!(joined.schema, ["ts", "user", "kind", "name", "kind_right"]);
+        assert_eq!(
+            rows(&joined),
+            vec![
+                vec!["1", "3", "click", "cy", "guest"],
+                vec!["1", "3", "click", "dee", "guest"],
+                vec!["2", "1", "view", "ann", "admin"],
+                vec!["4", "2", "click", "bob", "admin"],
+                vec!["5", "3", "click", "cy", "guest"],
+                vec!["5", "3", "click", "dee", "guest"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_without_matches() {
+        let parser = AlsParser::new();
+        let left = parser.parse("#id #flag\n1>3|?T~F:1.2").unwrap();
+        let right = parser.parse("#id #derived\n7 8|@0(7:x)*2").unwrap();
+        let joined = join(&left, &right, "id", &AlsCompressor::new()).unwrap();
+        assert_eq!(joined.schema, ["id", "flag", "derived"]);
+        assert_eq!(joined.row_count(), 0);
+
+        assert!(matches!(
+            join(&left, &right, "missing", &AlsCompressor::new()),
+            Err(AlsError::UnknownColumn { name }) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_join_derived_and_boolean_columns() {
+        let parser = AlsParser::new();
+        let left = parser.parse("#id #side #sign\n1>4|buy sell*2 buy|@1(buy:+1 sell:-1)*4").unwrap();
+        let right = parser.parse("#id #active\n4>1:-1|?true~false:2.2").unwrap();
+        let joined = join(&left, &right, "id", &AlsCompressor::new()).unwrap();
+        assert_eq!(
+            rows(&joined),
+            vec![
+                vec!["1", "buy", "1", "false"],
+                vec!["2", "sell", "-1", "false"],
+                vec!["3", "sell", "-1", "true"],
+                vec!["4", "buy", "1", "true"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! JavaScript decoder for ALS documents.
+//!
+//! Dashboards fetching ALS from an API would otherwise need a WebAssembly
+//! build of this crate to read it. [`DECODER`] is a small self-contained
+//! script, usable as a CommonJS module or from a `<script>` tag, that
+//! expands version 1 text into arrays of rows or columns; `als js-decoder`
+//! writes it out. Documents using flagged features, such as date ranges or
+//! column references, should be served through
+//! `AlsSerializer::serialize_as_version(&doc, 1)`, which expands them.
+//!
+//! The decoder is checked against the [conformance](crate::conformance)
+//! vectors of version 1.
+
+/// Source of the JavaScript decoder.
+pub const DECODER: &str = include_str!("../js/als-decoder.js");
+
+/// TypeScript declarations for [`DECODER`].
+pub const DECODER_TYPES: &str = include_str!("../js/als-decoder.d.ts");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::VECTORS;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    /// Runs the vectors the decoder supports, printing the names of those
+    /// that fail.
+    const RUNNER: &str = r#"
+        const decoder = require(process.argv[1]);
+        const suite = JSON.parse(require("fs").readFileSync(0, "utf8"));
+        const failed = suite.vectors
+          .filter((vector) => (vector.features || []).every((f) => decoder.SUPPORTED_FEATURES.includes(f)))
+          .filter((vector) => {
+            try {
+              const { columns, rows } = decoder.decode(vector.input);
+              return JSON.stringify([columns, rows]) !== JSON.stringify([vector.columns, vector.rows]);
+            } catch (e) {
+              return e.kind === undefined || e.kind !== vector.error;
+            }
+          });
+        console.log(failed.map((vector) => vector.name).join("\n"));
+    "#;
+
+    #[test]
+    fn test_decoder_conformance() {
+        // Only checked where Node.js is installed
+        if Command::new("node").arg("--version").output().is_err() {
+            return;
+        }
+        let path = std::env::temp_dir().join(format!("als-decoder-{}.js", std::process::id()));
+        std::fs::write(&path, DECODER).unwrap();
+
+        let mut node = Command::new("node")
+            .arg("-e")
+            .arg(RUNNER)
+            .arg(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        node.stdin.take().unwrap().write_all(VECTORS.as_bytes()).unwrap();
+        let output = node.wait_with_output().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), "");
+    }
+}
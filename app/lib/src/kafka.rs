@@ -0,0 +1,201 @@
+//! Kafka integration.
+//!
+//! Archives the JSON messages of Kafka topics as ALS frames. A
+//! [`KafkaArchiver`] consumes messages, batches them with a
+//! [`FrameBatcher`] and hands each frame to a [`FrameSink`], such as a
+//! directory of `.als` files or another topic.
+//!
+//! Offsets are committed only after a frame has been written, so every
+//! message ends up in a frame at least once, even if the archiver stops
+//! between frames.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use als_compression::kafka::{consumer, DirectorySink, KafkaArchiver};
+//! use std::sync::atomic::AtomicBool;
+//!
+//! let consumer = consumer("localhost:9092", "als-archiver", &["app-logs"])?;
+//! let sink = DirectorySink::new("archive", "app-logs")?;
+//! let mut archiver = KafkaArchiver::new(consumer, sink);
+//! archiver.run(&AtomicBool::new(false))?;
+//! ```
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::error::KafkaError;
+use rdkafka::message::Message;
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+
+use crate::error::{AlsError, Result};
+use crate::streaming::FrameBatcher;
+pub use crate::streaming::{DirectorySink, FrameSink};
+
+/// Default time to wait for a message in each poll.
+const DEFAULT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Produces each frame as a message of a topic.
+pub struct TopicSink {
+    producer: BaseProducer,
+    topic: String,
+    timeout: Duration,
+}
+
+impl TopicSink {
+    /// Produce frames to `topic`, waiting up to 30 seconds for each to be
+    /// delivered.
+    pub fn new(producer: BaseProducer, topic: &str) -> Self {
+        Self {
+            producer,
+            topic: topic.to_string(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set how long to wait for a frame to be delivered.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl FrameSink for TopicSink {
+    fn write_frame(&mut self, frame: &str) -> Result<()> {
+        self.producer
+            .send(BaseRecord::<(), str>::to(&self.topic).payload(frame))
+            .map_err(|(e, _)| kafka_error(e))?;
+        self.producer.flush(self.timeout).map_err(kafka_error)
+    }
+}
+
+/// Create a consumer subscribed to `topics`, with automatic offset commits
+/// turned off as [`KafkaArchiver`] expects.
+pub fn consumer(brokers: &str, group: &str, topics: &[&str]) -> Result<BaseConsumer> {
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set("group.id", group)
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", "earliest")
+        .create()
+        .map_err(kafka_error)?;
+    consumer.subscribe(topics).map_err(kafka_error)?;
+    Ok(consumer)
+}
+
+/// Consumes JSON messages and writes them to a sink as ALS frames.
+///
+/// The consumer must have `enable.auto.commit` set to `false`; see
+/// [`consumer`].
+pub struct KafkaArchiver<S: FrameSink> {
+    consumer: BaseConsumer,
+    batcher: FrameBatcher,
+    sink: S,
+    poll_timeout: Duration,
+    frames: usize,
+    skipped: usize,
+}
+
+impl<S: FrameSink> KafkaArchiver<S> {
+    /// Create an archiver with a default batcher.
+    pub fn new(consumer: BaseConsumer, sink: S) -> Self {
+        Self {
+            consumer,
+            batcher: FrameBatcher::new(),
+            sink,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            frames: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Set the batcher, which decides when frames are cut.
+    pub fn with_batcher(mut self, batcher: FrameBatcher) -> Self {
+        self.batcher = batcher;
+        self
+    }
+
+    /// Set how long each poll waits for a message.
+    pub fn with_poll_timeout(mut self, timeout: Duration) -> Self {
+        self.poll_timeout = timeout;
+        self
+    }
+
+    /// Consume at most one message, writing a frame if a batch completes
+    /// or its age is reached.
+    ///
+    /// Messages that are not JSON objects are skipped and counted. Returns
+    /// whether a frame was written.
+    pub fn poll(&mut self) -> Result<bool> {
+        let frame = match self.consumer.poll(self.poll_timeout) {
+            Some(message) => {
+                let message = message.map_err(kafka_error)?;
+                let pushed = message
+                    .payload_view::<str>()
+                    .and_then(|payload| payload.ok())
+                    .map(|payload| self.batcher.push_json(payload));
+                match pushed {
+                    Some(Ok(frame)) => frame,
+                    Some(Err(_)) | None => {
+                        self.skipped += 1;
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        let frame = match frame {
+            Some(frame) => Some(frame),
+            None => self.batcher.poll()?,
+        };
+        match frame {
+            Some(frame) => self.write(&frame).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Poll until `stop` is set, then write the last partial frame.
+    pub fn run(&mut self, stop: &AtomicBool) -> Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            self.poll()?;
+        }
+        self.flush()
+    }
+
+    /// Write the buffered messages as a frame, if there are any.
+    pub fn flush(&mut self) -> Result<()> {
+        match self.batcher.flush()? {
+            Some(frame) => self.write(&frame),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the number of frames written.
+    pub fn frames_written(&self) -> usize {
+        self.frames
+    }
+
+    /// Get the number of messages skipped because they were not JSON
+    /// objects.
+    pub fn skipped_messages(&self) -> usize {
+        self.skipped
+    }
+
+    /// Get the sink.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    fn write(&mut self, frame: &str) -> Result<()> {
+        self.sink.write_frame(frame)?;
+        self.frames += 1;
+        self.consumer.commit_consumer_state(CommitMode::Sync).map_err(kafka_error)
+    }
+}
+
+fn kafka_error(error: KafkaError) -> AlsError {
+    AlsError::IoError(io::Error::other(format!("Kafka: {}", error)))
+}
@@ -261,16 +261,49 @@
 #![warn(clippy::all)]
 
 // Module declarations
+pub mod aggregate;
 pub mod als;
 pub mod compress;
 pub mod config;
+pub mod conformance;
 pub mod convert;
+pub mod daemon;
 pub mod error;
 pub mod hashmap;
+pub mod join;
+pub mod js;
 pub mod pattern;
+pub mod query;
+pub mod reader;
 pub mod simd;
 pub mod streaming;
 
+mod rng;
+
+// Kafka integration (optional)
+#[cfg(feature = "kafka")]
+pub mod kafka;
+
+// HTTP content negotiation (optional)
+#[cfg(feature = "http")]
+pub mod http;
+
+// Protobuf messages for gRPC (optional)
+#[cfg(feature = "proto")]
+pub mod proto;
+
+// Tracing layer writing ALS frames (optional)
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
+
+// Property-based test helpers (optional)
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+// Synthetic benchmark datasets (optional)
+#[cfg(feature = "datasets")]
+pub mod datasets;
+
 // Python bindings (optional)
 #[cfg(feature = "python")]
 pub mod python;
@@ -282,24 +315,30 @@ pub mod ffi;
 // Re-exports for convenience
 pub use als::{
     decode_als_value, encode_als_value, escape_als_string, is_empty_token, is_null_token,
-    needs_escaping, unescape_als_string, AlsDocument, AlsOperator, AlsParser, AlsPrettyPrinter,
-    AlsSerializer, ColumnStream, FormatIndicator, Token, Tokenizer, VersionType, EMPTY_TOKEN,
-    NULL_TOKEN,
+    needs_escaping, unescape_als_string, AlsContainerReader, AlsContainerWriter, AlsDocument, AlsGenerator, AlsOperator, AlsParser, AlsPrettyPrinter,
+    AlsSerializer, AlsSignature, CalendarCycle, CaseTransform, ColumnContract, ColumnDescriptor, ColumnIndex, ColumnStream, ContainerEntry, Expander, Feature, FileMetadata, ForeignKey, FormatIndicator, PartialDocument, PseudonymKey, Quantization, Redaction, SchemaDescriptor, SchemaValidator, SchemaViolation, SharedDictionary, StoreFrame, StoredFormat,
+    TimeTruncation, Token, Tokenizer, ValueTransform, VersionType, EMPTY_TOKEN, NULL_TOKEN,
 };
-pub use config::{CompressorConfig, ParserConfig, SimdConfig};
-pub use convert::{Column, ColumnType, TabularData, Value, parse_syslog, to_syslog, MessageType, SyslogEntry, parse_syslog_optimized};
-pub use error::{AlsError, Result};
+pub use config::{ColumnStrategy, CompressorConfig, JsonConfig, JsonFormat, ParserConfig, SerializerConfig, SimdConfig};
+pub use convert::{Column, ColumnType, TabularData, TimeGranularity, TimePartition, Value, parse_syslog, parse_syslog_with_config, to_syslog, MessageType, SyslogConfig, SyslogEntry, parse_syslog_optimized};
+pub use error::{AlsError, Result, SourceLocation};
 pub use pattern::{
-    CombinedDetector, DetectionResult, PatternDetector, PatternEngine, PatternType,
-    RangeDetector, RepeatDetector, RunDetector, ToggleDetector,
+    BooleanDetector, CalendarCycleDetector, CombinedDetector, CorrelationDetector, CostModel, DateRangeDetector, DetectionResult, FloatDeltaDetector, MotifDetector, MotifSpan,
+    PatternDetector, PatternEngine, PatternType, RangeDetector, RepeatDetector, RunDetector, SegmentDetector,
+    TextCostModel, ToggleDetector, TransformDetector,
 };
 pub use compress::{
-    AlsCompressor, ColumnStats, CompressionReport, CompressionStats, DictionaryBuilder,
-    DictionaryEntry, EnumDetector, StatsSnapshot,
+    Advice, AllocationCounts, AlsCompressor, CardinalityEstimator, ColumnExplanation, ColumnMetrics, ColumnProfile, ColumnStats, CompressionMetrics,
+    CompressionReport, CompressionStats, CountingAllocator, DetectorTiming, DictionaryBuilder, DictionaryEntry, EnumDetector,
+    HierarchicalEnum, OpaqueDetector, Restructure, ScratchBuffers, StatsSnapshot, Suggestion, ADVICE_SAMPLE_ROWS,
 };
 pub use hashmap::AdaptiveMap;
+pub use join::join;
+pub use aggregate::Aggregate;
+pub use query::{AlsQuery, Comparison, Predicate};
+pub use reader::{AlsChunkedReader, ColumnVector, RecordBatch};
 pub use simd::{CpuFeatures, SimdDispatcher, SimdLevel};
-pub use streaming::{StreamingCompressor, StreamingParser};
+pub use streaming::{DirectorySink, FrameBatcher, FrameSink, StreamingCompressor, StreamingParser};
 
 /// Thread safety verification module.
 ///
@@ -319,7 +358,7 @@ pub use streaming::{StreamingCompressor, StreamingParser};
 /// - [`CompressionStats`]: Uses atomic operations for all counters, allowing
 ///   concurrent updates from multiple threads without locks.
 ///
-/// - [`StatsSnapshot`], [`ColumnStats`], [`CompressionReport`]: Immutable value
+/// - [`StatsSnapshot`], [`ColumnStats`], [`ColumnProfile`], [`CompressionReport`]: Immutable value
 ///   types that can be safely shared.
 ///
 /// ## Data Types
@@ -377,22 +416,33 @@ mod thread_safety {
         assert_send_sync::<CompressionStats>();
         assert_send_sync::<StatsSnapshot>();
         assert_send_sync::<ColumnStats>();
+        assert_send_sync::<ColumnProfile>();
         assert_send_sync::<CompressionReport>();
         assert_send_sync::<DictionaryBuilder>();
         assert_send_sync::<DictionaryEntry>();
         assert_send_sync::<EnumDetector>();
+        assert_send_sync::<HierarchicalEnum>();
     }
 
     /// Verify all public ALS document types are thread-safe.
     #[test]
     fn als_types_are_send_sync() {
-        assert_send_sync::<AlsDocument>();
-        assert_send_sync::<AlsOperator>();
-        assert_send_sync::<ColumnStream>();
+        assert_send_sync::<AlsDocument<'static>>();
+        assert_send_sync::<AlsOperator<'static>>();
+        assert_send_sync::<CalendarCycle>();
+        assert_send_sync::<ValueTransform>();
+        assert_send_sync::<ColumnStream<'static>>();
         assert_send_sync::<FormatIndicator>();
         assert_send_sync::<AlsParser>();
         assert_send_sync::<AlsSerializer>();
+        assert_send_sync::<AlsSignature>();
+        assert_send_sync::<AlsContainerReader>();
+        assert_send_sync::<AlsContainerWriter<Vec<u8>>>();
+        assert_send_sync::<ContainerEntry>();
         assert_send_sync::<AlsPrettyPrinter>();
+        assert_send_sync::<AlsQuery>();
+        assert_send_sync::<AlsChunkedReader>();
+        assert_send_sync::<RecordBatch>();
         assert_send_sync::<Token>();
         assert_send_sync::<Tokenizer>();
         assert_send_sync::<VersionType>();
@@ -426,6 +476,15 @@ mod thread_safety {
         assert_send_sync::<ToggleDetector>();
         assert_send_sync::<CombinedDetector>();
         assert_send_sync::<RunDetector>();
+        assert_send_sync::<MotifDetector>();
+        assert_send_sync::<MotifSpan>();
+        assert_send_sync::<CorrelationDetector>();
+        assert_send_sync::<DateRangeDetector>();
+        assert_send_sync::<FloatDeltaDetector>();
+        assert_send_sync::<CalendarCycleDetector>();
+        assert_send_sync::<BooleanDetector>();
+        assert_send_sync::<SegmentDetector>();
+        assert_send_sync::<TransformDetector>();
     }
 
     /// Verify all public SIMD types are thread-safe.
@@ -447,6 +506,8 @@ mod thread_safety {
     fn streaming_types_are_send_sync() {
         use std::io::Cursor;
         assert_send_sync::<StreamingCompressor<Cursor<Vec<u8>>>>();
+        assert_send_sync::<FrameBatcher>();
+        assert_send_sync::<DirectorySink>();
         assert_send_sync::<StreamingParser<Cursor<Vec<u8>>>>();
     }
 
@@ -0,0 +1,167 @@
+//! Boolean column detection.
+//!
+//! This module detects columns holding only the two values of a common
+//! boolean spelling (`true`/`false`, `T`/`F`, `yes`/`no`, `1`/`0`, ...)
+//! and encodes them as alternating runs (e.g., `?true~false:3.1.2`).
+
+use super::detector::{DetectionResult, PatternDetector};
+
+/// Boolean spellings recognized by the detector, as (true, false) pairs.
+const BOOLEAN_PAIRS: &[(&str, &str)] = &[
+    ("true", "false"),
+    ("True", "False"),
+    ("TRUE", "FALSE"),
+    ("t", "f"),
+    ("T", "F"),
+    ("yes", "no"),
+    ("Yes", "No"),
+    ("YES", "NO"),
+    ("y", "n"),
+    ("Y", "N"),
+    ("1", "0"),
+    ("on", "off"),
+    ("On", "Off"),
+    ("ON", "OFF"),
+];
+
+/// Detector for boolean columns.
+///
+/// Detects columns whose values are the two spellings of one boolean pair
+/// and encodes them as run lengths, which is far smaller than raw values
+/// or dictionary references for typical flag columns.
+#[derive(Debug, Clone)]
+pub struct BooleanDetector {
+    min_pattern_length: usize,
+}
+
+impl BooleanDetector {
+    /// Create a new boolean detector with the given minimum pattern length.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self { min_pattern_length }
+    }
+
+    /// Check if two labels form a recognized boolean pair.
+    pub fn is_boolean_pair(a: &str, b: &str) -> bool {
+        BOOLEAN_PAIRS
+            .iter()
+            .any(|&(t, f)| (a == t && b == f) || (a == f && b == t))
+    }
+
+    /// Split the values into alternating runs.
+    ///
+    /// Returns the two labels in order of first appearance and the run
+    /// lengths, or `None` if the column does not hold exactly two values.
+    fn collect_runs<'a>(values: &[&'a str]) -> Option<(&'a str, &'a str, Vec<usize>)> {
+        let first = values[0];
+        let mut second = None;
+        let mut runs = vec![0];
+        let mut current = first;
+
+        for &value in values {
+            if value != current {
+                match second {
+                    None => second = Some(value),
+                    Some(s) if value != s && value != first => return None,
+                    Some(_) => {}
+                }
+                current = value;
+                runs.push(0);
+            }
+            *runs.last_mut()? += 1;
+        }
+
+        Some((first, second?, runs))
+    }
+
+    /// Calculate the original string length of the values.
+    fn calculate_original_length(values: &[&str]) -> usize {
+        let value_len: usize = values.iter().map(|v| v.len()).sum();
+        let separator_len = values.len().saturating_sub(1);
+        value_len + separator_len
+    }
+}
+
+impl PatternDetector for BooleanDetector {
+    fn detect(&self, values: &[&str]) -> Option<DetectionResult> {
+        if values.len() < self.min_pattern_length.max(2) {
+            return None;
+        }
+
+        let (first, second, runs) = Self::collect_runs(values)?;
+        if !Self::is_boolean_pair(first, second) {
+            return None;
+        }
+
+        let original_len = Self::calculate_original_length(values);
+        let result = DetectionResult::bool_runs(first, second, runs, original_len);
+
+        if result.compression_ratio > 1.0 {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsOperator;
+    use crate::pattern::PatternType;
+
+    #[test]
+    fn test_true_false_column() {
+        let detector = BooleanDetector::new(3);
+        let values = vec!["true", "true", "false", "true", "false", "false", "false"];
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(result.pattern_type, PatternType::Boolean);
+        assert_eq!(
+            result.operator,
+            AlsOperator::bool_runs("true", "false", vec![2, 1, 1, 3])
+        );
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_labels_in_order_of_appearance() {
+        let detector = BooleanDetector::new(3);
+        let mut values = vec!["N"; 20];
+        values.extend(["Y"; 10]);
+        let result = detector.detect(&values).unwrap();
+        assert_eq!(result.operator, AlsOperator::bool_runs("N", "Y", vec![20, 10]));
+    }
+
+    #[test]
+    fn test_numeric_flags() {
+        let detector = BooleanDetector::new(3);
+        let values = vec!["0", "0", "0", "1", "1", "0"];
+        assert!(detector.detect(&values).is_some());
+    }
+
+    #[test]
+    fn test_mixed_spellings_rejected() {
+        let detector = BooleanDetector::new(3);
+        assert!(detector.detect(&["true", "false", "yes"]).is_none());
+        assert!(detector.detect(&["true", "no", "true"]).is_none());
+    }
+
+    #[test]
+    fn test_single_value_rejected() {
+        let detector = BooleanDetector::new(3);
+        assert!(detector.detect(&["true", "true", "true"]).is_none());
+    }
+
+    #[test]
+    fn test_non_boolean_pair_rejected() {
+        let detector = BooleanDetector::new(3);
+        assert!(detector.detect(&["red", "blue", "red"]).is_none());
+    }
+
+    #[test]
+    fn test_is_boolean_pair() {
+        assert!(BooleanDetector::is_boolean_pair("false", "true"));
+        assert!(BooleanDetector::is_boolean_pair("Y", "N"));
+        assert!(!BooleanDetector::is_boolean_pair("Y", "no"));
+    }
+}
@@ -3,6 +3,9 @@
 //! This module detects repeated patterns such as repeated ranges (e.g., `(1>3)*2`)
 //! and repeated alternating patterns.
 
+use std::sync::Arc;
+
+use super::cost::{CostModel, TextCostModel};
 use super::detector::{DetectionResult, PatternDetector, PatternType};
 use super::range::RangeDetector;
 use super::toggle::ToggleDetector;
@@ -17,6 +20,7 @@ pub struct CombinedDetector {
     min_pattern_length: usize,
     range_detector: RangeDetector,
     toggle_detector: ToggleDetector,
+    cost_model: Arc<dyn CostModel>,
 }
 
 impl CombinedDetector {
@@ -26,9 +30,16 @@ impl CombinedDetector {
             min_pattern_length,
             range_detector: RangeDetector::new(2), // Allow shorter ranges for combined patterns
             toggle_detector: ToggleDetector::new(2),
+            cost_model: Arc::new(TextCostModel),
         }
     }
 
+    /// Use the given cost model to price repeated patterns.
+    pub fn with_cost_model(mut self, cost_model: Arc<dyn CostModel>) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
     /// Try to detect a repeated range pattern.
     ///
     /// Looks for patterns like 1, 2, 3, 1, 2, 3 which can be encoded as (1>3)*2.
@@ -196,7 +207,7 @@ impl CombinedDetector {
         // Try to parse first two values as integers to detect arithmetic sequences
         let first: i64 = values[0].trim().parse().ok()?;
         let second: i64 = values[1].trim().parse().ok()?;
-        let step = second - first;
+        let step = second.checked_sub(first)?;
 
         if step == 0 {
             return None; // All same values, not a range pattern
@@ -205,7 +216,7 @@ impl CombinedDetector {
         // Find where the sequence breaks (value doesn't follow the expected pattern)
         for i in 2..values.len() {
             let current: i64 = values[i].trim().parse().ok()?;
-            let expected = first + (i as i64) * step;
+            let expected = (i as i64).checked_mul(step).and_then(|offset| first.checked_add(offset))?;
 
             if current != expected {
                 // Found a break - this is the pattern length
@@ -275,15 +286,9 @@ impl CombinedDetector {
                     };
 
                     let original_len = Self::calculate_original_length(values);
-                    // Estimate compression - this is a rough estimate
-                    let compressed_len = 10.0 + (repeat_count as f64).log10() + 1.0;
-                    let compression_ratio = original_len as f64 / compressed_len;
-
-                    return Some(DetectionResult {
-                        operator,
-                        compression_ratio,
-                        pattern_type: PatternType::RepeatedToggle,
-                    });
+                    let compression_ratio = self.cost_model.compression_ratio(original_len, &operator);
+
+                    return Some(DetectionResult::new(operator, compression_ratio, PatternType::RepeatedToggle));
                 }
             }
         }
@@ -0,0 +1,174 @@
+//! Cross-column correlation detection.
+//!
+//! This module detects columns that move in lockstep with an earlier
+//! column, such as `side=buy/sell` and `sign=+1/-1` in market data. Such a
+//! column can be encoded as a column reference (`@0(buy:+1 sell:-1)*n`)
+//! instead of a stream of its own.
+
+/// Detector for columns derivable from another column.
+///
+/// A target column is derivable from a source column when every
+/// occurrence of a source value lines up with the same target value.
+/// The resulting mapping is kept small so the reference stays cheap.
+#[derive(Debug, Clone)]
+pub struct CorrelationDetector {
+    max_mapping_size: usize,
+}
+
+impl CorrelationDetector {
+    /// Default maximum number of distinct source values in a mapping.
+    pub const DEFAULT_MAX_MAPPING_SIZE: usize = 16;
+
+    /// Create a new correlation detector with the default mapping size.
+    pub fn new() -> Self {
+        Self {
+            max_mapping_size: Self::DEFAULT_MAX_MAPPING_SIZE,
+        }
+    }
+
+    /// Set the maximum number of distinct source values in a mapping.
+    pub fn with_max_mapping_size(mut self, max_mapping_size: usize) -> Self {
+        self.max_mapping_size = max_mapping_size;
+        self
+    }
+
+    /// Get the maximum mapping size.
+    pub fn max_mapping_size(&self) -> usize {
+        self.max_mapping_size
+    }
+
+    /// Find the mapping that derives `target` from `source`.
+    ///
+    /// Returns the `(source, target)` pairs in order of first appearance,
+    /// omitting values that map to themselves. Returns `None` if the
+    /// columns differ in length, are empty, or if some source value lines
+    /// up with more than one target value.
+    pub fn find_mapping<S, T>(&self, source: &[S], target: &[T]) -> Option<Vec<(String, String)>>
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+    {
+        if source.is_empty() || source.len() != target.len() {
+            return None;
+        }
+
+        let mut pairs: Vec<(&str, &str)> = Vec::new();
+
+        for (from, to) in source.iter().zip(target.iter()) {
+            let (from, to) = (from.as_ref(), to.as_ref());
+            match pairs.iter().find(|(seen, _)| *seen == from) {
+                Some(&(_, mapped)) if mapped == to => {}
+                Some(_) => return None,
+                None => {
+                    if pairs.len() >= self.max_mapping_size {
+                        return None;
+                    }
+                    pairs.push((from, to));
+                }
+            }
+        }
+
+        Some(
+            pairs
+                .into_iter()
+                .filter(|(from, to)| from != to)
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        )
+    }
+}
+
+impl Default for CorrelationDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockstep_columns() {
+        let detector = CorrelationDetector::new();
+        let side = vec!["buy", "sell", "sell", "buy", "sell"];
+        let sign = vec!["+1", "-1", "-1", "+1", "-1"];
+
+        let mapping = detector.find_mapping(&side, &sign).unwrap();
+        assert_eq!(
+            mapping,
+            vec![
+                ("buy".to_string(), "+1".to_string()),
+                ("sell".to_string(), "-1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identical_columns_have_empty_mapping() {
+        let detector = CorrelationDetector::new();
+        let values = vec!["a", "b", "c"];
+        assert_eq!(detector.find_mapping(&values, &values), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_identity_pairs_omitted() {
+        let detector = CorrelationDetector::new();
+        let source = vec!["x", "y", "x"];
+        let target = vec!["x", "Y", "x"];
+        let mapping = detector.find_mapping(&source, &target).unwrap();
+        assert_eq!(mapping, vec![("y".to_string(), "Y".to_string())]);
+    }
+
+    #[test]
+    fn test_conflicting_values() {
+        let detector = CorrelationDetector::new();
+        let source = vec!["buy", "sell", "buy"];
+        let target = vec!["+1", "-1", "-1"];
+        assert!(detector.find_mapping(&source, &target).is_none());
+    }
+
+    #[test]
+    fn test_length_mismatch_and_empty() {
+        let detector = CorrelationDetector::new();
+        assert!(detector.find_mapping(&["a"], &["a", "b"]).is_none());
+        let empty: Vec<&str> = Vec::new();
+        assert!(detector.find_mapping(&empty, &empty).is_none());
+    }
+
+    #[test]
+    fn test_mapping_size_limit() {
+        let detector = CorrelationDetector::new().with_max_mapping_size(2);
+        assert_eq!(detector.max_mapping_size(), 2);
+        let source = vec!["a", "b", "c"];
+        let target = vec!["1", "2", "3"];
+        assert!(detector.find_mapping(&source, &target).is_none());
+    }
+
+    #[test]
+    fn test_parenthesized_values_round_trip() {
+        use crate::als::{AlsOperator, AlsParser, AlsSerializer, ColumnStream};
+
+        let detector = CorrelationDetector::new();
+        let source = vec!["a (1)", "b", "a (1)", "b)"];
+        let target = vec!["(x)", "y(", "(x)", ")"];
+        let mapping = detector.find_mapping(&source, &target).unwrap();
+
+        let mut doc = crate::als::AlsDocument::with_schema(vec!["source".to_string(), "target".to_string()]);
+        doc.add_stream(source.iter().map(|&value| AlsOperator::raw(value)).collect());
+        doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::column_ref(0, mapping, target.len())]));
+
+        let parser = AlsParser::new();
+        let parsed = parser.parse(&AlsSerializer::new().serialize(&doc)).unwrap();
+        let columns = parser.expand_columns(&parsed).unwrap();
+        assert_eq!(columns, [source, target]);
+    }
+
+    #[test]
+    fn test_owned_strings() {
+        let detector = CorrelationDetector::new();
+        let source = vec!["a".to_string(), "b".to_string()];
+        let target = vec!["1", "2"];
+        assert!(detector.find_mapping(&source, &target).is_some());
+    }
+}
@@ -0,0 +1,112 @@
+//! Byte-cost estimation for encoded operators.
+//!
+//! Detectors compare candidate encodings by how many bytes they take once
+//! serialized. The `CostModel` trait makes that estimate pluggable, so the
+//! operator selection can follow whatever serializer the output goes
+//! through instead of a detector's built-in guess.
+
+use std::fmt::Debug;
+use std::panic::RefUnwindSafe;
+
+use crate::als::{AlsOperator, AlsSerializer};
+
+/// Estimates the encoded size of operators.
+///
+/// Models are shared by compressors, so they must be `Send + Sync` and
+/// `RefUnwindSafe`, which keeps compressors usable across `catch_unwind`.
+///
+/// The default [`TextCostModel`] measures the ALS text serialization. A
+/// model for another encoding only needs to price a single operator:
+///
+/// ```
+/// use als_compression::{AlsOperator, CostModel};
+///
+/// /// Every operator costs a tag byte plus eight bytes per operand.
+/// #[derive(Debug)]
+/// struct FixedWidthCost;
+///
+/// impl CostModel for FixedWidthCost {
+///     fn operator_cost(&self, operator: &AlsOperator) -> f64 {
+///         match operator {
+///             AlsOperator::Raw(value) => 1.0 + value.len() as f64,
+///             AlsOperator::Range { .. } => 25.0,
+///             _ => 9.0,
+///         }
+///     }
+///
+///     fn separator_cost(&self) -> f64 {
+///         0.0
+///     }
+/// }
+///
+/// let model = FixedWidthCost;
+/// assert_eq!(model.stream_cost(&[AlsOperator::range(1, 9), AlsOperator::raw("x")]), 27.0);
+/// ```
+pub trait CostModel: Debug + Send + Sync + RefUnwindSafe {
+    /// Encoded size of a single operator, in bytes.
+    fn operator_cost(&self, operator: &AlsOperator) -> f64;
+
+    /// Size of the separator written between operators of a stream.
+    fn separator_cost(&self) -> f64 {
+        1.0
+    }
+
+    /// Encoded size of a sequence of operators, separators included.
+    fn stream_cost(&self, operators: &[AlsOperator]) -> f64 {
+        let separators = operators.len().saturating_sub(1) as f64 * self.separator_cost();
+        operators.iter().map(|op| self.operator_cost(op)).sum::<f64>() + separators
+    }
+
+    /// Compression ratio of encoding `original_len` bytes as `operator`.
+    fn compression_ratio(&self, original_len: usize, operator: &AlsOperator) -> f64 {
+        let cost = self.operator_cost(operator);
+        if cost > 0.0 {
+            original_len as f64 / cost
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Cost of the ALS text format, measured by serializing the operator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextCostModel;
+
+impl CostModel for TextCostModel {
+    fn operator_cost(&self, operator: &AlsOperator) -> f64 {
+        let mut output = String::new();
+        AlsSerializer::new().serialize_operator(&mut output, operator);
+        output.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_cost_matches_serialization() {
+        let model = TextCostModel;
+        assert_eq!(model.operator_cost(&AlsOperator::range(1, 100)), 5.0);
+        assert_eq!(model.operator_cost(&AlsOperator::toggle("T", "F", 10)), 6.0);
+        assert_eq!(
+            model.operator_cost(&AlsOperator::multiply(AlsOperator::toggle("A", "B", 2), 4)),
+            "(A~B*2)*4".len() as f64
+        );
+    }
+
+    #[test]
+    fn test_stream_cost_counts_separators() {
+        let model = TextCostModel;
+        let operators = [AlsOperator::raw("a"), AlsOperator::raw("bc"), AlsOperator::dict_ref(3)];
+        assert_eq!(model.stream_cost(&operators), "a bc _3".len() as f64);
+        assert_eq!(model.stream_cost(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        let model = TextCostModel;
+        assert_eq!(model.compression_ratio(50, &AlsOperator::range(1, 100)), 10.0);
+        assert_eq!(model.compression_ratio(50, &AlsOperator::raw("")), 1.0);
+    }
+}
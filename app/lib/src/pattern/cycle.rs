@@ -0,0 +1,184 @@
+//! Calendar cycle pattern detection.
+//!
+//! This module detects columns that step through a built-in calendar
+//! cycle (day-of-week names, month names, quarter labels) at a fixed row
+//! cadence, so they can be encoded by name (e.g., `&dow+0*30`).
+
+use super::detector::{DetectionResult, PatternDetector};
+use crate::als::CalendarCycle;
+
+/// Detector for calendar-cyclic columns.
+///
+/// Detects:
+/// - Consecutive labels (e.g., Mon, Tue, Wed, ... → `&dow+0*n`)
+/// - Labels held for several rows (e.g., hourly rows tagged with the
+///   day name → `&dow+0:24*n`)
+/// - Sequences starting anywhere in the cycle and wrapping around, even
+///   part way through a run (e.g., hourly rows starting on a Wednesday
+///   afternoon → `&dow+62:24*n`)
+#[derive(Debug, Clone)]
+pub struct CalendarCycleDetector {
+    min_pattern_length: usize,
+}
+
+impl CalendarCycleDetector {
+    /// Create a new calendar cycle detector with the given minimum pattern length.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self { min_pattern_length }
+    }
+
+    /// Detect the offset and run length of `values` within `cycle`.
+    fn detect_cycle(values: &[&str], cycle: CalendarCycle) -> Option<(usize, usize)> {
+        let labels = cycle.values();
+        let first = cycle.position(values[0])?;
+
+        let lead = values.iter().take_while(|v| **v == values[0]).count();
+        if lead == values.len() {
+            // A single label is better encoded as a repeat
+            return None;
+        }
+
+        // The first and last runs may be cut short, so the cadence is the
+        // length of the second run, or the longer one if there are only two
+        let second = values[lead..].iter().take_while(|v| **v == values[lead]).count();
+        let run = if lead + second == values.len() { lead.max(second) } else { second };
+        if lead > run {
+            return None;
+        }
+        let offset = first * run + (run - lead);
+
+        for (i, value) in values.iter().enumerate() {
+            if *value != labels[cycle.label_position(offset, run, i)] {
+                return None;
+            }
+        }
+
+        Some((offset, run))
+    }
+
+    /// Calculate the original string length of the values.
+    fn calculate_original_length(values: &[&str]) -> usize {
+        let value_len: usize = values.iter().map(|v| v.len()).sum();
+        let separator_len = values.len().saturating_sub(1);
+        value_len + separator_len
+    }
+}
+
+impl PatternDetector for CalendarCycleDetector {
+    fn detect(&self, values: &[&str]) -> Option<DetectionResult> {
+        if values.len() < self.min_pattern_length.max(2) {
+            return None;
+        }
+
+        let (cycle, offset, run) = CalendarCycle::ALL.into_iter().find_map(|cycle| {
+            Self::detect_cycle(values, cycle).map(|(offset, run)| (cycle, offset, run))
+        })?;
+
+        let original_len = Self::calculate_original_length(values);
+        let result = DetectionResult::cycle(cycle, offset, run, values.len(), original_len);
+
+        if result.compression_ratio > 1.0 {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsOperator;
+    use crate::pattern::PatternType;
+
+    #[test]
+    fn test_weekdays() {
+        let detector = CalendarCycleDetector::new(3);
+        let values: Vec<&str> = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .iter()
+            .cycle()
+            .skip(4)
+            .take(30)
+            .copied()
+            .collect();
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(result.pattern_type, PatternType::CalendarCycle);
+        assert_eq!(
+            result.operator,
+            AlsOperator::cycle(CalendarCycle::Weekday, 4, 1, 30)
+        );
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_months_with_run() {
+        let detector = CalendarCycleDetector::new(3);
+        let mut values = Vec::new();
+        for month in CalendarCycle::MonthFull.values() {
+            values.extend(std::iter::repeat_n(*month, 3));
+        }
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(
+            result.operator,
+            AlsOperator::cycle(CalendarCycle::MonthFull, 0, 3, 36)
+        );
+    }
+
+    #[test]
+    fn test_partial_final_run() {
+        let detector = CalendarCycleDetector::new(3);
+        let values = vec!["Q3", "Q3", "Q4", "Q4", "Q1"];
+        let result = detector.detect(&values).unwrap();
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_partial_first_run() {
+        let detector = CalendarCycleDetector::new(3);
+        let mut values = vec!["Wed"; 10];
+        for day in ["Thu", "Fri", "Sat", "Sun", "Mon"] {
+            values.extend(std::iter::repeat_n(day, 24));
+        }
+        values.extend(std::iter::repeat_n("Tue", 5));
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(
+            result.operator,
+            AlsOperator::cycle(CalendarCycle::Weekday, 2 * 24 + 14, 24, 135)
+        );
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+
+        let values = vec!["Q2", "Q3", "Q3", "Q4", "Q4", "Q1"];
+        let result = detector.detect(&values).unwrap();
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_skipped_label() {
+        let detector = CalendarCycleDetector::new(3);
+        let values = vec!["Mon", "Tue", "Thu", "Fri"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_uneven_runs() {
+        let detector = CalendarCycleDetector::new(3);
+        let values = vec!["Jan", "Jan", "Feb", "Mar", "Mar"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_single_label() {
+        let detector = CalendarCycleDetector::new(3);
+        let values = vec!["Mon", "Mon", "Mon"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_non_calendar_values() {
+        let detector = CalendarCycleDetector::new(3);
+        assert!(detector.detect(&["a", "b", "c"]).is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Date and timestamp range pattern detection.
+//!
+//! This module detects columns of evenly spaced timestamps, such as daily
+//! dates or hourly readings, that can be encoded as a single date range
+//! (e.g., `%{%Y-%m-%d}1704067200+86400*365`).
+
+use super::detector::{DetectionResult, PatternDetector};
+use crate::als::datetime::parse_timestamp;
+
+/// Timestamp formats tried by the detector, most specific first.
+const CANDIDATE_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%SZ",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+];
+
+/// Detector for evenly spaced date and timestamp columns.
+///
+/// Detects:
+/// - Daily dates (e.g., 2024-01-01, 2024-01-02, ... → `%{%Y-%m-%d}1704067200+86400*n`)
+/// - Hourly or minutely timestamps in ISO 8601 style
+/// - Descending timestamps (negative step)
+#[derive(Debug, Clone)]
+pub struct DateRangeDetector {
+    min_pattern_length: usize,
+}
+
+impl DateRangeDetector {
+    /// Create a new date range detector with the given minimum pattern length.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self { min_pattern_length }
+    }
+
+    /// Find the first candidate format that parses `value`.
+    fn detect_format(value: &str) -> Option<&'static str> {
+        CANDIDATE_FORMATS
+            .iter()
+            .copied()
+            .find(|format| parse_timestamp(value, format).is_some())
+    }
+
+    /// Detect a date range in the values using the given format.
+    ///
+    /// Returns the start timestamp and step if every value is evenly spaced.
+    fn detect_range(values: &[&str], format: &str) -> Option<(i64, i64)> {
+        let start = parse_timestamp(values[0], format)?;
+        let step = parse_timestamp(values[1], format)?.checked_sub(start)?;
+
+        // Step of 0 means all values are the same - better as a repeat
+        if step == 0 {
+            return None;
+        }
+
+        for (i, value) in values.iter().enumerate().skip(2) {
+            let expected = start.checked_add((i as i64).checked_mul(step)?)?;
+            if parse_timestamp(value, format)? != expected {
+                return None;
+            }
+        }
+
+        Some((start, step))
+    }
+
+    /// Calculate the original string length of the values.
+    fn calculate_original_length(values: &[&str]) -> usize {
+        let value_len: usize = values.iter().map(|v| v.len()).sum();
+        let separator_len = values.len().saturating_sub(1);
+        value_len + separator_len
+    }
+}
+
+impl PatternDetector for DateRangeDetector {
+    fn detect(&self, values: &[&str]) -> Option<DetectionResult> {
+        if values.len() < self.min_pattern_length.max(2) {
+            return None;
+        }
+
+        let format = Self::detect_format(values[0])?;
+        let (start, step) = Self::detect_range(values, format)?;
+
+        let original_len = Self::calculate_original_length(values);
+        let result = DetectionResult::date_range(start, step, values.len(), format, original_len);
+
+        if result.compression_ratio > 1.0 {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsOperator;
+    use crate::pattern::PatternType;
+
+    #[test]
+    fn test_daily_dates() {
+        let detector = DateRangeDetector::new(3);
+        let values = vec!["2024-01-30", "2024-01-31", "2024-02-01", "2024-02-02"];
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(result.pattern_type, PatternType::DateRange);
+        assert_eq!(
+            result.operator,
+            AlsOperator::date_range(1_706_572_800, 86_400, 4, "%Y-%m-%d")
+        );
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_hourly_timestamps() {
+        let detector = DateRangeDetector::new(3);
+        let values: Vec<String> = (0..48)
+            .map(|h| format!("2024-03-{:02}T{:02}:00:00Z", 10 + h / 24, h % 24))
+            .collect();
+        let refs: Vec<&str> = values.iter().map(|s| s.as_str()).collect();
+        let result = detector.detect(&refs).unwrap();
+
+        assert!(result.compression_ratio > 10.0);
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_descending_dates() {
+        let detector = DateRangeDetector::new(3);
+        let values = vec!["2024/03/01", "2024/02/29", "2024/02/28"];
+        let result = detector.detect(&values).unwrap();
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_uneven_dates() {
+        let detector = DateRangeDetector::new(3);
+        let values = vec!["2024-01-01", "2024-01-02", "2024-01-04"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_repeated_date() {
+        let detector = DateRangeDetector::new(3);
+        let values = vec!["2024-01-01", "2024-01-01", "2024-01-01"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_mixed_formats() {
+        let detector = DateRangeDetector::new(3);
+        let values = vec!["2024-01-01", "2024/01/02", "2024-01-03"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_non_dates() {
+        let detector = DateRangeDetector::new(3);
+        assert!(detector.detect(&["1", "2", "3"]).is_none());
+        assert!(detector.detect(&["apple", "banana", "cherry"]).is_none());
+    }
+
+    #[test]
+    fn test_too_short() {
+        let detector = DateRangeDetector::new(5);
+        let values = vec!["2024-01-01", "2024-01-02", "2024-01-03"];
+        assert!(detector.detect(&values).is_none());
+    }
+}
@@ -3,7 +3,7 @@
 //! This module defines the `PatternDetector` trait and associated types
 //! used by all pattern detection implementations.
 
-use crate::als::AlsOperator;
+use crate::als::{AlsOperator, CalendarCycle, ValueTransform};
 
 /// Trait for pattern detection algorithms.
 ///
@@ -15,6 +15,14 @@ pub trait PatternDetector: Send + Sync {
     ///
     /// Returns `None` if no beneficial pattern is detected.
     fn detect(&self, values: &[&str]) -> Option<DetectionResult>;
+
+    /// Name reported in compression metrics.
+    ///
+    /// Defaults to the implementing type's name without its module path.
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
 
 /// Result of pattern detection.
@@ -23,7 +31,7 @@ pub trait PatternDetector: Send + Sync {
 #[derive(Debug, Clone)]
 pub struct DetectionResult {
     /// The ALS operator that encodes the detected pattern.
-    pub operator: AlsOperator,
+    pub operator: AlsOperator<'static>,
     /// Compression ratio (original_size / compressed_size).
     ///
     /// Higher values indicate better compression.
@@ -31,24 +39,30 @@ pub struct DetectionResult {
     pub compression_ratio: f64,
     /// The type of pattern detected.
     pub pattern_type: PatternType,
+    /// Runner-up results, best first.
+    ///
+    /// Empty unless requested through `PatternEngine::detect_n_best`.
+    pub candidates: Vec<DetectionResult>,
 }
 
 impl DetectionResult {
     /// Create a new detection result.
-    pub fn new(operator: AlsOperator, compression_ratio: f64, pattern_type: PatternType) -> Self {
+    pub fn new(operator: AlsOperator<'static>, compression_ratio: f64, pattern_type: PatternType) -> Self {
         Self {
             operator,
             compression_ratio,
             pattern_type,
+            candidates: Vec::new(),
         }
     }
 
     /// Create a raw (no compression) result for empty input.
     pub fn raw_empty() -> Self {
         Self {
-            operator: AlsOperator::Raw(String::new()),
+            operator: AlsOperator::raw(String::new()),
             compression_ratio: 1.0,
             pattern_type: PatternType::Raw,
+            candidates: Vec::new(),
         }
     }
 
@@ -61,19 +75,20 @@ impl DetectionResult {
         // operator with the first value (the actual encoding will handle
         // multiple values separately)
         let operator = if values.is_empty() {
-            AlsOperator::Raw(String::new())
+            AlsOperator::raw(String::new())
         } else if values.len() == 1 {
-            AlsOperator::Raw(values[0].to_string())
+            AlsOperator::raw(values[0].to_string())
         } else {
             // For multiple raw values, we use a placeholder
             // The actual compression will handle each value individually
-            AlsOperator::Raw(values.join(" "))
+            AlsOperator::raw(values.join(" "))
         };
 
         Self {
             operator,
             compression_ratio: 1.0,
             pattern_type: PatternType::Raw,
+            candidates: Vec::new(),
         }
     }
 
@@ -96,13 +111,14 @@ impl DetectionResult {
             } else {
                 PatternType::Arithmetic
             },
+            candidates: Vec::new(),
         }
     }
 
     /// Create a repeat detection result.
     pub fn repeat(value: &str, count: usize, _original_len: usize) -> Self {
         let operator = AlsOperator::Multiply {
-            value: Box::new(AlsOperator::Raw(value.to_string())),
+            value: Box::new(AlsOperator::raw(value.to_string())),
             count,
         };
         
@@ -119,6 +135,7 @@ impl DetectionResult {
             operator,
             compression_ratio,
             pattern_type: PatternType::Repeat,
+            candidates: Vec::new(),
         }
     }
 
@@ -146,6 +163,7 @@ impl DetectionResult {
             operator,
             compression_ratio,
             pattern_type: PatternType::Toggle,
+            candidates: Vec::new(),
         }
     }
 
@@ -171,9 +189,115 @@ impl DetectionResult {
             operator,
             compression_ratio,
             pattern_type: PatternType::RepeatedRange,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Create a date range detection result.
+    pub fn date_range(start: i64, step: i64, count: usize, format: &str, original_len: usize) -> Self {
+        let operator = AlsOperator::date_range(start, step, count, format);
+
+        // Estimate compressed size: %{format}start+step*count
+        let compressed_len = 3 + format.len()
+            + Self::digit_count_i64(start)
+            + 1
+            + Self::digit_count(step.unsigned_abs() as usize)
+            + 1
+            + Self::digit_count(count);
+        let original_size = original_len as f64;
+        let compression_ratio = original_size / compressed_len as f64;
+
+        Self {
+            operator,
+            compression_ratio,
+            pattern_type: PatternType::DateRange,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Create a calendar cycle detection result.
+    pub fn cycle(cycle: CalendarCycle, offset: usize, run: usize, count: usize, original_len: usize) -> Self {
+        let operator = AlsOperator::cycle(cycle, offset, run, count);
+
+        // Estimate compressed size: &name+offset[:run]*count
+        let run_len = if run == 1 { 0 } else { 1 + Self::digit_count(run) };
+        let compressed_len = 2 + cycle.name().len()
+            + Self::digit_count(offset)
+            + run_len
+            + 1
+            + Self::digit_count(count);
+        let original_size = original_len as f64;
+        let compression_ratio = original_size / compressed_len as f64;
+
+        Self {
+            operator,
+            compression_ratio,
+            pattern_type: PatternType::CalendarCycle,
+            candidates: Vec::new(),
         }
     }
 
+    /// Create a boolean runs detection result.
+    pub fn bool_runs(first: &str, second: &str, runs: Vec<usize>, original_len: usize) -> Self {
+        // Estimate compressed size: ?first~second:r1.r2.r3
+        let runs_len: usize = runs.iter().map(|&run| Self::digit_count(run)).sum::<usize>()
+            + runs.len().saturating_sub(1);
+        let compressed_len = 3 + first.len() + second.len() + runs_len;
+        let operator = AlsOperator::bool_runs(first, second, runs);
+        let original_size = original_len as f64;
+        let compression_ratio = original_size / compressed_len as f64;
+
+        Self {
+            operator,
+            compression_ratio,
+            pattern_type: PatternType::Boolean,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Create a float delta detection result.
+    pub fn float_delta(places: u8, start: i64, deltas: Vec<i64>, original_len: usize) -> Self {
+        // Estimate compressed size: %.places:start+d1-d2...
+        let deltas_len: usize = deltas.iter().map(|&delta| 1 + Self::digit_count(delta.unsigned_abs() as usize)).sum();
+        let compressed_len = 3 + Self::digit_count(usize::from(places)) + Self::digit_count_i64(start) + deltas_len;
+        let operator = AlsOperator::float_delta(places, start, deltas);
+        let original_size = original_len as f64;
+        let compression_ratio = original_size / compressed_len as f64;
+
+        Self {
+            operator,
+            compression_ratio,
+            pattern_type: PatternType::FloatDelta,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Wrap the detected operator in a formatting transform.
+    ///
+    /// The compression ratio is recomputed to account for the transform.
+    pub fn with_transform(self, transform: ValueTransform, original_len: usize) -> Self {
+        let original_size = original_len as f64;
+        let compressed_len = original_size / self.compression_ratio + transform.overhead() as f64;
+
+        Self {
+            operator: AlsOperator::transform(self.operator, transform),
+            compression_ratio: original_size / compressed_len,
+            pattern_type: self.pattern_type,
+            candidates: Vec::new(),
+        }
+    }
+
+    /// Attach runner-up results, best first.
+    pub fn with_candidates(mut self, candidates: Vec<DetectionResult>) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// The best runner-up result, if any were recorded.
+    pub fn runner_up(&self) -> Option<&DetectionResult> {
+        self.candidates.first()
+    }
+
     /// Estimate the string length of a range operator.
     fn estimate_range_length(start: i64, end: i64, step: i64) -> f64 {
         let start_len = Self::digit_count_i64(start);
@@ -225,6 +349,23 @@ pub enum PatternType {
     RepeatedRange,
     /// Repeated toggle pattern (e.g., (A~B)*2).
     RepeatedToggle,
+    /// Two-valued boolean column (e.g., ?true~false:3.1.2).
+    Boolean,
+    /// Built-in calendar cycle (e.g., &dow+0*n).
+    CalendarCycle,
+    /// Evenly spaced timestamps (e.g., %{%Y-%m-%d}1704067200+86400*n).
+    DateRange,
+    /// Pattern revealed by stripping shared formatting (e.g., ^ID{3}(1>100)).
+    Transformed,
+    /// Repeated motif inside a column (e.g., x H~B~F*30 y).
+    RepeatedMotif,
+    /// Column derived from an earlier column (e.g., @0(buy:+1 sell:-1)*n).
+    Correlated,
+    /// Decimals drifting by small steps (e.g., %.2:2153+3-1).
+    FloatDelta,
+    /// URL, path or hierarchical enum values encoded part by part
+    /// (e.g., </2(a b)(x y) or <2(us-east-1*2)(a b)).
+    Segmented,
     /// Raw values (no pattern detected).
     Raw,
 }
@@ -301,6 +442,41 @@ mod tests {
         assert_eq!(result.pattern_type, PatternType::RepeatedRange);
     }
 
+    #[test]
+    fn test_detection_result_date_range() {
+        let result = DetectionResult::date_range(1_704_067_200, 86_400, 365, "%Y-%m-%d", 365 * 11 - 1);
+        assert!(result.compression_ratio > 100.0);
+        assert_eq!(result.pattern_type, PatternType::DateRange);
+        assert!(result.operator.is_date_range());
+    }
+
+    #[test]
+    fn test_detection_result_bool_runs() {
+        let result = DetectionResult::bool_runs("true", "false", vec![10, 5, 20], 35 * 5);
+        assert!(result.compression_ratio > 5.0);
+        assert_eq!(result.pattern_type, PatternType::Boolean);
+        assert!(result.operator.is_bool_runs());
+    }
+
+    #[test]
+    fn test_detection_result_cycle() {
+        let result = DetectionResult::cycle(CalendarCycle::Weekday, 0, 1, 70, 70 * 4 - 1);
+        assert!(result.compression_ratio > 10.0);
+        assert_eq!(result.pattern_type, PatternType::CalendarCycle);
+        assert!(result.operator.is_cycle());
+    }
+
+    #[test]
+    fn test_detection_result_with_transform() {
+        let plain = DetectionResult::range(1, 100, 1, 291);
+        let formatted = DetectionResult::range(1, 100, 1, 399)
+            .with_transform(ValueTransform::new().with_width(3), 399);
+        assert!(formatted.operator.is_transform());
+        assert_eq!(formatted.pattern_type, PatternType::Sequential);
+        assert!(formatted.compression_ratio > 1.0);
+        assert!(formatted.compression_ratio < plain.compression_ratio * 399.0 / 291.0);
+    }
+
     #[test]
     fn test_pattern_type_is_compressed() {
         assert!(PatternType::Sequential.is_compressed());
@@ -308,6 +484,13 @@ mod tests {
         assert!(PatternType::Repeat.is_compressed());
         assert!(PatternType::Toggle.is_compressed());
         assert!(PatternType::RepeatedRange.is_compressed());
+        assert!(PatternType::RepeatedMotif.is_compressed());
+        assert!(PatternType::DateRange.is_compressed());
+        assert!(PatternType::CalendarCycle.is_compressed());
+        assert!(PatternType::Boolean.is_compressed());
+        assert!(PatternType::Transformed.is_compressed());
+        assert!(PatternType::Correlated.is_compressed());
+        assert!(PatternType::FloatDelta.is_compressed());
         assert!(!PatternType::Raw.is_compressed());
     }
 
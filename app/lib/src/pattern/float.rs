@@ -0,0 +1,116 @@
+//! Float delta pattern detection.
+//!
+//! This module detects measurement columns of decimals that drift slowly,
+//! and encodes them as the differences of their scaled integers (e.g.,
+//! `%.2:2153+3-1+0`).
+
+use super::detector::{DetectionResult, PatternDetector};
+use crate::als::float::{decimal_places, parse_scaled};
+use crate::als::MAX_FLOAT_DELTA_PLACES;
+
+/// Detector for decimal columns.
+///
+/// Detects readings such as 21.53, 21.56, 21.5, ... → `%.2:2153+3-6`,
+/// which no string-level detector compresses since hardly any two values
+/// repeat. Values are scaled by the most places any of them has, and must
+/// be written the way floats print, which is how the operator expands
+/// them, so a column holding `1.50` is left alone.
+#[derive(Debug, Clone)]
+pub struct FloatDeltaDetector {
+    min_pattern_length: usize,
+}
+
+impl FloatDeltaDetector {
+    /// Create a new float delta detector with the given minimum pattern length.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self { min_pattern_length }
+    }
+
+    /// Scale every value by the most places any of them has.
+    ///
+    /// Returns the places and the first scaled value followed by the
+    /// differences, or `None` if the values are all integers or any is not
+    /// a plain decimal.
+    fn scale(values: &[&str]) -> Option<(u8, i64, Vec<i64>)> {
+        let places = values.iter().map(|value| decimal_places(value)).max()?;
+        let places = u8::try_from(places).ok().filter(|places| (1..=MAX_FLOAT_DELTA_PLACES).contains(places))?;
+        let start = parse_scaled(values[0], places)?;
+        let mut previous = start;
+        let mut deltas = Vec::with_capacity(values.len() - 1);
+        for value in &values[1..] {
+            let scaled = parse_scaled(value, places)?;
+            // Scaled values stay below 10^15, so differences cannot overflow
+            deltas.push(scaled - previous);
+            previous = scaled;
+        }
+        Some((places, start, deltas))
+    }
+
+    /// Calculate the original string length of the values.
+    fn calculate_original_length(values: &[&str]) -> usize {
+        let value_len: usize = values.iter().map(|v| v.len()).sum();
+        let separator_len = values.len().saturating_sub(1);
+        value_len + separator_len
+    }
+}
+
+impl PatternDetector for FloatDeltaDetector {
+    fn detect(&self, values: &[&str]) -> Option<DetectionResult> {
+        if values.len() < self.min_pattern_length.max(2) {
+            return None;
+        }
+
+        let (places, start, deltas) = Self::scale(values)?;
+        let original_len = Self::calculate_original_length(values);
+        let result = DetectionResult::float_delta(places, start, deltas, original_len);
+
+        if result.compression_ratio > 1.0 {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsOperator;
+    use crate::pattern::PatternType;
+
+    #[test]
+    fn test_drifting_readings() {
+        let detector = FloatDeltaDetector::new(3);
+        let values = vec!["21.53", "21.56", "21.55", "21.55", "21.6", "21.58", "22"];
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(result.pattern_type, PatternType::FloatDelta);
+        assert_eq!(result.operator, AlsOperator::float_delta(2, 2153, vec![3, -1, 0, 5, -2, 42]));
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_crosses_zero() {
+        let detector = FloatDeltaDetector::new(3);
+        let values = vec!["0.125", "0.06", "-0.005", "-0.07", "-1.135"];
+        let result = detector.detect(&values).unwrap();
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_rejects_other_spellings() {
+        let detector = FloatDeltaDetector::new(3);
+        assert!(detector.detect(&["1.5", "1.50", "1.55"]).is_none());
+        assert!(detector.detect(&["1.5", "-0", "1.55"]).is_none());
+        assert!(detector.detect(&["1", "2", "3"]).is_none());
+        assert!(detector.detect(&["1.5", "n/a", "1.6"]).is_none());
+        assert!(detector.detect(&["1.5", "1.6"]).is_none());
+    }
+
+    #[test]
+    fn test_rejects_wild_values() {
+        // Differences as long as the values save nothing
+        let detector = FloatDeltaDetector::new(3);
+        assert!(detector.detect(&["1.5", "-9023.7", "88213.4", "-0.1"]).is_none());
+    }
+}
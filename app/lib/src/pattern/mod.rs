@@ -2,20 +2,45 @@
 //!
 //! This module provides pattern detection algorithms that analyze column data
 //! and identify compressible patterns such as sequential ranges, repetitions,
-//! alternations, and combined patterns.
+//! alternations, combined patterns, boolean flags, calendar cycles, evenly spaced timestamps,
+//! slowly drifting decimal measurements, patterns hidden behind shared
+//! formatting, repeated motifs embedded in a column, columns that move in
+//! lockstep with each other, and URL or path columns whose segments repeat.
 
 mod detector;
 mod range;
 mod repeat;
 mod toggle;
 mod combined;
+mod boolean;
+mod correlation;
+mod cost;
+mod cycle;
+mod date;
+mod float;
+mod motif;
+mod segment;
+mod transform;
 
 pub use detector::{DetectionResult, PatternDetector, PatternType};
 pub use range::RangeDetector;
 pub use repeat::{RepeatDetector, RunDetector};
 pub use toggle::ToggleDetector;
 pub use combined::CombinedDetector;
+pub use boolean::BooleanDetector;
+pub use correlation::CorrelationDetector;
+pub use cost::{CostModel, TextCostModel};
+pub use cycle::CalendarCycleDetector;
+pub use date::DateRangeDetector;
+pub use float::FloatDeltaDetector;
+pub use motif::{MotifDetector, MotifSpan};
+pub use segment::SegmentDetector;
+pub use transform::TransformDetector;
 
+use std::sync::Arc;
+
+use crate::als::AlsOperator;
+use crate::compress::{arena, metrics};
 use crate::config::CompressorConfig;
 
 /// Main pattern detection engine that combines all detectors.
@@ -29,6 +54,14 @@ pub struct PatternEngine {
     repeat_detector: RepeatDetector,
     toggle_detector: ToggleDetector,
     combined_detector: CombinedDetector,
+    boolean_detector: BooleanDetector,
+    cycle_detector: CalendarCycleDetector,
+    date_detector: DateRangeDetector,
+    float_detector: FloatDeltaDetector,
+    transform_detector: TransformDetector,
+    motif_detector: MotifDetector,
+    correlation_detector: CorrelationDetector,
+    segment_detector: SegmentDetector,
 }
 
 impl PatternEngine {
@@ -43,7 +76,16 @@ impl PatternEngine {
             range_detector: RangeDetector::new(config.min_pattern_length),
             repeat_detector: RepeatDetector::new(config.min_pattern_length),
             toggle_detector: ToggleDetector::new(config.min_pattern_length),
-            combined_detector: CombinedDetector::new(config.min_pattern_length),
+            combined_detector: CombinedDetector::new(config.min_pattern_length)
+                .with_cost_model(Arc::clone(&config.cost_model)),
+            boolean_detector: BooleanDetector::new(config.min_pattern_length),
+            cycle_detector: CalendarCycleDetector::new(config.min_pattern_length),
+            date_detector: DateRangeDetector::new(config.min_pattern_length),
+            float_detector: FloatDeltaDetector::new(config.min_pattern_length),
+            transform_detector: TransformDetector::new(config.min_pattern_length),
+            motif_detector: MotifDetector::new(config.min_pattern_length),
+            correlation_detector: CorrelationDetector::new(),
+            segment_detector: SegmentDetector::new(config.min_pattern_length),
             config,
         }
     }
@@ -51,8 +93,18 @@ impl PatternEngine {
     /// Detect the best pattern for the given values.
     ///
     /// Analyzes the values using all available detectors and returns
-    /// the result with the best compression ratio.
+    /// the result with the best compression ratio. Values sharing a
+    /// common format are also checked for patterns once the format is
+    /// stripped.
     pub fn detect(&self, values: &[&str]) -> DetectionResult {
+        self.detect_n_best(values, 1)
+    }
+
+    /// Detect the best pattern and keep up to `n - 1` runners-up.
+    ///
+    /// The runners-up are stored in the result's `candidates`, best first.
+    /// Only results that compress at all are reported.
+    pub fn detect_n_best(&self, values: &[&str], n: usize) -> DetectionResult {
         if values.is_empty() {
             return DetectionResult::raw_empty();
         }
@@ -61,38 +113,168 @@ impl PatternEngine {
             return DetectionResult::raw_from_values(values);
         }
 
-        // Collect results from all detectors
-        let mut best_result = DetectionResult::raw_from_values(values);
+        let mut candidates = self.direct_candidates(values);
 
-        // Try range detection (for integer sequences)
-        if let Some(result) = self.range_detector.detect(values) {
-            if result.compression_ratio > best_result.compression_ratio {
-                best_result = result;
+        // A date range stays right across month ends, unlike a transform of
+        // the digits of the same dates once the column is scaled or sliced,
+        // so dates skip the second pass
+        let dates = candidates
+            .iter()
+            .any(|candidate| candidate.pattern_type == PatternType::DateRange && candidate.compression_ratio > 1.0);
+
+        // Try the detectors again on values with their formatting removed
+        let transforms = if dates {
+            Vec::new()
+        } else {
+            metrics::timed("TransformDetector", || self.transform_detector.candidates(values))
+        };
+        for (transform, inner) in transforms {
+            let inner_result = arena::with_strs(&inner, |inner_refs| self.detect_direct(inner_refs));
+            if !inner_result.pattern_type.is_compressed() {
+                continue;
             }
+
+            let operator = AlsOperator::transform(inner_result.operator, transform);
+            let compression_ratio = self
+                .config
+                .cost_model
+                .compression_ratio(Self::original_length(values), &operator);
+            candidates.push(DetectionResult::new(operator, compression_ratio, PatternType::Transformed));
         }
 
-        // Try repeat detection
-        if let Some(result) = self.repeat_detector.detect(values) {
-            if result.compression_ratio > best_result.compression_ratio {
-                best_result = result;
-            }
+        Self::rank(values, candidates, n)
+    }
+
+    /// Detect the best pattern with the cheap whole-column detectors only.
+    ///
+    /// Skips the combined detector and the second pass over values with
+    /// their formatting removed, whose cost is not repaid on small inputs.
+    pub fn detect_quick(&self, values: &[&str]) -> DetectionResult {
+        if values.is_empty() {
+            return DetectionResult::raw_empty();
         }
 
-        // Try toggle detection
-        if let Some(result) = self.toggle_detector.detect(values) {
-            if result.compression_ratio > best_result.compression_ratio {
-                best_result = result;
-            }
+        if values.len() < self.config.min_pattern_length {
+            return DetectionResult::raw_from_values(values);
         }
 
-        // Try combined pattern detection
-        if let Some(result) = self.combined_detector.detect(values) {
-            if result.compression_ratio > best_result.compression_ratio {
-                best_result = result;
+        let combined = self.combined_detector.name();
+        let detectors = self.direct_detectors().into_iter().filter(|detector| detector.name() != combined);
+        Self::rank(values, self.score(values, detectors), 1)
+    }
+
+    /// Run the whole-column detectors and keep only the best result.
+    fn detect_direct(&self, values: &[&str]) -> DetectionResult {
+        Self::rank(values, self.direct_candidates(values), 1)
+    }
+
+    /// Run the whole-column detectors on the values as they are.
+    ///
+    /// With a detection sample size configured, long columns only go
+    /// through the detectors that find a pattern in their leading sample.
+    fn direct_candidates(&self, values: &[&str]) -> Vec<DetectionResult> {
+        let detectors = self.direct_detectors();
+        match self.config.detection_sample_size {
+            Some(size) if values.len() > size => {
+                let sample = &values[..size];
+                let candidates = detectors.into_iter().filter(|detector| {
+                    detector
+                        .detect(sample)
+                        .is_some_and(|result| result.compression_ratio > 1.0)
+                });
+                self.score(values, candidates)
             }
+            _ => self.score(values, detectors),
+        }
+    }
+
+    /// Whole-column detectors, in order of preference on ties.
+    fn direct_detectors(&self) -> [&dyn PatternDetector; 8] {
+        [
+            // Integer sequences
+            &self.range_detector,
+            &self.repeat_detector,
+            &self.toggle_detector,
+            &self.combined_detector,
+            // Two-valued flag columns
+            &self.boolean_detector,
+            // Day/month/quarter names
+            &self.cycle_detector,
+            // Timestamp columns
+            &self.date_detector,
+            // Measurement columns
+            &self.float_detector,
+        ]
+    }
+
+    /// Run the given detectors, re-scoring their results with the
+    /// configured cost model so detectors' own size estimates do not
+    /// decide between candidates.
+    fn score<'d>(&self, values: &[&str], detectors: impl IntoIterator<Item = &'d dyn PatternDetector>) -> Vec<DetectionResult> {
+        let original_len = Self::original_length(values);
+        detectors
+            .into_iter()
+            .filter_map(|detector| metrics::timed(detector.name(), || detector.detect(values)))
+            .map(|mut result| {
+                result.compression_ratio = self.config.cost_model.compression_ratio(original_len, &result.operator);
+                result
+            })
+            .collect()
+    }
+
+    /// Order candidates best first and keep the winner plus `n - 1` runners-up.
+    ///
+    /// Candidates that do not beat raw encoding are dropped; ties keep the
+    /// order the candidates were produced in.
+    fn rank(values: &[&str], mut candidates: Vec<DetectionResult>, n: usize) -> DetectionResult {
+        candidates.retain(|candidate| candidate.compression_ratio > 1.0);
+        candidates.sort_by(|a, b| b.compression_ratio.total_cmp(&a.compression_ratio));
+
+        let mut ranked = candidates.into_iter();
+        match ranked.next() {
+            Some(best) => best.with_candidates(ranked.take(n.saturating_sub(1)).collect()),
+            None => DetectionResult::raw_from_values(values),
+        }
+    }
+
+    /// Calculate the original string length of the values.
+    fn original_length(values: &[&str]) -> usize {
+        let value_len: usize = values.iter().map(|v| v.len()).sum();
+        value_len + values.len().saturating_sub(1)
+    }
+
+    /// Find repeated motifs anywhere in the given values.
+    ///
+    /// Used when no single operator covers the whole column: the returned
+    /// spans can be encoded as operators while the values between them are
+    /// encoded individually.
+    pub fn detect_motifs(&self, values: &[&str]) -> Vec<MotifSpan> {
+        if values.len() < self.config.min_pattern_length {
+            return Vec::new();
         }
 
-        best_result
+        metrics::timed("MotifDetector", || self.motif_detector.find_motifs(values))
+    }
+
+    /// Split URL or path values into per-segment sub-columns.
+    ///
+    /// Returns the segments of every value, one vector per segment
+    /// position, when the segments repeat more than the whole values.
+    pub fn split_segments<'a>(&self, values: &[&'a str]) -> Option<Vec<Vec<&'a str>>> {
+        metrics::timed("SegmentDetector", || self.segment_detector.split(values))
+    }
+
+    /// Find a mapping that derives the `target` column from the `source` column.
+    ///
+    /// Returns the value translations needed to encode `target` as a
+    /// column reference to `source`, or `None` if the columns are not
+    /// in lockstep.
+    pub fn detect_column_mapping<S, T>(&self, source: &[S], target: &[T]) -> Option<Vec<(String, String)>>
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+    {
+        metrics::timed("CorrelationDetector", || self.correlation_detector.find_mapping(source, target))
     }
 
     /// Get the minimum pattern length configuration.
@@ -151,6 +333,17 @@ mod tests {
         assert_eq!(result.pattern_type, PatternType::Sequential);
     }
 
+    #[test]
+    fn test_pattern_engine_detect_quick_skips_combined() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["1", "2", "3", "4", "1", "2", "3", "4", "1", "2", "3", "4"];
+        assert_eq!(engine.detect(&values).pattern_type, PatternType::RepeatedRange);
+        assert_ne!(engine.detect_quick(&values).pattern_type, PatternType::RepeatedRange);
+
+        let values: Vec<&str> = vec!["1", "2", "3", "4", "5", "6"];
+        assert_eq!(engine.detect_quick(&values).pattern_type, PatternType::Sequential);
+    }
+
     #[test]
     fn test_pattern_engine_selects_repeat() {
         let engine = PatternEngine::new();
@@ -182,6 +375,73 @@ mod tests {
         assert_eq!(result.pattern_type, PatternType::RepeatedRange);
     }
 
+    #[test]
+    fn test_pattern_engine_sampling_keeps_full_column_result() {
+        let numbers: Vec<String> = (1..=5_000).map(|i| i.to_string()).collect();
+        let values: Vec<&str> = numbers.iter().map(String::as_str).collect();
+
+        let full = PatternEngine::new().detect(&values);
+        let sampled = PatternEngine::with_config(CompressorConfig::new().with_detection_sample_size(100)).detect(&values);
+        assert_eq!(sampled.pattern_type, PatternType::Sequential);
+        assert_eq!(sampled.operator, full.operator);
+    }
+
+    #[test]
+    fn test_pattern_engine_sampling_verifies_on_full_column() {
+        // The sample is a clean repeat, but the full column is not.
+        let mut values = vec!["x"; 50];
+        values.extend(["a", "b", "c", "d", "e", "f", "g", "h"]);
+
+        let engine = PatternEngine::with_config(CompressorConfig::new().with_detection_sample_size(10));
+        let result = engine.detect(&values);
+        assert_ne!(result.pattern_type, PatternType::Repeat);
+        assert_eq!(result.pattern_type, PatternEngine::new().detect(&values).pattern_type);
+    }
+
+    #[test]
+    fn test_pattern_engine_uses_configured_cost_model() {
+        /// Text costs, except that plain toggles are priced out.
+        #[derive(Debug)]
+        struct NoToggles;
+
+        impl CostModel for NoToggles {
+            fn operator_cost(&self, operator: &AlsOperator) -> f64 {
+                match operator {
+                    AlsOperator::Toggle { .. } => 1_000.0,
+                    other => TextCostModel.operator_cost(other),
+                }
+            }
+        }
+
+        let values: Vec<&str> = vec!["T", "F", "T", "F", "T", "F", "T", "F"];
+        assert_eq!(PatternEngine::new().detect(&values).pattern_type, PatternType::Toggle);
+
+        let config = CompressorConfig::new().with_cost_model(Arc::new(NoToggles));
+        let result = PatternEngine::with_config(config).detect(&values);
+        assert_ne!(result.pattern_type, PatternType::Toggle);
+        assert!(result.compression_ratio > 1.0);
+    }
+
+    #[test]
+    fn test_pattern_engine_n_best() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["1", "2", "3", "1", "2", "3", "1", "2", "3"];
+
+        let single = engine.detect(&values);
+        assert!(single.candidates.is_empty());
+
+        let result = engine.detect_n_best(&values, 3);
+        assert_eq!(result.pattern_type, single.pattern_type);
+        assert_eq!(result.operator, single.operator);
+        assert!(!result.candidates.is_empty() && result.candidates.len() <= 2);
+        let ratios: Vec<f64> = std::iter::once(&result)
+            .chain(&result.candidates)
+            .map(|r| r.compression_ratio)
+            .collect();
+        assert!(ratios.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(ratios.iter().all(|&ratio| ratio > 1.0));
+    }
+
     #[test]
     fn test_pattern_engine_falls_back_to_raw() {
         let engine = PatternEngine::new();
@@ -211,6 +471,105 @@ mod tests {
         assert_eq!(result.pattern_type, PatternType::Arithmetic);
     }
 
+    #[test]
+    fn test_pattern_engine_selects_date_range() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["2024-01-01", "2024-01-02", "2024-01-03", "2024-01-04"];
+        let result = engine.detect(&values);
+        assert_eq!(result.pattern_type, PatternType::DateRange);
+    }
+
+    #[test]
+    fn test_pattern_engine_selects_float_delta() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["21.53", "21.56", "21.55", "21.55", "21.61", "21.58", "21.6"];
+        let result = engine.detect(&values);
+        assert_eq!(result.pattern_type, PatternType::FloatDelta);
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+
+        // Steady readings are better as a repeat
+        let values: Vec<&str> = vec!["21.50"; 8];
+        assert_eq!(engine.detect(&values).pattern_type, PatternType::Repeat);
+    }
+
+    #[test]
+    fn test_pattern_engine_selects_boolean() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["true", "false", "false", "true", "true", "true", "false", "true"];
+        let result = engine.detect(&values);
+        assert_eq!(result.pattern_type, PatternType::Boolean);
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_pattern_engine_prefers_toggle_for_alternating_booleans() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["T", "F", "T", "F", "T", "F", "T", "F"];
+        assert_eq!(engine.detect(&values).pattern_type, PatternType::Toggle);
+    }
+
+    #[test]
+    fn test_pattern_engine_selects_calendar_cycle() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec![
+            "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun",
+            "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun",
+        ];
+        let result = engine.detect(&values);
+        // Naming the cycle beats spelling out a toggle
+        assert_eq!(result.pattern_type, PatternType::CalendarCycle);
+    }
+
+    #[test]
+    fn test_pattern_engine_selects_prefixed_range() {
+        let engine = PatternEngine::new();
+        let values: Vec<String> = (1..=100).map(|i| format!("USER{:03}", i)).collect();
+        let refs: Vec<&str> = values.iter().map(|s| s.as_str()).collect();
+        let result = engine.detect(&refs);
+
+        assert_eq!(result.pattern_type, PatternType::Transformed);
+        assert!(result.compression_ratio > 20.0);
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_pattern_engine_selects_uppercase_cycle() {
+        let engine = PatternEngine::new();
+        let values: Vec<&str> = vec!["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG"];
+        let result = engine.detect(&values);
+
+        assert_eq!(result.pattern_type, PatternType::Transformed);
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_pattern_engine_detect_motifs() {
+        let engine = PatternEngine::new();
+        let mut values: Vec<&str> = vec!["first"];
+        for _ in 0..20 {
+            values.extend(["header", "body", "footer"]);
+        }
+        values.push("last");
+
+        // The whole column is not a single pattern
+        assert_eq!(engine.detect(&values).pattern_type, PatternType::Raw);
+
+        let spans = engine.detect_motifs(&values);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].start, 1);
+        assert_eq!(spans[0].period, 3);
+        assert_eq!(spans[0].repeat_count, 20);
+    }
+
+    #[test]
+    fn test_pattern_engine_detect_column_mapping() {
+        let engine = PatternEngine::new();
+        let side = vec!["buy", "sell", "buy", "buy"];
+        let sign = vec!["+1", "-1", "+1", "+1"];
+        assert_eq!(engine.detect_column_mapping(&side, &sign).unwrap().len(), 2);
+        assert!(engine.detect_column_mapping(&sign, &["a", "b", "c", "d"]).is_none());
+    }
+
     #[test]
     fn test_pattern_engine_is_send_sync() {
         fn assert_send_sync<T: Send + Sync>() {}
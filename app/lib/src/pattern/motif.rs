@@ -0,0 +1,405 @@
+//! Repeated motif detection.
+//!
+//! This module finds repeated multi-value motifs anywhere inside a column,
+//! such as header/body/footer blocks that recur hundreds of times between
+//! unrelated values. Unlike `CombinedDetector`, which requires the whole
+//! column to be an exact k-fold repetition, motifs may start and stop at
+//! arbitrary positions and several motifs may appear in the same column.
+//!
+//! Windows of values are compared with polynomial rolling hashes over
+//! interned value ids, so each candidate comparison is O(1); hash hits are
+//! verified against the ids to rule out collisions.
+
+use std::collections::HashMap;
+
+use crate::als::AlsOperator;
+
+/// Multiplier for the polynomial rolling hash.
+const HASH_BASE: u64 = 0x100_0000_01b3;
+
+/// A repeated motif found within a column.
+///
+/// The values in `start..start + period` repeat `repeat_count` times
+/// back to back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotifSpan {
+    /// Index of the first value covered by the motif.
+    pub start: usize,
+    /// Number of values in one repetition of the motif.
+    pub period: usize,
+    /// Number of consecutive repetitions.
+    pub repeat_count: usize,
+}
+
+impl MotifSpan {
+    /// Total number of values covered by this span.
+    pub fn len(&self) -> usize {
+        self.period * self.repeat_count
+    }
+
+    /// Check if the span covers no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index one past the last value covered by this span.
+    pub fn end(&self) -> usize {
+        self.start + self.len()
+    }
+
+    /// Build the operator that encodes this span.
+    ///
+    /// Single-value motifs become `val*n`; longer motifs become a
+    /// multi-value toggle (`a~b~c*n`) that cycles through the motif.
+    pub fn to_operator<'v>(&self, values: &[&'v str]) -> AlsOperator<'v> {
+        let motif = &values[self.start..self.start + self.period];
+        if self.period == 1 {
+            AlsOperator::multiply(AlsOperator::raw(motif[0]), self.repeat_count)
+        } else {
+            AlsOperator::toggle_multi(motif.to_vec(), self.len())
+        }
+    }
+}
+
+/// Detector for repeated motifs embedded in a column.
+///
+/// Detects patterns like:
+/// - `x, H, B, F, H, B, F, H, B, F, y` → `x H~B~F*9 y`
+/// - `a, b, c, c, c, c, d` → `a b c*4 d`
+#[derive(Debug, Clone)]
+pub struct MotifDetector {
+    min_pattern_length: usize,
+    max_motif_length: usize,
+}
+
+impl MotifDetector {
+    /// Default upper bound on the number of values in a single motif.
+    pub const DEFAULT_MAX_MOTIF_LENGTH: usize = 32;
+
+    /// Create a new motif detector with the given minimum pattern length.
+    ///
+    /// A span must cover at least `min_pattern_length` values to be reported.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self {
+            min_pattern_length,
+            max_motif_length: Self::DEFAULT_MAX_MOTIF_LENGTH,
+        }
+    }
+
+    /// Set the maximum number of values in a single motif.
+    pub fn with_max_motif_length(mut self, max_motif_length: usize) -> Self {
+        self.max_motif_length = max_motif_length.max(1);
+        self
+    }
+
+    /// Get the maximum motif length.
+    pub fn max_motif_length(&self) -> usize {
+        self.max_motif_length
+    }
+
+    /// Find non-overlapping repeated motifs in the values.
+    ///
+    /// Scans left to right; at each position the motif length with the
+    /// largest estimated byte saving wins and the scan resumes after it.
+    /// Only spans that are shorter when encoded than the raw values are
+    /// returned, in ascending order of `start`.
+    pub fn find_motifs(&self, values: &[&str]) -> Vec<MotifSpan> {
+        let mut spans = Vec::new();
+        if values.len() < self.min_pattern_length.max(2) {
+            return spans;
+        }
+
+        let hashes = RollingHash::new(values);
+        let mut position = 0;
+
+        while position < values.len() {
+            match self.best_span_at(values, &hashes, position) {
+                Some(span) => {
+                    position = span.end();
+                    spans.push(span);
+                }
+                None => position += 1,
+            }
+        }
+
+        spans
+    }
+
+    /// Find the most beneficial motif starting exactly at `start`.
+    fn best_span_at(&self, values: &[&str], hashes: &RollingHash, start: usize) -> Option<MotifSpan> {
+        let remaining = values.len() - start;
+        let max_period = self.max_motif_length.min(remaining / 2);
+
+        let mut best: Option<(MotifSpan, usize)> = None;
+
+        for period in 1..=max_period {
+            if !hashes.windows_equal(start, start + period, period) {
+                continue;
+            }
+
+            let mut repeat_count = 2;
+            while start + (repeat_count + 1) * period <= values.len()
+                && hashes.windows_equal(start, start + repeat_count * period, period)
+            {
+                repeat_count += 1;
+            }
+
+            let span = MotifSpan {
+                start,
+                period,
+                repeat_count,
+            };
+            if span.len() < self.min_pattern_length {
+                continue;
+            }
+
+            let original_len = Self::calculate_original_length(&values[span.start..span.end()]);
+            let encoded_len = Self::estimate_encoded_length(values, &span);
+            if encoded_len >= original_len {
+                continue;
+            }
+
+            let saved = original_len - encoded_len;
+            if best.as_ref().is_none_or(|&(_, best_saved)| saved > best_saved) {
+                best = Some((span, saved));
+            }
+        }
+
+        best.map(|(span, _)| span)
+    }
+
+    /// Estimate the encoded length of a span, including its separator.
+    fn estimate_encoded_length(values: &[&str], span: &MotifSpan) -> usize {
+        let motif = &values[span.start..span.start + span.period];
+        let motif_len: usize = motif.iter().map(|v| v.len()).sum();
+        let count = if span.period == 1 {
+            span.repeat_count
+        } else {
+            span.len()
+        };
+        // motif values + '~' between them + '*' + count + separator
+        motif_len + (span.period - 1) + 1 + Self::digit_count(count) + 1
+    }
+
+    /// Calculate the original string length of the values, one separator each.
+    fn calculate_original_length(values: &[&str]) -> usize {
+        values.iter().map(|v| v.len() + 1).sum()
+    }
+
+    /// Count digits in a usize.
+    fn digit_count(mut n: usize) -> usize {
+        let mut digits = 1;
+        while n >= 10 {
+            n /= 10;
+            digits += 1;
+        }
+        digits
+    }
+}
+
+impl Default for MotifDetector {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+/// Prefix rolling hashes over interned value ids.
+struct RollingHash {
+    ids: Vec<u32>,
+    prefix: Vec<u64>,
+    powers: Vec<u64>,
+}
+
+impl RollingHash {
+    fn new(values: &[&str]) -> Self {
+        let mut interned: HashMap<&str, u32> = HashMap::new();
+        let ids: Vec<u32> = values
+            .iter()
+            .map(|&v| {
+                let next_id = interned.len() as u32;
+                *interned.entry(v).or_insert(next_id)
+            })
+            .collect();
+
+        let mut prefix = Vec::with_capacity(ids.len() + 1);
+        let mut powers = Vec::with_capacity(ids.len() + 1);
+        prefix.push(0u64);
+        powers.push(1u64);
+        for (i, &id) in ids.iter().enumerate() {
+            prefix.push(prefix[i].wrapping_mul(HASH_BASE).wrapping_add(Self::mix(id)));
+            powers.push(powers[i].wrapping_mul(HASH_BASE));
+        }
+
+        Self { ids, prefix, powers }
+    }
+
+    /// Spread ids across the full 64-bit range (splitmix64 finalizer).
+    fn mix(id: u32) -> u64 {
+        let mut z = (id as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Hash of the window `start..start + len`.
+    fn window(&self, start: usize, len: usize) -> u64 {
+        self.prefix[start + len].wrapping_sub(self.prefix[start].wrapping_mul(self.powers[len]))
+    }
+
+    /// Check whether the windows at `a` and `b` of length `len` hold the same values.
+    fn windows_equal(&self, a: usize, b: usize, len: usize) -> bool {
+        self.window(a, len) == self.window(b, len)
+            && self.ids[a..a + len] == self.ids[b..b + len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_spans(values: &[&str], spans: &[MotifSpan]) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut position = 0;
+        for span in spans {
+            result.extend(values[position..span.start].iter().map(|v| v.to_string()));
+            result.extend(span.to_operator(values).expand(None).unwrap());
+            position = span.end();
+        }
+        result.extend(values[position..].iter().map(|v| v.to_string()));
+        result
+    }
+
+    #[test]
+    fn test_no_motif_in_distinct_values() {
+        let detector = MotifDetector::new(3);
+        let values: Vec<&str> = vec!["a", "b", "c", "d", "e", "f"];
+        assert!(detector.find_motifs(&values).is_empty());
+    }
+
+    #[test]
+    fn test_empty_and_short_input() {
+        let detector = MotifDetector::new(3);
+        assert!(detector.find_motifs(&[]).is_empty());
+        assert!(detector.find_motifs(&["a", "a"]).is_empty());
+    }
+
+    #[test]
+    fn test_block_motif_with_prefix_and_suffix() {
+        let detector = MotifDetector::new(3);
+        let mut values: Vec<&str> = vec!["start"];
+        for _ in 0..50 {
+            values.extend(["header", "body", "body", "body", "footer"]);
+        }
+        values.push("end");
+
+        let spans = detector.find_motifs(&values);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(
+            spans[0],
+            MotifSpan {
+                start: 1,
+                period: 5,
+                repeat_count: 50,
+            }
+        );
+
+        if let AlsOperator::Toggle { values: motif, count } = spans[0].to_operator(&values) {
+            assert_eq!(motif, vec!["header", "body", "body", "body", "footer"]);
+            assert_eq!(count, 250);
+        } else {
+            panic!("Expected Toggle operator");
+        }
+    }
+
+    #[test]
+    fn test_single_value_run() {
+        let detector = MotifDetector::new(3);
+        let values: Vec<&str> = vec!["a", "b", "c", "c", "c", "c", "c", "d"];
+        let spans = detector.find_motifs(&values);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].start, 2);
+        assert_eq!(spans[0].period, 1);
+        assert_eq!(spans[0].repeat_count, 5);
+
+        if let AlsOperator::Multiply { value, count } = spans[0].to_operator(&values) {
+            assert_eq!(*value, AlsOperator::raw("c"));
+            assert_eq!(count, 5);
+        } else {
+            panic!("Expected Multiply operator");
+        }
+    }
+
+    #[test]
+    fn test_multiple_motifs() {
+        let detector = MotifDetector::new(3);
+        let mut values: Vec<&str> = Vec::new();
+        for _ in 0..10 {
+            values.extend(["GET", "POST"]);
+        }
+        values.push("break");
+        for _ in 0..10 {
+            values.extend(["x", "y", "z"]);
+        }
+
+        let spans = detector.find_motifs(&values);
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[0].start, spans[0].period, spans[0].repeat_count), (0, 2, 10));
+        assert_eq!((spans[1].start, spans[1].period, spans[1].repeat_count), (21, 3, 10));
+    }
+
+    #[test]
+    fn test_spans_roundtrip() {
+        let detector = MotifDetector::new(3);
+        let mut values: Vec<&str> = vec!["p", "q"];
+        for _ in 0..7 {
+            values.extend(["alpha", "beta", "gamma", "beta"]);
+        }
+        values.extend(["alpha", "beta", "r", "r", "r", "r"]);
+
+        let spans = detector.find_motifs(&values);
+        assert!(!spans.is_empty());
+        let expanded = expand_spans(&values, &spans);
+        let original: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        assert_eq!(expanded, original);
+    }
+
+    #[test]
+    fn test_span_shorter_than_min_pattern_length_ignored() {
+        let detector = MotifDetector::new(5);
+        let values: Vec<&str> = vec!["a", "b", "a", "b"];
+        assert!(detector.find_motifs(&values).is_empty());
+    }
+
+    #[test]
+    fn test_max_motif_length() {
+        let detector = MotifDetector::new(3).with_max_motif_length(2);
+        assert_eq!(detector.max_motif_length(), 2);
+
+        let mut values: Vec<&str> = Vec::new();
+        for _ in 0..20 {
+            values.extend(["one", "two", "three"]);
+        }
+        assert!(detector.find_motifs(&values).is_empty());
+    }
+
+    #[test]
+    fn test_rolling_hash_windows() {
+        let values: Vec<&str> = vec!["a", "b", "c", "a", "b", "c", "a", "b"];
+        let hashes = RollingHash::new(&values);
+        assert!(hashes.windows_equal(0, 3, 3));
+        assert!(hashes.windows_equal(0, 6, 2));
+        assert!(!hashes.windows_equal(0, 1, 2));
+    }
+
+    #[test]
+    fn test_motif_span_bounds() {
+        let span = MotifSpan {
+            start: 4,
+            period: 3,
+            repeat_count: 5,
+        };
+        assert_eq!(span.len(), 15);
+        assert_eq!(span.end(), 19);
+        assert!(!span.is_empty());
+    }
+}
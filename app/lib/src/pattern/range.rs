@@ -1,9 +1,11 @@
 //! Sequential and arithmetic range pattern detection.
 //!
 //! This module detects integer sequences that can be encoded using
-//! range syntax (e.g., `1>5` or `10>50:10`).
+//! range syntax (e.g., `1>5` or `10>50:10`). Zero-padded or grouped
+//! numbers keep their formatting through a transform (e.g., `^{4}(1>5)`).
 
 use super::detector::{DetectionResult, PatternDetector};
+use crate::als::{ValueTransform, GROUP_SEPARATORS};
 
 /// Detector for sequential and arithmetic range patterns.
 ///
@@ -11,6 +13,8 @@ use super::detector::{DetectionResult, PatternDetector};
 /// - Sequential integers with step 1 (e.g., 1, 2, 3, 4, 5 → `1>5`)
 /// - Arithmetic sequences with custom step (e.g., 10, 20, 30 → `10>30:10`)
 /// - Descending sequences (e.g., 5, 4, 3, 2, 1 → `5>1`)
+/// - Zero-padded sequences (e.g., 0001, 0002, 0003 → `^{4}(1>3)`)
+/// - Grouped sequences (e.g., 1,000 2,000 3,000 → `^{,}(1000>3000:1000)`)
 #[derive(Debug, Clone)]
 pub struct RangeDetector {
     min_pattern_length: usize,
@@ -27,6 +31,50 @@ impl RangeDetector {
         s.trim().parse::<i64>().ok()
     }
 
+    /// Parse values as integers and infer their shared number format.
+    ///
+    /// Returns the integers and a transform reproducing the original
+    /// text, or `None` if the values are not uniformly formatted integers.
+    fn parse_formatted(values: &[&str]) -> Option<(Vec<i64>, ValueTransform)> {
+        let trimmed: Vec<&str> = values.iter().map(|v| v.trim()).collect();
+        let grouping = trimmed
+            .iter()
+            .flat_map(|v| v.chars())
+            .find(|c| GROUP_SEPARATORS.contains(c));
+
+        let mut width = 0;
+        let mut integers = Vec::with_capacity(values.len());
+        for value in &trimmed {
+            let plain: String = match grouping {
+                Some(separator) => value.chars().filter(|&c| c != separator).collect(),
+                None => value.to_string(),
+            };
+            integers.push(Self::parse_integer(&plain)?);
+
+            let digits = plain.trim_start_matches('-');
+            if digits.len() > 1 && digits.starts_with('0') {
+                width = width.max(plain.len());
+            }
+        }
+
+        let mut transform = ValueTransform::new().with_width(width);
+        if let Some(separator) = grouping {
+            transform = transform.with_grouping(separator);
+        }
+
+        // A non-trivial format must reproduce every value exactly
+        if !transform.is_identity()
+            && integers
+                .iter()
+                .zip(&trimmed)
+                .any(|(n, value)| transform.apply(&n.to_string()) != *value)
+        {
+            return None;
+        }
+
+        Some((integers, transform))
+    }
+
     /// Detect a range pattern in the values.
     ///
     /// Returns the start, end, and step if a valid range is detected.
@@ -36,7 +84,7 @@ impl RangeDetector {
         }
 
         let start = values[0];
-        let step = values[1] - values[0];
+        let step = values[1].checked_sub(values[0])?;
 
         // Step of 0 means all values are the same - not a range pattern
         if step == 0 {
@@ -71,15 +119,17 @@ impl PatternDetector for RangeDetector {
         }
 
         // Try to parse all values as integers
-        let integers: Option<Vec<i64>> = values.iter().map(|s| Self::parse_integer(s)).collect();
-        let integers = integers?;
+        let (integers, format) = Self::parse_formatted(values)?;
 
         // Detect range pattern
         let (start, end, step) = self.detect_range(&integers)?;
 
         // Calculate compression benefit
         let original_len = Self::calculate_original_length(values);
-        let result = DetectionResult::range(start, end, step, original_len);
+        let mut result = DetectionResult::range(start, end, step, original_len);
+        if !format.is_identity() {
+            result = result.with_transform(format, original_len);
+        }
 
         // Only return if there's compression benefit
         if result.compression_ratio > 1.0 {
@@ -224,6 +274,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_zero_padded_sequence() {
+        let detector = RangeDetector::new(3);
+        let values: Vec<&str> = vec!["0008", "0009", "0010", "0011"];
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(result.pattern_type, PatternType::Sequential);
+        assert!(result.operator.is_transform());
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_thousands_separated_sequence() {
+        let detector = RangeDetector::new(3);
+        let values: Vec<&str> = vec!["1,000", "2,000", "3,000", "4,000", "5,000"];
+        let result = detector.detect(&values).unwrap();
+
+        assert_eq!(result.pattern_type, PatternType::Arithmetic);
+        assert_eq!(result.operator.expand(None).unwrap(), values);
+    }
+
+    #[test]
+    fn test_inconsistent_padding() {
+        let detector = RangeDetector::new(3);
+        let values: Vec<&str> = vec!["08", "009", "010"];
+        assert!(detector.detect(&values).is_none());
+    }
+
+    #[test]
+    fn test_inconsistent_grouping() {
+        let detector = RangeDetector::new(3);
+        let values: Vec<&str> = vec!["998", "999", "1000", "1,001"];
+        assert!(detector.detect(&values).is_none());
+    }
+
     #[test]
     fn test_whitespace_handling() {
         let detector = RangeDetector::new(3);
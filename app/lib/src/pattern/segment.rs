@@ -0,0 +1,142 @@
+//! URL and path segment detection.
+//!
+//! Whole URLs or file paths are rarely repeated, so a dictionary of full
+//! values grows with every new path. Their segments (scheme, host,
+//! directories) repeat heavily, though. This module splits such columns on
+//! `/` so each segment can be compressed as its own sub-stream.
+
+use crate::hashmap::AdaptiveMap;
+
+/// Detector for delimited columns whose segments repeat more than the
+/// whole values do.
+///
+/// Like the `TransformDetector`, this does not produce an operator by
+/// itself: it proposes the per-segment values, which the compressor
+/// encodes independently before wrapping them in a split operator.
+#[derive(Debug, Clone)]
+pub struct SegmentDetector {
+    min_pattern_length: usize,
+    max_segments: usize,
+}
+
+impl SegmentDetector {
+    /// Separator between segments.
+    pub const SEPARATOR: char = '/';
+
+    /// Default upper bound on the number of segments per value.
+    pub const DEFAULT_MAX_SEGMENTS: usize = 8;
+
+    /// Create a new segment detector with the given minimum pattern length.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self {
+            min_pattern_length,
+            max_segments: Self::DEFAULT_MAX_SEGMENTS,
+        }
+    }
+
+    /// Set the maximum number of segments a value is split into.
+    pub fn with_max_segments(mut self, max_segments: usize) -> Self {
+        self.max_segments = max_segments;
+        self
+    }
+
+    /// Split the values into segments.
+    ///
+    /// Every value is split into the same number of parts: the smallest
+    /// segment count in the column, capped at the maximum. The last part
+    /// keeps the remainder of each value, separators included. Returns
+    /// `None` unless every value contains the separator and dictionaries
+    /// of the distinct segments would be smaller than one of the distinct
+    /// whole values.
+    pub fn split<'a>(&self, values: &[&'a str]) -> Option<Vec<Vec<&'a str>>> {
+        if values.len() < self.min_pattern_length.max(2) {
+            return None;
+        }
+
+        let segments = values
+            .iter()
+            .map(|v| v.matches(Self::SEPARATOR).count() + 1)
+            .min()?
+            .min(self.max_segments);
+        if segments < 2 {
+            return None;
+        }
+
+        let mut parts: Vec<Vec<&str>> = vec![Vec::with_capacity(values.len()); segments];
+        for value in values {
+            for (part, segment) in parts.iter_mut().zip(value.splitn(segments, Self::SEPARATOR)) {
+                part.push(segment);
+            }
+        }
+
+        let whole_size = Self::distinct_size(values);
+        let segments_size: usize = parts.iter().map(|part| Self::distinct_size(part)).sum();
+        if segments_size >= whole_size {
+            return None;
+        }
+
+        Some(parts)
+    }
+
+    /// Size of a dictionary holding the distinct values, one separator each.
+    fn distinct_size(values: &[&str]) -> usize {
+        let mut seen = AdaptiveMap::inline();
+        for value in values {
+            seen.entry(*value).or_insert(());
+        }
+        seen.into_iter().map(|(value, _)| value.len() + 1).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_urls() {
+        let detector = SegmentDetector::new(3);
+        let values = vec![
+            "https://api.example.com/v1/users",
+            "https://api.example.com/v1/orders",
+            "https://cdn.example.com/v1/users",
+            "https://cdn.example.com/v1/orders",
+        ];
+        let parts = detector.split(&values).unwrap();
+
+        assert_eq!(parts.len(), 5);
+        assert_eq!(parts[0], vec!["https:"; 4]);
+        assert_eq!(parts[1], vec![""; 4]);
+        assert_eq!(parts[2], vec!["api.example.com", "api.example.com", "cdn.example.com", "cdn.example.com"]);
+        assert_eq!(parts[4], vec!["users", "orders", "users", "orders"]);
+    }
+
+    #[test]
+    fn test_last_part_keeps_remainder() {
+        let detector = SegmentDetector::new(3);
+        let values = vec!["/var/log/a", "/var/log/b/c", "/var/log/a"];
+        let parts = detector.split(&values).unwrap();
+
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[3], vec!["a", "b/c", "a"]);
+    }
+
+    #[test]
+    fn test_unique_segments_not_split() {
+        let detector = SegmentDetector::new(3);
+        assert!(detector.split(&["a/1", "b/2", "c/3"]).is_none());
+    }
+
+    #[test]
+    fn test_values_without_separator() {
+        let detector = SegmentDetector::new(3);
+        assert!(detector.split(&["a/1", "a/2", "plain"]).is_none());
+    }
+
+    #[test]
+    fn test_max_segments() {
+        let detector = SegmentDetector::new(3).with_max_segments(2);
+        let values = vec!["x/a/1", "x/a/2", "x/b/1", "x/b/2"];
+        let parts = detector.split(&values).unwrap();
+        assert_eq!(parts, vec![vec!["x"; 4], vec!["a/1", "a/2", "b/1", "b/2"]]);
+    }
+}
@@ -0,0 +1,224 @@
+//! Transform candidate detection.
+//!
+//! This module finds formatting shared by every value of a column, such
+//! as a common prefix, zero-padded numbers or uniform letter case. Once
+//! the formatting is stripped, the remaining values often form a pattern
+//! (e.g., `USER001, USER002, ...` becomes the range `1>100`).
+
+use crate::als::{CaseTransform, ValueTransform, MAX_TRANSFORM_WIDTH};
+
+/// Detector for formatting transforms shared by all values in a column.
+///
+/// Unlike the other detectors this does not produce an operator on its
+/// own: it proposes transforms together with the untransformed values,
+/// which the `PatternEngine` then runs through its regular detectors.
+#[derive(Debug, Clone)]
+pub struct TransformDetector {
+    min_pattern_length: usize,
+}
+
+impl TransformDetector {
+    /// Create a new transform detector with the given minimum pattern length.
+    pub fn new(min_pattern_length: usize) -> Self {
+        Self { min_pattern_length }
+    }
+
+    /// Propose transforms that explain the formatting of `values`.
+    ///
+    /// Each candidate is returned with the inner values it must be applied
+    /// to; applying the transform to those values reproduces `values`.
+    pub fn candidates(&self, values: &[&str]) -> Vec<(ValueTransform, Vec<String>)> {
+        if values.len() < self.min_pattern_length {
+            return Vec::new();
+        }
+
+        let mut candidates = Vec::new();
+        if let Some(candidate) = Self::detect_number_format(values) {
+            candidates.push(candidate);
+        }
+        if let Some(candidate) = Self::detect_case(values) {
+            candidates.push(candidate);
+        }
+        candidates
+    }
+
+    /// Detect numbers sharing a prefix, suffix or zero-padding width.
+    fn detect_number_format(values: &[&str]) -> Option<(ValueTransform, Vec<String>)> {
+        let prefix = Self::common_prefix(values).trim_end_matches(|c: char| c.is_ascii_digit());
+        let suffix = Self::common_suffix(values).trim_start_matches(|c: char| c.is_ascii_digit());
+
+        // The affixes must survive tokenization around the placeholder
+        if prefix.contains(['{', '(']) || suffix.contains('(') {
+            return None;
+        }
+
+        let mut width = 0;
+        let mut min_len = usize::MAX;
+        let mut numbers = Vec::with_capacity(values.len());
+
+        for value in values {
+            let digits = value
+                .get(prefix.len()..value.len().checked_sub(suffix.len())?)
+                .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))?;
+
+            if digits.len() > 1 && digits.starts_with('0') {
+                // Every zero-padded number must share one width
+                if width != 0 && width != digits.len() {
+                    return None;
+                }
+                width = digits.len();
+            }
+            min_len = min_len.min(digits.len());
+
+            let trimmed = digits.trim_start_matches('0');
+            numbers.push(if trimmed.is_empty() { "0" } else { trimmed }.to_string());
+        }
+
+        // Unpadded numbers shorter than the width would be padded on expansion
+        if width > MAX_TRANSFORM_WIDTH || (width != 0 && min_len < width) {
+            return None;
+        }
+
+        let transform = ValueTransform::new()
+            .with_prefix(prefix)
+            .with_width(width)
+            .with_suffix(suffix);
+        if transform.is_identity() {
+            return None;
+        }
+
+        Some((transform, numbers))
+    }
+
+    /// Detect values that are all uppercase or all lowercase.
+    fn detect_case(values: &[&str]) -> Option<(ValueTransform, Vec<String>)> {
+        let case = if values.iter().all(|v| Self::is_cased(v, CaseTransform::Upper)) {
+            CaseTransform::Upper
+        } else if values.iter().all(|v| Self::is_cased(v, CaseTransform::Lower)) {
+            CaseTransform::Lower
+        } else {
+            return None;
+        };
+
+        let inner: Vec<String> = values.iter().map(|v| CaseTransform::Title.apply(v)).collect();
+        if inner.iter().zip(values).any(|(i, v)| case.apply(i) != *v) {
+            return None;
+        }
+
+        Some((ValueTransform::new().with_case(case), inner))
+    }
+
+    /// Check if a value has letters and is entirely in the given case.
+    fn is_cased(value: &str, case: CaseTransform) -> bool {
+        value.chars().any(char::is_alphabetic) && case.apply(value) == value
+    }
+
+    /// Longest prefix shared by all values.
+    fn common_prefix<'a>(values: &[&'a str]) -> &'a str {
+        let first = values[0];
+        let mut end = first.len();
+        for value in &values[1..] {
+            end = first
+                .char_indices()
+                .zip(value.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((i, c), _)| i + c.len_utf8())
+                .min(end);
+        }
+        &first[..end]
+    }
+
+    /// Longest suffix shared by all values.
+    fn common_suffix<'a>(values: &[&'a str]) -> &'a str {
+        let first = values[0];
+        let mut start = 0;
+        for value in &values[1..] {
+            let shared: usize = first
+                .chars()
+                .rev()
+                .zip(value.chars().rev())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a.len_utf8())
+                .sum();
+            start = start.max(first.len() - shared);
+        }
+        &first[start..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefixed_padded_ids() {
+        let detector = TransformDetector::new(3);
+        let values = vec!["USER001", "USER002", "USER003", "USER010"];
+        let (transform, inner) = &detector.candidates(&values)[0];
+        assert_eq!(transform, &ValueTransform::new().with_prefix("USER").with_width(3));
+        assert_eq!(inner, &vec!["1", "2", "3", "10"]);
+    }
+
+    #[test]
+    fn test_prefix_does_not_swallow_digits() {
+        let detector = TransformDetector::new(3);
+        let values = vec!["node-11.local", "node-12.local", "node-13.local"];
+        let (transform, inner) = &detector.candidates(&values)[0];
+
+        assert_eq!(transform.prefix, "node-");
+        assert_eq!(transform.suffix, ".local");
+        assert_eq!(transform.width, 0);
+        assert_eq!(inner, &vec!["11", "12", "13"]);
+    }
+
+    #[test]
+    fn test_mixed_padding_widths() {
+        let detector = TransformDetector::new(3);
+        assert!(detector.candidates(&["01", "002", "003"]).is_empty());
+        assert!(detector.candidates(&["001", "002", "3"]).is_empty());
+    }
+
+    #[test]
+    fn test_padding_over_the_width_limit() {
+        let detector = TransformDetector::new(3);
+        let values: Vec<String> = (1..4).map(|n| format!("{:0300}", n)).collect();
+        let values: Vec<&str> = values.iter().map(String::as_str).collect();
+        assert!(detector.candidates(&values).is_empty());
+    }
+
+    #[test]
+    fn test_plain_numbers_have_no_transform() {
+        let detector = TransformDetector::new(3);
+        assert!(detector.candidates(&["1", "2", "3"]).is_empty());
+    }
+
+    #[test]
+    fn test_uppercase_values() {
+        let detector = TransformDetector::new(3);
+        let values = vec!["MON", "TUE", "WED"];
+        let (transform, inner) = &detector.candidates(&values)[0];
+
+        assert_eq!(transform, &ValueTransform::new().with_case(CaseTransform::Upper));
+        assert_eq!(inner, &vec!["Mon", "Tue", "Wed"]);
+    }
+
+    #[test]
+    fn test_lowercase_values() {
+        let detector = TransformDetector::new(3);
+        let (transform, _) = &detector.candidates(&["jan", "feb", "mar"])[0];
+        assert_eq!(transform.case, Some(CaseTransform::Lower));
+    }
+
+    #[test]
+    fn test_mixed_case_values() {
+        let detector = TransformDetector::new(3);
+        assert!(detector.candidates(&["Mon", "TUE", "wed"]).is_empty());
+    }
+
+    #[test]
+    fn test_too_short() {
+        let detector = TransformDetector::new(5);
+        assert!(detector.candidates(&["ID01", "ID02"]).is_empty());
+    }
+}
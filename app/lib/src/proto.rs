@@ -0,0 +1,949 @@
+//! Protobuf messages for ALS documents.
+//!
+//! Services exchanging compressed tables over gRPC can send a [`Document`]
+//! rather than ALS text: the messages mirror [`AlsDocument`] field for
+//! field, with one message per operator, so a document crosses the wire
+//! without being re-encoded and is never expanded on either side. The
+//! schema is in `proto/als.proto` for services in other languages.
+//!
+//! Float deltas are sent as the floats themselves, XORed with their
+//! predecessors as in Gorilla, rather than as text differences. Runs of raw
+//! integers, left where no range or repeat fits, are sent as packed blocks
+//! of up to 128 offsets from the block's minimum, in the fewest bits that
+//! hold them, whenever that is smaller than the values themselves.
+//! Boolean runs are likewise sent as one bit per value when that is
+//! smaller than their run lengths.
+//!
+//! Indexes, Bloom filters and zone maps are left out, as they only speed up
+//! local queries, and so are redactions, quantizations, truncations and
+//! file metadata. A
+//! delta only makes sense next to its base and is refused, and so is a
+//! document with foreign keys, which only make sense next to its parents.
+//!
+//! # Examples
+//!
+//! ```
+//! use als_compression::AlsParser;
+//! use als_compression::proto::{decode_document, encode_document};
+//!
+//! let doc = AlsParser::new().parse("$default:GET|POST\n#id #method\n1>3|_0 _1 _0").unwrap();
+//!
+//! let bytes = encode_document(&doc).unwrap();
+//! assert_eq!(decode_document(&bytes).unwrap(), doc);
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+
+use prost::Message;
+
+use crate::als::{AlsDocument, CalendarCycle, CaseTransform, ColumnStream as AlsColumnStream, FormatIndicator};
+use crate::als::float::{format_scaled, parse_scaled};
+use crate::als::{xor_decode, xor_encode, AlsOperator, ValueTransform, MAX_FLOAT_DELTA_PLACES, MAX_TRANSFORM_WIDTH};
+use crate::als::bitpack::{BitReader, BitWriter};
+use crate::als::{bit_pack, bit_unpack, PACKED_BLOCK_SIZE};
+use crate::error::{AlsError, Result};
+
+/// A compressed table.
+#[derive(Clone, PartialEq, Message)]
+pub struct Document {
+    /// ALS format version.
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    /// Dictionaries, keyed by name.
+    #[prost(map = "string, message", tag = "2")]
+    pub dictionaries: HashMap<String, Dictionary>,
+    /// Column names, in column order.
+    #[prost(string, repeated, tag = "3")]
+    pub schema: Vec<String>,
+    /// One stream per column, in column order.
+    #[prost(message, repeated, tag = "4")]
+    pub streams: Vec<ColumnStream>,
+    /// Whether the whole document is CTX.
+    #[prost(bool, tag = "5")]
+    pub ctx: bool,
+    /// Rows per block in block mode, or 0.
+    #[prost(uint64, tag = "6")]
+    pub block_size: u64,
+    /// Whether text was normalized to Unicode NFC when compressing.
+    #[prost(bool, tag = "7")]
+    pub nfc_normalized: bool,
+}
+
+/// The entries of a dictionary.
+#[derive(Clone, PartialEq, Message)]
+pub struct Dictionary {
+    /// Entries, in reference order.
+    #[prost(string, repeated, tag = "1")]
+    pub entries: Vec<String>,
+}
+
+/// The operators producing the values of one column.
+#[derive(Clone, PartialEq, Message)]
+pub struct ColumnStream {
+    /// Operators, in order.
+    #[prost(message, repeated, tag = "1")]
+    pub operators: Vec<Operator>,
+    /// Whether the column stores its values verbatim.
+    #[prost(bool, tag = "2")]
+    pub ctx: bool,
+}
+
+/// One operator of a column stream.
+#[derive(Clone, PartialEq, Message)]
+pub struct Operator {
+    /// What the operator produces.
+    #[prost(oneof = "operator::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13")]
+    pub kind: Option<operator::Kind>,
+}
+
+/// Kinds of operator.
+pub mod operator {
+    /// What an operator produces.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        /// A single literal value.
+        #[prost(string, tag = "1")]
+        Raw(String),
+        /// A range of integers.
+        #[prost(message, tag = "2")]
+        Range(super::Range),
+        /// Repeated values.
+        #[prost(message, tag = "3")]
+        Multiply(Box<super::Multiply>),
+        /// Alternating values.
+        #[prost(message, tag = "4")]
+        Toggle(super::Toggle),
+        /// A reference into the default dictionary.
+        #[prost(uint64, tag = "5")]
+        DictRef(u64),
+        /// Values derived from an earlier column.
+        #[prost(message, tag = "6")]
+        ColumnRef(super::ColumnRef),
+        /// Formatted timestamps.
+        #[prost(message, tag = "7")]
+        DateRange(super::DateRange),
+        /// Calendar labels.
+        #[prost(message, tag = "8")]
+        Cycle(super::Cycle),
+        /// Reformatted values.
+        #[prost(message, tag = "9")]
+        Transform(Box<super::Transform>),
+        /// Runs of two labels.
+        #[prost(message, tag = "10")]
+        BoolRuns(super::BoolRuns),
+        /// Values split on a separator.
+        #[prost(message, tag = "11")]
+        Split(super::Split),
+        /// Decimals with a fixed number of places.
+        #[prost(message, tag = "12")]
+        FloatDelta(super::FloatDelta),
+        /// Raw integers, packed. Only found in lists of operators.
+        #[prost(message, tag = "13")]
+        BitPacked(super::BitPacked),
+    }
+}
+
+/// Integers from `start` to `end` inclusive, `step` apart.
+#[derive(Clone, PartialEq, Message)]
+pub struct Range {
+    /// First value.
+    #[prost(sint64, tag = "1")]
+    pub start: i64,
+    /// Last value.
+    #[prost(sint64, tag = "2")]
+    pub end: i64,
+    /// Difference between values.
+    #[prost(sint64, tag = "3")]
+    pub step: i64,
+}
+
+/// The values of an operator, repeated.
+#[derive(Clone, PartialEq, Message)]
+pub struct Multiply {
+    /// Operator to repeat.
+    #[prost(message, optional, boxed, tag = "1")]
+    pub value: Option<Box<Operator>>,
+    /// Number of repetitions.
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+}
+
+/// Values alternating between a fixed set.
+#[derive(Clone, PartialEq, Message)]
+pub struct Toggle {
+    /// Values, in order.
+    #[prost(string, repeated, tag = "1")]
+    pub values: Vec<String>,
+    /// Number of values produced.
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+}
+
+/// Values derived from an earlier column through a mapping.
+#[derive(Clone, PartialEq, Message)]
+pub struct ColumnRef {
+    /// Position of the source column.
+    #[prost(uint64, tag = "1")]
+    pub column: u64,
+    /// Source values and what they map to.
+    #[prost(message, repeated, tag = "2")]
+    pub mapping: Vec<Mapping>,
+    /// Number of values produced.
+    #[prost(uint64, tag = "3")]
+    pub count: u64,
+}
+
+/// One entry of a column reference mapping.
+#[derive(Clone, PartialEq, Message)]
+pub struct Mapping {
+    /// Source value.
+    #[prost(string, tag = "1")]
+    pub from: String,
+    /// Derived value.
+    #[prost(string, tag = "2")]
+    pub to: String,
+}
+
+/// Formatted timestamps at a fixed interval.
+#[derive(Clone, PartialEq, Message)]
+pub struct DateRange {
+    /// First timestamp, in seconds since the epoch.
+    #[prost(sint64, tag = "1")]
+    pub start: i64,
+    /// Seconds between timestamps.
+    #[prost(sint64, tag = "2")]
+    pub step: i64,
+    /// Number of timestamps.
+    #[prost(uint64, tag = "3")]
+    pub count: u64,
+    /// strftime-style format.
+    #[prost(string, tag = "4")]
+    pub format: String,
+}
+
+/// Calendar labels, each repeated for a run of rows.
+#[derive(Clone, PartialEq, Message)]
+pub struct Cycle {
+    /// Cycle name as in ALS text, e.g. `dow`.
+    #[prost(string, tag = "1")]
+    pub cycle: String,
+    /// Position of the first row in the cycle, counting each label `run`
+    /// times.
+    #[prost(uint64, tag = "2")]
+    pub offset: u64,
+    /// Rows per label.
+    #[prost(uint64, tag = "3")]
+    pub run: u64,
+    /// Number of values produced.
+    #[prost(uint64, tag = "4")]
+    pub count: u64,
+}
+
+/// The values of an operator, reformatted.
+#[derive(Clone, PartialEq, Message)]
+pub struct Transform {
+    /// Operator producing the values to reformat.
+    #[prost(message, optional, boxed, tag = "1")]
+    pub value: Option<Box<Operator>>,
+    /// Case conversion.
+    #[prost(enumeration = "Case", tag = "2")]
+    pub case: i32,
+    /// Zero-padding width, or 0.
+    #[prost(uint64, tag = "3")]
+    pub width: u64,
+    /// Thousands separator, or empty.
+    #[prost(string, tag = "4")]
+    pub grouping: String,
+    /// Text placed before each value.
+    #[prost(string, tag = "5")]
+    pub prefix: String,
+    /// Text placed after each value.
+    #[prost(string, tag = "6")]
+    pub suffix: String,
+}
+
+/// Case conversion of a transform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Case {
+    /// Keep the case.
+    None = 0,
+    /// Convert to uppercase.
+    Upper = 1,
+    /// Convert to lowercase.
+    Lower = 2,
+    /// Capitalize each word.
+    Title = 3,
+}
+
+/// Alternating runs of two labels, starting with the first.
+#[derive(Clone, PartialEq, Message)]
+pub struct BoolRuns {
+    /// The two labels.
+    #[prost(string, repeated, tag = "1")]
+    pub labels: Vec<String>,
+    /// Run lengths, empty when the values are packed into `bits`.
+    #[prost(uint64, repeated, tag = "2")]
+    pub runs: Vec<u64>,
+    /// Number of values packed into `bits`, or 0.
+    #[prost(uint64, tag = "3")]
+    pub count: u64,
+    /// One bit per value, most significant first, set for the second label.
+    #[prost(bytes = "vec", tag = "4")]
+    pub bits: Vec<u8>,
+}
+
+/// Values split on a separator, with each part encoded on its own.
+#[derive(Clone, PartialEq, Message)]
+pub struct Split {
+    /// Separator between parts, empty to concatenate them.
+    #[prost(string, tag = "1")]
+    pub separator: String,
+    /// Operators producing each part.
+    #[prost(message, repeated, tag = "2")]
+    pub parts: Vec<OperatorList>,
+}
+
+/// Decimals with a fixed number of places, as Gorilla XOR compressed
+/// floats.
+#[derive(Clone, PartialEq, Message)]
+pub struct FloatDelta {
+    /// Digits after the decimal point.
+    #[prost(uint32, tag = "1")]
+    pub places: u32,
+    /// Number of values.
+    #[prost(uint64, tag = "2")]
+    pub count: u64,
+    /// The values as written by [`xor_encode`].
+    #[prost(bytes = "vec", tag = "3")]
+    pub xor: Vec<u8>,
+}
+
+/// A block of raw integers, as offsets from their minimum packed in
+/// `width` bits each, standing for one raw operator per integer.
+#[derive(Clone, PartialEq, Message)]
+pub struct BitPacked {
+    /// Smallest value.
+    #[prost(sint64, tag = "1")]
+    pub reference: i64,
+    /// Bits per offset.
+    #[prost(uint32, tag = "2")]
+    pub width: u32,
+    /// Number of values, at most [`PACKED_BLOCK_SIZE`].
+    #[prost(uint64, tag = "3")]
+    pub count: u64,
+    /// The offsets as written by [`bit_pack`].
+    #[prost(bytes = "vec", tag = "4")]
+    pub packed: Vec<u8>,
+}
+
+/// A sequence of operators.
+#[derive(Clone, PartialEq, Message)]
+pub struct OperatorList {
+    /// Operators, in order.
+    #[prost(message, repeated, tag = "1")]
+    pub operators: Vec<Operator>,
+}
+
+/// Convert a document to its protobuf message.
+///
+/// # Errors
+///
+/// Returns `AlsError::InvalidDelta` for a delta, which cannot be sent
+/// without its base, and `AlsError::InvalidForeignKey` for a document with
+/// foreign keys, which cannot be sent without its parents.
+pub fn to_proto(doc: &AlsDocument) -> Result<Document> {
+    if doc.delta.is_some() {
+        return Err(AlsError::InvalidDelta {
+            message: "a delta cannot be sent as a protobuf document".to_string(),
+        });
+    }
+    if let Some(column) = doc.foreign_keys.keys().next() {
+        return Err(AlsError::InvalidForeignKey {
+            column: column.clone(),
+            message: "a document with foreign keys cannot be sent as a protobuf document".to_string(),
+        });
+    }
+    Ok(Document {
+        version: u32::from(doc.version),
+        dictionaries: doc
+            .dictionaries
+            .iter()
+            .map(|(name, entries)| (name.clone(), Dictionary { entries: entries.clone() }))
+            .collect(),
+        schema: doc.schema.clone(),
+        streams: doc
+            .streams
+            .iter()
+            .map(|stream| ColumnStream {
+                operators: operators_to_proto(&stream.operators),
+                ctx: stream.is_ctx(),
+            })
+            .collect(),
+        ctx: doc.is_ctx(),
+        block_size: doc.block_size.map_or(0, |size| size as u64),
+        nfc_normalized: doc.nfc_normalized,
+    })
+}
+
+/// Convert a protobuf message back to a document.
+///
+/// # Errors
+///
+/// Returns `AlsError::IoError` if the message is not a valid document,
+/// such as an operator with no kind or an unknown calendar cycle.
+pub fn from_proto(message: &Document) -> Result<AlsDocument<'static>> {
+    let mut doc = AlsDocument::with_schema(message.schema.clone());
+    doc.version = u8::try_from(message.version).map_err(|_| invalid(&format!("version {}", message.version)))?;
+    doc.dictionaries = message
+        .dictionaries
+        .iter()
+        .map(|(name, dictionary)| (name.clone(), dictionary.entries.clone()))
+        .collect();
+    doc.streams = message
+        .streams
+        .iter()
+        .map(|stream| {
+            Ok(AlsColumnStream {
+                operators: operators_from_proto(&stream.operators)?,
+                format_indicator: format_indicator(stream.ctx),
+            })
+        })
+        .collect::<Result<_>>()?;
+    doc.format_indicator = format_indicator(message.ctx);
+    doc.block_size = match message.block_size {
+        0 => None,
+        size => Some(to_usize(size)?),
+    };
+    doc.nfc_normalized = message.nfc_normalized;
+    Ok(doc)
+}
+
+/// Encode a document as protobuf bytes.
+///
+/// # Errors
+///
+/// Returns `AlsError::InvalidDelta` for a delta and
+/// `AlsError::InvalidForeignKey` for a document with foreign keys.
+pub fn encode_document(doc: &AlsDocument) -> Result<Vec<u8>> {
+    Ok(to_proto(doc)?.encode_to_vec())
+}
+
+/// Decode a document from protobuf bytes.
+///
+/// # Errors
+///
+/// Returns `AlsError::IoError` if the bytes are not a valid document.
+pub fn decode_document(bytes: &[u8]) -> Result<AlsDocument<'static>> {
+    let message = Document::decode(bytes).map_err(|e| invalid(&e.to_string()))?;
+    from_proto(&message)
+}
+
+fn operators_to_proto(operators: &[AlsOperator]) -> Vec<Operator> {
+    let mut messages = Vec::with_capacity(operators.len());
+    let mut rest = operators;
+    while let Some(op) = rest.first() {
+        let integers: Vec<i64> = rest.iter().map_while(raw_integer).take(PACKED_BLOCK_SIZE).collect();
+        if integers.is_empty() {
+            messages.push(operator_to_proto(op));
+            rest = &rest[1..];
+            continue;
+        }
+
+        let (run, tail) = rest.split_at(integers.len());
+        let (reference, width, packed) = bit_pack(&integers);
+        let block = Operator {
+            kind: Some(operator::Kind::BitPacked(BitPacked {
+                reference,
+                width,
+                count: integers.len() as u64,
+                packed,
+            })),
+        };
+        let raw: Vec<Operator> = run.iter().map(operator_to_proto).collect();
+        if block.encoded_len() < raw.iter().map(Message::encoded_len).sum() {
+            messages.push(block);
+        } else {
+            messages.extend(raw);
+        }
+        rest = tail;
+    }
+    messages
+}
+
+/// Get the integer a raw operator holds, if it is written as `i64` prints
+/// it, so that packing it loses nothing.
+fn raw_integer(op: &AlsOperator) -> Option<i64> {
+    let AlsOperator::Raw(value) = op else {
+        return None;
+    };
+    value.parse::<i64>().ok().filter(|integer| value.as_ref() == integer.to_string())
+}
+
+fn operator_to_proto(op: &AlsOperator) -> Operator {
+    use operator::Kind;
+
+    let kind = match op {
+        AlsOperator::Raw(value) => Kind::Raw(value.to_string()),
+        AlsOperator::Range { start, end, step } => Kind::Range(Range {
+            start: *start,
+            end: *end,
+            step: *step,
+        }),
+        AlsOperator::Multiply { value, count } => Kind::Multiply(Box::new(Multiply {
+            value: Some(Box::new(operator_to_proto(value))),
+            count: *count as u64,
+        })),
+        AlsOperator::Toggle { values, count } => Kind::Toggle(Toggle {
+            values: values.clone(),
+            count: *count as u64,
+        }),
+        AlsOperator::DictRef(index) => Kind::DictRef(*index as u64),
+        AlsOperator::ColumnRef { column, mapping, count } => Kind::ColumnRef(ColumnRef {
+            column: *column as u64,
+            mapping: mapping
+                .iter()
+                .map(|(from, to)| Mapping {
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+                .collect(),
+            count: *count as u64,
+        }),
+        AlsOperator::DateRange { start, step, count, format } => Kind::DateRange(DateRange {
+            start: *start,
+            step: *step,
+            count: *count as u64,
+            format: format.clone(),
+        }),
+        AlsOperator::Cycle { cycle, offset, run, count } => Kind::Cycle(Cycle {
+            cycle: cycle.name().to_string(),
+            offset: *offset as u64,
+            run: *run as u64,
+            count: *count as u64,
+        }),
+        AlsOperator::Transform { value, transform } => Kind::Transform(Box::new(Transform {
+            value: Some(Box::new(operator_to_proto(value))),
+            case: match transform.case {
+                None => Case::None,
+                Some(CaseTransform::Upper) => Case::Upper,
+                Some(CaseTransform::Lower) => Case::Lower,
+                Some(CaseTransform::Title) => Case::Title,
+            } as i32,
+            width: transform.width as u64,
+            grouping: transform.grouping.map(String::from).unwrap_or_default(),
+            prefix: transform.prefix.clone(),
+            suffix: transform.suffix.clone(),
+        })),
+        AlsOperator::BoolRuns { labels, runs } => Kind::BoolRuns(bool_runs_to_proto(labels, runs)),
+        AlsOperator::Split { separator, parts } => Kind::Split(Split {
+            separator: separator.map(String::from).unwrap_or_default(),
+            parts: parts
+                .iter()
+                .map(|part| OperatorList {
+                    operators: operators_to_proto(part),
+                })
+                .collect(),
+        }),
+        AlsOperator::FloatDelta { places, start, deltas } => {
+            let mut scaled = *start;
+            let mut values = Vec::with_capacity(deltas.len() + 1);
+            values.push(scaled);
+            for delta in deltas {
+                scaled = scaled.saturating_add(*delta);
+                values.push(scaled);
+            }
+            // The float nearest each decimal, which formats back to it
+            let floats: Vec<f64> = values
+                .iter()
+                .map(|&value| format_scaled(value, *places).parse().unwrap_or(f64::NAN))
+                .collect();
+            Kind::FloatDelta(FloatDelta {
+                places: u32::from(*places),
+                count: floats.len() as u64,
+                xor: xor_encode(&floats),
+            })
+        }
+    };
+    Operator { kind: Some(kind) }
+}
+
+fn operators_from_proto(operators: &[Operator]) -> Result<Vec<AlsOperator<'static>>> {
+    let mut decoded = Vec::with_capacity(operators.len());
+    for op in operators {
+        let Some(operator::Kind::BitPacked(block)) = &op.kind else {
+            decoded.push(operator_from_proto(op)?);
+            continue;
+        };
+        let count = to_usize(block.count)?;
+        if count > PACKED_BLOCK_SIZE {
+            return Err(invalid(&format!("packed block of {} integers", count)));
+        }
+        let integers = bit_unpack(block.reference, block.width, &block.packed, count)
+            .ok_or_else(|| invalid("packed integers without their values"))?;
+        decoded.extend(integers.into_iter().map(|integer| AlsOperator::Raw(integer.to_string().into())));
+    }
+    Ok(decoded)
+}
+
+fn operator_from_proto(op: &Operator) -> Result<AlsOperator<'static>> {
+    use operator::Kind;
+
+    let inner = |value: &Option<Box<Operator>>| match value {
+        Some(value) => Ok(Box::new(operator_from_proto(value)?)),
+        None => Err(invalid("operator without a value")),
+    };
+
+    Ok(match op.kind.as_ref().ok_or_else(|| invalid("operator without a kind"))? {
+        Kind::Raw(value) => AlsOperator::Raw(value.clone().into()),
+        Kind::Range(range) => AlsOperator::Range {
+            start: range.start,
+            end: range.end,
+            step: range.step,
+        },
+        Kind::Multiply(multiply) => AlsOperator::Multiply {
+            value: inner(&multiply.value)?,
+            count: to_usize(multiply.count)?,
+        },
+        Kind::Toggle(toggle) => AlsOperator::Toggle {
+            values: toggle.values.clone(),
+            count: to_usize(toggle.count)?,
+        },
+        Kind::DictRef(index) => AlsOperator::DictRef(to_usize(*index)?),
+        Kind::ColumnRef(column_ref) => AlsOperator::ColumnRef {
+            column: to_usize(column_ref.column)?,
+            mapping: column_ref
+                .mapping
+                .iter()
+                .map(|mapping| (mapping.from.clone(), mapping.to.clone()))
+                .collect(),
+            count: to_usize(column_ref.count)?,
+        },
+        Kind::DateRange(range) => AlsOperator::DateRange {
+            start: range.start,
+            step: range.step,
+            count: to_usize(range.count)?,
+            format: range.format.clone(),
+        },
+        Kind::Cycle(cycle) => AlsOperator::Cycle {
+            cycle: CalendarCycle::from_name(&cycle.cycle)
+                .ok_or_else(|| invalid(&format!("unknown calendar cycle '{}'", cycle.cycle)))?,
+            offset: to_usize(cycle.offset)?,
+            run: to_usize(cycle.run)?,
+            count: to_usize(cycle.count)?,
+        },
+        Kind::Transform(transform) => {
+            let case = match Case::try_from(transform.case) {
+                Ok(Case::None) => None,
+                Ok(Case::Upper) => Some(CaseTransform::Upper),
+                Ok(Case::Lower) => Some(CaseTransform::Lower),
+                Ok(Case::Title) => Some(CaseTransform::Title),
+                Err(_) => return Err(invalid(&format!("unknown case {}", transform.case))),
+            };
+            let mut grouping = transform.grouping.chars();
+            let separator = grouping.next();
+            if grouping.next().is_some() {
+                return Err(invalid(&format!("grouping '{}' is not one character", transform.grouping)));
+            }
+            AlsOperator::Transform {
+                value: inner(&transform.value)?,
+                transform: ValueTransform {
+                    case,
+                    width: to_usize(transform.width)
+                        .ok()
+                        .filter(|&width| width <= MAX_TRANSFORM_WIDTH)
+                        .ok_or_else(|| invalid(&format!("transform width {} is over the limit", transform.width)))?,
+                    grouping: separator,
+                    prefix: transform.prefix.clone(),
+                    suffix: transform.suffix.clone(),
+                },
+            }
+        }
+        Kind::BoolRuns(runs) => AlsOperator::BoolRuns {
+            labels: <[String; 2]>::try_from(runs.labels.clone())
+                .map_err(|labels| invalid(&format!("boolean runs with {} labels", labels.len())))?,
+            runs: bool_runs_from_proto(runs)?,
+        },
+        Kind::Split(split) => {
+            let mut chars = split.separator.chars();
+            let separator = match (chars.next(), chars.next()) {
+                (None, _) => None,
+                (Some(character), None) => Some(character),
+                _ => return Err(invalid(&format!("split separator '{}' is not one character", split.separator))),
+            };
+            AlsOperator::Split {
+                separator,
+                parts: split
+                    .parts
+                    .iter()
+                    .map(|part| operators_from_proto(&part.operators))
+                    .collect::<Result<_>>()?,
+            }
+        }
+        Kind::FloatDelta(delta) => {
+            let places = u8::try_from(delta.places)
+                .ok()
+                .filter(|places| (1..=MAX_FLOAT_DELTA_PLACES).contains(places))
+                .ok_or_else(|| invalid(&format!("float deltas with {} places", delta.places)))?;
+            let floats = xor_decode(&delta.xor, to_usize(delta.count)?)
+                .filter(|floats| !floats.is_empty())
+                .ok_or_else(|| invalid("float deltas without their values"))?;
+            // Floats print as the decimals they were parsed from
+            let values = floats
+                .iter()
+                .map(|x| {
+                    parse_scaled(&x.to_string(), places)
+                        .ok_or_else(|| invalid(&format!("float delta value {} has more than {} places", x, places)))
+                })
+                .collect::<Result<Vec<i64>>>()?;
+            AlsOperator::FloatDelta {
+                places,
+                start: values[0],
+                deltas: values.windows(2).map(|pair| pair[1] - pair[0]).collect(),
+            }
+        }
+        Kind::BitPacked(_) => return Err(invalid("packed integers outside a list of operators")),
+    })
+}
+
+/// Send boolean runs as one bit per value when that is smaller than the
+/// run lengths and rebuilds them exactly, which takes every run after the
+/// first to be non-empty.
+fn bool_runs_to_proto(labels: &[String; 2], runs: &[usize]) -> BoolRuns {
+    let listed = BoolRuns {
+        labels: labels.to_vec(),
+        runs: runs.iter().map(|&run| run as u64).collect(),
+        ..BoolRuns::default()
+    };
+    let count: usize = runs.iter().sum();
+    if count == 0 || runs.iter().skip(1).any(|&run| run == 0) {
+        return listed;
+    }
+
+    let mut writer = BitWriter::default();
+    for (i, &run) in runs.iter().enumerate() {
+        for _ in 0..run {
+            writer.write((i % 2) as u64, 1);
+        }
+    }
+    let packed = BoolRuns {
+        labels: labels.to_vec(),
+        runs: Vec::new(),
+        count: count as u64,
+        bits: writer.bytes,
+    };
+    if packed.encoded_len() < listed.encoded_len() {
+        packed
+    } else {
+        listed
+    }
+}
+
+fn bool_runs_from_proto(runs: &BoolRuns) -> Result<Vec<usize>> {
+    if runs.count == 0 {
+        return runs.runs.iter().map(|&run| to_usize(run)).collect();
+    }
+    let count = to_usize(runs.count)?;
+    if !runs.runs.is_empty() || count.div_ceil(8) != runs.bits.len() {
+        return Err(invalid(&format!("{} boolean values in {} bytes", count, runs.bits.len())));
+    }
+
+    // A column starting with the second label has an empty first run
+    let mut reader = BitReader::new(&runs.bits);
+    let mut decoded = vec![0];
+    let mut label = 0;
+    for _ in 0..count {
+        let bit = reader.read(1).expect("the length was checked");
+        if bit != label {
+            decoded.push(0);
+            label = bit;
+        }
+        *decoded.last_mut().expect("runs are never empty") += 1;
+    }
+    Ok(decoded)
+}
+
+fn format_indicator(ctx: bool) -> FormatIndicator {
+    if ctx {
+        FormatIndicator::Ctx
+    } else {
+        FormatIndicator::Als
+    }
+}
+
+fn to_usize(value: u64) -> Result<usize> {
+    usize::try_from(value).map_err(|_| invalid(&format!("{} does not fit in a usize", value)))
+}
+
+/// Create an error for a message that is not a valid document.
+fn invalid(message: &str) -> AlsError {
+    AlsError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid protobuf document: {}", message),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::{AlsParser, AlsSerializer};
+    use crate::AlsCompressor;
+
+    fn roundtrip(doc: &AlsDocument) -> AlsDocument<'static> {
+        decode_document(&encode_document(doc).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_operators() {
+        let als = "$default:buy|sell\n#id #side #sign #day #ts #ok #code #n #tag\n\
+                   1>3|_0 _1*2|@1(buy:+1 sell:-1)*3|&dow+2:2*3|%{%Y-%m-%d}1704067200+86400*3|?T~F:1.2|^ID{U4}(7>9)|-5>-9:-2|a~b*3";
+        let doc = AlsParser::new().parse(als).unwrap();
+        let decoded = roundtrip(&doc);
+        assert_eq!(decoded, doc);
+        assert_eq!(AlsSerializer::new().serialize(&decoded), AlsSerializer::new().serialize(&doc));
+    }
+
+    #[test]
+    fn test_roundtrip_compressed() {
+        let csv = "id,path,note\n1,/a/b,x\n2,/a/c,\n3,/b/c,\"y, z\"\n4,/b/d,x\n";
+        let doc = AlsCompressor::new().compress(&crate::convert::csv::parse_csv(csv).unwrap()).unwrap();
+        assert_eq!(roundtrip(&doc), doc);
+    }
+
+    #[test]
+    fn test_roundtrip_float_delta() {
+        let doc = AlsParser::new().parse("#temp #load\n%.2:2153+3-1+0+6-2160|%.3:-125+65+0+60-1000").unwrap();
+        let message = to_proto(&doc).unwrap();
+        let Some(operator::Kind::FloatDelta(delta)) = &message.streams[0].operators[0].kind else {
+            panic!("expected float deltas");
+        };
+        assert_eq!(delta.count, 6);
+        assert_eq!(roundtrip(&doc), doc);
+
+        let mut message = message;
+        message.streams[1].operators[0] = Operator {
+            kind: Some(operator::Kind::FloatDelta(FloatDelta {
+                places: 2,
+                count: 2,
+                xor: xor_encode(&[1.5, 0.125]),
+            })),
+        };
+        assert!(from_proto(&message).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_bit_packed() {
+        let latencies: Vec<String> = (0..300).map(|i| (1000 + i * 7919 % 1000).to_string()).collect();
+        let als = format!("#ms #code\n{}|007 8 9 x*297", latencies.join(" "));
+        let doc = AlsParser::new().parse(&als).unwrap();
+        let message = to_proto(&doc).unwrap();
+
+        let counts: Vec<u64> = message.streams[0]
+            .operators
+            .iter()
+            .map(|op| match &op.kind {
+                Some(operator::Kind::BitPacked(block)) => block.count,
+                kind => panic!("expected packed integers, got {:?}", kind),
+            })
+            .collect();
+        assert_eq!(counts, vec![128, 128, 44]);
+        // Too few integers to pay for a block, and a spelling packing would lose
+        assert_eq!(message.streams[1].operators.len(), 4);
+        assert!(encode_document(&doc).unwrap().len() < als.len() / 2);
+        assert_eq!(roundtrip(&doc), doc);
+    }
+
+    #[test]
+    fn test_invalid_bit_packed() {
+        let mut message = to_proto(&AlsParser::new().parse("#a\n1>3").unwrap()).unwrap();
+        let (reference, width, packed) = bit_pack(&[0; 200]);
+        let block = BitPacked { reference, width, count: 200, packed };
+        message.streams[0].operators[0] = Operator {
+            kind: Some(operator::Kind::BitPacked(block.clone())),
+        };
+        assert!(from_proto(&message).is_err());
+
+        message.streams[0].operators[0] = Operator {
+            kind: Some(operator::Kind::Multiply(Box::new(Multiply {
+                value: Some(Box::new(Operator {
+                    kind: Some(operator::Kind::BitPacked(BitPacked { count: 3, ..block })),
+                })),
+                count: 2,
+            }))),
+        };
+        assert!(from_proto(&message).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_bool_runs() {
+        let flags: Vec<&str> = (0..400).map(|i| if (i * 7919) % 3 == 0 { "yes" } else { "no" }).collect();
+        let als = format!("#flag\n?no~yes:{}", boolean_runs(&flags).join("."));
+        let doc = AlsParser::new().parse(&als).unwrap();
+        let message = to_proto(&doc).unwrap();
+
+        let Some(operator::Kind::BoolRuns(runs)) = &message.streams[0].operators[0].kind else {
+            panic!("expected boolean runs");
+        };
+        assert_eq!((runs.count, runs.bits.len()), (400, 50));
+        assert!(runs.runs.is_empty());
+        assert_eq!(roundtrip(&doc), doc);
+
+        // Long runs stay listed, and so do empty first runs
+        for als in ["#flag\n?no~yes:300.200", "#flag\n?no~yes:0.1.1.1.1.1.1.1.1.1.1.1.1.1.1.1.1.1.1"] {
+            let doc = AlsParser::new().parse(als).unwrap();
+            assert_eq!(roundtrip(&doc), doc, "{}", als);
+        }
+    }
+
+    fn boolean_runs(flags: &[&str]) -> Vec<String> {
+        let mut runs = vec![0usize];
+        let mut current = "no";
+        for &flag in flags {
+            if flag != current {
+                runs.push(0);
+                current = flag;
+            }
+            *runs.last_mut().unwrap() += 1;
+        }
+        runs.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_invalid_bool_runs() {
+        let mut message = to_proto(&AlsParser::new().parse("#a\n1>3").unwrap()).unwrap();
+        let runs = BoolRuns {
+            labels: vec!["no".to_string(), "yes".to_string()],
+            runs: Vec::new(),
+            count: 20,
+            bits: vec![0b1010_0000],
+        };
+        message.streams[0].operators[0] = Operator {
+            kind: Some(operator::Kind::BoolRuns(runs.clone())),
+        };
+        assert!(from_proto(&message).is_err());
+
+        message.streams[0].operators[0] = Operator {
+            kind: Some(operator::Kind::BoolRuns(BoolRuns { count: 3, ..runs })),
+        };
+        let doc = from_proto(&message).unwrap();
+        assert_eq!(doc.streams[0].operators[0], AlsOperator::bool_runs("no", "yes", vec![0, 1, 1, 1]));
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let mut message = to_proto(&AlsParser::new().parse("#a\n1>3").unwrap()).unwrap();
+        message.streams[0].operators.push(Operator { kind: None });
+        assert!(matches!(from_proto(&message), Err(AlsError::IoError(_))));
+
+        message.streams[0].operators[1] = Operator {
+            kind: Some(operator::Kind::Cycle(Cycle {
+                cycle: "fortnight".to_string(),
+                ..Cycle::default()
+            })),
+        };
+        assert!(from_proto(&message).is_err());
+
+        assert!(decode_document(b"\xff\xff\xff").is_err());
+    }
+}
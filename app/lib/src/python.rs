@@ -28,7 +28,7 @@
 //! ```
 
 use pyo3::prelude::*;
-use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
 use pyo3::types::PyAny;
 use crate::{AlsCompressor as RustAlsCompressor, AlsParser as RustAlsParser, AlsError, CompressorConfig, ParserConfig};
 
@@ -429,6 +429,30 @@ impl PyAlsParser {
             .map_err(convert_als_error)
     }
 
+    /// Parse ALS format and convert to column-oriented JSON.
+    ///
+    /// Args:
+    ///     als_data (str): ALS format data as a string
+    ///
+    /// Returns:
+    ///     str: JSON data (object mapping column names to value arrays)
+    ///
+    /// Raises:
+    ///     ValueError: If the ALS data is malformed
+    ///     RuntimeError: If parsing fails
+    ///
+    /// Example:
+    ///     >>> parser = AlsParser()
+    ///     >>> als = "#id #name\\n1>3|Alice Bob Charlie"
+    ///     >>> json = parser.to_json_columnar(als)
+    ///     >>> print(json)
+    ///     {"id":[1,2,3],"name":["Alice","Bob","Charlie"]}
+    fn to_json_columnar(&self, als_data: &str) -> PyResult<String> {
+        self.inner
+            .to_json_columnar(als_data)
+            .map_err(convert_als_error)
+    }
+
     /// Get a string representation of the parser.
     fn __repr__(&self) -> String {
         "AlsParser()".to_string()
@@ -541,6 +565,12 @@ fn convert_als_error(error: AlsError) -> PyErr {
                 line, column, message
             ))
         }
+        AlsError::LogParseError { line, message } => {
+            PyValueError::new_err(format!(
+                "Log parsing error at line {}: {}",
+                line, message
+            ))
+        }
         AlsError::JsonParseError(e) => {
             PyValueError::new_err(format!("JSON parsing error: {}", e))
         }
@@ -556,6 +586,12 @@ fn convert_als_error(error: AlsError) -> PyErr {
                 index, size
             ))
         }
+        AlsError::InvalidColumnRef { index, columns } => {
+            PyValueError::new_err(format!(
+                "Invalid column reference: @{} (document has {} preceding columns)",
+                index, columns
+            ))
+        }
         AlsError::RangeOverflow { start, end, step } => {
             PyValueError::new_err(format!(
                 "Range overflow: {} to {} with step {} would produce too many values",
@@ -568,12 +604,36 @@ fn convert_als_error(error: AlsError) -> PyErr {
                 expected, found
             ))
         }
+        AlsError::UnsupportedFeature { feature } => {
+            PyValueError::new_err(format!("Unsupported feature: {}", feature))
+        }
         AlsError::ColumnMismatch { schema, data } => {
             PyValueError::new_err(format!(
                 "Column count mismatch: schema has {} columns, data has {} columns",
                 schema, data
             ))
         }
+        AlsError::UnknownColumn { name } => {
+            PyKeyError::new_err(format!("Unknown column: {}", name))
+        }
+        AlsError::ChecksumMismatch { name, expected, found } => {
+            PyValueError::new_err(format!(
+                "Checksum mismatch in '{}': expected {:08x}, found {:08x}",
+                name, expected, found
+            ))
+        }
+        AlsError::InvalidDelta { message } => {
+            PyValueError::new_err(format!("Invalid delta: {}", message))
+        }
+        AlsError::InvalidSignature { message } => {
+            PyValueError::new_err(format!("Invalid signature: {}", message))
+        }
+        AlsError::DuplicateEntry { name } => {
+            PyKeyError::new_err(format!("Duplicate container entry: {}", name))
+        }
+        error @ (AlsError::SchemaViolation { .. } | AlsError::InvalidSchema { .. } | AlsError::InvalidPredicate { .. } | AlsError::InvalidPattern { .. } | AlsError::InvalidTimestamp { .. } | AlsError::InvalidRow { .. } | AlsError::InvalidBlock { .. } | AlsError::InvalidReshape { .. } | AlsError::InvalidForeignKey { .. }) => {
+            PyValueError::new_err(error.to_string())
+        }
         AlsError::IoError(e) => {
             PyRuntimeError::new_err(format!("IO error: {}", e))
         }
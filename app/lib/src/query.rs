@@ -0,0 +1,699 @@
+//! Equality lookups on ALS documents.
+//!
+//! `AlsQuery` finds the rows of a document where a column equals a value.
+//! Columns with a value index (see `CompressorConfig::build_indexes`) are
+//! answered from the index alone. In block mode, only the blocks whose Bloom
+//! filter may hold the value are expanded and scanned; other columns are
+//! expanded in full.
+//!
+//! A [`Predicate`] such as `status != "debug"` or `path ~ "^/api/"` names a
+//! column, a comparison and a value. The same predicates select rows of a document with
+//! [`AlsQuery::find`] and of tabular data with `TabularData::filter`.
+
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+use crate::als::search::{compile_regex, matches};
+use crate::als::{AlsDocument, AlsOperator, AlsParser, ColumnStream, ZoneOrder, NULL_TOKEN};
+use crate::error::{AlsError, Result};
+
+/// Equality queries over a parsed ALS document.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::{AlsCompressor, AlsQuery, AlsSerializer, CompressorConfig};
+///
+/// let config = CompressorConfig::new().with_build_indexes(true);
+/// let doc = AlsCompressor::with_config(config)
+///     .compress(&als_compression::convert::csv::parse_csv("id,status\n1,open\n2,done\n3,open").unwrap())
+///     .unwrap();
+///
+/// let query = AlsQuery::parse(&AlsSerializer::new().serialize(&doc)).unwrap();
+/// assert!(query.is_indexed("status"));
+/// assert_eq!(query.find_eq("status", "open").unwrap(), vec![0, 2]);
+/// ```
+pub struct AlsQuery {
+    doc: AlsDocument<'static>,
+    parser: AlsParser,
+}
+
+impl AlsQuery {
+    /// Create a query over a document.
+    pub fn new(doc: AlsDocument<'static>) -> Self {
+        Self {
+            doc,
+            parser: AlsParser::new(),
+        }
+    }
+
+    /// Parse ALS text and create a query over it.
+    pub fn parse(input: &str) -> Result<Self> {
+        let parser = AlsParser::new();
+        let doc = parser.parse(input)?;
+        Ok(Self { doc, parser })
+    }
+
+    /// Get the queried document.
+    pub fn document(&self) -> &AlsDocument<'static> {
+        &self.doc
+    }
+
+    /// Check if `column` has a value index.
+    pub fn is_indexed(&self, column: &str) -> bool {
+        self.position(column)
+            .is_ok_and(|position| self.doc.index(position).is_some())
+    }
+
+    /// Find the rows where `column` equals `value`, in ascending order.
+    ///
+    /// Values are compared with their ALS string form, so nulls match
+    /// `NULL_TOKEN` and empty strings match `EMPTY_TOKEN`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the document has no such column,
+    /// or a parse error if an unindexed column cannot be expanded.
+    pub fn find_eq(&self, column: &str, value: &str) -> Result<Vec<usize>> {
+        let position = self.position(column)?;
+        if let Some(index) = self.doc.index(position) {
+            return Ok(index.rows(value));
+        }
+
+        let blocks = self.candidate_blocks(column, value)?;
+        self.scan_blocks(position, &blocks, |v| v == value)
+    }
+
+    /// Find the rows where `column` lies within `range`, in ascending order.
+    ///
+    /// Numeric columns compare numerically and other columns as strings;
+    /// nulls never match. Blocks whose zone map lies outside the range are
+    /// skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, AlsQuery, CompressorConfig};
+    ///
+    /// let config = CompressorConfig::new().with_zone_maps(true);
+    /// let als = AlsCompressor::with_config(config).compress_csv("n\n5\n50\n500").unwrap();
+    ///
+    /// let query = AlsQuery::parse(&als).unwrap();
+    /// assert_eq!(query.find_range("n", "10".."100").unwrap(), vec![1]);
+    /// assert_eq!(query.find_range("n", "50"..).unwrap(), vec![1, 2]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the document has no such column,
+    /// or a parse error if the column cannot be expanded.
+    pub fn find_range<'a, R: RangeBounds<&'a str>>(&self, column: &str, range: R) -> Result<Vec<usize>> {
+        let position = self.position(column)?;
+        let order = match self.doc.zone_maps.get(&position).and_then(|zones| zones.first()) {
+            Some(zone) => zone.order,
+            None => {
+                // Without zone maps, the order comes from the values themselves
+                let values = self.expand_column(position)?;
+                let order = value_order(&values);
+                return Ok(matching_rows(&values, 0, |v| v != NULL_TOKEN && order.contains(&range, v)).collect());
+            }
+        };
+
+        let blocks = self.candidate_range_blocks(column, &range)?;
+        self.scan_blocks(position, &blocks, |v| v != NULL_TOKEN && order.contains(&range, v))
+    }
+
+    /// Find the rows matching `predicate`, in ascending order.
+    ///
+    /// Equality goes through `find_eq` and ordered comparisons through
+    /// `find_range`, so indexes, Bloom filters and zone maps are used as
+    /// they would be there. `!=` matches every row `=` does not, nulls
+    /// included.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{AlsCompressor, AlsQuery, Predicate};
+    ///
+    /// let als = AlsCompressor::new().compress_csv("id,level\n1,debug\n2,info\n3,warn").unwrap();
+    /// let query = AlsQuery::parse(&als).unwrap();
+    /// assert_eq!(query.find(&"level != debug".parse::<Predicate>().unwrap()).unwrap(), vec![1, 2]);
+    /// assert_eq!(query.find(&Predicate::parse("id >= 2").unwrap()).unwrap(), vec![1, 2]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the document has no such column,
+    /// or a parse error if the column cannot be expanded.
+    pub fn find(&self, predicate: &Predicate) -> Result<Vec<usize>> {
+        let column = predicate.column.as_str();
+        match (predicate.comparison, predicate.range()) {
+            (Comparison::Eq, _) => self.find_eq(column, &predicate.value),
+            (Comparison::Matches, _) => self.find_matches(column, &predicate.value),
+            (_, Some(range)) => self.find_range(column, range),
+            (_, None) => {
+                let equal = self.find_eq(column, &predicate.value)?;
+                let mut equal = equal.into_iter().peekable();
+                Ok((0..self.doc.row_count())
+                    .filter(|&row| equal.next_if_eq(&row).is_none())
+                    .collect())
+            }
+        }
+    }
+
+    /// Find the rows where `column` has a match of the regular expression
+    /// `pattern`, in ascending order.
+    ///
+    /// The expression is unanchored and nulls never match. It is tested
+    /// once per dictionary entry rather than once per row, so columns of
+    /// dictionary references are searched without expanding them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::AlsQuery;
+    ///
+    /// let query = AlsQuery::parse("$default:200|404|503\n#id #status\n1>4|_0 _2 _1 _2").unwrap();
+    /// assert_eq!(query.find_matches("status", r"^5\d\d$").unwrap(), vec![1, 3]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if the document has no such column,
+    /// `AlsError::InvalidPattern` if `pattern` is not a valid regular
+    /// expression, or a parse error if the column cannot be expanded.
+    pub fn find_matches(&self, column: &str, pattern: &str) -> Result<Vec<usize>> {
+        let position = self.position(column)?;
+        self.parser.search_column_regex(&self.doc, position, &compile_regex(pattern)?)
+    }
+
+    /// Get the blocks that may hold rows where `column` lies within `range`.
+    ///
+    /// Blocks whose zone map lies outside the range are skipped; without
+    /// zone maps for the column, every block is a candidate.
+    pub fn candidate_range_blocks<'a, R: RangeBounds<&'a str>>(&self, column: &str, range: &R) -> Result<Vec<usize>> {
+        let position = self.position(column)?;
+        Ok(match self.doc.zone_maps.get(&position) {
+            Some(zones) => zones
+                .iter()
+                .enumerate()
+                .filter(|(_, zone)| zone.may_overlap(range))
+                .map(|(block, _)| block)
+                .collect(),
+            None => (0..self.doc.block_count()).collect(),
+        })
+    }
+
+    /// Get the blocks that may hold rows where `column` equals `value`.
+    ///
+    /// Blocks whose Bloom filter rules the value out are skipped; without
+    /// filters for the column, every block is a candidate.
+    pub fn candidate_blocks(&self, column: &str, value: &str) -> Result<Vec<usize>> {
+        let position = self.position(column)?;
+        Ok(match self.doc.blooms.get(&position) {
+            Some(filters) => filters
+                .iter()
+                .enumerate()
+                .filter(|(_, filter)| filter.may_contain(value))
+                .map(|(block, _)| block)
+                .collect(),
+            None => (0..self.doc.block_count()).collect(),
+        })
+    }
+
+    /// Count the rows where `column` equals `value`.
+    pub fn count_eq(&self, column: &str, value: &str) -> Result<usize> {
+        let position = self.position(column)?;
+        match self.doc.index(position) {
+            Some(index) => Ok(index.count(value)),
+            None => self.find_eq(column, value).map(|rows| rows.len()),
+        }
+    }
+
+    /// Get a document holding only the rows where `column` equals `value`.
+    ///
+    /// The result stores its values verbatim and carries no indexes.
+    pub fn select_eq(&self, column: &str, value: &str) -> Result<AlsDocument<'static>> {
+        let rows = self.find_eq(column, value)?;
+        self.select_rows(&rows)
+    }
+
+    /// Get a document holding only the given rows, in the given order.
+    ///
+    /// Rows past the end of the document are ignored. Values are read one
+    /// by one with [`ColumnStream::value_at`], so only columns referring to
+    /// other columns are expanded. The result stores its values verbatim
+    /// and carries no indexes.
+    pub fn select_rows(&self, rows: &[usize]) -> Result<AlsDocument<'static>> {
+        let row_count = self.doc.row_count();
+        let rows: Vec<usize> = rows.iter().copied().filter(|&row| row < row_count).collect();
+        let dictionary = self.doc.default_dictionary().map(|d| d.as_slice());
+
+        let mut result = AlsDocument::with_schema(self.doc.schema.clone());
+        for (position, stream) in self.doc.streams.iter().enumerate() {
+            if stream.expanded_count() != row_count {
+                return Err(AlsError::ColumnMismatch {
+                    schema: row_count,
+                    data: stream.expanded_count(),
+                });
+            }
+            let operators = if stream.has_column_refs() {
+                let values = self.expand_column(position)?;
+                rows.iter().map(|&row| AlsOperator::raw(values[row].clone())).collect()
+            } else {
+                rows.iter()
+                    .map(|&row| Ok(AlsOperator::raw(stream.value_at(row, dictionary)?.unwrap_or_default())))
+                    .collect::<Result<_>>()?
+            };
+            result.add_stream(ColumnStream::from_operators(operators));
+        }
+
+        Ok(result)
+    }
+
+    /// Scan the given blocks of the column at `position` for values
+    /// matching `predicate`.
+    fn scan_blocks<P: Fn(&str) -> bool>(&self, position: usize, blocks: &[usize], predicate: P) -> Result<Vec<usize>> {
+        let mut rows = Vec::new();
+        for &block in blocks {
+            let start = self.doc.block_rows(block).start;
+            let values = self.expand_block_column(position, block)?;
+            rows.extend(matching_rows(&values, start, &predicate));
+        }
+        Ok(rows)
+    }
+
+    /// Expand the whole column at `position`, block by block.
+    pub(crate) fn expand_column(&self, position: usize) -> Result<Vec<String>> {
+        let mut values = Vec::new();
+        for block in 0..self.doc.block_count() {
+            values.extend(self.expand_block_column(position, block)?);
+        }
+        Ok(values)
+    }
+
+    /// Expand one block of the column at `position`, along with the
+    /// preceding columns of the block if it refers to them.
+    pub(crate) fn expand_block_column(&self, position: usize, block: usize) -> Result<Vec<String>> {
+        let dictionary = self.doc.default_dictionary().map(|d| d.as_slice());
+        let stream = self.doc.block_stream(position, block)?;
+        if !stream.has_column_refs() {
+            return stream.expand(dictionary);
+        }
+
+        let mut columns = Vec::with_capacity(position);
+        for preceding in 0..position {
+            let preceding = self.doc.block_stream(preceding, block)?;
+            let values = if preceding.has_column_refs() {
+                preceding.expand_with_columns(dictionary, &columns)?
+            } else {
+                preceding.expand(dictionary)?
+            };
+            columns.push(values);
+        }
+        stream.expand_with_columns(dictionary, &columns)
+    }
+
+    /// Get the position of `column` in the schema.
+    pub(crate) fn position(&self, column: &str) -> Result<usize> {
+        self.doc
+            .schema
+            .iter()
+            .position(|name| name == column)
+            .ok_or_else(|| AlsError::UnknownColumn {
+                name: column.to_string(),
+            })
+    }
+}
+
+/// Comparison operator of a [`Predicate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `=` or `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `~`, a match of a regular expression
+    Matches,
+}
+
+impl Comparison {
+    /// Operators as written, two-character ones first so `<=` is not read
+    /// as `<`.
+    const OPERATORS: [(Comparison, &'static str); 8] = [
+        (Comparison::Ne, "!="),
+        (Comparison::Le, "<="),
+        (Comparison::Ge, ">="),
+        (Comparison::Eq, "=="),
+        (Comparison::Lt, "<"),
+        (Comparison::Gt, ">"),
+        (Comparison::Eq, "="),
+        (Comparison::Matches, "~"),
+    ];
+
+    /// Get the operator as written in a predicate.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Matches => "~",
+        }
+    }
+}
+
+/// A condition on one column, such as `status != "debug"`,
+/// `latency >= 250` or `code ~ "5\d\d"`.
+///
+/// Values are compared with their ALS string form, as in
+/// [`AlsQuery::find_eq`]. Ordered comparisons follow
+/// [`AlsQuery::find_range`]: columns whose values are all numbers compare
+/// numerically, others as strings, and nulls never match. `~` follows
+/// [`AlsQuery::find_matches`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Predicate {
+    /// Column the condition applies to
+    pub column: String,
+    /// How values are compared
+    pub comparison: Comparison,
+    /// Value compared against
+    pub value: String,
+}
+
+impl Predicate {
+    /// Create a predicate.
+    pub fn new<C: Into<String>, V: Into<String>>(column: C, comparison: Comparison, value: V) -> Self {
+        Self {
+            column: column.into(),
+            comparison,
+            value: value.into(),
+        }
+    }
+
+    /// Parse a predicate written as `COLUMN OP VALUE`, where `OP` is one of
+    /// `=`, `==`, `!=`, `<`, `<=`, `>`, `>=` or `~`.
+    ///
+    /// Spaces around the column and value are ignored. The value may be
+    /// wrapped in single or double quotes to keep such spaces.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use als_compression::{Comparison, Predicate};
+    ///
+    /// let predicate = Predicate::parse(r#"status != "debug""#).unwrap();
+    /// assert_eq!(predicate, Predicate::new("status", Comparison::Ne, "debug"));
+    /// assert_eq!(Predicate::parse("n<=5").unwrap().comparison, Comparison::Le);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidPredicate` if the column or operator is
+    /// missing, and `AlsError::InvalidPattern` if the value of `~` is not a
+    /// valid regular expression.
+    pub fn parse(input: &str) -> Result<Self> {
+        let invalid = |message: &str| AlsError::InvalidPredicate {
+            message: format!("{} in '{}'", message, input),
+        };
+
+        let split = input
+            .find(['=', '!', '<', '>', '~'])
+            .ok_or_else(|| invalid("expected COLUMN=VALUE or a comparison"))?;
+        let (column, rest) = input.split_at(split);
+        let column = column.trim();
+        if column.is_empty() {
+            return Err(invalid("missing column name"));
+        }
+        let (comparison, value) = Comparison::OPERATORS
+            .into_iter()
+            .find_map(|(comparison, op)| rest.strip_prefix(op).map(|value| (comparison, value)))
+            .ok_or_else(|| invalid("expected one of =, !=, <, <=, >, >= or ~"))?;
+
+        let value = value.trim();
+        let value = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        if comparison == Comparison::Matches {
+            compile_regex(value)?;
+        }
+        Ok(Self::new(column, comparison, value))
+    }
+
+    /// Get the value range matched by an ordered comparison or equality, or
+    /// `None` for `!=` and `~`.
+    pub fn range(&self) -> Option<(Bound<&str>, Bound<&str>)> {
+        let value = self.value.as_str();
+        Some(match self.comparison {
+            Comparison::Eq => (Bound::Included(value), Bound::Included(value)),
+            Comparison::Ne | Comparison::Matches => return None,
+            Comparison::Lt => (Bound::Unbounded, Bound::Excluded(value)),
+            Comparison::Le => (Bound::Unbounded, Bound::Included(value)),
+            Comparison::Gt => (Bound::Excluded(value), Bound::Unbounded),
+            Comparison::Ge => (Bound::Included(value), Bound::Unbounded),
+        })
+    }
+
+    /// Check each of a column's values, given in their ALS string form.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::InvalidPattern` if the value of `~` is not a
+    /// valid regular expression.
+    pub(crate) fn matches<S: AsRef<str>>(&self, values: &[S]) -> Result<Vec<bool>> {
+        let value = self.value.as_str();
+        Ok(match (self.comparison, self.range()) {
+            (Comparison::Eq, _) => values.iter().map(|v| v.as_ref() == value).collect(),
+            (Comparison::Matches, _) => {
+                let regex = compile_regex(value)?;
+                values.iter().map(|v| matches(&regex, v.as_ref())).collect()
+            }
+            (_, Some(range)) => {
+                let order = value_order(values);
+                values
+                    .iter()
+                    .map(|v| v.as_ref() != NULL_TOKEN && order.contains(&range, v.as_ref()))
+                    .collect()
+            }
+            (_, None) => values.iter().map(|v| v.as_ref() != value).collect(),
+        })
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = AlsError;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Self::parse(input)
+    }
+}
+
+/// Order values compare in: numeric if every non-null value is a number.
+pub(crate) fn value_order<S: AsRef<str>>(values: &[S]) -> ZoneOrder {
+    let numeric = values
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|v| *v != NULL_TOKEN)
+        .all(|v| v.parse::<f64>().is_ok());
+    if numeric {
+        ZoneOrder::Numeric
+    } else {
+        ZoneOrder::Text
+    }
+}
+
+/// Rows of `values` matching `predicate`, numbered from `start`.
+fn matching_rows<'a, P: Fn(&str) -> bool + 'a>(
+    values: &'a [String],
+    start: usize,
+    predicate: P,
+) -> impl Iterator<Item = usize> + 'a {
+    values
+        .iter()
+        .enumerate()
+        .filter(move |(_, v)| predicate(v))
+        .map(move |(i, _)| start + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::als::AlsSerializer;
+    use crate::compress::AlsCompressor;
+    use crate::config::CompressorConfig;
+    use crate::convert::csv::parse_csv;
+
+    const CSV: &str = "id,status,note\n1,open,a\n2,open,\n3,done,b\n4,open,c\n5,done,\n6,held,d";
+
+    fn query(build_indexes: bool) -> AlsQuery {
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_build_indexes(build_indexes);
+        let doc = AlsCompressor::with_config(config).compress(&parse_csv(CSV).unwrap()).unwrap();
+        AlsQuery::parse(&AlsSerializer::new().serialize(&doc)).unwrap()
+    }
+
+    #[test]
+    fn test_indexed_and_scanned_lookups_agree() {
+        let indexed = query(true);
+        let scanned = query(false);
+        assert!(indexed.is_indexed("status"));
+        assert!(!indexed.is_indexed("id"));
+        assert!(!scanned.is_indexed("status"));
+
+        for (column, value) in [("status", "open"), ("status", "done"), ("status", "gone"), ("note", "\\0"), ("id", "3")] {
+            assert_eq!(indexed.find_eq(column, value).unwrap(), scanned.find_eq(column, value).unwrap());
+            assert_eq!(indexed.count_eq(column, value).unwrap(), scanned.count_eq(column, value).unwrap());
+        }
+        assert_eq!(indexed.find_eq("status", "open").unwrap(), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_select_eq() {
+        let selected = query(true).select_eq("status", "done").unwrap();
+        let rows = AlsParser::new().expand(&selected).unwrap();
+        assert_eq!(rows, vec![vec!["3", "done", "b"], vec!["5", "done", "\\0"]]);
+
+        let empty = query(true).select_eq("status", "gone").unwrap();
+        assert_eq!(empty.schema.len(), 3);
+        assert_eq!(empty.row_count(), 0);
+    }
+
+    #[test]
+    fn test_block_mode_skips_blocks() {
+        let mut csv = String::from("id,user,level\n");
+        for i in 0..400 {
+            csv.push_str(&format!("{},user{},{}\n", i, i / 100, if i % 7 == 0 { "warn" } else { "info" }));
+        }
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_block_size(100)
+            .with_build_indexes(true);
+        let doc = AlsCompressor::with_config(config).compress(&parse_csv(&csv).unwrap()).unwrap();
+        let text = AlsSerializer::new().serialize(&doc);
+        assert!(text.contains("\n!blocks 100"));
+        assert!(doc.indexes.is_empty());
+
+        let blocked = AlsQuery::parse(&text).unwrap();
+        assert_eq!(blocked.document().block_count(), 4);
+        assert_eq!(blocked.candidate_blocks("user", "user2").unwrap(), vec![2]);
+        assert_eq!(blocked.find_eq("user", "user2").unwrap(), (200..300).collect::<Vec<_>>());
+        assert_eq!(blocked.find_eq("id", "317").unwrap(), vec![317]);
+
+        let scanned = AlsQuery::parse(&AlsCompressor::new().compress_csv(&csv).unwrap()).unwrap();
+        for (column, value) in [("level", "warn"), ("level", "info"), ("user", "user9")] {
+            assert_eq!(blocked.find_eq(column, value).unwrap(), scanned.find_eq(column, value).unwrap());
+        }
+        assert_eq!(AlsParser::new().expand(blocked.document()).unwrap(), AlsParser::new().expand(scanned.document()).unwrap());
+    }
+
+    #[test]
+    fn test_find_range_prunes_blocks() {
+        let mut csv = String::from("ts,name\n");
+        for i in 0..300 {
+            csv.push_str(&format!("{},{}\n", 1000 + i, if i % 50 == 0 { "" } else { "x" }));
+        }
+        let data = parse_csv(&csv).unwrap();
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_block_size(100)
+            .with_zone_maps(true);
+        let doc = AlsCompressor::with_config(config).compress(&data).unwrap();
+        let zoned = AlsQuery::parse(&AlsSerializer::new().serialize(&doc)).unwrap();
+        let plain = AlsQuery::new(AlsCompressor::new().compress(&data).unwrap());
+
+        let zone = zoned.document().column_zone(0).unwrap();
+        assert_eq!((zone.min.as_deref(), zone.max.as_deref()), (Some("1000"), Some("1299")));
+        assert_eq!(zoned.document().column_zone(1).unwrap().null_count, 6);
+
+        assert_eq!(zoned.candidate_range_blocks("ts", &("1150".."1180")).unwrap(), vec![1]);
+        assert_eq!(zoned.candidate_range_blocks("ts", &("1299"..)).unwrap(), vec![2]);
+        assert!(zoned.candidate_range_blocks("ts", &(.."1000")).unwrap().is_empty());
+        assert_eq!(plain.candidate_range_blocks("ts", &("1150".."1180")).unwrap(), vec![0]);
+
+        for range in [("1150".."1180"), ("990".."1003"), ("1299".."2000")] {
+            let rows = zoned.find_range("ts", range.clone()).unwrap();
+            assert_eq!(rows, plain.find_range("ts", range.clone()).unwrap());
+            let (start, end): (i64, i64) = (range.start.parse().unwrap(), range.end.parse().unwrap());
+            assert_eq!(rows, (0..300).filter(|i| (start..end).contains(&(1000 + i))).map(|i| i as usize).collect::<Vec<_>>());
+        }
+        assert_eq!(zoned.find_range("name", "w"..="x").unwrap().len(), 294);
+        assert_eq!(plain.find_range("name", ..).unwrap().len(), 294);
+    }
+
+    #[test]
+    fn test_select_rows() {
+        let selected = query(false).select_rows(&[5, 0, 99]).unwrap();
+        let rows = AlsParser::new().expand(&selected).unwrap();
+        assert_eq!(rows, vec![vec!["6", "held", "d"], vec!["1", "open", "a"]]);
+
+        // Columns referring to others are expanded with them
+        let query = AlsQuery::parse("$default:buy|sell\n#side #sign\n_0 _1*2 _0|@0(buy:1 sell:-1)*4").unwrap();
+        let rows = AlsParser::new().expand(&query.select_rows(&[2, 0]).unwrap()).unwrap();
+        assert_eq!(rows, vec![vec!["sell", "-1"], vec!["buy", "1"]]);
+    }
+
+    #[test]
+    fn test_predicate_parse() {
+        let parse = |input: &str| Predicate::parse(input).unwrap();
+        assert_eq!(parse("status=open"), Predicate::new("status", Comparison::Eq, "open"));
+        assert_eq!(parse("status == open"), Predicate::new("status", Comparison::Eq, "open"));
+        assert_eq!(parse(r#" status != "debug" "#), Predicate::new("status", Comparison::Ne, "debug"));
+        assert_eq!(parse("note = ' a b '"), Predicate::new("note", Comparison::Eq, " a b "));
+        assert_eq!(parse("n>=5"), Predicate::new("n", Comparison::Ge, "5"));
+        assert_eq!(parse("n < -1"), Predicate::new("n", Comparison::Lt, "-1"));
+        assert_eq!(parse("note="), Predicate::new("note", Comparison::Eq, ""));
+        assert_eq!(parse(r#"code ~ "5\d\d""#), Predicate::new("code", Comparison::Matches, r"5\d\d"));
+
+        for input in ["status", "=open", "status ! open", " != x"] {
+            assert!(matches!(Predicate::parse(input), Err(AlsError::InvalidPredicate { .. })), "{input}");
+        }
+        assert!(matches!(Predicate::parse("code ~ 5(\\d"), Err(AlsError::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_find_predicate() {
+        for query in [query(true), query(false)] {
+            let find = |input: &str| query.find(&Predicate::parse(input).unwrap()).unwrap();
+            assert_eq!(find("status = open"), vec![0, 1, 3]);
+            assert_eq!(find("status != open"), vec![2, 4, 5]);
+            assert_eq!(find("note != a"), vec![1, 2, 3, 4, 5]);
+            assert_eq!(find("id > 4"), vec![4, 5]);
+            assert_eq!(find("status < h"), vec![2, 4]);
+            assert_eq!(find("status ~ ^(done|held)$"), vec![2, 4, 5]);
+            assert_eq!(find("note ~ ."), vec![0, 2, 3, 5]);
+        }
+
+        let predicate = Predicate::parse("n <= 10").unwrap();
+        assert_eq!(predicate.matches(&["5", "10", "100", NULL_TOKEN]).unwrap(), vec![true, true, false, false]);
+        assert_eq!(Predicate::parse("n != 5").unwrap().matches(&["5", NULL_TOKEN]).unwrap(), vec![false, true]);
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let query = AlsQuery::parse("$default:GET /a 200|GET /b 503|POST /a 500\n#id #line\n1>6|_0*2 _1 (_2)*2 \\0").unwrap();
+        assert_eq!(query.find_matches("line", r" 5\d\d$").unwrap(), vec![2, 3, 4]);
+        assert_eq!(query.find_matches("line", "^GET").unwrap(), vec![0, 1, 2]);
+        assert_eq!(query.find_matches("id", "^[56]$").unwrap(), vec![4, 5]);
+        assert!(matches!(query.find_matches("line", "("), Err(AlsError::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        assert!(matches!(
+            query(true).find_eq("missing", "x"),
+            Err(AlsError::UnknownColumn { name }) if name == "missing"
+        ));
+    }
+}
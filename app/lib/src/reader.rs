@@ -0,0 +1,537 @@
+//! Chunked columnar reads for query engines.
+//!
+//! `AlsChunkedReader` decodes a document into batches of typed column
+//! vectors, so the scan operator of a query engine can read ALS without
+//! going through CSV text. Projection limits the columns that are decoded,
+//! and filters drop rows; equality and range filters also skip whole blocks
+//! using the Bloom filters and zone maps of block-mode documents.
+//!
+//! Each column has one type for the whole scan, inferred from its values
+//! the way decompression to CSV infers them: integers, floats, booleans,
+//! and strings for everything else. Integer columns holding floats widen to
+//! floats; other mixes are read as strings.
+
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+use crate::als::{AlsDocument, ZoneOrder, EMPTY_TOKEN, NULL_TOKEN};
+use crate::convert::ColumnType;
+use crate::error::{AlsError, Result};
+use crate::query::AlsQuery;
+
+/// Default number of rows in a batch.
+pub const DEFAULT_BATCH_SIZE: usize = 2048;
+
+/// Values of one column in a batch, with `None` for nulls.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnVector {
+    /// Integer column.
+    Integer(Vec<Option<i64>>),
+    /// Float column.
+    Float(Vec<Option<f64>>),
+    /// Boolean column.
+    Boolean(Vec<Option<bool>>),
+    /// String column.
+    String(Vec<Option<String>>),
+}
+
+impl ColumnVector {
+    /// Create an empty vector for a column of type `column_type`.
+    fn with_capacity(column_type: ColumnType, capacity: usize) -> Self {
+        match column_type {
+            ColumnType::Integer => ColumnVector::Integer(Vec::with_capacity(capacity)),
+            ColumnType::Float => ColumnVector::Float(Vec::with_capacity(capacity)),
+            ColumnType::Boolean => ColumnVector::Boolean(Vec::with_capacity(capacity)),
+            ColumnType::String | ColumnType::Mixed => ColumnVector::String(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Get the type of the column.
+    pub fn column_type(&self) -> ColumnType {
+        match self {
+            ColumnVector::Integer(_) => ColumnType::Integer,
+            ColumnVector::Float(_) => ColumnType::Float,
+            ColumnVector::Boolean(_) => ColumnType::Boolean,
+            ColumnVector::String(_) => ColumnType::String,
+        }
+    }
+
+    /// Get the number of values.
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnVector::Integer(values) => values.len(),
+            ColumnVector::Float(values) => values.len(),
+            ColumnVector::Boolean(values) => values.len(),
+            ColumnVector::String(values) => values.len(),
+        }
+    }
+
+    /// Check if the vector holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append an expanded ALS value.
+    fn push(&mut self, value: &str) {
+        if value == NULL_TOKEN {
+            match self {
+                ColumnVector::Integer(values) => values.push(None),
+                ColumnVector::Float(values) => values.push(None),
+                ColumnVector::Boolean(values) => values.push(None),
+                ColumnVector::String(values) => values.push(None),
+            }
+            return;
+        }
+        match self {
+            ColumnVector::Integer(values) => values.push(value.parse().ok()),
+            ColumnVector::Float(values) => values.push(value.parse().ok()),
+            ColumnVector::Boolean(values) => values.push(parse_boolean(value)),
+            ColumnVector::String(values) => {
+                let value = if value == EMPTY_TOKEN { "" } else { value };
+                values.push(Some(value.to_string()));
+            }
+        }
+    }
+}
+
+/// A batch of rows, one vector per projected column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordBatch {
+    /// Positions of the rows in the document.
+    pub rows: Vec<usize>,
+    /// Column values, in projection order.
+    pub columns: Vec<ColumnVector>,
+}
+
+impl RecordBatch {
+    /// Get the number of rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Check if the batch holds no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+/// Row filter on the ALS string form of a value.
+#[derive(Clone)]
+enum Filter {
+    Eq(String),
+    Range(Bound<String>, Bound<String>),
+    Custom(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+/// Columns, types and blocks of a scan, resolved on first use.
+struct Plan {
+    columns: Vec<usize>,
+    types: Vec<ColumnType>,
+    filters: Vec<(usize, Filter, ZoneOrder)>,
+    blocks: Vec<usize>,
+}
+
+/// Expanded values of the block being read.
+struct BlockValues {
+    /// Row number of the first value.
+    start: usize,
+    /// Values of every column the scan reads, by column position.
+    values: Vec<Option<Vec<String>>>,
+    /// Next row to read, relative to `start`.
+    next: usize,
+    len: usize,
+}
+
+/// Reads a document in batches of typed column vectors.
+///
+/// # Example
+///
+/// ```
+/// use als_compression::{AlsChunkedReader, AlsCompressor, ColumnVector};
+///
+/// let als = AlsCompressor::new().compress_csv("id,level\n1,info\n2,warn\n3,info\n4,error").unwrap();
+/// let mut reader = AlsChunkedReader::parse(&als)
+///     .unwrap()
+///     .with_batch_size(2)
+///     .with_projection(&["id"])
+///     .with_filter_eq("level", "info");
+///
+/// let batch = reader.next_batch().unwrap().unwrap();
+/// assert_eq!(batch.rows, vec![0, 2]);
+/// assert_eq!(batch.columns[0], ColumnVector::Integer(vec![Some(1), Some(3)]));
+/// assert!(reader.next_batch().unwrap().is_none());
+/// ```
+pub struct AlsChunkedReader {
+    query: AlsQuery,
+    batch_size: usize,
+    projection: Option<Vec<String>>,
+    filters: Vec<(String, Filter)>,
+    plan: Option<Plan>,
+    next_block: usize,
+    block: Option<BlockValues>,
+}
+
+impl AlsChunkedReader {
+    /// Create a reader over a document, reading every column.
+    pub fn new(doc: AlsDocument<'static>) -> Self {
+        Self::from_query(AlsQuery::new(doc))
+    }
+
+    /// Parse ALS text and create a reader over it.
+    pub fn parse(input: &str) -> Result<Self> {
+        AlsQuery::parse(input).map(Self::from_query)
+    }
+
+    fn from_query(query: AlsQuery) -> Self {
+        Self {
+            query,
+            batch_size: DEFAULT_BATCH_SIZE,
+            projection: None,
+            filters: Vec::new(),
+            plan: None,
+            next_block: 0,
+            block: None,
+        }
+    }
+
+    /// Set the number of rows in each batch; only the last batch may be
+    /// shorter.
+    pub fn with_batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = rows.max(1);
+        self
+    }
+
+    /// Read only the named columns, in the given order.
+    pub fn with_projection(mut self, columns: &[&str]) -> Self {
+        self.projection = Some(columns.iter().map(|column| column.to_string()).collect());
+        self.plan = None;
+        self
+    }
+
+    /// Read only the rows where `column` equals `value`, compared in ALS
+    /// string form as in [`AlsQuery::find_eq`].
+    ///
+    /// Blocks whose Bloom filter rules the value out are skipped.
+    pub fn with_filter_eq(self, column: &str, value: &str) -> Self {
+        self.with(column, Filter::Eq(value.to_string()))
+    }
+
+    /// Read only the rows where `column` lies within `range`, compared as
+    /// in [`AlsQuery::find_range`].
+    ///
+    /// Blocks whose zone map lies outside the range are skipped.
+    pub fn with_filter_range<'a, R: RangeBounds<&'a str>>(self, column: &str, range: R) -> Self {
+        let start = range.start_bound().map(|bound| bound.to_string());
+        let end = range.end_bound().map(|bound| bound.to_string());
+        self.with(column, Filter::Range(start, end))
+    }
+
+    /// Read only the rows where `predicate` accepts the value of `column`.
+    ///
+    /// The predicate sees values in ALS string form, with nulls as
+    /// `NULL_TOKEN`. No blocks are skipped.
+    pub fn with_filter<F>(self, column: &str, predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.with(column, Filter::Custom(Arc::new(predicate)))
+    }
+
+    fn with(mut self, column: &str, filter: Filter) -> Self {
+        self.filters.push((column.to_string(), filter));
+        self.plan = None;
+        self
+    }
+
+    /// Get the names and types of the projected columns.
+    ///
+    /// Inferring the types expands the projected columns once, so the
+    /// first call costs about as much as a scan.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if a projected or filtered column
+    /// is not in the document.
+    pub fn schema(&mut self) -> Result<Vec<(String, ColumnType)>> {
+        self.prepare()?;
+        let plan = self.plan.as_ref().expect("plan was resolved");
+        let schema = &self.query.document().schema;
+        Ok(plan
+            .columns
+            .iter()
+            .zip(&plan.types)
+            .map(|(&position, &column_type)| (schema[position].clone(), column_type))
+            .collect())
+    }
+
+    /// Read the next batch, or `None` once every row was read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::UnknownColumn` if a projected or filtered column
+    /// is not in the document, or a parse error if a block cannot be
+    /// expanded.
+    pub fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        self.prepare()?;
+        let plan = self.plan.as_ref().expect("plan was resolved");
+        let mut batch = RecordBatch {
+            rows: Vec::with_capacity(self.batch_size),
+            columns: plan
+                .types
+                .iter()
+                .map(|&column_type| ColumnVector::with_capacity(column_type, self.batch_size))
+                .collect(),
+        };
+
+        while batch.len() < self.batch_size {
+            let block = match &mut self.block {
+                Some(block) if block.next < block.len => block,
+                _ => {
+                    let Some(&block) = plan.blocks.get(self.next_block) else {
+                        break;
+                    };
+                    self.next_block += 1;
+                    self.block = Some(expand_block(&self.query, plan, block)?);
+                    continue;
+                }
+            };
+
+            let row = block.next;
+            block.next += 1;
+            let value = |position: usize| block.values[position].as_ref().map_or("", |values| values[row].as_str());
+            let matches = plan.filters.iter().all(|(position, filter, order)| {
+                let value = value(*position);
+                match filter {
+                    Filter::Eq(expected) => value == expected,
+                    Filter::Range(start, end) => {
+                        value != NULL_TOKEN
+                            && order.contains(&(start.as_ref().map(String::as_str), end.as_ref().map(String::as_str)), value)
+                    }
+                    Filter::Custom(predicate) => predicate(value),
+                }
+            });
+            if !matches {
+                continue;
+            }
+
+            batch.rows.push(block.start + row);
+            for (vector, &position) in batch.columns.iter_mut().zip(&plan.columns) {
+                vector.push(value(position));
+            }
+        }
+
+        Ok((!batch.is_empty()).then_some(batch))
+    }
+
+    /// Resolve the columns, types and candidate blocks of the scan.
+    fn prepare(&mut self) -> Result<()> {
+        if self.plan.is_none() {
+            self.plan = Some(self.resolve()?);
+            self.next_block = 0;
+            self.block = None;
+        }
+        Ok(())
+    }
+
+    fn resolve(&self) -> Result<Plan> {
+        let doc = self.query.document();
+        let columns = match &self.projection {
+            Some(names) => names.iter().map(|name| self.position(name)).collect::<Result<Vec<_>>>()?,
+            None => (0..doc.column_count()).collect(),
+        };
+
+        let mut blocks: Vec<usize> = (0..doc.block_count()).collect();
+        let mut filters = Vec::with_capacity(self.filters.len());
+        for (name, filter) in &self.filters {
+            let position = self.position(name)?;
+            let candidates = match filter {
+                Filter::Eq(value) => Some(self.query.candidate_blocks(name, value)?),
+                Filter::Range(start, end) => Some(
+                    self.query
+                        .candidate_range_blocks(name, &(start.as_ref().map(String::as_str), end.as_ref().map(String::as_str)))?,
+                ),
+                Filter::Custom(_) => None,
+            };
+            if let Some(candidates) = candidates {
+                blocks.retain(|block| candidates.contains(block));
+            }
+
+            let order = match doc.zone_maps.get(&position).and_then(|zones| zones.first()) {
+                Some(zone) => zone.order,
+                None if matches!(filter, Filter::Range(..)) => match self.infer_type(position)? {
+                    ColumnType::Integer | ColumnType::Float => ZoneOrder::Numeric,
+                    _ => ZoneOrder::Text,
+                },
+                None => ZoneOrder::Text,
+            };
+            filters.push((position, filter.clone(), order));
+        }
+
+        let types = columns.iter().map(|&position| self.infer_type(position)).collect::<Result<_>>()?;
+        Ok(Plan {
+            columns,
+            types,
+            filters,
+            blocks,
+        })
+    }
+
+    /// Infer the type of the column at `position` from all its values.
+    fn infer_type(&self, position: usize) -> Result<ColumnType> {
+        let mut inferred: Option<ColumnType> = None;
+        for block in 0..self.query.document().block_count() {
+            for value in self.query.expand_block_column(position, block)? {
+                let Some(value_type) = value_type(&value) else {
+                    continue;
+                };
+                inferred = Some(match (inferred, value_type) {
+                    (None, value_type) => value_type,
+                    (Some(a), b) if a == b => a,
+                    (Some(ColumnType::Integer), ColumnType::Float) | (Some(ColumnType::Float), ColumnType::Integer) => {
+                        ColumnType::Float
+                    }
+                    _ => return Ok(ColumnType::String),
+                });
+            }
+        }
+        Ok(inferred.unwrap_or(ColumnType::String))
+    }
+
+    fn position(&self, column: &str) -> Result<usize> {
+        self.query
+            .document()
+            .schema
+            .iter()
+            .position(|name| name == column)
+            .ok_or_else(|| AlsError::UnknownColumn {
+                name: column.to_string(),
+            })
+    }
+}
+
+impl Iterator for AlsChunkedReader {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+/// Expand the columns a scan reads for one block.
+fn expand_block(query: &AlsQuery, plan: &Plan, block: usize) -> Result<BlockValues> {
+    let doc = query.document();
+    let mut values: Vec<Option<Vec<String>>> = vec![None; doc.column_count()];
+    let positions = plan.columns.iter().chain(plan.filters.iter().map(|(position, _, _)| position));
+    for &position in positions {
+        if values[position].is_none() {
+            values[position] = Some(query.expand_block_column(position, block)?);
+        }
+    }
+    let rows = doc.block_rows(block);
+    Ok(BlockValues {
+        start: rows.start,
+        values,
+        next: 0,
+        len: rows.len(),
+    })
+}
+
+/// Type of an expanded value, or `None` for nulls.
+fn value_type(value: &str) -> Option<ColumnType> {
+    if value == NULL_TOKEN || value.is_empty() {
+        None
+    } else if value == EMPTY_TOKEN {
+        Some(ColumnType::String)
+    } else if value.parse::<i64>().is_ok() {
+        Some(ColumnType::Integer)
+    } else if value.parse::<f64>().is_ok() {
+        Some(ColumnType::Float)
+    } else if parse_boolean(value).is_some() {
+        Some(ColumnType::Boolean)
+    } else {
+        Some(ColumnType::String)
+    }
+}
+
+/// Parse a boolean the way decompression to CSV does.
+fn parse_boolean(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "t" => Some(true),
+        "false" | "no" | "n" | "f" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress::AlsCompressor;
+    use crate::config::CompressorConfig;
+
+    const CSV: &str = "id,level,latency,ok\n1,info,1.5,true\n2,warn,,false\n3,info,2,true\n4,error,9.25,false\n5,info,0.5,";
+
+    fn reader(config: CompressorConfig) -> AlsChunkedReader {
+        AlsChunkedReader::parse(&AlsCompressor::with_config(config).compress_csv(CSV).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_schema_types() {
+        let mut reader = reader(CompressorConfig::new());
+        assert_eq!(
+            reader.schema().unwrap(),
+            vec![
+                ("id".to_string(), ColumnType::Integer),
+                ("level".to_string(), ColumnType::String),
+                ("latency".to_string(), ColumnType::Float),
+                ("ok".to_string(), ColumnType::Boolean),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batches() {
+        let batches: Vec<RecordBatch> = reader(CompressorConfig::new())
+            .with_batch_size(2)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::len).collect::<Vec<_>>(), [2, 2, 1]);
+        assert_eq!(batches[0].columns[2], ColumnVector::Float(vec![Some(1.5), None]));
+        assert_eq!(batches[2].columns[3], ColumnVector::Boolean(vec![None]));
+        assert_eq!(batches[2].rows, vec![4]);
+    }
+
+    #[test]
+    fn test_projection_and_filters() {
+        let mut reader = reader(CompressorConfig::new())
+            .with_projection(&["level", "id"])
+            .with_filter_range("latency", "1".."10")
+            .with_filter("level", |level| level != "warn");
+        let batch = reader.next_batch().unwrap().unwrap();
+        assert_eq!(batch.rows, vec![0, 2, 3]);
+        assert_eq!(
+            batch.columns[0],
+            ColumnVector::String(vec![Some("info".to_string()), Some("info".to_string()), Some("error".to_string())])
+        );
+        assert_eq!(batch.columns[1], ColumnVector::Integer(vec![Some(1), Some(3), Some(4)]));
+        assert!(reader.next_batch().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_block_pruning() {
+        let config = CompressorConfig::new()
+            .with_ctx_fallback_threshold(1.0)
+            .with_block_size(2)
+            .with_zone_maps(true);
+        let mut reader = reader(config).with_projection(&["id"]).with_filter_range("id", "4"..);
+        reader.schema().unwrap();
+        assert_eq!(reader.plan.as_ref().unwrap().blocks, vec![1, 2]);
+        let batch = reader.next_batch().unwrap().unwrap();
+        assert_eq!(batch.columns[0], ColumnVector::Integer(vec![Some(4), Some(5)]));
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let mut reader = reader(CompressorConfig::new()).with_projection(&["missing"]);
+        assert!(matches!(reader.next_batch(), Err(AlsError::UnknownColumn { .. })));
+    }
+}
@@ -51,11 +51,15 @@
 //! }
 //! ```
 
+use std::fs;
 use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::als::{AlsParser, AlsSerializer};
 use crate::compress::AlsCompressor;
 use crate::config::{CompressorConfig, ParserConfig};
+use crate::convert::json::{records_to_tabular, JsonRecord, JsonRecordReader};
 use crate::convert::{TabularData, Value};
 use crate::error::Result;
 
@@ -68,6 +72,15 @@ const DEFAULT_CSV_CHUNK_SIZE: usize = 1000;
 /// Default chunk size for JSON processing (number of objects per chunk).
 const DEFAULT_JSON_CHUNK_SIZE: usize = 1000;
 
+/// Default number of records in a batch of a `FrameBatcher`.
+const DEFAULT_BATCH_RECORDS: usize = 10_000;
+
+/// Default approximate JSON size of a batch of a `FrameBatcher` (8 MB).
+const DEFAULT_BATCH_BYTES: usize = 8 * 1024 * 1024;
+
+/// Default maximum age of a batch of a `FrameBatcher`.
+const DEFAULT_BATCH_AGE: Duration = Duration::from_secs(60);
+
 /// Streaming compressor for processing large inputs in chunks.
 ///
 /// The `StreamingCompressor` reads input data in chunks, compresses each chunk
@@ -167,19 +180,20 @@ impl<R: Read> StreamingCompressor<R> {
 
     /// Compress JSON input in chunks, yielding ALS fragments.
     ///
-    /// This method reads JSON array data in chunks, compresses each chunk to ALS
-    /// format, and yields the compressed fragments as strings. The first fragment
-    /// includes the schema header.
+    /// This method reads JSON array (or NDJSON) data incrementally, compresses
+    /// each chunk of objects to ALS format, and yields the compressed fragments
+    /// as strings. Only the current chunk is held in memory. Each fragment
+    /// carries its own schema, which may differ between chunks when objects
+    /// have differing keys.
     ///
     /// # Returns
     ///
     /// An iterator that yields `Result<String>` for each compressed chunk.
     pub fn compress_json_chunks(&mut self) -> impl Iterator<Item = Result<String>> + '_ {
         StreamingJsonCompressor {
-            compressor: self,
-            first_chunk: true,
-            schema: None,
-            buffer: String::new(),
+            config: self.config.clone(),
+            chunk_size: self.json_chunk_size,
+            records: JsonRecordReader::with_config(&mut self.reader, &self.config.json),
             finished: false,
         }
     }
@@ -238,7 +252,7 @@ impl<'a, R: Read> StreamingCsvCompressor<'a, R> {
     /// Read a chunk of CSV rows from the input.
     fn read_csv_chunk(&mut self) -> Result<Option<TabularData<'static>>> {
         self.buffer.clear();
-        let mut lines_read = 0;
+        let mut rows_read = 0;
         let mut header_line = String::new();
 
         // Read header if this is the first chunk
@@ -247,7 +261,6 @@ impl<'a, R: Read> StreamingCsvCompressor<'a, R> {
                 return Ok(None); // Empty file
             }
             self.buffer.push_str(&header_line);
-            lines_read += 1;
         } else {
             // For subsequent chunks, reconstruct header from schema
             if let Some(ref schema) = self.schema {
@@ -259,18 +272,18 @@ impl<'a, R: Read> StreamingCsvCompressor<'a, R> {
 
         // Read data rows
         let mut line = String::new();
-        while lines_read < self.compressor.csv_chunk_size {
+        while rows_read < self.compressor.csv_chunk_size {
             line.clear();
             let bytes_read = self.compressor.reader.read_line(&mut line)?;
             if bytes_read == 0 {
                 break; // End of file
             }
             self.buffer.push_str(&line);
-            lines_read += 1;
+            rows_read += 1;
         }
 
-        // If we only read the header and no data, we're done
-        if lines_read <= 1 && self.schema.is_some() {
+        // If a later chunk has no data, we're done
+        if rows_read == 0 && self.schema.is_some() {
             return Ok(None);
         }
 
@@ -289,10 +302,9 @@ impl<'a, R: Read> StreamingCsvCompressor<'a, R> {
 
 /// Iterator for streaming JSON compression.
 struct StreamingJsonCompressor<'a, R: Read> {
-    compressor: &'a mut StreamingCompressor<R>,
-    first_chunk: bool,
-    schema: Option<Vec<String>>,
-    buffer: String,
+    config: CompressorConfig,
+    chunk_size: usize,
+    records: JsonRecordReader<&'a mut BufReader<R>>,
     finished: bool,
 }
 
@@ -305,18 +317,12 @@ impl<'a, R: Read> Iterator for StreamingJsonCompressor<'a, R> {
         }
 
         // Read a chunk of JSON objects
-        match self.read_json_chunk() {
+        match self.records.read_chunk(self.chunk_size) {
             Ok(Some(chunk_data)) => {
                 // Compress the chunk
-                let als_compressor = AlsCompressor::with_config(self.compressor.config.clone());
+                let als_compressor = AlsCompressor::with_config(self.config.clone());
                 match als_compressor.compress(&chunk_data) {
                     Ok(doc) => {
-                        // Capture schema from first chunk
-                        if self.first_chunk {
-                            self.schema = Some(doc.schema.clone());
-                            self.first_chunk = false;
-                        }
-                        
                         // Each chunk is a complete, independently parseable ALS document
                         let serializer = AlsSerializer::new();
                         Some(Ok(serializer.serialize(&doc)))
@@ -336,31 +342,6 @@ impl<'a, R: Read> Iterator for StreamingJsonCompressor<'a, R> {
     }
 }
 
-impl<'a, R: Read> StreamingJsonCompressor<'a, R> {
-    /// Read a chunk of JSON objects from the input.
-    fn read_json_chunk(&mut self) -> Result<Option<TabularData<'static>>> {
-        // Read the entire JSON array into memory
-        // Note: True streaming JSON parsing would require a more sophisticated approach
-        // For now, we read chunks of objects from a JSON array
-        
-        self.buffer.clear();
-        self.compressor.reader.read_to_string(&mut self.buffer)?;
-        
-        if self.buffer.trim().is_empty() {
-            return Ok(None);
-        }
-
-        // Parse the JSON
-        use crate::convert::json::parse_json;
-        let data = parse_json(&self.buffer)?;
-        
-        // Mark as finished since we read everything
-        self.finished = true;
-        
-        Ok(Some(data))
-    }
-}
-
 /// Streaming parser for processing large ALS files in chunks.
 ///
 /// The `StreamingParser` reads ALS format data in chunks, expands each chunk,
@@ -532,6 +513,240 @@ impl<'a, R: Read> StreamingRowParser<'a, R> {
     }
 }
 
+/// Batches JSON records into ALS frames.
+///
+/// Records are buffered until a batch reaches a record count, a byte size
+/// or an age, then compressed into one frame: a complete, independently
+/// parseable ALS document. This suits archiving message streams and logs,
+/// where records arrive one at a time.
+///
+/// # Examples
+///
+/// ```
+/// use als_compression::{AlsParser, FrameBatcher};
+///
+/// let mut batcher = FrameBatcher::new().with_max_records(2);
+/// assert!(batcher.push_json(r#"{"id": 1, "level": "info"}"#).unwrap().is_none());
+/// let frame = batcher.push_json(r#"{"id": 2, "level": "warn"}"#).unwrap().unwrap();
+/// assert_eq!(AlsParser::new().parse(&frame).unwrap().row_count(), 2);
+/// assert!(batcher.is_empty());
+/// ```
+pub struct FrameBatcher {
+    compressor: AlsCompressor,
+    records: Vec<JsonRecord>,
+    bytes: usize,
+    started: Option<Instant>,
+    max_records: usize,
+    max_bytes: usize,
+    max_age: Duration,
+}
+
+impl FrameBatcher {
+    /// Create a batcher with default configuration and limits.
+    pub fn new() -> Self {
+        Self::with_config(CompressorConfig::default())
+    }
+
+    /// Create a batcher with custom compression configuration.
+    pub fn with_config(config: CompressorConfig) -> Self {
+        Self {
+            compressor: AlsCompressor::with_config(config),
+            records: Vec::new(),
+            bytes: 0,
+            started: None,
+            max_records: DEFAULT_BATCH_RECORDS,
+            max_bytes: DEFAULT_BATCH_BYTES,
+            max_age: DEFAULT_BATCH_AGE,
+        }
+    }
+
+    /// Set the number of records that completes a batch.
+    pub fn with_max_records(mut self, records: usize) -> Self {
+        self.max_records = records.max(1);
+        self
+    }
+
+    /// Set the approximate JSON size in bytes that completes a batch.
+    pub fn with_max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Set the age of the oldest record at which [`poll`](Self::poll)
+    /// flushes a batch.
+    pub fn with_max_age(mut self, age: Duration) -> Self {
+        self.max_age = age;
+        self
+    }
+
+    /// Add a record, returning a frame if the batch is now complete.
+    pub fn push(&mut self, record: JsonRecord) -> Result<Option<String>> {
+        let size = record.iter().map(|(key, value)| key.len() + value_size(value)).sum();
+        self.push_sized(record, size)
+    }
+
+    /// Add a record given as a JSON object, returning a frame if the batch
+    /// is now complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlsError::JsonParseError` if `message` is not a JSON object;
+    /// the batch is left unchanged.
+    pub fn push_json(&mut self, message: &str) -> Result<Option<String>> {
+        let record: JsonRecord = serde_json::from_str(message)?;
+        self.push_sized(record, message.len())
+    }
+
+    fn push_sized(&mut self, record: JsonRecord, size: usize) -> Result<Option<String>> {
+        let batch = self.push_record(record, size);
+        self.compress(batch)
+    }
+
+    /// Add a record, returning the records of the batch if it is now
+    /// complete, without compressing them.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn push_batch(&mut self, record: JsonRecord) -> Option<Vec<JsonRecord>> {
+        let size = record.iter().map(|(key, value)| key.len() + value_size(value)).sum();
+        self.push_record(record, size)
+    }
+
+    fn push_record(&mut self, record: JsonRecord, size: usize) -> Option<Vec<JsonRecord>> {
+        self.started.get_or_insert_with(Instant::now);
+        self.records.push(record);
+        self.bytes += size;
+        if self.records.len() >= self.max_records || self.bytes >= self.max_bytes {
+            self.take()
+        } else {
+            None
+        }
+    }
+
+    /// Flush the batch if its oldest record has reached the maximum age.
+    ///
+    /// Call this periodically, so quiet streams still produce frames.
+    pub fn poll(&mut self) -> Result<Option<String>> {
+        let batch = self.poll_batch();
+        self.compress(batch)
+    }
+
+    /// Take the records of the batch if its oldest record has reached the
+    /// maximum age, without compressing them.
+    pub(crate) fn poll_batch(&mut self) -> Option<Vec<JsonRecord>> {
+        match self.started {
+            Some(started) if started.elapsed() >= self.max_age => self.take(),
+            _ => None,
+        }
+    }
+
+    /// Compress the buffered records into a frame, if there are any.
+    pub fn flush(&mut self) -> Result<Option<String>> {
+        let batch = self.take();
+        self.compress(batch)
+    }
+
+    /// Take the buffered records, if there are any, starting a new batch.
+    pub(crate) fn take(&mut self) -> Option<Vec<JsonRecord>> {
+        if self.records.is_empty() {
+            return None;
+        }
+        self.bytes = 0;
+        self.started = None;
+        Some(std::mem::take(&mut self.records))
+    }
+
+    fn compress(&self, batch: Option<Vec<JsonRecord>>) -> Result<Option<String>> {
+        batch.map(|records| compress_batch(&self.compressor, records)).transpose()
+    }
+
+    /// Get the compressor that turns batches into frames.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn compressor(&self) -> &AlsCompressor {
+        &self.compressor
+    }
+
+    /// Get the number of buffered records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Check if no records are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl Default for FrameBatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Destination of ALS frames, such as those cut by a [`FrameBatcher`].
+pub trait FrameSink {
+    /// Write one frame, a complete ALS document.
+    fn write_frame(&mut self, frame: &str) -> Result<()>;
+}
+
+impl<S: FrameSink + ?Sized> FrameSink for Box<S> {
+    fn write_frame(&mut self, frame: &str) -> Result<()> {
+        (**self).write_frame(frame)
+    }
+}
+
+/// Writes each frame to its own file in a directory.
+///
+/// Files are named `<prefix>-<unix millis>-<sequence>.als`, so they sort in
+/// the order they were written.
+#[derive(Debug)]
+pub struct DirectorySink {
+    dir: PathBuf,
+    prefix: String,
+    sequence: u64,
+}
+
+impl DirectorySink {
+    /// Write frames into `dir`, creating it if needed.
+    pub fn new(dir: impl Into<PathBuf>, prefix: &str) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            prefix: prefix.to_string(),
+            sequence: 0,
+        })
+    }
+}
+
+impl FrameSink for DirectorySink {
+    fn write_frame(&mut self, frame: &str) -> Result<()> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis());
+        let name = format!("{}-{:013}-{:06}.als", self.prefix, millis, self.sequence);
+        fs::write(self.dir.join(name), frame)?;
+        self.sequence += 1;
+        Ok(())
+    }
+}
+
+/// Compress the records of a batch into a frame.
+pub(crate) fn compress_batch(compressor: &AlsCompressor, records: Vec<JsonRecord>) -> Result<String> {
+    let data = records_to_tabular(records)?;
+    let doc = compressor.compress(&data)?;
+    Ok(AlsSerializer::new().serialize(&doc))
+}
+
+/// Approximate size of a JSON value as text.
+fn value_size(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Null | serde_json::Value::Bool(_) => 5,
+        serde_json::Value::Number(_) => 8,
+        serde_json::Value::String(s) => s.len() + 2,
+        serde_json::Value::Array(values) => values.iter().map(value_size).sum::<usize>() + 2,
+        serde_json::Value::Object(map) => map.iter().map(|(key, value)| key.len() + value_size(value)).sum::<usize>() + 2,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -558,6 +773,73 @@ mod tests {
         assert!(chunks[0].contains("#id") || chunks[0].contains("#name"));
     }
 
+    #[test]
+    fn test_streaming_compressor_csv_chunk_rows() {
+        let csv_data = "id,name\n1,a\n2,b\n3,c\n4,d\n5,e\n";
+        let mut compressor = StreamingCompressor::new(Cursor::new(csv_data.as_bytes())).with_csv_chunk_size(2);
+        let chunks: Vec<String> = compressor.compress_csv_chunks().collect::<Result<_>>().unwrap();
+
+        let parser = AlsParser::new();
+        let rows: Vec<usize> = chunks.iter().map(|c| parser.parse(c).unwrap().row_count()).collect();
+        assert_eq!(rows, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn test_streaming_compressor_json() {
+        let json_data = r#"[{"id": 1, "name": "Alice"}, {"id": 2, "name": "Bob"}, {"id": 3, "name": "Carol"}]"#;
+        let cursor = Cursor::new(json_data.as_bytes());
+
+        let mut compressor = StreamingCompressor::new(cursor).with_json_chunk_size(2);
+        let chunks: Vec<String> = compressor
+            .compress_json_chunks()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        let parser = AlsParser::new();
+        let rows: usize = chunks.iter().map(|c| parser.parse(c).unwrap().row_count()).sum();
+        assert_eq!(rows, 3);
+        assert_eq!(parser.to_json(&chunks[1]).unwrap(), r#"[{"id":3,"name":"Carol"}]"#);
+    }
+
+    #[test]
+    fn test_frame_batcher_triggers() {
+        let mut batcher = FrameBatcher::new().with_max_records(3).with_max_bytes(60);
+        assert!(batcher.push_json(r#"{"id": 1}"#).unwrap().is_none());
+        assert!(batcher.push_json(r#"{"id": 2, "x": [1, 2]"#).is_err());
+        assert_eq!(batcher.len(), 1);
+        assert!(batcher.push_json(r#"{"id": 2}"#).unwrap().is_none());
+        let frame = batcher.push_json(r#"{"id": 3}"#).unwrap().unwrap();
+        assert_eq!(AlsParser::new().to_json(&frame).unwrap(), r#"[{"id":1},{"id":2},{"id":3}]"#);
+
+        let long = format!(r#"{{"message": "{}"}}"#, "x".repeat(60));
+        assert!(batcher.push_json(&long).unwrap().is_some());
+        assert!(batcher.flush().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_frame_batcher_poll() {
+        let mut batcher = FrameBatcher::new().with_max_age(Duration::ZERO);
+        assert!(batcher.poll().unwrap().is_none());
+        let mut record = JsonRecord::new();
+        record.insert("level".to_string(), "info".into());
+        assert!(batcher.push(record).unwrap().is_none());
+        let frame = batcher.poll().unwrap().unwrap();
+        assert_eq!(AlsParser::new().parse(&frame).unwrap().row_count(), 1);
+        assert!(batcher.is_empty());
+    }
+
+    #[test]
+    fn test_streaming_compressor_invalid_json() {
+        let cursor = Cursor::new(r#"[{"id": 1}, 2]"#.as_bytes());
+        let mut compressor = StreamingCompressor::new(cursor).with_json_chunk_size(1);
+        let results: Vec<Result<String>> = compressor.compress_json_chunks().collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+
     #[test]
     fn test_streaming_compressor_empty_csv() {
         let csv_data = "";
@@ -655,4 +937,22 @@ mod tests {
         
         assert_eq!(rows.len(), 3);
     }
+
+    #[test]
+    fn test_directory_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = DirectorySink::new(dir.path().join("frames"), "logs").unwrap();
+        sink.write_frame("#id\n1>3").unwrap();
+        sink.write_frame("#id\n4>5").unwrap();
+
+        let mut names: Vec<String> = fs::read_dir(dir.path().join("frames"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].starts_with("logs-") && names[0].ends_with("-000000.als"));
+        let frame = fs::read_to_string(dir.path().join("frames").join(&names[1])).unwrap();
+        assert_eq!(AlsParser::new().parse(&frame).unwrap().row_count(), 2);
+    }
 }
@@ -0,0 +1,234 @@
+//! Property-based testing helpers.
+//!
+//! Enabled by the `test-util` feature. Provides [`proptest`] strategies for
+//! random [`TabularData`] and [`AlsDocument`] values, and assertions that
+//! data survives compression, serialization, parsing and expansion
+//! unchanged. Custom detectors and converters can reuse them to check their
+//! output round-trips through ALS.
+//!
+//! Generated columns mix the shapes the compressor looks for (ranges,
+//! repeats, alternations, low cardinality strings) with random values, and
+//! strings include the characters ALS has to escape.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use als_compression::test_util::{arb_tabular_data, assert_round_trip};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn my_converter_round_trips(data in arb_tabular_data()) {
+//!         assert_round_trip(&my_converter(data));
+//!     }
+//! }
+//! ```
+
+use std::borrow::Cow;
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::als::{AlsDocument, AlsParser, AlsSerializer, EMPTY_TOKEN, NULL_TOKEN};
+use crate::compress::AlsCompressor;
+use crate::config::CompressorConfig;
+use crate::convert::{Column, TabularData, Value};
+
+/// Largest number of rows in generated tables.
+pub const MAX_ROWS: usize = 64;
+
+/// Largest number of columns in generated tables.
+pub const MAX_COLUMNS: usize = 6;
+
+/// Strategy for string cells, including characters ALS escapes.
+///
+/// Never produces the null and empty tokens themselves, which stand for
+/// null and the empty string.
+pub fn arb_string() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z]{1,8}",
+        "[A-Za-z0-9 _.:/-]{0,12}",
+        r"[a-z0-9 |>*~_@%&^?<()\[\]{}$#!\\,;=+-]{1,10}",
+        "[^\\p{C}]{1,6}",
+    ]
+    .prop_filter("null and empty tokens are reserved", |s| s != NULL_TOKEN && s != EMPTY_TOKEN)
+}
+
+/// Strategy for a single value of any type.
+pub fn arb_value() -> impl Strategy<Value = Value<'static>> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<i64>().prop_map(Value::Integer),
+        (-1e6f64..1e6).prop_map(Value::Float),
+        any::<bool>().prop_map(Value::Boolean),
+        arb_string().prop_map(|s| Value::String(Cow::Owned(s))),
+    ]
+}
+
+/// Strategy for the values of a column with `rows` rows.
+///
+/// Columns are patterned or random, so both the detectors and the raw
+/// fallback are exercised.
+pub fn arb_column_values(rows: usize) -> impl Strategy<Value = Vec<Value<'static>>> {
+    prop_oneof![
+        // Arithmetic sequence
+        (-1000i64..1000, -5i64..=5).prop_map(move |(start, step)| {
+            (0..rows as i64).map(|i| Value::Integer(start + i * step)).collect()
+        }),
+        // Runs of a few values
+        (vec(arb_value(), 1..4), 1usize..8).prop_map(move |(values, run)| {
+            (0..rows).map(|i| values[(i / run) % values.len()].clone()).collect()
+        }),
+        // Low cardinality strings
+        vec(arb_string(), 1..5).prop_flat_map(move |labels| {
+            vec(prop::sample::select(labels), rows)
+                .prop_map(|labels| labels.into_iter().map(|s| Value::String(Cow::Owned(s))).collect())
+        }),
+        // Random integers
+        vec(any::<i64>().prop_map(Value::Integer), rows),
+        // Random values of mixed types
+        vec(arb_value(), rows),
+    ]
+}
+
+/// Strategy for tables of up to [`MAX_COLUMNS`] columns and [`MAX_ROWS`] rows.
+pub fn arb_tabular_data() -> impl Strategy<Value = TabularData<'static>> {
+    (1..=MAX_COLUMNS, 0..=MAX_ROWS).prop_flat_map(|(columns, rows)| {
+        vec(arb_column_values(rows), columns).prop_map(|columns| {
+            let mut data = TabularData::with_capacity(columns.len());
+            for (i, values) in columns.into_iter().enumerate() {
+                data.add_column(Column::new(format!("c{}", i), values));
+            }
+            data
+        })
+    })
+}
+
+/// Strategy for compressor configurations that change the encoding.
+pub fn arb_compressor_config() -> impl Strategy<Value = CompressorConfig> {
+    (prop::option::of(1usize..16), any::<bool>(), any::<bool>(), 1.0f64..3.0).prop_map(
+        |(block_size, indexes, zone_maps, threshold)| {
+            let mut config = CompressorConfig::new()
+                .with_build_indexes(indexes)
+                .with_zone_maps(zone_maps)
+                .with_ctx_fallback_threshold(threshold);
+            if let Some(rows) = block_size {
+                config = config.with_block_size(rows);
+            }
+            config
+        },
+    )
+}
+
+/// Strategy for documents compressed from random tables.
+pub fn arb_als_document() -> impl Strategy<Value = AlsDocument<'static>> {
+    (arb_tabular_data(), arb_compressor_config()).prop_map(|(data, config)| {
+        AlsCompressor::with_config(config)
+            .compress(&data)
+            .expect("generated tables compress")
+    })
+}
+
+/// Render the rows of a table as the strings ALS expands them to.
+pub fn expected_rows(data: &TabularData) -> Vec<Vec<String>> {
+    (0..data.row_count)
+        .map(|row| {
+            data.columns
+                .iter()
+                .map(|column| column.values[row].to_string_repr().into_owned())
+                .collect()
+        })
+        .collect()
+}
+
+/// Assert that `data` round-trips through ALS with the default configuration.
+///
+/// # Panics
+///
+/// Panics if compressing, serializing, parsing or expanding fails, or the
+/// expanded rows differ from `data`.
+pub fn assert_round_trip(data: &TabularData) {
+    assert_round_trip_with_config(data, CompressorConfig::new());
+}
+
+/// Assert that `data` round-trips through ALS compressed with `config`.
+///
+/// # Panics
+///
+/// Panics if compressing, serializing, parsing or expanding fails, or the
+/// expanded rows differ from `data`.
+pub fn assert_round_trip_with_config(data: &TabularData, config: CompressorConfig) {
+    let doc = AlsCompressor::with_config(config)
+        .compress(data)
+        .unwrap_or_else(|e| panic!("compression failed: {}", e));
+    let rows = AlsParser::new()
+        .expand(&doc)
+        .unwrap_or_else(|e| panic!("expanding the compressed document failed: {}", e));
+    assert_rows_eq(&rows, &expected_rows(data), "compressed document");
+    assert_document_round_trip(&doc);
+}
+
+/// Assert that `doc` serializes to text that parses back to the same
+/// document and rows.
+///
+/// # Panics
+///
+/// Panics if parsing or expanding fails, or the parsed document differs
+/// from `doc`.
+pub fn assert_document_round_trip(doc: &AlsDocument) {
+    let parser = AlsParser::new();
+    let text = AlsSerializer::new().serialize(doc);
+    let parsed = parser
+        .parse(&text)
+        .unwrap_or_else(|e| panic!("parsing serialized document failed: {}\n{}", e, text));
+    let rows = parser
+        .expand(doc)
+        .unwrap_or_else(|e| panic!("expanding document failed: {}\n{}", e, text));
+    let parsed_rows = parser
+        .expand(&parsed)
+        .unwrap_or_else(|e| panic!("expanding parsed document failed: {}\n{}", e, text));
+    assert_rows_eq(&parsed_rows, &rows, &format!("parsed document\n{}\n", text));
+    assert_eq!(parsed.schema, doc.schema, "parsed document has a different schema\n{}", text);
+}
+
+/// Assert that two sets of rows are equal, reporting the first difference.
+fn assert_rows_eq(actual: &[Vec<String>], expected: &[Vec<String>], what: &str) {
+    assert_eq!(actual.len(), expected.len(), "{} expands to a different number of rows", what);
+    for (row, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+        assert_eq!(actual.len(), expected.len(), "{} expands row {} to a different number of columns", what, row);
+        if let Some(column) = (0..actual.len()).find(|&column| actual[column] != expected[column]) {
+            panic!(
+                "{} expands row {} column {} to {:?} instead of {:?}",
+                what, row, column, actual[column], expected[column]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_tables_round_trip(data in arb_tabular_data(), config in arb_compressor_config()) {
+            assert_round_trip_with_config(&data, config);
+        }
+
+        #[test]
+        fn test_documents_round_trip(doc in arb_als_document()) {
+            assert_document_round_trip(&doc);
+        }
+    }
+
+    #[test]
+    fn test_scientific_notation_round_trips() {
+        let values = ["1e3", "-2.5E-3", "1.e3", "-e3", "1e", "7E+2"];
+        let mut data = TabularData::with_capacity(1);
+        data.add_column(Column::new(
+            "c0",
+            values.iter().map(|s| Value::String(Cow::Borrowed(*s))).collect(),
+        ));
+        assert_round_trip(&data);
+    }
+}
@@ -0,0 +1,374 @@
+//! Structured logging to ALS frames.
+//!
+//! [`AlsLayer`] is a `tracing-subscriber` layer that records each event as
+//! a row, with its timestamp, level, target, enclosing spans and fields. The
+//! rows are batched by a [`FrameBatcher`] and written to a [`FrameSink`] as
+//! ALS frames, so services keep compressed structured logs without writing
+//! JSON lines and compressing them later.
+//!
+//! # Examples
+//!
+//! ```rust,ignore
+//! use als_compression::tracing_layer::AlsLayer;
+//! use als_compression::DirectorySink;
+//! use std::time::Duration;
+//! use tracing_subscriber::prelude::*;
+//!
+//! let layer = AlsLayer::new(DirectorySink::new("logs", "app")?)
+//!     .with_flush_interval(Duration::from_secs(10));
+//! let handle = layer.handle();
+//! tracing_subscriber::registry().with(layer).init();
+//!
+//! tracing::info!(user = "alice", "logged in");
+//! handle.flush()?;
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::als::datetime::format_timestamp;
+use crate::compress::AlsCompressor;
+use crate::convert::json::JsonRecord;
+use crate::error::Result;
+use crate::streaming::{compress_batch, FrameBatcher, FrameSink};
+
+thread_local! {
+    /// Set while a thread writes a frame, so events logged by the compressor
+    /// or the sink are dropped instead of feeding back into the layer.
+    static WRITING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A `tracing-subscriber` layer that writes events as ALS frames.
+///
+/// Buffered events are written when the batch is complete, when a
+/// [`handle`](Self::handle) is flushed, and when the layer and all its
+/// handles are dropped.
+pub struct AlsLayer {
+    state: Arc<State>,
+}
+
+/// Handle to flush an [`AlsLayer`] after it was installed.
+#[derive(Clone)]
+pub struct AlsLayerHandle {
+    state: Arc<State>,
+}
+
+/// The batch and the writer are locked separately, so events are recorded
+/// while a frame is compressed and written. A thread that cuts a batch locks
+/// the writer before releasing the batch, which keeps frames in order.
+struct State {
+    batcher: Mutex<FrameBatcher>,
+    writer: Mutex<Writer>,
+}
+
+struct Writer {
+    compressor: AlsCompressor,
+    sink: Box<dyn FrameSink + Send>,
+    frames: usize,
+    errors: usize,
+}
+
+impl AlsLayer {
+    /// Create a layer writing frames to `sink` with a default batcher.
+    pub fn new<S: FrameSink + Send + 'static>(sink: S) -> Self {
+        let batcher = FrameBatcher::new();
+        let writer = Writer {
+            compressor: batcher.compressor().clone(),
+            sink: Box::new(sink),
+            frames: 0,
+            errors: 0,
+        };
+        Self {
+            state: Arc::new(State {
+                batcher: Mutex::new(batcher),
+                writer: Mutex::new(writer),
+            }),
+        }
+    }
+
+    /// Set the batcher, which decides when frames are cut.
+    pub fn with_batcher(self, batcher: FrameBatcher) -> Self {
+        self.state.writer.lock().compressor = batcher.compressor().clone();
+        *self.state.batcher.lock() = batcher;
+        self
+    }
+
+    /// Flush buffered events every `interval` from a background thread, so
+    /// quiet services still write their logs.
+    ///
+    /// The thread stops once the layer and all its handles are dropped.
+    pub fn with_flush_interval(self, interval: Duration) -> Self {
+        let state = Arc::downgrade(&self.state);
+        thread::spawn(move || {
+            flush_periodically(state, || {
+                thread::sleep(interval);
+                true
+            })
+        });
+        self
+    }
+
+    /// Get a handle to flush the layer once it is installed.
+    pub fn handle(&self) -> AlsLayerHandle {
+        AlsLayerHandle {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl fmt::Debug for AlsLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlsLayer").finish_non_exhaustive()
+    }
+}
+
+impl AlsLayerHandle {
+    /// Write the buffered events as a frame, if there are any.
+    pub fn flush(&self) -> Result<()> {
+        self.state.flush()
+    }
+
+    /// Get the number of frames written.
+    pub fn frames_written(&self) -> usize {
+        self.state.writer.lock().frames
+    }
+
+    /// Get the number of frames that could not be compressed or written.
+    ///
+    /// Events have nowhere to report errors, so failed frames are counted
+    /// and dropped.
+    pub fn write_errors(&self) -> usize {
+        self.state.writer.lock().errors
+    }
+}
+
+impl fmt::Debug for AlsLayerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlsLayerHandle").finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for AlsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if WRITING.with(Cell::get) {
+            return;
+        }
+
+        let metadata = event.metadata();
+        let mut record = JsonRecord::new();
+        record.insert("timestamp".to_string(), now().into());
+        record.insert("level".to_string(), metadata.level().as_str().into());
+        record.insert("target".to_string(), metadata.target().into());
+        if let Some(scope) = ctx.event_scope(event) {
+            let spans: Vec<&str> = scope.from_root().map(|span| span.name()).collect();
+            record.insert("span".to_string(), spans.join(":").into());
+        }
+        event.record(&mut FieldVisitor(&mut record));
+
+        let mut batcher = self.state.batcher.lock();
+        let batch = batcher.push_batch(record).or_else(|| batcher.poll_batch());
+        if batch.is_some() {
+            let mut writer = self.state.writer.lock();
+            drop(batcher);
+            // Failed frames are counted by the writer
+            let _ = writer.write(batch);
+        }
+    }
+}
+
+impl State {
+    fn flush(&self) -> Result<()> {
+        let mut batcher = self.batcher.lock();
+        let batch = batcher.take();
+        let mut writer = self.writer.lock();
+        drop(batcher);
+        writer.write(batch)
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        let batch = self.batcher.get_mut().take();
+        let _ = self.writer.get_mut().write(batch);
+    }
+}
+
+impl Writer {
+    /// Compress a batch and write it to the sink, counting errors.
+    fn write(&mut self, batch: Option<Vec<JsonRecord>>) -> Result<()> {
+        let Some(records) = batch else {
+            return Ok(());
+        };
+        WRITING.with(|writing| writing.set(true));
+        let written = compress_batch(&self.compressor, records).and_then(|frame| self.sink.write_frame(&frame));
+        WRITING.with(|writing| writing.set(false));
+        match written {
+            Ok(()) => self.frames += 1,
+            Err(_) => self.errors += 1,
+        }
+        written
+    }
+}
+
+/// Collects the fields of an event into a record.
+///
+/// Fields never replace the timestamp, level, target or span of the event.
+struct FieldVisitor<'a>(&'a mut JsonRecord);
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: serde_json::Value) {
+        self.0.entry(field.name()).or_insert(value);
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+/// Current time in ISO 8601 with milliseconds, in UTC.
+fn now() -> String {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = format_timestamp(elapsed.as_secs() as i64, "%Y-%m-%dT%H:%M:%S").unwrap_or_default();
+    format!("{}.{:03}Z", seconds, elapsed.subsec_millis())
+}
+
+/// Flush the layer each time `wait` returns, until it returns `false` or
+/// the layer is dropped.
+fn flush_periodically(state: Weak<State>, mut wait: impl FnMut() -> bool) {
+    while wait() {
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        // Failed frames are counted by the writer
+        let _ = state.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AlsParser;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Sink collecting frames in memory.
+    #[derive(Clone, Default)]
+    struct Frames(Arc<Mutex<Vec<String>>>);
+
+    impl FrameSink for Frames {
+        fn write_frame(&mut self, frame: &str) -> Result<()> {
+            self.0.lock().push(frame.to_string());
+            Ok(())
+        }
+    }
+
+    fn rows(frame: &str) -> (Vec<String>, Vec<Vec<String>>) {
+        AlsParser::new().parse_and_expand(frame).unwrap()
+    }
+
+    #[test]
+    fn test_events_become_rows() {
+        let frames = Frames::default();
+        let layer = AlsLayer::new(frames.clone());
+        let handle = layer.handle();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", id = 7);
+            let _entered = span.enter();
+            tracing::info!(user = "alice", attempts = 2, "logged in");
+            tracing::warn!(user = "bob", level = "ignored", "locked out");
+        });
+        assert!(frames.0.lock().is_empty());
+        handle.flush().unwrap();
+
+        let frames = frames.0.lock();
+        assert_eq!(frames.len(), 1);
+        let (headers, rows) = rows(&frames[0]);
+        let column = |name: &str| {
+            let index = headers.iter().position(|header| header == name).unwrap();
+            rows.iter().map(|row| row[index].as_str()).collect::<Vec<_>>()
+        };
+        assert_eq!(column("level"), ["INFO", "WARN"]);
+        assert_eq!(column("user"), ["alice", "bob"]);
+        assert_eq!(column("message"), ["logged in", "locked out"]);
+        assert_eq!(column("span"), ["request", "request"]);
+        assert!(column("timestamp")[0].ends_with('Z'));
+        assert_eq!(handle.frames_written(), 1);
+    }
+
+    #[test]
+    fn test_batches_and_drop_flush() {
+        let frames = Frames::default();
+        let layer = AlsLayer::new(frames.clone()).with_batcher(FrameBatcher::new().with_max_records(2));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..5 {
+                tracing::info!(i, "tick");
+            }
+        });
+
+        let sizes: Vec<usize> = frames.0.lock().iter().map(|frame| rows(frame).1.len()).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+    }
+
+    #[test]
+    fn test_flush_periodically() {
+        let frames = Frames::default();
+        let layer = AlsLayer::new(frames.clone());
+        let handle = layer.handle();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || tracing::info!("quiet"));
+
+        // Each tick sent on the channel is one interval elapsing
+        let (ticks, wait) = std::sync::mpsc::sync_channel(0);
+        let state = Arc::downgrade(&handle.state);
+        let flusher = thread::spawn(move || flush_periodically(state, || wait.recv().is_ok()));
+        ticks.send(()).unwrap();
+        ticks.send(()).unwrap();
+        drop(ticks);
+        flusher.join().unwrap();
+
+        assert_eq!(handle.frames_written(), 1);
+        assert_eq!(rows(&frames.0.lock()[0]).1.len(), 1);
+
+        // The thread stops once the layer is dropped
+        let state = Arc::downgrade(&handle.state);
+        drop(handle);
+        flush_periodically(state, || true);
+    }
+}
@@ -225,3 +225,42 @@ fn test_csv_error_handling_malformed() {
     // Should return an error
     assert!(result.is_err());
 }
+
+#[test]
+fn test_csv_delta_round_trip() {
+    use als_compression::convert::csv::{parse_csv, to_csv};
+    use als_compression::AlsSerializer;
+
+    let compressor = AlsCompressor::new();
+    let parser = AlsParser::new();
+
+    let mut base_csv = String::from("id,status,owner\n");
+    for i in 0..1000 {
+        base_csv.push_str(&format!("{},open,user{}\n", i, i % 17));
+    }
+    // Close one row, delete one and append two
+    let new_csv = base_csv
+        .replace("\n500,open,", "\n500,closed,")
+        .replace("\n42,open,user8\n", "\n")
+        + "1000,open,user0\n1001,open,user1\n";
+
+    let base = compressor.compress(&parse_csv(&base_csv).unwrap()).unwrap();
+    let new = parse_csv(&new_csv).unwrap();
+    let delta = compressor.compress_delta(&base, &new).unwrap();
+    let script = delta.delta.as_ref().unwrap();
+    assert_eq!(script.inserted_rows(), 3);
+    assert_eq!(script.deleted_rows(), 2);
+
+    // The delta survives serialization and is far smaller than a snapshot
+    let delta_text = AlsSerializer::new().serialize(&delta);
+    assert!(delta_text.len() * 10 < compressor.compress_csv(&new_csv).unwrap().len());
+    let delta = parser.parse(&delta_text).unwrap();
+
+    let restored = parser.apply_delta(&base, &delta).unwrap();
+    assert_eq!(to_csv(&restored).unwrap(), to_csv(&new).unwrap());
+
+    // A delta only applies to its own base
+    let other = compressor.compress(&parse_csv(&new_csv).unwrap()).unwrap();
+    assert!(parser.apply_delta(&other, &delta).is_err());
+    assert!(parser.apply_delta(&base, &base).is_err());
+}
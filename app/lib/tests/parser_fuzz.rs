@@ -0,0 +1,136 @@
+//! Property tests feeding malformed input to the ALS parser.
+//!
+//! The parser must reject bad documents with an error, never panic or
+//! overflow the stack. The cargo-fuzz targets under `fuzz/` explore the
+//! same ground for longer.
+
+use als_compression::{AlsDocument, AlsOperator, AlsParser, AlsSerializer, ColumnStream, ValueTransform};
+use proptest::prelude::*;
+
+/// Largest document expanded by the tests, in values per column.
+const MAX_EXPANDED: usize = 10_000;
+
+/// Pieces of ALS syntax, valid or not.
+fn arb_fragment() -> impl Strategy<Value = String> {
+    prop_oneof![
+        prop::sample::select(vec![
+            "(", ")", "*", ">", "~", "|", ":", " ", "\n", "-", "!", "1e3", "99999999999999999999", "*99999999999",
+            "0>9999999999", "_9", "_=", "@5", "\\e", "\\0", "\\", "$d:a|b", "#c", "!ctx", "\n!blocks 2",
+            "\n!zone 0 n 1 2 0", "\n!bloom 0 7 ffff", "\\(", "\\)", "\\{", "a\\ b",
+        ])
+        .prop_map(String::from),
+        "[ -~]{1,4}",
+    ]
+}
+
+/// Strategy for a single stream element, nesting groups, transforms and
+/// splits, and sometimes broken.
+fn arb_element() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        8 => prop::sample::select(vec![
+            "x", "3", "-7", "1.5", "1>5", "5>1:-2", "x*3", "a~b*4", "-1~-2", "_0", "_1", "_+", "_+1", "@0*2",
+            "@0(a:b)*2", "%{%Y-%m-%d}0+86400*2", "&dow+0*3", "&qtr+1:2*4", "?a~b:1.2",
+        ])
+        .prop_map(String::from),
+        1 => arb_fragment(),
+    ];
+    leaf.prop_recursive(6, 64, 4, |inner| {
+        prop_oneof![
+            (inner.clone(), 0usize..4).prop_map(|(element, count)| format!("({})*{}", element, count)),
+            inner.clone().prop_map(|element| format!("^{{U}}({})", element)),
+            inner.clone().prop_map(|element| format!("^ID{{3}}({})", element)),
+            (inner.clone(), inner).prop_map(|(a, b)| format!("</2({})({})", a, b)),
+        ]
+    })
+}
+
+/// Strategy for documents with a valid header and random streams.
+fn arb_document_text() -> impl Strategy<Value = String> {
+    let header = prop::sample::select(vec!["", "!v1\n", "!ctx\n", "$default:a|b|c\n"]);
+    let streams = prop::collection::vec(prop::collection::vec(arb_element(), 0..6), 1..4);
+    (header, streams).prop_map(|(header, streams)| {
+        let schema: Vec<String> = (0..streams.len()).map(|i| format!("#c{}", i)).collect();
+        let streams: Vec<String> = streams.iter().map(|elements| elements.join(" ")).collect();
+        format!("{}{}\n{}", header, schema.join(" "), streams.join("|"))
+    })
+}
+
+/// Document holding `value` in every operator that carries text.
+fn document_with_value(value: &str) -> AlsDocument<'_> {
+    let mut doc = AlsDocument::with_schema(vec!["a", "b"]);
+    doc.add_stream(ColumnStream::from_operators(vec![
+        AlsOperator::raw(value),
+        AlsOperator::multiply(AlsOperator::raw(value), 2),
+        AlsOperator::toggle(value, "z", 3),
+        AlsOperator::bool_runs("z", value, vec![1, 1]),
+        AlsOperator::transform(AlsOperator::raw("v"), ValueTransform::new().with_prefix(value).with_suffix(value)),
+        AlsOperator::split('/', vec![vec![AlsOperator::raw(value)], vec![AlsOperator::raw("q")]]),
+    ]));
+    doc.add_stream(ColumnStream::from_operators(vec![AlsOperator::column_ref(
+        0,
+        vec![(value.to_string(), value.to_string())],
+        10,
+    )]));
+    doc
+}
+
+/// Parse `input` and, when it parses, exercise the parsed document.
+fn check(input: &str) {
+    let parser = AlsParser::new();
+    let Ok(doc) = parser.parse(input) else {
+        return;
+    };
+    if doc.streams.iter().all(|stream| stream.expanded_count() <= MAX_EXPANDED) {
+        let _ = parser.expand(&doc);
+    }
+
+    // Whatever parses must serialize to text that parses again
+    let text = AlsSerializer::new().serialize(&doc);
+    let reparsed: AlsDocument = parser
+        .parse(&text)
+        .unwrap_or_else(|e| panic!("serialized document does not parse: {}\n{:?}\n{}", e, input, text));
+    assert_eq!(reparsed.schema, doc.schema);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn test_parser_never_panics_on_fragments(input in arb_document_text()) {
+        check(&input);
+    }
+
+    #[test]
+    fn test_parser_never_panics_on_text(input in "\\PC{0,200}") {
+        check(&input);
+    }
+
+    #[test]
+    fn test_any_value_round_trips(value in "\\PC{1,20}") {
+        let doc = document_with_value(&value);
+        let parser = AlsParser::new();
+        let text = AlsSerializer::new().serialize(&doc);
+        let parsed = parser.parse(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+        prop_assert_eq!(parser.expand(&parsed).unwrap(), parser.expand(&doc).unwrap());
+    }
+}
+
+#[test]
+fn test_deep_nesting_is_rejected() {
+    let depth = 100_000;
+    let inputs = [
+        format!("#a\n{}x{}", "(".repeat(depth), ")".repeat(depth)),
+        format!("#a\n{}x{}", "^{U}(".repeat(depth), ")".repeat(depth)),
+        format!("#a\n{}x{}", "</2(".repeat(depth), ")(y)".repeat(depth)),
+    ];
+    for input in inputs {
+        let error = AlsParser::new().parse(&input).unwrap_err();
+        assert!(error.to_string().contains("nested more than"), "{}", error);
+    }
+}
+
+#[test]
+fn test_long_runs_of_empty_values() {
+    let input = format!("#a\n{}", "\\e ".repeat(200_000));
+    let _ = AlsParser::new().parse(&input);
+}